@@ -0,0 +1,310 @@
+//! Headless subcommands of the AI Terminal
+//!
+//! Besides the UI, the binary answers one-shot requests for scripts and CI:
+//!
+//! - `ai-terminal ask "why is the sky blue"` streams the model's answer to
+//!   stdout; input piped in is sent along, as in `git diff | ai-terminal ask "review this"`
+//! - `ai-terminal run "list the largest files here"` asks the model for a shell
+//!   command, shows it and runs it once confirmed, or straight away with `--yes`
+//! - `ai-terminal exec deploy` runs the workflow named `deploy`
+//! - `ai-terminal doctor` checks the configuration and environment and says
+//!   how to fix what is wrong, exiting with 1 when something is
+//!
+//! `ask` and `run` use the configured backend and model, `--model` and
+//! `--persona` choosing others. Command output goes to stdout as it
+//! arrives, and the binary exits with the command's exit code, or 1 when a
+//! workflow stops. With `--json`, nothing is streamed: stdout gets one
+//! [`Document`] describing the request, the response and what ran
+//! once the command is done, errors included.
+
+use std::io::{IsTerminal, Read, Write};
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use futures_util::StreamExt;
+use ollama_client::{BackendKind, ChatRequest};
+use terminal_emulator::{ExecutionEvent, OutputBuffer, PtyExecutor};
+use terminal_ui::protocol::{AskOutput, CommandResult, Document, ErrorOutput, ExecOutput, ModelRequest, ModelResponse, Output, RunOutput, StepOutput};
+use terminal_ui::doctor::{self, Check, Status};
+use terminal_ui::keymap::Keymap;
+use terminal_ui::privacy::Redactor;
+use terminal_ui::processors::ProcessorRegistry;
+use terminal_ui::tab_colors::TabColorRules;
+use terminal_ui::{custom_commands, snippets};
+use terminal_ui::workflows::{WorkflowRun, Workflows};
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+
+/// Most input read from a pipe for `ask`, in bytes
+const MAX_PIPED_INPUT: u64 = 256 * 1024;
+
+/// System prompt of `run`, asking for one command
+const RUN_SYSTEM_PROMPT: &str = "You turn requests into shell commands. Reply with a single command for the request in a \
+fenced sh block, chaining steps with && if it needs several, and nothing else. Prefer read-only commands when they do the job.";
+
+/// Model and system prompt of a request, from the configuration and the flags
+struct ModelChoice {
+    model: String,
+    system: Option<String>,
+}
+
+impl ModelChoice {
+    fn new(config: &Config, model: Option<&String>, persona: Option<&String>) -> Result<Self> {
+        let system = match persona {
+            Some(name) => Some(config.custom_prompts.get(name).cloned().with_context(|| format!("no persona named '{}' in custom_prompts", name))?),
+            None => config.ollama.system_prompt.clone(),
+        };
+        Ok(Self { model: model.cloned().unwrap_or_else(|| config.ollama.model.clone()), system })
+    }
+}
+
+/// Stream the answer to a prompt to stdout
+pub async fn ask(config: &Config, prompt: &str, model: Option<&String>, persona: Option<&String>, json: bool) -> Result<i32> {
+    let choice = ModelChoice::new(config, model, persona)?;
+    let mut prompt = prompt.to_string();
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        let mut piped = String::new();
+        stdin.lock().take(MAX_PIPED_INPUT).read_to_string(&mut piped).context("reading the piped input")?;
+        if !piped.trim().is_empty() {
+            prompt = format!("{}\n\n```\n{}\n```", prompt, piped.trim_end());
+        }
+    }
+    let mut request = ChatRequest::new(choice.model, prompt);
+    request.system = choice.system;
+
+    let backend = config.backend.resolve()?.build()?;
+    if json {
+        let started = Instant::now();
+        let response = backend.generate(&request).await?;
+        let response = ModelResponse::new(&response, elapsed_ms(started));
+        print_document(0, Output::Ask(AskOutput { request: model_request(&request), response }))?;
+        return Ok(0);
+    }
+    let mut stream = backend.stream(&request).await?;
+    let mut stdout = std::io::stdout();
+    let mut ends_with_newline = true;
+    while let Some(fragment) = stream.next().await {
+        let fragment = fragment?;
+        if let Some(last) = fragment.chars().last() {
+            ends_with_newline = last == '\n';
+        }
+        stdout.write_all(fragment.as_bytes())?;
+        stdout.flush()?;
+    }
+    if !ends_with_newline {
+        writeln!(stdout)?;
+    }
+    Ok(0)
+}
+
+/// Ask the model for a command doing what `request` describes and run it once confirmed
+pub async fn run(config: &Config, request: &str, model: Option<&String>, persona: Option<&String>, yes: bool, json: bool) -> Result<i32> {
+    let choice = ModelChoice::new(config, model, persona)?;
+    let executor = PtyExecutor::new()?;
+    let prompt = format!("Request: {}\nWorking directory: {}\nOperating system: {}", request, executor.working_dir(), std::env::consts::OS);
+    let mut chat = ChatRequest::new(choice.model, prompt);
+    chat.system = Some(match choice.system {
+        Some(system) => format!("{}\n\n{}", system, RUN_SYSTEM_PROMPT),
+        None => RUN_SYSTEM_PROMPT.to_string(),
+    });
+    let backend = config.backend.resolve()?.build()?;
+    let started = Instant::now();
+    let reply = backend.generate(&chat).await?;
+    let response = ModelResponse::new(&reply, elapsed_ms(started));
+    let command = command_from_reply(&reply.text).context("the model did not reply with a command")?;
+    let report = |exit_code, result| {
+        if !json {
+            return Ok(());
+        }
+        print_document(exit_code, Output::Run(RunOutput { request: model_request(&chat), response: response.clone(), command: command.clone(), result }))
+    };
+
+    eprintln!("$ {}", command);
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            bail!("not running the command without confirmation; pass --yes to run it unasked");
+        }
+        eprint!("Run it? [y/N] ");
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("Not run.");
+            report(1, None)?;
+            return Ok(1);
+        }
+    }
+    let result = run_command(&executor, &command, !json).await?;
+    let exit_code = result.exit_code;
+    report(exit_code, Some(result))?;
+    Ok(exit_code)
+}
+
+/// Run a workflow, its output streamed to stdout and its progress to stderr
+pub async fn exec(name: &str, json: bool) -> Result<i32> {
+    let mut workflows = Workflows::new();
+    workflows.load()?;
+    let workflow = workflows.get(name).cloned().with_context(|| format!("no workflow named '{}'", name))?;
+    let executor = PtyExecutor::new()?;
+    let mut run = WorkflowRun::new(workflow, std::path::Path::new(executor.working_dir()));
+    let total = run.workflow().steps.len();
+    let started = Instant::now();
+    let mut steps = Vec::new();
+    while let Some(step) = run.next_step().cloned() {
+        eprintln!("▶ {}/{} {}", steps.len() + 1, total, step.label());
+        let mut step_executor = executor.with_env(run.step_env(&step));
+        step_executor.set_working_dir(run.step_dir(&step).to_string_lossy().into_owned());
+        let step_started = Instant::now();
+        let result = run_command(&step_executor, &step.run, !json).await?;
+        run.record(result.exit_code, step_started.elapsed());
+        steps.push(StepOutput { label: step.label().to_string(), result });
+    }
+    eprintln!("{}", run.report());
+    let exit_code = if run.succeeded() { 0 } else { 1 };
+    if json {
+        print_document(exit_code, Output::Exec(ExecOutput {
+            workflow: run.workflow().name.clone(),
+            steps,
+            succeeded: run.succeeded(),
+            stopped: run.stopped().map(str::to_string),
+            duration_ms: elapsed_ms(started),
+        }))?;
+    }
+    Ok(exit_code)
+}
+
+/// Check the configuration, the model server and models, the PTY and the terminal, printing what to fix
+pub async fn doctor(config: Result<Config>) -> i32 {
+    let mut checks = Vec::new();
+    match &config {
+        Ok(config) => {
+            checks.push(config_check(config));
+            match config.backend.build() {
+                Ok(backend) => {
+                    // Only Ollama reports its version
+                    let ollama = config.backend.resolve().is_ok_and(|resolved| resolved.kind == BackendKind::Ollama)
+                        .then(|| config.backend.ollama_client().ok())
+                        .flatten();
+                    let models: Vec<String> = std::iter::once(config.ollama.model.clone()).chain(config.backend.fallback_model.clone()).collect();
+                    checks.extend(doctor::backend_checks(backend.as_ref(), ollama.as_ref(), &models).await);
+                }
+                Err(e) => checks.push(Check::fail("Backend", format!("could not be set up: {}", e), "check the [backend] section of config.toml")),
+            }
+        }
+        Err(e) => checks.push(Check::fail(
+            "Configuration",
+            format!("config.toml could not be read: {:#}", e),
+            "fix the file, or start again from the config.toml in the repository",
+        )),
+    }
+    checks.push(doctor::pty_check().await);
+    checks.extend(doctor::terminal_checks());
+    println!("{}", doctor::report(&checks));
+    i32::from(checks.iter().any(|check| check.status == Status::Fail))
+}
+
+/// Check the sections of the configuration the UI validates when it starts
+fn config_check(config: &Config) -> Check {
+    let sections = [
+        ("keys", Keymap::from_config(&config.keys).map(drop)),
+        ("processors", ProcessorRegistry::from_config(&config.processors).map(drop)),
+        ("tab_colors", TabColorRules::from_config(&config.tab_colors).map(drop)),
+        ("redaction", Redactor::from_config(&config.redaction).map(drop)),
+        ("commands", custom_commands::palette_commands(&config.commands).map(drop)),
+    ];
+    let problems: Vec<String> = sections
+        .into_iter()
+        .filter_map(|(section, result)| result.err().map(|e| format!("[{}] {:#}", section, e)))
+        .collect();
+    if problems.is_empty() {
+        Check::ok("Configuration", "config.toml is valid")
+    } else {
+        Check::fail("Configuration", problems.join("; "), "fix these sections of config.toml, as the UI will not start with them")
+    }
+}
+
+/// Print a failed command's error as a document, for `--json`
+pub fn print_error(error: &anyhow::Error) -> Result<()> {
+    print_document(1, Output::Error(ErrorOutput { message: format!("{:#}", error) }))
+}
+
+/// Print the one document of `--json` to stdout
+fn print_document(exit_code: i32, output: Output) -> Result<()> {
+    println!("{}", serde_json::to_string(&Document::new(exit_code, output))?);
+    Ok(())
+}
+
+/// Describe a request as the protocol does
+fn model_request(request: &ChatRequest) -> ModelRequest {
+    ModelRequest { model: request.model.clone(), system: request.system.clone(), prompt: request.prompt.clone() }
+}
+
+/// Get the milliseconds since `started`
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// Take the command out of a reply, fenced or not
+fn command_from_reply(reply: &str) -> Option<String> {
+    let command = match snippets::code_blocks(reply, "").into_iter().next() {
+        Some(block) => block.code,
+        None => reply.trim().trim_matches('`').to_string(),
+    };
+    let command = command.trim();
+    (!command.is_empty()).then(|| command.to_string())
+}
+
+/// Run a command, writing its output to stdout as it arrives when `echo` is set, or collecting it
+async fn run_command(executor: &PtyExecutor, command: &str, echo: bool) -> Result<CommandResult> {
+    let started = Instant::now();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let task = {
+        let (executor, command) = (executor.clone(), command.to_string());
+        tokio::spawn(async move { executor.execute(&command, tx).await })
+    };
+    let mut stdout = std::io::stdout();
+    let mut exit_code = 1;
+    // Progress bars redraw their lines in the buffer, leaving only their last state
+    let mut output = OutputBuffer::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            ExecutionEvent::StdoutData(data) | ExecutionEvent::StderrData(data) if !echo => output.push_str(&data),
+            ExecutionEvent::StdoutData(data) => {
+                stdout.write_all(data.as_bytes())?;
+                stdout.flush()?;
+            }
+            ExecutionEvent::StderrData(data) => eprint!("{}", data),
+            // The terminal running us draws the image itself
+            ExecutionEvent::Graphics(image) if echo => {
+                stdout.write_all(image.sequence.as_bytes())?;
+                stdout.flush()?;
+            }
+            ExecutionEvent::Completed { exit_code: code, .. } => exit_code = code,
+            ExecutionEvent::Failed(error) => eprintln!("Error: {}", error),
+            ExecutionEvent::Started | ExecutionEvent::Cancelled | ExecutionEvent::WorkingDir(_) | ExecutionEvent::Graphics(_) | ExecutionEvent::Usage(_) => {}
+        }
+    }
+    task.await??;
+    Ok(CommandResult {
+        command: command.to_string(),
+        cwd: executor.working_dir().to_string(),
+        exit_code,
+        duration_ms: elapsed_ms(started),
+        output: output.text(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_from_reply() {
+        assert_eq!(command_from_reply("```sh\ndu -sh * | sort -h\n```").as_deref(), Some("du -sh * | sort -h"));
+        assert_eq!(command_from_reply("Use this:\n\n```bash\nls -la\n```\nIt lists files.").as_deref(), Some("ls -la"));
+        assert_eq!(command_from_reply("`df -h`").as_deref(), Some("df -h"));
+        assert_eq!(command_from_reply("  "), None);
+    }
+}
@@ -1,8 +1,40 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use serde::Deserialize;
+use ollama_client::BackendConfig;
+use terminal_emulator::{ImpactConfig, SandboxConfig, ScrollbackConfig};
+use terminal_ui::keymap::KeymapConfig;
+use terminal_ui::offline::OfflineConfig;
+use terminal_ui::agent::AgentConfig;
+use terminal_ui::prompt_guard::GuardConfig;
+use terminal_ui::share::ShareConfig;
+use terminal_ui::idle_lock::LockConfig;
+use terminal_ui::privacy::RedactionConfig;
+use terminal_ui::trust::TrustConfig;
+use terminal_ui::intent_router::RouterConfig;
+use terminal_ui::custom_commands::CustomCommand;
+use terminal_ui::plugins::PluginConfig;
+use terminal_ui::hooks::HookConfig;
+use terminal_ui::file_index::IndexConfig;
+use terminal_ui::summaries::SummaryConfig;
+use terminal_ui::corrections::CorrectionConfig;
+use terminal_ui::images::ImageConfig;
+use terminal_ui::tab_colors::TabColorConfig;
+
+use crate::mcp::server::McpServerConfig;
+use terminal_ui::theme::AccessibilityConfig;
+
+/// Find the configuration: `config.toml` in the working directory, or else the one in the config directory
+pub fn path() -> PathBuf {
+    let local = PathBuf::from("config.toml");
+    match terminal_ui::onboarding::config_dir() {
+        Some(dir) if !local.exists() => dir.join("config.toml"),
+        _ => local,
+    }
+}
 
 /// The main configuration structure, representing the TOML format
 #[derive(Debug, Deserialize, Clone)]
@@ -10,9 +42,113 @@ pub struct Config {
     /// Ollama-specific configuration
     pub ollama: OllamaConfig,
     
+    /// Server that AI commands are sent to
+    #[serde(default)]
+    pub backend: BackendConfig,
+    
     /// Custom prompts that can be referenced by name
     #[serde(default)]
     pub custom_prompts: std::collections::HashMap<String, String>,
+    
+    /// Limits on how much command output is kept in memory
+    #[serde(default)]
+    pub scrollback: ScrollbackConfig,
+    
+    /// Approval and change records for commands that modify files
+    #[serde(default)]
+    pub impact: ImpactConfig,
+    
+    /// Restrictions for `!sandbox` commands, and whether agent commands are sandboxed
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    
+    /// Accessibility settings, such as a palette for color vision deficiency
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+    
+    /// Keys for scrollback motions, overriding the defaults
+    #[serde(default)]
+    pub keys: KeymapConfig,
+    
+    /// Whether to start offline and queue AI prompts made offline
+    #[serde(default)]
+    pub offline: OfflineConfig,
+    
+    /// Approval and output limits for `ai-terminal --mcp-server`
+    #[serde(default)]
+    pub mcp_server: McpServerConfig,
+    
+    /// MCP servers whose tools are offered to the model, by name, with the command starting each
+    #[serde(default)]
+    pub mcp_servers: std::collections::BTreeMap<String, String>,
+    
+    /// Output processors by command pattern, such as `"^cat .*\\.json$" = "json"`
+    #[serde(default)]
+    pub processors: std::collections::BTreeMap<String, String>,
+    
+    /// Tab colors and icons by command pattern, such as `"^ssh .*prod" = "red 🔥"`, the first match in file order winning
+    #[serde(default)]
+    pub tab_colors: TabColorConfig,
+    
+    /// Step budget and commands run without asking for `/agent`
+    #[serde(default)]
+    pub agent: AgentConfig,
+    
+    /// Size above which AI requests are previewed, and the longest response kept
+    #[serde(default)]
+    pub prompt_guard: GuardConfig,
+    
+    /// Gist or paste service `/share` uploads sessions to
+    #[serde(default)]
+    pub share: ShareConfig,
+    
+    /// Privacy screen covering idle sessions until the passphrase is entered
+    #[serde(default)]
+    pub idle_lock: LockConfig,
+    
+    /// Masking of secrets in command output sent to the AI, with extra patterns
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    
+    /// How directories without a `/trust` mark are treated
+    #[serde(default)]
+    pub trust: TrustConfig,
+    
+    /// Routing of AI prompts that ask for a system tool to its command
+    #[serde(default)]
+    pub router: RouterConfig,
+    
+    /// Commands added to the command palette
+    #[serde(default)]
+    pub commands: Vec<CustomCommand>,
+    
+    /// Whether WebAssembly plugins are loaded, and the capabilities granted to each
+    #[serde(default)]
+    pub plugins: PluginConfig,
+    
+    /// Shell commands run before and after every command block
+    #[serde(default)]
+    pub hooks: HookConfig,
+    
+    /// Prompt templates by name, replacing built-in ones of the same name
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+    
+    /// Embedding model and sizes of the project index
+    #[serde(default)]
+    pub index: IndexConfig,
+    
+    /// When long command output is summarized by the AI
+    #[serde(default)]
+    pub summaries: SummaryConfig,
+    
+    /// Whether failed commands get a suggested correction, and whether the AI is asked for one
+    #[serde(default)]
+    pub corrections: CorrectionConfig,
+    
+    /// How images in command output are drawn, detected from the terminal when unset
+    #[serde(default)]
+    pub images: ImageConfig,
 }
 
 /// Configuration for Ollama integration
@@ -35,6 +171,7 @@ impl Config {
     }
     
     /// Get a custom prompt by name
+    #[allow(dead_code)]
     pub fn get_custom_prompt(&self, name: &str) -> Option<&String> {
         self.custom_prompts.get(name)
     }
@@ -43,9 +180,9 @@ impl Config {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
     use std::io::Write;
     use tempfile::NamedTempFile;
+    use terminal_ui::plugins::Capability;
 
     #[test]
     fn test_config_loading() {
@@ -86,6 +223,60 @@ model = "test-model"
         // Verify the loaded configuration
         assert_eq!(config.ollama.model, "test-model");
         assert_eq!(config.ollama.system_prompt, None);
+        assert_eq!(config.scrollback, ScrollbackConfig::default());
+        assert_eq!(config.backend, BackendConfig::default());
+        assert_eq!(config.accessibility.color_vision, None);
+        assert!(!config.offline.enabled);
+        assert_eq!(config.agent, AgentConfig::default());
+        assert_eq!(config.share, ShareConfig::default());
+        assert_eq!(config.sandbox, SandboxConfig::default());
+        assert_eq!(config.idle_lock, LockConfig::default());
+        assert_eq!(config.redaction, RedactionConfig::default());
+        assert_eq!(config.trust, TrustConfig::default());
+        assert_eq!(config.router, RouterConfig::default());
+    }
+
+    #[test]
+    fn test_config_loading_openai_backend() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "qwen2.5-7b-instruct"
+
+[backend]
+kind = "openai"
+base_url = "http://localhost:1234/v1"
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+
+        assert_eq!(config.backend.kind, ollama_client::BackendKind::Openai);
+        assert_eq!(config.backend.base_url.as_deref(), Some("http://localhost:1234/v1"));
+        assert_eq!(config.backend.api_key_env, None);
+    }
+
+    #[test]
+    fn test_config_loading_scrollback_limits() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[scrollback.block]
+max_lines = 500
+
+[scrollback.total]
+max_bytes = 1048576
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+
+        // Unset limits keep their defaults
+        assert_eq!(config.scrollback.block.max_lines, 500);
+        assert_eq!(config.scrollback.block.max_bytes, ScrollbackConfig::default().block.max_bytes);
+        assert_eq!(config.scrollback.total.max_bytes, 1048576);
     }
 
     #[test]
@@ -110,4 +301,172 @@ prompt2 = "Second test prompt"
         assert_eq!(config.get_custom_prompt("prompt2"), Some(&"Second test prompt".to_string()));
         assert_eq!(config.get_custom_prompt("nonexistent"), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_bindings() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[keys]
+previous_block = ["ctrl+p", "alt+up"]
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.keys.bindings.len(), 1);
+        assert!(terminal_ui::keymap::Keymap::from_config(&config.keys).is_ok());
+    }
+
+    #[test]
+    fn test_custom_commands() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[[commands]]
+name = "Deploy"
+run = "./deploy.sh {{ENV}}"
+
+[[commands]]
+name = "Explain Last Error"
+category = "AI"
+ask = "Explain the last error"
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        let commands = terminal_ui::custom_commands::palette_commands(&config.commands).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].argument.as_deref(), Some("env"));
+        assert_eq!(commands[1].category, "AI");
+    }
+
+    #[test]
+    fn test_plugin_grants() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[plugins.grants]
+command-stats = ["commands", "status"]
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert!(config.plugins.enabled);
+        assert_eq!(config.plugins.grants["command-stats"], [Capability::Commands, Capability::Status]);
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[hooks]
+pre_exec = ["tmux rename-window {{command}}"]
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.hooks.pre_exec, ["tmux rename-window {{command}}"]);
+        assert!(config.hooks.post_exec.is_empty());
+        assert_eq!(config.hooks.timeout_secs, 5);
+    }
+
+    #[test]
+    fn test_templates() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[templates]
+code-review = "Review this:\n{{clipboard}}"
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.templates["code-review"], "Review this:\n{{clipboard}}");
+    }
+
+    #[test]
+    fn test_index() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[index]
+model = "mxbai-embed-large"
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.index.model, "mxbai-embed-large");
+        assert_eq!(config.index.results, 6);
+    }
+
+    #[test]
+    fn test_summaries() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[summaries]
+mode = "auto"
+min_lines = 80
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert_eq!(config.summaries.mode, terminal_ui::summaries::SummaryMode::Auto);
+        assert_eq!(config.summaries.min_lines, 80);
+        assert_eq!(config.summaries.model, None);
+    }
+
+    #[test]
+    fn test_corrections() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[ollama]
+model = "test-model"
+
+[corrections]
+ai = true
+"#;
+        temp_file.write_all(config_content.as_bytes()).unwrap();
+
+        let config = Config::load(temp_file.path()).unwrap();
+        assert!(config.corrections.enabled);
+        assert!(config.corrections.ai);
+    }
+
+    #[test]
+    fn test_onboarding_config() {
+        use terminal_ui::onboarding::{Answers, KeyPreset};
+
+        let dir = tempfile::tempdir().unwrap();
+        let answers = Answers {
+            url: terminal_ui::onboarding::DEFAULT_URL.to_string(),
+            model: "llama3.2".to_string(),
+            theme: "light".to_string(),
+            keys: KeyPreset::Vi,
+            ai_commands: false,
+        };
+        let path = answers.save(dir.path()).unwrap();
+        let config = Config::load(path).unwrap();
+        assert_eq!(config.ollama.model, "llama3.2");
+        assert_eq!(config.backend.base_url.as_deref(), Some("http://localhost:11434/api"));
+        assert!(config.trust.untrusted_by_default);
+        assert!(config.agent.auto_approve.is_empty());
+        assert!(terminal_ui::keymap::Keymap::from_config(&config.keys).is_ok());
+        assert!(dir.path().join("theme.toml").exists());
+    }
+}
@@ -1,47 +1,195 @@
+use std::io::IsTerminal;
+
 use anyhow::Result;
-use clap::Command;
+use clap::{Arg, ArgAction, Command};
 use tracing::info;
-use tracing_subscriber;
 
 use terminal_ui::TerminalSession;
+use terminal_ui::onboarding;
+use terminal_ui::startup::StartupProfile;
+use terminal_emulator::{CommandHistory, PtyExecutor};
 
+#[allow(dead_code, unused_imports)] // The standalone AI command processor is not used by the UI
 mod mcp;
 mod config; // Add this line to import the config module
+mod cli;
 
-use mcp::{MCPClient, process_ai_command};
 use config::Config; // Add this line to import the Config struct
+use mcp::client::McpToolbox;
+use mcp::server::{TerminalMcpServer, TtyApprover};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    let mut startup = StartupProfile::start();
     
-    // Load configuration
-    let config = Config::load("config.toml")?;
-    info!("Loaded configuration: {:?}", config);
-    
-    let _matches = Command::new("ai-terminal")
+    let matches = Command::new("ai-terminal")
         .version("0.1.0")
         .about("AI-powered terminal")
+        .arg(
+            Arg::new("mcp-server")
+                .long("mcp-server")
+                .action(ArgAction::SetTrue)
+                .help("Serve terminal tools to MCP clients over stdin/stdout instead of starting the UI"),
+        )
+        .arg(
+            Arg::new("mcp-sse")
+                .long("mcp-sse")
+                .value_name("PORT")
+                .value_parser(clap::value_parser!(u16))
+                .conflicts_with("mcp-server")
+                .help("Serve terminal tools to MCP clients over HTTP with server-sent events on 127.0.0.1:PORT instead of starting the UI"),
+        )
+        .subcommand(
+            Command::new("ask")
+                .about("Stream the AI's answer to a prompt to stdout, with piped input sent along")
+                .args(model_args())
+                .arg(json_arg())
+                .arg(Arg::new("prompt").required(true).num_args(1..).help("Prompt to send")),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Ask the AI for a shell command doing what is described and run it once confirmed")
+                .args(model_args())
+                .arg(json_arg())
+                .arg(Arg::new("yes").long("yes").short('y').action(ArgAction::SetTrue).help("Run the command without asking"))
+                .arg(Arg::new("request").required(true).num_args(1..).help("What the command should do")),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Run a workflow from the workflows directory")
+                .arg(json_arg())
+                .arg(Arg::new("workflow").required(true).help("Name of the workflow")),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the configuration, the model server and models, the PTY and the terminal, saying how to fix problems"),
+        )
         .get_matches();
+    let mcp_sse = matches.get_one::<u16>("mcp-sse").copied();
+    let mcp_server = matches.get_flag("mcp-server") || mcp_sse.is_some();
+    
+    // Initialize logging; stdout carries the protocol when serving MCP and the result of one-shot commands
+    if matches.subcommand().is_some() {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).with_max_level(tracing::Level::WARN).init();
+    } else if mcp_server {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+    
+    // The doctor reports a broken configuration rather than stopping at it
+    if let Some(("doctor", _)) = matches.subcommand() {
+        std::process::exit(cli::doctor(Config::load(config::path())).await);
+    }
+    
+    // The first start asks for what the defaults cannot know before opening the UI
+    if matches.subcommand().is_none()
+        && !mcp_server
+        && onboarding::is_first_run()
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+        && let Some(dir) = onboarding::config_dir()
+    {
+        match onboarding::run(&dir).await? {
+            Some(path) => info!("Wrote {}", path.display()),
+            None => {
+                println!("Setup cancelled; nothing was written. Run ai-terminal again to set it up, or write config.toml yourself.");
+                return Ok(());
+            }
+        }
+    }
+    
+    // Load configuration
+    let config = startup.time("configuration", || Config::load(config::path()))?;
+    info!("Loaded configuration: {:?}", config);
+    
+    if let Some((name, args)) = matches.subcommand() {
+        let words = |id: &str| args.get_many::<String>(id).map(|words| words.cloned().collect::<Vec<_>>().join(" ")).unwrap_or_default();
+        let model_arg = |id: &str| args.try_get_one::<String>(id).ok().flatten();
+        let (model, persona) = (model_arg("model"), model_arg("persona"));
+        let json = args.get_flag("json");
+        let result = match name {
+            "ask" => cli::ask(&config, &words("prompt"), model, persona, json).await,
+            "run" => cli::run(&config, &words("request"), model, persona, args.get_flag("yes"), json).await,
+            _ => cli::exec(&words("workflow"), json).await,
+        };
+        let exit_code = match result {
+            Ok(exit_code) => exit_code,
+            Err(e) if json => {
+                cli::print_error(&e)?;
+                1
+            }
+            Err(e) => return Err(e),
+        };
+        std::process::exit(exit_code);
+    }
+    
+    if mcp_server {
+        let history = CommandHistory::new(1000)?;
+        let mut server = TerminalMcpServer::new(PtyExecutor::new()?, history, config.mcp_server, TtyApprover);
+        return match mcp_sse {
+            Some(port) => server.serve_sse(tokio::net::TcpListener::bind(("127.0.0.1", port)).await?).await,
+            None => server.serve_stdio().await,
+        };
+    }
 
     info!("Starting AI Terminal...");
 
     // Create and configure terminal session
     info!("About to create terminal session");
-    let mut terminal_session = TerminalSession::new()?;
+    let mut terminal_session = TerminalSession::with_startup_profile(startup)?;
+    terminal_session.configure_ai(config.ollama.model.clone(), config.ollama.system_prompt.clone());
+    // Bad server settings leave a plain shell rather than no terminal at all
+    if let Err(e) = terminal_session.configure_backend(&config.backend) {
+        tracing::warn!("Could not set up the model server: {:#}", e);
+        terminal_session.disable_backend(format!("invalid [backend] settings: {:#}", e));
+    }
+    terminal_session.configure_personas(config.custom_prompts.clone());
+    terminal_session.configure_scrollback(config.scrollback);
+    terminal_session.configure_impact(config.impact);
+    terminal_session.configure_sandbox(config.sandbox);
+    terminal_session.configure_accessibility(config.accessibility);
+    terminal_session.configure_keys(&config.keys)?;
+    terminal_session.configure_offline(config.offline);
+    terminal_session.configure_processors(&config.processors)?;
+    terminal_session.configure_tab_colors(&config.tab_colors)?;
+    terminal_session.configure_agent(config.agent.clone());
+    terminal_session.configure_prompt_guard(config.prompt_guard);
+    terminal_session.configure_share(config.share.clone());
+    terminal_session.configure_idle_lock(config.idle_lock.clone())?;
+    terminal_session.configure_redaction(&config.redaction)?;
+    terminal_session.configure_trust(config.trust);
+    terminal_session.configure_router(config.router.clone());
+    terminal_session.configure_commands(&config.commands)?;
+    terminal_session.configure_plugins(config.plugins.clone());
+    terminal_session.configure_hooks(config.hooks.clone());
+    terminal_session.configure_templates(&config.templates);
+    terminal_session.configure_index(config.index.clone());
+    terminal_session.configure_summaries(config.summaries.clone());
+    terminal_session.configure_corrections(config.corrections);
+    terminal_session.configure_images(config.images);
+    terminal_session.configure_scripts();
+    if !config.mcp_servers.is_empty() {
+        let toolbox = McpToolbox::connect(&config.mcp_servers).await;
+        terminal_session.configure_tools(Box::new(toolbox));
+    }
     info!("Terminal session created successfully");
     
     info!("About to start terminal application");
     
-    // Setup terminal
-    let mut terminal = terminal_session.setup_terminal()?;
-    
-    // Run the application
-    let result = terminal_session.run().await;
-    
-    // Restore terminal
-    terminal_session.restore_terminal(&mut terminal)?;
-    
-    result
+    // Run the application (sets up and restores the terminal itself)
+    terminal_session.run().await
+}
+
+/// Options choosing the model of a one-shot request
+fn model_args() -> [Arg; 2] {
+    [
+        Arg::new("model").long("model").short('m').help("Model to use instead of the configured one"),
+        Arg::new("persona").long("persona").help("Persona from custom_prompts to use as the system prompt"),
+    ]
+}
+
+/// The `--json` flag of the headless subcommands
+fn json_arg() -> Arg {
+    Arg::new("json").long("json").action(ArgAction::SetTrue).help("Print one JSON document describing the request and its result instead of streaming text")
 }
\ No newline at end of file
@@ -6,7 +6,6 @@ use anyhow::Result;
 use tracing::{info, error};
 
 use ollama_client::{OllamaClient, OllamaRequest, OllamaResponse};
-use terminal_emulator::CommandBlock;
 use futures_util::StreamExt;
 
 /// Process an AI command and return the response
@@ -1,28 +1,41 @@
 //! # MCP Client for AI Terminal
 //!
 //! This module implements an MCP client that can connect to MCP servers
-//! and interact with their tools.
+//! and interact with their tools. [`McpToolbox`] connects to every server in
+//! the `[mcp_servers]` section of the configuration and offers their tools to
+//! the model through the terminal UI's [`ToolProvider`] interface.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use anyhow::Result;
-use jsonrpc_core::{Call, MethodCall, Params, Version};
+use futures_util::future::BoxFuture;
+use jsonrpc_core::{MethodCall, Notification, Params, Version};
 use jsonrpc_core::serde_json::{self, Value};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicU64, Ordering};
+use terminal_ui::tools::{ToolOutput, ToolProvider, ToolSpec};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, ChildStdout};
-use tracing::{info, error};
+use tracing::{info, error, warn};
+
+/// MCP protocol revision requested from servers
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Longest wait for a server to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// MCP client for connecting to MCP servers
 pub struct MCPClient {
     /// Child process for the MCP server
     child: Child,
-    
+
     /// stdin handle for sending requests
     stdin: ChildStdin,
-    
+
     /// stdout handle for reading responses
     stdout: BufReader<ChildStdout>,
-    
+
     /// Request ID counter
     id_counter: AtomicU64,
 }
@@ -34,6 +47,26 @@ pub struct ListToolsParams {
     pub cursor: Option<String>,
 }
 
+/// Result of listing tools
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    pub tools: Vec<ToolInfo>,
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// A tool published by a server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
 /// Parameters for calling a tool
 #[derive(Debug, Serialize)]
 pub struct CallToolParams {
@@ -43,8 +76,10 @@ pub struct CallToolParams {
 
 /// Result of calling a tool
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CallToolResult {
     pub content: Vec<TextContent>,
+    #[serde(default)]
     pub is_error: bool,
 }
 
@@ -53,42 +88,82 @@ pub struct CallToolResult {
 pub struct TextContent {
     #[serde(rename = "type")]
     pub content_type: String,
+    #[serde(default)]
     pub text: String,
 }
 
+impl CallToolResult {
+    /// Join the text blocks of the result, noting blocks of other kinds
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|content| match content.content_type.as_str() {
+                "text" => content.text.clone(),
+                other => format!("[{} content]", other),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
 impl MCPClient {
-    /// Create a new MCP client
+    /// Start an MCP server and complete the initialization handshake
     pub async fn new(server_command: &str) -> Result<Self> {
         info!("Starting MCP server: {}", server_command);
-        
-        // Spawn the MCP server process
+
+        // Spawn the MCP server process; its stderr would draw over the UI
         let mut child = tokio::process::Command::new("sh")
             .arg("-c")
             .arg(server_command)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::inherit())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
             .spawn()?;
-        
+
         // Get handles to stdin and stdout
         let stdin = child.stdin.take().ok_or(anyhow::anyhow!("Failed to get stdin"))?;
         let stdout = BufReader::new(child.stdout.take().ok_or(anyhow::anyhow!("Failed to get stdout"))?);
-        
-        Ok(Self {
+
+        let mut client = Self {
             child,
             stdin,
             stdout,
             id_counter: AtomicU64::new(1),
-        })
+        };
+        client.initialize().await?;
+        Ok(client)
     }
-    
-    /// List available tools from the MCP server
-    pub async fn list_tools(&mut self) -> Result<Value> {
-        let params = ListToolsParams { cursor: None };
-        let result = self.send_request("tools/list", Some(serde_json::to_value(params)?)).await?;
-        Ok(result)
+
+    /// Agree on the protocol with the server
+    async fn initialize(&mut self) -> Result<()> {
+        let params = serde_json::json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "ai-terminal", "version": env!("CARGO_PKG_VERSION") },
+        });
+        let result = self.send_request("initialize", Some(params)).await?;
+        let server_info = result.get("serverInfo").cloned().unwrap_or_default();
+        info!("MCP server initialized: {}", server_info);
+        self.send_notification("notifications/initialized").await
+    }
+
+    /// List available tools from the MCP server, following pagination
+    pub async fn list_tools(&mut self) -> Result<Vec<ToolInfo>> {
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        loop {
+            let params = ListToolsParams { cursor };
+            let result = self.send_request("tools/list", Some(serde_json::to_value(params)?)).await?;
+            let page: ListToolsResult = serde_json::from_value(result)?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
     }
-    
+
     /// Call a specific tool on the MCP server
     pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<CallToolResult> {
         let params = CallToolParams {
@@ -99,50 +174,155 @@ impl MCPClient {
         let tool_result: CallToolResult = serde_json::from_value(result)?;
         Ok(tool_result)
     }
-    
+
+    /// Write one JSON-RPC message to the server
+    async fn send_message(&mut self, message: &str) -> Result<()> {
+        self.stdin.write_all(message.as_bytes()).await?;
+        self.stdin.write_all(b"\n").await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Send a JSON-RPC notification, which gets no response
+    async fn send_notification(&mut self, method: &str) -> Result<()> {
+        let notification = Notification {
+            jsonrpc: Some(Version::V2),
+            method: method.to_string(),
+            params: Params::None,
+        };
+        self.send_message(&serde_json::to_string(&notification)?).await
+    }
+
     /// Send a JSON-RPC request to the MCP server
     async fn send_request(&mut self, method: &str, params: Option<Value>) -> Result<Value> {
         // Generate a unique request ID
         let id = self.id_counter.fetch_add(1, Ordering::Relaxed);
-        
+
         // Create the JSON-RPC request
         let request = MethodCall {
             jsonrpc: Some(Version::V2),
             method: method.to_string(),
-            params: params.map(|p| Params::from(p)).unwrap_or(Params::None),
+            params: params.map(serde_json::from_value).transpose()?.unwrap_or(Params::None),
             id: jsonrpc_core::Id::Num(id),
         };
-        
+
         // Serialize the request to JSON
         let request_json = serde_json::to_string(&request)?;
         info!("Sending request: {}", request_json);
-        
-        // Send the request to the server
-        self.stdin.write_all(request_json.as_bytes()).await?;
-        self.stdin.write_all(b"\\n").await?;
-        self.stdin.flush().await?;
-        
-        // Read the response from the server
-        let mut response_line = String::new();
-        self.stdout.read_line(&mut response_line).await?;
-        
-        info!("Received response: {}", response_line);
-        
-        // Parse the response
-        let response: jsonrpc_core::Output = serde_json::from_str(&response_line)?;
-        
+        self.send_message(&request_json).await?;
+
+        // Read until the response to this request, skipping notifications from the server
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, async {
+            let mut response_line = String::new();
+            loop {
+                response_line.clear();
+                if self.stdout.read_line(&mut response_line).await? == 0 {
+                    anyhow::bail!("MCP server closed its output");
+                }
+                info!("Received response: {}", response_line.trim_end());
+                let Ok(output) = serde_json::from_str::<jsonrpc_core::Output>(&response_line) else {
+                    continue;
+                };
+                if *output.id() == jsonrpc_core::Id::Num(id) {
+                    return Ok(output);
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("MCP server did not answer {} within {:?}", method, REQUEST_TIMEOUT))??;
+
         match response {
             jsonrpc_core::Output::Success(success) => Ok(success.result),
             jsonrpc_core::Output::Failure(failure) => {
                 error!("JSON-RPC error: {:?}", failure.error);
-                Err(anyhow::anyhow!("JSON-RPC error: {:?}", failure.error))
+                Err(anyhow::anyhow!("JSON-RPC error: {}", failure.error.message))
             }
         }
     }
-    
+
     /// Kill the MCP server process
     pub async fn kill(mut self) -> Result<()> {
         self.child.kill().await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// The tools of every configured MCP server, named `server.tool`
+#[derive(Default)]
+pub struct McpToolbox {
+    /// Connected servers by name
+    clients: BTreeMap<String, MCPClient>,
+
+    /// Tools of all servers
+    tools: Vec<ToolSpec>,
+}
+
+impl McpToolbox {
+    /// Connect to each server, given as name and command; servers that fail are skipped with a warning
+    pub async fn connect(servers: &BTreeMap<String, String>) -> Self {
+        let mut toolbox = Self::default();
+        for (name, command) in servers {
+            if let Err(e) = toolbox.add_server(name, command).await {
+                warn!("Skipping MCP server {}: {:#}", name, e);
+            }
+        }
+        toolbox
+    }
+
+    /// Start one server and collect its tools
+    async fn add_server(&mut self, name: &str, command: &str) -> Result<()> {
+        let mut client = MCPClient::new(command).await?;
+        for tool in client.list_tools().await? {
+            self.tools.push(ToolSpec {
+                name: format!("{}.{}", name, tool.name),
+                description: tool.description,
+                input_schema: tool.input_schema,
+            });
+        }
+        self.clients.insert(name.to_string(), client);
+        Ok(())
+    }
+}
+
+impl ToolProvider for McpToolbox {
+    fn tools(&self) -> &[ToolSpec] {
+        &self.tools
+    }
+
+    fn call<'a>(&'a mut self, name: &'a str, arguments: Value) -> BoxFuture<'a, Result<ToolOutput>> {
+        Box::pin(async move {
+            let (server, tool) = name.split_once('.').ok_or_else(|| anyhow::anyhow!("unknown tool '{}'", name))?;
+            let client = self.clients.get_mut(server).ok_or_else(|| anyhow::anyhow!("unknown tool '{}'", name))?;
+            let result = client.call_tool(tool, arguments).await?;
+            Ok(ToolOutput { text: result.text(), is_error: result.is_error })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_toolbox_with_scripted_server() {
+        // A shell script stands in for a server, sending a notification before one response
+        let script = r#"
+            read init; echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05","capabilities":{}}}'
+            read initialized
+            read list; echo '{"jsonrpc":"2.0","method":"notifications/message","params":{}}'
+            echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"echo","description":"Echo text","inputSchema":{"type":"object"}}]}}'
+            read call; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"hello"}],"isError":false}}'
+        "#;
+        let servers = BTreeMap::from([
+            ("fake".to_string(), script.to_string()),
+            ("broken".to_string(), "exit 1".to_string()),
+        ]);
+        let mut toolbox = McpToolbox::connect(&servers).await;
+        assert_eq!(toolbox.tools().len(), 1);
+        assert_eq!(toolbox.tools()[0].name, "fake.echo");
+
+        let output = toolbox.call("fake.echo", serde_json::json!({ "text": "hello" })).await.unwrap();
+        assert_eq!(output, ToolOutput { text: "hello".to_string(), is_error: false });
+        assert!(toolbox.call("broken.echo", Value::Null).await.is_err());
+    }
+}
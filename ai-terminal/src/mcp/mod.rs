@@ -1,9 +1,11 @@
 //! # MCP Module for AI Terminal
 //!
-//! This module contains the MCP client implementation for connecting to MCP servers.
+//! This module contains the MCP client implementation for connecting to MCP servers,
+//! and the server exposing the terminal's own actions to MCP clients.
 
 pub mod client;
 pub mod ai_command_processor;
+pub mod server;
 
 pub use client::MCPClient;
 pub use ai_command_processor::process_ai_command;
\ No newline at end of file
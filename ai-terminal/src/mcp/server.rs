@@ -0,0 +1,611 @@
+//! # MCP Server for AI Terminal
+//!
+//! This module exposes the terminal to external AI clients through the Model
+//! Context Protocol. Started with `ai-terminal --mcp-server`, the binary reads
+//! JSON-RPC requests from stdin and answers on stdout, one message per line.
+//! Started with `ai-terminal --mcp-sse <port>`, it serves the same requests
+//! over HTTP on `127.0.0.1`: a client opens an event stream with `GET /sse`,
+//! is told where to post its messages, and gets the responses back as events
+//! on the stream. Either way it publishes these tools:
+//!
+//! - `run_command`: run a shell command and return its output
+//! - `read_block_output`: read the full output of a command run earlier
+//! - `list_history`: list recent commands from the shared command history
+//! - `get_cwd`: get the directory commands run in
+//!
+//! Every tool call is shown on the controlling terminal and only runs once the
+//! user approves it, unless the tool is listed in `auto_approve` in the
+//! `[mcp_server]` section of the configuration.
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+
+use anyhow::{bail, ensure, Context, Result};
+use jsonrpc_core::serde_json::{self, Value, json};
+use jsonrpc_core::{Call, Error, Failure, Id, MethodCall, Output, Request, Response, Success, Version};
+use serde::Deserialize;
+use terminal_emulator::{CommandBlock, CommandHistory, PtyExecutor};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// MCP protocol revision implemented by the server
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Number of command blocks kept for `read_block_output`
+const MAX_BLOCKS: usize = 100;
+
+/// Path a client opens the event stream at
+const SSE_PATH: &str = "/sse";
+
+/// Path a client posts its messages to, followed by its session
+const MESSAGE_PATH: &str = "/message";
+
+/// Largest message body accepted over HTTP
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long a client may take to send a request, so a silent one cannot hold up the rest
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Configuration for the MCP server
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct McpServerConfig {
+    /// Tools that run without asking, such as `get_cwd`
+    pub auto_approve: Vec<String>,
+
+    /// Longest output returned by a tool, in bytes; longer output keeps its end
+    pub max_output_bytes: usize,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            auto_approve: Vec::new(),
+            max_output_bytes: 64 * 1024,
+        }
+    }
+}
+
+/// Asks the user whether a tool call may run
+pub trait Approver {
+    /// Ask about the action described by `summary`, returning whether it was approved
+    fn approve(&mut self, summary: &str) -> Result<bool>;
+}
+
+/// Asks on the controlling terminal, since stdin and stdout carry the protocol
+pub struct TtyApprover;
+
+impl Approver for TtyApprover {
+    fn approve(&mut self, summary: &str) -> Result<bool> {
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|e| anyhow::anyhow!("no terminal to ask for approval ({}); add the tool to auto_approve", e))?;
+        write!(tty, "\r\nMCP client request: {}\r\nAllow? [y/N] ", summary)?;
+        tty.flush()?;
+        let mut answer = String::new();
+        BufReader::new(tty).read_line(&mut answer)?;
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+/// Arguments of `run_command`
+#[derive(Debug, Deserialize)]
+struct RunCommandArgs {
+    command: String,
+    cwd: Option<String>,
+}
+
+/// Arguments of `read_block_output`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ReadBlockArgs {
+    block: Option<usize>,
+}
+
+/// Arguments of `list_history`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ListHistoryArgs {
+    limit: Option<usize>,
+    query: Option<String>,
+}
+
+/// MCP server publishing terminal actions as tools
+pub struct TerminalMcpServer<A: Approver> {
+    /// Runs the commands
+    executor: PtyExecutor,
+
+    /// Command history shared with the terminal UI
+    history: CommandHistory,
+
+    /// Commands run by this server, oldest first, numbered from 1
+    blocks: Vec<CommandBlock>,
+
+    /// Number of blocks dropped from the front of `blocks`
+    dropped_blocks: usize,
+
+    /// Settings
+    config: McpServerConfig,
+
+    /// Asks the user about each tool call
+    approver: A,
+}
+
+impl<A: Approver> TerminalMcpServer<A> {
+    /// Create a server running commands with `executor` and recording them in `history`
+    pub fn new(executor: PtyExecutor, history: CommandHistory, config: McpServerConfig, approver: A) -> Self {
+        Self {
+            executor,
+            history,
+            blocks: Vec::new(),
+            dropped_blocks: 0,
+            config,
+            approver,
+        }
+    }
+
+    /// Serve requests from stdin until it closes
+    pub async fn serve_stdio(&mut self) -> Result<()> {
+        let mut reader = AsyncBufReader::new(tokio::io::stdin());
+        let mut stdout = tokio::io::stdout();
+        let mut line = String::new();
+
+        info!("AI Terminal MCP server ready");
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                break;
+            }
+            if let Some(response) = self.handle_line(line.trim()).await {
+                stdout.write_all(response.as_bytes()).await?;
+                stdout.write_all(b"\n").await?;
+                stdout.flush().await?;
+            }
+        }
+        info!("AI Terminal MCP server shutting down");
+        Ok(())
+    }
+
+    /// Serve clients over HTTP with server-sent events, one event stream at a time
+    ///
+    /// A newer event stream takes over from an older one, whose session then
+    /// stops being accepted.
+    pub async fn serve_sse(&mut self, listener: TcpListener) -> Result<()> {
+        info!("AI Terminal MCP server listening on http://{}{}", listener.local_addr()?, SSE_PATH);
+        let mut events: Option<(String, TcpStream)> = None;
+        loop {
+            let (mut connection, _) = listener.accept().await?;
+            let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut connection)).await {
+                Ok(Ok(request)) => request,
+                Ok(Err(e)) => {
+                    warn!("Bad MCP HTTP request: {:#}", e);
+                    let _ = respond(&mut connection, "400 Bad Request", &format!("{:#}", e)).await;
+                    continue;
+                }
+                Err(_) => {
+                    warn!("MCP HTTP client sent no request within {}s", REQUEST_TIMEOUT.as_secs());
+                    continue;
+                }
+            };
+            // Browsers send an origin; pages from elsewhere must not reach the terminal
+            if request.origin.as_deref().is_some_and(|origin| !is_local_origin(origin)) {
+                let _ = respond(&mut connection, "403 Forbidden", "only local clients may connect").await;
+                continue;
+            }
+            let session = request.path.strip_prefix(MESSAGE_PATH).and_then(|query| query.strip_prefix("?sessionId="));
+            match (request.method.as_str(), request.path.as_str(), session) {
+                ("GET", SSE_PATH, _) => {
+                    let session = format!("{:x}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos());
+                    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+                    let endpoint = format!("event: endpoint\ndata: {}?sessionId={}\n\n", MESSAGE_PATH, session);
+                    if connection.write_all(format!("{}{}", head, endpoint).as_bytes()).await.is_ok() {
+                        info!("MCP client connected over SSE");
+                        events = Some((session, connection));
+                    }
+                }
+                ("POST", _, Some(session)) if events.as_ref().is_some_and(|(current, _)| current == session) => {
+                    let _ = respond(&mut connection, "202 Accepted", "").await;
+                    let Some(response) = self.handle_line(request.body.trim()).await else {
+                        continue;
+                    };
+                    let Some((_, stream)) = &mut events else {
+                        continue;
+                    };
+                    let event = format!("event: message\ndata: {}\n\n", response);
+                    if stream.write_all(event.as_bytes()).await.is_err() {
+                        info!("MCP client closed its event stream");
+                        events = None;
+                    }
+                }
+                ("POST", _, Some(_)) => {
+                    let _ = respond(&mut connection, "404 Not Found", "unknown session; open the event stream again").await;
+                }
+                _ => {
+                    let _ = respond(&mut connection, "404 Not Found", "").await;
+                }
+            }
+        }
+    }
+
+    /// Handle one JSON-RPC message, returning the response to send, if any
+    pub async fn handle_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            return None;
+        }
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Unparseable MCP message: {}", e);
+                let failure = Output::Failure(Failure { jsonrpc: Some(Version::V2), error: Error::parse_error(), id: Id::Null });
+                return serde_json::to_string(&Response::Single(failure)).ok();
+            }
+        };
+
+        let response = match request {
+            Request::Single(call) => self.handle_call(call).await.map(Response::Single),
+            Request::Batch(calls) => {
+                let mut outputs = Vec::new();
+                for call in calls {
+                    outputs.extend(self.handle_call(call).await);
+                }
+                (!outputs.is_empty()).then_some(Response::Batch(outputs))
+            }
+        }?;
+        serde_json::to_string(&response).ok()
+    }
+
+    /// Handle one call; notifications get no output
+    async fn handle_call(&mut self, call: Call) -> Option<Output> {
+        let Call::MethodCall(MethodCall { method, params, id, .. }) = call else {
+            return None;
+        };
+        let params: Value = params.parse().unwrap_or(Value::Null);
+        let result = match method.as_str() {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "ai-terminal", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                Ok(match self.call_tool(&name, arguments).await {
+                    Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+                    Err(e) => json!({ "content": [{ "type": "text", "text": format!("{:#}", e) }], "isError": true }),
+                })
+            }
+            _ => Err(Error::method_not_found()),
+        };
+
+        Some(match result {
+            Ok(result) => Output::Success(Success { jsonrpc: Some(Version::V2), result, id }),
+            Err(error) => Output::Failure(Failure { jsonrpc: Some(Version::V2), error, id }),
+        })
+    }
+
+    /// Run a tool once the user approves it, returning its text result
+    async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<String> {
+        let summary = match name {
+            "run_command" => {
+                let args: RunCommandArgs = serde_json::from_value(arguments.clone())?;
+                format!("run `{}` in {}", args.command, args.cwd.as_deref().unwrap_or(self.executor.working_dir()))
+            }
+            "read_block_output" => "read the output of a command it ran".to_string(),
+            "list_history" => "list your command history".to_string(),
+            "get_cwd" => "see the current directory".to_string(),
+            _ => anyhow::bail!("unknown tool '{}'", name),
+        };
+        if !self.config.auto_approve.iter().any(|tool| tool == name) && !self.approver.approve(&summary)? {
+            anyhow::bail!("the user declined to {}", summary);
+        }
+
+        match name {
+            "run_command" => self.run_command(serde_json::from_value(arguments)?).await,
+            "read_block_output" => self.read_block_output(serde_json::from_value(arguments)?),
+            "list_history" => self.list_history(serde_json::from_value(arguments)?),
+            _ => Ok(self.executor.working_dir().to_string()),
+        }
+    }
+
+    /// Run a command in a new block and describe the result
+    async fn run_command(&mut self, args: RunCommandArgs) -> Result<String> {
+        let mut executor = self.executor.clone();
+        if let Some(cwd) = args.cwd {
+            executor.set_working_dir(cwd);
+        }
+        let mut block = CommandBlock::new(args.command.clone(), executor.working_dir().to_string());
+        executor.execute_block(&mut block).await?;
+        if let Err(e) = self.history.add_command(args.command) {
+            warn!("Failed to record MCP command in history: {:?}", e);
+        }
+
+        let text = format!(
+            "Block {} exited with {} ({:?})\n{}",
+            self.dropped_blocks + self.blocks.len() + 1,
+            block.exit_code.map_or("no exit code".to_string(), |code| code.to_string()),
+            block.state,
+            self.limit_output(&block.output.text()),
+        );
+        if self.blocks.len() == MAX_BLOCKS {
+            self.blocks.remove(0);
+            self.dropped_blocks += 1;
+        }
+        self.blocks.push(block);
+        Ok(text)
+    }
+
+    /// Get the full output of a block, the latest by default
+    fn read_block_output(&self, args: ReadBlockArgs) -> Result<String> {
+        let block = match args.block {
+            Some(number) => number
+                .checked_sub(self.dropped_blocks + 1)
+                .and_then(|index| self.blocks.get(index))
+                .ok_or_else(|| anyhow::anyhow!("no block {}", number))?,
+            None => self.blocks.last().ok_or_else(|| anyhow::anyhow!("no commands have run yet"))?,
+        };
+        Ok(self.limit_output(&block.full_output()?))
+    }
+
+    /// List recent commands, newest first
+    fn list_history(&self, args: ListHistoryArgs) -> Result<String> {
+        let entries = self.history.search_all(args.query.as_deref().unwrap_or(""), args.limit.unwrap_or(20))?;
+        Ok(entries
+            .iter()
+            .map(|entry| format!("{}  {}", entry.timestamp.format("%Y-%m-%d %H:%M:%S"), entry.command))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Keep the end of `output` within the configured limit
+    fn limit_output(&self, output: &str) -> String {
+        let limit = self.config.max_output_bytes;
+        if output.len() <= limit {
+            return output.to_string();
+        }
+        let mut start = output.len() - limit;
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        format!("[… {} earlier bytes omitted]\n{}", start, &output[start..])
+    }
+}
+
+/// An HTTP request from an SSE client
+struct HttpRequest {
+    method: String,
+    /// Path with its query
+    path: String,
+    /// `Origin` header, sent by browsers
+    origin: Option<String>,
+    body: String,
+}
+
+/// Read one HTTP request from a connection
+async fn read_request(connection: &mut TcpStream) -> Result<HttpRequest> {
+    let mut reader = AsyncBufReader::new(connection);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(path)) = (words.next(), words.next()) else {
+        bail!("malformed request line");
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let (mut length, mut origin) = (0, None);
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            bail!("the connection closed in the headers");
+        }
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().context("invalid Content-Length")?,
+            "origin" => origin = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    ensure!(length <= MAX_BODY_BYTES, "the body is larger than {} bytes", MAX_BODY_BYTES);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(HttpRequest { method, path, origin, body: String::from_utf8(body).context("the body is not UTF-8")? })
+}
+
+/// Answer a request with a status and a plain text body, closing the connection
+async fn respond(connection: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    );
+    connection.write_all(response.as_bytes()).await?;
+    connection.shutdown().await?;
+    Ok(())
+}
+
+/// Check whether an `Origin` header names this machine
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let host = if host.starts_with('[') {
+        host.split_inclusive(']').next().unwrap_or(host)
+    } else {
+        host.split([':', '/']).next().unwrap_or(host)
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Describe the published tools and their input schemas
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "run_command",
+            "description": "Run a shell command in the user's terminal and return its exit code and output",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "The shell command to run" },
+                    "cwd": { "type": "string", "description": "Directory to run it in, instead of the terminal's" }
+                },
+                "required": ["command"]
+            }
+        },
+        {
+            "name": "read_block_output",
+            "description": "Read the full output of a command run earlier, by the block number run_command reported",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "block": { "type": "integer", "description": "Block number; the latest block when left out" }
+                }
+            }
+        },
+        {
+            "name": "list_history",
+            "description": "List recent commands from the user's command history, newest first",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "limit": { "type": "integer", "description": "Most commands to list (default 20)" },
+                    "query": { "type": "string", "description": "Only list commands containing this text" }
+                }
+            }
+        },
+        {
+            "name": "get_cwd",
+            "description": "Get the directory commands run in",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Approves or declines everything, recording what it was asked
+    struct FixedApprover(bool, Vec<String>);
+
+    impl Approver for FixedApprover {
+        fn approve(&mut self, summary: &str) -> Result<bool> {
+            self.1.push(summary.to_string());
+            Ok(self.0)
+        }
+    }
+
+    fn server(approve: bool, dir: &tempfile::TempDir) -> TerminalMcpServer<FixedApprover> {
+        let mut executor = PtyExecutor::new().unwrap();
+        executor.set_working_dir(dir.path().to_string_lossy().into_owned());
+        let history = CommandHistory::with_file(100, dir.path().join("history.db")).unwrap();
+        TerminalMcpServer::new(executor, history, McpServerConfig::default(), FixedApprover(approve, Vec::new()))
+    }
+
+    async fn call(server: &mut TerminalMcpServer<FixedApprover>, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = server.handle_line(&request.to_string()).await.unwrap();
+        serde_json::from_str::<Value>(&response).unwrap()["result"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_tools_run_after_approval() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = server(true, &dir);
+
+        let init = call(&mut server, "initialize", json!({})).await;
+        assert_eq!(init["protocolVersion"], PROTOCOL_VERSION);
+        assert!(server.handle_line(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).await.is_none());
+        let tools = call(&mut server, "tools/list", json!({})).await;
+        assert_eq!(tools["tools"].as_array().unwrap().len(), 4);
+
+        let run = call(&mut server, "tools/call", json!({ "name": "run_command", "arguments": { "command": "echo mcp-hello" } })).await;
+        assert_eq!(run["isError"], false);
+        assert!(run["content"][0]["text"].as_str().unwrap().starts_with("Block 1 exited with 0"));
+        let output = call(&mut server, "tools/call", json!({ "name": "read_block_output", "arguments": { "block": 1 } })).await;
+        assert!(output["content"][0]["text"].as_str().unwrap().contains("mcp-hello"));
+        let history = call(&mut server, "tools/call", json!({ "name": "list_history", "arguments": {} })).await;
+        assert!(history["content"][0]["text"].as_str().unwrap().contains("echo mcp-hello"));
+        assert_eq!(server.approver.1.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_declined_and_unknown_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = server(false, &dir);
+
+        let declined = call(&mut server, "tools/call", json!({ "name": "get_cwd", "arguments": {} })).await;
+        assert_eq!(declined["isError"], true);
+        assert!(declined["content"][0]["text"].as_str().unwrap().contains("declined"));
+
+        // Auto-approved tools do not ask
+        server.config.auto_approve = vec!["get_cwd".to_string()];
+        let cwd = call(&mut server, "tools/call", json!({ "name": "get_cwd", "arguments": {} })).await;
+        assert_eq!(cwd["content"][0]["text"], dir.path().to_string_lossy().as_ref());
+        assert_eq!(server.approver.1.len(), 1);
+
+        let unknown = call(&mut server, "tools/call", json!({ "name": "format_disk", "arguments": {} })).await;
+        assert_eq!(unknown["isError"], true);
+        let response = server.handle_line(r#"{"jsonrpc":"2.0","id":2,"method":"resources/list"}"#).await.unwrap();
+        assert!(response.contains("-32601"));
+    }
+
+    /// Read the data of the next event on an SSE stream, skipping the response head
+    async fn next_event(events: &mut AsyncBufReader<TcpStream>) -> String {
+        let mut data = String::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            events.read_line(&mut line).await.unwrap();
+            if let Some(value) = line.strip_prefix("data: ") {
+                data.push_str(value.trim_end());
+            } else if line.trim().is_empty() && !data.is_empty() {
+                return data;
+            }
+        }
+    }
+
+    /// Post a message, returning the status line and body of the answer
+    async fn post(addr: std::net::SocketAddr, path: &str, origin: &str, body: &str) -> String {
+        let mut connection = TcpStream::connect(addr).await.unwrap();
+        let request = format!("POST {} HTTP/1.1\r\nHost: localhost\r\nOrigin: {}\r\nContent-Length: {}\r\n\r\n{}", path, origin, body.len(), body);
+        connection.write_all(request.as_bytes()).await.unwrap();
+        let mut answer = String::new();
+        connection.read_to_string(&mut answer).await.unwrap();
+        answer
+    }
+
+    #[tokio::test]
+    async fn test_sse_transport() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = server(true, &dir);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut events = TcpStream::connect(addr).await.unwrap();
+            events.write_all(b"GET /sse HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+            let mut events = AsyncBufReader::new(events);
+            let endpoint = next_event(&mut events).await;
+            assert!(endpoint.starts_with("/message?sessionId="));
+
+            let list = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/list" }).to_string();
+            assert!(post(addr, &endpoint, "http://evil.example", &list).await.starts_with("HTTP/1.1 403"));
+            assert!(post(addr, "/message?sessionId=stale", "http://localhost:6274", &list).await.starts_with("HTTP/1.1 404"));
+            assert!(post(addr, &endpoint, "http://localhost:6274", &list).await.starts_with("HTTP/1.1 202"));
+            serde_json::from_str::<Value>(&next_event(&mut events).await).unwrap()
+        });
+        let response = tokio::select! {
+            result = server.serve_sse(listener) => panic!("the server stopped: {:?}", result),
+            response = client => response.unwrap(),
+        };
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["tools"].as_array().unwrap().len(), 4);
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("https://localhost.evil.example"));
+    }
+}
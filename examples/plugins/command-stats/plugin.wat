@@ -0,0 +1,121 @@
+;; Command stats, a sample AI Terminal plugin
+;;
+;; Counts the commands run in the session and how many failed, shows the
+;; counts in the status bar and reports them from its palette command. It
+;; is written in the WebAssembly text format so it needs no toolchain; a
+;; plugin compiled from Rust or another language to `wasm32-unknown-unknown`
+;; exports the same three things:
+;;
+;; - `memory`
+;; - `alloc(len: i32) -> i32`, returning where the terminal may write an event of `len` bytes
+;; - `handle(ptr: i32, len: i32) -> i64`, reading the JSON event written there and
+;;   returning the pointer of its JSON response in the high 32 bits and its length
+;;   in the low 32 bits, or 0 for no response
+;;
+;; A real plugin would parse the event; this one only looks for text in it.
+
+(module
+  (memory (export "memory") 1)
+
+  ;; Commands finished and how many of them failed
+  (global $runs (mut i32) (i32.const 0))
+  (global $failed (mut i32) (i32.const 0))
+
+  ;; End of the response being written, which starts at 256
+  (global $out (mut i32) (i32.const 256))
+
+  ;; Text looked for in events and written into responses, below 256
+  (data (i32.const 0) "\"event\":\"block_complete\"")
+  (data (i32.const 24) "\"exit_code\":0,")
+  (data (i32.const 38) "\"event\":\"load\"")
+  (data (i32.const 52) "\"event\":\"palette_command\"")
+  (data (i32.const 77) "{\"status\":\"▣ ")
+  (data (i32.const 92) " run, ")
+  (data (i32.const 98) " failed\"}")
+  (data (i32.const 107) "{\"message\":\"")
+  (data (i32.const 119) " command(s) ran in this session and ")
+  (data (i32.const 155) " failed.\"}")
+
+  ;; Events are written from 4096, growing the memory to fit
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $pages i32)
+    (local.set $pages
+      (i32.add (i32.shr_u (i32.add (local.get $len) (i32.const 4096)) (i32.const 16)) (i32.const 1)))
+    (if (i32.gt_u (local.get $pages) (memory.size))
+      (then (drop (memory.grow (i32.sub (local.get $pages) (memory.size))))))
+    (i32.const 4096))
+
+  ;; Whether the bytes at $needle occur in those at $hay
+  (func $contains (param $hay i32) (param $hay_len i32) (param $needle i32) (param $needle_len i32) (result i32)
+    (local $i i32)
+    (local $j i32)
+    (block $done
+      (loop $outer
+        (br_if $done (i32.gt_u (i32.add (local.get $i) (local.get $needle_len)) (local.get $hay_len)))
+        (local.set $j (i32.const 0))
+        (block $mismatch
+          (loop $inner
+            (if (i32.eq (local.get $j) (local.get $needle_len))
+              (then (return (i32.const 1))))
+            (br_if $mismatch
+              (i32.ne
+                (i32.load8_u (i32.add (local.get $hay) (i32.add (local.get $i) (local.get $j))))
+                (i32.load8_u (i32.add (local.get $needle) (local.get $j)))))
+            (local.set $j (i32.add (local.get $j) (i32.const 1)))
+            (br $inner)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $outer)))
+    (i32.const 0))
+
+  ;; Add bytes to the response
+  (func $append (param $src i32) (param $len i32)
+    (memory.copy (global.get $out) (local.get $src) (local.get $len))
+    (global.set $out (i32.add (global.get $out) (local.get $len))))
+
+  ;; Add a number to the response in decimal, its digits first written backwards below 1034
+  (func $append_number (param $n i32)
+    (local $i i32)
+    (local.set $i (i32.const 1034))
+    (loop $digit
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (i32.store8 (local.get $i) (i32.add (i32.const 48) (i32.rem_u (local.get $n) (i32.const 10))))
+      (local.set $n (i32.div_u (local.get $n) (i32.const 10)))
+      (br_if $digit (i32.ne (local.get $n) (i32.const 0))))
+    (call $append (local.get $i) (i32.sub (i32.const 1034) (local.get $i))))
+
+  ;; Pack the pointer and length of the response
+  (func $response (result i64)
+    (i64.or
+      (i64.shl (i64.const 256) (i64.const 32))
+      (i64.extend_i32_u (i32.sub (global.get $out) (i32.const 256)))))
+
+  ;; Respond with the counts as the status bar segment
+  (func $status (result i64)
+    (global.set $out (i32.const 256))
+    (call $append (i32.const 77) (i32.const 15))
+    (call $append_number (global.get $runs))
+    (call $append (i32.const 92) (i32.const 6))
+    (call $append_number (global.get $failed))
+    (call $append (i32.const 98) (i32.const 9))
+    (call $response))
+
+  (func (export "handle") (param $ptr i32) (param $len i32) (result i64)
+    (if (call $contains (local.get $ptr) (local.get $len) (i32.const 0) (i32.const 24))
+      (then
+        (global.set $runs (i32.add (global.get $runs) (i32.const 1)))
+        (if (i32.eqz (call $contains (local.get $ptr) (local.get $len) (i32.const 24) (i32.const 14)))
+          (then (global.set $failed (i32.add (global.get $failed) (i32.const 1)))))
+        (return (call $status))))
+    (if (call $contains (local.get $ptr) (local.get $len) (i32.const 38) (i32.const 14))
+      (then (return (call $status))))
+    (if (call $contains (local.get $ptr) (local.get $len) (i32.const 52) (i32.const 25))
+      (then
+        (global.set $out (i32.const 256))
+        (call $append (i32.const 107) (i32.const 12))
+        (call $append_number (global.get $runs))
+        (call $append (i32.const 119) (i32.const 36))
+        (call $append_number (global.get $failed))
+        (call $append (i32.const 155) (i32.const 10))
+        (return (call $response))))
+    (i64.const 0))
+)
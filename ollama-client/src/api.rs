@@ -4,12 +4,12 @@
 //! It handles sending requests, managing streaming responses, and maintaining
 //! conversation history.
 
-use crate::{error::OllamaError, models::{OllamaRequest, OllamaResponse}, history::ConversationHistory};
+use crate::{error::OllamaError, models::{ModelInfo, ModelList, ModelShow, ServerVersion, OllamaChatRequest, OllamaChatResponse, OllamaRequest, OllamaResponse, PullProgress}, history::ConversationHistory};
 use reqwest::{Client, Response};
-use serde_json::Value;
+use serde::de::DeserializeOwned;
 use std::env;
 use tracing::{info, error};
-use futures_util::StreamExt;
+use futures_util::{Stream, StreamExt};
 
 /// The default base URL for the Ollama API
 const DEFAULT_BASE_URL: &str = "http://localhost:11434/api";
@@ -54,6 +54,16 @@ impl OllamaClient {
         Ok(client)
     }
     
+    /// Get the HTTP client used for requests
+    pub(crate) fn http_client(&self) -> &Client {
+        &self.http_client
+    }
+    
+    /// Replace the HTTP client, such as with one carrying TLS and authentication settings
+    pub fn set_http_client(&mut self, http_client: Client) {
+        self.http_client = http_client;
+    }
+    
     /// Send a request to the Ollama API
     pub async fn send_request(&self, request: OllamaRequest) -> Result<Response, OllamaError> {
         let url = format!("{}/generate", self.base_url);
@@ -68,6 +78,22 @@ impl OllamaClient {
         Ok(response)
     }
     
+    /// Send a non-streaming request and return the complete response
+    pub async fn generate(&self, mut request: OllamaRequest) -> Result<OllamaResponse, OllamaError> {
+        request.stream = Some(false);
+        let response = check_status(self.send_request(request).await?).await?;
+        Ok(response.json::<OllamaResponse>().await?)
+    }
+    
+    /// Send a conversation to `/api/chat` and return the complete reply
+    pub async fn chat(&self, mut request: OllamaChatRequest) -> Result<OllamaChatResponse, OllamaError> {
+        request.stream = false;
+        let url = format!("{}/chat", self.base_url);
+        info!("Sending request to: {}", url);
+        let response = self.http_client.post(&url).json(&request).send().await?;
+        Ok(check_status(response).await?.json::<OllamaChatResponse>().await?)
+    }
+    
     /// Send a request to the Ollama API and stream the response
     pub async fn stream_request(&self, request: OllamaRequest) -> Result<impl StreamExt<Item = Result<OllamaResponse, OllamaError>>, OllamaError> {
         let response = self.send_request(request).await?;
@@ -75,7 +101,7 @@ impl OllamaClient {
         // Check if the response is successful
         if !response.status().is_success() {
             return Err(OllamaError::RequestFailed(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
         
@@ -96,11 +122,129 @@ impl OllamaClient {
             
         Ok(stream)
     }
+    
+    /// List the models installed in Ollama
+    pub async fn get_models(&self) -> Result<Vec<ModelInfo>, OllamaError> {
+        let url = format!("{}/tags", self.base_url);
+        let response = check_status(self.http_client.get(&url).send().await?).await?;
+        Ok(response.json::<ModelList>().await?.models)
+    }
+    
+    /// Get the version of the Ollama server
+    pub async fn version(&self) -> Result<String, OllamaError> {
+        let url = format!("{}/version", self.base_url);
+        let response = check_status(self.http_client.get(&url).send().await?).await?;
+        Ok(response.json::<ServerVersion>().await?.version)
+    }
+    
+    /// Get the details of an installed model
+    pub async fn show_model(&self, name: &str) -> Result<ModelShow, OllamaError> {
+        let url = format!("{}/show", self.base_url);
+        let response = self.http_client
+            .post(&url)
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?;
+        Ok(check_status(response).await?.json::<ModelShow>().await?)
+    }
+    
+    /// Delete an installed model
+    pub async fn delete_model(&self, name: &str) -> Result<(), OllamaError> {
+        let url = format!("{}/delete", self.base_url);
+        info!("Deleting model {}", name);
+        let response = self.http_client
+            .delete(&url)
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?;
+        check_status(response).await?;
+        Ok(())
+    }
+    
+    /// Pull a model from the registry, streaming download progress
+    ///
+    /// The stream ends after an update whose status is `success`; errors
+    /// Ollama reports mid-download are yielded as `Err`.
+    pub async fn pull_model(&self, name: &str) -> Result<impl Stream<Item = Result<PullProgress, OllamaError>> + Send + 'static, OllamaError> {
+        let url = format!("{}/pull", self.base_url);
+        info!("Pulling model {}", name);
+        let response = self.http_client
+            .post(&url)
+            .json(&serde_json::json!({ "model": name, "stream": true }))
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+        
+        Ok(ndjson_stream::<PullProgress>(response).map(|progress| {
+            let mut progress = progress?;
+            match progress.error.take() {
+                Some(message) => Err(OllamaError::InvalidResponse(message)),
+                None => Ok(progress),
+            }
+        }))
+    }
+}
+
+/// Turn an unsuccessful response into an error carrying the server's message
+///
+/// Ollama reports `{"error": "..."}`; OpenAI-compatible servers report
+/// `{"error": {"message": "..."}}`.
+pub(crate) async fn check_status(response: Response) -> Result<Response, OllamaError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| {
+            let error = value.get("error")?;
+            error.as_str().or_else(|| error.get("message")?.as_str()).map(str::to_string)
+        })
+        .unwrap_or(body);
+    error!("Server returned status {}: {}", status, message);
+    Err(OllamaError::Status { status: status.as_u16(), message })
+}
+
+/// Parse a response body of newline-delimited JSON objects
+pub(crate) fn ndjson_stream<T: DeserializeOwned>(response: Response) -> impl Stream<Item = Result<T, OllamaError>> {
+    line_stream(response).map(|line| serde_json::from_slice::<T>(&line?).map_err(OllamaError::from))
+}
+
+/// Split a response body into its non-blank lines
+///
+/// Chunks from the network do not line up with lines, so bytes are
+/// buffered until a full line has arrived.
+pub(crate) fn line_stream(response: Response) -> impl Stream<Item = Result<Vec<u8>, OllamaError>> {
+    async_stream::stream! {
+        let mut bytes = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = bytes.next().await {
+            match chunk {
+                Ok(chunk) => buffer.extend_from_slice(&chunk),
+                Err(e) => {
+                    yield Err(OllamaError::RequestFailed(e));
+                    return;
+                }
+            }
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if !line.trim_ascii().is_empty() {
+                    yield Ok(line.trim_ascii().to_vec());
+                }
+            }
+        }
+        if !buffer.trim_ascii().is_empty() {
+            yield Ok(buffer.trim_ascii().to_vec());
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_json, method, path}};
     
     #[tokio::test]
     async fn test_ollama_client_with_config() {
@@ -109,4 +253,105 @@ mod tests {
         let client = OllamaClient::with_model("llama3".to_string());
         assert!(client.is_ok());
     }
+    
+    /// Create a client talking to a mock server
+    fn mock_client(server: &MockServer) -> OllamaClient {
+        let mut client = OllamaClient::new().unwrap();
+        client.base_url = format!("{}/api", server.uri());
+        client
+    }
+    
+    #[tokio::test]
+    async fn test_get_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"models":[{"name":"llama3:latest","modified_at":"2024-05-01T10:00:00Z","size":4661224676,"digest":"365c","details":{"format":"gguf","family":"llama","parameter_size":"8.0B","quantization_level":"Q4_0"}}]}"#
+            ))
+            .mount(&server)
+            .await;
+        
+        let models = mock_client(&server).get_models().await.unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "llama3:latest");
+        assert_eq!(models[0].size, 4661224676);
+        assert_eq!(models[0].details.parameter_size, "8.0B");
+    }
+    
+    #[tokio::test]
+    async fn test_delete_model_reports_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/delete"))
+            .and(body_json(serde_json::json!({ "model": "llama3" })))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/delete"))
+            .and(body_json(serde_json::json!({ "model": "missing" })))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"model 'missing' not found"}"#))
+            .mount(&server)
+            .await;
+        
+        let client = mock_client(&server);
+        assert!(client.delete_model("llama3").await.is_ok());
+        let error = client.delete_model("missing").await.unwrap_err();
+        assert!(error.to_string().contains("model 'missing' not found"));
+    }
+    
+    #[tokio::test]
+    async fn test_pull_model_streams_progress() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+                "{\"status\":\"pulling manifest\"}\n",
+                "{\"status\":\"pulling 6a07\",\"digest\":\"sha256:6a07\",\"total\":100,\"completed\":40}\n",
+                "{\"status\":\"success\"}\n",
+            )))
+            .mount(&server)
+            .await;
+        
+        let stream = mock_client(&server).pull_model("llama3").await.unwrap();
+        let updates: Vec<PullProgress> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[1].fraction(), Some(0.4));
+        assert!(updates[2].is_success());
+    }
+    
+    #[tokio::test]
+    async fn test_pull_model_yields_stream_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "{\"status\":\"pulling manifest\"}\n{\"error\":\"pull model manifest: file does not exist\"}\n"
+            ))
+            .mount(&server)
+            .await;
+        
+        let stream = mock_client(&server).pull_model("nope").await.unwrap();
+        let updates: Vec<Result<PullProgress, OllamaError>> = stream.collect().await;
+        assert!(updates[0].is_ok());
+        assert!(matches!(&updates[1], Err(OllamaError::InvalidResponse(message)) if message.contains("file does not exist")));
+    }
+    
+    #[tokio::test]
+    async fn test_show_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"license":"MIT","parameters":"stop \"<|eot_id|>\"","template":"{{ .Prompt }}","details":{"family":"llama","parameter_size":"8.0B"}}"#
+            ))
+            .mount(&server)
+            .await;
+        
+        let details = mock_client(&server).show_model("llama3").await.unwrap();
+        assert_eq!(details.license.as_deref(), Some("MIT"));
+        assert_eq!(details.details.family, "llama");
+        assert!(details.modelfile.is_none());
+    }
 }
\ No newline at end of file
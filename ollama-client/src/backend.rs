@@ -0,0 +1,434 @@
+//! # Chat Backends
+//!
+//! The terminal talks to language models through the [`ChatBackend`] trait so
+//! that servers other than Ollama can be used. Two implementations exist:
+//! [`OllamaClient`] for Ollama's native API and [`OpenAiClient`] for any
+//! server speaking the OpenAI chat completions API (llama.cpp server, vLLM,
+//! LM Studio and others). [`BackendConfig`] selects one from configuration
+//! and wraps it in a [`ResilientBackend`] for retries and model fallback.
+//! Named [`BackendProfile`]s keep the settings of other servers, such as a
+//! shared GPU box reached over HTTPS, ready to be selected with `profile`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{check_status, ndjson_stream, OllamaClient};
+use crate::connection::ConnectionConfig;
+use crate::error::OllamaError;
+use crate::models::{ChatMessage, FunctionCall, OllamaChatRequest, OllamaRequest, OllamaResponse, TokenUsage, ToolDefinition};
+use crate::openai::OpenAiClient;
+use crate::resilience::{ResilientBackend, RetryPolicy};
+
+/// A single prompt for a chat backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatRequest {
+    /// The model to use
+    pub model: String,
+
+    /// The user's prompt
+    pub prompt: String,
+
+    /// Optional system message to guide the model
+    pub system: Option<String>,
+
+    /// Functions the model may call, on backends that support tool calling
+    pub tools: Vec<ToolDefinition>,
+
+    /// Messages following the prompt, such as function calls and their results
+    pub turns: Vec<ChatMessage>,
+}
+
+impl ChatRequest {
+    /// Create a request without a system message
+    pub fn new(model: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            prompt: prompt.into(),
+            system: None,
+            tools: Vec::new(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// Add a system message to the request
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+}
+
+/// A complete response from a chat backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatResponse {
+    /// The model that answered
+    pub model: String,
+
+    /// The generated text
+    pub text: String,
+
+    /// Token counts and timings, if the server reports them
+    pub usage: Option<TokenUsage>,
+
+    /// How many times the request was retried after transient failures
+    pub retries: u32,
+
+    /// Set when the requested model could not answer and another one did
+    pub fallback: Option<Fallback>,
+
+    /// Functions the model called instead of, or besides, answering
+    pub tool_calls: Vec<FunctionCall>,
+}
+
+impl ChatResponse {
+    /// Create the response of `model`, answered on the first attempt
+    pub fn new(model: impl Into<String>, text: impl Into<String>, usage: Option<TokenUsage>) -> Self {
+        Self {
+            model: model.into(),
+            text: text.into(),
+            usage,
+            retries: 0,
+            fallback: None,
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+/// Why a response came from the fallback model
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fallback {
+    /// The model that was asked first
+    pub primary: String,
+
+    /// The error the requested model failed with
+    pub reason: String,
+}
+
+/// A server that generates text and embeddings
+#[async_trait]
+pub trait ChatBackend: Send + Sync + std::fmt::Debug {
+    /// Name of the backend, for display
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend passes `tools` to the model and reports its calls
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    /// Generate a complete response
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse, OllamaError>;
+
+    /// Generate a response as a stream of text fragments
+    async fn stream(&self, request: &ChatRequest) -> Result<BoxStream<'static, Result<String, OllamaError>>, OllamaError>;
+
+    /// List the names of the models the server offers
+    async fn list_models(&self) -> Result<Vec<String>, OllamaError>;
+
+    /// Compute an embedding for each input text
+    async fn embeddings(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, OllamaError>;
+}
+
+/// Response of Ollama's `/api/embed`
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddings {
+    /// One embedding per input
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl From<&ChatRequest> for OllamaChatRequest {
+    fn from(request: &ChatRequest) -> Self {
+        let mut messages: Vec<ChatMessage> = request.system
+            .iter()
+            .map(|system| ChatMessage::new("system", system.clone()))
+            .collect();
+        messages.push(ChatMessage::new("user", request.prompt.clone()));
+        messages.extend(request.turns.iter().cloned());
+        Self {
+            model: request.model.clone(),
+            messages,
+            tools: request.tools.clone(),
+            stream: false,
+        }
+    }
+}
+
+impl From<&ChatRequest> for OllamaRequest {
+    fn from(request: &ChatRequest) -> Self {
+        match &request.system {
+            Some(system) => OllamaRequest::with_system(request.model.clone(), request.prompt.clone(), system.clone()),
+            None => OllamaRequest::new(request.model.clone(), request.prompt.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaClient {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse, OllamaError> {
+        // Tool calls need the chat endpoint; plain prompts keep using generate
+        if !request.tools.is_empty() || !request.turns.is_empty() {
+            let response = self.chat(request.into()).await?;
+            let usage = response.usage();
+            let message = response.message;
+            let mut chat = ChatResponse::new(request.model.clone(), message.content, usage);
+            chat.tool_calls = message.tool_calls.into_iter().map(|entry| entry.function).collect();
+            return Ok(chat);
+        }
+        let response = OllamaClient::generate(self, request.into()).await?;
+        let usage = response.usage();
+        Ok(ChatResponse::new(request.model.clone(), response.response, usage))
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<BoxStream<'static, Result<String, OllamaError>>, OllamaError> {
+        let mut request = OllamaRequest::from(request);
+        request.stream = Some(true);
+        let response = check_status(self.send_request(request).await?).await?;
+        Ok(ndjson_stream::<OllamaResponse>(response)
+            .map(|chunk| chunk.map(|chunk| chunk.response))
+            .boxed())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, OllamaError> {
+        Ok(self.get_models().await?.into_iter().map(|model| model.name).collect())
+    }
+
+    async fn embeddings(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, OllamaError> {
+        let response = self.http_client()
+            .post(format!("{}/embed", self.base_url))
+            .json(&serde_json::json!({ "model": model, "input": input }))
+            .send()
+            .await?;
+        Ok(check_status(response).await?.json::<OllamaEmbeddings>().await?.embeddings)
+    }
+}
+
+/// Which kind of server to talk to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// Ollama's native API
+    #[default]
+    Ollama,
+    /// An OpenAI-compatible chat completions API
+    #[serde(alias = "openai_compatible")]
+    Openai,
+}
+
+/// Configuration selecting the chat backend
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    /// Which kind of server to talk to
+    pub kind: BackendKind,
+
+    /// Base URL of the API, such as `http://localhost:8080/v1`; defaults per kind
+    pub base_url: Option<String>,
+
+    /// Environment variable holding the API key, for servers that need one
+    pub api_key_env: Option<String>,
+
+    /// Model to ask when the requested one is missing, fails to load or times out
+    pub fallback_model: Option<String>,
+
+    /// Seconds to wait for a response before trying the fallback model
+    pub timeout_secs: Option<u64>,
+
+    /// Retries after transient failures such as a server restarting
+    pub retry: RetryPolicy,
+
+    /// TLS, authentication and proxy settings for the server
+    pub connection: ConnectionConfig,
+
+    /// Name of the profile to use instead of the server settings above
+    pub profile: Option<String>,
+
+    /// Settings of other servers, by name
+    pub profiles: BTreeMap<String, BackendProfile>,
+}
+
+/// Settings of a server kept under a name, such as a team's shared GPU box
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackendProfile {
+    /// Which kind of server it is
+    pub kind: BackendKind,
+
+    /// Base URL of the API; defaults per kind
+    pub base_url: Option<String>,
+
+    /// Environment variable holding the API key, for servers that need one
+    pub api_key_env: Option<String>,
+
+    /// TLS, authentication and proxy settings for the server
+    pub connection: ConnectionConfig,
+}
+
+impl BackendConfig {
+    /// Apply the selected profile, giving the settings of the server to use
+    pub fn resolve(&self) -> Result<BackendConfig, OllamaError> {
+        let mut config = self.clone();
+        if let Some(name) = config.profile.take() {
+            let profile = self.profiles.get(&name)
+                .ok_or_else(|| OllamaError::Connection(format!("unknown backend profile '{}'", name)))?;
+            config.kind = profile.kind;
+            config.base_url = profile.base_url.clone();
+            config.api_key_env = profile.api_key_env.clone();
+            config.connection = profile.connection.clone();
+        }
+        Ok(config)
+    }
+
+    /// Create the configured backend, with retries and fallback
+    pub fn build(&self) -> Result<Arc<dyn ChatBackend>, OllamaError> {
+        let config = self.resolve()?;
+        let mut backend = ResilientBackend::new(config.build_client()?, config.retry.clone());
+        if let Some(model) = &config.fallback_model {
+            backend = backend.with_fallback_model(model.clone());
+        }
+        if let Some(secs) = config.timeout_secs {
+            backend = backend.with_timeout(Duration::from_secs(secs));
+        }
+        Ok(Arc::new(backend))
+    }
+
+    /// Create a client for the configured server speaking Ollama's API, as model management needs
+    pub fn ollama_client(&self) -> Result<OllamaClient, OllamaError> {
+        let config = self.resolve()?;
+        let mut client = OllamaClient::new()?;
+        client.set_http_client(config.connection.build_client()?);
+        if let Some(base_url) = &config.base_url {
+            client.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        Ok(client)
+    }
+
+    /// Create the client for the configured kind of server
+    fn build_client(&self) -> Result<Arc<dyn ChatBackend>, OllamaError> {
+        match self.kind {
+            BackendKind::Ollama => Ok(Arc::new(self.ollama_client()?)),
+            BackendKind::Openai => {
+                let api_key = match &self.api_key_env {
+                    Some(var) => Some(std::env::var(var).map_err(|_| OllamaError::MissingEnvVar(var.clone()))?),
+                    None => None,
+                };
+                let base_url = self.base_url.as_deref().unwrap_or(OpenAiClient::DEFAULT_BASE_URL);
+                let client = OpenAiClient::new(base_url, api_key).with_http_client(self.connection.build_client()?);
+                Ok(Arc::new(client))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_partial_json, method, path}};
+
+    #[test]
+    fn test_backend_config_builds() {
+        let config: BackendConfig = serde_json::from_str(r#"{"kind":"openai","base_url":"http://localhost:1234/v1"}"#).unwrap();
+        assert_eq!(config.kind, BackendKind::Openai);
+        assert_eq!(config.build().unwrap().name(), "OpenAI-compatible");
+
+        assert_eq!(BackendConfig::default().build().unwrap().name(), "Ollama");
+
+        let missing_key = BackendConfig {
+            kind: BackendKind::Openai,
+            api_key_env: Some("AI_TERMINAL_TEST_UNSET_KEY".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(missing_key.build(), Err(OllamaError::MissingEnvVar(_))));
+    }
+
+    #[test]
+    fn test_profile_replaces_server_settings() {
+        let config: BackendConfig = serde_json::from_str(r#"{
+            "profile": "gpu",
+            "profiles": {"gpu": {"kind": "openai", "base_url": "https://gpu.example.com/v1", "connection": {"basic_user": "team"}}}
+        }"#).unwrap();
+        let resolved = config.resolve().unwrap();
+        assert_eq!(resolved.kind, BackendKind::Openai);
+        assert_eq!(resolved.base_url.as_deref(), Some("https://gpu.example.com/v1"));
+        assert_eq!(resolved.connection.basic_user.as_deref(), Some("team"));
+        assert_eq!(config.build().unwrap().name(), "OpenAI-compatible");
+
+        let unknown = BackendConfig { profile: Some("cpu".to_string()), ..config };
+        assert!(matches!(unknown.build(), Err(OllamaError::Connection(_))));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_stream_and_embeddings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(serde_json::json!({ "stream": true, "system": "be brief" })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+                "{\"model\":\"llama3\",\"created_at\":\"\",\"response\":\"Hel\",\"done\":false}\n",
+                "{\"model\":\"llama3\",\"created_at\":\"\",\"response\":\"lo\",\"done\":true}\n",
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"embeddings":[[0.5,1.0]]}"#))
+            .mount(&server)
+            .await;
+
+        let config = BackendConfig { base_url: Some(format!("{}/api", server.uri())), ..Default::default() };
+        let backend = config.build().unwrap();
+        let request = ChatRequest::new("llama3", "hi").with_system("be brief");
+        let chunks: Vec<String> = backend.stream(&request).await.unwrap().map(Result::unwrap).collect().await;
+        assert_eq!(chunks.concat(), "Hello");
+
+        let embeddings = backend.embeddings("llama3", &["hi".to_string()]).await.unwrap();
+        assert_eq!(embeddings, vec![vec![0.5, 1.0]]);
+    }
+
+    #[tokio::test]
+    async fn test_ollama_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(serde_json::json!({
+                "stream": false,
+                "tools": [{"type": "function", "function": {"name": "files.read"}}],
+                "messages": [{"role": "user", "content": "read a.txt"}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+                r#"{"model":"llama3","done":true,"eval_count":3,"message":{"role":"assistant","content":"","#,
+                r#""tool_calls":[{"function":{"name":"files.read","arguments":{"path":"a.txt"}}}]}}"#,
+            )))
+            .mount(&server)
+            .await;
+
+        let config = BackendConfig { base_url: Some(format!("{}/api", server.uri())), ..Default::default() };
+        let backend = config.build().unwrap();
+        assert!(backend.supports_tools());
+        let mut request = ChatRequest::new("llama3", "read a.txt");
+        request.tools = vec![ToolDefinition::function("files.read", "Read a file", serde_json::json!({"type": "object"}))];
+        let response = backend.generate(&request).await.unwrap();
+        assert_eq!(response.tool_calls, vec![FunctionCall {
+            name: "files.read".to_string(),
+            arguments: serde_json::json!({"path": "a.txt"}),
+        }]);
+        assert_eq!(response.usage.map(|usage| usage.completion_tokens), Some(3));
+
+        // The result goes back after the assistant's call
+        request.turns = vec![ChatMessage::assistant("", &response.tool_calls), ChatMessage::tool("hello")];
+        let chat = OllamaChatRequest::from(&request);
+        assert_eq!(chat.messages.len(), 3);
+        assert_eq!(serde_json::to_value(&chat.messages[1]).unwrap()["tool_calls"][0]["function"]["name"], "files.read");
+    }
+}
@@ -1 +1,70 @@
-//! # Ollama MCP Server Binary\n//!\n//! This binary runs an MCP server that exposes Ollama functionality as MCP tools.\n\nuse anyhow::Result;\nuse tokio::io::{stdin, stdout, AsyncBufReadExt, AsyncWriteExt, BufReader};\nuse tracing::{info, error};\n\nuse ollama_client::mcp::OllamaMcpServer;\n\n#[tokio::main]\nasync fn main() -> Result<()> {\n    // Initialize logging\n    tracing_subscriber::fmt::init();\n    \n    info!(\"Starting Ollama MCP Server...\");\n    \n    // Create the MCP server\n    let server = OllamaMcpServer::new()?;\n    let io_handler = server.io_handler();\n    \n    // Set up stdin/stdout for JSON-RPC communication\n    let stdin = stdin();\n    let mut stdout = stdout();\n    let mut reader = BufReader::new(stdin);\n    let mut buffer = String::new();\n    \n    info!(\"Ollama MCP Server started and ready to accept requests\");\n    \n    // Main loop for processing JSON-RPC requests\n    loop {\n        buffer.clear();\n        \n        // Read a line from stdin\n        match reader.read_line(&mut buffer).await {\n            Ok(0) => {\n                // EOF reached, exit the loop\n                break;\n            }\n            Ok(_) => {\n                // Process the JSON-RPC request\n                if let Ok(request) = serde_json::from_str(&buffer) {\n                    // Handle the request using the io_handler\n                    if let Some(response) = io_handler.handle_request(&request).await {\n                        // Write the response to stdout\n                        if let Err(e) = stdout.write_all(response.as_bytes()).await {\n                            error!(\"Error writing response to stdout: {}\", e);\n                        }\n                        if let Err(e) = stdout.write_all(b\"\\n\").await {\n                            error!(\"Error writing newline to stdout: {}\", e);\n                        }\n                        if let Err(e) = stdout.flush().await {\n                            error!(\"Error flushing stdout: {}\", e);\n                        }\n                    }\n                } else {\n                    error!(\"Failed to parse JSON-RPC request: {}\", buffer);\n                }\n            }\n            Err(e) => {\n                error!(\"Error reading from stdin: {}\", e);\n                break;\n            }\n        }\n    }\n    \n    info!(\"Ollama MCP Server shutting down\");\n    Ok(())\n}
\ No newline at end of file
+//! # Ollama MCP Server Binary
+//!
+//! This binary runs an MCP server that exposes Ollama functionality as MCP tools.
+
+use anyhow::Result;
+use tokio::io::{stdin, stdout, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{info, error};
+
+use ollama_client::mcp::OllamaMcpServer;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    tracing_subscriber::fmt::init();
+    
+    info!("Starting Ollama MCP Server...");
+    
+    // Create the MCP server
+    let server = OllamaMcpServer::new()?;
+    let io_handler = server.io_handler();
+    
+    // Set up stdin/stdout for JSON-RPC communication
+    let stdin = stdin();
+    let mut stdout = stdout();
+    let mut reader = BufReader::new(stdin);
+    let mut buffer = String::new();
+    
+    info!("Ollama MCP Server started and ready to accept requests");
+    
+    // Main loop for processing JSON-RPC requests
+    loop {
+        buffer.clear();
+        
+        // Read a line from stdin
+        match reader.read_line(&mut buffer).await {
+            Ok(0) => {
+                // EOF reached, exit the loop
+                break;
+            }
+            Ok(_) => {
+                // Process the JSON-RPC request
+                let request = buffer.trim();
+                if request.is_empty() {
+                    continue;
+                }
+                
+                // Handle the request using the io_handler
+                if let Some(response) = io_handler.handle_request(request).await {
+                    // Write the response to stdout
+                    if let Err(e) = stdout.write_all(response.as_bytes()).await {
+                        error!("Error writing response to stdout: {}", e);
+                    }
+                    if let Err(e) = stdout.write_all(b"\n").await {
+                        error!("Error writing newline to stdout: {}", e);
+                    }
+                    if let Err(e) = stdout.flush().await {
+                        error!("Error flushing stdout: {}", e);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Error reading from stdin: {}", e);
+                break;
+            }
+        }
+    }
+    
+    info!("Ollama MCP Server shutting down");
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! # Connection Settings
+//!
+//! Servers other than a local Ollama, such as a GPU box shared by a team,
+//! are often reached over HTTPS behind authentication. [`ConnectionConfig`]
+//! holds how to reach one: extra CA certificates to trust, a bearer token or
+//! basic authentication, custom headers, a proxy and a connect timeout. It
+//! builds the HTTP client used by [`OllamaClient`](crate::OllamaClient) and
+//! [`OpenAiClient`](crate::OpenAiClient). Secrets are read from environment
+//! variables so they stay out of the configuration file.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use base64::Engine as _;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, Client, Proxy};
+use serde::{Deserialize, Serialize};
+
+use crate::error::OllamaError;
+
+/// Value of `proxy` that ignores proxies from the environment
+const NO_PROXY: &str = "none";
+
+/// How to reach a server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConnectionConfig {
+    /// PEM file of CA certificates to trust besides the system ones
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Environment variable holding a token sent as `Authorization: Bearer`
+    pub bearer_token_env: Option<String>,
+
+    /// User name for HTTP basic authentication
+    pub basic_user: Option<String>,
+
+    /// Environment variable holding the basic authentication password
+    pub basic_password_env: Option<String>,
+
+    /// Headers sent with every request
+    pub headers: BTreeMap<String, String>,
+
+    /// Proxy URL for this server, or `"none"` to connect directly
+    pub proxy: Option<String>,
+
+    /// Seconds to wait for a connection to open
+    pub connect_timeout_secs: Option<u64>,
+}
+
+impl ConnectionConfig {
+    /// Build an HTTP client with these settings
+    pub fn build_client(&self) -> Result<Client, OllamaError> {
+        let mut builder = Client::builder().default_headers(self.headers()?);
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(path)
+                .map_err(|e| OllamaError::Connection(format!("could not read CA bundle {}: {}", path.display(), e)))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| OllamaError::Connection(format!("invalid CA bundle {}: {}", path.display(), e)))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        match self.proxy.as_deref() {
+            Some(NO_PROXY) => builder = builder.no_proxy(),
+            Some(url) => {
+                let proxy = Proxy::all(url).map_err(|e| OllamaError::Connection(format!("invalid proxy {}: {}", url, e)))?;
+                builder = builder.proxy(proxy);
+            }
+            None => {}
+        }
+        if let Some(secs) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Build the headers sent with every request, including authentication
+    fn headers(&self) -> Result<HeaderMap, OllamaError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| OllamaError::Connection(format!("invalid header name '{}'", name)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| OllamaError::Connection(format!("invalid value for header '{}'", name)))?;
+            headers.insert(name, value);
+        }
+
+        let authorization = match (&self.bearer_token_env, &self.basic_user) {
+            (Some(_), Some(_)) => {
+                return Err(OllamaError::Connection("set either bearer_token_env or basic_user, not both".to_string()));
+            }
+            (Some(var), None) => Some(format!("Bearer {}", read_env(var)?)),
+            (None, Some(user)) => {
+                let password = self.basic_password_env.as_deref().map(read_env).transpose()?.unwrap_or_default();
+                let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+                Some(format!("Basic {}", credentials))
+            }
+            (None, None) => None,
+        };
+        if let Some(authorization) = authorization {
+            let mut value = HeaderValue::from_str(&authorization)
+                .map_err(|_| OllamaError::Connection("credentials contain characters not allowed in a header".to_string()))?;
+            // Keep credentials out of debug output
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+}
+
+/// Read a secret from the environment
+fn read_env(var: &str) -> Result<String, OllamaError> {
+    std::env::var(var).map_err(|_| OllamaError::MissingEnvVar(var.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{header, method, path}};
+
+    #[tokio::test]
+    async fn test_client_sends_configured_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .and(header("authorization", "Basic dGVhbTo="))
+            .and(header("x-team", "ml"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let config = ConnectionConfig {
+            basic_user: Some("team".to_string()),
+            headers: BTreeMap::from([("X-Team".to_string(), "ml".to_string())]),
+            proxy: Some(NO_PROXY.to_string()),
+            connect_timeout_secs: Some(5),
+            ..Default::default()
+        };
+        let response = config.build_client().unwrap().get(format!("{}/api/tags", server.uri())).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[test]
+    fn test_invalid_settings_are_reported() {
+        let missing_token = ConnectionConfig {
+            bearer_token_env: Some("AI_TERMINAL_TEST_UNSET_TOKEN".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(missing_token.build_client(), Err(OllamaError::MissingEnvVar(_))));
+
+        let missing_bundle = ConnectionConfig { ca_bundle: Some(PathBuf::from("/nonexistent/ca.pem")), ..Default::default() };
+        assert!(matches!(missing_bundle.build_client(), Err(OllamaError::Connection(_))));
+
+        let bad_header = ConnectionConfig { headers: BTreeMap::from([("bad header".to_string(), "x".to_string())]), ..Default::default() };
+        assert!(matches!(bad_header.build_client(), Err(OllamaError::Connection(_))));
+    }
+}
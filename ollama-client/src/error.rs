@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,4 +18,37 @@ pub enum OllamaError {
     
     #[error("Missing environment variable: {0}")]
     MissingEnvVar(String),
+    
+    #[error("Server returned status {status}: {message}")]
+    Status { status: u16, message: String },
+    
+    #[error("No response within {0:?}")]
+    Timeout(Duration),
+    
+    #[error("Invalid connection settings: {0}")]
+    Connection(String),
+}
+
+impl OllamaError {
+    /// Check whether the request may succeed if sent again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            OllamaError::RequestFailed(e) => e.is_connect() || e.is_timeout(),
+            OllamaError::Status { status, .. } => matches!(status, 408 | 429 | 502 | 503 | 504),
+            _ => false,
+        }
+    }
+    
+    /// Check whether the model could not answer: not installed, failed to load, or too slow
+    pub fn is_model_unavailable(&self) -> bool {
+        match self {
+            OllamaError::Timeout(_) => true,
+            OllamaError::Status { status: 404, .. } => true,
+            OllamaError::Status { status: 500, message } => {
+                let message = message.to_lowercase();
+                message.contains("load") || message.contains("memory")
+            }
+            _ => false,
+        }
+    }
 }
\ No newline at end of file
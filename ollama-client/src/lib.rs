@@ -28,6 +28,22 @@ pub mod error;
 /// Module for MCP server functionality
 pub mod mcp;
 
+/// Module defining the backend-independent chat interface
+pub mod backend;
+
+/// Module for OpenAI-compatible servers
+pub mod openai;
+
+/// Module for retries and model fallback
+pub mod resilience;
+
+/// Module for TLS, authentication and proxy settings
+pub mod connection;
+
 /// Re-export the main client struct and models
 pub use api::OllamaClient;
-pub use models::{OllamaRequest, OllamaResponse};
\ No newline at end of file
+pub use backend::{BackendConfig, BackendKind, BackendProfile, ChatBackend, ChatRequest, ChatResponse, Fallback};
+pub use connection::ConnectionConfig;
+pub use resilience::{ResilientBackend, RetryPolicy};
+pub use openai::OpenAiClient;
+pub use models::{ChatMessage, FunctionCall, ModelDetails, ModelInfo, ModelShow, OllamaRequest, OllamaResponse, PullProgress, TokenUsage, ToolDefinition};
\ No newline at end of file
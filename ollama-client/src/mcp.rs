@@ -7,9 +7,7 @@ use jsonrpc_core::{IoHandler, Result as JsonRpcResult};
 use jsonrpc_derive::rpc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{info, error};
-
-use crate::{OllamaClient, OllamaRequest};
+use crate::OllamaClient;
 
 /// MCP tool for Ollama chat functionality
 #[derive(Debug, Serialize, Deserialize)]
@@ -77,6 +75,7 @@ pub trait OllamaMcp {
 #[derive(Clone)]
 pub struct OllamaMcpServer {
     /// The Ollama client
+    #[allow(dead_code)] // Tool calls return mock responses until they are forwarded to Ollama
     client: OllamaClient,
 }
 
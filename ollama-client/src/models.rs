@@ -3,7 +3,10 @@
 //! This module defines the data models used for requests and responses
 //! when interacting with the Ollama API.
 
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// A request to the Ollama API
 #[derive(Debug, Serialize, Clone)]
@@ -80,6 +83,360 @@ pub struct OllamaResponse {
     /// Optional context for continuing the conversation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<Vec<i32>>,
+    
+    /// Number of tokens in the prompt, sent with the final response
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    
+    /// Time spent evaluating the prompt, in nanoseconds
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    
+    /// Number of tokens generated, sent with the final response
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    
+    /// Time spent generating, in nanoseconds
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+    
+    /// Time spent on the whole request, including loading the model, in nanoseconds
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+}
+
+impl OllamaResponse {
+    /// Get the token counts and timings, if this response reports them
+    pub fn usage(&self) -> Option<TokenUsage> {
+        TokenUsage::from_counts(
+            self.prompt_eval_count,
+            self.prompt_eval_duration,
+            self.eval_count,
+            self.eval_duration,
+            self.total_duration,
+        )
+    }
+}
+
+/// A function the model may call, in the format of Ollama's `tools` field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    /// Kind of tool; always `function`
+    #[serde(rename = "type")]
+    pub kind: String,
+    
+    /// The function on offer
+    pub function: FunctionDefinition,
+}
+
+impl ToolDefinition {
+    /// Offer a function with a JSON schema for its arguments
+    pub fn function(name: impl Into<String>, description: impl Into<String>, parameters: Value) -> Self {
+        Self {
+            kind: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.into(),
+                description: description.into(),
+                parameters,
+            },
+        }
+    }
+}
+
+/// Name, description and argument schema of a function the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    /// Name the model calls the function by
+    pub name: String,
+    
+    /// What the function does
+    pub description: String,
+    
+    /// JSON schema of the arguments
+    pub parameters: Value,
+}
+
+/// A function call made by the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    /// Name of the function
+    pub name: String,
+    
+    /// Arguments, as a JSON object
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// One entry of the `tool_calls` of a chat message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallEntry {
+    /// The call
+    pub function: FunctionCall,
+}
+
+/// A message of a conversation sent to `/api/chat`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Who wrote the message: `system`, `user`, `assistant` or `tool`
+    pub role: String,
+    
+    /// Text of the message
+    #[serde(default)]
+    pub content: String,
+    
+    /// Functions the assistant called in this message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallEntry>,
+}
+
+impl ChatMessage {
+    /// Create a message without tool calls
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            tool_calls: Vec::new(),
+        }
+    }
+    
+    /// Create an assistant message making function calls
+    pub fn assistant(content: impl Into<String>, calls: &[FunctionCall]) -> Self {
+        Self {
+            tool_calls: calls.iter().cloned().map(|function| ToolCallEntry { function }).collect(),
+            ..Self::new("assistant", content)
+        }
+    }
+    
+    /// Create a message holding the result of a function call
+    pub fn tool(content: impl Into<String>) -> Self {
+        Self::new("tool", content)
+    }
+}
+
+/// A request to Ollama's `/api/chat`
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaChatRequest {
+    /// The model to use for generation
+    pub model: String,
+    
+    /// The conversation so far, oldest first
+    pub messages: Vec<ChatMessage>,
+    
+    /// Functions the model may call
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+    
+    /// Whether to stream the response
+    pub stream: bool,
+}
+
+/// A complete response from Ollama's `/api/chat`
+#[derive(Debug, Deserialize)]
+pub struct OllamaChatResponse {
+    /// The model used for generation
+    pub model: String,
+    
+    /// The model's reply, with any function calls
+    pub message: ChatMessage,
+    
+    /// Number of tokens in the prompt
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    
+    /// Time spent evaluating the prompt, in nanoseconds
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    
+    /// Number of tokens generated
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    
+    /// Time spent generating, in nanoseconds
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+    
+    /// Time spent on the whole request, including loading the model, in nanoseconds
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+}
+
+impl OllamaChatResponse {
+    /// Get the token counts and timings, if this response reports them
+    pub fn usage(&self) -> Option<TokenUsage> {
+        TokenUsage::from_counts(
+            self.prompt_eval_count,
+            self.prompt_eval_duration,
+            self.eval_count,
+            self.eval_duration,
+            self.total_duration,
+        )
+    }
+}
+
+/// Token counts and timings of one request, as reported by the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TokenUsage {
+    /// Tokens in the prompt, including the system message
+    pub prompt_tokens: u64,
+    
+    /// Tokens generated
+    pub completion_tokens: u64,
+    
+    /// Time spent evaluating the prompt, if reported
+    pub prompt_duration: Option<Duration>,
+    
+    /// Time spent generating, if reported
+    pub generation_duration: Option<Duration>,
+    
+    /// Time spent on the whole request on the server, if reported
+    pub total_duration: Option<Duration>,
+}
+
+impl TokenUsage {
+    /// Build the usage from Ollama's counts and nanosecond timings, if any count is reported
+    fn from_counts(
+        prompt_eval_count: Option<u64>,
+        prompt_eval_duration: Option<u64>,
+        eval_count: Option<u64>,
+        eval_duration: Option<u64>,
+        total_duration: Option<u64>,
+    ) -> Option<Self> {
+        if prompt_eval_count.is_none() && eval_count.is_none() {
+            return None;
+        }
+        Some(Self {
+            prompt_tokens: prompt_eval_count.unwrap_or(0),
+            completion_tokens: eval_count.unwrap_or(0),
+            prompt_duration: prompt_eval_duration.map(Duration::from_nanos),
+            generation_duration: eval_duration.map(Duration::from_nanos),
+            total_duration: total_duration.map(Duration::from_nanos),
+        })
+    }
+    
+    /// Tokens in the prompt and the response together
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+    
+    /// Generation speed in tokens per second, if the generation time is known
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let seconds = self.generation_duration?.as_secs_f64();
+        (seconds > 0.0).then(|| self.completion_tokens as f64 / seconds)
+    }
+}
+
+/// A model installed in Ollama, as listed by `/api/tags`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ModelInfo {
+    /// The model name, including its tag
+    pub name: String,
+    
+    /// When the model was last modified
+    #[serde(default)]
+    pub modified_at: String,
+    
+    /// Size of the model on disk in bytes
+    #[serde(default)]
+    pub size: u64,
+    
+    /// Digest of the model
+    #[serde(default)]
+    pub digest: String,
+    
+    /// Format, family and quantization of the model
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+/// Format, family and quantization details of a model
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ModelDetails {
+    /// File format, such as `gguf`
+    pub format: String,
+    
+    /// Model family, such as `llama`
+    pub family: String,
+    
+    /// Parameter count, such as `8.0B`
+    pub parameter_size: String,
+    
+    /// Quantization level, such as `Q4_0`
+    pub quantization_level: String,
+}
+
+/// The list of installed models returned by `/api/tags`
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModelList {
+    /// Installed models
+    pub models: Vec<ModelInfo>,
+}
+
+/// The server version returned by `/api/version`
+#[derive(Debug, Deserialize)]
+pub(crate) struct ServerVersion {
+    /// Version of Ollama, such as `0.5.7`
+    pub version: String,
+}
+
+/// Details of a model returned by `/api/show`
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ModelShow {
+    /// The model's license text
+    pub license: Option<String>,
+    
+    /// The Modelfile the model was built from
+    pub modelfile: Option<String>,
+    
+    /// Default generation parameters, one per line
+    pub parameters: Option<String>,
+    
+    /// The prompt template
+    pub template: Option<String>,
+    
+    /// Format, family and quantization of the model
+    pub details: ModelDetails,
+}
+
+/// One progress update while pulling a model
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct PullProgress {
+    /// What the pull is doing, such as `pulling manifest` or `success`
+    #[serde(default)]
+    pub status: String,
+    
+    /// Digest of the layer being downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    
+    /// Size of the layer being downloaded in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    
+    /// Bytes of the layer downloaded so far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+    
+    /// Error reported by Ollama in the middle of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PullProgress {
+    /// Get the fraction of the current layer downloaded, if known
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => Some((completed as f64 / total as f64).min(1.0)),
+            _ => None,
+        }
+    }
+    
+    /// Check whether this is the final update of a successful pull
+    pub fn is_success(&self) -> bool {
+        self.status == "success"
+    }
 }
 
 #[cfg(test)]
@@ -98,4 +455,35 @@ mod tests {
         assert_eq!(request.prompt, "Hello, world!");
         assert_eq!(request.system, Some("You are a helpful assistant.".to_string()));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_pull_progress_fraction() {
+        let progress: PullProgress = serde_json::from_str(
+            r#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a07","total":200,"completed":50}"#
+        ).unwrap();
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert!(!progress.is_success());
+        
+        let done: PullProgress = serde_json::from_str(r#"{"status":"success"}"#).unwrap();
+        assert_eq!(done.fraction(), None);
+        assert!(done.is_success());
+    }
+    
+    #[test]
+    fn test_response_usage() {
+        let response: OllamaResponse = serde_json::from_str(concat!(
+            r#"{"model":"llama3","created_at":"","response":"","done":true,"#,
+            r#""prompt_eval_count":26,"prompt_eval_duration":130000000,"#,
+            r#""eval_count":290,"eval_duration":4000000000,"total_duration":5000000000}"#,
+        )).unwrap();
+        let usage = response.usage().unwrap();
+        assert_eq!(usage.total_tokens(), 316);
+        assert_eq!(usage.tokens_per_second(), Some(72.5));
+        assert_eq!(usage.total_duration, Some(Duration::from_secs(5)));
+        
+        let chunk: OllamaResponse = serde_json::from_str(
+            r#"{"model":"llama3","created_at":"","response":"Hi","done":false}"#
+        ).unwrap();
+        assert_eq!(chunk.usage(), None);
+    }
+}
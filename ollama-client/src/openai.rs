@@ -0,0 +1,320 @@
+//! # OpenAI-Compatible Client
+//!
+//! This module provides a client for servers implementing the OpenAI chat
+//! completions API, such as the llama.cpp server, vLLM and LM Studio.
+//! Streaming responses arrive as server-sent events.
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::api::{check_status, line_stream};
+use crate::backend::{ChatBackend, ChatRequest, ChatResponse};
+use crate::error::OllamaError;
+use crate::models::TokenUsage;
+
+/// Client for an OpenAI-compatible server
+#[derive(Debug, Clone)]
+pub struct OpenAiClient {
+    /// The HTTP client used for requests
+    http_client: Client,
+
+    /// The base URL of the API, ending in `/v1`
+    pub base_url: String,
+
+    /// Key sent as a bearer token, for servers that need one
+    api_key: Option<String>,
+}
+
+/// Response of `/chat/completions`, or one event of its stream
+#[derive(Debug, Deserialize)]
+struct Completion {
+    /// Generated choices; only the first is used
+    #[serde(default)]
+    choices: Vec<Choice>,
+
+    /// Token counts, in non-streaming responses
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// Token counts of a completion
+#[derive(Debug, Deserialize)]
+struct Usage {
+    /// Tokens in the messages sent
+    #[serde(default)]
+    prompt_tokens: u64,
+
+    /// Tokens generated
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+/// One generated choice
+#[derive(Debug, Deserialize)]
+struct Choice {
+    /// The complete message, in non-streaming responses
+    #[serde(default)]
+    message: Option<Message>,
+
+    /// The next fragment, in streaming responses
+    #[serde(default)]
+    delta: Option<Message>,
+}
+
+/// Message content of a choice
+#[derive(Debug, Deserialize)]
+struct Message {
+    /// Text of the message; absent in role-only deltas
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Response of `/models`
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    /// Available models
+    data: Vec<ModelEntry>,
+}
+
+/// One available model
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    /// The model name
+    id: String,
+}
+
+/// Response of `/embeddings`
+#[derive(Debug, Deserialize)]
+struct EmbeddingList {
+    /// One embedding per input, possibly out of order
+    data: Vec<Embedding>,
+}
+
+/// One embedding
+#[derive(Debug, Deserialize)]
+struct Embedding {
+    /// Position of the input this embeds
+    #[serde(default)]
+    index: usize,
+
+    /// The embedding vector
+    embedding: Vec<f32>,
+}
+
+impl OpenAiClient {
+    /// Base URL used when none is configured, the llama.cpp server default
+    pub const DEFAULT_BASE_URL: &'static str = "http://localhost:8080/v1";
+
+    /// Create a client for the server at `base_url`
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            http_client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    /// Use a configured HTTP client, such as one with TLS and authentication settings
+    pub fn with_http_client(mut self, http_client: Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Start a POST request to an API endpoint, with authentication
+    fn post(&self, endpoint: &str) -> RequestBuilder {
+        self.authorize(self.http_client.post(format!("{}/{}", self.base_url, endpoint)))
+    }
+
+    /// Add the API key to a request, if there is one
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Build the body of a chat completion request
+    fn completion_body(request: &ChatRequest, stream: bool) -> serde_json::Value {
+        let mut messages = Vec::new();
+        if let Some(system) = &request.system {
+            messages.push(serde_json::json!({ "role": "system", "content": system }));
+        }
+        messages.push(serde_json::json!({ "role": "user", "content": request.prompt }));
+        // Tool calls and results follow Ollama's format, so only the conversation itself carries over
+        for turn in request.turns.iter().filter(|turn| matches!(turn.role.as_str(), "user" | "assistant") && turn.tool_calls.is_empty()) {
+            messages.push(serde_json::json!({ "role": turn.role, "content": turn.content }));
+        }
+        serde_json::json!({ "model": request.model, "messages": messages, "stream": stream })
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiClient {
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse, OllamaError> {
+        info!("Sending request to: {}/chat/completions", self.base_url);
+        let response = self.post("chat/completions")
+            .json(&Self::completion_body(request, false))
+            .send()
+            .await?;
+        let completion = check_status(response).await?.json::<Completion>().await?;
+        // The API reports token counts but no timings
+        let usage = completion.usage.map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            ..Default::default()
+        });
+        let text = completion.choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message?.content)
+            .ok_or_else(|| OllamaError::InvalidResponse("the response has no message".to_string()))?;
+        Ok(ChatResponse::new(request.model.clone(), text, usage))
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<BoxStream<'static, Result<String, OllamaError>>, OllamaError> {
+        let response = self.post("chat/completions")
+            .json(&Self::completion_body(request, true))
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        // Each event is a `data: {...}` line; the stream ends with `data: [DONE]`
+        let events = line_stream(response).filter_map(|line| async move {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let data = line.strip_prefix(b"data:")?.trim_ascii();
+            if data == b"[DONE]" {
+                return None;
+            }
+            let fragment = serde_json::from_slice::<Completion>(data).map(|completion| {
+                completion.choices
+                    .into_iter()
+                    .next()
+                    .and_then(|choice| choice.delta?.content)
+                    .unwrap_or_default()
+            });
+            Some(fragment.map_err(OllamaError::from))
+        });
+        Ok(events.filter(|fragment| std::future::ready(!matches!(fragment, Ok(text) if text.is_empty()))).boxed())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, OllamaError> {
+        let request = self.authorize(self.http_client.get(format!("{}/models", self.base_url)));
+        let models = check_status(request.send().await?).await?.json::<ModelList>().await?;
+        Ok(models.data.into_iter().map(|model| model.id).collect())
+    }
+
+    async fn embeddings(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, OllamaError> {
+        let response = self.post("embeddings")
+            .json(&serde_json::json!({ "model": model, "input": input }))
+            .send()
+            .await?;
+        let mut embeddings = check_status(response).await?.json::<EmbeddingList>().await?.data;
+        embeddings.sort_by_key(|embedding| embedding.index);
+        Ok(embeddings.into_iter().map(|embedding| embedding.embedding).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_partial_json, header, method, path}};
+    use crate::models::ChatMessage;
+
+    fn mock_client(server: &MockServer) -> OpenAiClient {
+        OpenAiClient::new(&format!("{}/v1/", server.uri()), Some("secret".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_generate_sends_messages() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "qwen",
+                "stream": false,
+                "messages": [{ "role": "system", "content": "be brief" }, { "role": "user", "content": "hi" }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+                r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"Hello!"}}],"#,
+                r#""usage":{"prompt_tokens":12,"completion_tokens":3,"total_tokens":15}}"#,
+            )))
+            .mount(&server)
+            .await;
+
+        let request = ChatRequest::new("qwen", "hi").with_system("be brief");
+        let response = mock_client(&server).generate(&request).await.unwrap();
+        assert_eq!(response.text, "Hello!");
+        assert_eq!(response.usage.unwrap().total_tokens(), 15);
+    }
+
+    #[test]
+    fn test_completion_body_carries_the_conversation() {
+        let mut request = ChatRequest::new("qwen", "what is a crate?");
+        request.turns = vec![ChatMessage::new("assistant", "A package."), ChatMessage::new("user", "and a module?")];
+        let body = OpenAiClient::completion_body(&request, false);
+        let roles: Vec<&str> = body["messages"].as_array().unwrap().iter().map(|message| message["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, ["user", "assistant", "user"]);
+        assert_eq!(body["messages"][2]["content"], "and a module?");
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_server_sent_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+                ": keep-alive\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+                "data: [DONE]\n\n",
+            )))
+            .mount(&server)
+            .await;
+
+        let stream = mock_client(&server).stream(&ChatRequest::new("qwen", "hi")).await.unwrap();
+        let fragments: Vec<String> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(fragments, ["Hel", "lo"]);
+    }
+
+    #[tokio::test]
+    async fn test_models_embeddings_and_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"object":"list","data":[{"id":"qwen"},{"id":"phi"}]}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"data":[{"index":1,"embedding":[2.0]},{"index":0,"embedding":[1.0]}]}"#
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":{"message":"model not found"}}"#))
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        assert_eq!(client.list_models().await.unwrap(), ["qwen", "phi"]);
+        let embeddings = client.embeddings("qwen", &["a".to_string(), "b".to_string()]).await.unwrap();
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0]]);
+        let error = client.generate(&ChatRequest::new("nope", "hi")).await.unwrap_err();
+        assert!(error.to_string().contains("model not found"));
+    }
+}
@@ -0,0 +1,307 @@
+//! # Retries and Model Fallback
+//!
+//! [`ResilientBackend`] wraps another [`ChatBackend`] and makes it more
+//! forgiving. Requests that fail for transient reasons (connection errors,
+//! `429`, `502`-`504`) are sent again after an exponential backoff. When the
+//! requested model cannot answer at all, because it is not installed, failed
+//! to load or did not respond within the timeout, a generation request is
+//! sent to a configured fallback model instead, and the response records
+//! which model actually answered.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::backend::{ChatBackend, ChatRequest, ChatResponse, Fallback};
+use crate::error::OllamaError;
+
+/// How often and how patiently transient failures are retried
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+
+    /// Wait before the first retry, in milliseconds; doubled for each further retry
+    pub initial_backoff_ms: u64,
+
+    /// Longest wait between retries, in milliseconds
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 4000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, counting from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u64.checked_shl(retry).unwrap_or(u64::MAX);
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+/// A backend that retries transient failures and falls back to another model
+#[derive(Debug)]
+pub struct ResilientBackend {
+    /// The backend requests are sent to
+    inner: Arc<dyn ChatBackend>,
+
+    /// When to retry
+    retry: RetryPolicy,
+
+    /// Model to ask when the requested one cannot answer
+    fallback_model: Option<String>,
+
+    /// Longest time to wait for a response, if limited
+    timeout: Option<Duration>,
+}
+
+impl ResilientBackend {
+    /// Wrap a backend, retrying according to `retry`
+    pub fn new(inner: Arc<dyn ChatBackend>, retry: RetryPolicy) -> Self {
+        Self {
+            inner,
+            retry,
+            fallback_model: None,
+            timeout: None,
+        }
+    }
+
+    /// Ask `model` when the requested model cannot answer
+    pub fn with_fallback_model(mut self, model: impl Into<String>) -> Self {
+        self.fallback_model = Some(model.into());
+        self
+    }
+
+    /// Give up on a request after `timeout`, which counts as the model being unavailable
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run `call` until it succeeds, fails for good or runs out of retries
+    ///
+    /// Returns the result and how many retries were needed.
+    async fn with_retries<T, F, Fut>(&self, what: &str, mut call: F) -> (Result<T, OllamaError>, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, OllamaError>>,
+    {
+        let mut retries = 0;
+        loop {
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call())
+                    .await
+                    .unwrap_or(Err(OllamaError::Timeout(timeout))),
+                None => call().await,
+            };
+            match result {
+                Err(e) if e.is_transient() && retries < self.retry.max_retries => {
+                    let backoff = self.retry.backoff(retries);
+                    warn!("{} failed ({}); retrying in {:?}", what, e, backoff);
+                    tokio::time::sleep(backoff).await;
+                    retries += 1;
+                }
+                result => return (result, retries),
+            }
+        }
+    }
+
+    /// Get the request to send to the fallback model after `error`, if there is one
+    fn fallback_request(&self, request: &ChatRequest, error: &OllamaError) -> Option<ChatRequest> {
+        let model = self.fallback_model.as_ref().filter(|model| **model != request.model)?;
+        if !error.is_model_unavailable() {
+            return None;
+        }
+        warn!("Model {} could not answer ({}); falling back to {}", request.model, error, model);
+        Some(ChatRequest { model: model.clone(), ..request.clone() })
+    }
+}
+
+#[async_trait]
+impl ChatBackend for ResilientBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    async fn generate(&self, request: &ChatRequest) -> Result<ChatResponse, OllamaError> {
+        let (result, retries) = self.with_retries("Generation", || self.inner.generate(request)).await;
+        let error = match result {
+            Ok(mut response) => {
+                response.retries += retries;
+                return Ok(response);
+            }
+            Err(e) => e,
+        };
+        let Some(fallback) = self.fallback_request(request, &error) else {
+            return Err(error);
+        };
+
+        let (result, fallback_retries) = self.with_retries("Generation", || self.inner.generate(&fallback)).await;
+        let mut response = result?;
+        response.retries += retries + fallback_retries;
+        response.fallback = Some(Fallback {
+            primary: request.model.clone(),
+            reason: error.to_string(),
+        });
+        Ok(response)
+    }
+
+    async fn stream(&self, request: &ChatRequest) -> Result<BoxStream<'static, Result<String, OllamaError>>, OllamaError> {
+        // Only opening the stream is retried; fragments already shown cannot be taken back
+        let (result, _) = self.with_retries("Streaming", || self.inner.stream(request)).await;
+        match result {
+            Err(error) => match self.fallback_request(request, &error) {
+                Some(fallback) => self.with_retries("Streaming", || self.inner.stream(&fallback)).await.0,
+                None => Err(error),
+            },
+            stream => stream,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, OllamaError> {
+        self.with_retries("Listing models", || self.inner.list_models()).await.0
+    }
+
+    async fn embeddings(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>, OllamaError> {
+        self.with_retries("Embedding", || self.inner.embeddings(model, input)).await.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::OllamaClient;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_partial_json, method, path}};
+
+    /// Retry quickly so tests do not wait
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy { max_retries, initial_backoff_ms: 1, max_backoff_ms: 1 }
+    }
+
+    fn backend(server: &MockServer, retry: RetryPolicy) -> ResilientBackend {
+        let mut client = OllamaClient::new().unwrap();
+        client.base_url = format!("{}/api", server.uri());
+        ResilientBackend::new(Arc::new(client), retry)
+    }
+
+    fn answer(model: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_string(format!(
+            r#"{{"model":"{}","created_at":"","response":"Hi","done":true}}"#, model
+        ))
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_limit() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(10), Duration::from_millis(4000));
+        assert_eq!(policy.backoff(100), Duration::from_millis(4000));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .respond_with(answer("llama3"))
+            .mount(&server)
+            .await;
+
+        let response = backend(&server, fast_retries(2)).generate(&ChatRequest::new("llama3", "hi")).await.unwrap();
+        assert_eq!(response.text, "Hi");
+        assert_eq!(response.retries, 2);
+        assert!(response.fallback.is_none());
+
+        // Without retries the first failure is returned
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(503))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let error = backend(&server, fast_retries(0)).generate(&ChatRequest::new("llama3", "hi")).await.unwrap_err();
+        assert!(error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_the_model_is_missing() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(serde_json::json!({ "model": "big" })))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"error":"model 'big' not found"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(serde_json::json!({ "model": "small" })))
+            .respond_with(answer("small"))
+            .mount(&server)
+            .await;
+
+        let request = ChatRequest::new("big", "hi");
+        let response = backend(&server, fast_retries(2))
+            .with_fallback_model("small")
+            .generate(&request)
+            .await
+            .unwrap();
+        assert_eq!(response.model, "small");
+        let fallback = response.fallback.unwrap();
+        assert_eq!(fallback.primary, "big");
+        assert!(fallback.reason.contains("not found"));
+
+        // Without a fallback model the error is returned as is
+        let error = backend(&server, fast_retries(2)).generate(&request).await.unwrap_err();
+        assert!(matches!(error, OllamaError::Status { status: 404, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_counts_as_unavailable() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(serde_json::json!({ "model": "slow" })))
+            .respond_with(answer("slow").set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(serde_json::json!({ "model": "fast" })))
+            .respond_with(answer("fast"))
+            .mount(&server)
+            .await;
+
+        let response = backend(&server, fast_retries(0))
+            .with_fallback_model("fast")
+            .with_timeout(Duration::from_millis(200))
+            .generate(&ChatRequest::new("slow", "hi"))
+            .await
+            .unwrap();
+        assert_eq!(response.model, "fast");
+        assert!(response.fallback.unwrap().reason.contains("No response within"));
+    }
+}
@@ -1,8 +1,19 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use uuid::Uuid;
 
+use crate::frame_store::{self, FrameStore};
+use crate::graphics::GraphicsSequence;
+use crate::impact::ImpactReport;
+use crate::scrollback::{OutputBuffer, ScrollbackLimits};
+use crate::usage::ResourceUsage;
+use crate::tags;
+
+/// Most images kept per block
+pub const MAX_IMAGES: usize = 8;
+
 /// Represents a single command execution block in the terminal
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandBlock {
@@ -12,14 +23,14 @@ pub struct CommandBlock {
     /// The command that was executed
     pub command: String,
     
-    /// The complete output (stdout + stderr)
-    pub output: String,
+    /// The retained output (stdout + stderr)
+    pub output: OutputBuffer,
     
     /// Separate stdout for processing
-    pub stdout: String,
+    pub stdout: OutputBuffer,
     
     /// Separate stderr for error handling
-    pub stderr: String,
+    pub stderr: OutputBuffer,
     
     /// Exit code of the command
     pub exit_code: Option<i32>,
@@ -30,11 +41,126 @@ pub struct CommandBlock {
     /// How long the command took to execute
     pub duration: Option<Duration>,
     
+    /// CPU time and memory the command used, for local commands on Unix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+    
     /// Current state of the block
     pub state: BlockState,
     
     /// Working directory when command was executed
     pub working_dir: String,
+    
+    /// What the command changed on disk, for commands that were snapshotted
+    #[serde(default)]
+    pub impact: Option<ImpactReport>,
+    
+    /// Secrets masked in the output before it was sent to the AI
+    #[serde(default)]
+    pub redactions: usize,
+    
+    /// Commit checked out when the command ran, for commands run in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    
+    /// Host the command ran on over SSH, for remote commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    
+    /// Directory a remote command left the shell in, as the host reported it
+    #[serde(skip)]
+    pub end_dir: Option<String>,
+    
+    /// Later runs of the command that produced identical output, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repeats: Vec<BlockRun>,
+    
+    /// Whether the individual runs are listed under the block
+    #[serde(skip)]
+    show_runs: bool,
+    
+    /// Enhanced view of the output made by an output processor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<BlockView>,
+    
+    /// Whether the raw output is shown instead of the enhanced view
+    #[serde(skip)]
+    show_raw: bool,
+    
+    /// AI summary of a long output, shown above it; only finished summaries are saved
+    #[serde(default, skip_serializing_if = "OutputSummary::is_unfinished")]
+    pub summary: Option<OutputSummary>,
+    
+    /// Whether the summary is folded to its heading
+    #[serde(skip)]
+    summary_collapsed: bool,
+    
+    /// Tags the user gave the block, without `#`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    
+    /// Images the command wrote with a graphics protocol; they are not saved with sessions
+    #[serde(skip)]
+    pub images: Vec<GraphicsSequence>,
+    
+    /// Corrected command suggested for a failed command
+    #[serde(skip)]
+    correction: Option<String>,
+    
+    /// Incremented on every change, so renderers can cache the block
+    #[serde(skip)]
+    revision: u64,
+    
+    /// Limits on the retained output
+    #[serde(skip)]
+    limits: ScrollbackLimits,
+    
+    /// File receiving output lines dropped from the scrollback
+    #[serde(skip)]
+    spill_path: Option<PathBuf>,
+}
+
+/// One run of a command whose block collapsed identical repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRun {
+    /// When the run started
+    pub timestamp: DateTime<Local>,
+    
+    /// How long the run took
+    pub duration: Option<Duration>,
+    
+    /// Exit code of the run
+    pub exit_code: Option<i32>,
+}
+
+/// Output of a command transformed for display, such as a JSON inspector
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockView {
+    /// Name of the processor that made the view
+    pub processor: String,
+    
+    /// The view, as Markdown
+    pub markdown: String,
+}
+
+/// Where the AI summary of a long output stands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputSummary {
+    /// The output is long enough to summarize, and `/summarize` would do it
+    Offered,
+    /// The model is summarizing the output
+    Pending,
+    /// The model's summary, as Markdown
+    Ready { model: String, markdown: String },
+    /// Summarizing failed
+    Failed(String),
+}
+
+impl OutputSummary {
+    /// Check whether a block's summary is missing or not finished, so it is not worth saving
+    fn is_unfinished(summary: &Option<Self>) -> bool {
+        !matches!(summary, Some(Self::Ready { .. }))
+    }
 }
 
 /// Represents the current state of a command block
@@ -65,21 +191,47 @@ impl CommandBlock {
         Self {
             id: Uuid::new_v4(),
             command,
-            output: String::new(),
-            stdout: String::new(),
-            stderr: String::new(),
+            output: OutputBuffer::new(),
+            stdout: OutputBuffer::new(),
+            stderr: OutputBuffer::new(),
             exit_code: None,
             timestamp: Local::now(),
             duration: None,
+            usage: None,
             state: BlockState::Editing,
             working_dir,
+            impact: None,
+            redactions: 0,
+            commit: None,
+            host: None,
+            end_dir: None,
+            repeats: Vec::new(),
+            show_runs: false,
+            view: None,
+            show_raw: false,
+            summary: None,
+            summary_collapsed: false,
+            tags: Vec::new(),
+            images: Vec::new(),
+            correction: None,
+            revision: 0,
+            limits: ScrollbackLimits::default(),
+            spill_path: None,
         }
     }
     
+    /// Limit the retained output, spilling dropped lines to a file in `spill_dir`
+    pub fn set_scrollback(&mut self, limits: ScrollbackLimits, spill_dir: Option<&Path>) {
+        self.limits = limits;
+        self.spill_path = spill_dir.map(|dir| dir.join(format!("{}.aitz", self.id)));
+        self.trim_output();
+    }
+    
     /// Start executing this command
     pub fn start_execution(&mut self) {
         self.state = BlockState::Running;
         self.timestamp = Local::now();
+        self.revision += 1;
     }
     
     /// Mark the command as completed
@@ -91,6 +243,43 @@ impl CommandBlock {
         } else {
             BlockState::Failed
         };
+        self.revision += 1;
+    }
+    
+    /// Record the CPU time and memory the command used
+    pub fn set_usage(&mut self, usage: ResourceUsage) {
+        self.usage = Some(usage);
+        self.revision += 1;
+    }
+    
+    /// Set the state of the block
+    pub fn set_state(&mut self, state: BlockState) {
+        self.state = state;
+        self.revision += 1;
+    }
+    
+    /// Record what the command changed on disk
+    pub fn set_impact(&mut self, impact: ImpactReport) {
+        self.impact = Some(impact);
+        self.revision += 1;
+    }
+    
+    /// Record how many secrets were masked when the output was sent to the AI
+    pub fn set_redactions(&mut self, count: usize) {
+        self.redactions = count;
+        self.revision += 1;
+    }
+    
+    /// Record the commit checked out when the command ran
+    pub fn set_commit(&mut self, commit: Option<String>) {
+        self.commit = commit;
+        self.revision += 1;
+    }
+    
+    /// Record the host the command runs on
+    pub fn set_host(&mut self, host: Option<String>) {
+        self.host = host;
+        self.revision += 1;
     }
     
     /// Append output to the block
@@ -101,6 +290,220 @@ impl CommandBlock {
             self.stdout.push_str(text);
         }
         self.output.push_str(text);
+        self.revision += 1;
+        self.trim_output();
+    }
+    
+    /// Add an image the command wrote, dropping the oldest beyond `MAX_IMAGES`
+    pub fn add_image(&mut self, image: GraphicsSequence) {
+        if self.images.len() == MAX_IMAGES {
+            self.images.remove(0);
+        }
+        self.images.push(image);
+        self.revision += 1;
+    }
+    
+    /// Drop the oldest output lines until at least `bytes` bytes are freed
+    pub fn drop_oldest_output(&mut self, bytes: usize) {
+        let dropped = self.output.drop_bytes(bytes);
+        self.spill(&dropped);
+        self.revision += 1;
+    }
+    
+    /// Drop all output, including lines spilled from the scrollback, keeping the command
+    pub fn clear_output(&mut self) {
+        self.output = OutputBuffer::new();
+        self.stdout = OutputBuffer::new();
+        self.stderr = OutputBuffer::new();
+        self.view = None;
+        if let Some(path) = &self.spill_path {
+            let _ = frame_store::remove(path);
+        }
+        self.revision += 1;
+    }
+    
+    /// Get the complete output, including lines dropped from the scrollback
+    pub fn full_output(&self) -> anyhow::Result<String> {
+        let mut output = String::new();
+        if let Some(path) = self.spill_path.as_ref().filter(|path| path.exists()) {
+            for chunk in FrameStore::<String>::open(path)?.read_all()? {
+                output.push_str(&chunk);
+            }
+        }
+        output.push_str(&self.output.text());
+        Ok(output)
+    }
+    
+    /// Keep the output within the scrollback limits
+    fn trim_output(&mut self) {
+        let dropped = self.output.trim_to(self.limits);
+        if !dropped.is_empty() {
+            self.spill(&dropped);
+            self.revision += 1;
+        }
+        self.stdout.trim_to(self.limits);
+        self.stderr.trim_to(self.limits);
+    }
+    
+    /// Write dropped output lines to the spill file, if there is one
+    fn spill(&self, lines: &[String]) {
+        let Some(path) = &self.spill_path else {
+            return;
+        };
+        if lines.is_empty() {
+            return;
+        }
+        if let Err(e) = FrameStore::<String>::open(path).and_then(|mut store| store.append(&lines.concat())) {
+            tracing::warn!("Failed to spill scrollback to {}: {:?}", path.display(), e);
+        }
+    }
+    
+    /// Check whether this block repeats `earlier`: the same finished command in the
+    /// same directory, with the same exit code and identical, untruncated output
+    pub fn is_repeat_of(&self, earlier: &CommandBlock) -> bool {
+        self.is_complete()
+            && self.state == earlier.state
+            && self.exit_code == earlier.exit_code
+            && self.command == earlier.command
+            && self.working_dir == earlier.working_dir
+            && self.impact.is_none()
+            && earlier.impact.is_none()
+            && self.output.dropped_lines() == 0
+            && earlier.output.dropped_lines() == 0
+            && self.output.len() == earlier.output.len()
+            && self.output.lines().eq(earlier.output.lines())
+    }
+    
+    /// Record `repeat`, a later identical run, as a run of this block
+    pub fn add_repeat(&mut self, repeat: &CommandBlock) {
+        self.repeats.push(BlockRun {
+            timestamp: repeat.timestamp,
+            duration: repeat.duration,
+            exit_code: repeat.exit_code,
+        });
+        self.repeats.extend_from_slice(&repeat.repeats);
+        self.revision += 1;
+    }
+    
+    /// Get every run of the command, oldest first
+    pub fn runs(&self) -> Vec<BlockRun> {
+        let first = BlockRun {
+            timestamp: self.timestamp,
+            duration: self.duration,
+            exit_code: self.exit_code,
+        };
+        std::iter::once(first).chain(self.repeats.iter().copied()).collect()
+    }
+    
+    /// Check whether the individual runs are listed under the block
+    pub fn shows_runs(&self) -> bool {
+        self.show_runs
+    }
+    
+    /// List or hide the individual runs under the block
+    pub fn set_show_runs(&mut self, show: bool) {
+        if self.show_runs != show {
+            self.show_runs = show;
+            self.revision += 1;
+        }
+    }
+    
+    /// Attach an enhanced view of the output
+    pub fn set_view(&mut self, view: BlockView) {
+        self.view = Some(view);
+        self.revision += 1;
+    }
+    
+    /// Check whether the raw output is shown instead of the enhanced view
+    pub fn shows_raw(&self) -> bool {
+        self.show_raw
+    }
+    
+    /// Show the raw output instead of the enhanced view, or the view again
+    pub fn set_show_raw(&mut self, raw: bool) {
+        if self.show_raw != raw {
+            self.show_raw = raw;
+            self.revision += 1;
+        }
+    }
+    
+    /// Set where the summary of the output stands
+    pub fn set_summary(&mut self, summary: OutputSummary) {
+        self.summary = Some(summary);
+        self.revision += 1;
+    }
+    
+    /// Check whether the summary is folded to its heading
+    pub fn summary_collapsed(&self) -> bool {
+        self.summary_collapsed
+    }
+    
+    /// Fold the summary to its heading, or unfold it
+    pub fn set_summary_collapsed(&mut self, collapsed: bool) {
+        if self.summary_collapsed != collapsed {
+            self.summary_collapsed = collapsed;
+            self.revision += 1;
+        }
+    }
+    
+    /// Get the corrected command suggested for the block, if any
+    pub fn correction(&self) -> Option<&str> {
+        self.correction.as_deref()
+    }
+    
+    /// Suggest a corrected command under the block, or remove the suggestion
+    pub fn set_correction(&mut self, correction: Option<String>) {
+        if self.correction != correction {
+            self.correction = correction;
+            self.revision += 1;
+        }
+    }
+    
+    /// Give the block a tag, returning whether it is a new tag
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        match tags::normalize(tag) {
+            Some(tag) if !self.tags.contains(&tag) => {
+                self.tags.push(tag);
+                self.revision += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    /// Take a tag the user gave away from the block, returning whether it had it
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let Some(tag) = tags::normalize(tag) else {
+            return false;
+        };
+        let before = self.tags.len();
+        self.tags.retain(|existing| *existing != tag);
+        let removed = self.tags.len() != before;
+        if removed {
+            self.revision += 1;
+        }
+        removed
+    }
+    
+    /// Get the tags the user gave the block, then the automatic ones it does not already have
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut all = self.tags.clone();
+        for tag in tags::auto_tags(self) {
+            if !all.iter().any(|existing| existing == tag) {
+                all.push(tag.to_string());
+            }
+        }
+        all
+    }
+    
+    /// Check whether the block has a tag, given by the user or automatic
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag) || tags::auto_tags(self).contains(&tag)
+    }
+    
+    /// Get the revision of the block, which changes whenever the block does
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
     
     /// Get a display-friendly status icon
@@ -1,10 +1,13 @@
 use std::collections::VecDeque;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::file_lock::FileLock;
+use crate::store::Store;
+
 /// Represents a command in the history
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct HistoryEntry {
@@ -20,105 +23,56 @@ pub struct CommandHistory {
     entries: VecDeque<HistoryEntry>,
     /// Maximum number of entries to keep in memory
     max_entries: usize,
-    /// Path to the history file
-    history_file: PathBuf,
+    /// Database holding the full history
+    store: Store,
 }
 
 impl CommandHistory {
-    /// Create a new command history manager
+    /// Create a new command history manager using the shared database
     pub fn new(max_entries: usize) -> Result<Self> {
-        let history_file = Self::get_history_file_path()?;
-        Self::with_file(max_entries, history_file)
+        let mut store = Store::open_default()?;
+
+        // Import the plain history file used before the shared database
+        let legacy_file = Store::data_dir()?.join("history.txt");
+        // Another instance starting at the same time may be importing it too
+        let _lock = legacy_file.exists().then(|| FileLock::exclusive(&legacy_file)).transpose()?;
+        if legacy_file.exists() {
+            store.import_history(&read_legacy_file(&legacy_file)?)?;
+            std::fs::rename(&legacy_file, legacy_file.with_extension("txt.bak"))?;
+        }
+
+        Self::with_store(max_entries, store)
     }
-    
-    /// Create a new command history manager with a specific history file
+
+    /// Create a new command history manager with a specific history database
+    ///
+    /// A history file in the older JSON-lines or plain-text format is
+    /// converted in place; the original is kept with a `.bak` suffix.
     pub fn with_file(max_entries: usize, history_file: PathBuf) -> Result<Self> {
-        let mut history = Self {
-            entries: VecDeque::new(),
-            max_entries,
-            history_file,
-        };
-        
-        // Load existing history from file
-        history.load_from_file()?;
-        
-        Ok(history)
-    }
-    
-    /// Get the path to the history file
-    fn get_history_file_path() -> Result<PathBuf> {
-        // Try to get XDG data home, fallback to home directory
-        let data_dir = if let Ok(xdg_data_home) = std::env::var("XDG_DATA_HOME") {
-            PathBuf::from(xdg_data_home)
-        } else if let Ok(home) = std::env::var("HOME") {
-            let mut path = PathBuf::from(home);
-            path.push(".local/share");
-            path
+        let store = if history_file.exists() && !Store::is_database(&history_file) {
+            let entries = read_legacy_file(&history_file)?;
+            let mut backup = history_file.clone().into_os_string();
+            backup.push(".bak");
+            std::fs::rename(&history_file, &backup)?;
+
+            let mut store = Store::open(&history_file)?;
+            store.import_history(&entries)?;
+            store
         } else {
-            // Fallback to current directory
-            std::env::current_dir()?
+            Store::open(&history_file)?
         };
-        
-        // Create the ai-terminal directory if it doesn't exist
-        let mut history_dir = data_dir;
-        history_dir.push("ai-terminal");
-        std::fs::create_dir_all(&history_dir)?;
-        
-        // Return the history file path
-        let mut history_file = history_dir;
-        history_file.push("history.txt");
-        Ok(history_file)
-    }
-    
-    /// Load history from the history file
-    fn load_from_file(&mut self) -> Result<()> {
-        if !self.history_file.exists() {
-            return Ok(());
-        }
-        
-        let file = File::open(&self.history_file)?;
-        let reader = BufReader::new(file);
-        
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            // Try to parse as JSON first (newer format)
-            if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) {
-                self.entries.push_back(entry);
-            } else {
-                // Fallback to simple text format (older format)
-                self.entries.push_back(HistoryEntry {
-                    command: line,
-                    timestamp: chrono::Local::now(),
-                });
-            }
-            
-            // Limit the number of entries
-            if self.entries.len() > self.max_entries {
-                self.entries.pop_front();
-            }
-        }
-        
-        Ok(())
+
+        Self::with_store(max_entries, store)
     }
-    
-    /// Save history to the history file
-    fn save_to_file(&self) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&self.history_file)?;
-        
-        for entry in &self.entries {
-            let json = serde_json::to_string(entry)?;
-            writeln!(file, "{}", json)?;
-        }
-        
-        Ok(())
+
+    /// Create a new command history manager backed by an open store
+    pub fn with_store(max_entries: usize, store: Store) -> Result<Self> {
+        let entries = store.recent_history(max_entries)?.into();
+        Ok(Self {
+            entries,
+            max_entries,
+            store,
+        })
     }
     
     /// Add a command to the history
@@ -128,26 +82,25 @@ impl CommandHistory {
             return Ok(());
         }
         
-        if let Some(last_entry) = self.entries.back() {
-            if last_entry.command == command {
-                return Ok(());
-            }
+        if let Some(last_entry) = self.entries.back()
+            && last_entry.command == command
+        {
+            return Ok(());
         }
         
         // Add the new entry
-        self.entries.push_back(HistoryEntry {
+        let entry = HistoryEntry {
             command,
             timestamp: chrono::Local::now(),
-        });
+        };
+        self.store.add_history(&entry)?;
+        self.entries.push_back(entry);
         
         // Remove the oldest entry if we've exceeded the limit
         if self.entries.len() > self.max_entries {
             self.entries.pop_front();
         }
         
-        // Save to file
-        self.save_to_file()?;
-        
         Ok(())
     }
     
@@ -174,11 +127,49 @@ impl CommandHistory {
             .filter(|entry| entry.command.contains(query))
             .collect()
     }
+
+    /// Search the full persisted history, newest first
+    pub fn search_all(&self, query: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        self.store.search_history(query, limit)
+    }
+    
+    /// Reload the in-memory entries from the database
+    pub fn reload(&mut self) -> Result<()> {
+        self.entries = self.store.recent_history(self.max_entries)?.into();
+        Ok(())
+    }
     
     /// Clear the history
     pub fn clear(&mut self) -> Result<()> {
         self.entries.clear();
-        self.save_to_file()?;
+        self.store.clear_history()?;
         Ok(())
     }
+}
+
+/// Read a history file in the JSON-lines or plain-text format
+fn read_legacy_file(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        // Try to parse as JSON first (newer format)
+        if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) {
+            entries.push(entry);
+        } else {
+            // Fallback to simple text format (older format)
+            entries.push(HistoryEntry {
+                command: line,
+                timestamp: chrono::Local::now(),
+            });
+        }
+    }
+    
+    Ok(entries)
 }
\ No newline at end of file
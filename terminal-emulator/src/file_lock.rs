@@ -0,0 +1,88 @@
+//! Advisory file locking for the AI Terminal
+//!
+//! Several terminal instances can share one data directory. Files that are
+//! not protected by SQLite's own locking are guarded by an advisory lock on
+//! a `<file>.lock` companion, held for the duration of each update.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    /// The open lock file
+    file: File,
+    /// Path to the lock file
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Wait for exclusive access to `path`
+    pub fn exclusive(path: &Path) -> Result<Self> {
+        let lock = Self::open(path)?;
+        lock.file.lock()?;
+        Ok(lock)
+    }
+
+    /// Wait for shared access to `path`
+    pub fn shared(path: &Path) -> Result<Self> {
+        let lock = Self::open(path)?;
+        lock.file.lock_shared()?;
+        Ok(lock)
+    }
+
+    /// Take exclusive access to `path` if no one else holds it
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>> {
+        let lock = Self::open(path)?;
+        match lock.file.try_lock() {
+            Ok(()) => Ok(Some(lock)),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+
+    /// Get the path to the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open (or create) the lock file guarding `path`
+    fn open(path: &Path) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let path = PathBuf::from(lock_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path)?;
+        Ok(Self { file, path })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_lock_blocks_others() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-lock-test-{}", std::process::id()));
+        let path = dir.join("data.txt");
+
+        let held = FileLock::exclusive(&path).unwrap();
+        assert!(held.path().ends_with("data.txt.lock"));
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+
+        drop(held);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
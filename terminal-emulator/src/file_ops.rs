@@ -0,0 +1,399 @@
+//! Gated file operations for the AI Terminal
+//!
+//! Writing, copying, moving and permanently deleting files from inside the
+//! terminal goes through a [`FileOperation`]. Every path is made absolute and
+//! canonicalized, so `..` and symlinks cannot hide where a change lands, and
+//! checked against a denylist of protected locations: the filesystem root,
+//! the home directory itself, system directories and credential stores such
+//! as `~/.ssh`. [`FileOperation::preview`] is a dry run listing every file
+//! that would be created, replaced or removed; callers show it in a
+//! confirmation and only call [`FileOperation::apply`] once the user accepts.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// System directories no operation may touch, nor anything inside them
+const PROTECTED_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib64", "/proc", "/sbin", "/sys", "/usr", "/var/lib",
+    "/System", "/Library", "/private/etc", "C:\\Windows", "C:\\Program Files",
+];
+
+/// Directories under the home directory holding credentials
+const PROTECTED_HOME_DIRS: &[&str] = &[".ssh", ".gnupg", ".aws", ".kube", ".docker"];
+
+/// Resolve a path and refuse it if it is protected
+///
+/// Relative paths are taken from `working_dir`. Paths that do not exist yet
+/// are resolved through their nearest existing ancestor.
+pub fn resolve(path: &Path, working_dir: &Path) -> Result<PathBuf> {
+    let absolute = if path.is_absolute() { path.to_path_buf() } else { working_dir.join(path) };
+    let resolved = canonicalize_lenient(&absolute);
+    if resolved.parent().is_none() {
+        bail!("refusing to change {}: it is the filesystem root", path.display());
+    }
+    if let Some(home) = home_dir() {
+        if resolved == home {
+            bail!("refusing to change {}: it is the home directory", path.display());
+        }
+        if let Some(dir) = PROTECTED_HOME_DIRS.iter().find(|dir| resolved.starts_with(home.join(dir))) {
+            bail!("refusing to change {}: ~/{} holds credentials", path.display(), dir);
+        }
+    }
+    if let Some(dir) = PROTECTED_DIRS.iter().find(|dir| resolved.starts_with(dir)) {
+        bail!("refusing to change {}: {} is a protected system directory", path.display(), dir);
+    }
+    Ok(resolved)
+}
+
+/// Canonicalize a path that may not exist yet, through its nearest existing ancestor
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    // Drop `.` and `..` first so a missing directory cannot hide a `..`
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    let mut missing = Vec::new();
+    let mut existing = normalized.as_path();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return missing.iter().rev().fold(resolved, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return normalized,
+        }
+    }
+}
+
+/// Get the user's home directory
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from).and_then(|home| home.canonicalize().ok())
+}
+
+/// One file an operation changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A new file
+    Create(PathBuf),
+    /// An existing file that is overwritten
+    Replace(PathBuf),
+    /// A file or directory that is removed
+    Remove(PathBuf),
+}
+
+impl Change {
+    /// Describe the change on one line
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Create(path) => format!("+ {}", path.display()),
+            Change::Replace(path) => format!("~ {}", path.display()),
+            Change::Remove(path) => format!("- {}", path.display()),
+        }
+    }
+}
+
+/// What an operation would do, from a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preview {
+    /// Every file the operation changes
+    pub changes: Vec<Change>,
+}
+
+impl Preview {
+    /// Count the files created, replaced and removed
+    pub fn counts(&self) -> (usize, usize, usize) {
+        self.changes.iter().fold((0, 0, 0), |(created, replaced, removed), change| match change {
+            Change::Create(_) => (created + 1, replaced, removed),
+            Change::Replace(_) => (created, replaced + 1, removed),
+            Change::Remove(_) => (created, replaced, removed + 1),
+        })
+    }
+
+    /// Describe the preview: the counts, then up to `limit` changes
+    pub fn describe(&self, limit: usize) -> String {
+        let (created, replaced, removed) = self.counts();
+        let mut lines = vec![format!("{} created, {} replaced, {} removed", created, replaced, removed)];
+        lines.extend(self.changes.iter().take(limit).map(Change::describe));
+        if self.changes.len() > limit {
+            lines.push(format!("… and {} more", self.changes.len() - limit));
+        }
+        lines.join("\n")
+    }
+}
+
+/// A change to the filesystem that is previewed and confirmed before it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOperation {
+    /// Write `contents` to `path`, replacing any file there
+    Write { path: PathBuf, contents: Vec<u8> },
+    /// Copy files or directories; into `destination` when it is a directory
+    Copy { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Move files or directories; into `destination` when it is a directory
+    Move { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Delete files or directories for good
+    Delete { paths: Vec<PathBuf> },
+}
+
+impl FileOperation {
+    /// Build an operation with every path resolved from `working_dir` and checked
+    pub fn resolved(self, working_dir: &Path) -> Result<Self> {
+        let all = |paths: Vec<PathBuf>| -> Result<Vec<PathBuf>> {
+            if paths.is_empty() {
+                bail!("no paths given");
+            }
+            paths.iter().map(|path| resolve(path, working_dir)).collect()
+        };
+        Ok(match self {
+            FileOperation::Write { path, contents } => FileOperation::Write { path: resolve(&path, working_dir)?, contents },
+            FileOperation::Copy { sources, destination } => FileOperation::Copy {
+                sources: all(sources)?,
+                destination: resolve(&destination, working_dir)?,
+            },
+            FileOperation::Move { sources, destination } => FileOperation::Move {
+                sources: all(sources)?,
+                destination: resolve(&destination, working_dir)?,
+            },
+            FileOperation::Delete { paths } => FileOperation::Delete { paths: all(paths)? },
+        })
+    }
+
+    /// Name of the operation, for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileOperation::Write { .. } => "Write",
+            FileOperation::Copy { .. } => "Copy",
+            FileOperation::Move { .. } => "Move",
+            FileOperation::Delete { .. } => "Delete",
+        }
+    }
+
+    /// List what the operation would change, without changing anything
+    pub fn preview(&self) -> Result<Preview> {
+        let mut changes = Vec::new();
+        match self {
+            FileOperation::Write { path, .. } => {
+                if path.is_dir() {
+                    bail!("{} is a directory", path.display());
+                }
+                changes.push(if path.exists() { Change::Replace(path.clone()) } else { Change::Create(path.clone()) });
+            }
+            FileOperation::Copy { sources, destination } | FileOperation::Move { sources, destination } => {
+                for (source, target) in self.targets(sources, destination)? {
+                    if target.starts_with(&source) {
+                        bail!("cannot put {} inside itself", source.display());
+                    }
+                    for (from, to) in file_pairs(&source, &target)? {
+                        changes.push(if to.exists() { Change::Replace(to) } else { Change::Create(to) });
+                        if matches!(self, FileOperation::Move { .. }) {
+                            changes.push(Change::Remove(from));
+                        }
+                    }
+                }
+            }
+            FileOperation::Delete { paths } => {
+                for path in paths {
+                    existing(path)?;
+                    changes.extend(files_under(path)?.into_iter().map(Change::Remove));
+                }
+            }
+        }
+        Ok(Preview { changes })
+    }
+
+    /// Carry out the operation, returning what it changed
+    pub fn apply(&self) -> Result<Preview> {
+        let preview = self.preview()?;
+        match self {
+            FileOperation::Write { path, contents } => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, contents).with_context(|| format!("could not write {}", path.display()))?;
+            }
+            FileOperation::Copy { sources, destination } => {
+                for (source, target) in self.targets(sources, destination)? {
+                    copy_recursive(&source, &target)?;
+                }
+            }
+            FileOperation::Move { sources, destination } => {
+                for (source, target) in self.targets(sources, destination)? {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    // Renaming fails across filesystems; copy and remove instead
+                    if fs::rename(&source, &target).is_err() {
+                        copy_recursive(&source, &target)?;
+                        remove(&source)?;
+                    }
+                }
+            }
+            FileOperation::Delete { paths } => {
+                for path in paths {
+                    remove(path)?;
+                }
+            }
+        }
+        Ok(preview)
+    }
+
+    /// Pair each source with where it ends up
+    fn targets(&self, sources: &[PathBuf], destination: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+        if sources.len() > 1 && !destination.is_dir() {
+            bail!("{} must be an existing directory to take several files", destination.display());
+        }
+        sources
+            .iter()
+            .map(|source| {
+                existing(source)?;
+                let target = match source.file_name() {
+                    Some(name) if destination.is_dir() => destination.join(name),
+                    _ => destination.to_path_buf(),
+                };
+                Ok((source.clone(), target))
+            })
+            .collect()
+    }
+}
+
+/// Fail unless a path exists, without following symlinks
+fn existing(path: &Path) -> Result<()> {
+    fs::symlink_metadata(path).map(|_| ()).with_context(|| format!("{} does not exist", path.display()))
+}
+
+/// List the files under a path, or the path itself when it is not a directory
+fn files_under(path: &Path) -> Result<Vec<PathBuf>> {
+    if !fs::symlink_metadata(path)?.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<_, _>>()?;
+    entries.sort();
+    for entry in entries {
+        files.extend(files_under(&entry)?);
+    }
+    if files.is_empty() {
+        files.push(path.to_path_buf());
+    }
+    Ok(files)
+}
+
+/// Pair each file under `source` with its place under `target`
+fn file_pairs(source: &Path, target: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    Ok(files_under(source)?
+        .into_iter()
+        .map(|file| {
+            let relative = file.strip_prefix(source).unwrap_or(Path::new(""));
+            let to = if relative.as_os_str().is_empty() { target.to_path_buf() } else { target.join(relative) };
+            (file, to)
+        })
+        .collect())
+}
+
+/// Copy a file or a directory tree, recreating symlinks rather than following them
+fn copy_recursive(source: &Path, target: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(source)?;
+    if metadata.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    #[cfg(unix)]
+    if metadata.file_type().is_symlink() {
+        let _ = fs::remove_file(target);
+        std::os::unix::fs::symlink(fs::read_link(source)?, target)
+            .with_context(|| format!("could not copy {}", source.display()))?;
+        return Ok(());
+    }
+    fs::copy(source, target).with_context(|| format!("could not copy {} to {}", source.display(), target.display()))?;
+    Ok(())
+}
+
+/// Remove a file or a directory tree
+fn remove(path: &Path) -> Result<()> {
+    let result = if fs::symlink_metadata(path)?.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+    result.with_context(|| format!("could not delete {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-file-ops-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_protected_paths() {
+        let dir = temp_dir("protected");
+        assert!(resolve(Path::new("/"), &dir).is_err());
+        assert!(resolve(Path::new("/etc/passwd"), &dir).is_err());
+        // `..` cannot climb out into a protected directory
+        assert!(resolve(Path::new("missing/../../../../../../../usr/bin/ls"), &dir).is_err());
+        if let Some(home) = home_dir() {
+            assert!(resolve(&home, &dir).is_err());
+            assert!(resolve(&home.join(".ssh/authorized_keys"), &dir).is_err());
+        }
+        assert_eq!(resolve(Path::new("new/file.txt"), &dir).unwrap(), dir.join("new/file.txt"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_preview_then_apply() {
+        let dir = temp_dir("apply");
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::write(dir.join("src/a.txt"), "a").unwrap();
+        fs::write(dir.join("src/nested/b.txt"), "b").unwrap();
+        fs::write(dir.join("notes.txt"), "old").unwrap();
+
+        let write = FileOperation::Write { path: "notes.txt".into(), contents: b"new".to_vec() }.resolved(&dir).unwrap();
+        assert_eq!(write.preview().unwrap().changes, vec![Change::Replace(dir.join("notes.txt"))]);
+        write.apply().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("notes.txt")).unwrap(), "new");
+
+        let copy = FileOperation::Copy { sources: vec!["src".into()], destination: "copy".into() }.resolved(&dir).unwrap();
+        // The dry run changes nothing
+        assert_eq!(copy.preview().unwrap().counts(), (2, 0, 0));
+        assert!(!dir.join("copy").exists());
+        copy.apply().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("copy/nested/b.txt")).unwrap(), "b");
+
+        fs::create_dir(dir.join("dest")).unwrap();
+        let moved = FileOperation::Move { sources: vec!["notes.txt".into(), "copy".into()], destination: "dest".into() }
+            .resolved(&dir)
+            .unwrap();
+        assert_eq!(moved.preview().unwrap().counts(), (3, 0, 3));
+        moved.apply().unwrap();
+        assert!(dir.join("dest/copy/a.txt").exists());
+        assert!(!dir.join("notes.txt").exists());
+
+        let into_itself = FileOperation::Copy { sources: vec!["src".into()], destination: "src/inner".into() }.resolved(&dir).unwrap();
+        assert!(into_itself.preview().is_err());
+
+        let delete = FileOperation::Delete { paths: vec!["dest".into()] }.resolved(&dir).unwrap();
+        assert!(delete.preview().unwrap().describe(1).starts_with("0 created, 0 replaced, 3 removed\n- "));
+        delete.apply().unwrap();
+        assert!(!dir.join("dest").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
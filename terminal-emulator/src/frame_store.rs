@@ -0,0 +1,492 @@
+//! Compressed record storage for the AI Terminal
+//!
+//! Sessions, scrollback spill files and LLM logs are stored as a sequence of
+//! zstd frames, one per record, so files stay small and any record can be
+//! decompressed on its own. A sidecar index of frame offsets gives random
+//! access without scanning; it is rebuilt automatically when missing or stale.
+//!
+//! Files written in an earlier JSON format (a JSON array or JSON lines) are
+//! migrated in place when opened; the original is kept with a `.bak` suffix.
+//!
+//! Writers hold an advisory [`FileLock`] and pick up records appended by
+//! other instances first, so several terminals can share one file.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::file_lock::FileLock;
+
+/// Magic bytes at the start of a record file
+const DATA_MAGIC: &[u8; 4] = b"AITZ";
+/// Magic bytes at the start of an index file
+const INDEX_MAGIC: &[u8; 4] = b"AITI";
+/// Current format version
+const FORMAT_VERSION: u8 = 1;
+/// Length of the record file header
+const DATA_HEADER_LEN: u64 = 5;
+/// Length of the index file header (magic, version, indexed data length)
+const INDEX_HEADER_LEN: u64 = 13;
+/// zstd compression level used for new frames
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// An append-only file of zstd-compressed records
+pub struct FrameStore<T> {
+    /// Path to the record file
+    path: PathBuf,
+    /// Byte offset of every record in the file
+    offsets: Vec<u64>,
+    /// Current length of the record file
+    data_len: u64,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> FrameStore<T> {
+    /// Open a record file, creating it (or migrating a JSON file) if needed
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut store = Self {
+            path,
+            offsets: Vec::new(),
+            data_len: DATA_HEADER_LEN,
+            _record: PhantomData,
+        };
+
+        let _lock = FileLock::exclusive(&store.path)?;
+        if !store.path.exists() {
+            store.write_records(&[])?;
+        } else if !has_magic(&store.path, DATA_MAGIC)? {
+            store.migrate_json()?;
+        } else {
+            store.data_len = fs::metadata(&store.path)?.len();
+            if !store.load_index()? {
+                store.rebuild_index()?;
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Get the path to the record file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of records
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Check whether the store has no records
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Append a record to the end of the file
+    pub fn append(&mut self, record: &T) -> Result<()> {
+        let frame = encode(record)?;
+
+        let _lock = FileLock::exclusive(&self.path)?;
+        self.sync()?;
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(&frame)?;
+        file.flush()?;
+
+        let offset = self.data_len;
+        self.data_len += frame.len() as u64;
+        self.offsets.push(offset);
+
+        // Keep the index in step; a failure here only costs a rescan later
+        if let Err(e) = self.append_to_index(offset) {
+            tracing::warn!("Failed to update index for {}: {:?}", self.path.display(), e);
+        }
+        Ok(())
+    }
+
+    /// Read a single record by position
+    pub fn get(&self, index: usize) -> Result<Option<T>> {
+        let Some(&offset) = self.offsets.get(index) else {
+            return Ok(None);
+        };
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        read_frame(&mut file).map(Some)
+    }
+
+    /// Read a contiguous range of records
+    pub fn read_range(&self, range: Range<usize>) -> Result<Vec<T>> {
+        let end = range.end.min(self.offsets.len());
+        let Some(&offset) = self.offsets.get(range.start).filter(|_| range.start < end) else {
+            return Ok(Vec::new());
+        };
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+        (range.start..end).map(|_| read_frame(&mut reader)).collect()
+    }
+
+    /// Read every record
+    pub fn read_all(&self) -> Result<Vec<T>> {
+        self.read_range(0..self.offsets.len())
+    }
+
+    /// Replace the contents of the store atomically
+    pub fn replace_all(&mut self, records: &[T]) -> Result<()> {
+        let _lock = FileLock::exclusive(&self.path)?;
+        self.write_records(records)
+    }
+
+    /// Pick up records written through other handles since this one last looked
+    pub fn refresh(&mut self) -> Result<()> {
+        let _lock = FileLock::shared(&self.path)?;
+        self.sync()
+    }
+
+    /// Reload the offsets if the record file changed; the caller holds the lock
+    fn sync(&mut self) -> Result<()> {
+        let data_len = fs::metadata(&self.path)?.len();
+        if data_len == self.data_len {
+            return Ok(());
+        }
+
+        self.data_len = data_len;
+        if !self.load_index()? {
+            self.rebuild_index()?;
+        }
+        Ok(())
+    }
+
+    /// Write a fresh record file and index through a temporary file
+    fn write_records(&mut self, records: &[T]) -> Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+        let mut offsets = Vec::with_capacity(records.len());
+        let mut data_len = DATA_HEADER_LEN;
+
+        {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            writer.write_all(DATA_MAGIC)?;
+            writer.write_all(&[FORMAT_VERSION])?;
+            for record in records {
+                let frame = encode(record)?;
+                writer.write_all(&frame)?;
+                offsets.push(data_len);
+                data_len += frame.len() as u64;
+            }
+            writer.flush()?;
+        }
+
+        fs::rename(&temp_path, &self.path)?;
+        self.offsets = offsets;
+        self.data_len = data_len;
+        self.write_index()
+    }
+
+    /// Convert a file in an earlier JSON format into frames
+    fn migrate_json(&mut self) -> Result<()> {
+        let text = fs::read_to_string(&self.path)
+            .with_context(|| format!("{} is neither a record file nor JSON", self.path.display()))?;
+
+        let records: Vec<T> = if text.trim_start().starts_with('[') {
+            serde_json::from_str(&text)?
+        } else {
+            text.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut backup = self.path.clone().into_os_string();
+        backup.push(".bak");
+        fs::copy(&self.path, &backup)?;
+
+        tracing::info!("Migrating {} records in {} to compressed frames", records.len(), self.path.display());
+        self.write_records(&records)
+    }
+
+    /// Get the path to the sidecar index
+    fn index_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Load offsets from the index if it matches the record file
+    fn load_index(&mut self) -> Result<bool> {
+        let Ok(bytes) = fs::read(self.index_path()) else {
+            return Ok(false);
+        };
+        if bytes.len() < INDEX_HEADER_LEN as usize
+            || &bytes[..4] != INDEX_MAGIC
+            || bytes[4] != FORMAT_VERSION
+            || !(bytes.len() - INDEX_HEADER_LEN as usize).is_multiple_of(8)
+        {
+            return Ok(false);
+        }
+
+        let indexed_len = u64::from_le_bytes(bytes[5..13].try_into()?);
+        if indexed_len != self.data_len {
+            return Ok(false);
+        }
+
+        self.offsets = bytes[INDEX_HEADER_LEN as usize..]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes")))
+            .collect();
+        Ok(true)
+    }
+
+    /// Recover offsets by walking the frames, then rewrite the index
+    fn rebuild_index(&mut self) -> Result<()> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(DATA_HEADER_LEN))?;
+
+        self.offsets.clear();
+        let mut offset = DATA_HEADER_LEN;
+        let mut len_bytes = [0u8; 4];
+        while offset + 4 <= self.data_len {
+            reader.read_exact(&mut len_bytes)?;
+            let frame_len = u32::from_le_bytes(len_bytes) as u64;
+            if offset + 4 + frame_len > self.data_len {
+                // A torn write at the end of the file; drop the partial record
+                tracing::warn!("Ignoring truncated record at end of {}", self.path.display());
+                break;
+            }
+            self.offsets.push(offset);
+            reader.seek_relative(frame_len as i64)?;
+            offset += 4 + frame_len;
+        }
+
+        if offset != self.data_len {
+            OpenOptions::new().write(true).open(&self.path)?.set_len(offset)?;
+            self.data_len = offset;
+        }
+        self.write_index()
+    }
+
+    /// Write the whole index file
+    fn write_index(&self) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(self.index_path())?);
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&self.data_len.to_le_bytes())?;
+        for offset in &self.offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Record a newly appended frame in the index
+    fn append_to_index(&self, offset: u64) -> Result<()> {
+        let mut file = OpenOptions::new().write(true).open(self.index_path())?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&offset.to_le_bytes())?;
+        file.seek(SeekFrom::Start(5))?;
+        file.write_all(&self.data_len.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// Delete a record file along with its index and lock companions
+pub fn remove(path: &Path) -> Result<()> {
+    for companion in ["", ".idx", ".lock"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(companion);
+        match fs::remove_file(&file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Check whether a file starts with the given magic bytes
+fn has_magic(path: &Path, magic: &[u8; 4]) -> Result<bool> {
+    let mut header = [0u8; 4];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == magic),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Serialize and compress a record into a length-prefixed frame
+fn encode<T: Serialize>(record: &T) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(record)?;
+    let compressed = zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)?;
+    let Ok(len) = u32::try_from(compressed.len()) else {
+        bail!("record of {} bytes is too large", compressed.len());
+    };
+
+    let mut frame = Vec::with_capacity(4 + compressed.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(&compressed);
+    Ok(frame)
+}
+
+/// Read and decode the frame at the reader's position
+fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+
+    let mut compressed = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    reader.read_exact(&mut compressed)?;
+
+    let json = zstd::decode_all(compressed.as_slice())?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        id: u32,
+        text: String,
+    }
+
+    fn entry(id: u32) -> Entry {
+        Entry { id, text: format!("record number {}", id).repeat(10) }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-frames-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("store.aitz")
+    }
+
+    #[test]
+    fn test_handles_share_appends() {
+        let path = temp_path("shared");
+        let mut first = FrameStore::<Entry>::open(&path).unwrap();
+        let mut second = FrameStore::<Entry>::open(&path).unwrap();
+
+        first.append(&entry(1)).unwrap();
+        second.append(&entry(2)).unwrap();
+        first.append(&entry(3)).unwrap();
+
+        let ids: Vec<u32> = first.read_all().unwrap().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        second.refresh().unwrap();
+        assert_eq!(second.len(), 3);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_append_and_random_access() {
+        let path = temp_path("append");
+        let mut store = FrameStore::open(&path).unwrap();
+        for id in 0..5 {
+            store.append(&entry(id)).unwrap();
+        }
+
+        assert_eq!(store.len(), 5);
+        assert_eq!(store.get(3).unwrap(), Some(entry(3)));
+        assert_eq!(store.get(5).unwrap(), None);
+        assert_eq!(store.read_range(1..3).unwrap(), vec![entry(1), entry(2)]);
+
+        // Reopening uses the index written during appends
+        let reopened: FrameStore<Entry> = FrameStore::open(&path).unwrap();
+        assert_eq!(reopened.read_all().unwrap(), (0..5).map(entry).collect::<Vec<_>>());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_records_are_compressed() {
+        let path = temp_path("compressed");
+        let mut store = FrameStore::open(&path).unwrap();
+        let big = Entry { id: 1, text: "abc".repeat(10_000) };
+        store.append(&big).unwrap();
+
+        assert!(fs::metadata(&path).unwrap().len() < 1_000);
+        assert_eq!(store.get(0).unwrap(), Some(big));
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_stale_index_is_rebuilt() {
+        let path = temp_path("rebuild");
+        let mut store = FrameStore::open(&path).unwrap();
+        store.append(&entry(1)).unwrap();
+        store.append(&entry(2)).unwrap();
+        fs::remove_file(store.index_path()).unwrap();
+
+        // Simulate a torn write after the last record
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2]).unwrap();
+
+        let mut reopened: FrameStore<Entry> = FrameStore::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        reopened.append(&entry(3)).unwrap();
+        assert_eq!(reopened.get(2).unwrap(), Some(entry(3)));
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_migrates_json_formats() {
+        let array_path = temp_path("json-array");
+        fs::create_dir_all(array_path.parent().unwrap()).unwrap();
+        fs::write(&array_path, serde_json::to_string(&vec![entry(1), entry(2)]).unwrap()).unwrap();
+        let store: FrameStore<Entry> = FrameStore::open(&array_path).unwrap();
+        assert_eq!(store.read_all().unwrap(), vec![entry(1), entry(2)]);
+        assert!(array_path.with_extension("aitz.bak").exists());
+
+        let lines_path = temp_path("json-lines");
+        fs::create_dir_all(lines_path.parent().unwrap()).unwrap();
+        let lines = format!("{}\n\n{}\n", serde_json::to_string(&entry(7)).unwrap(), serde_json::to_string(&entry(8)).unwrap());
+        fs::write(&lines_path, lines).unwrap();
+        let store: FrameStore<Entry> = FrameStore::open(&lines_path).unwrap();
+        assert_eq!(store.read_all().unwrap(), vec![entry(7), entry(8)]);
+
+        let _ = fs::remove_dir_all(array_path.parent().unwrap());
+        let _ = fs::remove_dir_all(lines_path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let path = temp_path("replace");
+        let mut store = FrameStore::open(&path).unwrap();
+        store.append(&entry(1)).unwrap();
+        store.replace_all(&[entry(4), entry(5)]).unwrap();
+        assert_eq!(store.read_all().unwrap(), vec![entry(4), entry(5)]);
+
+        let reopened: FrameStore<Entry> = FrameStore::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_remove_deletes_companions() {
+        let path = temp_path("remove");
+        let mut store = FrameStore::open(&path).unwrap();
+        store.append(&entry(1)).unwrap();
+        let dir = path.parent().unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 3);
+
+        remove(&path).unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 0);
+        remove(&path).unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+}
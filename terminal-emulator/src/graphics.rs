@@ -0,0 +1,295 @@
+//! Inline graphics in command output
+//!
+//! Programs that draw plots or previews write images into the terminal with
+//! one of three protocols: sixel (a DCS sequence ending in `q`), kitty's
+//! graphics protocol (APC sequences starting with `G`) and iTerm2's inline
+//! files (OSC 1337). The PTY reader runs its output through a
+//! [`GraphicsFilter`], which takes these sequences out before the escape
+//! codes are stripped, so the images reach the block instead of being lost.
+//! Kitty transfers split into chunks are joined into one sequence, and its
+//! queries and deletions are dropped, as there is no image store to answer
+//! them from.
+//!
+//! Builds without the `graphics` feature pass the sequences through, to be
+//! stripped with the other escape codes.
+
+use serde::{Deserialize, Serialize};
+
+/// Escape byte starting every sequence
+#[cfg(feature = "graphics")]
+const ESC: u8 = 0x1b;
+
+/// Bell byte, which also ends OSC sequences
+#[cfg(feature = "graphics")]
+const BEL: u8 = 0x07;
+
+/// Largest graphics sequence kept; bigger ones are dropped
+pub const MAX_SEQUENCE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Longest introducer checked before a sequence is known not to be graphics
+#[cfg(feature = "graphics")]
+const MAX_INTRODUCER: usize = 32;
+
+/// Protocol an image was written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageProtocol {
+    /// DEC sixel graphics
+    Sixel,
+    /// The kitty graphics protocol
+    Kitty,
+    /// iTerm2 inline files
+    Iterm2,
+}
+
+impl ImageProtocol {
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageProtocol::Sixel => "sixel",
+            ImageProtocol::Kitty => "kitty",
+            ImageProtocol::Iterm2 => "iTerm2",
+        }
+    }
+}
+
+/// An image written by a command, as the escape sequence that carried it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphicsSequence {
+    /// Protocol of the sequence
+    pub protocol: ImageProtocol,
+    /// The whole sequence, introducer and terminator included; kitty chunks follow one another
+    pub sequence: String,
+}
+
+/// What the bytes at an escape start
+#[cfg(feature = "graphics")]
+enum Introducer {
+    /// Too few bytes to tell yet
+    Partial,
+    /// Something other than graphics
+    Other,
+    /// A graphics sequence
+    Graphics(ImageProtocol),
+}
+
+/// Takes graphics sequences out of a stream of terminal output
+#[cfg(feature = "graphics")]
+#[derive(Debug, Default)]
+pub struct GraphicsFilter {
+    /// Bytes of an unfinished sequence, or of an escape too short to tell
+    pending: Vec<u8>,
+    /// How much of `pending` was searched for a terminator already
+    searched: usize,
+    /// Protocol of an oversized sequence being skipped to its end
+    skipping: Option<ImageProtocol>,
+    /// Chunks of an unfinished kitty transfer
+    kitty: String,
+}
+
+#[cfg(feature = "graphics")]
+impl GraphicsFilter {
+    /// Create a filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed output, returning the bytes that are not graphics and the graphics sequences completed
+    pub fn feed(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<GraphicsSequence>) {
+        let mut passed = Vec::with_capacity(bytes.len());
+        let mut found = Vec::new();
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(bytes);
+        let mut at = 0;
+
+        if let Some(protocol) = self.skipping {
+            match find_terminator(&data, 0, protocol) {
+                Some(end) => {
+                    self.skipping = None;
+                    at = end;
+                }
+                // Keep a trailing escape, which may start the terminator
+                None => {
+                    if data.last() == Some(&ESC) {
+                        self.pending.push(ESC);
+                    }
+                    return (passed, found);
+                }
+            }
+        }
+
+        while at < data.len() {
+            let Some(offset) = data[at..].iter().position(|&byte| byte == ESC) else {
+                passed.extend_from_slice(&data[at..]);
+                break;
+            };
+            let start = at + offset;
+            passed.extend_from_slice(&data[at..start]);
+            match introducer(&data[start..]) {
+                Introducer::Partial => {
+                    self.pending = data[start..].to_vec();
+                    break;
+                }
+                Introducer::Other => {
+                    passed.push(ESC);
+                    at = start + 1;
+                }
+                Introducer::Graphics(protocol) => {
+                    let from = start + self.searched.max(2);
+                    match find_terminator(&data, from, protocol) {
+                        Some(end) => {
+                            self.searched = 0;
+                            let sequence = String::from_utf8_lossy(&data[start..end]).into_owned();
+                            found.extend(self.complete(protocol, sequence));
+                            at = end;
+                        }
+                        None if data.len() - start > MAX_SEQUENCE_BYTES => {
+                            self.searched = 0;
+                            self.skipping = Some(protocol);
+                            break;
+                        }
+                        None => {
+                            // The last byte may be the escape of the terminator, so it is searched again
+                            self.searched = data.len() - start - 1;
+                            self.pending = data[start..].to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        (passed, found)
+    }
+
+    /// Finish the stream, returning the bytes held back that were not graphics
+    pub fn finish(self) -> Vec<u8> {
+        match introducer(&self.pending) {
+            Introducer::Graphics(_) => Vec::new(),
+            _ => self.pending,
+        }
+    }
+
+    /// Turn a finished sequence into an image, joining kitty chunks and dropping kitty commands that show nothing
+    fn complete(&mut self, protocol: ImageProtocol, sequence: String) -> Option<GraphicsSequence> {
+        if protocol != ImageProtocol::Kitty {
+            return Some(GraphicsSequence { protocol, sequence });
+        }
+        let keys = kitty_keys(&sequence);
+        let first = self.kitty.is_empty();
+        if first && !keys.iter().any(|(key, value)| *key == "a" && *value == "T") {
+            return None;
+        }
+        self.kitty.push_str(&sequence);
+        if keys.iter().any(|(key, value)| *key == "m" && *value == "1") {
+            return None;
+        }
+        Some(GraphicsSequence { protocol, sequence: std::mem::take(&mut self.kitty) })
+    }
+}
+
+/// Stand-in for builds without the `graphics` feature, which takes nothing out
+#[cfg(not(feature = "graphics"))]
+#[derive(Debug, Default)]
+pub struct GraphicsFilter;
+
+#[cfg(not(feature = "graphics"))]
+impl GraphicsFilter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<GraphicsSequence>) {
+        (bytes.to_vec(), Vec::new())
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+/// Tell whether the bytes at an escape start a graphics sequence
+#[cfg(feature = "graphics")]
+fn introducer(bytes: &[u8]) -> Introducer {
+    const ITERM2: &[u8] = b"\x1b]1337;File=";
+    const KITTY: &[u8] = b"\x1b_G";
+    for (prefix, protocol) in [(ITERM2, ImageProtocol::Iterm2), (KITTY, ImageProtocol::Kitty)] {
+        if bytes.starts_with(prefix) {
+            return Introducer::Graphics(protocol);
+        }
+        if prefix.starts_with(bytes) {
+            return Introducer::Partial;
+        }
+    }
+    // Sixel: ESC P, numeric parameters, then q
+    if let Some(rest) = bytes.strip_prefix(b"\x1bP") {
+        return match rest.iter().position(|byte| !(byte.is_ascii_digit() || *byte == b';')) {
+            Some(end) if rest[end] == b'q' => Introducer::Graphics(ImageProtocol::Sixel),
+            Some(_) => Introducer::Other,
+            None if bytes.len() < MAX_INTRODUCER => Introducer::Partial,
+            None => Introducer::Other,
+        };
+    }
+    if bytes == [ESC] { Introducer::Partial } else { Introducer::Other }
+}
+
+/// Find the end of a sequence, just past its terminator, searching from `from`
+#[cfg(feature = "graphics")]
+fn find_terminator(data: &[u8], from: usize, protocol: ImageProtocol) -> Option<usize> {
+    let from = from.min(data.len());
+    data[from..].iter().enumerate().find_map(|(i, &byte)| match byte {
+        BEL if protocol == ImageProtocol::Iterm2 => Some(from + i + 1),
+        ESC if data.get(from + i + 1) == Some(&b'\\') => Some(from + i + 2),
+        _ => None,
+    })
+}
+
+/// Read the control keys of a kitty graphics sequence, as in `a=T,f=100`
+pub fn kitty_keys(sequence: &str) -> Vec<(&str, &str)> {
+    let Some(control) = sequence.strip_prefix("\x1b_G") else {
+        return Vec::new();
+    };
+    let control = control.split([';', '\x1b']).next().unwrap_or_default();
+    control.split(',').filter_map(|pair| pair.split_once('=')).collect()
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences_are_taken_out() {
+        let mut filter = GraphicsFilter::new();
+        let (passed, found) = filter.feed(b"plot:\x1bPq#0;2;0;0;0~-\x1b\\done\x1b[1mbold\n");
+        assert_eq!(passed, b"plot:done\x1b[1mbold\n");
+        assert_eq!(found[0].protocol, ImageProtocol::Sixel);
+        assert_eq!(found[0].sequence, "\x1bPq#0;2;0;0;0~-\x1b\\");
+
+        let (passed, found) = filter.feed(b"a\x1b]1337;File=inline=1:AAAA\x07b\x1b]0;title\x07");
+        assert_eq!(passed, b"ab\x1b]0;title\x07");
+        assert_eq!(found[0].protocol, ImageProtocol::Iterm2);
+        assert!(filter.finish().is_empty());
+    }
+
+    #[test]
+    fn test_split_sequences_and_kitty_chunks() {
+        let mut filter = GraphicsFilter::new();
+        let whole = b"x\x1b_Ga=T,f=100,m=1;AAAA\x1b\\\x1b_Gm=0;BBBB\x1b\\y\x1b_Ga=q,i=1;AAAA\x1b\\z";
+        let mut passed = Vec::new();
+        let mut found = Vec::new();
+        // Fed a few bytes at a time, as a PTY may hand it over
+        for chunk in whole.chunks(3) {
+            let (bytes, images) = filter.feed(chunk);
+            passed.extend(bytes);
+            found.extend(images);
+        }
+        assert_eq!(passed, b"xyz");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].sequence, "\x1b_Ga=T,f=100,m=1;AAAA\x1b\\\x1b_Gm=0;BBBB\x1b\\");
+        assert_eq!(kitty_keys(&found[0].sequence), [("a", "T"), ("f", "100"), ("m", "1")]);
+
+        let mut filter = GraphicsFilter::new();
+        let (passed, _) = filter.feed(b"end\x1b");
+        assert_eq!(passed, b"end");
+        assert_eq!(filter.finish(), b"\x1b");
+    }
+}
@@ -0,0 +1,484 @@
+//! Command impact snapshots for the AI Terminal
+//!
+//! Commands that modify the filesystem (`rm`, `mv`, `chmod`, overwriting
+//! redirections, ...) are recognised from their command line. Before such a
+//! command runs, the metadata of its target paths is captured, together with
+//! the contents of small files; afterwards the same paths are captured again
+//! and the difference is stored on the block as an [`ImpactReport`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+/// Programs whose purpose is to modify files
+const MODIFYING_PROGRAMS: &[&str] = &[
+    "rm", "rmdir", "unlink", "shred", "mv", "cp", "ln", "install", "rsync", "chmod", "chown", "chgrp",
+    "truncate", "dd",
+];
+
+/// Programs whose first operand is not a path (a mode or an owner)
+const SKIP_FIRST_OPERAND: &[&str] = &["chmod", "chown", "chgrp"];
+
+/// Settings for impact snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ImpactConfig {
+    /// Ask before running modifying commands and record what they change
+    pub enabled: bool,
+    /// Maximum number of filesystem entries captured per snapshot
+    pub max_entries: usize,
+    /// Files up to this size have their contents captured
+    pub max_content_bytes: u64,
+}
+
+impl Default for ImpactConfig {
+    fn default() -> Self {
+        Self { enabled: true, max_entries: 1000, max_content_bytes: 64 * 1024 }
+    }
+}
+
+/// Split a command line into simple commands, each as a list of words
+fn simple_commands(command: &str) -> Vec<Vec<String>> {
+    command
+        .split(['\n', ';', '&', '|'])
+        .filter(|segment| !segment.trim().is_empty())
+        .map(|segment| shlex::split(segment).unwrap_or_else(|| segment.split_whitespace().map(str::to_string).collect()))
+        .map(|words| {
+            // Skip wrappers and variable assignments in front of the program
+            let start = words
+                .iter()
+                .position(|word| !matches!(word.as_str(), "sudo" | "doas" | "env" | "command" | "nohup") && !is_assignment(word))
+                .unwrap_or(words.len());
+            words[start..].to_vec()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+/// Check whether a word is a `NAME=value` assignment
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Get the program name of a simple command
+fn program(words: &[String]) -> &str {
+    words[0].rsplit('/').next().unwrap_or(&words[0])
+}
+
+/// Check whether the program of a simple command modifies its operands
+fn modifies_operands(words: &[String]) -> bool {
+    let program = program(words);
+    MODIFYING_PROGRAMS.contains(&program)
+        || (program == "sed" && words.iter().any(|word| word.starts_with("-i") || word == "--in-place"))
+        || (program == "find" && words.iter().any(|word| word == "-delete"))
+}
+
+/// Check whether a simple command modifies files
+fn modifies_files(words: &[String]) -> bool {
+    modifies_operands(words) || redirect_targets(words).next().is_some()
+}
+
+/// Iterate over the files a simple command overwrites with `>`
+fn redirect_targets(words: &[String]) -> impl Iterator<Item = &str> {
+    words.iter().enumerate().filter_map(|(i, word)| {
+        let target = match word.as_str() {
+            ">" | "1>" | "2>" => words.get(i + 1).map(String::as_str),
+            _ => word.strip_prefix('>').filter(|rest| !rest.starts_with(['>', '&'])),
+        };
+        target.filter(|path| !path.is_empty() && *path != "/dev/null")
+    })
+}
+
+/// Check whether a command line modifies files and deserves a snapshot
+pub fn is_high_risk(command: &str) -> bool {
+    simple_commands(command).iter().any(|words| modifies_files(words))
+}
+
+/// Get the paths a command line may modify, resolved against `working_dir`
+///
+/// Operands containing glob characters are replaced by their directory,
+/// since the shell expands them only when the command runs.
+pub fn target_paths(command: &str, working_dir: &Path) -> Vec<PathBuf> {
+    let mut targets: Vec<PathBuf> = Vec::new();
+    for words in simple_commands(command).iter().filter(|words| modifies_files(words)) {
+        let program = program(words);
+        let mut operands: Vec<&str> = Vec::new();
+        let mut redirect = false;
+        let own_operands = if modifies_operands(words) { &words[1..] } else { &[] };
+        for word in own_operands {
+            if redirect {
+                redirect = false;
+                continue;
+            }
+            if word.contains(['>', '<']) {
+                // Overwritten files are added from `redirect_targets`; a bare operator takes the next word
+                redirect = word.ends_with(['>', '<']);
+                continue;
+            }
+            if program == "dd" {
+                operands.extend(word.strip_prefix("of="));
+            } else if program == "find" && word.starts_with('-') {
+                // Everything after the first expression is not a starting point
+                break;
+            } else if !word.starts_with('-') {
+                operands.push(word);
+            }
+        }
+        if SKIP_FIRST_OPERAND.contains(&program) || (program == "sed" && !words.iter().any(|word| word == "-e" || word == "-f")) {
+            // The mode, owner or sed script
+            operands.drain(..operands.len().min(1));
+        }
+        operands.extend(redirect_targets(words));
+
+        for operand in operands {
+            let operand = if operand == "~" || operand.starts_with("~/") {
+                match std::env::var_os("HOME") {
+                    Some(home) => Path::new(&home).join(operand.trim_start_matches('~').trim_start_matches('/')),
+                    None => PathBuf::from(operand),
+                }
+            } else {
+                PathBuf::from(operand)
+            };
+            let mut path = working_dir.join(operand);
+            if path.to_string_lossy().contains(['*', '?', '[']) {
+                path = path
+                    .ancestors()
+                    .find(|dir| !dir.to_string_lossy().contains(['*', '?', '[']))
+                    .unwrap_or(working_dir)
+                    .to_path_buf();
+            }
+            if !targets.contains(&path) {
+                targets.push(path);
+            }
+        }
+    }
+    targets
+}
+
+/// The kind of a filesystem entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// The captured state of one filesystem entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EntryState {
+    /// What the entry is
+    kind: EntryKind,
+    /// Size in bytes
+    size: u64,
+    /// Permission bits
+    mode: u32,
+    /// Last modification time
+    modified: Option<SystemTime>,
+    /// Contents of small regular files
+    content: Option<Vec<u8>>,
+}
+
+impl EntryState {
+    /// Capture the state of `path` without following symlinks
+    fn capture(path: &Path, max_content_bytes: u64) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            EntryKind::Symlink
+        } else if file_type.is_dir() {
+            EntryKind::Directory
+        } else if file_type.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        };
+        let content = (kind == EntryKind::File && metadata.len() <= max_content_bytes)
+            .then(|| fs::read(path).ok())
+            .flatten();
+        Some(Self { kind, size: metadata.len(), mode: mode(&metadata), modified: metadata.modified().ok(), content })
+    }
+}
+
+/// Get the permission bits of an entry
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+/// Get the permission bits of an entry
+#[cfg(not(unix))]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() { 0o444 } else { 0o644 }
+}
+
+/// The state of a command's target paths at one point in time
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Paths the snapshot was taken of
+    roots: Vec<PathBuf>,
+    /// Captured entries below the roots
+    entries: BTreeMap<PathBuf, EntryState>,
+    /// Whether entries were left out to stay within the limit
+    truncated: bool,
+    /// Limits the snapshot was taken with
+    config: ImpactConfig,
+}
+
+impl Snapshot {
+    /// Capture the state of `roots` and everything below them
+    pub fn capture(roots: Vec<PathBuf>, config: ImpactConfig) -> Self {
+        let mut snapshot = Self { roots, entries: BTreeMap::new(), truncated: false, config };
+        let mut pending: Vec<PathBuf> = snapshot.roots.iter().rev().cloned().collect();
+        while let Some(path) = pending.pop() {
+            if snapshot.entries.contains_key(&path) {
+                continue;
+            }
+            if snapshot.entries.len() >= config.max_entries {
+                snapshot.truncated = true;
+                break;
+            }
+            let Some(state) = EntryState::capture(&path, config.max_content_bytes) else {
+                continue;
+            };
+            if state.kind == EntryKind::Directory
+                && let Ok(children) = fs::read_dir(&path)
+            {
+                let mut children: Vec<PathBuf> = children.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+                children.sort_by(|a, b| b.cmp(a));
+                pending.extend(children);
+            }
+            snapshot.entries.insert(path, state);
+        }
+        snapshot
+    }
+
+    /// Capture the same paths again
+    pub fn recapture(&self) -> Self {
+        Self::capture(self.roots.clone(), self.config)
+    }
+
+    /// Get the number of captured entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether nothing was captured
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Describe what changed between this snapshot and a later one
+    pub fn diff(&self, after: &Snapshot) -> ImpactReport {
+        let mut changes = Vec::new();
+        for (path, before) in &self.entries {
+            match after.entries.get(path) {
+                None => changes.push(PathChange {
+                    path: path.clone(),
+                    kind: ChangeKind::Removed,
+                    details: vec![describe_entry(before)],
+                }),
+                Some(now) if now != before => {
+                    let details = describe_changes(before, now);
+                    if !details.is_empty() {
+                        changes.push(PathChange { path: path.clone(), kind: ChangeKind::Modified, details });
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for (path, now) in &after.entries {
+            if !self.entries.contains_key(path) {
+                changes.push(PathChange { path: path.clone(), kind: ChangeKind::Created, details: vec![describe_entry(now)] });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        ImpactReport { targets: self.roots.clone(), changes, truncated: self.truncated || after.truncated }
+    }
+}
+
+/// Describe an entry that appeared or disappeared
+fn describe_entry(state: &EntryState) -> String {
+    match state.kind {
+        EntryKind::Directory => "directory".to_string(),
+        EntryKind::Symlink => "symlink".to_string(),
+        EntryKind::Other => "special file".to_string(),
+        EntryKind::File => format!("{} B", state.size),
+    }
+}
+
+/// Describe how an entry changed
+fn describe_changes(before: &EntryState, after: &EntryState) -> Vec<String> {
+    let mut details = Vec::new();
+    if before.kind != after.kind {
+        details.push(format!("{} → {}", describe_entry(before), describe_entry(after)));
+        return details;
+    }
+    if before.mode != after.mode {
+        details.push(format!("mode {:o} → {:o}", before.mode, after.mode));
+    }
+    // Directory sizes and times change with their children, which are listed themselves
+    if before.kind == EntryKind::Directory {
+        return details;
+    }
+    if before.size != after.size {
+        details.push(format!("{} → {} B", before.size, after.size));
+    }
+    match (&before.content, &after.content) {
+        (Some(old), Some(new)) if old != new => {
+            let (added, removed) = line_changes(old, new);
+            details.push(format!("+{} −{} lines", added, removed));
+        }
+        (Some(old), Some(new)) if old == new => {}
+        _ if before.modified != after.modified => details.push("contents modified".to_string()),
+        _ => {}
+    }
+    details
+}
+
+/// Count the lines added and removed between two file contents
+///
+/// Lines are compared as multisets, so moved lines are not counted.
+fn line_changes(old: &[u8], new: &[u8]) -> (usize, usize) {
+    let mut counts: HashMap<&[u8], isize> = HashMap::new();
+    for line in old.split(|&b| b == b'\n') {
+        *counts.entry(line).or_default() -= 1;
+    }
+    for line in new.split(|&b| b == b'\n') {
+        *counts.entry(line).or_default() += 1;
+    }
+    counts.values().fold((0, 0), |(added, removed), &count| {
+        if count > 0 {
+            (added + count as usize, removed)
+        } else {
+            (added, removed + (-count) as usize)
+        }
+    })
+}
+
+/// How a path changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Created,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    /// Marker shown in front of a changed path
+    pub fn marker(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "+",
+            ChangeKind::Removed => "-",
+            ChangeKind::Modified => "~",
+        }
+    }
+}
+
+/// A change to one path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathChange {
+    /// The changed path
+    pub path: PathBuf,
+    /// Whether the path was created, removed or modified
+    pub kind: ChangeKind,
+    /// What changed, such as sizes or permissions
+    pub details: Vec<String>,
+}
+
+impl fmt::Display for PathChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind.marker(), self.path.display())?;
+        if !self.details.is_empty() {
+            write!(f, " ({})", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What a command changed on disk
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactReport {
+    /// Paths that were snapshotted
+    pub targets: Vec<PathBuf>,
+    /// Changes below the targets, sorted by path
+    pub changes: Vec<PathChange>,
+    /// Whether the snapshots hit the entry limit, so changes may be missing
+    pub truncated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_commands() {
+        assert!(is_high_risk("rm -rf build"));
+        assert!(is_high_risk("sudo chmod 755 script.sh"));
+        assert!(is_high_risk("cd src && sed -i 's/a/b/' main.rs"));
+        assert!(is_high_risk("echo hi > notes.txt"));
+        assert!(is_high_risk("find . -name '*.tmp' -delete"));
+        assert!(!is_high_risk("ls -la"));
+        assert!(!is_high_risk("echo hi >> notes.txt"));
+        assert!(!is_high_risk("cargo build 2>/dev/null"));
+        assert!(!is_high_risk("grep -r rm ."));
+    }
+
+    #[test]
+    fn test_target_paths() {
+        let cwd = Path::new("/work");
+        assert_eq!(target_paths("rm -rf build 'my file'", cwd), vec![PathBuf::from("/work/build"), PathBuf::from("/work/my file")]);
+        assert_eq!(target_paths("chmod +x /usr/local/bin/tool", cwd), vec![PathBuf::from("/usr/local/bin/tool")]);
+        assert_eq!(target_paths("rm logs/*.log", cwd), vec![PathBuf::from("/work/logs")]);
+        assert_eq!(target_paths("echo hi > out.txt", cwd), vec![PathBuf::from("/work/out.txt")]);
+        assert_eq!(target_paths("dd if=/dev/zero of=disk.img bs=1M", cwd), vec![PathBuf::from("/work/disk.img")]);
+        assert_eq!(target_paths("sed -i 's/a/b/' a.txt", cwd), vec![PathBuf::from("/work/a.txt")]);
+    }
+
+    #[test]
+    fn test_diff_reports_changes() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-impact-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("keep.txt"), "one\ntwo\n").unwrap();
+        fs::write(dir.join("gone.txt"), "bye").unwrap();
+        fs::write(dir.join("same.txt"), "same").unwrap();
+
+        let before = Snapshot::capture(vec![dir.clone()], ImpactConfig::default());
+        fs::write(dir.join("keep.txt"), "one\nthree\nfour\n").unwrap();
+        fs::remove_file(dir.join("gone.txt")).unwrap();
+        fs::write(dir.join("new.txt"), "hello").unwrap();
+        let report = before.diff(&before.recapture());
+
+        let changes: Vec<String> = report.changes.iter().map(|change| {
+            change.to_string().replace(&dir.display().to_string(), "")
+        }).collect();
+        assert_eq!(changes, vec!["- /gone.txt (3 B)", "~ /keep.txt (8 → 15 B, +2 −1 lines)", "+ /new.txt (5 B)"]);
+        assert!(!report.truncated);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_snapshot_respects_entry_limit() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-impact-limit-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..5 {
+            fs::write(dir.join(format!("{}.txt", i)), "x").unwrap();
+        }
+
+        let snapshot = Snapshot::capture(vec![dir.clone()], ImpactConfig { max_entries: 3, ..ImpactConfig::default() });
+        assert_eq!(snapshot.len(), 3);
+        assert!(snapshot.diff(&snapshot.recapture()).truncated);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,34 @@
 pub mod command_block;
 pub mod command_history;
+pub mod file_lock;
+pub mod file_ops;
+pub mod frame_store;
+pub mod graphics;
+pub mod impact;
+pub mod progress;
 pub mod pty_executor;
+pub mod remote;
+pub mod safe_delete;
+pub mod sandbox;
+pub mod scrollback;
+pub mod store;
+pub mod tags;
+pub mod usage;
 
 // Re-export main types for convenience
-pub use command_block::{BlockState, CommandBlock};
+pub use command_block::{BlockRun, BlockState, BlockView, CommandBlock, OutputSummary};
 pub use command_history::{CommandHistory, HistoryEntry};
-pub use pty_executor::{ExecutionEvent, PtyExecutor};
\ No newline at end of file
+pub use file_lock::FileLock;
+pub use file_ops::{Change, FileOperation, Preview};
+pub use frame_store::FrameStore;
+pub use graphics::{GraphicsSequence, ImageProtocol};
+pub use impact::{ImpactConfig, ImpactReport, Snapshot};
+pub use progress::ProgressFilter;
+pub use pty_executor::{ExecutionEvent, PtyExecutor};
+pub use remote::RemoteHost;
+pub use safe_delete::{DeletedBatch, SafeDelete};
+pub use sandbox::{SandboxConfig, SandboxProfile};
+pub use scrollback::{OutputBuffer, ScrollbackConfig, ScrollbackLimits};
+pub use store::{Bookmark, ConversationInfo, ConversationMessage, InstanceInfo, KnownError, ModelUsage, PinnedBlock, SessionBlock, SessionInfo, Snippet, Store, UsageRecord};
+pub use tags::TagFilter;
+pub use usage::ResourceUsage;
\ No newline at end of file
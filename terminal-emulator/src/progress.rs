@@ -0,0 +1,135 @@
+//! Progress updates in command output
+//!
+//! Progress bars redraw themselves in place: pip and cargo go back to the
+//! start of the line with `\r`, and docker and rich move the cursor up with
+//! `ESC [ n A` and erase lines with `ESC [ K` to redraw several at once.
+//! Stripping escape codes loses these moves, so every redraw would be added
+//! to the output. The PTY reader runs its output through a
+//! [`ProgressFilter`] instead, which strips every other escape code but keeps
+//! the moves, written as `\r`, [`ERASE_LINE`] and `ESC [ n A`, for
+//! [`OutputBuffer`](crate::OutputBuffer) to apply to its lines.
+
+/// Escape byte starting every sequence
+const ESC: u8 = 0x1b;
+
+/// Longest control sequence held back when a read ends inside it
+const MAX_HELD: usize = 32;
+
+/// Sequence erasing the line the cursor is on
+pub const ERASE_LINE: &str = "\x1b[K";
+
+/// Strips escape codes from output, keeping the cursor moves progress bars redraw with
+#[derive(Debug, Default)]
+pub struct ProgressFilter {
+    /// Start of a control sequence cut off at the end of the last read
+    held: Vec<u8>,
+}
+
+impl ProgressFilter {
+    /// Create a filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filter the next bytes read
+    pub fn feed(&mut self, bytes: &[u8]) -> String {
+        let mut input = std::mem::take(&mut self.held);
+        input.extend_from_slice(bytes);
+        if let Some(start) = unfinished_sequence(&input) {
+            self.held = input.split_off(start);
+        }
+        translate(&input)
+    }
+
+    /// Filter whatever is held once the output ends
+    pub fn finish(self) -> String {
+        translate(&self.held)
+    }
+}
+
+/// Find a control sequence at the end of `bytes` that is still missing its final byte
+fn unfinished_sequence(bytes: &[u8]) -> Option<usize> {
+    let start = bytes.iter().rposition(|&b| b == ESC)?;
+    let sequence = &bytes[start..];
+    let unfinished = match sequence {
+        [ESC] => true,
+        [ESC, b'[', params @ ..] => params.iter().all(|b| (0x20..0x40).contains(b)),
+        _ => false,
+    };
+    (unfinished && sequence.len() <= MAX_HELD).then_some(start)
+}
+
+/// Strip escape codes, writing the moves kept in their canonical form
+fn translate(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let (len, kept) = match bytes[i] {
+            b'\r' => (1, Some("\r".to_string())),
+            ESC if bytes.get(i + 1) == Some(&b'[') => {
+                let params = bytes[i + 2..].iter().take_while(|b| (0x20..0x40).contains(*b)).count();
+                let count = || std::str::from_utf8(&bytes[i + 2..i + 2 + params]).ok()?.parse::<usize>().ok();
+                let kept = match bytes.get(i + 2 + params) {
+                    Some(b'A') => Some(format!("\x1b[{}A", count().unwrap_or(1).max(1))),
+                    // To the start of a line above
+                    Some(b'F') => Some(format!("\x1b[{}A\r", count().unwrap_or(1).max(1))),
+                    Some(b'K') => Some(ERASE_LINE.to_string()),
+                    _ => None,
+                };
+                (params + 3, kept)
+            }
+            _ => (1, None),
+        };
+        if let Some(kept) = kept {
+            text.push_str(&String::from_utf8_lossy(&strip_ansi_escapes::strip(&bytes[start..i])));
+            text.push_str(&kept);
+            start = (i + len).min(bytes.len());
+        }
+        i += len;
+    }
+    text.push_str(&String::from_utf8_lossy(&strip_ansi_escapes::strip(&bytes[start.min(bytes.len())..])));
+    text
+}
+
+/// Read how far along a progress line is, as a percentage or a count such as `12/40`
+pub fn percent(line: &str) -> Option<f32> {
+    let words: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || matches!(c, '[' | ']' | '(' | ')' | '|'))
+        .map(|word| word.trim_end_matches([':', ',']))
+        .collect();
+    let percent = words.iter().rev().find_map(|word| {
+        let value: f32 = word.strip_suffix('%')?.parse().ok()?;
+        (0.0..=100.0).contains(&value).then_some(value)
+    });
+    percent.or_else(|| {
+        words.iter().rev().find_map(|word| {
+            let (done, total) = word.split_once('/')?;
+            let (done, total): (f32, f32) = (done.parse().ok()?, total.parse().ok()?);
+            (total > 0.0 && done <= total).then(|| done / total * 100.0)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_keeps_cursor_moves() {
+        let mut filter = ProgressFilter::new();
+        assert_eq!(filter.feed(b"\x1b[32mok\x1b[0m\r\n 10%\r 20%\x1b["), "ok\r\n 10%\r 20%");
+        assert_eq!(filter.feed(b"2K\x1b[2A\x1b[?25llayer\x1b[1F"), "\x1b[K\x1b[2Alayer\x1b[1A\r");
+        assert_eq!(filter.feed(b"done\x1b"), "done");
+        assert_eq!(filter.finish(), "");
+    }
+
+    #[test]
+    fn test_progress_is_read_from_lines() {
+        assert_eq!(percent("Downloading torch (2.1 GB) ━━━━━━━━ 45%"), Some(45.0));
+        assert_eq!(percent("    Building [=======>     ] 120/480: serde"), Some(25.0));
+        assert_eq!(percent("Step 3/4 : RUN make"), Some(75.0));
+        assert_eq!(percent("Compiling serde v1.0"), None);
+        assert_eq!(percent("grew 250% this year"), None);
+    }
+}
@@ -1,11 +1,17 @@
 use anyhow::{Context, Result};
 use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
+use std::fmt;
 use std::io::{BufReader, Read};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::command_block::{BlockState, CommandBlock};
+use crate::graphics::{GraphicsFilter, GraphicsSequence};
+use crate::progress::ProgressFilter;
+use crate::remote::{control_dir, DirFilter, RemoteHost};
+use crate::sandbox::SandboxProfile;
+use crate::usage::ResourceUsage;
 
 /// Events that can occur during command execution
 #[derive(Debug, Clone)]
@@ -19,6 +25,9 @@ pub enum ExecutionEvent {
     /// Output received from stderr
     StderrData(String),
     
+    /// CPU time and memory the command used, sent just before it completes
+    Usage(ResourceUsage),
+    
     /// Command completed with exit code
     Completed { exit_code: i32, duration: Duration },
     
@@ -27,6 +36,24 @@ pub enum ExecutionEvent {
     
     /// Command was cancelled
     Cancelled,
+    
+    /// Remote shell reported the directory the command left it in
+    WorkingDir(String),
+    
+    /// An image written with a terminal graphics protocol
+    Graphics(GraphicsSequence),
+}
+
+/// Extra environment variables of commands, such as filled-in secrets
+///
+/// Only the names show in debug output, so values cannot end up in logs.
+#[derive(Clone, Default)]
+struct CommandEnv(Vec<(String, String)>);
+
+impl fmt::Debug for CommandEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|(name, _)| name)).finish()
+    }
 }
 
 /// PTY-based command executor
@@ -37,6 +64,18 @@ pub struct PtyExecutor {
     
     /// Shell to use (bash, zsh, etc.)
     shell: String,
+    
+    /// Restrictions commands run under, if they are sandboxed
+    sandbox: Option<SandboxProfile>,
+    
+    /// Environment variables set for commands besides the inherited ones
+    env: CommandEnv,
+    
+    /// Host commands run on over SSH instead of locally
+    remote: Option<RemoteHost>,
+    
+    /// Inherited environment variables commands do not get, such as secrets of the terminal itself
+    hidden_env: Vec<String>,
 }
 
 impl PtyExecutor {
@@ -60,9 +99,37 @@ impl PtyExecutor {
         Ok(Self {
             working_dir,
             shell,
+            sandbox: None,
+            env: CommandEnv::default(),
+            remote: None,
+            hidden_env: Vec::new(),
         })
     }
     
+    /// Get a copy of this executor that runs commands under a sandbox profile
+    pub fn sandboxed(&self, profile: SandboxProfile) -> Self {
+        Self {
+            sandbox: Some(profile),
+            ..self.clone()
+        }
+    }
+    
+    /// Get a copy of this executor that sets extra environment variables for commands
+    pub fn with_env(&self, env: Vec<(String, String)>) -> Self {
+        Self {
+            env: CommandEnv(env),
+            ..self.clone()
+        }
+    }
+    
+    /// Get a copy of this executor that runs commands on a remote host
+    pub fn on_host(&self, remote: RemoteHost) -> Self {
+        Self {
+            remote: Some(remote),
+            ..self.clone()
+        }
+    }
+    
     /// Execute a command and stream events
     pub async fn execute(
         &self,
@@ -89,22 +156,67 @@ impl PtyExecutor {
             .openpty(pty_size)
             .context("Failed to open PTY")?;
         
-        // Build the command
-        let mut cmd = CommandBuilder::new(&self.shell);
-        cmd.arg("-c");
-        cmd.arg(command);
-        cmd.cwd(&self.working_dir);
+        // Refuse rather than drop the restrictions that were asked for
+        if self.sandbox.is_some() && !SandboxProfile::is_available() {
+            event_tx.send(ExecutionEvent::Failed("bubblewrap (bwrap) is not installed, so the command was not run".to_string()))
+                .map_err(|e| anyhow::anyhow!("Failed to send error event: {}", e))?;
+            return Ok(());
+        }
+        
+        // The sandbox restricts this machine, not the host commands would run on
+        if self.sandbox.is_some() && self.remote.is_some() {
+            event_tx.send(ExecutionEvent::Failed("remote commands cannot be sandboxed, so the command was not run".to_string()))
+                .map_err(|e| anyhow::anyhow!("Failed to send error event: {}", e))?;
+            return Ok(());
+        }
+        
+        // Build the command, inside bubblewrap when sandboxed and through ssh when remote
+        let scratch = match self.sandbox {
+            Some(profile) => profile.scratch_dir()?,
+            None => None,
+        };
+        let cwd = scratch.as_ref().map_or(self.working_dir.clone(), |dir| dir.to_string_lossy().to_string());
+        let mut cmd = match (self.sandbox, &self.remote) {
+            (Some(profile), _) => {
+                CommandBuilder::from_argv(profile.argv(&self.shell, command, cwd.as_ref()).into_iter().map(Into::into).collect())
+            }
+            (None, Some(remote)) => {
+                CommandBuilder::from_argv(remote.argv(command, &self.env.0, &control_dir()?).into_iter().map(Into::into).collect())
+            }
+            (None, None) => {
+                let mut cmd = CommandBuilder::new(&self.shell);
+                cmd.arg("-c");
+                cmd.arg(command);
+                cmd
+            }
+        };
+        cmd.cwd(&cwd);
+        for name in &self.hidden_env {
+            cmd.env_remove(name);
+        }
+        // Remote commands get their variables set on the host
+        if self.remote.is_none() {
+            for (name, value) in &self.env.0 {
+                cmd.env(name, value);
+            }
+        }
         
         // Spawn the child process
         let mut child = pair.slave.spawn_command(cmd)
             .context("Failed to spawn command")?;
         
+        // Drop our handle to the slave so the reader sees EOF once the child exits
+        drop(pair.slave);
+        
         // Set up readers for stdout/stderr
         let reader = pair.master.try_clone_reader()
             .context("Failed to clone PTY reader")?;
         
         // Create a thread to read output
         let event_tx_clone = event_tx.clone();
+        let mut dir_filter = self.remote.is_some().then(DirFilter::default);
+        let mut graphics = GraphicsFilter::new();
+        let mut progress = ProgressFilter::new();
         let read_thread = std::thread::spawn(move || {
             let mut buf_reader = BufReader::new(reader);
             let mut buffer = vec![0u8; 4096];
@@ -113,11 +225,23 @@ impl PtyExecutor {
                 match buf_reader.read(&mut buffer) {
                     Ok(0) => break, // EOF
                     Ok(n) => {
-                        let raw_bytes = &buffer[..n];
+                        // Images are taken out before stripping would destroy them
+                        let (raw_bytes, images) = graphics.feed(&buffer[..n]);
+                        for image in images {
+                            let _ = event_tx_clone.send(ExecutionEvent::Graphics(image));
+                        }
+                        
+                        // Strip ANSI escape codes for cleaner output, keeping the moves progress bars redraw with
+                        let mut cleaned = progress.feed(&raw_bytes);
                         
-                        // Strip ANSI escape codes for cleaner output
-                        let cleaned_bytes = strip_ansi_escapes::strip(raw_bytes);
-                        let cleaned = String::from_utf8_lossy(&cleaned_bytes).to_string();
+                        // Remote shells report their directory in the output
+                        if let Some(filter) = &mut dir_filter {
+                            let (shown, dir) = filter.feed(&cleaned);
+                            cleaned = shown;
+                            if let Some(dir) = dir {
+                                let _ = event_tx_clone.send(ExecutionEvent::WorkingDir(dir));
+                            }
+                        }
                         
                         // Send output event
                         if let Err(e) = event_tx_clone.send(ExecutionEvent::StdoutData(cleaned)) {
@@ -131,25 +255,42 @@ impl PtyExecutor {
                     }
                 }
             }
+            let held = progress.feed(&graphics.finish()) + &progress.finish();
+            if !held.is_empty() {
+                let _ = event_tx_clone.send(ExecutionEvent::StdoutData(held));
+            }
+            if let Some(rest) = dir_filter.map(DirFilter::finish).filter(|rest| !rest.is_empty()) {
+                let _ = event_tx_clone.send(ExecutionEvent::StdoutData(rest));
+            }
         });
         
-        // Wait for the process to complete
-        let wait_result = tokio::task::spawn_blocking(move || {
-            child.wait()
+        // Wait for the process to complete; wait4 also tells what a local command used
+        #[cfg(unix)]
+        let pid = child.process_id().filter(|_| self.remote.is_none());
+        let wait_result = tokio::task::spawn_blocking(move || -> std::io::Result<(i32, Option<ResourceUsage>)> {
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                return crate::usage::wait(pid).map(|(exit_code, usage)| (exit_code, Some(usage)));
+            }
+            let status = child.wait()?;
+            // On Windows, assume success if we get here
+            Ok((if cfg!(unix) { status.exit_code() as i32 } else { 0 }, None))
         }).await;
         
+        // The reader sends what its filters held back at EOF, which belongs before completion
+        if let Err(e) = read_thread.join() {
+            warn!("Read thread panicked: {:?}", e);
+        }
+        
         match wait_result {
-            Ok(Ok(status)) => {
-                let exit_code = if cfg!(unix) {
-                    // On Unix, extract exit code
-                    status.exit_code() as i32
-                } else {
-                    // On Windows, assume success if we get here
-                    0
-                };
-                
+            Ok(Ok((exit_code, usage))) => {
                 let duration = start_time.elapsed();
                 
+                if let Some(usage) = usage {
+                    event_tx.send(ExecutionEvent::Usage(usage))
+                        .map_err(|e| anyhow::anyhow!("Failed to send usage event: {}", e))?;
+                }
+                
                 // Send completion event
                 event_tx.send(ExecutionEvent::Completed { exit_code, duration })
                     .map_err(|e| anyhow::anyhow!("Failed to send completion event: {}", e))?;
@@ -168,9 +309,11 @@ impl PtyExecutor {
             }
         }
         
-        // Wait for read thread to complete
-        if let Err(e) = read_thread.join() {
-            warn!("Read thread panicked: {:?}", e);
+        // Nothing written to a temporary working directory outlives the command
+        if let Some(dir) = scratch
+            && let Err(e) = std::fs::remove_dir_all(&dir)
+        {
+            warn!("Failed to remove sandbox directory {}: {}", dir.display(), e);
         }
         
         Ok(())
@@ -182,6 +325,9 @@ impl PtyExecutor {
         
         // Start execution
         block.start_execution();
+        if let Some(profile) = self.sandbox {
+            block.append_output(&format!("[sandboxed: {}]\n", profile.describe()), false);
+        }
         
         // Spawn execution task
         let command = block.command.clone();
@@ -202,19 +348,31 @@ impl PtyExecutor {
                 ExecutionEvent::StderrData(data) => {
                     block.append_output(&data, true);
                 }
+                ExecutionEvent::Graphics(image) => {
+                    block.add_image(image);
+                }
+                ExecutionEvent::Usage(usage) => {
+                    block.set_usage(usage);
+                }
                 ExecutionEvent::Completed { exit_code, duration } => {
                     block.complete(exit_code, duration);
-                    break;
+                    // A remote directory is only reported once, so wait for it
+                    if self.remote.is_none() {
+                        break;
+                    }
                 }
                 ExecutionEvent::Failed(error) => {
-                    block.state = BlockState::Failed;
+                    block.set_state(BlockState::Failed);
                     block.append_output(&format!("\n[Error: {}]", error), true);
                     break;
                 }
                 ExecutionEvent::Cancelled => {
-                    block.state = BlockState::Cancelled;
+                    block.set_state(BlockState::Cancelled);
                     break;
                 }
+                ExecutionEvent::WorkingDir(dir) => {
+                    block.end_dir = Some(dir);
+                }
             }
         }
         
@@ -233,4 +391,11 @@ impl PtyExecutor {
     pub fn working_dir(&self) -> &str {
         &self.working_dir
     }
+    
+    /// Keep an inherited environment variable from commands
+    pub fn hide_env(&mut self, name: &str) {
+        if !self.hidden_env.iter().any(|hidden| hidden == name) {
+            self.hidden_env.push(name.to_string());
+        }
+    }
 }
\ No newline at end of file
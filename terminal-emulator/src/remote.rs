@@ -0,0 +1,181 @@
+//! Commands run on a remote host over SSH
+//!
+//! A pane connected to a host runs each of its commands through `ssh`,
+//! wrapped in a short `sh` script that changes to the pane's remote
+//! directory first and reports the directory it ends in after. The report
+//! is a marker line taken out of the output, so `cd` on the host carries
+//! over to the next command as it does locally. Connections are shared
+//! through an OpenSSH control socket, so only the first command pays for
+//! logging in. The socket is kept in a directory only the user can enter,
+//! since `ssh` would use one another user put in its place. Nothing is typed
+//! into `ssh`, so the host must accept a key or the agent's keys rather than
+//! ask for a password.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::store::Store;
+
+/// Start of the line reporting the remote directory after a command
+///
+/// Output is stripped of control characters, so the marker starts with a
+/// private use character instead, which no program prints.
+const DIR_MARKER: &str = "\u{e000}ai-terminal-cwd:";
+
+/// How long an idle shared connection is kept open, in seconds
+const CONTROL_PERSIST: u32 = 600;
+
+/// A host commands run on, and the directory they run in there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteHost {
+    /// Host as given to `ssh`, such as `deploy@web-1` or an alias from `~/.ssh/config`
+    pub host: String,
+    /// Directory on the host, or `None` for the login directory
+    pub dir: Option<String>,
+}
+
+impl RemoteHost {
+    /// Check a host name and start in its login directory
+    pub fn new(host: &str) -> Result<Self> {
+        let host = host.trim();
+        if host.is_empty() || host.starts_with('-') || host.chars().any(char::is_whitespace) {
+            bail!("'{}' is not a host name", host);
+        }
+        Ok(Self { host: host.to_string(), dir: None })
+    }
+
+    /// Describe where commands run, such as `web-1:/srv/app`
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.host, self.dir.as_deref().unwrap_or("~"))
+    }
+
+    /// Build the `ssh` command line running `command` with `env` set, in the remote directory
+    ///
+    /// The shared connection's socket is made in `control_dir`.
+    pub fn argv(&self, command: &str, env: &[(String, String)], control_dir: &Path) -> Vec<String> {
+        let report = format!("printf '{}%s\\n' \"$PWD\"", DIR_MARKER);
+        let mut script = String::new();
+        // A directory removed since stays behind, leaving the next command in the login directory
+        if let Some(dir) = &self.dir {
+            script.push_str(&format!("cd {} || {{ cd; {}; exit 1; }}\n", quote(dir), report));
+        }
+        for (name, value) in env {
+            script.push_str(&format!("export {}={}\n", name, quote(value)));
+        }
+        script.push_str(command);
+        script.push_str(&format!("\nai_terminal_status=$?\n{}\nexit $ai_terminal_status", report));
+
+        let control_path = control_dir.join("%C");
+        let mut argv: Vec<String> = [
+            "ssh", "-tt",
+            "-o", "BatchMode=yes",
+            "-o", "LogLevel=ERROR",
+            "-o", "ConnectTimeout=10",
+            "-o", "ControlMaster=auto",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        argv.push("-o".to_string());
+        argv.push(format!("ControlPath={}", control_path.display()));
+        argv.push("-o".to_string());
+        argv.push(format!("ControlPersist={}", CONTROL_PERSIST));
+        argv.push(self.host.clone());
+        argv.push("--".to_string());
+        // ssh joins its arguments into a line for the login shell, which then starts sh
+        argv.push(format!("sh -c {}", quote(&script)));
+        argv
+    }
+}
+
+/// Get the directory for control sockets, `ssh` in the data directory, creating it private to the user
+pub fn control_dir() -> Result<PathBuf> {
+    let dir = Store::data_dir()?.join("ssh");
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to make {} private", dir.display()))?;
+    }
+    Ok(dir)
+}
+
+/// Quote a value as one shell word
+fn quote(value: &str) -> String {
+    shlex::try_quote(value).map_or_else(|_| value.to_string(), |quoted| quoted.into_owned())
+}
+
+/// Takes the directory report out of a remote command's output as it arrives
+#[derive(Debug, Default)]
+pub struct DirFilter {
+    /// Output held back because it may be the start of the report
+    pending: String,
+}
+
+impl DirFilter {
+    /// Pass on a chunk of output, returning the text to show and the directory if it was reported
+    pub fn feed(&mut self, chunk: &str) -> (String, Option<String>) {
+        self.pending.push_str(chunk);
+        if let Some(start) = self.pending.find(DIR_MARKER) {
+            let rest = &self.pending[start + DIR_MARKER.len()..];
+            let Some(end) = rest.find('\n') else {
+                // Wait for the rest of the line
+                let shown = self.pending[..start].to_string();
+                self.pending.replace_range(..start, "");
+                return (shown, None);
+            };
+            let dir = rest[..end].trim_end_matches('\r').to_string();
+            let shown = self.pending[..start].to_string() + &rest[end + 1..];
+            self.pending.clear();
+            return (shown, Some(dir));
+        }
+        // Hold back an ending that could grow into the marker
+        let held = (1..DIR_MARKER.len().min(self.pending.len() + 1))
+            .rev()
+            .filter(|len| DIR_MARKER.is_char_boundary(*len))
+            .find(|len| self.pending.ends_with(&DIR_MARKER[..*len]))
+            .unwrap_or(0);
+        let shown = self.pending[..self.pending.len() - held].to_string();
+        self.pending.replace_range(..self.pending.len() - held, "");
+        (shown, None)
+    }
+
+    /// Get the output still held back once the command ended
+    pub fn finish(self) -> String {
+        self.pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argv_wraps_command() {
+        let mut remote = RemoteHost::new("deploy@web-1").unwrap();
+        assert!(RemoteHost::new("-oProxyCommand=x").is_err());
+        assert_eq!(remote.location(), "deploy@web-1:~");
+        remote.dir = Some("/srv/my app".to_string());
+        let argv = remote.argv("ls", &[("TOKEN".to_string(), "s3cr3t".to_string())], Path::new("/home/u/.local/share/ai-terminal/ssh"));
+        assert_eq!(argv[0], "ssh");
+        assert!(argv.contains(&"ControlPath=/home/u/.local/share/ai-terminal/ssh/%C".to_string()));
+        assert_eq!(argv[argv.len() - 3..argv.len() - 1], ["deploy@web-1", "--"]);
+        let script = shlex::split(argv.last().unwrap()).unwrap();
+        assert_eq!(script[..2], ["sh", "-c"]);
+        assert!(script[2].starts_with("cd '/srv/my app' || { cd; printf"));
+        assert!(script[2].contains("\nexport TOKEN=s3cr3t\nls\nai_terminal_status=$?\n"));
+    }
+
+    #[test]
+    fn test_filter_takes_out_directory() {
+        let mut filter = DirFilter::default();
+        assert_eq!(filter.feed("total 0\r\n\u{e000}ai-ter"), ("total 0\r\n".to_string(), None));
+        assert_eq!(filter.feed("minal-cwd:/srv"), (String::new(), None));
+        assert_eq!(filter.feed("/app\r\n"), (String::new(), Some("/srv/app".to_string())));
+        assert_eq!(filter.feed("Connection closed.\r\n"), ("Connection closed.\r\n".to_string(), None));
+        assert_eq!(filter.feed("50\u{e000}"), ("50".to_string(), None));
+        assert_eq!(filter.finish(), "\u{e000}");
+    }
+}
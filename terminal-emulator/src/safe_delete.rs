@@ -0,0 +1,165 @@
+//! Safe delete for the AI Terminal
+//!
+//! Deleting moves files to the platform trash (the XDG trash on Linux and
+//! the BSDs, the Recycle Bin on Windows, the Finder trash on macOS) so that
+//! mistakes can be undone. Each delete is remembered as a batch; undoing
+//! restores the most recent batch from the trash. Permanent deletion is a
+//! separate call that callers only make after an explicit confirmation.
+//! Builds without the `trash` feature can only delete permanently.
+
+#[cfg(all(feature = "trash", any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+
+use crate::file_ops;
+
+/// Files moved to the trash by one delete
+#[derive(Debug, Clone)]
+pub struct DeletedBatch {
+    /// Original locations of the trashed files
+    pub paths: Vec<PathBuf>,
+    /// When the files were trashed
+    pub deleted_at: DateTime<Local>,
+}
+
+/// Deletes files through the trash and undoes those deletes
+#[derive(Debug, Default)]
+pub struct SafeDelete {
+    /// Batches that can still be undone, oldest first
+    batches: Vec<DeletedBatch>,
+}
+
+impl SafeDelete {
+    /// Create a safe delete with nothing to undo
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether this build can move files to the trash
+    pub fn is_available() -> bool {
+        cfg!(feature = "trash")
+    }
+
+    /// Move `paths` to the trash, remembering them for [`SafeDelete::undo`]
+    pub fn trash(&mut self, paths: &[PathBuf]) -> Result<&DeletedBatch> {
+        if !Self::is_available() {
+            bail!("this build has no trash support; files can only be deleted permanently");
+        }
+        check_paths(paths)?;
+        let deleted_at = Local::now();
+        #[cfg(feature = "trash")]
+        trash::delete_all(paths).context("could not move the files to the trash")?;
+
+        self.batches.push(DeletedBatch { paths: paths.to_vec(), deleted_at });
+        Ok(self.batches.last().expect("a batch was just added"))
+    }
+
+    /// Delete `paths` for good, bypassing the trash
+    pub fn delete_permanently(paths: &[PathBuf]) -> Result<()> {
+        check_paths(paths)?;
+        for path in paths {
+            let result = if std::fs::symlink_metadata(path)?.is_dir() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            };
+            result.with_context(|| format!("could not delete {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Check whether there is a delete to undo
+    pub fn can_undo(&self) -> bool {
+        !self.batches.is_empty()
+    }
+
+    /// Restore the most recently trashed batch to its original locations
+    #[cfg(all(feature = "trash", any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+    pub fn undo(&mut self) -> Result<DeletedBatch> {
+        let Some(batch) = self.batches.last() else {
+            bail!("nothing to undo");
+        };
+
+        // The trash may hold older copies of the same paths; take the newest of each
+        let since = batch.deleted_at.timestamp() - 1;
+        let mut items: Vec<trash::TrashItem> = trash::os_limited::list()
+            .context("could not read the trash")?
+            .into_iter()
+            .filter(|item| item.time_deleted >= since && batch.paths.contains(&item.original_path()))
+            .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.original_path()));
+        if items.is_empty() {
+            bail!("the deleted files are no longer in the trash");
+        }
+
+        trash::os_limited::restore_all(items).map_err(|e| match e {
+            trash::Error::RestoreCollision { path, .. } => {
+                anyhow::anyhow!("{} exists again; move it away and retry", path.display())
+            }
+            other => anyhow::Error::new(other).context("could not restore from the trash"),
+        })?;
+        Ok(self.batches.pop().expect("checked above"))
+    }
+
+    /// Restore the most recently trashed batch to its original locations
+    #[cfg(not(all(feature = "trash", any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))))]
+    pub fn undo(&mut self) -> Result<DeletedBatch> {
+        let Some(batch) = self.batches.last() else {
+            bail!("nothing to undo");
+        };
+        bail!("restoring from the trash is not supported on this platform; {} item(s) can be put back from the system trash", batch.paths.len())
+    }
+}
+
+/// Refuse to delete paths that do not exist or that no command should remove
+fn check_paths(paths: &[PathBuf]) -> Result<()> {
+    if paths.is_empty() {
+        bail!("no paths given");
+    }
+    for path in paths {
+        if std::fs::symlink_metadata(path).is_err() {
+            bail!("{} does not exist", path.display());
+        }
+        if !path.is_absolute() {
+            bail!("{} is not an absolute path", path.display());
+        }
+        file_ops::resolve(path, Path::new("/"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refuses_dangerous_paths() {
+        assert!(check_paths(&[]).is_err());
+        assert!(check_paths(&[PathBuf::from("/")]).is_err());
+        assert!(check_paths(&[PathBuf::from("/definitely/not/here")]).is_err());
+        if let Some(home) = std::env::var_os("HOME") {
+            assert!(check_paths(&[PathBuf::from(home)]).is_err());
+        }
+        assert!(check_paths(&[PathBuf::from("/etc/hosts")]).is_err());
+    }
+
+    #[test]
+    fn test_delete_permanently() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-delete-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("nested/file.txt"), "x").unwrap();
+        std::fs::write(dir.join("single.txt"), "x").unwrap();
+
+        SafeDelete::delete_permanently(&[dir.join("nested"), dir.join("single.txt")]).unwrap();
+        assert!(!dir.join("nested").exists());
+        assert!(!dir.join("single.txt").exists());
+        assert!(!SafeDelete::new().can_undo());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
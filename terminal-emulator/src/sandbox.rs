@@ -0,0 +1,170 @@
+//! Sandboxed command execution for the AI Terminal
+//!
+//! A command typed as `!sandbox npm install`, or proposed by the AI when
+//! `ai_commands` is set, runs inside bubblewrap (`bwrap`) with the restrictions
+//! of a [`SandboxProfile`]: a read-only view of the filesystem, no network,
+//! and optionally a fresh temporary working directory that is removed once
+//! the command ends. It always gets its own process, IPC and hostname
+//! namespaces, so it cannot see or signal the processes outside. When
+//! bubblewrap is not installed the command is refused rather than run
+//! without the restrictions that were asked for.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Prefix that runs a typed command in the sandbox
+pub const SANDBOX_PREFIX: &str = "!sandbox ";
+
+/// Program that sets up the sandbox
+const BWRAP: &str = "bwrap";
+
+/// Restrictions a sandboxed command runs under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SandboxProfile {
+    /// Mount the filesystem read-only, with a private `/tmp`
+    pub read_only: bool,
+    /// Run without network access
+    pub no_network: bool,
+    /// Run in an empty temporary directory instead of the working directory
+    pub temp_dir: bool,
+}
+
+impl SandboxProfile {
+    /// Check whether sandboxed commands can run on this system
+    pub fn is_available() -> bool {
+        cfg!(target_os = "linux") && find_program(BWRAP).is_some()
+    }
+
+    /// Describe the restrictions, such as "read-only filesystem, no network"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.read_only {
+            parts.push("read-only filesystem");
+        }
+        if self.no_network {
+            parts.push("no network");
+        }
+        if self.temp_dir {
+            parts.push("temporary working directory");
+        }
+        if parts.is_empty() {
+            parts.push("separate process namespaces");
+        }
+        parts.join(", ")
+    }
+
+    /// Build the command line running `command` with `shell` in `working_dir` under these restrictions
+    pub fn argv(&self, shell: &str, command: &str, working_dir: &Path) -> Vec<String> {
+        let dir = working_dir.to_string_lossy().to_string();
+        let mut argv = vec![BWRAP.to_string()];
+        let root = if self.read_only { "--ro-bind" } else { "--bind" };
+        argv.extend([root, "/", "/", "--dev", "/dev", "--proc", "/proc"].map(String::from));
+        if self.read_only {
+            argv.extend(["--tmpfs", "/tmp"].map(String::from));
+        }
+        argv.extend(["--unshare-pid", "--unshare-ipc", "--unshare-uts"].map(String::from));
+        if self.no_network {
+            argv.push("--unshare-net".to_string());
+        }
+        if self.temp_dir {
+            // The scratch directory stays writable even on a read-only root
+            argv.extend(["--bind".to_string(), dir.clone(), dir.clone()]);
+        }
+        argv.extend(["--chdir".to_string(), dir, "--die-with-parent".to_string(), "--".to_string()]);
+        argv.extend([shell.to_string(), "-c".to_string(), command.to_string()]);
+        argv
+    }
+
+    /// Create the temporary working directory for a command, if the profile asks for one
+    pub fn scratch_dir(&self) -> Result<Option<PathBuf>> {
+        if !self.temp_dir {
+            return Ok(None);
+        }
+        let dir = std::env::temp_dir().join(format!("ai-terminal-sandbox-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Some(dir))
+    }
+}
+
+/// Sandbox settings from the `[sandbox]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Mount the filesystem read-only
+    pub read_only: bool,
+    /// Run without network access
+    pub no_network: bool,
+    /// Run in an empty temporary directory
+    pub temp_dir: bool,
+    /// Sandbox every command the AI proposes, not only `!sandbox` ones
+    pub ai_commands: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            read_only: true,
+            no_network: true,
+            temp_dir: false,
+            ai_commands: false,
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Get the restrictions sandboxed commands run under
+    pub fn profile(&self) -> SandboxProfile {
+        SandboxProfile {
+            read_only: self.read_only,
+            no_network: self.no_network,
+            temp_dir: self.temp_dir,
+        }
+    }
+}
+
+/// Get the command after a `!sandbox` prefix
+pub fn strip_prefix(command: &str) -> Option<&str> {
+    command.strip_prefix(SANDBOX_PREFIX).map(str::trim).filter(|rest| !rest.is_empty())
+}
+
+/// Find a program on `PATH`
+fn find_program(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_argv() {
+        let profile = SandboxConfig::default().profile();
+        let argv = profile.argv("/bin/sh", "npm install", Path::new("/work"));
+        assert_eq!(argv[..4], ["bwrap", "--ro-bind", "/", "/"]);
+        assert!(argv.contains(&"--unshare-net".to_string()));
+        assert!(argv.ends_with(&["--chdir", "/work", "--die-with-parent", "--", "/bin/sh", "-c", "npm install"].map(String::from)));
+        assert_eq!(profile.describe(), "read-only filesystem, no network");
+
+        let scratch = SandboxProfile { read_only: true, no_network: false, temp_dir: true };
+        let argv = scratch.argv("/bin/sh", "ls", Path::new("/tmp/x"));
+        assert!(argv.windows(3).any(|w| w == ["--bind", "/tmp/x", "/tmp/x"]));
+        assert!(!argv.contains(&"--unshare-net".to_string()));
+
+        // With no other restriction the description names the namespaces the argv sets up
+        let bare = SandboxProfile { read_only: false, no_network: false, temp_dir: false };
+        let argv = bare.argv("/bin/sh", "ls", Path::new("/work"));
+        assert_eq!(bare.describe(), "separate process namespaces");
+        for flag in ["--unshare-pid", "--unshare-ipc", "--unshare-uts"] {
+            assert!(argv.contains(&flag.to_string()));
+        }
+        assert_eq!(argv[..4], ["bwrap", "--bind", "/", "/"]);
+
+        assert_eq!(strip_prefix("!sandbox  npm install"), Some("npm install"));
+        assert_eq!(strip_prefix("!sandbox "), None);
+        assert_eq!(strip_prefix("npm install"), None);
+    }
+}
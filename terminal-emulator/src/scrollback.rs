@@ -0,0 +1,443 @@
+//! Bounded scrollback for the AI Terminal
+//!
+//! Command output is kept in an [`OutputBuffer`], a ring of lines with a
+//! running byte count. When a block grows past its limits the oldest lines
+//! are dropped (down to 90% of the limit, so trimming does not run on every
+//! append) and handed back to the caller, which can spill them to disk. A
+//! second, global limit is applied across all blocks by [`enforce_total`].
+//!
+//! The buffer also applies the cursor moves kept by the
+//! [`ProgressFilter`](crate::progress::ProgressFilter): after `\r` the line
+//! is written again, `ESC [ K` erases it and `ESC [ n A` goes up `n` lines,
+//! so progress bars update their lines instead of adding new ones.
+//!
+//! Each running instance spills into its own directory and holds that
+//! directory's lock until it exits; [`prune_spill_dirs`] removes the
+//! directories no instance holds, left behind by ones that did not exit
+//! cleanly.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::progress::ERASE_LINE;
+
+use crate::command_block::CommandBlock;
+use crate::file_lock::FileLock;
+
+/// Line and byte limits for scrollback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ScrollbackLimits {
+    /// Maximum number of lines
+    pub max_lines: usize,
+    /// Maximum number of bytes
+    pub max_bytes: usize,
+}
+
+impl ScrollbackLimits {
+    /// Default limits for a single block
+    pub const BLOCK: Self = Self { max_lines: 10_000, max_bytes: 4 * 1024 * 1024 };
+    /// Default limits for all blocks together
+    pub const TOTAL: Self = Self { max_lines: 100_000, max_bytes: 32 * 1024 * 1024 };
+
+    /// Check whether `lines` and `bytes` fit within the limits
+    pub fn allows(&self, lines: usize, bytes: usize) -> bool {
+        lines <= self.max_lines && bytes <= self.max_bytes
+    }
+
+    /// Get the limits trimming aims for once they are exceeded
+    fn low_water(&self) -> Self {
+        Self { max_lines: self.max_lines - self.max_lines / 10, max_bytes: self.max_bytes - self.max_bytes / 10 }
+    }
+}
+
+impl Default for ScrollbackLimits {
+    fn default() -> Self {
+        Self::BLOCK
+    }
+}
+
+/// Scrollback limits per block and across all blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ScrollbackConfig {
+    /// Limits for each block
+    pub block: ScrollbackLimits,
+    /// Limits for all blocks together
+    pub total: ScrollbackLimits,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        Self { block: ScrollbackLimits::BLOCK, total: ScrollbackLimits::TOTAL }
+    }
+}
+
+/// Output text stored as a ring of lines
+///
+/// Every line but the last ends with `\n`. The buffer serializes as a plain
+/// string, so saved sessions keep their earlier format.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct OutputBuffer {
+    /// Retained lines, oldest first
+    lines: VecDeque<String>,
+    /// Total length of the retained lines in bytes
+    bytes: usize,
+    /// Number of lines dropped from the front
+    dropped_lines: usize,
+    /// Lines the cursor is above the bottom row, which is the unfinished last line or the row after it
+    up: usize,
+    /// Whether the next text starts its line again, after a `\r`
+    restart: bool,
+    /// Line last written again in place, counting dropped lines
+    redrawn: Option<usize>,
+}
+
+impl OutputBuffer {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append text, continuing an unterminated last line and applying the cursor moves in it
+    pub fn push_str(&mut self, text: &str) {
+        let mut rest = text;
+        while !rest.is_empty() {
+            let end = rest.find(['\r', '\n', '\x1b']).unwrap_or(rest.len());
+            self.write(&rest[..end]);
+            rest = &rest[end..];
+            if let Some(after) = rest.strip_prefix('\r') {
+                self.restart = true;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('\n') {
+                self.new_line();
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix(ERASE_LINE) {
+                self.erase_line();
+                rest = after;
+            } else if let Some((lines, after)) = rest
+                .strip_prefix("\x1b[")
+                .and_then(|after| after.split_once('A'))
+                .filter(|(lines, _)| lines.chars().all(|c| c.is_ascii_digit()))
+            {
+                self.cursor_up(lines.parse().unwrap_or(1));
+                rest = after;
+            } else if !rest.is_empty() {
+                // Any other escape byte is dropped
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    /// Check whether the last line is unfinished
+    fn open(&self) -> bool {
+        self.lines.back().is_some_and(|line| !line.ends_with('\n'))
+    }
+
+    /// Get the index of the line the cursor is on, `None` being the row after the last line
+    fn cursor_line(&self) -> Option<usize> {
+        let bottom = if self.open() { self.lines.len() - 1 } else { self.lines.len() };
+        let line = bottom.checked_sub(self.up)?;
+        (line < self.lines.len()).then_some(line)
+    }
+
+    /// Write text without line breaks at the cursor
+    fn write(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let Some(index) = self.cursor_line() else {
+            self.bytes += text.len();
+            self.lines.push_back(text.to_string());
+            self.restart = false;
+            return;
+        };
+        let line = &mut self.lines[index];
+        let ending = if line.ends_with('\n') { "\n" } else { "" };
+        let before = line.len();
+        line.truncate(before - ending.len());
+        if self.restart {
+            line.clear();
+            self.redrawn = Some(self.dropped_lines + index);
+        }
+        line.push_str(text);
+        line.push_str(ending);
+        self.bytes = self.bytes - before + line.len();
+        self.restart = false;
+    }
+
+    /// End the line at the cursor, or go down to the next one above the bottom
+    fn new_line(&mut self) {
+        self.restart = false;
+        if self.up > 0 {
+            self.up -= 1;
+            return;
+        }
+        self.bytes += 1;
+        match self.lines.back_mut() {
+            Some(line) if !line.ends_with('\n') => line.push('\n'),
+            _ => self.lines.push_back("\n".to_string()),
+        }
+    }
+
+    /// Erase the line at the cursor, leaving its line break
+    fn erase_line(&mut self) {
+        let Some(index) = self.cursor_line() else {
+            return;
+        };
+        let line = &mut self.lines[index];
+        let ending = if line.ends_with('\n') { "\n" } else { "" };
+        self.bytes -= line.len() - ending.len();
+        line.truncate(0);
+        line.push_str(ending);
+        self.redrawn = Some(self.dropped_lines + index);
+        if ending.is_empty() {
+            self.lines.pop_back();
+        }
+    }
+
+    /// Move the cursor up `lines` lines, stopping at the first one
+    fn cursor_up(&mut self, lines: usize) {
+        let bottom = if self.open() { self.lines.len().saturating_sub(1) } else { self.lines.len() };
+        self.up = (self.up + lines).min(bottom);
+    }
+
+    /// Get the line a progress bar last drew in place, if it is still kept
+    pub fn redrawn_line(&self) -> Option<&str> {
+        let index = self.redrawn?.checked_sub(self.dropped_lines)?;
+        self.lines.get(index).map(|line| line.trim_end_matches('\n'))
+    }
+
+    /// Drop the oldest lines once the buffer exceeds `limits`
+    ///
+    /// Returns the dropped lines, oldest first. The newest line is always kept.
+    pub fn trim_to(&mut self, limits: ScrollbackLimits) -> Vec<String> {
+        if limits.allows(self.lines.len(), self.bytes) {
+            return Vec::new();
+        }
+
+        let target = limits.low_water();
+        let mut dropped = Vec::new();
+        while self.lines.len() > 1 && !target.allows(self.lines.len(), self.bytes) {
+            dropped.extend(self.pop_front());
+        }
+        dropped
+    }
+
+    /// Drop the oldest lines until at least `bytes` bytes are freed
+    pub fn drop_bytes(&mut self, bytes: usize) -> Vec<String> {
+        let mut freed = 0;
+        let mut dropped = Vec::new();
+        while freed < bytes && let Some(line) = self.pop_front() {
+            freed += line.len();
+            dropped.push(line);
+        }
+        dropped
+    }
+
+    /// Remove the oldest line
+    fn pop_front(&mut self) -> Option<String> {
+        let line = self.lines.pop_front()?;
+        self.bytes -= line.len();
+        self.dropped_lines += 1;
+        Some(line)
+    }
+
+    /// Get the retained text
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.bytes);
+        text.extend(self.lines.iter().map(String::as_str));
+        text
+    }
+
+    /// Iterate over the retained lines, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Get the number of retained lines
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Get the size of the retained text in bytes
+    pub fn len(&self) -> usize {
+        self.bytes
+    }
+
+    /// Check whether no text is retained
+    pub fn is_empty(&self) -> bool {
+        self.bytes == 0
+    }
+
+    /// Get the number of lines dropped so far
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped_lines
+    }
+}
+
+impl fmt::Display for OutputBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.lines.iter().try_for_each(|line| f.write_str(line))
+    }
+}
+
+impl PartialEq<&str> for OutputBuffer {
+    fn eq(&self, other: &&str) -> bool {
+        self.bytes == other.len() && self.lines().eq(other.split_inclusive('\n'))
+    }
+}
+
+impl From<String> for OutputBuffer {
+    fn from(text: String) -> Self {
+        let mut buffer = Self::new();
+        buffer.push_str(&text);
+        buffer
+    }
+}
+
+impl From<OutputBuffer> for String {
+    fn from(buffer: OutputBuffer) -> Self {
+        buffer.text()
+    }
+}
+
+/// Trim the oldest output of `blocks` until all of them fit within `limits`
+///
+/// Blocks are trimmed in the order given, so pass the oldest first. Returns
+/// the number of lines dropped.
+pub fn enforce_total<'a>(blocks: impl IntoIterator<Item = &'a mut CommandBlock>, limits: ScrollbackLimits) -> usize {
+    let mut blocks: Vec<&mut CommandBlock> = blocks.into_iter().collect();
+    let mut lines: usize = blocks.iter().map(|block| block.output.line_count()).sum();
+    let mut bytes: usize = blocks.iter().map(|block| block.output.len()).sum();
+    if limits.allows(lines, bytes) {
+        return 0;
+    }
+
+    let target = limits.low_water();
+    let mut dropped = 0;
+    for block in blocks.iter_mut() {
+        while !target.allows(lines, bytes) && !block.output.is_empty() {
+            let (block_lines, block_bytes) = (block.output.line_count(), block.output.len());
+            // Free whole lines: at least the byte excess, or one line for the line excess
+            let excess = bytes.saturating_sub(target.max_bytes).max(1);
+            block.drop_oldest_output(excess);
+            lines -= block_lines - block.output.line_count();
+            bytes -= block_bytes - block.output.len();
+            dropped += block_lines - block.output.line_count();
+        }
+    }
+    dropped
+}
+
+/// Remove the spill directories under `root` whose lock no running instance holds, returning how many were removed
+pub fn prune_spill_dirs(root: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(root) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for dir in entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()) {
+        let Ok(Some(lock)) = FileLock::try_exclusive(&dir) else {
+            continue;
+        };
+        if fs::remove_dir_all(&dir).is_ok() {
+            removed += 1;
+        }
+        let lock_path = lock.path().to_path_buf();
+        drop(lock);
+        let _ = fs::remove_file(lock_path);
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_str_splits_lines() {
+        let mut buffer = OutputBuffer::new();
+        buffer.push_str("one\ntw");
+        buffer.push_str("o\nthree");
+        assert_eq!(buffer.lines().collect::<Vec<_>>(), vec!["one\n", "two\n", "three"]);
+        assert_eq!(buffer, "one\ntwo\nthree");
+        assert_eq!(buffer.len(), 13);
+    }
+
+    #[test]
+    fn test_progress_redraws_lines_in_place() {
+        let mut buffer = OutputBuffer::new();
+        buffer.push_str("Collecting torch\r\n 10% |#");
+        buffer.push_str("    |\r 60% |###  |\r");
+        buffer.push_str("100% |#####|\r\n");
+        assert_eq!(buffer, "Collecting torch\n100% |#####|\n");
+        assert_eq!(buffer.redrawn_line(), Some("100% |#####|"));
+
+        // Several lines redrawn from above, as docker does
+        buffer.push_str("a: Waiting\nb: Waiting\n\x1b[2A\x1b[K\ra: Done\n\x1b[K\rb: 50%\n");
+        assert_eq!(buffer, "Collecting torch\n100% |#####|\na: Done\nb: 50%\n");
+        assert_eq!(buffer.len(), buffer.text().len());
+        assert_eq!(buffer.redrawn_line(), Some("b: 50%"));
+        buffer.push_str("\x1b[9Afirst\n");
+        assert_eq!(buffer.lines().next(), Some("Collecting torchfirst\n"));
+    }
+
+    #[test]
+    fn test_trim_drops_oldest_lines() {
+        let mut buffer = OutputBuffer::new();
+        for i in 0..20 {
+            buffer.push_str(&format!("line {}\n", i));
+        }
+
+        let dropped = buffer.trim_to(ScrollbackLimits { max_lines: 10, max_bytes: usize::MAX });
+        assert_eq!(dropped.len(), 11);
+        assert_eq!(dropped[0], "line 0\n");
+        assert_eq!(buffer.line_count(), 9);
+        assert_eq!(buffer.dropped_lines(), 11);
+        assert_eq!(buffer.lines().next(), Some("line 11\n"));
+        assert!(buffer.trim_to(ScrollbackLimits { max_lines: 10, max_bytes: usize::MAX }).is_empty());
+    }
+
+    #[test]
+    fn test_serializes_as_string() {
+        let buffer = OutputBuffer::from("a\nb".to_string());
+        let json = serde_json::to_string(&buffer).unwrap();
+        assert_eq!(json, "\"a\\nb\"");
+        assert_eq!(serde_json::from_str::<OutputBuffer>(&json).unwrap(), buffer);
+    }
+
+    #[test]
+    fn test_enforce_total_trims_oldest_blocks_first() {
+        let mut old = CommandBlock::new("old".to_string(), "/".to_string());
+        let mut new = CommandBlock::new("new".to_string(), "/".to_string());
+        for i in 0..10 {
+            old.append_output(&format!("old {}\n", i), false);
+            new.append_output(&format!("new {}\n", i), false);
+        }
+
+        let limits = ScrollbackLimits { max_lines: 15, max_bytes: usize::MAX };
+        let dropped = enforce_total([&mut old, &mut new], limits);
+        assert_eq!(dropped, 6);
+        assert_eq!(old.output.line_count(), 4);
+        assert_eq!(new.output.line_count(), 10);
+    }
+
+    #[test]
+    fn test_prune_spill_dirs_keeps_held_ones() {
+        let root = std::env::temp_dir().join(format!("ai-terminal-spill-{}", std::process::id()));
+        let (running, exited) = (root.join("running"), root.join("exited"));
+        fs::create_dir_all(&running).unwrap();
+        fs::create_dir_all(&exited).unwrap();
+        let _held = FileLock::exclusive(&running).unwrap();
+
+        assert_eq!(prune_spill_dirs(&root), 1);
+        assert!(running.exists());
+        assert!(!exited.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
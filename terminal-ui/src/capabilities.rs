@@ -0,0 +1,108 @@
+//! Build capabilities of the AI Terminal
+//!
+//! Distributions can leave optional subsystems out of the binary with cargo
+//! features. This module reports which ones were compiled in, so the UI can
+//! explain a missing subsystem instead of failing when it is used.
+
+use anyhow::{bail, Result};
+use terminal_emulator::SafeDelete;
+
+/// An optional subsystem selected at build time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Highlighting of code blocks in AI responses
+    SyntaxHighlighting,
+    /// Deleting through the platform trash, with undo
+    Trash,
+    /// GPU load and VRAM in the resource monitor
+    Gpu,
+    /// Lua scripts from the config directory
+    Scripting,
+    /// Search by meaning: `/history semantic`, `/index`, `/ask-project` and the intent router
+    Embeddings,
+    /// Images written by commands or shown with `img`
+    Graphics,
+}
+
+impl Capability {
+    /// Every optional subsystem
+    pub const ALL: [Capability; 6] = [
+        Capability::SyntaxHighlighting,
+        Capability::Trash,
+        Capability::Gpu,
+        Capability::Scripting,
+        Capability::Embeddings,
+        Capability::Graphics,
+    ];
+
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::SyntaxHighlighting => "Syntax highlighting",
+            Capability::Trash => "Trash and undo for deletes",
+            Capability::Gpu => "GPU usage in the resource monitor",
+            Capability::Scripting => "Lua scripting",
+            Capability::Embeddings => "Search by meaning",
+            Capability::Graphics => "Images in command output",
+        }
+    }
+
+    /// Cargo feature that enables the subsystem
+    pub fn feature(self) -> &'static str {
+        match self {
+            Capability::SyntaxHighlighting => "syntax-highlighting",
+            Capability::Trash => "trash",
+            Capability::Gpu => "gpu",
+            Capability::Scripting => "lua",
+            Capability::Embeddings => "embeddings",
+            Capability::Graphics => "graphics",
+        }
+    }
+
+    /// Check whether the subsystem was compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            Capability::SyntaxHighlighting => cfg!(feature = "syntax-highlighting"),
+            Capability::Trash => SafeDelete::is_available(),
+            Capability::Gpu => cfg!(feature = "gpu"),
+            Capability::Scripting => cfg!(feature = "lua"),
+            Capability::Embeddings => cfg!(feature = "embeddings"),
+            Capability::Graphics => cfg!(feature = "graphics"),
+        }
+    }
+
+    /// Fail with what is missing when the subsystem was not compiled in
+    pub fn require(self) -> Result<()> {
+        if !self.is_available() {
+            bail!("{} is not built in; it needs the `{}` feature", self.name(), self.feature());
+        }
+        Ok(())
+    }
+}
+
+/// Describe which optional subsystems this build includes
+pub fn report() -> String {
+    let mut lines = vec![format!("AI Terminal {}", env!("CARGO_PKG_VERSION"))];
+    lines.extend(Capability::ALL.iter().map(|capability| {
+        let status = if capability.is_available() { "✓" } else { "✗ not built in" };
+        format!("  {} {} (feature \"{}\")", status, capability.name(), capability.feature())
+    }));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_every_capability() {
+        let report = report();
+        for capability in Capability::ALL {
+            assert!(report.contains(capability.feature()));
+            assert_eq!(capability.require().is_ok(), capability.is_available());
+        }
+        if !Capability::Graphics.is_available() {
+            assert!(Capability::Graphics.require().unwrap_err().to_string().contains("`graphics` feature"));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Saved conversations of the chat pane
+//!
+//! Every conversation of the chat pane is saved in the database as it goes,
+//! with the name of the tab it was held in, and titled by its first
+//! question. The command palette lists them as `chats: <title>`, so typing
+//! `chats:` there browses them; picking one reopens it in the chat pane to
+//! carry on. `/chats` lists them too and `/chats search <text>` finds them
+//! by the text of any message; `/chats open <id>`, `/chats rename <id>
+//! <title>`, `/chats delete <id>` and `/chats export <id> <path>` manage
+//! them, the export being Markdown that goes through the privacy scrubber.
+
+use terminal_emulator::{ConversationInfo, ConversationMessage};
+
+use crate::chat_pane::{Conversation, Message, Role};
+use crate::widgets::command_palette::{Action, Command};
+
+/// Prefix of the command palette ids of saved conversations
+pub const PALETTE_PREFIX: &str = "conversation:";
+
+/// Category of the saved conversations in the command palette
+pub const PALETTE_CATEGORY: &str = "Chats";
+
+/// Most characters of a title taken from the first question
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Title a conversation is saved under: the one it was given, or its first question
+pub fn title(conversation: &Conversation) -> String {
+    if let Some(title) = &conversation.title {
+        return title.clone();
+    }
+    let messages = &conversation.messages;
+    let first = messages.iter().find(|message| message.role == Role::User && message.label.is_none())
+        .map(|message| message.text.as_str())
+        .or_else(|| messages.iter().find_map(|message| message.label.as_deref()))
+        .and_then(|text| text.lines().map(str::trim).find(|line| !line.is_empty()))
+        .unwrap_or("Untitled");
+    let mut title: String = first.chars().take(MAX_TITLE_CHARS).collect();
+    if title.len() < first.len() {
+        title.push('…');
+    }
+    title
+}
+
+/// Convert the messages of a conversation to how they are saved
+pub fn to_saved(messages: &[Message]) -> Vec<ConversationMessage> {
+    messages
+        .iter()
+        .map(|message| ConversationMessage {
+            role: message.role.as_str().to_string(),
+            text: message.text.clone(),
+            label: message.label.clone(),
+            model: message.model.clone(),
+        })
+        .collect()
+}
+
+/// Rebuild a saved conversation, skipping messages of unknown roles
+pub fn from_saved(info: &ConversationInfo, saved: Vec<ConversationMessage>) -> Conversation {
+    let messages = saved
+        .into_iter()
+        .filter_map(|message| Some(Message {
+            role: Role::parse(&message.role)?,
+            text: message.text,
+            label: message.label,
+            model: message.model,
+        }))
+        .collect();
+    Conversation { id: Some(info.id), title: Some(info.title.clone()), messages }
+}
+
+/// Describe a saved conversation in one line, for `/chats`
+pub fn summary(info: &ConversationInfo) -> String {
+    format!(
+        "{}. {} ({} message(s) in {}, {})",
+        info.id, info.title, info.messages, info.tab, info.updated_at.format("%Y-%m-%d %H:%M"),
+    )
+}
+
+/// Render a conversation as Markdown, for `/chats export`
+pub fn to_markdown(title: &str, messages: &[Message]) -> String {
+    let mut out = format!("# {}\n", title);
+    for message in messages {
+        match (message.role, &message.label) {
+            (Role::User, Some(label)) => out.push_str(&format!("\n## You sent: {}\n\n{}\n", label, message.text.trim_end())),
+            (Role::User, None) => out.push_str(&format!("\n## You\n\n{}\n", message.text.trim_end())),
+            (Role::Assistant, _) => out.push_str(&format!(
+                "\n## {}\n\n{}\n",
+                message.model.as_deref().unwrap_or("Assistant"),
+                message.text.trim_end(),
+            )),
+            (Role::Error, _) => out.push_str(&format!("\n> Request failed: {}\n", message.text.trim_end())),
+        }
+    }
+    out
+}
+
+/// Build the palette commands reopening each saved conversation, after one searching them
+pub fn palette_commands(conversations: &[ConversationInfo]) -> Vec<Command> {
+    let search = Command::new(
+        &format!("{}search", PALETTE_PREFIX),
+        "chats: Search Conversations",
+        "List the saved conversations with a message containing the text",
+        PALETTE_CATEGORY,
+        "🔎",
+    )
+        .with_action(Action::Slash("/chats search".to_string()))
+        .with_argument("text");
+    std::iter::once(search)
+        .chain(conversations.iter().map(|info| {
+            let description = format!("{} message(s) in {}, {}", info.messages, info.tab, info.updated_at.format("%Y-%m-%d %H:%M"));
+            Command::new(&format!("{}{}", PALETTE_PREFIX, info.id), &format!("chats: {}", info.title), &description, PALETTE_CATEGORY, "💬")
+                .with_action(Action::Slash(format!("/chats open {}", info.id)))
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn info(id: i64, title: &str) -> ConversationInfo {
+        ConversationInfo { id, title: title.to_string(), tab: "Tab 1".to_string(), messages: 3, created_at: Local::now(), updated_at: Local::now() }
+    }
+
+    #[test]
+    fn test_titles_and_round_trip() {
+        let mut conversation = Conversation::default();
+        assert_eq!(title(&conversation), "Untitled");
+        conversation.messages = vec![
+            Message { role: Role::User, text: "test failed".to_string(), label: Some("Output of cargo test".to_string()), model: None },
+            Message { role: Role::User, text: format!("\n{}", "why ".repeat(20)), label: None, model: None },
+            Message { role: Role::Assistant, text: "A missing file.".to_string(), label: None, model: Some("llama3".to_string()) },
+            Message { role: Role::Error, text: "connection refused".to_string(), label: None, model: None },
+        ];
+        assert_eq!(title(&conversation), format!("{}…", "why ".repeat(15)));
+        conversation.title = Some("Failing tests".to_string());
+        assert_eq!(title(&conversation), "Failing tests");
+
+        let saved = to_saved(&conversation.messages);
+        assert_eq!(saved[2].role, "assistant");
+        let reopened = from_saved(&info(7, "Failing tests"), saved);
+        assert_eq!(reopened, Conversation { id: Some(7), ..conversation.clone() });
+
+        let markdown = to_markdown("Failing tests", &conversation.messages);
+        assert!(markdown.starts_with("# Failing tests\n\n## You sent: Output of cargo test\n\ntest failed\n"));
+        assert!(markdown.contains("\n## llama3\n\nA missing file.\n"));
+        assert!(markdown.ends_with("\n> Request failed: connection refused\n"));
+    }
+
+    #[test]
+    fn test_palette_commands() {
+        let commands = palette_commands(&[info(7, "Failing tests")]);
+        assert_eq!(commands[0].action, Action::Slash("/chats search".to_string()));
+        assert_eq!(commands[1].id, "conversation:7");
+        assert_eq!(commands[1].name, "chats: Failing tests");
+        assert_eq!(commands[1].action, Action::Slash("/chats open 7".to_string()));
+    }
+}
@@ -0,0 +1,331 @@
+//! Chat pane of the AI Terminal
+//!
+//! AI prompts typed as `/prompt` are answered in blocks among the commands.
+//! The chat pane is a conversation with the model kept apart from them, in
+//! a split to the right of the panes with its own scrollback. Every message
+//! goes along with the next question, so follow-ups keep their context.
+//!
+//! F5 or 'Toggle Chat Pane' shows the pane and hands it the input line,
+//! whose text is kept for the terminal meanwhile; Esc hands the input line
+//! back and F5 again hides the pane. `/chat send [n]` adds the output of the
+//! pane's latest block, or the n-th latest, to the conversation with
+//! secrets masked, and `/chat clear` starts over.
+//!
+//! Each tab holds its own conversation, which is saved as it goes; see
+//! [`crate::chat_history`] for reopening saved ones.
+
+use ollama_client::{ChatMessage, ChatRequest};
+use ratatui::layout::Rect;
+
+use crate::keymap::Motion;
+use crate::line_editor::LineEditor;
+
+/// Share of the content area's width the chat pane takes, in percent
+const WIDTH_PERCENT: u16 = 40;
+
+/// Fewest columns the chat pane takes, if the content area has them
+const MIN_WIDTH: u16 = 30;
+
+/// Most bytes of the conversation sent with a question; the oldest messages are left out past it
+pub const MAX_HISTORY_BYTES: usize = 64 * 1024;
+
+/// Who wrote a message of the conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The user, or a block the user sent
+    User,
+    /// The model
+    Assistant,
+    /// The terminal, telling a request failed; not sent to the model
+    Error,
+}
+
+impl Role {
+    /// Get the name the role is sent and saved under
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Error => "error",
+        }
+    }
+
+    /// Look up a role by the name it is saved under
+    pub fn parse(name: &str) -> Option<Self> {
+        [Role::User, Role::Assistant, Role::Error].into_iter().find(|role| role.as_str() == name)
+    }
+}
+
+/// A message of the conversation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub role: Role,
+    /// Text sent to the model, as Markdown
+    pub text: String,
+    /// What a sent block is, shown instead of its text
+    pub label: Option<String>,
+    /// Model that wrote an answer
+    pub model: Option<String>,
+}
+
+impl Message {
+    fn new(role: Role, text: String) -> Self {
+        Self { role, text, label: None, model: None }
+    }
+}
+
+/// A conversation, as each tab holds one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conversation {
+    /// Id in the database, once the conversation is saved
+    pub id: Option<i64>,
+    /// Title given by `/chats rename`, instead of the first question
+    pub title: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+/// The conversation of the active tab, and whether the pane is shown and has the input line
+#[derive(Debug, Default)]
+pub struct ChatPane {
+    conversation: Conversation,
+    visible: bool,
+    focused: bool,
+    /// Rows scrolled back from the newest, 0 following the conversation
+    scroll_back: usize,
+    /// The terminal's input while the chat has the input line, or the chat's while the terminal has it
+    parked: LineEditor,
+}
+
+impl ChatPane {
+    /// Check whether the pane is shown
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Check whether the pane has the input line
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Show the pane and hand it the input line, keeping the text of `input` for the terminal
+    pub fn focus(&mut self, input: &mut LineEditor) {
+        self.visible = true;
+        if !self.focused {
+            std::mem::swap(input, &mut self.parked);
+            self.focused = true;
+        }
+    }
+
+    /// Hand the input line back to the terminal, keeping the pane shown
+    pub fn unfocus(&mut self, input: &mut LineEditor) {
+        if self.focused {
+            std::mem::swap(input, &mut self.parked);
+            self.focused = false;
+        }
+    }
+
+    /// Hide the pane, handing the input line back to the terminal
+    pub fn hide(&mut self, input: &mut LineEditor) {
+        self.unfocus(input);
+        self.visible = false;
+    }
+
+    /// Show the pane with the input line, or hide it; returns whether it is shown
+    pub fn toggle(&mut self, input: &mut LineEditor) -> bool {
+        if self.visible {
+            self.hide(input);
+        } else {
+            self.focus(input);
+        }
+        self.visible
+    }
+
+    /// Get the messages, oldest first
+    pub fn messages(&self) -> &[Message] {
+        &self.conversation.messages
+    }
+
+    /// Get the conversation
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    /// Record the id the conversation was saved under
+    pub fn set_saved(&mut self, id: i64) {
+        self.conversation.id = Some(id);
+    }
+
+    /// Set the conversation's title
+    pub fn set_title(&mut self, title: String) {
+        self.conversation.title = Some(title);
+    }
+
+    /// Put another conversation in the pane, such as another tab's, returning the one it held
+    pub fn replace(&mut self, conversation: Conversation) -> Conversation {
+        self.scroll_back = 0;
+        std::mem::replace(&mut self.conversation, conversation)
+    }
+
+    /// Add a question typed by the user
+    pub fn ask(&mut self, text: String) {
+        self.push(Message::new(Role::User, text));
+    }
+
+    /// Add a block sent by the user, shown as its label
+    pub fn attach(&mut self, label: String, text: String) {
+        self.push(Message { label: Some(label), ..Message::new(Role::User, text) });
+    }
+
+    /// Add the model's answer
+    pub fn reply(&mut self, model: String, text: String) {
+        self.push(Message { model: Some(model), ..Message::new(Role::Assistant, text) });
+    }
+
+    /// Add a note that a request failed
+    pub fn fail(&mut self, error: String) {
+        self.push(Message::new(Role::Error, error));
+    }
+
+    /// Add a message, following the conversation to it
+    fn push(&mut self, message: Message) {
+        self.conversation.messages.push(message);
+        self.scroll_back = 0;
+    }
+
+    /// Start a new conversation, returning how many messages the last one had; a saved one stays saved
+    pub fn clear(&mut self) -> usize {
+        self.replace(Conversation::default()).messages.len()
+    }
+
+    /// Get the rows scrolled back from the newest
+    pub fn scroll_back(&self) -> usize {
+        self.scroll_back
+    }
+
+    /// Scroll the conversation, `page` rows of `total` being in view
+    pub fn scroll(&mut self, motion: Motion, page: usize, total: usize) {
+        let back = self.scroll_back;
+        self.scroll_back = match motion {
+            Motion::LineUp => back + 1,
+            Motion::LineDown => back.saturating_sub(1),
+            Motion::HalfPageUp => back + page / 2,
+            Motion::HalfPageDown => back.saturating_sub(page / 2),
+            Motion::PageUp => back + page,
+            Motion::PageDown => back.saturating_sub(page),
+            Motion::Top => usize::MAX,
+            Motion::Bottom => 0,
+            _ => back,
+        }
+        .min(total.saturating_sub(page));
+    }
+
+    /// Build the request answering the latest question, with as much of the conversation before it as fits
+    ///
+    /// Consecutive messages of the user, such as a sent block and the
+    /// question about it, go as one; failures are left out. Returns `None`
+    /// when the conversation does not end with the user.
+    pub fn request(&self, model: &str, system: Option<String>) -> Option<ChatRequest> {
+        let mut turns: Vec<(Role, String)> = Vec::new();
+        for message in self.conversation.messages.iter().filter(|message| message.role != Role::Error) {
+            match turns.last_mut() {
+                Some((role, text)) if *role == message.role => {
+                    text.push_str("\n\n");
+                    text.push_str(&message.text);
+                }
+                _ => turns.push((message.role, message.text.clone())),
+            }
+        }
+        if turns.last().is_none_or(|(role, _)| *role != Role::User) {
+            return None;
+        }
+        // The oldest turns go first, keeping the conversation starting with the user
+        let mut bytes: usize = turns.iter().map(|(_, text)| text.len()).sum();
+        while turns.len() > 1 && (bytes > MAX_HISTORY_BYTES || turns[0].0 != Role::User) {
+            bytes -= turns.remove(0).1.len();
+        }
+        let mut turns = turns.into_iter();
+        let (_, prompt) = turns.next()?;
+        let mut request = ChatRequest::new(model, prompt);
+        request.system = system;
+        request.turns = turns
+            .map(|(role, text)| ChatMessage::new(role.as_str(), text))
+            .collect();
+        Some(request)
+    }
+}
+
+/// Split the content area into the panes' area and the chat pane's, if it is shown
+pub fn split(area: Rect, visible: bool) -> (Rect, Option<Rect>) {
+    if !visible {
+        return (area, None);
+    }
+    let width = (area.width * WIDTH_PERCENT / 100).max(MIN_WIDTH).min(area.width / 2);
+    let panes = Rect { width: area.width - width, ..area };
+    (panes, Some(Rect { x: panes.right(), width, ..area }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_request() {
+        let mut chat = ChatPane::default();
+        assert!(chat.request("llama3", None).is_none());
+        chat.attach("Output of cargo test".to_string(), "test failed".to_string());
+        chat.ask("why?".to_string());
+        let request = chat.request("llama3", Some("be brief".to_string())).unwrap();
+        assert_eq!(request.prompt, "test failed\n\nwhy?");
+        assert_eq!(request.system.as_deref(), Some("be brief"));
+        assert!(request.turns.is_empty());
+
+        chat.reply("llama3".to_string(), "A missing file.".to_string());
+        assert!(chat.request("llama3", None).is_none());
+        chat.fail("connection refused".to_string());
+        chat.ask("which one?".to_string());
+        let request = chat.request("llama3", None).unwrap();
+        let roles: Vec<&str> = request.turns.iter().map(|turn| turn.role.as_str()).collect();
+        assert_eq!(roles, ["assistant", "user"]);
+        assert_eq!(request.turns[1].content, "which one?");
+
+        // Past the limit the oldest exchange is left out
+        chat.reply("llama3".to_string(), "x".repeat(MAX_HISTORY_BYTES));
+        chat.ask("and now?".to_string());
+        let request = chat.request("llama3", None).unwrap();
+        assert_eq!(request.prompt, "and now?");
+        assert_eq!(chat.clear(), 7);
+    }
+
+    #[test]
+    fn test_focus_parks_the_input() {
+        let mut chat = ChatPane::default();
+        let mut input = LineEditor::new();
+        input.set("cargo build");
+        assert!(chat.toggle(&mut input));
+        assert!(chat.is_focused());
+        assert!(input.is_empty());
+        input.set("what is a crate?");
+        chat.unfocus(&mut input);
+        assert_eq!(input.as_str(), "cargo build");
+        assert!(chat.is_visible());
+        chat.focus(&mut input);
+        assert_eq!(input.as_str(), "what is a crate?");
+        assert!(!chat.toggle(&mut input));
+        assert_eq!(input.as_str(), "cargo build");
+
+        chat.scroll(Motion::PageUp, 10, 25);
+        assert_eq!(chat.scroll_back(), 10);
+        chat.scroll(Motion::Top, 10, 25);
+        assert_eq!(chat.scroll_back(), 15);
+        chat.scroll(Motion::Bottom, 10, 25);
+        assert_eq!(chat.scroll_back(), 0);
+    }
+
+    #[test]
+    fn test_split() {
+        let area = Rect::new(0, 1, 100, 30);
+        assert_eq!(split(area, false), (area, None));
+        assert_eq!(split(area, true), (Rect::new(0, 1, 60, 30), Some(Rect::new(60, 1, 40, 30))));
+        assert_eq!(split(Rect::new(0, 1, 50, 30), true).1.unwrap().width, 25);
+    }
+}
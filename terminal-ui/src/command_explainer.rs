@@ -0,0 +1,811 @@
+//! Explanations of a command line before it runs
+//!
+//! Ctrl+/ with a command on the input line, or 'AI: Explain Command Line'
+//! in the command palette, opens a popup going through the line part by
+//! part: each program, subcommand, flag and argument, and the pipes, chains
+//! and redirections between them. Common tools are explained from the flags
+//! bundled here, like explainshell does from man pages. When the line has
+//! parts they do not cover, the model explains the whole line instead; while
+//! offline the bundled breakdown is shown with those parts marked `?`. Yes
+//! in the popup runs the command, and No leaves it on the input line.
+
+use ollama_client::ChatRequest;
+
+/// A tool whose subcommands and flags are bundled
+struct Tool {
+    /// Name of the program
+    name: &'static str,
+    /// What the program does
+    summary: &'static str,
+    /// What the arguments that are not flags are
+    operands: &'static str,
+    /// Subcommands and what they do
+    subcommands: &'static [(&'static str, &'static str)],
+    /// Flags written as in `-f, --file FILE`, a word after a spelling naming its value, and what they do
+    flags: &'static [(&'static str, &'static str)],
+}
+
+/// Tools explained without the model
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "ls",
+        summary: "list directory contents",
+        operands: "file or directory to list",
+        subcommands: &[],
+        flags: &[
+            ("-l", "long format: permissions, owner, size and modification time"),
+            ("-a, --all", "include entries starting with ."),
+            ("-A, --almost-all", "include hidden entries except . and .."),
+            ("-h, --human-readable", "sizes in K, M and G"),
+            ("-t", "sort by modification time, newest first"),
+            ("-r, --reverse", "reverse the sort order"),
+            ("-S", "sort by size, largest first"),
+            ("-R, --recursive", "list subdirectories recursively"),
+            ("-1", "one entry per line"),
+            ("-d, --directory", "list directories themselves, not their contents"),
+        ],
+    },
+    Tool {
+        name: "cd",
+        summary: "change the working directory",
+        operands: "directory to change to",
+        subcommands: &[],
+        flags: &[],
+    },
+    Tool {
+        name: "rm",
+        summary: "remove files or directories",
+        operands: "file or directory to remove",
+        subcommands: &[],
+        flags: &[
+            ("-r, -R, --recursive", "remove directories and their contents"),
+            ("-f, --force", "ignore missing files and never prompt"),
+            ("-i", "prompt before every removal"),
+            ("-v, --verbose", "print each file removed"),
+            ("-d, --dir", "remove empty directories"),
+        ],
+    },
+    Tool {
+        name: "cp",
+        summary: "copy files and directories",
+        operands: "source, then the destination last",
+        subcommands: &[],
+        flags: &[
+            ("-r, -R, --recursive", "copy directories recursively"),
+            ("-a, --archive", "copy recursively, keeping permissions, times and links"),
+            ("-f, --force", "overwrite destination files that cannot be opened"),
+            ("-i, --interactive", "prompt before overwriting"),
+            ("-n, --no-clobber", "never overwrite existing files"),
+            ("-p", "keep mode, ownership and timestamps"),
+            ("-v, --verbose", "print each file copied"),
+        ],
+    },
+    Tool {
+        name: "mv",
+        summary: "move or rename files",
+        operands: "source, then the destination last",
+        subcommands: &[],
+        flags: &[
+            ("-f, --force", "overwrite without prompting"),
+            ("-i, --interactive", "prompt before overwriting"),
+            ("-n, --no-clobber", "never overwrite existing files"),
+            ("-v, --verbose", "print each file moved"),
+        ],
+    },
+    Tool {
+        name: "mkdir",
+        summary: "create directories",
+        operands: "directory to create",
+        subcommands: &[],
+        flags: &[
+            ("-p, --parents", "create missing parents, and do not fail if it exists"),
+            ("-m, --mode MODE", "set the permissions of new directories"),
+            ("-v, --verbose", "print each directory created"),
+        ],
+    },
+    Tool {
+        name: "chmod",
+        summary: "change file permissions",
+        operands: "mode (as in 755 or u+x), then the files",
+        subcommands: &[],
+        flags: &[
+            ("-R, --recursive", "change files and directories recursively"),
+            ("-v, --verbose", "print each file processed"),
+        ],
+    },
+    Tool {
+        name: "grep",
+        summary: "print lines matching a pattern",
+        operands: "pattern, then the files to search",
+        subcommands: &[],
+        flags: &[
+            ("-i, --ignore-case", "ignore case"),
+            ("-v, --invert-match", "print lines that do not match"),
+            ("-r, --recursive", "search directories recursively"),
+            ("-R, --dereference-recursive", "search recursively, following links"),
+            ("-n, --line-number", "print line numbers"),
+            ("-l, --files-with-matches", "print only names of files with a match"),
+            ("-c, --count", "print only a count of matching lines"),
+            ("-w, --word-regexp", "match whole words only"),
+            ("-x, --line-regexp", "match whole lines only"),
+            ("-E, --extended-regexp", "use extended regular expressions"),
+            ("-F, --fixed-strings", "match the pattern as plain text"),
+            ("-o, --only-matching", "print only the matched parts"),
+            ("-e, --regexp PATTERN", "use PATTERN for matching"),
+            ("-A, --after-context NUM", "print NUM lines after each match"),
+            ("-B, --before-context NUM", "print NUM lines before each match"),
+            ("-C, --context NUM", "print NUM lines around each match"),
+            ("-q, --quiet", "print nothing, only set the exit status"),
+            ("--include GLOB", "search only files matching GLOB"),
+            ("--exclude GLOB", "skip files matching GLOB"),
+            ("--color WHEN", "color the matches"),
+        ],
+    },
+    Tool {
+        name: "find",
+        summary: "search for files in a directory tree",
+        operands: "directory to start from, or a value of the test before",
+        subcommands: &[],
+        flags: &[
+            ("-name PATTERN", "file name matches PATTERN"),
+            ("-iname PATTERN", "file name matches PATTERN, ignoring case"),
+            ("-path PATTERN", "path matches PATTERN"),
+            ("-type TYPE", "file is of TYPE: f file, d directory, l link"),
+            ("-size SIZE", "file size is SIZE, as in +10M"),
+            ("-mtime DAYS", "modified DAYS days ago, as in -7 for the last week"),
+            ("-newer FILE", "modified more recently than FILE"),
+            ("-maxdepth LEVELS", "descend at most LEVELS directories"),
+            ("-mindepth LEVELS", "skip the first LEVELS levels"),
+            ("-user NAME", "owned by user NAME"),
+            ("-empty", "file or directory is empty"),
+            ("-delete", "delete the files found"),
+            ("-print", "print the path of each file found"),
+            ("-print0", "print paths separated by NUL, for xargs -0"),
+            ("-exec COMMAND", "run COMMAND on each file found, {} standing for it, up to \\; or +"),
+            ("-not", "negate the next test"),
+            ("-o, -or", "either the test before or the one after"),
+        ],
+    },
+    Tool {
+        name: "tar",
+        summary: "create or extract archives",
+        operands: "files to archive or extract",
+        subcommands: &[],
+        flags: &[
+            ("-c, --create", "create a new archive"),
+            ("-x, --extract", "extract files from an archive"),
+            ("-t, --list", "list the contents of an archive"),
+            ("-z, --gzip", "compress or decompress with gzip"),
+            ("-j, --bzip2", "compress or decompress with bzip2"),
+            ("-J, --xz", "compress or decompress with xz"),
+            ("-v, --verbose", "list files as they are processed"),
+            ("-f, --file ARCHIVE", "use the archive file ARCHIVE"),
+            ("-C, --directory DIR", "change to DIR first"),
+            ("--exclude PATTERN", "leave out files matching PATTERN"),
+        ],
+    },
+    Tool {
+        name: "curl",
+        summary: "transfer data from or to a URL",
+        operands: "URL",
+        subcommands: &[],
+        flags: &[
+            ("-o, --output FILE", "write the response to FILE"),
+            ("-O, --remote-name", "write the response to a file named as in the URL"),
+            ("-L, --location", "follow redirects"),
+            ("-s, --silent", "hide the progress meter and errors"),
+            ("-S, --show-error", "show errors even when silent"),
+            ("-f, --fail", "fail on HTTP errors without printing the page"),
+            ("-I, --head", "fetch only the headers"),
+            ("-i, --include", "print the response headers too"),
+            ("-v, --verbose", "print the details of the request and response"),
+            ("-X, --request METHOD", "use the HTTP method METHOD"),
+            ("-H, --header HEADER", "add the request header HEADER"),
+            ("-d, --data DATA", "send DATA in a POST request"),
+            ("-u, --user USER:PASSWORD", "log in with USER:PASSWORD"),
+            ("-k, --insecure", "skip TLS certificate checks"),
+        ],
+    },
+    Tool {
+        name: "ps",
+        summary: "list processes",
+        operands: "argument",
+        subcommands: &[],
+        flags: &[
+            ("-e, -A", "every process"),
+            ("-f", "full format, with the command line"),
+            ("-u, --user USER", "processes of USER"),
+            ("-p, --pid PID", "the process PID"),
+            ("aux", "every process, with owner and resource use (BSD style)"),
+        ],
+    },
+    Tool {
+        name: "kill",
+        summary: "send a signal to processes",
+        operands: "process id",
+        subcommands: &[],
+        flags: &[
+            ("-9", "send SIGKILL, which cannot be caught"),
+            ("-15", "send SIGTERM, asking the process to stop"),
+            ("-s SIGNAL", "send SIGNAL"),
+            ("-l", "list signal names"),
+        ],
+    },
+    Tool {
+        name: "du",
+        summary: "estimate disk usage",
+        operands: "file or directory to measure",
+        subcommands: &[],
+        flags: &[
+            ("-h, --human-readable", "sizes in K, M and G"),
+            ("-s, --summarize", "only a total for each argument"),
+            ("-a, --all", "count files as well as directories"),
+            ("-c, --total", "print a grand total"),
+            ("-d, --max-depth DEPTH", "show directories at most DEPTH levels down"),
+        ],
+    },
+    Tool {
+        name: "df",
+        summary: "report free disk space",
+        operands: "file whose file system is reported",
+        subcommands: &[],
+        flags: &[
+            ("-h, --human-readable", "sizes in K, M and G"),
+            ("-T, --print-type", "print the file system type"),
+            ("-i, --inodes", "report inodes instead of blocks"),
+        ],
+    },
+    Tool {
+        name: "head",
+        summary: "print the first lines of files",
+        operands: "file to read",
+        subcommands: &[],
+        flags: &[
+            ("-n, --lines NUM", "print the first NUM lines"),
+            ("-c, --bytes NUM", "print the first NUM bytes"),
+        ],
+    },
+    Tool {
+        name: "tail",
+        summary: "print the last lines of files",
+        operands: "file to read",
+        subcommands: &[],
+        flags: &[
+            ("-n, --lines NUM", "print the last NUM lines"),
+            ("-f, --follow", "keep printing lines as they are added"),
+            ("-F", "follow, reopening the file when it is replaced"),
+            ("-c, --bytes NUM", "print the last NUM bytes"),
+        ],
+    },
+    Tool {
+        name: "sort",
+        summary: "sort lines of text",
+        operands: "file to sort",
+        subcommands: &[],
+        flags: &[
+            ("-n, --numeric-sort", "compare as numbers"),
+            ("-h, --human-numeric-sort", "compare sizes such as 2K and 1G"),
+            ("-r, --reverse", "reverse the order"),
+            ("-u, --unique", "print each line once"),
+            ("-k, --key KEY", "sort by field KEY"),
+            ("-t, --field-separator SEP", "fields are separated by SEP"),
+        ],
+    },
+    Tool {
+        name: "wc",
+        summary: "count lines, words and bytes",
+        operands: "file to count",
+        subcommands: &[],
+        flags: &[
+            ("-l, --lines", "count lines"),
+            ("-w, --words", "count words"),
+            ("-c, --bytes", "count bytes"),
+            ("-m, --chars", "count characters"),
+        ],
+    },
+    Tool {
+        name: "ssh",
+        summary: "log in to a remote host",
+        operands: "host, then a command to run there",
+        subcommands: &[],
+        flags: &[
+            ("-p PORT", "connect to PORT"),
+            ("-i FILE", "use the private key FILE"),
+            ("-L SPEC", "forward a local port, as in 8080:localhost:80"),
+            ("-R SPEC", "forward a remote port to this side"),
+            ("-N", "run no remote command, for forwarding only"),
+            ("-A", "forward the authentication agent"),
+            ("-v", "print debugging messages"),
+            ("-t", "force a terminal, for interactive commands"),
+        ],
+    },
+    Tool {
+        name: "git",
+        summary: "version control",
+        operands: "argument of the subcommand",
+        subcommands: &[
+            ("status", "show the state of the working tree"),
+            ("add", "stage changes for the next commit"),
+            ("commit", "record the staged changes"),
+            ("push", "upload commits to a remote"),
+            ("pull", "fetch and merge changes from a remote"),
+            ("fetch", "download commits from a remote"),
+            ("clone", "copy a repository"),
+            ("checkout", "switch branches or restore files"),
+            ("switch", "switch branches"),
+            ("branch", "list, create or delete branches"),
+            ("merge", "join another branch into the current one"),
+            ("rebase", "replay commits on top of another base"),
+            ("log", "show the commit history"),
+            ("diff", "show changes"),
+            ("reset", "move the current branch, optionally discarding changes"),
+            ("restore", "restore files from the index or a commit"),
+            ("stash", "set changes aside"),
+            ("tag", "list or create tags"),
+            ("remote", "manage remotes"),
+            ("show", "show a commit"),
+            ("cherry-pick", "apply the changes of existing commits"),
+            ("revert", "add commits undoing earlier ones"),
+        ],
+        flags: &[
+            ("-m, --message MESSAGE", "use MESSAGE as the commit message"),
+            ("-a, --all", "all tracked changes, or all branches"),
+            ("-b BRANCH", "create the branch BRANCH"),
+            ("-d, --delete", "delete"),
+            ("-D", "delete even if not merged"),
+            ("-f, --force", "force, overwriting what would be lost"),
+            ("-u, --set-upstream", "remember the remote branch to push to and pull from"),
+            ("-p, --patch", "work on parts of files, or show patches"),
+            ("-v, --verbose", "print more detail"),
+            ("-n", "limit the number of commits, or do a dry run"),
+            ("--amend", "replace the last commit"),
+            ("--hard", "discard all changes in the working tree and index"),
+            ("--soft", "keep changes staged"),
+            ("--oneline", "one line per commit"),
+            ("--graph", "draw the branch structure"),
+            ("--staged, --cached", "the staged changes"),
+            ("--force-with-lease", "force, unless the remote has commits not seen here"),
+            ("--no-verify", "skip the commit and push hooks"),
+            ("--rebase", "rebase instead of merging"),
+            ("--stat", "a summary of changed files"),
+        ],
+    },
+    Tool {
+        name: "docker",
+        summary: "manage containers",
+        operands: "image, container or argument of the subcommand",
+        subcommands: &[
+            ("run", "create and start a container"),
+            ("ps", "list containers"),
+            ("build", "build an image from a Dockerfile"),
+            ("exec", "run a command in a running container"),
+            ("logs", "print a container's output"),
+            ("stop", "stop containers"),
+            ("rm", "remove containers"),
+            ("rmi", "remove images"),
+            ("images", "list images"),
+            ("pull", "download an image"),
+            ("push", "upload an image"),
+            ("compose", "manage multi-container applications"),
+            ("network", "manage networks"),
+            ("volume", "manage volumes"),
+            ("system", "manage Docker itself"),
+        ],
+        flags: &[
+            ("-d, --detach", "run in the background"),
+            ("-i, --interactive", "keep standard input open"),
+            ("-t, --tty", "allocate a terminal"),
+            ("-p, --publish PORTS", "publish a container port, as in 8080:80"),
+            ("-v, --volume VOLUME", "mount a volume, as in ./data:/data"),
+            ("-e, --env VAR", "set an environment variable"),
+            ("--name NAME", "name the container NAME"),
+            ("--rm", "remove the container when it exits"),
+            ("-a, --all", "include stopped containers"),
+            ("-f, --follow", "keep printing new output"),
+            ("-f, --file FILE", "use the Dockerfile FILE"),
+            ("-t, --tag TAG", "tag the image TAG"),
+            ("-q, --quiet", "print only ids"),
+        ],
+    },
+    Tool {
+        name: "cargo",
+        summary: "the Rust package manager",
+        operands: "argument of the subcommand",
+        subcommands: &[
+            ("build", "compile the package"),
+            ("run", "build and run a binary"),
+            ("test", "build and run the tests"),
+            ("check", "check the package for errors without building it"),
+            ("clippy", "run the Clippy lints"),
+            ("fmt", "format the code"),
+            ("add", "add a dependency"),
+            ("update", "update dependencies in Cargo.lock"),
+            ("doc", "build the documentation"),
+            ("install", "install a binary"),
+            ("clean", "remove build output"),
+            ("bench", "run the benchmarks"),
+            ("publish", "upload the package to crates.io"),
+        ],
+        flags: &[
+            ("--release", "build with optimizations"),
+            ("--workspace", "every package of the workspace"),
+            ("-p, --package PACKAGE", "only the package PACKAGE"),
+            ("--all-targets", "tests, benches and examples as well"),
+            ("--all-features", "enable every feature"),
+            ("-F, --features FEATURES", "enable FEATURES"),
+            ("--bin NAME", "only the binary NAME"),
+            ("-j, --jobs N", "run N jobs at once"),
+            ("-q, --quiet", "print less"),
+            ("-v, --verbose", "print more"),
+            ("--", "pass what follows to the program or test binary"),
+        ],
+    },
+];
+
+/// Programs that run the rest of the line, and what they add
+const WRAPPERS: [(&str, &str); 6] = [
+    ("sudo", "run the rest as root"),
+    ("doas", "run the rest as root"),
+    ("env", "run the rest with the environment changed"),
+    ("nohup", "keep the rest running after the terminal closes"),
+    ("time", "report how long the rest takes"),
+    ("exec", "replace the shell with the rest"),
+];
+
+/// Widest part text lined up in a breakdown
+const MAX_PART_WIDTH: usize = 24;
+
+/// Marker of a part that nothing explains
+const UNKNOWN: &str = "?";
+
+/// A piece of a command line and what it does, if known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    /// The piece as written
+    pub text: String,
+    /// What it does
+    pub help: Option<String>,
+}
+
+impl Part {
+    fn new(text: impl Into<String>, help: Option<String>) -> Self {
+        Self { text: text.into(), help }
+    }
+}
+
+/// A command line broken down into parts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// The parts in order
+    pub parts: Vec<Part>,
+}
+
+impl Breakdown {
+    /// Check whether every part is explained
+    pub fn is_complete(&self) -> bool {
+        self.parts.iter().all(|part| part.help.is_some())
+    }
+
+    /// Lay the parts out one per line with their explanations lined up
+    pub fn describe(&self) -> String {
+        let width = self.parts.iter().map(|part| part.text.chars().count()).filter(|width| *width <= MAX_PART_WIDTH).max().unwrap_or(0);
+        let lines: Vec<String> = self
+            .parts
+            .iter()
+            .map(|part| format!("{:<width$}  {}", part.text, part.help.as_deref().unwrap_or(UNKNOWN), width = width))
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// A word or operator of a command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Word(String),
+    Operator(String),
+}
+
+/// Split a command line into words, keeping quotes as written, and operators
+pub(crate) fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(open) = quote {
+            word.push(c);
+            if c == open {
+                quote = None;
+            } else if c == '\\' && open == '"'
+                && let Some(escaped) = chars.next()
+            {
+                word.push(escaped);
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => {
+                quote = Some(c);
+                word.push(c);
+            }
+            '\\' => {
+                word.push(c);
+                word.extend(chars.next());
+            }
+            '|' | '&' | ';' | '<' | '>' => {
+                // A file descriptor in front of a redirection belongs to it, as in 2>
+                let mut operator = if (c == '<' || c == '>') && (word == "1" || word == "2") {
+                    std::mem::take(&mut word)
+                } else {
+                    String::new()
+                };
+                if !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                }
+                operator.push(c);
+                if chars.peek() == Some(&c) && c != '<' {
+                    operator.push(c);
+                    chars.next();
+                } else if c == '&' && chars.peek() == Some(&'>') {
+                    operator.push('>');
+                    chars.next();
+                }
+                // 2>&1 sends one descriptor to another
+                if operator.ends_with('>') && chars.peek() == Some(&'&') {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    if let Some(fd) = ahead.next().filter(char::is_ascii_digit) {
+                        operator.push('&');
+                        operator.push(fd);
+                        chars.next();
+                        chars.next();
+                    }
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+/// Explain an operator between commands, or a redirection to `target`
+fn operator_help(operator: &str, target: Option<&str>) -> Option<String> {
+    let target = target.unwrap_or("the file");
+    let help = match operator {
+        "|" => "send the output into the next command".to_string(),
+        "||" => "run the next command only if this one fails".to_string(),
+        "&&" => "run the next command only if this one succeeds".to_string(),
+        ";" => "then run the next command".to_string(),
+        "&" => "run the command before in the background".to_string(),
+        ">" | "1>" => format!("write the output to {}, replacing it", target),
+        ">>" | "1>>" => format!("append the output to {}", target),
+        "<" => format!("read the input from {}", target),
+        "2>" => format!("write the errors to {}, replacing it", target),
+        "2>>" => format!("append the errors to {}", target),
+        "&>" => format!("write the output and errors to {}", target),
+        "&>>" => format!("append the output and errors to {}", target),
+        "2>&1" => "send the errors where the output goes".to_string(),
+        "1>&2" | ">&2" => "send the output where the errors go".to_string(),
+        _ => return None,
+    };
+    Some(help)
+}
+
+/// Check whether an operator redirects to the word after it
+fn is_redirection(operator: &str) -> bool {
+    operator.contains(['<', '>']) && !operator.ends_with(|c: char| c.is_ascii_digit())
+}
+
+/// Break a command line down using the bundled tools
+pub fn breakdown(line: &str) -> Breakdown {
+    let mut parts = Vec::new();
+    let mut words = Vec::new();
+    let mut redirections = Vec::new();
+    let mut tokens = tokenize(line).into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Operator(operator) if is_redirection(&operator) => match tokens.next() {
+                Some(Token::Word(target)) => {
+                    let help = operator_help(&operator, Some(&target));
+                    redirections.push(Part::new(format!("{} {}", operator, target), help));
+                }
+                _ => redirections.push(Part::new(operator, None)),
+            },
+            Token::Operator(operator) if operator.contains(['<', '>']) => {
+                let help = operator_help(&operator, None);
+                redirections.push(Part::new(operator, help));
+            }
+            Token::Operator(operator) => {
+                explain_simple(&std::mem::take(&mut words), &mut parts);
+                parts.append(&mut redirections);
+                let help = operator_help(&operator, None);
+                parts.push(Part::new(operator, help));
+            }
+        }
+    }
+    explain_simple(&words, &mut parts);
+    parts.append(&mut redirections);
+    Breakdown { parts }
+}
+
+/// Explain the words of a simple command
+fn explain_simple(words: &[String], parts: &mut Vec<Part>) {
+    let mut words = words.iter().peekable();
+    // Variables set for the command and wrappers come before the program
+    while let Some(word) = words.peek() {
+        if let Some((name, _)) = word.split_once('=').filter(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')) {
+            parts.push(Part::new(word.as_str(), Some(format!("set the environment variable {} for the command", name))));
+        } else if let Some((_, help)) = WRAPPERS.iter().find(|(wrapper, _)| wrapper == word) {
+            parts.push(Part::new(word.as_str(), Some(help.to_string())));
+        } else {
+            break;
+        }
+        words.next();
+    }
+    let Some(program) = words.next() else {
+        return;
+    };
+    let name = program.rsplit('/').next().unwrap_or(program);
+    let Some(tool) = TOOLS.iter().find(|tool| tool.name == name) else {
+        parts.push(Part::new(program.as_str(), None));
+        parts.extend(words.map(|word| Part::new(word.as_str(), None)));
+        return;
+    };
+    parts.push(Part::new(program.as_str(), Some(tool.summary.to_string())));
+    let mut subcommand = tool.subcommands.is_empty();
+    let mut flags_ended = false;
+    let mut first = true;
+    while let Some(word) = words.next() {
+        if !subcommand && !word.starts_with('-') {
+            subcommand = true;
+            let help = tool.subcommands.iter().find(|(name, _)| name == word).map(|(_, help)| help.to_string());
+            parts.push(Part::new(word.as_str(), help));
+            continue;
+        }
+        if word == "--" && !flags_ended {
+            flags_ended = true;
+            let help = lookup(tool, "--").map_or("end of the flags; what follows are arguments", |(_, help)| help);
+            parts.push(Part::new(word.as_str(), Some(help.to_string())));
+            continue;
+        }
+        // tar and ps take their first flags without a dash, as in `tar xzf` and `ps aux`
+        let bundled = first && !word.starts_with('-') && matches!(tool.name, "tar" | "ps");
+        first = false;
+        if bundled {
+            match lookup(tool, word) {
+                Some((_, help)) => parts.push(Part::new(word.as_str(), Some(help.to_string()))),
+                None => explain_short_flags(tool, word, "", &mut words, parts),
+            }
+        } else if flags_ended || !word.starts_with('-') || word == "-" || (word[1..].parse::<f64>().is_ok() && lookup(tool, word).is_none()) {
+            parts.push(Part::new(word.as_str(), Some(tool.operands.to_string())));
+        } else if let Some(long) = word.strip_prefix("--") {
+            let (flag, inline) = match long.split_once('=') {
+                Some((flag, value)) => (format!("--{}", flag), Some(value)),
+                None => (word.to_string(), None),
+            };
+            match lookup(tool, &flag) {
+                Some((true, help)) if inline.is_none() => {
+                    let text = words.next().map_or(word.to_string(), |value| format!("{} {}", word, value));
+                    parts.push(Part::new(text, Some(help.to_string())));
+                }
+                Some((_, help)) => parts.push(Part::new(word.as_str(), Some(help.to_string()))),
+                None => parts.push(Part::new(word.as_str(), None)),
+            }
+        } else if let Some((takes_value, help)) = lookup(tool, word) {
+            // Single-dash long flags, as find has
+            let text = match takes_value.then(|| words.next()).flatten() {
+                Some(value) => format!("{} {}", word, value),
+                None => word.to_string(),
+            };
+            parts.push(Part::new(text, Some(help.to_string())));
+        } else {
+            explain_short_flags(tool, &word[1..], "-", &mut words, parts);
+        }
+    }
+}
+
+/// Explain short flags written together, as in `-xzvf archive.tgz`
+fn explain_short_flags<'a>(
+    tool: &Tool,
+    letters: &str,
+    dash: &str,
+    words: &mut impl Iterator<Item = &'a String>,
+    parts: &mut Vec<Part>,
+) {
+    for (i, letter) in letters.char_indices() {
+        let flag = format!("-{}", letter);
+        match lookup(tool, &flag) {
+            Some((true, help)) => {
+                // The value is the rest of the word, or the next word
+                let rest = &letters[i + letter.len_utf8()..];
+                let value = if rest.is_empty() { words.next().cloned() } else { Some(rest.to_string()) };
+                let text = match value {
+                    Some(value) => format!("{}{} {}", dash, letter, value),
+                    None => format!("{}{}", dash, letter),
+                };
+                parts.push(Part::new(text, Some(help.to_string())));
+                return;
+            }
+            Some((false, help)) => parts.push(Part::new(format!("{}{}", dash, letter), Some(help.to_string()))),
+            None => parts.push(Part::new(format!("{}{}", dash, letter), None)),
+        }
+    }
+}
+
+/// Find a flag of a tool, returning whether it takes a value and what it does
+fn lookup(tool: &Tool, flag: &str) -> Option<(bool, &'static str)> {
+    tool.flags.iter().find_map(|(spec, help)| {
+        // The value named after the last spelling goes with every spelling
+        let takes_value = spec.rsplit(", ").next().is_some_and(|last| last.contains(' '));
+        let mut spellings = spec.split(", ").map(|spelling| spelling.split(' ').next().unwrap_or_default());
+        spellings.any(|spelling| spelling == flag).then_some((takes_value, *help))
+    })
+}
+
+/// Build the request asking the model to explain a command line part by part
+pub fn request(model: &str, line: &str) -> ChatRequest {
+    let prompt = format!(
+        "Explain this shell command to someone about to run it, part by part: the program, each subcommand, \
+         flag and argument, and any pipes, chains or redirections. Answer with one line per part, in order, \
+         as `part: what it does`, then one line saying whether it changes or deletes anything. No other \
+         text.\n\n{}",
+        line,
+    );
+    ChatRequest::new(model, prompt)
+}
+
+/// Tidy the model's explanation for the popup
+pub fn parse_response(response: &str) -> Option<String> {
+    let lines: Vec<&str> = response
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', '•']).trim_start())
+        .filter(|line| !line.is_empty() && !line.starts_with("```"))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(breakdown: &Breakdown) -> Vec<&str> {
+        breakdown.parts.iter().map(|part| part.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let tar = breakdown("sudo tar -xzvf backup.tgz -C /srv 2>&1 | tail -n 5 > log.txt");
+        assert_eq!(texts(&tar), ["sudo", "tar", "-x", "-z", "-v", "-f backup.tgz", "-C /srv", "2>&1", "|", "tail", "-n 5", "> log.txt"]);
+        assert!(tar.is_complete());
+        assert_eq!(tar.parts[5].help.as_deref(), Some("use the archive file ARCHIVE"));
+        assert_eq!(tar.parts[11].help.as_deref(), Some("write the output to log.txt, replacing it"));
+
+        let git = breakdown("GIT_TRACE=1 git commit -am \"fix: a | b\" && git push --force-with-lease");
+        assert_eq!(texts(&git), ["GIT_TRACE=1", "git", "commit", "-a", "-m \"fix: a | b\"", "&&", "git", "push", "--force-with-lease"]);
+        assert!(git.is_complete());
+
+        assert_eq!(texts(&breakdown("tar xzf a.tgz")), ["tar", "x", "z", "f a.tgz"]);
+        assert_eq!(texts(&breakdown("find . -name '*.rs' -type f")), ["find", ".", "-name '*.rs'", "-type f"]);
+
+        let unknown = breakdown("ls -Q && frobnicate --all");
+        assert!(!unknown.is_complete());
+        assert_eq!(unknown.describe(), "ls          list directory contents\n-Q          ?\n&&          run the next command only if this one succeeds\nfrobnicate  ?\n--all       ?");
+    }
+
+    #[test]
+    fn test_responses() {
+        assert!(request("llama3", "ls -Q").prompt.ends_with("ls -Q"));
+        assert_eq!(parse_response("```\n- ls: list files\n\n- -Q: quote names\n```"), Some("ls: list files\n-Q: quote names".to_string()));
+        assert_eq!(parse_response(" \n"), None);
+    }
+}
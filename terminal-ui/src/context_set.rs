@@ -0,0 +1,165 @@
+//! Context attached to every AI request
+//!
+//! `/attach <path>...` and `/attach-block [n]` add files and command output
+//! to a context set that is sent along with each AI prompt until it is
+//! changed, instead of once as with the file browser. The set is shown in a
+//! panel over the right of the panes while it holds anything; `/context`
+//! lists it, `/context remove <n>` drops one item and `/context clear`
+//! empties it. Files are read when attached, so attaching one again brings
+//! it up to date.
+//!
+//! Command output keeps its last [`MAX_BLOCK_BYTES`], where errors and
+//! summaries are, and the set as a whole is limited to [`MAX_TOTAL_BYTES`]
+//! so it cannot crowd the prompt out of the model's context.
+
+use anyhow::{bail, Result};
+use terminal_emulator::CommandBlock;
+
+use crate::prompt_guard::ContextPiece;
+use crate::widgets::models_view::format_size;
+
+/// Most bytes of a command's output attached
+pub const MAX_BLOCK_BYTES: usize = 32 * 1024;
+
+/// Most bytes the whole set holds
+pub const MAX_TOTAL_BYTES: usize = 128 * 1024;
+
+/// A file or command output in the context set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextItem {
+    /// What the item is, such as `File src/main.rs`; attaching the same again replaces it
+    pub label: String,
+    /// Text sent to the model
+    pub text: String,
+}
+
+impl ContextItem {
+    /// Create the item of a file, with its text as [`crate::file_browser::file_context`] builds it
+    pub fn file(shown_path: &str, text: String) -> Self {
+        Self { label: format!("File {}", shown_path), text }
+    }
+
+    /// Create the item of a block's output, as given with secrets masked, keeping its end
+    ///
+    /// The CPU time and memory the command used go along, so the model can tell why it was slow.
+    pub fn block(block: &CommandBlock, output: &str) -> Self {
+        let output = output.trim_end();
+        let mut start = output.len().saturating_sub(MAX_BLOCK_BYTES);
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        let cut = if start > 0 { format!("[… the first {} bytes are left out]\n", start) } else { String::new() };
+        let exit = block.exit_code.map_or(String::new(), |code| format!(", which exited with {}", code));
+        let usage = block.usage.map_or(String::new(), |usage| format!("\n{}", usage.describe(block.duration.unwrap_or_default())));
+        Self {
+            label: format!("Output of {}", block.command),
+            text: format!("Output of the command `{}`{}:\n```\n{}{}\n```{}", block.command, exit, cut, &output[start..], usage),
+        }
+    }
+}
+
+/// The files and outputs attached to AI requests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextSet {
+    items: Vec<ContextItem>,
+}
+
+impl ContextSet {
+    /// Add an item, replacing one with the same label, unless the set would grow past its limit
+    pub fn attach(&mut self, item: ContextItem) -> Result<()> {
+        let others: usize = self.items.iter().filter(|other| other.label != item.label).map(|other| other.text.len()).sum();
+        if others + item.text.len() > MAX_TOTAL_BYTES {
+            bail!(
+                "{} ({}) does not fit in the context, which holds {} of {}; /context remove <n> makes room",
+                item.label, format_size(item.text.len() as u64), format_size(others as u64), format_size(MAX_TOTAL_BYTES as u64),
+            );
+        }
+        match self.items.iter_mut().find(|other| other.label == item.label) {
+            Some(other) => *other = item,
+            None => self.items.push(item),
+        }
+        Ok(())
+    }
+
+    /// Remove the item numbered `number`, counted from 1
+    pub fn remove(&mut self, number: usize) -> Option<ContextItem> {
+        (1..=self.items.len()).contains(&number).then(|| self.items.remove(number - 1))
+    }
+
+    /// Remove every item, returning how many there were
+    pub fn clear(&mut self) -> usize {
+        std::mem::take(&mut self.items).len()
+    }
+
+    /// Get the items in the order they were attached
+    pub fn items(&self) -> &[ContextItem] {
+        &self.items
+    }
+
+    /// Whether nothing is attached
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Get the bytes of text attached
+    pub fn bytes(&self) -> usize {
+        self.items.iter().map(|item| item.text.len()).sum()
+    }
+
+    /// Build the pieces of context added to a request
+    pub fn pieces(&self) -> Vec<ContextPiece> {
+        self.items.iter().map(|item| ContextPiece::context(item.label.clone(), item.text.clone())).collect()
+    }
+
+    /// List the items with their numbers and sizes, for `/context`
+    pub fn describe(&self) -> String {
+        if self.items.is_empty() {
+            return "Nothing is attached. /attach <path> adds a file and /attach-block [n] a command's output.".to_string();
+        }
+        let mut lines: Vec<String> = self.items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| format!("{}. {} ({})", i + 1, item.label, format_size(item.text.len() as u64)))
+            .collect();
+        lines.push(format!(
+            "\n{} of {} attached to every AI request; /context remove <n> or /context clear drops them.",
+            format_size(self.bytes() as u64), format_size(MAX_TOTAL_BYTES as u64),
+        ));
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use terminal_emulator::ResourceUsage;
+
+    #[test]
+    fn test_context_set() {
+        let mut set = ContextSet::default();
+        set.attach(ContextItem::file("src/main.rs", "fn main() {}".to_string())).unwrap();
+        let mut block = CommandBlock::new("cargo test".to_string(), "/src".to_string());
+        block.complete(101, Duration::from_secs(1));
+        set.attach(ContextItem::block(&block, "test failed\n")).unwrap();
+        set.attach(ContextItem::file("src/main.rs", "fn main() { run() }".to_string())).unwrap();
+        assert_eq!(set.items().len(), 2);
+        assert_eq!(set.items()[0].text, "fn main() { run() }");
+        assert_eq!(set.items()[1].text, "Output of the command `cargo test`, which exited with 101:\n```\ntest failed\n```");
+        assert_eq!(set.pieces()[1].label, "Output of cargo test");
+        assert!(set.describe().starts_with("1. File src/main.rs (19 B)\n2. Output of cargo test"));
+
+        block.set_usage(ResourceUsage { user_time: Duration::from_secs(3), system_time: Duration::ZERO, max_rss: 2048 });
+        assert!(ContextItem::block(&block, "").text.ends_with("```\nIt took 1s of wall time and 3s of CPU time (3s user, 0ns system, 300% of the wall time), with at most 2.0 KB of memory resident."));
+
+        let long = ContextItem::block(&CommandBlock::new("yes".to_string(), "/src".to_string()), &"y\n".repeat(MAX_BLOCK_BYTES));
+        assert!(long.text.contains("[… the first"));
+        assert!(long.text.len() < MAX_BLOCK_BYTES + 200);
+        assert!(set.attach(ContextItem::file("big.log", "x".repeat(MAX_TOTAL_BYTES))).is_err());
+
+        assert_eq!(set.remove(1).map(|item| item.label), Some("File src/main.rs".to_string()));
+        assert_eq!(set.remove(5), None);
+        assert_eq!(set.clear(), 1);
+        assert!(set.is_empty());
+    }
+}
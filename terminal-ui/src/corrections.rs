@@ -0,0 +1,263 @@
+//! Corrections of failed commands
+//!
+//! When a command fails with an error the terminal recognizes, a corrected
+//! command is shown under its block, and Tab on an empty input line runs
+//! it. The rules cover a mistyped program (`gti status` when `git` is on the
+//! `PATH`), the suggestions tools print themselves (git's "The most similar
+//! command is", clap's "a similar argument exists", `git push
+//! --set-upstream`) and commands refused for lack of permissions, which are
+//! run again with `sudo`.
+//!
+//! With `ai = true` in the `[corrections]` section, the model is asked for
+//! a correction of failures no rule covers:
+//!
+//! ```toml
+//! [corrections]
+//! enabled = true   # the default
+//! ai = true        # off by default
+//! ```
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use ollama_client::ChatRequest;
+use regex::Regex;
+use serde::Deserialize;
+
+/// Most edits between a mistyped program and the one suggested
+const MAX_DISTANCE: usize = 2;
+
+/// Output saying a command needs more permissions than it had
+const PERMISSION_ERRORS: [&str; 6] = [
+    "permission denied",
+    "operation not permitted",
+    "are you root",
+    "must be run as root",
+    "requires superuser",
+    "eacces",
+];
+
+/// Lines at the end of the output sent to the model
+const OUTPUT_LINES: usize = 40;
+
+/// Answer of the model when it has no correction
+const NO_CORRECTION: &str = "NONE";
+
+/// Settings from the `[corrections]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CorrectionConfig {
+    /// Whether corrections are suggested at all
+    pub enabled: bool,
+    /// Whether the model is asked when no rule matches
+    pub ai: bool,
+}
+
+impl Default for CorrectionConfig {
+    fn default() -> Self {
+        Self { enabled: true, ai: false }
+    }
+}
+
+/// Suggest a corrected command for a failed one from its output, which should already be masked for secrets
+///
+/// `programs` lists the programs that can be run, and is only called when
+/// the program was not found.
+pub fn suggest(command: &str, output: &str, programs: impl FnOnce() -> Vec<String>) -> Option<String> {
+    let command = command.trim();
+    let suggestion = tool_suggestion(command, output)
+        .or_else(|| missing_program(output).and_then(|missing| respell(command, missing, &programs())))
+        .or_else(|| needs_sudo(command, output));
+    suggestion.filter(|suggestion| suggestion != command)
+}
+
+/// Use the correction a tool printed itself
+fn tool_suggestion(command: &str, output: &str) -> Option<String> {
+    static UPSTREAM: OnceLock<Regex> = OnceLock::new();
+    static SIMILAR_COMMAND: OnceLock<Regex> = OnceLock::new();
+    static SIMILAR_ARGUMENT: OnceLock<Regex> = OnceLock::new();
+    let upstream = UPSTREAM.get_or_init(|| Regex::new(r"(?m)^\s+(git push --set-upstream \S+ \S+)\s*$").expect("upstream pattern is valid"));
+    if let Some(captures) = upstream.captures(output) {
+        return Some(captures[1].to_string());
+    }
+    // git: 'stauts' is not a git command ... The most similar command is\n\tstatus
+    let similar_command = SIMILAR_COMMAND.get_or_init(|| {
+        Regex::new(r"'(\S+)' is not a \S+ command[\s\S]*?(?:most similar commands? (?:is|are)|Did you mean (?:this|one of these)\?)\s*\n\s+(\S+)")
+            .expect("similar command pattern is valid")
+    });
+    if let Some(captures) = similar_command.captures(output) {
+        return replace_word(command, &captures[1], &captures[2]);
+    }
+    // clap: error: unexpected argument '--verbos' found ... tip: a similar argument exists: '--verbose'
+    let similar_argument = SIMILAR_ARGUMENT.get_or_init(|| {
+        Regex::new(r"(?:unexpected|unrecognized|unknown) (?:argument|option|flag) '([^']+)'[\s\S]*?similar (?:argument|option|flag) exists: '([^']+)'")
+            .expect("similar argument pattern is valid")
+    });
+    let captures = similar_argument.captures(output)?;
+    let wrong = captures[1].split('=').next().unwrap_or_default();
+    let right = captures[2].split_whitespace().next().unwrap_or_default();
+    replace_word(command, wrong, right)
+}
+
+/// Find the program a shell could not find
+fn missing_program(output: &str) -> Option<&str> {
+    static NOT_FOUND: OnceLock<Vec<Regex>> = OnceLock::new();
+    // zsh's message is tried first, as the others would take its "zsh:" for the program
+    let not_found = NOT_FOUND.get_or_init(|| {
+        [
+            r"command not found: (\S+)",
+            r"(\S+): command not found",
+            r"'(\S+)' is not recognized as an internal or external command",
+            r"(?m)(\S+): not found$",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("not found pattern is valid"))
+        .collect()
+    });
+    not_found.iter().find_map(|pattern| pattern.captures(output)).and_then(|captures| captures.get(1)).map(|found| found.as_str())
+}
+
+/// Replace a mistyped program with the nearest one that can be run
+fn respell(command: &str, missing: &str, programs: &[String]) -> Option<String> {
+    let first = command.split_whitespace().next()?;
+    if first != missing {
+        return None;
+    }
+    let nearest = programs
+        .iter()
+        .map(|program| (distance(missing, program), program))
+        .filter(|(distance, _)| *distance <= MAX_DISTANCE && *distance < missing.chars().count())
+        .min_by_key(|(distance, program)| (*distance, program.len()))?;
+    replace_word(command, missing, nearest.1)
+}
+
+/// Run a command refused for lack of permissions again with `sudo`
+fn needs_sudo(command: &str, output: &str) -> Option<String> {
+    if cfg!(windows) || command.starts_with("sudo ") {
+        return None;
+    }
+    let output = output.to_lowercase();
+    PERMISSION_ERRORS.iter().any(|error| output.contains(error)).then(|| format!("sudo {}", command))
+}
+
+/// Replace the first word of `command` equal to `wrong`
+fn replace_word(command: &str, wrong: &str, right: &str) -> Option<String> {
+    let mut replaced = false;
+    let words: Vec<&str> = command
+        .split(' ')
+        .map(|word| {
+            if !replaced && word == wrong {
+                replaced = true;
+                right
+            } else {
+                word
+            }
+        })
+        .collect();
+    replaced.then(|| words.join(" "))
+}
+
+/// Count the edits turning one word into another, a swap of neighbours counting as one
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// List the programs in the directories of `PATH`
+pub fn path_programs() -> Vec<String> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut programs = HashSet::new();
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|kind| !kind.is_dir()) {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                programs.insert(name.strip_suffix(".exe").map(str::to_string).unwrap_or(name));
+            }
+        }
+    }
+    programs.into_iter().collect()
+}
+
+/// Build the request asking the model for a corrected command
+pub fn request(model: &str, command: &str, output: &str) -> ChatRequest {
+    let prompt = format!(
+        "This shell command failed:\n`{}`\n\nIts output ended with:\n```\n{}\n```\n\n\
+         If a small change to the command fixes it, such as a typo, a missing flag or argument, answer with \
+         only the corrected command on one line, without quotes or explanation. Otherwise answer {}.",
+        command, tail(output), NO_CORRECTION,
+    );
+    ChatRequest::new(model, prompt)
+}
+
+/// Get the last lines of the output, where errors usually are
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(OUTPUT_LINES)..].join("\n")
+}
+
+/// Read the corrected command from the model's answer, or `None` if it had none
+pub fn parse_response(response: &str, command: &str) -> Option<String> {
+    let line = response.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with("```"))?;
+    let line = line.trim_matches('`').trim();
+    (line != NO_CORRECTION && !line.is_empty() && line != command.trim()).then(|| line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn programs() -> Vec<String> {
+        ["git", "grep", "gzip", "ls"].map(str::to_string).to_vec()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(suggest("gti status", "bash: gti: command not found", programs), Some("git status".to_string()));
+        assert_eq!(suggest("sl -la", "zsh: command not found: sl", programs), Some("ls -la".to_string()));
+        assert_eq!(suggest("xyzzy", "bash: xyzzy: command not found", programs), None);
+
+        let git = "git: 'stauts' is not a git command. See 'git --help'.\n\nThe most similar command is\n\tstatus\n";
+        assert_eq!(suggest("git stauts -s", git, programs), Some("git status -s".to_string()));
+        let clap = "error: unexpected argument '--verbos' found\n\n  tip: a similar argument exists: '--verbose'\n";
+        assert_eq!(suggest("cargo build --verbos", clap, programs), Some("cargo build --verbose".to_string()));
+        let push = "fatal: The current branch main has no upstream branch.\nTo push the current branch and set the remote as upstream, use\n\n    git push --set-upstream origin main\n";
+        assert_eq!(suggest("git push", push, programs), Some("git push --set-upstream origin main".to_string()));
+
+        if !cfg!(windows) {
+            assert_eq!(suggest("apt install jq", "E: Could not open lock file - open (13: Permission denied)", programs), Some("sudo apt install jq".to_string()));
+            assert_eq!(suggest("sudo apt install jq", "Permission denied", programs), None);
+        }
+        assert_eq!(suggest("make", "make: *** No targets specified", programs), None);
+    }
+
+    #[test]
+    fn test_distance_and_responses() {
+        assert_eq!(distance("gti", "git"), 1);
+        assert_eq!(distance("grpe", "grep"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert!(request("llama3", "tar xz f.tgz", "tar: f.tgz: Cannot open").prompt.contains("`tar xz f.tgz`"));
+        assert_eq!(parse_response("```\ntar xzf f.tgz\n```", "tar xz f.tgz"), Some("tar xzf f.tgz".to_string()));
+        assert_eq!(parse_response("NONE", "tar xz f.tgz"), None);
+    }
+}
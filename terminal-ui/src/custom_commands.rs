@@ -0,0 +1,167 @@
+//! Custom palette commands for the AI Terminal
+//!
+//! Besides its own commands, the command palette offers commands defined in
+//! the `[[commands]]` section of the configuration and in plugin files: TOML
+//! files in the `commands` directory of the config directory, each holding
+//! `[[commands]]` tables listed under the file's name unless they give a
+//! category. A command does one of four things:
+//!
+//! ```toml
+//! [[commands]]
+//! name = "Deploy"
+//! description = "Deploy the app to an environment"
+//! run = "./deploy.sh {{ENV}}"        # run a shell command
+//! # insert = "git commit -m ''"      # or put text on the input line
+//! # ask = "Review the diff of {{FILE}}" # or send a prompt to the AI
+//! # slash = "/workflow run ship"     # or run a slash command
+//! ```
+//!
+//! `{{NAME}}` placeholders are the command's arguments: words typed after
+//! its name in the palette fill them in order, and those left are filled
+//! from the environment or asked for, as for shell commands. Only `run`
+//! commands may have `{{NAME:secret}}` placeholders, since only a shell
+//! command can keep the value out of its text.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::placeholders;
+use crate::widgets::command_palette::{Action, Command};
+
+/// Prefix of the command palette ids of custom commands
+pub const PALETTE_PREFIX: &str = "custom:";
+
+/// Category of configured commands that do not give one
+pub const DEFAULT_CATEGORY: &str = "Custom";
+
+/// A command defined in the configuration or a plugin file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CustomCommand {
+    /// Name shown and searched in the palette
+    pub name: String,
+    /// What the command does; its text if not given
+    #[serde(default)]
+    pub description: String,
+    /// Section of the palette it is listed in
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Icon shown before the name
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Shell command to run
+    #[serde(default)]
+    pub run: Option<String>,
+    /// Text to put on the input line
+    #[serde(default)]
+    pub insert: Option<String>,
+    /// Prompt to send to the AI
+    #[serde(default)]
+    pub ask: Option<String>,
+    /// Slash command to run
+    #[serde(default)]
+    pub slash: Option<String>,
+}
+
+impl CustomCommand {
+    /// Get what the command does, checking it does exactly one thing
+    pub fn action(&self) -> Result<Action> {
+        let action = match (&self.run, &self.insert, &self.ask, &self.slash) {
+            (Some(command), None, None, None) => Action::Run(command.clone()),
+            (None, Some(text), None, None) => Action::Insert(text.clone()),
+            (None, None, Some(prompt), None) => Action::Ask(prompt.clone()),
+            (None, None, None, Some(command)) if command.starts_with('/') => Action::Slash(command.clone()),
+            (None, None, None, Some(_)) => bail!("the `slash` command of '{}' does not start with /", self.name),
+            _ => bail!("'{}' needs exactly one of `run`, `insert`, `ask` and `slash`", self.name),
+        };
+        let secret = placeholders::find(action.text().unwrap_or_default()).iter().any(|placeholder| placeholder.secret);
+        if secret && !matches!(action, Action::Run(_)) {
+            bail!("'{}' has a secret placeholder, which only `run` commands can keep out of their text", self.name);
+        }
+        Ok(action)
+    }
+
+    /// Build the palette command, listed under `category` unless the command gives its own
+    pub fn palette_command(&self, category: &str) -> Result<Command> {
+        let action = self.action()?;
+        let text = action.text().unwrap_or_default();
+        let arguments: Vec<String> = placeholders::find(text).into_iter().map(|placeholder| placeholder.name.to_lowercase()).collect();
+        let description = if self.description.is_empty() { text.to_string() } else { self.description.clone() };
+        let category = self.category.as_deref().unwrap_or(category);
+        let id = format!("{}{}:{}", PALETTE_PREFIX, category, self.name);
+        let command = Command::new(&id, &self.name, &description, category, self.icon.as_deref().unwrap_or("⚙")).with_action(action);
+        Ok(if arguments.is_empty() { command } else { command.with_argument(&arguments.join(" ")) })
+    }
+}
+
+/// A plugin file: commands listed under its name
+#[derive(Debug, Deserialize)]
+struct Plugin {
+    #[serde(default)]
+    commands: Vec<CustomCommand>,
+}
+
+/// Build the palette commands of the configured commands
+pub fn palette_commands(commands: &[CustomCommand]) -> Result<Vec<Command>> {
+    commands.iter().map(|command| command.palette_command(DEFAULT_CATEGORY)).collect()
+}
+
+/// Get the directory holding plugin files, if there is a config directory
+pub fn plugins_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|path| path.join("ai-terminal").join("commands"))
+}
+
+/// Parse a plugin file into palette commands, listed under `name` by default
+pub fn parse_plugin(name: &str, content: &str) -> Result<Vec<Command>> {
+    let plugin: Plugin = toml::from_str(content)?;
+    plugin.commands.iter().map(|command| command.palette_command(name)).collect()
+}
+
+/// Load the commands of every plugin file in `dir`
+///
+/// Files that cannot be parsed are skipped with a warning.
+pub fn load_plugins(dir: &Path) -> Result<Vec<Command>> {
+    let mut commands = Vec::new();
+    if !dir.exists() {
+        return Ok(commands);
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|content| parse_plugin(&name, &content)) {
+            Ok(plugin) => commands.extend(plugin),
+            Err(e) => tracing::warn!("Skipping command plugin {}: {:?}", path.display(), e),
+        }
+    }
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_commands() {
+        let content = "[[commands]]\nname = \"Deploy\"\nrun = \"./deploy.sh {{ENV}} {{TOKEN:secret}}\"\n\n[[commands]]\nname = \"Review\"\ndescription = \"Review a file\"\ncategory = \"AI\"\nask = \"Review {{FILE}}\"\n";
+        let commands = parse_plugin("ops", content).unwrap();
+        assert_eq!(commands[0].id, "custom:ops:Deploy");
+        assert_eq!(commands[0].description, "./deploy.sh {{ENV}} {{TOKEN:secret}}");
+        assert_eq!(commands[0].argument.as_deref(), Some("env token"));
+        assert_eq!((commands[1].category.as_str(), &commands[1].action), ("AI", &Action::Ask("Review {{FILE}}".to_string())));
+
+        let command = |fields: &str| toml::from_str::<CustomCommand>(&format!("name = \"x\"\n{}", fields)).unwrap();
+        assert!(command("").action().is_err());
+        assert!(command("run = \"ls\"\ninsert = \"ls\"").action().is_err());
+        assert!(command("slash = \"workflow run ship\"").action().is_err());
+        assert!(command("ask = \"Use {{KEY:secret}}\"").action().is_err());
+        assert_eq!(palette_commands(&[command("insert = \"git status\"")]).unwrap()[0].category, DEFAULT_CATEGORY);
+    }
+}
@@ -0,0 +1,260 @@
+//! Environment checks of the AI Terminal
+//!
+//! `ai-terminal doctor`, and `/doctor` or 'Doctor: Check Environment' in the
+//! command palette, check what the terminal depends on: whether the model
+//! server answers and which version it runs, whether the configured models
+//! are installed, whether commands can be started in a pseudo-terminal, and
+//! what the terminal emulator supports. The subcommand also checks that
+//! `config.toml` is valid. Every problem comes with what to do about it.
+
+use std::time::Duration;
+
+use ollama_client::{ChatBackend, OllamaClient};
+use terminal_emulator::{BlockState, CommandBlock, PtyExecutor};
+
+use crate::images::ImageSupport;
+
+/// Command running the checks in the UI
+pub const COMMAND: &str = "/doctor";
+
+/// How long a check waits for the model server or the test command
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most installed models named when a configured one is missing
+const MAX_SUGGESTED_MODELS: usize = 5;
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but not as well as it could
+    Warn,
+    Fail,
+}
+
+impl Status {
+    /// Glyph marking the outcome in the report
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Status::Ok => "✓",
+            Status::Warn => "⚠",
+            Status::Fail => "✗",
+        }
+    }
+}
+
+/// One thing checked, with what to do when it is not right
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    /// Record a check that passed
+    pub fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    /// Record a check that passed with a limitation, and how to lift it
+    pub fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    /// Record a check that failed, and how to fix it
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// Describe the checks, one per line with the fixes under them, and how many failed
+pub fn report(checks: &[Check]) -> String {
+    let mut lines = Vec::new();
+    for check in checks {
+        lines.push(format!("{} {}: {}", check.status.glyph(), check.name, check.detail));
+        if let Some(fix) = &check.fix {
+            lines.push(format!("    → {}", fix));
+        }
+    }
+    let failed = checks.iter().filter(|check| check.status == Status::Fail).count();
+    let warned = checks.iter().filter(|check| check.status == Status::Warn).count();
+    lines.push(String::new());
+    lines.push(match (failed, warned) {
+        (0, 0) => format!("All {} checks passed", checks.len()),
+        (failed, warned) => format!("{} problem(s) and {} warning(s) in {} checks", failed, warned, checks.len()),
+    });
+    lines.join("\n")
+}
+
+/// Check that the model server answers and has the configured models
+///
+/// `ollama` is the client of an Ollama server, which also reports its
+/// version; other servers are only asked for their models.
+pub async fn backend_checks(backend: &dyn ChatBackend, ollama: Option<&OllamaClient>, models: &[String]) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(ollama) = ollama {
+        match within(ollama.version()).await {
+            Ok(version) => checks.push(Check::ok("Ollama", format!("reachable at {}, version {}", ollama.base_url, version))),
+            Err(error) => {
+                checks.push(Check::fail(
+                    "Ollama",
+                    format!("not reachable at {}: {}", ollama.base_url, error),
+                    "start it with `ollama serve`, or point OLLAMA_HOST or backend.base_url in config.toml at the server",
+                ));
+                return checks;
+            }
+        }
+    }
+    let installed = match within(backend.list_models()).await {
+        Ok(installed) => installed,
+        Err(error) => {
+            checks.push(Check::fail(
+                "Models",
+                format!("could not list the models of the {} server: {}", backend.name(), error),
+                "check backend.base_url, the API key variable and the connection settings in config.toml",
+            ));
+            return checks;
+        }
+    };
+    let mut seen = Vec::new();
+    for model in models.iter().filter(|model| !model.is_empty()) {
+        if seen.contains(model) {
+            continue;
+        }
+        seen.push(model.clone());
+        // Ollama lists models with their tag, which defaults to `latest`
+        let found = installed.iter().any(|name| name == model || *name == format!("{}:latest", model));
+        if found {
+            checks.push(Check::ok(format!("Model {}", model), "installed"));
+            continue;
+        }
+        let others = installed.iter().take(MAX_SUGGESTED_MODELS).cloned().collect::<Vec<_>>().join(", ");
+        let choose = if others.is_empty() { String::new() } else { format!(" ({})", others) };
+        let fix = match ollama {
+            Some(_) => format!("run `ollama pull {}`, or set [ollama] model in config.toml to an installed one{}", model, choose),
+            None => format!("set [ollama] model in config.toml to one the server offers{}", choose),
+        };
+        checks.push(Check::fail(format!("Model {}", model), "not on the server", fix));
+    }
+    checks
+}
+
+/// Wait for a request to the model server, giving up after [`TIMEOUT`]
+async fn within<T, E: std::fmt::Display>(request: impl std::future::Future<Output = Result<T, E>>) -> Result<T, String> {
+    match tokio::time::timeout(TIMEOUT, request).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("no answer within {}s", TIMEOUT.as_secs())),
+    }
+}
+
+/// Check that commands can be started in a pseudo-terminal and their output read
+pub async fn pty_check() -> Check {
+    let fix = "check that /dev/ptmx exists and devpts is mounted, and that the shell in $SHELL runs";
+    let executor = match PtyExecutor::new() {
+        Ok(executor) => executor,
+        Err(e) => return Check::fail("PTY", format!("could not set up the executor: {:#}", e), fix),
+    };
+    let mut block = CommandBlock::new("echo pty-ok".to_string(), executor.working_dir().to_string());
+    match tokio::time::timeout(TIMEOUT, executor.execute_block(&mut block)).await {
+        Ok(Ok(())) if block.state == BlockState::Success && block.output.text().contains("pty-ok") => {
+            Check::ok("PTY", format!("commands run and their output is read, in {:?}", block.duration.unwrap_or_default()))
+        }
+        Ok(Ok(())) => Check::fail("PTY", format!("a test command ended {:?} with output {:?}", block.state, block.output.text().trim()), fix),
+        Ok(Err(e)) => Check::fail("PTY", format!("could not run a test command: {:#}", e), fix),
+        Err(_) => Check::fail("PTY", format!("a test command did not finish within {}s", TIMEOUT.as_secs()), fix),
+    }
+}
+
+/// Check what the terminal emulator supports
+pub fn terminal_checks() -> Vec<Check> {
+    use std::io::IsTerminal;
+    terminal_checks_from_env(|name| std::env::var(name).ok(), std::io::stdout().is_terminal())
+}
+
+/// Check what the terminal supports from environment variables, and whether stdout is a terminal
+pub fn terminal_checks_from_env(var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let term = var("TERM").unwrap_or_default();
+    checks.push(match (is_terminal, term.as_str()) {
+        (false, _) => Check::warn("Terminal", "stdout is not a terminal", "run ai-terminal in a terminal emulator; the UI needs one"),
+        (true, "" | "dumb") => Check::fail("Terminal", format!("TERM is '{}'", term), "set TERM to your terminal's type, such as xterm-256color"),
+        (true, _) => Check::ok("Terminal", format!("TERM is {}", term)),
+    });
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    checks.push(if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+        Check::ok("Colors", "24-bit color")
+    } else if term.contains("256color") {
+        Check::warn(
+            "Colors",
+            "256 colors; theme colors are approximated",
+            "if your terminal shows 24-bit color, export COLORTERM=truecolor",
+        )
+    } else {
+        Check::warn("Colors", "no 24-bit or 256-color support announced", "export COLORTERM=truecolor, or use a TERM such as xterm-256color")
+    });
+    // Mouse reporting is xterm's protocol, which the Linux console does not speak without gpm
+    checks.push(match term.as_str() {
+        "" | "dumb" => Check::fail("Mouse", "no mouse reporting without a terminal type", "set TERM to your terminal's type"),
+        "linux" => Check::warn("Mouse", "the Linux console reports no mouse events", "use a terminal emulator to click blocks and links"),
+        _ => Check::ok("Mouse", "xterm mouse reporting"),
+    });
+    let images = ImageSupport::from_env(&var);
+    checks.push(match images {
+        ImageSupport::Blocks if var("TMUX").is_some() || term.starts_with("screen") => Check::warn(
+            "Images",
+            "drawn with half blocks inside tmux or screen",
+            "run outside the multiplexer to see images in full resolution",
+        ),
+        images => Check::ok("Images", format!("drawn with {}", images.name())),
+    });
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{method, path}};
+
+    #[test]
+    fn test_terminal_checks() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+        let checks = terminal_checks_from_env(env(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]), true);
+        assert!(checks.iter().all(|check| check.status == Status::Ok));
+        let checks = terminal_checks_from_env(env(&[("TERM", "screen-256color")]), true);
+        assert_eq!(checks.iter().map(|check| check.status).collect::<Vec<_>>(), [Status::Ok, Status::Warn, Status::Ok, Status::Warn]);
+        assert!(report(&checks).ends_with("0 problem(s) and 2 warning(s) in 4 checks"));
+        assert_eq!(terminal_checks_from_env(env(&[]), true)[0].status, Status::Fail);
+    }
+
+    #[tokio::test]
+    async fn test_backend_checks() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/api/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"version":"0.5.7"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"models":[{"name":"llama3:latest"}]}"#))
+            .mount(&server)
+            .await;
+        let mut client = OllamaClient::new().unwrap();
+        client.base_url = format!("{}/api", server.uri());
+
+        let models = ["llama3".to_string(), "qwen2.5-coder".to_string()];
+        let checks = backend_checks(&client, Some(&client), &models).await;
+        assert_eq!(checks.len(), 3);
+        assert!(checks[0].detail.ends_with("version 0.5.7"));
+        assert_eq!(checks[1], Check::ok("Model llama3", "installed"));
+        assert_eq!(checks[2].status, Status::Fail);
+        assert!(checks[2].fix.as_deref().unwrap().starts_with("run `ollama pull qwen2.5-coder`"));
+
+        client.base_url = "http://127.0.0.1:9/api".to_string();
+        let checks = backend_checks(&client, Some(&client), &models).await;
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, Status::Fail);
+    }
+}
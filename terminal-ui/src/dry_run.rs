@@ -0,0 +1,305 @@
+//! Dry-run previews of the commands the agent proposes
+//!
+//! Before the agent asks whether it may run a command, the terminal looks at
+//! what the command would do without running it, and shows that in the
+//! approval popup: what the shell expands its variables, globs and `~` to,
+//! the output of the tool's own dry run for tools that have one (`rsync
+//! --dry-run`, `apt-get --simulate`, `git clean --dry-run`, `kubectl
+//! --dry-run=client`, `pip install --dry-run --only-binary=:all:`, ...), and the files and
+//! directories `rm`, `mv`, `cp`, `chmod` and overwriting redirections would
+//! touch. Commands with command substitutions are not expanded, since that
+//! would run them. A dry run never runs as root: a leading `sudo` is left out
+//! and the preview says so. `preview = false` in the `[agent]` section turns the
+//! preview off.
+
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use terminal_emulator::impact;
+
+use crate::command_explainer::{self, Token};
+use crate::widgets::models_view::format_size;
+
+/// Longest a preview command may run
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most lines of a preview command's output shown
+const MAX_LINES: usize = 12;
+
+/// Most entries counted in a directory that would be touched
+const MAX_COUNTED: usize = 10_000;
+
+/// Tools with a dry run: the programs, the subcommands it applies to (any when empty) and its flags
+const DRY_RUNS: [(&[&str], &[&str], &[&str]); 8] = [
+    (&["rsync"], &[], &["--dry-run"]),
+    (&["apt", "apt-get"], &["install", "remove", "purge", "upgrade", "dist-upgrade", "full-upgrade", "autoremove"], &["--simulate"]),
+    (&["dnf", "yum"], &["install", "remove", "upgrade", "update"], &["--assumeno"]),
+    (&["git"], &["clean", "rm", "add", "mv"], &["--dry-run"]),
+    (&["kubectl"], &["apply", "create", "delete", "replace", "patch"], &["--dry-run=client"]),
+    (&["helm"], &["install", "upgrade", "uninstall"], &["--dry-run"]),
+    // pip's dry run still builds source distributions, running their setup.py;
+    // allowing only wheels means the dry run runs no package code
+    (&["pip", "pip3"], &["install"], &["--dry-run", "--only-binary=:all:"]),
+    (&["npm"], &["install", "uninstall", "update"], &["--dry-run"]),
+];
+
+/// What a command would do, found without running it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preview {
+    /// The command with its variables, globs and `~` expanded, one line per simple command
+    pub expansion: Option<String>,
+    /// The dry-run command and its output
+    pub dry_run: Option<(String, String)>,
+    /// The paths the command would touch, described
+    pub paths: Vec<String>,
+    /// Whether the dry run left out the `sudo` the command runs with
+    pub unprivileged: bool,
+}
+
+impl Preview {
+    /// Check whether nothing was found
+    pub fn is_empty(&self) -> bool {
+        self.expansion.is_none() && self.dry_run.is_none() && self.paths.is_empty()
+    }
+
+    /// Describe the preview for the approval popup
+    pub fn describe(&self) -> String {
+        let mut sections = Vec::new();
+        if let Some(expansion) = &self.expansion {
+            sections.push(format!("Expands to:\n{}", expansion));
+        }
+        if let Some((command, output)) = &self.dry_run {
+            let unprivileged = if self.unprivileged { ", run without sudo," } else { "" };
+            sections.push(format!("`{}`{} says:\n{}", command, unprivileged, output));
+        }
+        if !self.paths.is_empty() {
+            sections.push(format!("Touches:\n{}", self.paths.join("\n")));
+        }
+        sections.join("\n\n")
+    }
+}
+
+/// Look at what a command would do in `working_dir` without running it
+pub async fn preview(command: &str, working_dir: &Path) -> Preview {
+    let expansion = match expansion_script(command) {
+        Some(script) => run(&script, working_dir).await.filter(|expanded| expanded.trim() != command.trim()),
+        None => None,
+    };
+    let dry_run = match dry_run_command(command) {
+        Some(dry_run) => run(&dry_run, working_dir).await.map(|output| (dry_run, output)),
+        None => None,
+    };
+    let paths = impact::target_paths(command, working_dir)
+        .iter()
+        .map(|path| describe_path(path, working_dir))
+        .collect();
+    let unprivileged = dry_run.is_some() && runs_with_sudo(command);
+    Preview { expansion, dry_run, paths, unprivileged }
+}
+
+/// Check whether a command line starts with `sudo`
+fn runs_with_sudo(command: &str) -> bool {
+    command.split_whitespace().next() == Some("sudo")
+}
+
+/// Check whether expanding a command line would run part of it
+fn has_substitution(command: &str) -> bool {
+    command.contains("$(") || command.contains('`') || command.contains("<(") || command.contains(">(")
+}
+
+/// Split a command line into the words of its simple commands, leaving out redirections
+fn simple_commands(command: &str) -> Vec<Vec<String>> {
+    let mut commands = vec![Vec::new()];
+    let mut target = false;
+    for token in command_explainer::tokenize(command) {
+        match token {
+            Token::Word(_) if std::mem::take(&mut target) => {}
+            Token::Word(word) => commands.last_mut().expect("there is always a command").push(word),
+            // The file of a redirection goes too, unlike the descriptor of 2>&1
+            Token::Operator(operator) if operator.contains(['<', '>']) => target = !operator.ends_with(|c: char| c.is_ascii_digit()),
+            Token::Operator(_) => commands.push(Vec::new()),
+        }
+    }
+    commands.retain(|words| !words.is_empty());
+    commands
+}
+
+/// Build the shell script printing a command line with its words expanded, if it has any to expand
+pub fn expansion_script(command: &str) -> Option<String> {
+    if cfg!(windows) || has_substitution(command) || !command.contains(['$', '*', '?', '[', '~', '{']) {
+        return None;
+    }
+    let lines: Vec<String> = simple_commands(command)
+        .into_iter()
+        .map(|words| format!("printf '%s ' {}; echo", words.join(" ")))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("; "))
+}
+
+/// Build the dry-run form of a single command, for tools that have one
+pub fn dry_run_command(command: &str) -> Option<String> {
+    if cfg!(windows) || has_substitution(command) {
+        return None;
+    }
+    let tokens = command_explainer::tokenize(command);
+    let mut words = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Word(word) => words.push(word),
+            // Only a single command without redirections is run
+            Token::Operator(_) => return None,
+        }
+    }
+    // The dry run needs no root, and sudo could not ask for a password
+    let start = usize::from(words.first().is_some_and(|word| word == "sudo"));
+    let words = &words[start..];
+    let program = words.first()?.rsplit('/').next()?;
+    let (_, subcommands, flags) = DRY_RUNS.iter().find(|(programs, _, _)| programs.contains(&program))?;
+    let at = if subcommands.is_empty() {
+        0
+    } else {
+        // The subcommand is the first word after the program that is not a flag
+        let (i, subcommand) = words.iter().enumerate().skip(1).find(|(_, word)| !word.starts_with('-'))?;
+        if !subcommands.contains(&subcommand.as_str()) {
+            return None;
+        }
+        i
+    };
+    let missing = flags.iter().filter(|flag| !words.iter().any(|word| word == *flag)).map(|flag| flag.to_string());
+    let mut dry_run = words[..=at].to_vec();
+    dry_run.extend(missing);
+    dry_run.extend_from_slice(&words[at + 1..]);
+    Some(dry_run.join(" "))
+}
+
+/// Run a preview script through the shell, returning the end of its output
+async fn run(script: &str, working_dir: &Path) -> Option<String> {
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(script)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = command.spawn().map_err(|e| tracing::warn!("Failed to start a preview: {:?}", e)).ok()?;
+    let output = match tokio::time::timeout(TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to run a preview: {:?}", e);
+            return None;
+        }
+        Err(_) => return Some(format!("(stopped after {}s)", TIMEOUT.as_secs())),
+    };
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    Some(last_lines(&text))
+}
+
+/// Keep the last lines of output, noting how many were left out
+fn last_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+    let skipped = lines.len().saturating_sub(MAX_LINES);
+    let kept = lines[skipped..].join("\n");
+    if skipped > 0 {
+        format!("[{} lines before]\n{}", skipped, kept)
+    } else {
+        kept
+    }
+}
+
+/// Describe a path a command would touch, relative to `working_dir` when it is inside
+fn describe_path(path: &Path, working_dir: &Path) -> String {
+    let shown = path.strip_prefix(working_dir).ok().filter(|relative| !relative.as_os_str().is_empty()).unwrap_or(path);
+    let shown = shown.display();
+    match fs::symlink_metadata(path) {
+        Err(_) => format!("{} (does not exist)", shown),
+        Ok(metadata) if metadata.is_symlink() => format!("{} (link)", shown),
+        Ok(metadata) if metadata.is_dir() => {
+            let (entries, bytes, complete) = measure(path);
+            let more = if complete { "" } else { "+" };
+            format!("{}/ (directory, {}{} entries, {}{})", shown, entries, more, format_size(bytes), more)
+        }
+        Ok(metadata) => format!("{} ({})", shown, format_size(metadata.len())),
+    }
+}
+
+/// Count the entries under a directory and their size, up to `MAX_COUNTED`, returning whether all were counted
+fn measure(dir: &Path) -> (usize, u64, bool) {
+    let mut entries = 0;
+    let mut bytes = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(children) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for child in children.flatten() {
+            if entries == MAX_COUNTED {
+                return (entries, bytes, false);
+            }
+            entries += 1;
+            match child.metadata() {
+                Ok(metadata) if metadata.is_dir() && !child.file_type().is_ok_and(|kind| kind.is_symlink()) => pending.push(child.path()),
+                Ok(metadata) => bytes += metadata.len(),
+                Err(_) => {}
+            }
+        }
+    }
+    (entries, bytes, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(dry_run_command("sudo apt-get -y install jq"), Some("apt-get -y install --simulate jq".to_string()));
+        assert_eq!(dry_run_command("rsync -a src/ 'backup dir/'"), Some("rsync --dry-run -a src/ 'backup dir/'".to_string()));
+        assert_eq!(dry_run_command("git clean -fd"), Some("git clean --dry-run -fd".to_string()));
+        assert_eq!(dry_run_command("git push"), None);
+        assert_eq!(
+            dry_run_command("sudo pip install --dry-run requests"),
+            Some("pip install --only-binary=:all: --dry-run requests".to_string()),
+        );
+        let unprivileged = Preview {
+            dry_run: Some(("apt-get install --simulate jq".to_string(), "Inst jq".to_string())),
+            unprivileged: true,
+            ..Default::default()
+        };
+        assert_eq!(unprivileged.describe(), "`apt-get install --simulate jq`, run without sudo, says:\nInst jq");
+        assert_eq!(dry_run_command("apt-get install jq > log"), None);
+        assert_eq!(dry_run_command("rsync -a $(ls) dst"), None);
+
+        assert_eq!(expansion_script("ls -la"), None);
+        assert_eq!(expansion_script("rm $(find . -name '*.tmp')"), None);
+        if !cfg!(windows) {
+            assert_eq!(
+                expansion_script("rm -f *.log 2>/dev/null && echo \"$HOME\""),
+                Some("printf '%s ' rm -f *.log; echo; printf '%s ' echo \"$HOME\"; echo".to_string()),
+            );
+        }
+        let long = (1..=20).map(|n| n.to_string()).collect::<Vec<_>>().join("\n");
+        assert_eq!(last_lines(&long), format!("[8 lines before]\n{}", (9..=20).map(|n| n.to_string()).collect::<Vec<_>>().join("\n")));
+    }
+
+    #[tokio::test]
+    async fn test_preview() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-dry-run-{}", std::process::id()));
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("logs").join("a.log"), "12345").unwrap();
+        fs::write(dir.join("logs").join("b.log"), "6789").unwrap();
+
+        let preview = preview("rm -r logs missing", &dir).await;
+        assert_eq!(preview.paths, ["logs/ (directory, 2 entries, 9 B)", "missing (does not exist)"]);
+        assert!(preview.dry_run.is_none());
+        if !cfg!(windows) {
+            let globbed = super::preview("rm logs/*.log", &dir).await;
+            assert_eq!(globbed.expansion.as_deref(), Some("rm logs/a.log logs/b.log"));
+            assert!(globbed.describe().starts_with("Expands to:\nrm logs/a.log logs/b.log\n\nTouches:\nlogs/ "));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
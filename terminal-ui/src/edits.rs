@@ -0,0 +1,167 @@
+//! AI-proposed file edits for the AI Terminal
+//!
+//! A model proposes a change to a file by answering with the whole new file
+//! in a fenced block whose info string names it, such as
+//! ```` ```rust path=src/main.rs ````. `/code edit [n] [path]` turns the
+//! block into a [`ProposedEdit`] against the file on disk and shows it as a
+//! unified diff; applying it writes the file through a checked
+//! [`FileOperation::Write`], and only if the file still holds what the diff
+//! was made from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use similar::TextDiff;
+use terminal_emulator::FileOperation;
+
+use crate::snippets::CodeSnippet;
+
+/// Lines of context around each change in the diff
+const CONTEXT_LINES: usize = 3;
+
+/// Keys in a fence info string that name the file a block is for
+const PATH_KEYS: &[&str] = &["path=", "file=", "filename="];
+
+/// Kind of a line of a unified diff, for coloring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    /// `---` and `+++` file headers
+    Header,
+    /// `@@` hunk headers
+    Hunk,
+    /// Lines the edit adds
+    Added,
+    /// Lines the edit removes
+    Removed,
+    /// Unchanged lines around the changes
+    Context,
+}
+
+impl DiffLineKind {
+    /// Classify a line of a unified diff
+    pub fn of(line: &str) -> Self {
+        if line.starts_with("+++") || line.starts_with("---") {
+            DiffLineKind::Header
+        } else if line.starts_with("@@") {
+            DiffLineKind::Hunk
+        } else if line.starts_with('+') {
+            DiffLineKind::Added
+        } else if line.starts_with('-') {
+            DiffLineKind::Removed
+        } else {
+            DiffLineKind::Context
+        }
+    }
+}
+
+/// Get the path a fence info string names, if any
+pub fn fence_path(language: &str) -> Option<&str> {
+    language.split_whitespace().find_map(|word| {
+        PATH_KEYS.iter().find_map(|key| word.strip_prefix(key)).map(|path| path.trim_matches(|c| c == '"' || c == '\''))
+    })
+}
+
+/// A file and the contents a model proposes for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedEdit {
+    /// Resolved path of the file
+    pub path: PathBuf,
+    /// Path as shown to the user
+    pub shown: String,
+    /// Contents on disk when the edit was made; `None` for a new file
+    pub old: Option<String>,
+    /// Proposed contents
+    pub new: String,
+}
+
+impl ProposedEdit {
+    /// Build an edit of the file a block names, or of `path` if given
+    pub fn from_snippet(snippet: &CodeSnippet, path: Option<&str>, working_dir: &Path) -> Result<Self> {
+        let Some(shown) = path.or_else(|| fence_path(&snippet.language)) else {
+            bail!("the code block names no file; give one, as in /code edit <n> <path>");
+        };
+        let path = terminal_emulator::file_ops::resolve(Path::new(shown), working_dir)?;
+        let old = match fs::read(&path) {
+            Ok(bytes) => Some(String::from_utf8(bytes).with_context(|| format!("{} is not a text file", shown))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("could not read {}", shown)),
+        };
+        Ok(Self { path, shown: shown.to_string(), old, new: snippet.code.clone() })
+    }
+
+    /// Format the edit as a unified diff
+    pub fn unified_diff(&self) -> String {
+        let old = self.old.as_deref().unwrap_or_default();
+        let old_header = if self.old.is_some() { format!("a/{}", self.shown) } else { "/dev/null".to_string() };
+        TextDiff::from_lines(old, &self.new)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&old_header, &format!("b/{}", self.shown))
+            .to_string()
+    }
+
+    /// Count the lines added and removed
+    pub fn counts(&self) -> (usize, usize) {
+        self.unified_diff().lines().fold((0, 0), |(added, removed), line| match DiffLineKind::of(line) {
+            DiffLineKind::Added => (added + 1, removed),
+            DiffLineKind::Removed => (added, removed + 1),
+            _ => (added, removed),
+        })
+    }
+
+    /// Whether the edit changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.old.as_deref() == Some(self.new.as_str())
+    }
+
+    /// Write the proposed contents, unless the file changed since the diff was made
+    pub fn apply(&self) -> Result<()> {
+        let current = fs::read(&self.path).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+        if current != self.old {
+            bail!("{} changed since the diff was made; ask for the edit again", self.shown);
+        }
+        FileOperation::Write { path: self.path.clone(), contents: self.new.clone().into_bytes() }.apply()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(language: &str, code: &str) -> CodeSnippet {
+        CodeSnippet { language: language.to_string(), code: code.to_string(), prompt: String::new() }
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        assert_eq!(fence_path("rust path=src/main.rs"), Some("src/main.rs"));
+        assert_eq!(fence_path("python file=\"app.py\""), Some("app.py"));
+        assert_eq!(fence_path("rust"), None);
+
+        let dir = std::env::temp_dir().join(format!("ai-terminal-edits-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+
+        let edit = ProposedEdit::from_snippet(&snippet("text path=notes.txt", "one\n2\nthree\nfour\n"), None, &dir).unwrap();
+        let diff = edit.unified_diff();
+        assert!(diff.starts_with("--- a/notes.txt\n+++ b/notes.txt\n@@"));
+        assert!(diff.contains("-two\n+2\n"));
+        assert_eq!(edit.counts(), (2, 1));
+        assert_eq!(DiffLineKind::of("@@ -1,3 +1,4 @@"), DiffLineKind::Hunk);
+
+        // A file changed after the diff was made is left alone
+        fs::write(dir.join("notes.txt"), "changed\n").unwrap();
+        assert!(edit.apply().is_err());
+        fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+        edit.apply().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("notes.txt")).unwrap(), "one\n2\nthree\nfour\n");
+
+        let new = ProposedEdit::from_snippet(&snippet("rust", "fn main() {}\n"), Some("src/new.rs"), &dir).unwrap();
+        assert!(new.old.is_none());
+        assert!(new.unified_diff().starts_with("--- /dev/null\n"));
+        assert!(ProposedEdit::from_snippet(&snippet("rust", ""), None, &dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+//! Knowledge base of errors explained before
+//!
+//! `/kb explain [n]` asks the model why the pane's latest failed command, or
+//! the n-th latest block, failed, and saves the answer in the database under
+//! the error's signature: a hash of the command's program and its error
+//! lines with numbers, addresses and directories taken out, so the same
+//! error from another file or run matches. When a command from the answer
+//! then succeeds, it is saved as the fix.
+//!
+//! Whenever a command fails with a known error, the saved explanation is
+//! shown straight away, without asking the model again, and the fix is put
+//! on the input line. `/kb` lists the known errors, which are also under
+//! Known Errors in the command palette; `/kb show <id>`, `/kb fix <id>
+//! <command>`, `/kb remove <id>` and `/kb clear` manage them.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use terminal_emulator::{CommandBlock, KnownError};
+
+use crate::runners::Runner;
+use crate::snippets;
+use crate::widgets::command_palette::{Action, Command};
+
+/// Prefix of the command palette ids of known errors
+pub const PALETTE_PREFIX: &str = "known-error:";
+
+/// Command asking the model to explain an error, which is an AI request unlike the other `/kb` commands
+pub const EXPLAIN_COMMAND: &str = "/kb explain";
+
+/// Most error lines in a signature, counted from the end of the output
+const MAX_LINES: usize = 20;
+
+/// Words marking the lines of an output that tell what went wrong
+const ERROR_WORDS: [&str; 10] = ["error", "fatal", "exception", "failed", "failure", "cannot", "not found", "denied", "panicked", "traceback"];
+
+/// An error as it is recognized again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSignature {
+    /// Hex SHA-256 of the program and the normalized error lines
+    pub hash: String,
+    /// Command that failed
+    pub command: String,
+    /// The error lines as printed
+    pub lines: String,
+}
+
+impl ErrorSignature {
+    /// Find the error in a failed command's output, which should already be masked for secrets
+    pub fn new(command: &str, output: &str) -> Option<Self> {
+        let lines: Vec<&str> = output.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect();
+        let marked: Vec<&str> = lines.iter().copied().filter(|line| is_error_line(line)).collect();
+        let chosen = if marked.is_empty() { &lines } else { &marked };
+        let chosen = &chosen[chosen.len().saturating_sub(MAX_LINES)..];
+        if chosen.is_empty() {
+            return None;
+        }
+        let program = command.split_whitespace().next().unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(program.as_bytes());
+        for line in chosen {
+            hasher.update(b"\n");
+            hasher.update(normalize(line).as_bytes());
+        }
+        Some(Self {
+            hash: format!("{:x}", hasher.finalize()),
+            command: command.to_string(),
+            lines: chosen.join("\n"),
+        })
+    }
+}
+
+/// Get the output of a block an error is looked for in: its stderr, or all of it when nothing was told apart
+pub fn error_output(block: &CommandBlock) -> String {
+    if block.stderr.is_empty() { block.output.text() } else { block.stderr.text() }
+}
+
+/// Check whether a line tells what went wrong
+fn is_error_line(line: &str) -> bool {
+    let line = line.to_lowercase();
+    ERROR_WORDS.iter().any(|word| line.contains(word))
+}
+
+/// Take out of a line what differs between occurrences of the same error
+fn normalize(line: &str) -> String {
+    static PATTERNS: OnceLock<[(Regex, &str); 4]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| [
+        (Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").expect("UUID pattern is valid"), "<id>"),
+        (Regex::new(r"(?i)\b0x[0-9a-f]+\b").expect("address pattern is valid"), "<addr>"),
+        (Regex::new(r"(?:[\w.~@+-]*/)+([\w.@+-]+)").expect("directory pattern is valid"), "$1"),
+        (Regex::new(r"\d+").expect("number pattern is valid"), "#"),
+    ]);
+    let mut line = line.to_string();
+    for (pattern, replacement) in patterns {
+        line = pattern.replace_all(&line, *replacement).into_owned();
+    }
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Build the prompt asking the model to explain an error, given the output it is sent
+pub fn explain_prompt(output_context: &str) -> String {
+    format!(
+        "Explain briefly why this command failed and how to fix it. Put the command that fixes it, if there is one, in a ```sh code block.\n\n{}",
+        output_context,
+    )
+}
+
+/// Commands of an explanation, one of which is saved as the fix once it succeeds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingFix {
+    /// Id of the known error
+    pub id: i64,
+    /// Shell code blocks of the explanation, whole and line by line
+    candidates: Vec<String>,
+}
+
+impl PendingFix {
+    /// Collect the commands of an error's explanation
+    pub fn new(id: i64, explanation: &str) -> Self {
+        let mut candidates = Vec::new();
+        for block in snippets::code_blocks(explanation, "") {
+            if Runner::for_language(&block.language) != Some(Runner::Shell) {
+                continue;
+            }
+            candidates.push(block.code.trim().to_string());
+            candidates.extend(block.code.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).map(str::to_string));
+        }
+        Self { id, candidates }
+    }
+
+    /// Check whether a command that succeeded is one of the explanation's
+    pub fn accepts(&self, command: &str) -> bool {
+        self.candidates.iter().any(|candidate| candidate == command.trim())
+    }
+}
+
+/// Get the first line of a known error's output
+fn first_line(known: &KnownError) -> &str {
+    known.error.lines().next().unwrap_or_default().trim()
+}
+
+/// Describe a known error in one line, for `/kb`
+pub fn summary(known: &KnownError) -> String {
+    let fixed = if known.fix.is_some() { ", fixed" } else { "" };
+    format!("{}. {}: {} (seen {}×{})", known.id, known.command, first_line(known), known.hits + 1, fixed)
+}
+
+/// Describe a known error with its explanation and fix, for `/kb show` and when it comes back
+pub fn describe(known: &KnownError) -> String {
+    let fix = match &known.fix {
+        Some(fix) => format!("The fix that worked: `{}`", fix),
+        None => "No fix is saved yet; a command from the explanation that succeeds is saved as one.".to_string(),
+    };
+    format!(
+        "Known error {} from `{}`, seen {} time(s), first on {}:\n```\n{}\n```\n\n{}\n\n{}",
+        known.id, known.command, known.hits + 1, known.created_at.format("%Y-%m-%d"), known.error, known.explanation.trim(), fix,
+    )
+}
+
+/// Build the palette commands showing each known error
+pub fn palette_commands(known: &[KnownError]) -> Vec<Command> {
+    known
+        .iter()
+        .map(|known| {
+            let name: String = format!("{}: {}", known.command, first_line(known)).chars().take(60).collect();
+            let mut description: String = known.explanation.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().chars().take(80).collect();
+            if let Some(fix) = &known.fix {
+                description = format!("Fix: {} · {}", fix, description);
+            }
+            Command::new(&format!("{}{}", PALETTE_PREFIX, known.id), &name, &description, "Known Errors", "📚")
+                .with_action(Action::Slash(format!("/kb show {}", known.id)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    #[test]
+    fn test_signatures_match_the_same_error() {
+        let first = ErrorSignature::new(
+            "cargo build",
+            "   Compiling app v0.1.0 (/home/a/app)\nerror[E0425]: cannot find value `x` in this scope\n --> /home/a/app/src/main.rs:12:5\nerror: could not compile `app` due to 1 previous error\n",
+        ).unwrap();
+        let second = ErrorSignature::new(
+            "cargo build --release",
+            "   Compiling app v0.2.0 (/tmp/b/app)\nerror[E0425]: cannot find value `x` in this scope\n --> /tmp/b/app/src/main.rs:40:9\nerror: could not compile `app` due to 1 previous error\n",
+        ).unwrap();
+        assert_eq!(first.hash, second.hash);
+        assert!(first.lines.starts_with("error[E0425]"));
+        assert!(!first.lines.contains("Compiling"));
+
+        let other = ErrorSignature::new("cargo build", "error[E0425]: cannot find value `y` in this scope\n").unwrap();
+        assert_ne!(first.hash, other.hash);
+        assert_ne!(ErrorSignature::new("npm build", &first.lines).unwrap().hash, first.hash);
+        assert_eq!(ErrorSignature::new("false", "\n  \n"), None);
+        assert_eq!(normalize("segfault at 0x7ffd1234 in   /usr/lib/libc.so.6"), "segfault at <addr> in libc.so.#");
+    }
+
+    #[test]
+    fn test_pending_fix_and_palette() {
+        let explanation = "The crate is missing.\n\n```sh\n# add it\ncargo add serde\ncargo build\n```\n\n```python\nprint(1)\n```";
+        let pending = PendingFix::new(3, explanation);
+        assert!(pending.accepts("cargo add serde "));
+        assert!(pending.accepts("# add it\ncargo add serde\ncargo build"));
+        assert!(!pending.accepts("print(1)"));
+        assert!(!pending.accepts("# add it"));
+
+        let known = KnownError {
+            id: 3,
+            signature: "abc".to_string(),
+            command: "cargo build".to_string(),
+            error: "error[E0433]: failed to resolve: use of undeclared crate `serde`".to_string(),
+            explanation: explanation.to_string(),
+            fix: Some("cargo add serde".to_string()),
+            hits: 2,
+            created_at: Local::now(),
+            last_seen: Local::now(),
+        };
+        assert_eq!(summary(&known), "3. cargo build: error[E0433]: failed to resolve: use of undeclared crate `serde` (seen 3×, fixed)");
+        assert!(describe(&known).ends_with("The fix that worked: `cargo add serde`"));
+        let commands = palette_commands(&[known]);
+        assert_eq!(commands[0].action, Action::Slash("/kb show 3".to_string()));
+        assert_eq!(commands[0].description, "Fix: cargo add serde · The crate is missing.");
+    }
+}
@@ -0,0 +1,310 @@
+//! Session transcript export for the AI Terminal
+//!
+//! Serializes command blocks and AI exchanges into Markdown with fenced code
+//! blocks, a standalone HTML page, or JSON. The format is chosen from the
+//! destination's file extension. Exported text is plain content; writing it
+//! goes through the privacy scrubber like every other outgoing artifact.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use pulldown_cmark::{html, Event, Parser};
+use serde::Serialize;
+use terminal_emulator::{BlockState, CommandBlock};
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    /// Choose a format from a file extension, defaulting to Markdown
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+            Some("html" | "htm") => ExportFormat::Html,
+            Some("json") => ExportFormat::Json,
+            _ => ExportFormat::Markdown,
+        }
+    }
+}
+
+/// What produced a transcript entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    /// A shell command and its output
+    Command,
+    /// An AI prompt and the model's response
+    Ai,
+}
+
+/// One exchange in a transcript
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEntry {
+    /// Whether this is a shell command or an AI exchange
+    pub kind: EntryKind,
+    /// The command line, or the prompt without its leading `/`
+    pub input: String,
+    /// Command output or AI response
+    pub output: String,
+    /// Final state of the block
+    pub state: BlockState,
+    /// Exit code, for commands that finished
+    pub exit_code: Option<i32>,
+    /// When the block started
+    pub started_at: DateTime<Local>,
+    /// How long the block took, in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Working directory of the block
+    pub working_dir: String,
+    /// Tags of the block, given by the user and automatic
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl TranscriptEntry {
+    /// Build an entry from a block, or `None` for informational messages
+    pub fn from_block(block: &CommandBlock) -> Option<Self> {
+        // Messages from the terminal itself have no working directory
+        if block.working_dir.is_empty() {
+            return None;
+        }
+
+        let (kind, input) = match block.command.strip_prefix('/') {
+            Some(prompt) => (EntryKind::Ai, prompt.trim().to_string()),
+            None => (EntryKind::Command, block.command.clone()),
+        };
+        Some(Self {
+            kind,
+            input,
+            output: block.full_output().unwrap_or_else(|_| block.output.text()),
+            state: block.state,
+            exit_code: block.exit_code,
+            started_at: block.timestamp,
+            duration_ms: block.duration.map(|d| d.as_millis() as u64),
+            working_dir: block.working_dir.clone(),
+            tags: block.all_tags(),
+        })
+    }
+
+    /// Describe when and how the entry ran, for the text formats
+    fn summary(&self) -> String {
+        let mut parts = vec![self.started_at.format("%Y-%m-%d %H:%M:%S").to_string()];
+        if self.kind == EntryKind::Command {
+            parts.insert(0, self.working_dir.clone());
+            if let Some(code) = self.exit_code {
+                parts.push(format!("exit {}", code));
+            }
+        }
+        if let Some(ms) = self.duration_ms {
+            parts.push(format!("{:.2}s", ms as f64 / 1000.0));
+        }
+        if !self.tags.is_empty() {
+            parts.push(self.tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" "));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// A session transcript ready to be serialized
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    /// Title of the transcript
+    pub title: String,
+    /// When the transcript was exported
+    pub exported_at: DateTime<Local>,
+    /// Exchanges in chronological order
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// Build a transcript from blocks, sorted by start time
+    pub fn from_blocks<'a>(title: impl Into<String>, blocks: impl IntoIterator<Item = &'a CommandBlock>) -> Self {
+        let mut entries: Vec<TranscriptEntry> = blocks.into_iter().filter_map(TranscriptEntry::from_block).collect();
+        entries.sort_by_key(|entry| entry.started_at);
+        Self {
+            title: title.into(),
+            exported_at: Local::now(),
+            entries,
+        }
+    }
+
+    /// Serialize the transcript in the given format
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Html => self.to_html(),
+            ExportFormat::Json => serde_json::to_string_pretty(self).expect("transcripts serialize to JSON"),
+        }
+    }
+
+    /// Render as Markdown, with command output in fenced code blocks
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# {}\n\n_Exported {}_\n",
+            self.title,
+            self.exported_at.format("%Y-%m-%d %H:%M:%S")
+        );
+
+        for entry in &self.entries {
+            match entry.kind {
+                EntryKind::Command => {
+                    out.push_str(&format!("\n## `$ {}`\n\n_{}_\n\n", entry.input, entry.summary()));
+                    let fence = fence_for(&entry.output);
+                    out.push_str(&format!("{}text\n{}\n{}\n", fence, entry.output.trim_end(), fence));
+                }
+                EntryKind::Ai => {
+                    out.push_str(&format!("\n## AI: {}\n\n_{}_\n\n", entry.input, entry.summary()));
+                    // Responses are Markdown already
+                    out.push_str(entry.output.trim_end());
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+
+    /// Render as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let mut body = String::new();
+        for entry in &self.entries {
+            match entry.kind {
+                EntryKind::Command => {
+                    body.push_str(&format!(
+                        "<section class=\"command\">\n<h2><code>$ {}</code></h2>\n<p class=\"meta\">{}</p>\n<pre>{}</pre>\n</section>\n",
+                        escape_html(&entry.input),
+                        escape_html(&entry.summary()),
+                        escape_html(entry.output.trim_end()),
+                    ));
+                }
+                EntryKind::Ai => {
+                    body.push_str(&format!(
+                        "<section class=\"ai\">\n<h2>AI: {}</h2>\n<p class=\"meta\">{}</p>\n{}</section>\n",
+                        escape_html(&entry.input),
+                        escape_html(&entry.summary()),
+                        markdown_to_html(&entry.output),
+                    ));
+                }
+            }
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"meta\">Exported {exported}</p>\n{body}</body>\n</html>\n",
+            title = escape_html(&self.title),
+            style = HTML_STYLE,
+            exported = self.exported_at.format("%Y-%m-%d %H:%M:%S"),
+            body = body,
+        )
+    }
+}
+
+/// Styles embedded in HTML exports
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+pre { background: #1e1e1e; color: #d4d4d4; padding: 1rem; overflow-x: auto; border-radius: 4px; }
+section { border-top: 1px solid #ddd; padding-top: 0.5rem; }
+section.ai { background: #f6f8fa; padding: 0.5rem 1rem; }
+.meta { color: #666; font-size: 0.9em; }
+";
+
+/// Choose a code fence longer than any backtick run in `text`
+fn fence_for(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Escape text for use in HTML
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Convert a Markdown response to HTML, showing any raw HTML as text
+fn markdown_to_html(markdown: &str) -> String {
+    let events = Parser::new(markdown).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn blocks() -> Vec<CommandBlock> {
+        let mut command = CommandBlock::new("echo <hi>".to_string(), "/tmp".to_string());
+        command.append_output("<hi>\n```\n", false);
+        command.complete(0, Duration::from_millis(1500));
+
+        let mut ai = CommandBlock::new("/explain ls".to_string(), "/tmp".to_string());
+        ai.append_output("Lists **files**. <script>x</script>", false);
+        ai.complete(0, Duration::from_millis(20));
+
+        let mut message = CommandBlock::new("Backup".to_string(), String::new());
+        message.append_output("Backed up", false);
+        vec![command, ai, message]
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("a.HTML")), ExportFormat::Html);
+        assert_eq!(ExportFormat::from_path(Path::new("a.json")), ExportFormat::Json);
+        assert_eq!(ExportFormat::from_path(Path::new("a.txt")), ExportFormat::Markdown);
+    }
+
+    #[test]
+    fn test_markdown_export() {
+        let mut blocks = blocks();
+        blocks[0].add_tag("demo");
+        let transcript = Transcript::from_blocks("Session", &blocks);
+        assert_eq!(transcript.entries.len(), 2);
+
+        let markdown = transcript.to_markdown();
+        assert!(markdown.contains("## `$ echo <hi>`"));
+        assert!(markdown.contains("````text\n<hi>\n```\n````"));
+        assert!(markdown.contains("## AI: explain ls"));
+        assert!(markdown.contains("exit 0 · 1.50s · #demo"));
+        assert!(!markdown.contains("Backed up"));
+    }
+
+    #[test]
+    fn test_html_export_escapes_content() {
+        let blocks = blocks();
+        let html = Transcript::from_blocks("Session", &blocks).to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<pre>&lt;hi&gt;"));
+        assert!(html.contains("<strong>files</strong>"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let blocks = blocks();
+        let json = Transcript::from_blocks("Session", &blocks).render(ExportFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["entries"][0]["kind"], "command");
+        assert_eq!(value["entries"][1]["kind"], "ai");
+        assert_eq!(value["entries"][1]["input"], "explain ls");
+        assert_eq!(value["entries"][0]["exit_code"], 0);
+    }
+}
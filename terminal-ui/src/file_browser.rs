@@ -0,0 +1,306 @@
+//! File browser for the AI Terminal
+//!
+//! F4 (or "Toggle File Browser" in the command palette) docks a tree of the
+//! working directory over the left of the panes. Directories expand in
+//! place; a file opens in a preview beside the tree, highlighted like code
+//! blocks, and its path can be inserted into the input or the file handed
+//! to the model with a question about it. Previews read at most
+//! [`MAX_PREVIEW_BYTES`], and binary files are only described.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Most bytes of a file read for its preview or sent to the model
+pub const MAX_PREVIEW_BYTES: usize = 64 * 1024;
+
+/// Directories never listed, as they are large and rarely browsed
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "__pycache__"];
+
+/// A line of the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Full path
+    pub path: PathBuf,
+    /// File name
+    pub name: String,
+    /// Levels below the root
+    pub depth: usize,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// Whether the entry is a directory shown expanded
+    pub expanded: bool,
+}
+
+/// Contents of a previewed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePreview {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Language token for highlighting, from the extension
+    pub language: String,
+    /// Text of the file, up to [`MAX_PREVIEW_BYTES`]; `None` for binary files
+    pub text: Option<String>,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+impl FilePreview {
+    /// Read the start of a file
+    pub fn read(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+        let size = file.metadata()?.len();
+        let mut bytes = Vec::new();
+        file.take(MAX_PREVIEW_BYTES as u64).read_to_end(&mut bytes)?;
+        // Text has no NUL bytes; a UTF-8 sequence cut off at the limit is dropped
+        let text = if bytes.contains(&0) {
+            None
+        } else {
+            match String::from_utf8(bytes) {
+                Ok(text) => Some(text),
+                Err(e) if e.utf8_error().error_len().is_none() => {
+                    let valid = e.utf8_error().valid_up_to();
+                    Some(String::from_utf8_lossy(&e.into_bytes()[..valid]).into_owned())
+                }
+                Err(_) => None,
+            }
+        };
+        let language = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_string();
+        Ok(Self { path: path.to_path_buf(), language, text, size })
+    }
+
+    /// Whether the file is longer than the preview
+    pub fn is_truncated(&self) -> bool {
+        self.size > MAX_PREVIEW_BYTES as u64
+    }
+}
+
+/// Tree of a directory with expanded subdirectories
+#[derive(Debug, Clone)]
+pub struct FileBrowser {
+    root: PathBuf,
+    expanded: BTreeSet<PathBuf>,
+    entries: Vec<Entry>,
+    selected: usize,
+    show_hidden: bool,
+    preview: Option<FilePreview>,
+    /// First line of the preview shown
+    scroll: usize,
+}
+
+impl FileBrowser {
+    /// Open a browser rooted at a directory
+    pub fn new(root: &Path) -> Self {
+        let mut browser = Self {
+            root: root.to_path_buf(),
+            expanded: BTreeSet::new(),
+            entries: Vec::new(),
+            selected: 0,
+            show_hidden: false,
+            preview: None,
+            scroll: 0,
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Get the directory at the top of the tree
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the lines of the tree
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Get the index of the selected line
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Get the selected entry
+    pub fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Get the open preview
+    pub fn preview(&self) -> Option<&FilePreview> {
+        self.preview.as_ref()
+    }
+
+    /// Get the first line of the preview shown
+    pub fn preview_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scroll the preview by a number of lines, up for negative ones
+    pub fn scroll_preview(&mut self, lines: isize) {
+        let last = self.preview.as_ref().and_then(|preview| preview.text.as_ref()).map_or(0, |text| text.lines().count().saturating_sub(1));
+        self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+    }
+
+    /// Read the tree again, keeping the selection on the same path if it still exists
+    pub fn refresh(&mut self) {
+        let selected = self.selected().map(|entry| entry.path.clone());
+        self.entries.clear();
+        let root = self.root.clone();
+        self.list(&root, 0);
+        self.selected = selected
+            .and_then(|path| self.entries.iter().position(|entry| entry.path == path))
+            .unwrap_or(0);
+    }
+
+    /// Add the children of a directory to the tree, directories first, recursing into expanded ones
+    fn list(&mut self, dir: &Path, depth: usize) {
+        let Ok(read) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut children: Vec<(bool, String, PathBuf)> = read
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let path = entry.path();
+                (path.is_dir(), entry.file_name().to_string_lossy().into_owned(), path)
+            })
+            .filter(|(is_dir, name, _)| {
+                (self.show_hidden || !name.starts_with('.')) && !(*is_dir && SKIPPED_DIRS.contains(&name.as_str()))
+            })
+            .collect();
+        children.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase())));
+        for (is_dir, name, path) in children {
+            let expanded = is_dir && self.expanded.contains(&path);
+            self.entries.push(Entry { path: path.clone(), name, depth, is_dir, expanded });
+            if expanded {
+                self.list(&path, depth + 1);
+            }
+        }
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Open the selection: expand or collapse a directory, preview a file
+    pub fn open(&mut self) -> Result<()> {
+        let Some(entry) = self.selected().cloned() else {
+            return Ok(());
+        };
+        if entry.is_dir {
+            if !self.expanded.remove(&entry.path) {
+                self.expanded.insert(entry.path);
+            }
+            self.refresh();
+        } else {
+            self.preview = Some(FilePreview::read(&entry.path)?);
+            self.scroll = 0;
+        }
+        Ok(())
+    }
+
+    /// Collapse the selected directory, or else select the directory holding the selection
+    pub fn collapse(&mut self) {
+        let Some(entry) = self.selected().cloned() else {
+            return;
+        };
+        if entry.expanded {
+            self.expanded.remove(&entry.path);
+            self.refresh();
+        } else if let Some(parent) = entry.path.parent()
+            && let Some(index) = self.entries.iter().position(|other| other.path == parent)
+        {
+            self.selected = index;
+        }
+    }
+
+    /// Move the root up to its parent directory
+    pub fn go_up(&mut self) {
+        if let Some(parent) = self.root.parent().map(Path::to_path_buf) {
+            let previous = std::mem::replace(&mut self.root, parent);
+            self.expanded.insert(previous.clone());
+            self.refresh();
+            if let Some(index) = self.entries.iter().position(|entry| entry.path == previous) {
+                self.selected = index;
+            }
+        }
+    }
+
+    /// Show or hide dotfiles
+    pub fn toggle_hidden(&mut self) -> bool {
+        self.show_hidden = !self.show_hidden;
+        self.refresh();
+        self.show_hidden
+    }
+
+    /// Close the preview, returning whether one was open
+    pub fn close_preview(&mut self) -> bool {
+        self.preview.take().is_some()
+    }
+}
+
+/// Get a path as typed in a command: relative to `working_dir` when inside it, quoted when needed
+pub fn insertable_path(path: &Path, working_dir: &Path) -> String {
+    let shown = path.strip_prefix(working_dir).ok().filter(|relative| !relative.as_os_str().is_empty()).unwrap_or(path);
+    let text = shown.to_string_lossy();
+    shlex::try_quote(&text).map_or_else(|_| text.to_string(), |quoted| quoted.into_owned())
+}
+
+/// Build the context piece text holding a file for the model
+pub fn file_context(preview: &FilePreview, shown_path: &str) -> Option<String> {
+    let text = preview.text.as_ref()?;
+    let cut = if preview.is_truncated() { format!("\n[… only the first {} bytes of {} are included]", MAX_PREVIEW_BYTES, preview.size) } else { String::new() };
+    Some(format!(
+        "Contents of the file {}:\n```{}\n{}\n```{}\n\nTo propose changes to the file, give its whole new contents in a fenced block with `path={}` after the language.",
+        shown_path, preview.language, text.trim_end(), cut, shown_path,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_preview_and_paths() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-browser-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("my notes.txt"), "hello").unwrap();
+        fs::write(dir.join("blob.bin"), [0u8, 1, 2]).unwrap();
+
+        let mut browser = FileBrowser::new(&dir);
+        let names: Vec<&str> = browser.entries().iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["src", "blob.bin", "my notes.txt"]);
+
+        browser.open().unwrap();
+        assert_eq!(browser.entries()[1].name, "main.rs");
+        assert_eq!(browser.entries()[1].depth, 1);
+        browser.move_selection_down();
+        browser.open().unwrap();
+        let preview = browser.preview().unwrap();
+        assert_eq!(preview.language, "rs");
+        assert!(file_context(preview, "src/main.rs").unwrap().contains("```rs\nfn main() {}\n```"));
+
+        browser.collapse();
+        assert_eq!(browser.selected().unwrap().name, "src");
+        browser.collapse();
+        assert_eq!(browser.entries().len(), 3);
+        assert!(FilePreview::read(&dir.join("blob.bin")).unwrap().text.is_none());
+        assert!(browser.toggle_hidden());
+        assert_eq!(browser.entries().len(), 3, ".git is never listed");
+
+        assert_eq!(insertable_path(&dir.join("my notes.txt"), &dir), "'my notes.txt'");
+        assert_eq!(insertable_path(&dir.join("src/main.rs"), &dir.join("other")), dir.join("src/main.rs").to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+//! Question answering over the files of a project
+//!
+//! `/index` splits the text files under the working directory into chunks
+//! of overlapping lines and stores an embedding of each, computed by the
+//! backend's embeddings API, in the embedding index of the database. In a
+//! git repository the files are those `git ls-files` lists, so ignored
+//! files are left out; elsewhere hidden directories and build output are
+//! skipped. Each chunk's key holds its path, lines and a hash of its text,
+//! so indexing again only embeds chunks that changed and drops those that
+//! are gone.
+//!
+//! `/ask-project <question>` embeds the question, reads the chunks nearest
+//! to it from disk and asks the model to answer from them, citing them by
+//! number. The answer ends with the sources as `path:line`, which hint mode
+//! (Alt+L) opens in `$EDITOR` at that line.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use ollama_client::ChatBackend;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+#[cfg(not(feature = "embeddings"))]
+use crate::capabilities::Capability;
+
+/// Command asking a question about the project
+pub const ASK_COMMAND: &str = "/ask-project";
+
+/// Source recorded with the embeddings of chunks
+pub const SOURCE: &str = "project";
+
+/// Lines in a chunk
+const CHUNK_LINES: usize = 40;
+
+/// Lines a chunk shares with the one before it, so code split between them is found whole in one
+const CHUNK_OVERLAP: usize = 8;
+
+/// Most characters of a chunk embedded and sent to the model
+const MAX_CHUNK_CHARS: usize = 3000;
+
+/// Files larger than this, in bytes, are not indexed
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// Chunks embedded in one request
+#[cfg(feature = "embeddings")]
+const BATCH_SIZE: usize = 32;
+
+/// Directories skipped outside git repositories
+const SKIPPED_DIRS: [&str; 6] = ["target", "node_modules", "build", "dist", "vendor", "__pycache__"];
+
+/// Settings from the `[index]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    /// Model computing the embeddings
+    pub model: String,
+    /// Chunks given to the model for a question
+    pub results: usize,
+    /// Most files indexed in a project
+    pub max_files: usize,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            model: "nomic-embed-text".to_string(),
+            results: 6,
+            max_files: 2000,
+        }
+    }
+}
+
+impl IndexConfig {
+    /// Get the start of the keys of the chunks of `root` embedded by the configured model
+    pub fn prefix(&self, root: &Path) -> String {
+        format!("index:{}:{}/", self.model, root.display())
+    }
+}
+
+/// Lines of a file, as indexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Path relative to the project root
+    pub path: String,
+    /// First line, counted from 1
+    pub start_line: usize,
+    /// Last line, included
+    pub end_line: usize,
+    /// Hash of the text, which tells whether the chunk changed
+    pub hash: String,
+    /// The lines, at most [`MAX_CHUNK_CHARS`] of them
+    pub text: String,
+}
+
+impl Chunk {
+    /// Create the chunk of `lines`, the first being line `start_line`
+    fn new(path: &str, start_line: usize, lines: &[&str]) -> Self {
+        let mut text = lines.join("\n");
+        if let Some((cut, _)) = text.char_indices().nth(MAX_CHUNK_CHARS) {
+            text.truncate(cut);
+        }
+        let hash = Sha256::digest(format!("{}\n{}", path, text).as_bytes());
+        Self {
+            path: path.to_string(),
+            start_line,
+            end_line: start_line + lines.len() - 1,
+            hash: format!("{:x}", hash)[..16].to_string(),
+            text,
+        }
+    }
+
+    /// Get the key of the chunk's embedding
+    pub fn key(&self, prefix: &str) -> String {
+        format!("{}{}#L{}-{}@{}", prefix, self.path, self.start_line, self.end_line, self.hash)
+    }
+
+    /// Read the chunk an embedding key stands for back from the file under `root`
+    ///
+    /// The text is the lines as they are now, so `None` means the file is gone.
+    pub fn from_key(key: &str, prefix: &str, root: &Path) -> Option<Self> {
+        let (rest, hash) = key.strip_prefix(prefix)?.rsplit_once('@')?;
+        let (path, lines) = rest.rsplit_once("#L")?;
+        let (start, end) = lines.split_once('-')?;
+        let (start_line, end_line): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+        let content = read_text(&root.join(path))?;
+        let text: Vec<&str> = content.lines().skip(start_line.saturating_sub(1)).take(end_line + 1 - start_line).collect();
+        if text.is_empty() {
+            return None;
+        }
+        Some(Self { hash: hash.to_string(), ..Self::new(path, start_line, &text) })
+    }
+
+    /// Get the text the embedding is computed from, which names the file
+    #[cfg(feature = "embeddings")]
+    fn embedding_input(&self) -> String {
+        format!("{}\n{}", self.path, self.text)
+    }
+
+    /// Refer to the chunk as compilers do, so hint mode can open it
+    pub fn citation(&self) -> String {
+        format!("{}:{}", self.path, self.start_line)
+    }
+}
+
+/// Split a file into chunks of overlapping lines, leaving out blank ones
+pub fn chunks(path: &str, content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        if lines[start..end].iter().any(|line| !line.trim().is_empty()) {
+            chunks.push(Chunk::new(path, start + 1, &lines[start..end]));
+        }
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+    chunks
+}
+
+/// Read a file if it is small enough and text
+fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|byte| *byte == 0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// List the files of a project, relative to its root, leaving out ignored ones
+pub fn files(root: &Path, max_files: usize) -> Result<Vec<String>> {
+    let listed = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard", "-z"])
+        .current_dir(root)
+        .output();
+    let mut files: Vec<String> = match listed {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => {
+            let mut files = Vec::new();
+            walk(root, root, &mut files, max_files)?;
+            files
+        }
+    };
+    files.sort();
+    files.truncate(max_files);
+    Ok(files)
+}
+
+/// Collect the files under `dir` that are not hidden or build output, up to `max_files`
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>, max_files: usize) -> Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+    for path in entries {
+        if files.len() >= max_files {
+            break;
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        // Links are not followed, so a link to a parent directory cannot loop
+        let Ok(file_type) = fs::symlink_metadata(&path).map(|metadata| metadata.file_type()) else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                walk(root, &path, files, max_files)?;
+            }
+        } else if file_type.is_file()
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            files.push(relative.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+/// What indexing a project again has to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexPlan {
+    /// Files read
+    pub files: usize,
+    /// Chunks of those files
+    pub chunks: usize,
+    /// Chunks that are new or changed since the last time, to be embedded
+    pub pending: Vec<Chunk>,
+    /// Keys of chunks that are gone, to be removed
+    pub stale: Vec<String>,
+}
+
+impl IndexPlan {
+    /// Compare the files under `root` with the keys already indexed
+    pub fn new(config: &IndexConfig, root: &Path, indexed: &[String]) -> Result<Self> {
+        let prefix = config.prefix(root);
+        let files = files(root, config.max_files)?;
+        let mut read = 0;
+        let mut current = Vec::new();
+        for path in &files {
+            if let Some(content) = read_text(&root.join(path)) {
+                read += 1;
+                current.extend(chunks(path, &content));
+            }
+        }
+        let keys: HashSet<String> = current.iter().map(|chunk| chunk.key(&prefix)).collect();
+        let indexed: HashSet<&String> = indexed.iter().collect();
+        let chunks = current.len();
+        let pending = current.into_iter().filter(|chunk| !indexed.contains(&chunk.key(&prefix))).collect();
+        let stale = indexed.into_iter().filter(|key| !keys.contains(*key)).cloned().collect();
+        Ok(Self { files: read, chunks, pending, stale })
+    }
+}
+
+/// Embeddings computed for some chunks, or why computing them failed
+pub type EmbeddedBatch = Result<Vec<(Chunk, Vec<f32>)>, String>;
+
+/// Compute the embeddings of chunks a batch at a time, sending each batch as it is done
+///
+/// Stops at the first failure, or once nobody is receiving.
+#[cfg(feature = "embeddings")]
+pub async fn embed(backend: Arc<dyn ChatBackend>, model: String, chunks: Vec<Chunk>, updates: mpsc::UnboundedSender<EmbeddedBatch>) {
+    for batch in chunks.chunks(BATCH_SIZE) {
+        let inputs: Vec<String> = batch.iter().map(Chunk::embedding_input).collect();
+        let result = match backend.embeddings(&model, &inputs).await {
+            Ok(vectors) if vectors.len() == batch.len() => Ok(batch.iter().cloned().zip(vectors).collect()),
+            Ok(vectors) => Err(format!("{} returned {} embeddings for {} chunks", model, vectors.len(), batch.len())),
+            Err(e) => Err(format!("{} could not compute embeddings: {}", model, e)),
+        };
+        let failed = result.is_err();
+        if updates.send(result).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Stand-in for builds without the `embeddings` feature, which fails the first batch
+#[cfg(not(feature = "embeddings"))]
+pub async fn embed(_backend: Arc<dyn ChatBackend>, _model: String, _chunks: Vec<Chunk>, updates: mpsc::UnboundedSender<EmbeddedBatch>) {
+    if let Err(e) = Capability::Embeddings.require() {
+        let _ = updates.send(Err(e.to_string()));
+    }
+}
+
+/// Get the question of an `/ask-project` command, if it is one
+pub fn question(ai_command: &str) -> Option<&str> {
+    let rest = ai_command.strip_prefix(ASK_COMMAND)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// Build the prompt answering `question` from the chunks found for it
+pub fn prompt(question: &str, found: &[Chunk]) -> Result<(String, String)> {
+    if found.is_empty() {
+        bail!("nothing in the index matches; index the project again with /index");
+    }
+    let prompt = format!(
+        "Answer this question about the project using the numbered excerpts of its files below. \
+         Cite the excerpts you rely on by number, as [1], and say so if they do not answer it.\n\nQuestion: {}",
+        question,
+    );
+    let excerpts: Vec<String> = found
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("[{}] {} (lines {}-{})\n```\n{}\n```", i + 1, chunk.path, chunk.start_line, chunk.end_line, chunk.text))
+        .collect();
+    Ok((prompt, excerpts.join("\n\n")))
+}
+
+/// List the chunks an answer was given, to end it with
+pub fn sources(found: &[Chunk]) -> String {
+    let lines: Vec<String> = found
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("[{}] {} (lines {}-{})", i + 1, chunk.citation(), chunk.start_line, chunk.end_line))
+        .collect();
+    format!("\n\nSources:\n\n{}", lines.join("  \n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_and_keys() {
+        let content: String = (1..=90).map(|n| format!("line {}\n", n)).collect();
+        let found = chunks("src/main.rs", &content);
+        let ranges: Vec<(usize, usize)> = found.iter().map(|chunk| (chunk.start_line, chunk.end_line)).collect();
+        assert_eq!(ranges, [(1, 40), (33, 72), (65, 90)]);
+        assert!(chunks("empty.txt", "\n  \n").is_empty());
+
+        let root = std::env::temp_dir().join(format!("ai-terminal-index-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/main.rs"), &content).unwrap();
+        fs::write(root.join("target/out.rs"), "built").unwrap();
+        fs::write(root.join("logo.png"), b"\x89PNG\0\0").unwrap();
+
+        let config = IndexConfig::default();
+        let prefix = config.prefix(&root);
+        let key = found[1].key(&prefix);
+        assert_eq!(Chunk::from_key(&key, &prefix, &root).as_ref(), Some(&found[1]));
+        assert_eq!(Chunk::from_key(&key, "index:other:", &root), None);
+
+        let plan = IndexPlan::new(&config, &root, &[]).unwrap();
+        assert_eq!((plan.files, plan.chunks, plan.pending.len()), (1, 3, 3));
+        let mut indexed: Vec<String> = plan.pending.iter().map(|chunk| chunk.key(&prefix)).collect();
+        indexed.push(format!("{}gone.rs#L1-2@0", prefix));
+        let plan = IndexPlan::new(&config, &root, &indexed).unwrap();
+        assert!(plan.pending.is_empty());
+        assert_eq!(plan.stale, [format!("{}gone.rs#L1-2@0", prefix)]);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(question("/ask-project where is config read?"), Some("where is config read?"));
+        assert_eq!(question("/ask-projects"), None);
+        assert!(sources(&found[..1]).ends_with("[1] src/main.rs:1 (lines 1-40)"));
+    }
+}
@@ -0,0 +1,270 @@
+//! Git awareness for the AI Terminal
+//!
+//! When the working directory is inside a git repository, the status bar
+//! shows its branch, whether the work tree has uncommitted changes and how
+//! far the branch is ahead of or behind its upstream, and every command run
+//! there records the commit checked out at the time on its block.
+//!
+//! Two AI workflows read the staged diff: "AI: Write Commit Message"
+//! (Ctrl+G) has the model draft a commit message, which opens in an editor
+//! and is committed with `git commit` once accepted, and "AI: Explain Diff"
+//! (Alt+G) has it explain the changes in a block, falling back to the
+//! unstaged changes when nothing is staged.
+//!
+//! Everything is read by running `git`, so nothing is shown without it. The
+//! status is read in the background, so a slow repository does not hold up
+//! the UI, again at most every [`REFRESH_INTERVAL`], and right after a
+//! command or a change of directory.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use tokio::sync::oneshot;
+
+/// Time after which the status is read again
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Most bytes of the staged diff sent to the model
+pub const MAX_DIFF_BYTES: usize = 48 * 1024;
+
+/// Command of the block answering a commit message request
+pub const COMMIT_MESSAGE_COMMAND: &str = "/commit message for the staged changes";
+
+/// Command of the block answering an explain diff request
+pub const EXPLAIN_DIFF_COMMAND: &str = "/explain the diff";
+
+/// Length of the abbreviated commit hashes shown
+const SHORT_HASH_LEN: usize = 8;
+
+/// State of the repository holding a directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitContext {
+    /// Branch checked out; `None` on a detached head
+    pub branch: Option<String>,
+    /// Commit checked out; `None` before the first commit
+    pub head: Option<String>,
+    /// Whether tracked or untracked files differ from the commit
+    pub dirty: bool,
+    /// Commits the branch is ahead of its upstream
+    pub ahead: u32,
+    /// Commits the branch is behind its upstream
+    pub behind: u32,
+}
+
+impl GitContext {
+    /// Read the repository holding `dir`, if it is in one
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let output = git(dir, &["status", "--porcelain=v2", "--branch", "--untracked-files=normal"]).ok()?;
+        Some(Self::parse(&output))
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`
+    fn parse(status: &str) -> Self {
+        let mut context = Self::default();
+        for line in status.lines() {
+            if let Some(oid) = line.strip_prefix("# branch.oid ") {
+                context.head = (oid != "(initial)").then(|| oid.to_string());
+            } else if let Some(head) = line.strip_prefix("# branch.head ") {
+                context.branch = (head != "(detached)").then(|| head.to_string());
+            } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+                for count in counts.split_whitespace() {
+                    if let Some(ahead) = count.strip_prefix('+') {
+                        context.ahead = ahead.parse().unwrap_or(0);
+                    } else if let Some(behind) = count.strip_prefix('-') {
+                        context.behind = behind.parse().unwrap_or(0);
+                    }
+                }
+            } else if !line.starts_with('#') && !line.is_empty() {
+                context.dirty = true;
+            }
+        }
+        context
+    }
+
+    /// Get the abbreviated hash of the commit checked out
+    pub fn short_head(&self) -> Option<&str> {
+        self.head.as_deref().map(|head| &head[..head.len().min(SHORT_HASH_LEN)])
+    }
+
+    /// Format the status bar segment, such as ` ⎇ main* ↑2 `
+    pub fn status_segment(&self) -> String {
+        let name = match (&self.branch, self.short_head()) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(head)) => format!("({})", head),
+            (None, None) => "(no commits)".to_string(),
+        };
+        let mut segment = format!(" ⎇ {}{}", name, if self.dirty { "*" } else { "" });
+        if self.ahead > 0 {
+            segment.push_str(&format!(" ↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            segment.push_str(&format!(" ↓{}", self.behind));
+        }
+        segment.push(' ');
+        segment
+    }
+}
+
+/// Keeps the git state of the working directory up to date
+#[derive(Debug, Default)]
+pub struct GitTracker {
+    dir: PathBuf,
+    context: Option<GitContext>,
+    checked: Option<Instant>,
+    /// Read of the status running in the background, with the directory it reads
+    pending: Option<(PathBuf, oneshot::Receiver<Option<GitContext>>)>,
+}
+
+impl GitTracker {
+    /// Get the state of the repository last read
+    pub fn context(&self) -> Option<&GitContext> {
+        self.context.as_ref()
+    }
+
+    /// Take the state once a read finishes, and start one if `dir` changed or the last read is older than [`REFRESH_INTERVAL`]
+    pub fn tick(&mut self, dir: &Path) {
+        if let Some((read_dir, pending)) = &mut self.pending {
+            let context = match pending.try_recv() {
+                Ok(context) => context,
+                Err(oneshot::error::TryRecvError::Empty) => return,
+                Err(oneshot::error::TryRecvError::Closed) => None,
+            };
+            self.dir = std::mem::take(read_dir);
+            self.context = context;
+            self.checked = Some(Instant::now());
+            self.pending = None;
+        }
+        let stale = self.checked.is_none_or(|checked| checked.elapsed() >= REFRESH_INTERVAL);
+        if stale || self.dir != dir {
+            self.refresh(dir);
+        }
+    }
+
+    /// Start reading the state of the repository holding `dir` in the background
+    pub fn refresh(&mut self, dir: &Path) {
+        // The state of another directory would be recorded on its blocks
+        if self.dir != dir {
+            self.context = None;
+        }
+        let (sender, receiver) = oneshot::channel();
+        let read_dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(GitContext::detect(&read_dir));
+        });
+        self.pending = Some((dir.to_path_buf(), receiver));
+    }
+
+    /// Read the state again on the next tick, as after a command that may have changed it
+    pub fn invalidate(&mut self) {
+        // A read already running may have started before the change
+        self.pending = None;
+        self.checked = None;
+    }
+}
+
+/// Build the prompt asking for a commit message for the diff staged in the repository holding `dir`, and the diff
+pub fn commit_message_prompt(dir: &Path) -> Result<(String, String)> {
+    let Some(diff) = diff_context(dir, true)? else {
+        bail!("nothing is staged; stage changes with git add first");
+    };
+    let prompt = "Write a git commit message for the staged changes: a subject line of at most 72 characters in the imperative mood, \
+                  a blank line, then a short body explaining what changed and why. Reply with the message only, in a fenced block.".to_string();
+    Ok((prompt, diff))
+}
+
+/// Build the prompt asking to explain the staged diff, or the unstaged one if nothing is staged, and the diff
+pub fn explain_diff_prompt(dir: &Path) -> Result<(String, String)> {
+    let diff = match diff_context(dir, true)? {
+        Some(diff) => diff,
+        None => diff_context(dir, false)?.ok_or_else(|| anyhow::anyhow!("there are no changes to explain"))?,
+    };
+    let prompt = "Explain these changes for a reviewer: what they do, file by file, and anything that looks risky or unfinished.".to_string();
+    Ok((prompt, diff))
+}
+
+/// Get the staged or unstaged diff with a summary of the files, cut to [`MAX_DIFF_BYTES`]; `None` if it is empty
+fn diff_context(dir: &Path, staged: bool) -> Result<Option<String>> {
+    let which: &[&str] = if staged { &["diff", "--cached"] } else { &["diff"] };
+    let mut diff = git(dir, &[which, &["--no-color", "--no-ext-diff"]].concat())?;
+    if diff.trim().is_empty() {
+        return Ok(None);
+    }
+    let stat = git(dir, &[which, &["--stat", "--no-color"]].concat())?;
+    if diff.len() > MAX_DIFF_BYTES {
+        let mut cut = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+        diff.push_str("\n[… the rest of the diff was left out; the summary above lists every file]");
+    }
+    let label = if staged { "Staged changes" } else { "Unstaged changes" };
+    Ok(Some(format!("{}:\n{}\n```diff\n{}\n```", label, stat.trim_end(), diff.trim_end())))
+}
+
+/// Get the commit message from the model's answer: its first fenced block, or else the whole answer
+pub fn extract_message(response: &str) -> String {
+    let message = crate::snippets::code_blocks(response, "")
+        .into_iter()
+        .next()
+        .map_or_else(|| response.to_string(), |block| block.code);
+    message.trim().to_string()
+}
+
+/// Build the command committing the staged changes with the message in `message_file`
+pub fn commit_command(message_file: &Path) -> String {
+    let path = message_file.to_string_lossy();
+    format!("git commit -F {}", shlex::try_quote(&path).unwrap_or(path.clone()))
+}
+
+/// Run git in `dir`, returning its standard output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .output()
+        .context("could not run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parse_and_segment() {
+        let status = "# branch.oid 0123456789abcdef\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -0\n1 .M N... 100644 100644 100644 abc abc src/lib.rs\n";
+        let context = GitContext::parse(status);
+        assert_eq!(context.branch.as_deref(), Some("main"));
+        assert_eq!(context.short_head(), Some("01234567"));
+        assert!(context.dirty);
+        assert_eq!(context.status_segment(), " ⎇ main* ↑2 ");
+
+        let detached = GitContext::parse("# branch.oid 0123456789abcdef\n# branch.head (detached)\n");
+        assert_eq!(detached.status_segment(), " ⎇ (01234567) ");
+        assert_eq!(GitContext::parse("# branch.oid (initial)\n# branch.head main\n").head, None);
+
+        assert_eq!(extract_message("Here you go:\n```\nFix the parser\n\nIt dropped the last token.\n```\n"), "Fix the parser\n\nIt dropped the last token.");
+        assert_eq!(extract_message("  Fix the parser \n"), "Fix the parser");
+        assert_eq!(commit_command(Path::new("/tmp/a b/MSG")), "git commit -F '/tmp/a b/MSG'");
+
+        let outside = std::env::temp_dir().join(format!("ai-terminal-git-{}", std::process::id()));
+        std::fs::create_dir_all(&outside).unwrap();
+        let mut tracker = GitTracker::default();
+        tracker.tick(&outside);
+        while tracker.pending.is_some() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            tracker.tick(&outside);
+        }
+        assert_eq!(tracker.dir, outside);
+        assert!(tracker.context().is_none() || outside.ancestors().skip(1).any(|dir| dir.join(".git").exists()));
+        std::fs::remove_dir_all(&outside).unwrap();
+    }
+}
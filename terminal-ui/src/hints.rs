@@ -0,0 +1,239 @@
+//! Link and path hints for the AI Terminal
+//!
+//! URLs and file paths in command output are found as the output is drawn
+//! and underlined. Hint mode, like kitty's, labels every one on screen with
+//! a letter or two: typing a label opens the URL in the browser or the file
+//! in `$EDITOR`, at the line a compiler pointed to, and typing it in capitals
+//! inserts the URL or path into the input line instead.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// Letters labels are made of, the easiest to reach first
+const LABEL_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// Get the pattern matching URLs with a scheme browsers open
+///
+/// Output is scanned on every frame, so the patterns are compiled once.
+fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"\b(?:https?|ftp|file)://[^\s<>"'`]+"#).expect("URL pattern is valid"))
+}
+
+/// Get the pattern matching paths with a slash, or file names with a line number as compilers print them
+fn path_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?:(?:~|\.\.?)?/[\w.@+-]+(?:/[\w.@+-]+)*|[\w.@+-]+(?:/[\w.@+-]+)+)(?::(\d+)(?::\d+)?)?|[\w@+-][\w.@+-]*\.[A-Za-z]\w{0,7}:(\d+)(?::\d+)?")
+            .expect("path pattern is valid")
+    })
+}
+
+/// What was found in a line of output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Found {
+    /// A URL
+    Url(String),
+    /// A path, as written, and the line it points to, if any
+    Path(String, Option<u32>),
+}
+
+/// Find the URLs and paths in a line, with the byte range each was found at
+///
+/// Text that is part of a URL is not taken as a path, and punctuation ending
+/// a sentence is left off.
+pub fn find(line: &str) -> Vec<(Range<usize>, Found)> {
+    let mut found: Vec<(Range<usize>, Found)> = url_pattern()
+        .find_iter(line)
+        .map(|url| {
+            let text = url.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}']);
+            (url.start()..url.start() + text.len(), Found::Url(text.to_string()))
+        })
+        .collect();
+    for path in path_pattern().captures_iter(line) {
+        let whole = path.get(0).expect("a match has a whole");
+        if found.iter().any(|(range, _)| range.start < whole.end() && whole.start() < range.end) {
+            continue;
+        }
+        let line_number = path.get(1).or_else(|| path.get(2)).and_then(|number| number.as_str().parse().ok());
+        let text = match line_number {
+            Some(_) => &line[whole.start()..whole.end()],
+            None => whole.as_str().trim_end_matches(['.', ',', ';', ':']),
+        };
+        let file = text.split(':').next().unwrap_or(text);
+        found.push((whole.start()..whole.start() + text.len(), Found::Path(file.to_string(), line_number)));
+    }
+    found.sort_by_key(|(range, _)| range.start);
+    found
+}
+
+/// Resolve a path found in output against the directory the command ran in
+pub fn resolve(path: &str, working_dir: &Path) -> PathBuf {
+    working_dir.join(crate::expand_home(path))
+}
+
+/// A URL or path on screen that hint mode can act on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// Text as it is shown
+    pub text: String,
+    /// What the text points to
+    pub kind: TargetKind,
+    /// Screen column the text starts at
+    pub column: u16,
+    /// Screen row the text is on
+    pub row: u16,
+}
+
+/// What a target points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetKind {
+    /// A URL, opened in the browser
+    Url(String),
+    /// An existing file or directory and the line to open it at, opened in the editor
+    Path(PathBuf, Option<u32>),
+}
+
+/// What to do with the chosen target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintAction {
+    /// Open the URL in the browser or the file in the editor
+    Open,
+    /// Insert the URL or path into the input line
+    Insert,
+}
+
+/// Targets labelled for choosing from the keyboard
+#[derive(Debug, Clone)]
+pub struct HintMode {
+    targets: Vec<Target>,
+    labels: Vec<String>,
+    /// Label letters typed so far
+    typed: String,
+}
+
+impl HintMode {
+    /// Label the targets on screen, returning `None` if there are none
+    pub fn new(targets: Vec<Target>) -> Option<Self> {
+        if targets.is_empty() {
+            return None;
+        }
+        let labels = labels(targets.len());
+        Some(Self { targets, labels, typed: String::new() })
+    }
+
+    /// Iterate over the targets still matching what was typed, with their labels
+    pub fn visible(&self) -> impl Iterator<Item = (&Target, &str)> {
+        self.targets
+            .iter()
+            .zip(&self.labels)
+            .filter(|(_, label)| label.starts_with(&self.typed))
+            .map(|(target, label)| (target, label.as_str()))
+    }
+
+    /// Get the label letters typed so far
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    /// Type a label letter, returning the target and action once a label is complete
+    ///
+    /// A capital letter chooses inserting over opening. Letters no label
+    /// continues with are ignored.
+    pub fn push(&mut self, c: char) -> Option<(Target, HintAction)> {
+        let action = if c.is_uppercase() { HintAction::Insert } else { HintAction::Open };
+        let typed = format!("{}{}", self.typed, c.to_ascii_lowercase());
+        if !self.labels.iter().any(|label| label.starts_with(&typed)) {
+            return None;
+        }
+        self.typed = typed;
+        let index = self.labels.iter().position(|label| *label == self.typed)?;
+        Some((self.targets[index].clone(), action))
+    }
+
+    /// Remove the last label letter typed, returning whether there was one
+    pub fn pop(&mut self) -> bool {
+        self.typed.pop().is_some()
+    }
+}
+
+/// Make `count` labels, none the start of another
+fn labels(count: usize) -> Vec<String> {
+    let keys: Vec<char> = LABEL_KEYS.chars().collect();
+    if count <= keys.len() {
+        return keys.iter().take(count).map(char::to_string).collect();
+    }
+    keys.iter()
+        .flat_map(|&first| keys.iter().map(move |&second| format!("{}{}", first, second)))
+        .take(count)
+        .collect()
+}
+
+/// Open a URL in the desktop's browser without waiting for it
+pub fn open_url(url: &str) -> std::io::Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Build the command opening a file in `$VISUAL` or `$EDITOR`, `vi` if neither is set
+///
+/// The line is passed as `+line`, which vi, Vim, Neovim, nano and Emacs
+/// all understand.
+pub fn editor_command(path: &Path, line: Option<u32>) -> Command {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = shlex::split(&editor).unwrap_or_else(|| vec![editor.clone()]).into_iter();
+    let mut command = Command::new(words.next().unwrap_or_else(|| "vi".to_string()));
+    command.args(words);
+    if let Some(line) = line {
+        command.arg(format!("+{}", line));
+    }
+    command.arg(path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_urls_and_paths() {
+        let found = find("error at src/main.rs:42:7, see https://docs.rs/regex. Also ~/notes and main.rs:3");
+        assert_eq!(found.iter().map(|(_, found)| found.clone()).collect::<Vec<_>>(), vec![
+            Found::Path("src/main.rs".to_string(), Some(42)),
+            Found::Url("https://docs.rs/regex".to_string()),
+            Found::Path("~/notes".to_string(), None),
+            Found::Path("main.rs".to_string(), Some(3)),
+        ]);
+        let (range, _) = &found[0];
+        assert_eq!(range.clone(), 9..25);
+        assert!(find("version 1.2.3 is out").is_empty());
+    }
+
+    #[test]
+    fn test_labels_choose_targets() {
+        assert_eq!(labels(3), ["a", "s", "d"]);
+        assert_eq!(labels(30)[27], "ss");
+
+        let target = |text: &str| Target { text: text.to_string(), kind: TargetKind::Url(text.to_string()), column: 0, row: 0 };
+        let mut hints = HintMode::new((0..30).map(|i| target(&i.to_string())).collect()).unwrap();
+        assert_eq!(hints.push('x'), None);
+        assert_eq!(hints.push('a'), None);
+        assert_eq!(hints.visible().count(), 26);
+        assert_eq!(hints.push('S'), Some((target("1"), HintAction::Insert)));
+        assert!(HintMode::new(Vec::new()).is_none());
+    }
+}
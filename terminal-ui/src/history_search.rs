@@ -0,0 +1,174 @@
+//! Semantic search of the command history
+//!
+//! Substring search only finds a command by words it contains.
+//! `/history semantic <query>`, or 'history semantic: Search History' in the
+//! command palette, finds commands by what they did instead, as in "that
+//! command where I fixed the docker network". Each distinct command of the
+//! history is embedded with the `[index]` model and kept in the embedding
+//! index of the database, together with the AI summary of its output when a
+//! block in view has one. A search embeds the commands not indexed yet, a
+//! batch of the newest at a time, then lists those nearest to the query.
+//! `/history search <text>` is the plain substring search of the whole
+//! history. Searching by meaning needs the `embeddings` feature; builds
+//! without it leave it out of the palette and `/history semantic` says why.
+
+use std::collections::{HashMap, HashSet};
+
+use sha2::{Digest, Sha256};
+use terminal_emulator::HistoryEntry;
+
+/// Command searching the history by meaning
+pub const SEMANTIC_COMMAND: &str = "/history semantic";
+
+/// Source recorded with the embeddings of commands
+pub const SOURCE: &str = "history";
+
+/// Most commands embedded by one search; later searches carry on with the rest
+pub const MAX_EMBEDDED_PER_SEARCH: usize = 256;
+
+/// Commands embedded in one request
+pub const BATCH_SIZE: usize = 32;
+
+/// Most history entries looked at
+pub const MAX_ENTRIES: usize = 5000;
+
+/// Results listed for a search
+pub const RESULTS: usize = 10;
+
+/// Most characters of an output summary embedded with its command
+const MAX_SUMMARY_CHARS: usize = 1000;
+
+/// A command of the history, as embedded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryItem {
+    /// The command
+    pub command: String,
+    /// AI summary of the command's output, if a block has one
+    pub summary: Option<String>,
+}
+
+impl HistoryItem {
+    /// Get the text the embedding is computed from
+    pub fn embedding_input(&self) -> String {
+        match &self.summary {
+            Some(summary) => format!("{}\n{}", self.command, summary),
+            None => self.command.clone(),
+        }
+    }
+
+    /// Get the key of the item's embedding, which changes with its input
+    pub fn key(&self, prefix: &str) -> String {
+        let hash = Sha256::digest(self.embedding_input().as_bytes());
+        format!("{}{}:{}", prefix, &format!("{:x}", hash)[..8], self.command)
+    }
+}
+
+/// Get the start of the keys of the commands embedded by `model`
+pub fn prefix(model: &str) -> String {
+    format!("history:{}:", model)
+}
+
+/// Read the command back from an embedding key
+pub fn command_from_key<'a>(key: &'a str, prefix: &str) -> Option<&'a str> {
+    key.strip_prefix(prefix)?.split_once(':').map(|(_, command)| command)
+}
+
+/// Get the query of a `/history semantic` command, if it is one
+pub fn query(ai_command: &str) -> Option<&str> {
+    let rest = ai_command.strip_prefix(SEMANTIC_COMMAND)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// The distinct commands of the history, newest first, with the summaries found for them
+pub fn items(entries: &[HistoryEntry], summaries: &HashMap<String, String>) -> Vec<HistoryItem> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .rev()
+        .filter(|entry| !entry.command.trim().is_empty() && seen.insert(entry.command.as_str()))
+        .map(|entry| HistoryItem {
+            command: entry.command.clone(),
+            summary: summaries.get(&entry.command).map(|summary| summary.chars().take(MAX_SUMMARY_CHARS).collect()),
+        })
+        .collect()
+}
+
+/// Split the items into those to embed and the keys they replace
+///
+/// A command embedded before is only embedded again when it has a summary
+/// now that it was not embedded with, so one without a summary in view keeps
+/// its embedding.
+pub fn plan(items: Vec<HistoryItem>, indexed: &[String], prefix: &str) -> (Vec<HistoryItem>, Vec<String>) {
+    let mut keys: HashMap<&str, Vec<&String>> = HashMap::new();
+    for key in indexed {
+        if let Some(command) = command_from_key(key, prefix) {
+            keys.entry(command).or_default().push(key);
+        }
+    }
+    let mut pending = Vec::new();
+    let mut stale = Vec::new();
+    for item in items {
+        let key = item.key(prefix);
+        match keys.get(item.command.as_str()) {
+            None => pending.push(item),
+            Some(existing) if item.summary.is_some() && !existing.contains(&&key) => {
+                stale.extend(existing.iter().map(|key| key.to_string()));
+                pending.push(item);
+            }
+            Some(_) => {}
+        }
+    }
+    (pending, stale)
+}
+
+/// List the commands found, nearest first, with how near they are
+pub fn describe(found: &[(String, f32)]) -> String {
+    let lines: Vec<String> = found
+        .iter()
+        .enumerate()
+        .map(|(i, (command, similarity))| format!("{}. {} ({:.0}%)", i + 1, command, similarity * 100.0))
+        .collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str) -> HistoryEntry {
+        HistoryEntry { command: command.to_string(), timestamp: chrono::Local::now() }
+    }
+
+    #[test]
+    fn test_items_keys_and_plan() {
+        let entries = [entry("docker network prune"), entry("ls"), entry("docker network prune"), entry(" ")];
+        let summaries = HashMap::from([("ls".to_string(), "Listed two files".to_string())]);
+        let found = items(&entries, &summaries);
+        let commands: Vec<&str> = found.iter().map(|item| item.command.as_str()).collect();
+        assert_eq!(commands, ["docker network prune", "ls"]);
+        assert_eq!(found[1].embedding_input(), "ls\nListed two files");
+
+        let prefix = prefix("nomic-embed-text");
+        let key = found[0].key(&prefix);
+        assert_eq!(command_from_key(&key, &prefix), Some("docker network prune"));
+        assert_eq!(query("/history semantic  fixed the network "), Some("fixed the network"));
+        assert_eq!(query("/history semantically"), None);
+
+        // `ls` was embedded without its summary, so it is embedded again with it
+        let bare_ls = HistoryItem { command: "ls".to_string(), summary: None }.key(&prefix);
+        let (pending, stale) = plan(found.clone(), &[key.clone(), bare_ls.clone()], &prefix);
+        assert_eq!(pending, [found[1].clone()]);
+        assert_eq!(stale, [bare_ls]);
+
+        // Without the summary in view, the summarized embedding stays
+        let summarized = found[1].key(&prefix);
+        let (pending, stale) = plan(items(&entries, &HashMap::new()), &[key, summarized], &prefix);
+        assert!(pending.is_empty() && stale.is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        let found = [("docker network prune".to_string(), 0.912), ("ls".to_string(), 0.2)];
+        assert_eq!(describe(&found), "1. docker network prune (91%)\n2. ls (20%)");
+    }
+}
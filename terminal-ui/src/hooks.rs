@@ -0,0 +1,194 @@
+//! Command hooks for the AI Terminal
+//!
+//! The `[hooks]` section lists shell commands run around every command
+//! block, to log commands, set a tmux window title and the like:
+//!
+//! ```toml
+//! [hooks]
+//! pre_exec = ["tmux rename-window {{command}}"]
+//! post_exec = ["echo \"$AI_TERMINAL_EXIT_CODE $AI_TERMINAL_COMMAND\" >> ~/.ai-terminal-commands.log"]
+//! ```
+//!
+//! Hooks run through `sh -c` (`cmd /V:ON /C` on Windows) in the command's
+//! directory. They get the command as `$AI_TERMINAL_COMMAND`, its directory
+//! as `$AI_TERMINAL_CWD` and, after it ran, its `$AI_TERMINAL_EXIT_CODE` and
+//! `$AI_TERMINAL_DURATION_MS`; `{{command}}`, `{{cwd}}`, `{{exit_code}}` and
+//! `{{duration_ms}}` in a hook are replaced by the same values, quoted as
+//! one shell word. `cmd` has no such quoting, so on Windows they become
+//! `!AI_TERMINAL_COMMAND!` and the like, which it expands only once the
+//! hook is parsed. Pre-exec hooks finish before the command starts, while
+//! post-exec hooks run in the background. A hook is stopped after
+//! `timeout_secs`, and a failing hook is logged without affecting the command.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// Names of the `{{name}}` templates hooks may use
+const TEMPLATES: [&str; 4] = ["command", "cwd", "exit_code", "duration_ms"];
+
+/// Hooks from the `[hooks]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    /// Commands run before every command block
+    pub pre_exec: Vec<String>,
+    /// Commands run after every command block
+    pub post_exec: Vec<String>,
+    /// Seconds a hook may run before it is stopped
+    pub timeout_secs: u64,
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            pre_exec: Vec::new(),
+            post_exec: Vec::new(),
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// A command block, as hooks see it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookContext {
+    pub command: String,
+    pub cwd: String,
+    /// Exit code and duration, once the command ran
+    pub finished: Option<(i32, Duration)>,
+}
+
+impl HookContext {
+    /// Get the variables set for hooks
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("AI_TERMINAL_COMMAND", self.command.clone()), ("AI_TERMINAL_CWD", self.cwd.clone())];
+        if let Some((exit_code, duration)) = self.finished {
+            env.push(("AI_TERMINAL_EXIT_CODE", exit_code.to_string()));
+            env.push(("AI_TERMINAL_DURATION_MS", duration.as_millis().to_string()));
+        }
+        env
+    }
+
+    /// Replace the `{{name}}` templates of a hook by quoted values
+    ///
+    /// Templates of values not known yet, such as `{{exit_code}}` before
+    /// the command ran, become empty words.
+    pub fn expand(&self, hook: &str) -> String {
+        self.expand_for(hook, cfg!(windows))
+    }
+
+    /// Replace the templates of a hook for `sh -c`, or for `cmd /V:ON /C` on Windows
+    ///
+    /// The hook is read once from left to right, so templates in the values
+    /// put in are left as they are.
+    fn expand_for(&self, hook: &str, windows: bool) -> String {
+        let env = self.env();
+        let mut expanded = String::with_capacity(hook.len());
+        let mut rest = hook;
+        while let Some(start) = rest.find("{{") {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(name) = TEMPLATES.into_iter().find(|name| rest[2..].starts_with(&format!("{}}}}}", name))) else {
+                expanded.push('{');
+                rest = &rest[1..];
+                continue;
+            };
+            let variable = format!("AI_TERMINAL_{}", name.to_uppercase());
+            let value = env.iter().find(|(key, _)| *key == variable).map(|(_, value)| value.as_str());
+            match (windows, value) {
+                (false, value) => expanded.push_str(&quote(value.unwrap_or(""))),
+                (true, Some(_)) => expanded.push_str(&format!("\"!{}!\"", variable)),
+                (true, None) => expanded.push_str("\"\""),
+            }
+            rest = &rest[name.len() + 4..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+/// Quote a value as one shell word
+fn quote(value: &str) -> String {
+    shlex::try_quote(value).map_or_else(|_| value.to_string(), |quoted| quoted.into_owned())
+}
+
+/// Run a hook, failing if it exits unsuccessfully or runs past `timeout`
+pub async fn run(hook: &str, context: &HookContext, timeout: Duration) -> Result<()> {
+    let hook = context.expand(hook);
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        // Delayed expansion puts in the values of templates after parsing
+        command.args(["/V:ON", "/C"]);
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command
+        .arg(&hook)
+        .current_dir(&context.cwd)
+        .envs(context.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = command.spawn().with_context(|| format!("starting hook `{}`", hook))?;
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .with_context(|| format!("hook `{}` ran longer than {}s and was stopped", hook, timeout.as_secs()))??;
+    if !output.status.success() {
+        bail!("hook `{}` failed with {}: {}", hook, output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Run hooks one after another, logging those that fail
+pub async fn run_all(hooks: &[String], context: &HookContext, timeout: Duration) {
+    for hook in hooks {
+        if let Err(e) = run(hook, context, timeout).await {
+            tracing::warn!("{:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_quotes_values() {
+        let context = HookContext { command: "echo 'hi' > out".to_string(), cwd: "/tmp".to_string(), finished: None };
+        assert_eq!(context.expand_for("log {{command}} {{cwd}} {{exit_code}} {{other}}", false), "log \"echo 'hi' > out\" /tmp '' {{other}}");
+        let finished = HookContext { finished: Some((2, Duration::from_millis(1500))), ..context };
+        assert_eq!(finished.expand_for("{{exit_code}} {{duration_ms}}", false), "2 1500");
+        assert_eq!(finished.env().len(), 4);
+
+        // Templates in the command are not expanded in turn
+        let literal = HookContext { command: "echo {{cwd}}".to_string(), cwd: "/tmp/my dir".to_string(), finished: None };
+        assert_eq!(literal.expand_for("log {{command}} {{{cwd}}", false), "log 'echo {{cwd}}' {'/tmp/my dir'");
+        assert_eq!(literal.expand_for("log {{command}} {{exit_code}}", true), "log \"!AI_TERMINAL_COMMAND!\" \"\"");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hooks() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let context = HookContext {
+            command: "make test".to_string(),
+            cwd: dir.to_string_lossy().into_owned(),
+            finished: Some((1, Duration::from_millis(20))),
+        };
+        run("echo \"$AI_TERMINAL_EXIT_CODE\" {{command}} > log", &context, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("log")).unwrap(), "1 make test\n");
+
+        let error = run("echo oops >&2; exit 3", &context, Duration::from_secs(5)).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("oops"));
+        let error = run("sleep 5", &context, Duration::from_millis(100)).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("stopped"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Clickable hyperlinks for the AI Terminal UI
+//!
+//! ratatui strips control characters from text, so OSC 8 escape sequences
+//! cannot be part of a span. Instead, links are rendered as styled text and
+//! this module rewrites the matching buffer cells afterwards: every pair of
+//! cells becomes a single symbol wrapped in OSC 8, and the renderer skips the
+//! cell that follows an over-wide symbol. Terminals without OSC 8 support
+//! ignore the sequence and show the plain link text.
+
+use ratatui::{buffer::Buffer, layout::Rect, style::Style};
+
+/// A link found while rendering Markdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperlink {
+    /// The visible link text
+    pub text: String,
+    /// The link target
+    pub url: String,
+}
+
+/// Wrap text in an OSC 8 hyperlink escape sequence
+pub fn osc8(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Turn rendered link text inside `area` into OSC 8 hyperlinks
+///
+/// Link text is recognised by `link_style`; each run of link-styled cells
+/// is matched against the known links, which also covers links that were
+/// wrapped over several lines.
+pub fn apply(buf: &mut Buffer, area: Rect, links: &[Hyperlink], link_style: Style) {
+    if links.is_empty() {
+        return;
+    }
+    let area = area.intersection(buf.area);
+
+    for y in area.top()..area.bottom() {
+        let mut x = area.left();
+        while x < area.right() {
+            if !is_link_cell(buf, x, y, link_style) {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x < area.right() && is_link_cell(buf, x, y, link_style) {
+                x += 1;
+            }
+
+            let text: String = (start..x).map(|cx| buf[(cx, y)].symbol()).collect();
+            if let Some(link) = links.iter().find(|link| link.text.contains(text.as_str())) {
+                link_cells(buf, start, x, y, &link.url);
+            }
+        }
+    }
+}
+
+/// Check whether a cell holds a single-width piece of link text
+fn is_link_cell(buf: &Buffer, x: u16, y: u16, link_style: Style) -> bool {
+    let cell = &buf[(x, y)];
+    let symbol = cell.symbol();
+    cell.fg == link_style.fg.unwrap_or(cell.fg)
+        && cell.modifier.contains(link_style.add_modifier)
+        && symbol.chars().count() == 1
+        && !symbol.starts_with('\x1b')
+}
+
+/// Rewrite the cells `start..end` on row `y` as a hyperlink
+fn link_cells(buf: &mut Buffer, start: u16, end: u16, y: u16, url: &str) {
+    // An odd trailing cell is left plain; wrapping it would hide the next cell
+    let mut x = start;
+    while x + 1 < end {
+        let pair = format!("{}{}", buf[(x, y)].symbol(), buf[(x + 1, y)].symbol());
+        buf[(x, y)].set_symbol(&osc8(url, &pair));
+        x += 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Modifier};
+
+    fn link_style() -> Style {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)
+    }
+
+    #[test]
+    fn test_link_cells_are_wrapped() {
+        let area = Rect::new(0, 0, 12, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "see ", Style::default());
+        buf.set_string(4, 0, "docs", link_style());
+
+        let links = vec![Hyperlink { text: "docs".to_string(), url: "https://example.com".to_string() }];
+        apply(&mut buf, area, &links, link_style());
+
+        assert_eq!(buf[(0, 0)].symbol(), "s");
+        assert_eq!(buf[(4, 0)].symbol(), osc8("https://example.com", "do"));
+        assert_eq!(buf[(6, 0)].symbol(), osc8("https://example.com", "cs"));
+    }
+
+    #[test]
+    fn test_unknown_text_is_left_alone() {
+        let area = Rect::new(0, 0, 8, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "other", link_style());
+
+        let links = vec![Hyperlink { text: "docs".to_string(), url: "https://example.com".to_string() }];
+        apply(&mut buf, area, &links, link_style());
+
+        assert_eq!(buf[(0, 0)].symbol(), "o");
+    }
+}
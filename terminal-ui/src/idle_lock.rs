@@ -0,0 +1,191 @@
+//! Idle lock for the AI Terminal
+//!
+//! Sessions often hold secrets pasted into commands or printed by them. With
+//! the lock enabled, a session left without a key press or click for
+//! `idle_minutes` is covered by a privacy screen that draws nothing of the
+//! session, and `/lock` covers it straight away. The screen is lifted with
+//! the passphrase from the environment variable in `passphrase_env`, which is
+//! read once at startup and kept only as an Argon2 hash; commands run in the
+//! terminal do not inherit the variable. Without a passphrase the screen
+//! still hides the session, and Enter lifts it.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+
+/// Idle lock settings from the `[idle_lock]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LockConfig {
+    /// Lock the session after it has been idle for `idle_minutes`
+    pub enabled: bool,
+    /// Minutes without a key press or click before the session locks
+    pub idle_minutes: u64,
+    /// Environment variable holding the passphrase that unlocks the session
+    pub passphrase_env: Option<String>,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 10,
+            passphrase_env: Some("AI_TERMINAL_LOCK_PASSPHRASE".to_string()),
+        }
+    }
+}
+
+impl LockConfig {
+    /// Read the passphrase, if its variable is set
+    pub fn passphrase(&self) -> Option<String> {
+        self.passphrase_env.as_deref().and_then(|var| std::env::var(var).ok()).filter(|passphrase| !passphrase.is_empty())
+    }
+}
+
+/// Privacy screen covering the session while it is locked
+#[derive(Debug)]
+pub struct IdleLock {
+    config: LockConfig,
+    /// Argon2 hash of the passphrase, in PHC string format
+    passphrase_hash: Option<String>,
+    last_activity: Instant,
+    locked: bool,
+    /// Passphrase typed so far on the lock screen
+    entry: String,
+    /// Wrong passphrases entered since the session locked
+    failed_attempts: u32,
+}
+
+impl Default for IdleLock {
+    fn default() -> Self {
+        Self {
+            config: LockConfig::default(),
+            passphrase_hash: None,
+            last_activity: Instant::now(),
+            locked: false,
+            entry: String::new(),
+            failed_attempts: 0,
+        }
+    }
+}
+
+impl IdleLock {
+    /// Create a lock with its settings and the passphrase that lifts it
+    pub fn new(config: LockConfig, passphrase: Option<&str>) -> Result<Self> {
+        let passphrase_hash = passphrase
+            .map(|passphrase| {
+                let salt = SaltString::generate(&mut OsRng);
+                Argon2::default()
+                    .hash_password(passphrase.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| anyhow!("could not hash the lock passphrase: {}", e))
+            })
+            .transpose()?;
+        Ok(Self { config, passphrase_hash, ..Default::default() })
+    }
+
+    /// Whether the session is covered
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether a passphrase is needed to unlock
+    pub fn has_passphrase(&self) -> bool {
+        self.passphrase_hash.is_some()
+    }
+
+    /// Get the number of characters typed on the lock screen
+    pub fn entry_len(&self) -> usize {
+        crate::text::grapheme_count(&self.entry)
+    }
+
+    /// Get the number of wrong passphrases entered since the session locked
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// Record a key press or click, postponing the idle lock
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Lock the session if it has been idle for too long
+    pub fn check(&mut self) {
+        if self.config.enabled && self.last_activity.elapsed() >= Duration::from_secs(self.config.idle_minutes * 60) {
+            self.lock();
+        }
+    }
+
+    /// Cover the session now
+    pub fn lock(&mut self) {
+        self.locked = true;
+        self.entry.clear();
+        self.failed_attempts = 0;
+    }
+
+    /// Add a character to the passphrase being typed
+    pub fn push(&mut self, c: char) {
+        self.entry.push(c);
+    }
+
+    /// Remove the last character of the passphrase being typed
+    pub fn pop(&mut self) {
+        crate::text::pop_grapheme(&mut self.entry);
+    }
+
+    /// Clear the passphrase being typed
+    pub fn clear(&mut self) {
+        self.entry.clear();
+    }
+
+    /// Check the typed passphrase, lifting the screen if it matches
+    pub fn submit(&mut self) -> bool {
+        let entry = std::mem::take(&mut self.entry);
+        let unlocked = match &self.passphrase_hash {
+            Some(hash) => PasswordHash::new(hash)
+                .is_ok_and(|hash| Argon2::default().verify_password(entry.as_bytes(), &hash).is_ok()),
+            None => true,
+        };
+        if unlocked {
+            self.locked = false;
+            self.touch();
+        } else {
+            self.failed_attempts += 1;
+        }
+        unlocked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let config = LockConfig { enabled: true, idle_minutes: 0, passphrase_env: None };
+        let mut lock = IdleLock::new(config, Some("open sesame")).unwrap();
+        assert!(!lock.is_locked());
+        lock.check();
+        assert!(lock.is_locked());
+
+        "wrong".chars().for_each(|c| lock.push(c));
+        assert!(!lock.submit());
+        assert_eq!(lock.failed_attempts(), 1);
+
+        "open sesamee".chars().for_each(|c| lock.push(c));
+        lock.pop();
+        assert_eq!(lock.entry_len(), 11);
+        assert!(lock.submit());
+        assert!(!lock.is_locked());
+
+        // Disabled locks only cover the session on request
+        let mut manual = IdleLock::new(LockConfig { idle_minutes: 0, ..Default::default() }, None).unwrap();
+        manual.check();
+        assert!(!manual.is_locked());
+        manual.lock();
+        assert!(manual.submit());
+    }
+}
@@ -0,0 +1,824 @@
+//! Images in command output
+//!
+//! Commands that draw plots or previews write them with sixel, kitty or
+//! iTerm2 graphics, which the PTY reader takes out of the output, and
+//! `img <path>` shows a PNG or PPM file the same way. Every image is decoded
+//! and drawn under its block's output with half blocks, two pixels to a
+//! cell, which any terminal can show. When the terminal the AI Terminal runs
+//! in speaks a graphics protocol itself, detected from its environment or
+//! set with `[images] protocol`, images wholly on screen are drawn over
+//! their half blocks at full resolution.
+//!
+//! Decoding needs the `graphics` feature; builds without it keep no images
+//! and `img` says why.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::Engine as _;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use serde::{Deserialize, Serialize};
+use terminal_emulator::{GraphicsSequence, ImageProtocol};
+#[cfg(feature = "graphics")]
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+#[cfg(feature = "graphics")]
+use base64::engine::DecodePaddingMode;
+#[cfg(feature = "graphics")]
+use flate2::read::ZlibDecoder;
+#[cfg(feature = "graphics")]
+use std::io::Read;
+#[cfg(feature = "graphics")]
+use terminal_emulator::graphics::kitty_keys;
+
+/// Most pixels an image may have
+const MAX_PIXELS: usize = 4096 * 4096;
+
+/// Most rows an image takes in a block
+pub const MAX_ROWS: u16 = 20;
+
+/// Images decoded lately, kept so re-rendering a block does not decode them again
+const DECODED_CACHE: usize = 16;
+
+/// Bytes of base64 in each chunk of a kitty transfer
+const KITTY_CHUNK: usize = 4096;
+
+/// Base64 that does not insist on padding, as kitty chunks may leave it out
+#[cfg(feature = "graphics")]
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Pixel with no color
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+/// An image as decoded, or why it could not be
+type Decoded = Result<Arc<Image>, String>;
+
+/// Images decoded lately, by the hash of their sequence
+static DECODED: Mutex<Vec<(u64, Decoded)>> = Mutex::new(Vec::new());
+
+/// How the terminal running the AI Terminal draws images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSupport {
+    /// The kitty graphics protocol, also spoken by WezTerm and Ghostty
+    Kitty,
+    /// iTerm2 inline images, also shown by WezTerm and mintty
+    Iterm2,
+    /// DEC sixel graphics
+    Sixel,
+    /// No graphics; half blocks only
+    Blocks,
+}
+
+impl ImageSupport {
+    /// Detect what the terminal supports from the environment
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    /// Detect what the terminal supports from environment variables
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        // tmux and screen would need every sequence wrapped to pass it on
+        if var("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+            return ImageSupport::Blocks;
+        }
+        if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty" || matches!(program.as_str(), "WezTerm" | "ghostty") {
+            ImageSupport::Kitty
+        } else if matches!(program.as_str(), "iTerm.app" | "mintty") || var("LC_TERMINAL").as_deref() == Some("iTerm2") {
+            ImageSupport::Iterm2
+        } else if ["sixel", "foot", "mlterm", "contour"].iter().any(|name| term.contains(name)) {
+            ImageSupport::Sixel
+        } else {
+            ImageSupport::Blocks
+        }
+    }
+
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageSupport::Kitty => "kitty graphics",
+            ImageSupport::Iterm2 => "iTerm2 inline images",
+            ImageSupport::Sixel => "sixel",
+            ImageSupport::Blocks => "half blocks",
+        }
+    }
+}
+
+/// Settings from the `[images]` section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// How images are drawn, detected from the environment when unset
+    pub protocol: Option<ImageSupport>,
+}
+
+impl ImageConfig {
+    /// Get how images are drawn
+    pub fn support(&self) -> ImageSupport {
+        self.protocol.unwrap_or_else(ImageSupport::detect)
+    }
+}
+
+/// An image on screen, drawn over its half blocks where the terminal supports graphics
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    /// Cells the image covers
+    pub area: Rect,
+    /// The image
+    pub image: Arc<Image>,
+}
+
+impl PartialEq for ImagePlacement {
+    fn eq(&self, other: &Self) -> bool {
+        self.area == other.area && Arc::ptr_eq(&self.image, &other.image)
+    }
+}
+
+/// A decoded image, as rows of RGBA pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Create an image from its RGBA pixels, row by row
+    pub fn new(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Result<Self> {
+        ensure!(width > 0 && height > 0, "the image is empty");
+        let count = pixel_count(width, height).with_context(|| format!("the image is larger than {} pixels", MAX_PIXELS))?;
+        ensure!(pixels.len() == count, "the image has {} pixels, not {}x{}", pixels.len(), width, height);
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Decode a PNG or PPM file
+    #[cfg(feature = "graphics")]
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            decode_png(bytes)
+        } else if bytes.starts_with(b"P6") || bytes.starts_with(b"P5") {
+            decode_ppm(bytes)
+        } else if bytes.starts_with(b"\xff\xd8") {
+            bail!("JPEG images are not supported; convert it to PNG")
+        } else {
+            bail!("not a PNG or PPM image")
+        }
+    }
+
+    /// Decode the image a graphics sequence carries
+    #[cfg(feature = "graphics")]
+    pub fn from_sequence(sequence: &GraphicsSequence) -> Result<Self> {
+        match sequence.protocol {
+            ImageProtocol::Sixel => decode_sixel(&sequence.sequence),
+            ImageProtocol::Kitty => decode_kitty(&sequence.sequence),
+            ImageProtocol::Iterm2 => decode_iterm2(&sequence.sequence),
+        }
+    }
+
+    /// Stand-in for builds without the `graphics` feature, which decode no images
+    #[cfg(not(feature = "graphics"))]
+    pub fn decode(_bytes: &[u8]) -> Result<Self> {
+        bail!("this build has no image support; it needs the `graphics` feature")
+    }
+
+    /// Stand-in for builds without the `graphics` feature, which decode no images
+    #[cfg(not(feature = "graphics"))]
+    pub fn from_sequence(_sequence: &GraphicsSequence) -> Result<Self> {
+        bail!("this build has no image support; it needs the `graphics` feature")
+    }
+
+    /// Get the width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the cells the image takes, at most `max_columns` wide and `max_rows` high
+    ///
+    /// A half block is about as wide as it is high, so each pixel maps to one.
+    pub fn size_in_cells(&self, max_columns: u16, max_rows: u16) -> (u16, u16) {
+        let scale = (max_columns.max(1) as f64 / self.width as f64)
+            .min(max_rows.max(1) as f64 * 2.0 / self.height as f64)
+            .min(1.0);
+        let columns = ((self.width as f64 * scale).round() as u16).clamp(1, max_columns.max(1));
+        let rows = ((self.height as f64 * scale / 2.0).ceil() as u16).clamp(1, max_rows.max(1));
+        (columns, rows)
+    }
+
+    /// Scale the image to a size, averaging the pixels each new one covers
+    pub fn resize(&self, width: usize, height: usize) -> Image {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (top, bottom) = span(y, height, self.height);
+            for x in 0..width {
+                let (left, right) = span(x, width, self.width);
+                let mut sum = [0u64; 4];
+                let mut count = 0;
+                for row in top..bottom {
+                    for &[r, g, b, a] in &self.pixels[row * self.width + left..row * self.width + right] {
+                        sum[0] += r as u64 * a as u64;
+                        sum[1] += g as u64 * a as u64;
+                        sum[2] += b as u64 * a as u64;
+                        sum[3] += a as u64;
+                        count += 1;
+                    }
+                }
+                pixels.push(match sum[3] {
+                    0 => TRANSPARENT,
+                    alpha => [(sum[0] / alpha) as u8, (sum[1] / alpha) as u8, (sum[2] / alpha) as u8, (alpha / count) as u8],
+                });
+            }
+        }
+        Image { width, height, pixels }
+    }
+
+    /// Draw the image in `columns` by `rows` cells of half blocks
+    pub fn to_lines(&self, columns: u16, rows: u16) -> Vec<Line<'static>> {
+        let scaled = self.resize(columns as usize, rows as usize * 2);
+        let color = |[r, g, b, a]: [u8; 4]| (a >= 128).then_some(Color::Rgb(r, g, b));
+        (0..rows as usize)
+            .map(|row| {
+                let spans: Vec<Span> = (0..columns as usize)
+                    .map(|x| {
+                        let top = color(scaled.pixels[row * 2 * scaled.width + x]);
+                        let bottom = color(scaled.pixels[(row * 2 + 1) * scaled.width + x]);
+                        match (top, bottom) {
+                            (Some(top), Some(bottom)) => Span::styled("▀", Style::default().fg(top).bg(bottom)),
+                            (Some(top), None) => Span::styled("▀", Style::default().fg(top)),
+                            (None, Some(bottom)) => Span::styled("▄", Style::default().fg(bottom)),
+                            (None, None) => Span::raw(" "),
+                        }
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    /// Build the sequence drawing the image in `columns` by `rows` cells, at the cursor
+    ///
+    /// `cell` is the size of a cell in pixels, which sixel images are scaled
+    /// to; the other protocols scale the image themselves.
+    pub fn encode(&self, support: ImageSupport, columns: u16, rows: u16, cell: (u16, u16)) -> Option<String> {
+        let (width, height) = (columns as usize * cell.0 as usize, rows as usize * cell.1 as usize);
+        // Never send more pixels than the cells can show
+        let fitted = if self.width > width || self.height > height { self.resize(width, height) } else { self.clone() };
+        match support {
+            ImageSupport::Kitty => {
+                let rgba: Vec<u8> = fitted.pixels.iter().flatten().copied().collect();
+                let payload = base64::engine::general_purpose::STANDARD.encode(rgba);
+                let chunks: Vec<&str> = payload.as_bytes().chunks(KITTY_CHUNK).map(|chunk| std::str::from_utf8(chunk).unwrap_or_default()).collect();
+                let mut out = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = (i + 1 < chunks.len()) as u8;
+                    if i == 0 {
+                        // Do not move the cursor, and send no replies into the input
+                        out.push_str(&format!("\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={};{}\x1b\\", fitted.width, fitted.height, columns, rows, more, chunk));
+                    } else {
+                        out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                    }
+                }
+                Some(out)
+            }
+            ImageSupport::Iterm2 => {
+                let png = fitted.to_png();
+                Some(format!(
+                    "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0:{}\x07",
+                    png.len(),
+                    columns,
+                    rows,
+                    base64::engine::general_purpose::STANDARD.encode(png),
+                ))
+            }
+            ImageSupport::Sixel => Some(self.resize(width, height).to_sixel()),
+            ImageSupport::Blocks => None,
+        }
+    }
+
+    /// Encode the image as a PNG file
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.height * (self.width * 4 + 1));
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        // Writing to a vector cannot fail
+        let _ = encoder.write_all(&raw);
+        let data = encoder.finish().unwrap_or_default();
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // 8 bits per sample, RGBA, no interlacing
+        header.extend([8, 6, 0, 0, 0]);
+        for (kind, body) in [(b"IHDR", &header), (b"IDAT", &data), (b"IEND", &Vec::new())] {
+            png.extend((body.len() as u32).to_be_bytes());
+            png.extend(kind);
+            png.extend(body);
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(kind);
+            crc.update(body);
+            png.extend(crc.finalize().to_be_bytes());
+        }
+        png
+    }
+
+    /// Encode the image as sixels, with its colors reduced to a 6×6×6 cube
+    fn to_sixel(&self) -> String {
+        let level = |value: u8| (value as u16 * 5 + 127) / 255;
+        let indices: Vec<Option<u8>> = self.pixels
+            .iter()
+            .map(|&[r, g, b, a]| (a >= 128).then(|| (level(r) * 36 + level(g) * 6 + level(b)) as u8))
+            .collect();
+        let mut used = [false; 216];
+        for index in indices.iter().flatten() {
+            used[*index as usize] = true;
+        }
+
+        // Pixels left out stay transparent
+        let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", self.width, self.height);
+        for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+            let percent = |level: usize| level * 100 / 5;
+            out.push_str(&format!("#{};2;{};{};{}", index, percent(index / 36), percent(index / 6 % 6), percent(index % 6)));
+        }
+        for band in (0..self.height).step_by(6) {
+            let mut colors: Vec<u8> = (band..(band + 6).min(self.height))
+                .flat_map(|y| indices[y * self.width..(y + 1) * self.width].iter().flatten().copied())
+                .collect();
+            colors.sort_unstable();
+            colors.dedup();
+            for (n, color) in colors.iter().enumerate() {
+                if n > 0 {
+                    out.push('$');
+                }
+                out.push_str(&format!("#{}", color));
+                let sixels = (0..self.width).map(|x| {
+                    let bits = (0..6)
+                        .filter(|i| band + i < self.height && indices[(band + i) * self.width + x] == Some(*color))
+                        .fold(0u8, |bits, i| bits | 1 << i);
+                    (bits + 0x3f) as char
+                });
+                push_runs(&mut out, sixels);
+            }
+            out.push('-');
+        }
+        out.push_str("\x1b\\");
+        out
+    }
+}
+
+/// Get the image a graphics sequence carries, decoding it only if it was not decoded lately
+pub fn decode_cached(sequence: &GraphicsSequence) -> Decoded {
+    let mut hasher = DefaultHasher::new();
+    sequence.sequence.hash(&mut hasher);
+    let key = hasher.finish();
+    let mut decoded = DECODED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, image)) = decoded.iter().find(|(hash, _)| *hash == key) {
+        return image.clone();
+    }
+    let image = Image::from_sequence(sequence).map(Arc::new).map_err(|e| format!("{:#}", e));
+    if decoded.len() == DECODED_CACHE {
+        decoded.drain(..1);
+    }
+    decoded.push((key, image.clone()));
+    image
+}
+
+/// Wrap an image file in an iTerm2 sequence, the way `img <path>` adds it to a block
+pub fn file_sequence(name: &str, bytes: &[u8]) -> GraphicsSequence {
+    let engine = base64::engine::general_purpose::STANDARD;
+    GraphicsSequence {
+        protocol: ImageProtocol::Iterm2,
+        sequence: format!("\x1b]1337;File=name={};size={};inline=1:{}\x07", engine.encode(name), bytes.len(), engine.encode(bytes)),
+    }
+}
+
+/// Get the number of pixels of an image, unless it has more than [`MAX_PIXELS`]
+fn pixel_count(width: usize, height: usize) -> Option<usize> {
+    width.checked_mul(height).filter(|&count| count <= MAX_PIXELS)
+}
+
+/// Get the source pixels covered by pixel `index` of `count`, scaling to `count` from `total`
+fn span(index: usize, count: usize, total: usize) -> (usize, usize) {
+    let start = index * total / count;
+    let end = ((index + 1) * total / count).max(start + 1).min(total);
+    (start.min(total - 1), end)
+}
+
+/// Write sixel characters, repeating runs with `!`
+fn push_runs(out: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut run: Option<(char, usize)> = None;
+    let flush = |out: &mut String, (sixel, count): (char, usize)| {
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, sixel));
+        } else {
+            out.extend(std::iter::repeat_n(sixel, count));
+        }
+    };
+    for sixel in sixels {
+        run = match run {
+            Some((current, count)) if current == sixel => Some((current, count + 1)),
+            Some(previous) => {
+                flush(out, previous);
+                Some((sixel, 1))
+            }
+            None => Some((sixel, 1)),
+        };
+    }
+    if let Some(run) = run {
+        flush(out, run);
+    }
+}
+
+/// Decode a PNG file that is not interlaced
+#[cfg(feature = "graphics")]
+fn decode_png(bytes: &[u8]) -> Result<Image> {
+    let mut header = None;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut alphas: &[u8] = &[];
+    let mut data = Vec::new();
+    let mut at = 8;
+    while at + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[at..at + 4].try_into()?) as usize;
+        let kind = &bytes[at + 4..at + 8];
+        let body = bytes.get(at + 8..at + 8 + length).context("the PNG file is truncated")?;
+        match kind {
+            b"IHDR" => {
+                ensure!(body.len() == 13, "the PNG header is malformed");
+                let width = u32::from_be_bytes(body[0..4].try_into()?) as usize;
+                let height = u32::from_be_bytes(body[4..8].try_into()?) as usize;
+                header = Some((width, height, body[8], body[9], body[12]));
+            }
+            b"PLTE" => palette = body.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect(),
+            b"tRNS" => alphas = body,
+            b"IDAT" => data.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + length;
+    }
+    let (width, height, depth, color, interlace) = header.context("the PNG file has no header")?;
+    ensure!(interlace == 0, "interlaced PNG images are not supported");
+    ensure!(width > 0 && height > 0 && pixel_count(width, height).is_some(), "the PNG image is empty or too large");
+    let channels = match color {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => bail!("PNG color type {} is not valid", color),
+    };
+    ensure!(matches!(depth, 1 | 2 | 4 | 8 | 16), "PNG bit depth {} is not valid", depth);
+    let bits = channels * depth as usize;
+    let step = (bits / 8).max(1);
+    let stride = (width * bits).div_ceil(8);
+
+    let mut raw = Vec::new();
+    ZlibDecoder::new(data.as_slice()).take(((stride + 1) * height) as u64).read_to_end(&mut raw).context("the PNG data is corrupt")?;
+    ensure!(raw.len() == (stride + 1) * height, "the PNG data is truncated");
+
+    let mut previous = vec![0u8; stride];
+    let mut pixels = Vec::with_capacity(width * height);
+    for line in raw.chunks_exact_mut(stride + 1) {
+        let (filter, row) = line.split_first_mut().context("empty PNG row")?;
+        unfilter(*filter, row, &previous, step)?;
+        let sample = |x: usize, channel: usize| -> u8 {
+            let index = x * channels + channel;
+            match depth {
+                8 => row[index],
+                // Keep the high byte of 16-bit samples
+                16 => row[index * 2],
+                _ => {
+                    let bit = index * depth as usize;
+                    let value = row[bit / 8] >> (8 - depth as usize - bit % 8) & ((1u16 << depth) - 1) as u8;
+                    // Palette indices stay as they are, gray levels are spread over 0-255
+                    if color == 3 { value } else { (value as u16 * 255 / ((1u16 << depth) - 1)) as u8 }
+                }
+            }
+        };
+        for x in 0..width {
+            pixels.push(match color {
+                0 => {
+                    let gray = sample(x, 0);
+                    [gray, gray, gray, 255]
+                }
+                2 => [sample(x, 0), sample(x, 1), sample(x, 2), 255],
+                3 => {
+                    let index = sample(x, 0) as usize;
+                    let [r, g, b] = *palette.get(index).context("a PNG pixel is outside the palette")?;
+                    [r, g, b, alphas.get(index).copied().unwrap_or(255)]
+                }
+                4 => [sample(x, 0), sample(x, 0), sample(x, 0), sample(x, 1)],
+                _ => [sample(x, 0), sample(x, 1), sample(x, 2), sample(x, 3)],
+            });
+        }
+        previous.copy_from_slice(row);
+    }
+    Image::new(width, height, pixels)
+}
+
+/// Undo the filter of a PNG row, given the row above it
+#[cfg(feature = "graphics")]
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], step: usize) -> Result<()> {
+    for i in 0..row.len() {
+        let left = if i >= step { row[i - step] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= step { previous[i - step] } else { 0 };
+        row[i] = row[i].wrapping_add(match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => {
+                let estimate = left as i16 + up as i16 - up_left as i16;
+                let (a, b, c) = ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - up_left as i16).abs());
+                if a <= b && a <= c { left } else if b <= c { up } else { up_left }
+            }
+            _ => bail!("PNG filter {} is not valid", filter),
+        });
+    }
+    Ok(())
+}
+
+/// Decode a binary PPM (P6) or PGM (P5) file with 8-bit samples
+#[cfg(feature = "graphics")]
+fn decode_ppm(bytes: &[u8]) -> Result<Image> {
+    let mut at = 2;
+    let mut fields = [0usize; 3];
+    for field in &mut fields {
+        loop {
+            match bytes.get(at) {
+                Some(byte) if byte.is_ascii_whitespace() => at += 1,
+                Some(b'#') => at += bytes[at..].iter().position(|&byte| byte == b'\n').unwrap_or(bytes.len() - at),
+                _ => break,
+            }
+        }
+        let digits = bytes[at..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+        ensure!(digits > 0, "the PPM header is malformed");
+        *field = std::str::from_utf8(&bytes[at..at + digits])?.parse()?;
+        at += digits;
+    }
+    let [width, height, max] = fields;
+    ensure!(max > 0 && max < 256, "only PPM images with 8-bit samples are supported");
+    let count = pixel_count(width, height).filter(|&count| count > 0).context("the PPM image is empty or too large")?;
+    // A single whitespace byte ends the header
+    let data = bytes.get(at + 1..).context("the PPM file has no data")?;
+    let channels = if bytes[1] == b'6' { 3 } else { 1 };
+    ensure!(data.len() >= count * channels, "the PPM data is truncated");
+    let scale = |value: u8| (value as usize * 255 / max) as u8;
+    let pixels = data
+        .chunks_exact(channels)
+        .take(count)
+        .map(|sample| match sample {
+            [r, g, b] => [scale(*r), scale(*g), scale(*b), 255],
+            _ => [scale(sample[0]), scale(sample[0]), scale(sample[0]), 255],
+        })
+        .collect();
+    Image::new(width, height, pixels)
+}
+
+/// Decode a sixel sequence, leaving pixels it does not paint transparent
+#[cfg(feature = "graphics")]
+fn decode_sixel(sequence: &str) -> Result<Image> {
+    let body = sequence.strip_prefix("\x1bP").context("not a sixel sequence")?;
+    let start = body.find('q').context("not a sixel sequence")? + 1;
+    let body = body[start..].trim_end_matches("\x1b\\").as_bytes();
+
+    // The VT340's default colors
+    let mut palette: Vec<[u8; 4]> = [
+        [0, 0, 0], [51, 51, 204], [204, 33, 33], [51, 204, 51], [204, 51, 204], [51, 204, 204], [204, 204, 51], [119, 119, 119],
+        [68, 68, 68], [84, 84, 153], [153, 68, 68], [84, 153, 84], [153, 84, 153], [84, 153, 153], [153, 153, 84], [204, 204, 204],
+    ]
+    .iter()
+    .cycle()
+    .take(256)
+    .map(|&[r, g, b]| [r, g, b, 255])
+    .collect();
+    let mut rows: Vec<Vec<[u8; 4]>> = Vec::new();
+    let (mut x, mut y, mut color) = (0usize, 0usize, 0usize);
+    let mut at = 0;
+    let numbers = |at: &mut usize| -> Vec<usize> {
+        let mut values = vec![0usize];
+        while let Some(&byte) = body.get(*at) {
+            match byte {
+                b'0'..=b'9' => {
+                    if let Some(last) = values.last_mut() {
+                        *last = last.saturating_mul(10).saturating_add((byte - b'0') as usize);
+                    }
+                }
+                b';' => values.push(0),
+                _ => break,
+            }
+            *at += 1;
+        }
+        values
+    };
+    while let Some(&byte) = body.get(at) {
+        at += 1;
+        let (sixel, count) = match byte {
+            b'"' => {
+                numbers(&mut at);
+                continue;
+            }
+            b'#' => {
+                let values = numbers(&mut at);
+                color = values[0] % palette.len();
+                if let [_, model, a, b, c, ..] = values[..] {
+                    palette[color] = match model {
+                        1 => hls(a, b, c),
+                        _ => [(a.min(100) * 255 / 100) as u8, (b.min(100) * 255 / 100) as u8, (c.min(100) * 255 / 100) as u8, 255],
+                    };
+                }
+                continue;
+            }
+            b'!' => {
+                let count = numbers(&mut at)[0];
+                match body.get(at) {
+                    Some(&sixel @ 0x3f..=0x7e) => {
+                        at += 1;
+                        (sixel, count)
+                    }
+                    _ => continue,
+                }
+            }
+            b'$' => {
+                x = 0;
+                continue;
+            }
+            b'-' => {
+                x = 0;
+                y += 6;
+                continue;
+            }
+            0x3f..=0x7e => (byte, 1),
+            _ => continue,
+        };
+        let bits = sixel - 0x3f;
+        let end = (x + count).min(4096);
+        if y + 6 > 4096 {
+            break;
+        }
+        for i in (0..6).filter(|i| bits & 1 << i != 0) {
+            if rows.len() <= y + i {
+                rows.resize(y + i + 1, Vec::new());
+            }
+            let row = &mut rows[y + i];
+            if row.len() < end {
+                row.resize(end, TRANSPARENT);
+            }
+            row[x..end].fill(palette[color]);
+        }
+        x = end;
+    }
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let height = rows.len();
+    let pixels = rows
+        .into_iter()
+        .flat_map(|mut row| {
+            row.resize(width, TRANSPARENT);
+            row
+        })
+        .collect();
+    Image::new(width, height, pixels)
+}
+
+/// Convert a sixel HLS color, whose hue starts at blue, to RGBA
+#[cfg(feature = "graphics")]
+fn hls(hue: usize, lightness: usize, saturation: usize) -> [u8; 4] {
+    let hue = ((hue + 240) % 360) as f64 / 360.0;
+    let (lightness, saturation) = (lightness.min(100) as f64 / 100.0, saturation.min(100) as f64 / 100.0);
+    let q = if lightness < 0.5 { lightness * (1.0 + saturation) } else { lightness + saturation - lightness * saturation };
+    let p = 2.0 * lightness - q;
+    let channel = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        let value = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (value * 255.0).round() as u8
+    };
+    [channel(hue + 1.0 / 3.0), channel(hue), channel(hue - 1.0 / 3.0), 255]
+}
+
+/// Decode a kitty transfer, its chunks one after another
+#[cfg(feature = "graphics")]
+fn decode_kitty(sequence: &str) -> Result<Image> {
+    let keys = kitty_keys(sequence);
+    let key = |name: &str| keys.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    let payload: String = sequence
+        .split("\x1b\\")
+        .filter_map(|chunk| chunk.split_once(';').map(|(_, payload)| payload))
+        .collect();
+    let mut data = BASE64.decode(payload.trim()).context("the kitty image data is not base64")?;
+    // Files and shared memory are named by whatever wrote the output, which
+    // must not get the terminal to read them, so only direct data is shown
+    match key("t").unwrap_or("d") {
+        "d" => {}
+        "f" | "t" => bail!("kitty images sent as files are not shown"),
+        _ => bail!("kitty images in shared memory are not shown"),
+    }
+    if key("o") == Some("z") {
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(data.as_slice()).take(MAX_PIXELS as u64 * 4).read_to_end(&mut inflated).context("the kitty image data is corrupt")?;
+        data = inflated;
+    }
+    let size = |name: &str| key(name).and_then(|value| value.parse::<usize>().ok()).unwrap_or(0);
+    let channels = match key("f").unwrap_or("32") {
+        "100" => return Image::decode(&data),
+        "24" => 3,
+        "32" => 4,
+        format => bail!("kitty image format {} is not supported", format),
+    };
+    let (width, height) = (size("s"), size("v"));
+    let count = pixel_count(width, height).context("the kitty image is too large")?;
+    ensure!(data.len() >= count * channels, "the kitty image data is truncated");
+    let pixels = data.chunks_exact(channels).take(count).map(|p| [p[0], p[1], p[2], if channels == 4 { p[3] } else { 255 }]).collect();
+    Image::new(width, height, pixels)
+}
+
+/// Decode an iTerm2 inline file
+#[cfg(feature = "graphics")]
+fn decode_iterm2(sequence: &str) -> Result<Image> {
+    let body = sequence.strip_prefix("\x1b]1337;File=").context("not an iTerm2 sequence")?;
+    let (arguments, payload) = body.split_once(':').context("the iTerm2 image has no data")?;
+    ensure!(arguments.split(';').any(|argument| argument == "inline=1"), "iTerm2 file downloads are not shown");
+    let payload = payload.trim_end_matches('\x07').trim_end_matches("\x1b\\");
+    Image::decode(&BASE64.decode(payload.trim()).context("the iTerm2 image data is not base64")?)
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+
+    fn checkerboard() -> Image {
+        let pixels = (0..16).map(|i| if (i % 4 + i / 4) % 2 == 0 { [255, 0, 0, 255] } else { [0, 0, 255, 255] }).collect();
+        Image::new(4, 4, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_round_trips_through_every_protocol() {
+        let image = checkerboard();
+        let png = image.to_png();
+        assert_eq!(Image::decode(&png).unwrap(), image);
+        assert_eq!(Image::from_sequence(&file_sequence("plot.png", &png)).unwrap(), image);
+
+        let kitty = image.encode(ImageSupport::Kitty, 4, 2, (1, 2)).unwrap();
+        assert_eq!(Image::from_sequence(&GraphicsSequence { protocol: ImageProtocol::Kitty, sequence: kitty }).unwrap(), image);
+        let sixel = image.encode(ImageSupport::Sixel, 4, 2, (1, 2)).unwrap();
+        assert_eq!(Image::from_sequence(&GraphicsSequence { protocol: ImageProtocol::Sixel, sequence: sixel }).unwrap(), image);
+        assert!(image.encode(ImageSupport::Blocks, 4, 2, (1, 2)).is_none());
+        let file = GraphicsSequence { protocol: ImageProtocol::Kitty, sequence: format!("\x1b_Ga=T,t=f;{}\x1b\\", BASE64.encode("/etc/passwd")) };
+        assert!(Image::from_sequence(&file).unwrap_err().to_string().contains("as files"));
+
+        let ppm = [b"P6\n# plot\n2 1\n255\n".as_slice(), &[255, 0, 0, 0, 0, 255]].concat();
+        assert_eq!(Image::decode(&ppm).unwrap().pixels, [[255, 0, 0, 255], [0, 0, 255, 255]]);
+        assert!(Image::decode(b"\xff\xd8\xff").unwrap_err().to_string().contains("JPEG"));
+        assert!(Image::decode(b"P6 1 1 255").is_err());
+        assert!(Image::decode(format!("P6 {} {} 255\n", usize::MAX, 2).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_half_blocks_and_detection() {
+        let image = checkerboard();
+        assert_eq!(image.size_in_cells(80, 20), (4, 2));
+        assert_eq!(image.size_in_cells(2, 20), (2, 1));
+        let lines = image.to_lines(4, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans[0].content, "▀");
+        assert_eq!(lines[0].spans[0].style, Style::default().fg(Color::Rgb(255, 0, 0)).bg(Color::Rgb(0, 0, 255)));
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+        assert_eq!(ImageSupport::from_env(env(&[("TERM", "xterm-kitty")])), ImageSupport::Kitty);
+        assert_eq!(ImageSupport::from_env(env(&[("TERM_PROGRAM", "iTerm.app")])), ImageSupport::Iterm2);
+        assert_eq!(ImageSupport::from_env(env(&[("TERM", "foot")])), ImageSupport::Sixel);
+        assert_eq!(ImageSupport::from_env(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")])), ImageSupport::Blocks);
+        assert_eq!(ImageSupport::from_env(env(&[("TERM", "xterm-256color")])), ImageSupport::Blocks);
+    }
+}
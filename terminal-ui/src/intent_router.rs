@@ -0,0 +1,183 @@
+//! Intent router for the AI Terminal
+//!
+//! Many AI prompts are really requests for a system tool: "how much disk
+//! space is left" wants `df -h`, not a paragraph about it. With the router
+//! enabled, each prompt is embedded with the configured embedding model and
+//! compared by cosine similarity with the descriptions of the system tools.
+//! When the best match reaches `threshold`, its command runs as if typed;
+//! otherwise the prompt goes to the model as usual. The built-in tools are
+//! read-only, and `[router.tools]` adds more as description = command pairs.
+//! Tool descriptions are embedded once per session, on the first prompt.
+//! Builds without the `embeddings` feature route nothing.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "embeddings")]
+use anyhow::bail;
+use anyhow::Result;
+use ollama_client::ChatBackend;
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "embeddings"))]
+use crate::capabilities::Capability;
+#[cfg(feature = "embeddings")]
+use terminal_emulator::store::cosine_similarity;
+
+/// Built-in system tools: name, description and command
+const SYSTEM_TOOLS: &[(&str, &str, &str)] = &[
+    ("disk usage", "Show disk usage and the free space left on mounted filesystems", "df -h"),
+    ("memory", "Show how much memory and swap is used and free", "free -h"),
+    ("processes", "List the running processes using the most CPU", "ps aux --sort=-%cpu | head -n 15"),
+    ("ports", "List the listening network ports and the programs using them", "ss -tulpn"),
+    ("uptime", "Show how long the system has been up and its load average", "uptime"),
+    ("folder sizes", "Show the size of the folders in the current directory, largest first", "du -sh -- * | sort -rh | head -n 15"),
+    ("addresses", "Show the network interfaces and their IP addresses", "ip -brief address"),
+    ("system", "Show the operating system, kernel version and architecture", "uname -a"),
+];
+
+/// Router settings from the `[router]` section
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RouterConfig {
+    /// Route AI prompts that match a system tool to its command
+    pub enabled: bool,
+    /// Model computing the embeddings
+    pub model: String,
+    /// Lowest cosine similarity that routes a prompt; below it the prompt goes to the model
+    pub threshold: f32,
+    /// Extra tools, as description = command
+    pub tools: BTreeMap<String, String>,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: "nomic-embed-text".to_string(),
+            threshold: 0.75,
+            tools: BTreeMap::new(),
+        }
+    }
+}
+
+/// A system tool a prompt can be routed to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// Short name of the tool
+    pub name: String,
+    /// What the tool does, which prompts are matched against
+    pub description: String,
+    /// Shell command run for the tool
+    pub command: String,
+}
+
+/// Routes prompts to system tools by embedding similarity
+#[derive(Debug)]
+pub struct IntentRouter {
+    config: RouterConfig,
+    routes: Vec<Route>,
+    /// Embeddings of the route descriptions, once computed
+    #[cfg(feature = "embeddings")]
+    embeddings: Option<Vec<Vec<f32>>>,
+}
+
+impl IntentRouter {
+    /// Create a router with the built-in tools and those of the settings
+    pub fn new(config: RouterConfig) -> Self {
+        let builtin = SYSTEM_TOOLS.iter().map(|(name, description, command)| Route {
+            name: name.to_string(),
+            description: description.to_string(),
+            command: command.to_string(),
+        });
+        let custom = config.tools.iter().map(|(description, command)| Route {
+            name: description.clone(),
+            description: description.clone(),
+            command: command.clone(),
+        });
+        let routes = builtin.chain(custom).collect();
+        Self {
+            config,
+            routes,
+            #[cfg(feature = "embeddings")]
+            embeddings: None,
+        }
+    }
+
+    /// Whether prompts are routed
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Get the lowest similarity that routes a prompt
+    pub fn threshold(&self) -> f32 {
+        self.config.threshold
+    }
+
+    /// Get the tools prompts can be routed to
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Find the tool most similar to a prompt, with its similarity, embedding the descriptions first if needed
+    #[cfg(feature = "embeddings")]
+    pub async fn best_route(&mut self, backend: &dyn ChatBackend, prompt: &str) -> Result<Option<(&Route, f32)>> {
+        if self.embeddings.is_none() {
+            let descriptions: Vec<String> = self.routes.iter().map(|route| route.description.clone()).collect();
+            let embeddings = backend.embeddings(&self.config.model, &descriptions).await?;
+            if embeddings.len() != descriptions.len() {
+                bail!("the embedding model returned {} embeddings for {} tools", embeddings.len(), descriptions.len());
+            }
+            self.embeddings = Some(embeddings);
+        }
+        let Some(query) = backend.embeddings(&self.config.model, &[prompt.to_string()]).await?.pop() else {
+            bail!("the embedding model returned no embedding for the prompt");
+        };
+        let embeddings = self.embeddings.as_deref().unwrap_or_default();
+        Ok(best_match(&query, embeddings).map(|(index, score)| (&self.routes[index], score)))
+    }
+
+    /// Stand-in for builds without the `embeddings` feature, which route nothing
+    #[cfg(not(feature = "embeddings"))]
+    pub async fn best_route(&mut self, _backend: &dyn ChatBackend, _prompt: &str) -> Result<Option<(&Route, f32)>> {
+        Capability::Embeddings.require()?;
+        Ok(None)
+    }
+
+    /// Find the tool a prompt should run instead of going to the model, if any
+    pub async fn route(&mut self, backend: &dyn ChatBackend, prompt: &str) -> Result<Option<Route>> {
+        let threshold = self.config.threshold;
+        Ok(self.best_route(backend, prompt).await?
+            .filter(|(_, score)| *score >= threshold)
+            .map(|(route, _)| route.clone()))
+    }
+}
+
+/// Find the index and similarity of the embedding most similar to `query`
+#[cfg(feature = "embeddings")]
+fn best_match(query: &[f32], embeddings: &[Vec<f32>]) -> Option<(usize, f32)> {
+    embeddings
+        .iter()
+        .enumerate()
+        .filter(|(_, embedding)| embedding.len() == query.len())
+        .map(|(index, embedding)| (index, cosine_similarity(query, embedding)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(all(test, feature = "embeddings"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_and_best_match() {
+        let mut config = RouterConfig::default();
+        config.tools.insert("List running docker containers".to_string(), "docker ps".to_string());
+        let router = IntentRouter::new(config);
+        assert_eq!(router.routes().len(), SYSTEM_TOOLS.len() + 1);
+        assert_eq!(router.routes().last().unwrap().command, "docker ps");
+
+        let embeddings = vec![vec![1.0, 0.0], vec![0.6, 0.8], vec![0.0, 1.0, 0.0]];
+        let (index, score) = best_match(&[0.0, 1.0], &embeddings).unwrap();
+        assert_eq!(index, 1);
+        assert!((score - 0.8).abs() < 1e-6);
+        assert_eq!(best_match(&[1.0], &embeddings), None);
+    }
+}
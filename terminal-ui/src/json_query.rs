@@ -0,0 +1,274 @@
+//! JSON documents and queries for the AI Terminal
+//!
+//! Reads the output of a block as a JSON document, with newline-delimited
+//! JSON read as an array of its lines, and runs a subset of jq filters on it:
+//! paths such as `.items[0].name`, `.["a key"]` and `.[]`, the `keys` and
+//! `length` builtins, `select(.path == value)` with any comparison, and pipes
+//! between them.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+/// Comparisons understood by `select`, longest first so `>=` is not read as `>`
+const OPERATORS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+
+/// Read output as a JSON object or array, or as newline-delimited JSON
+pub fn parse_document(output: &str) -> Option<Value> {
+    let output = output.trim();
+    if let Ok(value) = serde_json::from_str::<Value>(output) {
+        return (value.is_object() || value.is_array()).then_some(value);
+    }
+    // Every non-blank line of NDJSON is a document of its own
+    let lines: Vec<&str> = output.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if lines.len() < 2 {
+        return None;
+    }
+    lines
+        .into_iter()
+        .map(|line| serde_json::from_str(line).ok())
+        .collect::<Option<Vec<Value>>>()
+        .map(Value::Array)
+}
+
+/// Run a filter, giving its only result, or an array of its results when there are several
+pub fn run(value: &Value, filter: &str) -> Result<Value> {
+    let mut values = vec![value.clone()];
+    for stage in split_outside(filter, '|') {
+        let stage = stage.trim();
+        values = values
+            .iter()
+            .map(|value| apply(value, stage))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    Ok(if values.len() == 1 { values.remove(0) } else { Value::Array(values) })
+}
+
+/// Apply one stage of a filter to a value
+fn apply(value: &Value, stage: &str) -> Result<Vec<Value>> {
+    match stage {
+        "" | "." => Ok(vec![value.clone()]),
+        "keys" => match value {
+            Value::Object(object) => {
+                let mut keys: Vec<&String> = object.keys().collect();
+                keys.sort();
+                Ok(vec![Value::Array(keys.into_iter().map(|key| Value::String(key.clone())).collect())])
+            }
+            Value::Array(items) => Ok(vec![Value::Array((0..items.len()).map(Value::from).collect())]),
+            other => bail!("{} has no keys", type_name(other)),
+        },
+        "length" => Ok(vec![match value {
+            Value::Object(object) => Value::from(object.len()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::String(text) => Value::from(text.chars().count()),
+            Value::Null => Value::from(0),
+            other => bail!("{} has no length", type_name(other)),
+        }]),
+        _ => {
+            if let Some(condition) = stage.strip_prefix("select(").and_then(|rest| rest.strip_suffix(')')) {
+                return Ok(if select(value, condition)? { vec![value.clone()] } else { Vec::new() });
+            }
+            if stage.starts_with('.') {
+                return lookup(value, &parse_path(stage)?);
+            }
+            bail!("unknown filter '{}'; use paths such as .items[].name, keys, length, select(...) and |", stage)
+        }
+    }
+}
+
+/// Check a `select` condition, a path alone or compared with a JSON value
+fn select(value: &Value, condition: &str) -> Result<bool> {
+    // The first operator wins; at the same place the longer one does, as it is listed first
+    let operator = OPERATORS
+        .iter()
+        .filter_map(|operator| find_outside(condition, operator).map(|at| (at, *operator)))
+        .min_by_key(|(at, _)| *at);
+    let Some((at, operator)) = operator else {
+        let found = lookup(value, &parse_path(condition.trim())?)?;
+        return Ok(found.iter().any(truthy));
+    };
+    let path = parse_path(condition[..at].trim())?;
+    let literal = condition[at + operator.len()..].trim();
+    let expected: Value = serde_json::from_str(literal).map_err(|_| anyhow!("'{}' is not a JSON value", literal))?;
+    Ok(lookup(value, &path)?.iter().any(|found| {
+        let order = compare(found, &expected);
+        match operator {
+            "==" => order == Some(Ordering::Equal),
+            "!=" => order != Some(Ordering::Equal),
+            ">=" => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+            "<=" => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+            ">" => order == Some(Ordering::Greater),
+            _ => order == Some(Ordering::Less),
+        }
+    }))
+}
+
+/// Compare numbers as numbers and strings as strings; other values are only equal or not
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// Check whether jq treats a value as true
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// Step of a path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+/// Parse a path such as `.items[0].name`, `.["a key"]` or `.[]`
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let Some(mut rest) = path.strip_prefix('.') else {
+        bail!("'{}' is not a path; paths start with '.'", path);
+    };
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let end = find_outside(inner, "]").ok_or_else(|| anyhow!("unclosed '[' in '{}'", path))?;
+            let index = inner[..end].trim();
+            segments.push(if index.is_empty() {
+                Segment::Iterate
+            } else if let Ok(index) = index.parse() {
+                Segment::Index(index)
+            } else {
+                match serde_json::from_str(index) {
+                    Ok(Value::String(key)) => Segment::Key(key),
+                    _ => bail!("'[{}]' is neither an index nor a quoted key", index),
+                }
+            });
+            rest = &inner[end + 1..];
+        } else {
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '$') {
+                bail!("'{}' is not a key; quote it, as in .[\"{}\"]", key, key);
+            }
+            segments.push(Segment::Key(key.to_string()));
+            rest = &rest[end..];
+        }
+    }
+    Ok(segments)
+}
+
+/// Follow a path, giving every value it reaches
+fn lookup(value: &Value, path: &[Segment]) -> Result<Vec<Value>> {
+    let Some((segment, rest)) = path.split_first() else {
+        return Ok(vec![value.clone()]);
+    };
+    let next: Vec<&Value> = match (segment, value) {
+        (_, Value::Null) if *segment != Segment::Iterate => vec![&Value::Null],
+        (Segment::Key(key), Value::Object(object)) => vec![object.get(key).unwrap_or(&Value::Null)],
+        (Segment::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 { items.len() as i64 + index } else { *index };
+            vec![usize::try_from(index).ok().and_then(|index| items.get(index)).unwrap_or(&Value::Null)]
+        }
+        (Segment::Iterate, Value::Array(items)) => items.iter().collect(),
+        (Segment::Iterate, Value::Object(object)) => object.values().collect(),
+        (Segment::Key(key), other) => bail!("cannot get \"{}\" of {}", key, type_name(other)),
+        (Segment::Index(index), other) => bail!("cannot get [{}] of {}", index, type_name(other)),
+        (Segment::Iterate, other) => bail!("cannot iterate over {}", type_name(other)),
+    };
+    let mut found = Vec::new();
+    for value in next {
+        found.extend(lookup(value, rest)?);
+    }
+    Ok(found)
+}
+
+/// Get the name jq gives the type of a value
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Find `pattern` outside of quotes, brackets and parentheses
+fn find_outside(text: &str, pattern: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (at, c) in text.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        if depth == 0 && text[at..].starts_with(pattern) {
+            return Some(at);
+        }
+        match c {
+            '"' => quoted = true,
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split text on a character outside of quotes, brackets and parentheses
+fn split_outside(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(at) = find_outside(rest, separator.encode_utf8(&mut [0; 4])) {
+        parts.push(&rest[..at]);
+        rest = &rest[at + separator.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_documents_and_ndjson_are_read() {
+        assert_eq!(parse_document("{\"a\": [1, 2]}\n"), Some(json!({"a": [1, 2]})));
+        assert_eq!(parse_document("{\"level\":\"info\"}\n\n{\"level\":\"warn\"}\n"), Some(json!([{"level": "info"}, {"level": "warn"}])));
+        assert_eq!(parse_document("42"), None);
+        assert_eq!(parse_document("{\"a\": 1}\nnot json\n"), None);
+    }
+
+    #[test]
+    fn test_filters_follow_jq() {
+        let pods = json!({"items": [
+            {"name": "web", "restarts": 3, "labels": {"app.kubernetes.io/name": "nginx"}},
+            {"name": "db", "restarts": 0, "labels": {}},
+        ]});
+        assert_eq!(run(&pods, ".items[].name").unwrap(), json!(["web", "db"]));
+        assert_eq!(run(&pods, ".items[-1].name").unwrap(), json!("db"));
+        assert_eq!(run(&pods, ".items[0].labels[\"app.kubernetes.io/name\"]").unwrap(), json!("nginx"));
+        assert_eq!(run(&pods, ".items[] | select(.restarts >= 1) | .name").unwrap(), json!("web"));
+        assert_eq!(run(&pods, ".items[] | select(.name != \"web\") | keys").unwrap(), json!(["labels", "name", "restarts"]));
+        assert_eq!(run(&pods, ".items | length").unwrap(), json!(2));
+        assert_eq!(run(&pods, ".missing.deeper").unwrap(), json!(null));
+        assert!(run(&pods, ".items.name").is_err());
+        assert!(run(&pods, "map(.name)").is_err());
+    }
+}
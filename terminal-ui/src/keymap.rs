@@ -0,0 +1,264 @@
+//! Scrollback key bindings for the AI Terminal
+//!
+//! Scrolling moves through the output by structure as well as by lines:
+//! half pages, whole command blocks, the top and bottom of the current block
+//! and the most recent failed block. Every [`Motion`] has default keys, which
+//! the `[keys]` section of the configuration can replace, for example
+//! `previous_block = ["alt+up", "ctrl+p"]`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// A movement through the scrollback of the focused pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Motion {
+    /// One line towards older output
+    LineUp,
+    /// One line towards newer output
+    LineDown,
+    /// Half a page towards older output
+    HalfPageUp,
+    /// Half a page towards newer output
+    HalfPageDown,
+    /// A page towards older output
+    PageUp,
+    /// A page towards newer output
+    PageDown,
+    /// The oldest output
+    Top,
+    /// The newest output
+    Bottom,
+    /// The start of the previous block, or of the current one if it starts above
+    PreviousBlock,
+    /// The start of the next block
+    NextBlock,
+    /// The start of the most recent block that failed
+    LastError,
+    /// The first line of the current block
+    BlockStart,
+    /// The last line of the current block
+    BlockEnd,
+}
+
+impl Motion {
+    /// Every motion, in the order shown in help
+    pub const ALL: [Motion; 13] = [
+        Motion::LineUp,
+        Motion::LineDown,
+        Motion::HalfPageUp,
+        Motion::HalfPageDown,
+        Motion::PageUp,
+        Motion::PageDown,
+        Motion::Top,
+        Motion::Bottom,
+        Motion::PreviousBlock,
+        Motion::NextBlock,
+        Motion::LastError,
+        Motion::BlockStart,
+        Motion::BlockEnd,
+    ];
+
+    /// Keys bound to the motion unless the configuration says otherwise
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Motion::LineUp => &["ctrl+up"],
+            Motion::LineDown => &["ctrl+down"],
+            Motion::HalfPageUp => &["ctrl+u"],
+            Motion::HalfPageDown => &["ctrl+d"],
+            Motion::PageUp => &["pageup"],
+            Motion::PageDown => &["pagedown"],
+            Motion::Top => &["home"],
+            Motion::Bottom => &["end"],
+            Motion::PreviousBlock => &["alt+up"],
+            Motion::NextBlock => &["alt+down"],
+            Motion::LastError => &["alt+e"],
+            Motion::BlockStart => &["alt+home"],
+            Motion::BlockEnd => &["alt+end"],
+        }
+    }
+
+    /// Short description for help
+    pub fn description(self) -> &'static str {
+        match self {
+            Motion::LineUp => "Scroll up one line",
+            Motion::LineDown => "Scroll down one line",
+            Motion::HalfPageUp => "Scroll up half a page",
+            Motion::HalfPageDown => "Scroll down half a page",
+            Motion::PageUp => "Scroll up a page",
+            Motion::PageDown => "Scroll down a page",
+            Motion::Top => "Jump to the oldest output",
+            Motion::Bottom => "Jump to the newest output and follow it",
+            Motion::PreviousBlock => "Jump to the previous command block",
+            Motion::NextBlock => "Jump to the next command block",
+            Motion::LastError => "Jump to the last failed block",
+            Motion::BlockStart => "Jump to the top of the current block",
+            Motion::BlockEnd => "Jump to the bottom of the current block",
+        }
+    }
+}
+
+/// Key binding overrides from the `[keys]` section, by motion
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct KeymapConfig {
+    /// Keys for each overridden motion; an empty list unbinds it
+    pub bindings: BTreeMap<Motion, Vec<String>>,
+}
+
+/// A key with its modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Parse a key such as `ctrl+u`, `alt+home` or `shift+pageup`
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        // A trailing '+' is the plus key itself, as in "ctrl++"
+        if text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let key = parts.pop().filter(|key| !key.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("'{}' names no key", text))?;
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => anyhow::bail!("unknown modifier '{}' in '{}'", other, text),
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+                        _ => anyhow::bail!("unknown key '{}' in '{}'", key, text),
+                    }
+                }
+            },
+        };
+
+        // Plain characters are typed into the input line
+        if let KeyCode::Char(_) = code
+            && !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            anyhow::bail!("'{}' would capture typing; add ctrl+ or alt+", text);
+        }
+        Ok(Self { code, modifiers })
+    }
+
+    /// The chord of a key event, ignoring shift on characters as terminals differ there
+    pub(crate) fn from_event(key: &KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) => Self {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers: key.modifiers - KeyModifiers::SHIFT,
+            },
+            code => Self { code, modifiers: key.modifiers },
+        }
+    }
+}
+
+/// Keys bound to scrollback motions
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Motion of each bound key
+    motions: HashMap<KeyChord, Motion>,
+    /// Keys of each motion, as written, for help
+    keys: BTreeMap<Motion, Vec<String>>,
+}
+
+impl Keymap {
+    /// Build the keymap from the defaults and the configured overrides
+    pub fn from_config(config: &KeymapConfig) -> Result<Self> {
+        let mut motions = HashMap::new();
+        let mut keys = BTreeMap::new();
+        for motion in Motion::ALL {
+            let bound: Vec<String> = match config.bindings.get(&motion) {
+                Some(bound) => bound.clone(),
+                None => motion.default_keys().iter().map(|key| key.to_string()).collect(),
+            };
+            for key in &bound {
+                let chord = KeyChord::parse(key).with_context(|| format!("invalid key for {:?}", motion))?;
+                if let Some(other) = motions.insert(chord, motion)
+                    && other != motion
+                {
+                    anyhow::bail!("'{}' is bound to both {:?} and {:?}", key, other, motion);
+                }
+            }
+            keys.insert(motion, bound);
+        }
+        Ok(Self { motions, keys })
+    }
+
+    /// Get the motion bound to a key event
+    pub fn motion(&self, key: &KeyEvent) -> Option<Motion> {
+        self.motions.get(&KeyChord::from_event(key)).copied()
+    }
+
+    /// Get the keys bound to a motion, for display
+    pub fn keys(&self, motion: Motion) -> String {
+        match self.keys.get(&motion) {
+            Some(keys) if !keys.is_empty() => keys.join(", "),
+            _ => "unbound".to_string(),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&KeymapConfig::default()).expect("default key bindings are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        let keymap = Keymap::default();
+        let ctrl_u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.motion(&ctrl_u), Some(Motion::HalfPageUp));
+        assert_eq!(keymap.motion(&KeyEvent::new(KeyCode::Up, KeyModifiers::ALT)), Some(Motion::PreviousBlock));
+        assert_eq!(keymap.motion(&KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)), None);
+
+        let config: KeymapConfig = toml::from_str("previous_block = [\"ctrl+p\", \"F3\"]\nhalf_page_up = []").unwrap();
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.motion(&KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE)), Some(Motion::PreviousBlock));
+        assert_eq!(keymap.motion(&ctrl_u), None);
+        assert_eq!(keymap.keys(Motion::HalfPageUp), "unbound");
+        assert_eq!(keymap.keys(Motion::PreviousBlock), "ctrl+p, F3");
+    }
+
+    #[test]
+    fn test_invalid_bindings() {
+        for bad in ["x", "hyper+x", "ctrl+nokey", "ctrl+"] {
+            let config = KeymapConfig { bindings: BTreeMap::from([(Motion::Top, vec![bad.to_string()])]) };
+            assert!(Keymap::from_config(&config).is_err(), "{} was accepted", bad);
+        }
+        // Two motions on one key
+        let config = KeymapConfig { bindings: BTreeMap::from([(Motion::Top, vec!["ctrl+d".to_string()])]) };
+        assert!(Keymap::from_config(&config).is_err());
+    }
+}
@@ -4,4 +4,8 @@
 
 pub mod manager;
 pub mod pane;
-pub mod tab;
\ No newline at end of file
+pub mod tab;
+
+pub use manager::LayoutManager;
+pub use pane::{Pane, PaneManager, SplitOrientation};
+pub use tab::{Tab, TabManager, TabStyle};
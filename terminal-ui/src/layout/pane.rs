@@ -1,2 +1,1395 @@
-//! TODO: FIX MODULE IMPORT ISSUE
-//! Pane management for the AI Terminal UI\n//!\n//! This module provides functionality for managing multiple panes within the terminal,\n//! including splitting, resizing, and navigation.\n\nuse ratatui::{\n    layout::{Constraint, Direction, Layout, Rect},\n    style::{Style, Color},\n    widgets::{Block, Borders, Paragraph},\n    Frame,\n};\nuse terminal_emulator::CommandBlock;\n\n/// Represents the orientation of a pane split\n#[derive(Debug, Clone, Copy, PartialEq)]\npub enum SplitOrientation {\n    Horizontal,\n    Vertical,\n}\n\n/// Represents a pane in the terminal UI\n#[derive(Debug)]\npub struct Pane {\n    /// Unique identifier for the pane\n    pub id: usize,\n    /// Area occupied by this pane\n    pub area: Rect,\n    /// Command blocks displayed in this pane\n    pub command_blocks: Vec<CommandBlock>,\n    /// Scroll offset for this pane\n    pub scroll_offset: u16,\n    /// Whether this pane is currently focused\n    pub is_focused: bool,\n}\n\nimpl Pane {\n    /// Create a new pane\n    pub fn new(id: usize, area: Rect) -> Self {\n        Self {\n            id,\n            area,\n            command_blocks: Vec::new(),\n            scroll_offset: 0,\n            is_focused: false,\n        }\n    }\n\n    /// Add a command block to this pane\n    pub fn add_command_block(&mut self, block: CommandBlock) {\n        self.command_blocks.push(block);\n    }\n\n    /// Render the pane\n    pub fn render(&self, f: &mut Frame, style: &PaneStyle) {\n        let block = Block::default()\n            .borders(Borders::ALL)\n            .border_style(if self.is_focused {\n                style.focused_border\n            } else {\n                style.border\n            });\n\n        let inner_area = block.inner(self.area);\n        \n        // Render command blocks in this pane\n        let mut messages_text = Vec::new();\n        for block in &self.command_blocks {\n            messages_text.push(format!(\"$ {}\", block.command));\n            messages_text.push(block.output.clone());\n            messages_text.push(String::new()); // Empty line between blocks\n        }\n\n        let messages_paragraph = Paragraph::new(messages_text.join(\"\\n\"))\n            .block(block)\n            .style(style.background)\n            .scroll((self.scroll_offset, 0));\n\n        f.render_widget(messages_paragraph, self.area);\n    }\n}\n\n/// Styling for panes\n#[derive(Debug, Clone)]\npub struct PaneStyle {\n    pub background: Style,\n    pub border: Style,\n    pub focused_border: Style,\n}\n\nimpl Default for PaneStyle {\n    fn default() -> Self {\n        Self {\n            background: Style::default().bg(Color::Black).fg(Color::White),\n            border: Style::default().fg(Color::DarkGray),\n            focused_border: Style::default().fg(Color::Blue),\n        }\n    }\n}\n\n/// Manages multiple panes in the terminal UI\n#[derive(Debug)]\npub struct PaneManager {\n    /// List of panes\n    panes: Vec<Pane>,\n    /// ID of the currently focused pane\n    focused_pane_id: Option<usize>,\n    /// Next ID to assign to a new pane\n    next_id: usize,\n    /// Style for rendering panes\n    style: PaneStyle,\n}\n\nimpl PaneManager {\n    /// Create a new pane manager\n    pub fn new(area: Rect) -> Self {\n        let mut panes = Vec::new();\n        let initial_pane = Pane::new(0, area);\n        panes.push(initial_pane);\n        \n        Self {\n            panes,\n            focused_pane_id: Some(0),\n            next_id: 1,\n            style: PaneStyle::default(),\n        }\n    }\n\n    /// Get the currently focused pane\n    pub fn focused_pane(&self) -> Option<&Pane> {\n        self.focused_pane_id.and_then(|id| {\n            self.panes.iter().find(|pane| pane.id == id)\n        })\n    }\n\n    /// Get the currently focused pane mutably\n    pub fn focused_pane_mut(&mut self) -> Option<&mut Pane> {\n        self.focused_pane_id.and_then(|id| {\n            self.panes.iter_mut().find(|pane| pane.id == id)\n        })\n    }\n\n    /// Split the focused pane\n    pub fn split_focused_pane(&mut self, orientation: SplitOrientation) -> Result<(), &'static str> {\n        if let Some(focused_id) = self.focused_pane_id {\n            // Find the focused pane\n            let pane_index = self.panes.iter().position(|p| p.id == focused_id)\n                .ok_or(\"Focused pane not found\")?;\n            \n            // Get the area of the pane to split\n            let pane_area = self.panes[pane_index].area;\n            \n            // Create two new areas by splitting the current pane's area\n            let new_areas = match orientation {\n                SplitOrientation::Horizontal => {\n                    let chunks = Layout::default()\n                        .direction(Direction::Horizontal)\n                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])\n                        .split(pane_area);\n                    [chunks[0], chunks[1]]\n                },\n                SplitOrientation::Vertical => {\n                    let chunks = Layout::default()\n                        .direction(Direction::Vertical)\n                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])\n                        .split(pane_area);\n                    [chunks[0], chunks[1]]\n                },\n            };\n            \n            // Update the original pane with the first area\n            self.panes[pane_index].area = new_areas[0];\n            \n            // Create a new pane with the second area\n            let new_pane_id = self.next_id;\n            self.next_id += 1;\n            let mut new_pane = Pane::new(new_pane_id, new_areas[1]);\n            \n            // Copy command blocks from the original pane to the new pane\n            new_pane.command_blocks = self.panes[pane_index].command_blocks.clone();\n            \n            // Add the new pane to the list\n            self.panes.push(new_pane);\n            \n            // Focus the new pane\n            self.focused_pane_id = Some(new_pane_id);\n            \n            Ok(())\n        } else {\n            Err(\"No focused pane to split\")\n        }\n    }\n\n    /// Close the focused pane\n    pub fn close_focused_pane(&mut self) -> Result<(), &'static str> {\n        if self.panes.len() <= 1 {\n            return Err(\"Cannot close the last pane\");\n        }\n        \n        if let Some(focused_id) = self.focused_pane_id {\n            // Find the focused pane\n            let pane_index = self.panes.iter().position(|p| p.id == focused_id)\n                .ok_or(\"Focused pane not found\")?;\n            \n            // Remove the pane\n            self.panes.remove(pane_index);\n            \n            // Focus the previous pane (or the first pane if we removed the first one)\n            let new_focused_id = if pane_index > 0 {\n                self.panes[pane_index - 1].id\n            } else {\n                self.panes[0].id\n            };\n            \n            self.focused_pane_id = Some(new_focused_id);\n            \n            Ok(())\n        } else {\n            Err(\"No focused pane to close\")\n        }\n    }\n\n    /// Focus the next pane\n    pub fn focus_next_pane(&mut self) {\n        if self.panes.is_empty() {\n            return;\n        }\n        \n        // Clear focus from all panes\n        for pane in &mut self.panes {\n            pane.is_focused = false;\n        }\n        \n        // Set focus to the next pane\n        let current_index = self.focused_pane_id\n            .and_then(|id| self.panes.iter().position(|p| p.id == id))\n            .unwrap_or(0);\n        \n        let next_index = (current_index + 1) % self.panes.len();\n        self.panes[next_index].is_focused = true;\n        self.focused_pane_id = Some(self.panes[next_index].id);\n    }\n\n    /// Focus the previous pane\n    pub fn focus_prev_pane(&mut self) {\n        if self.panes.is_empty() {\n            return;\n        }\n        \n        // Clear focus from all panes\n        for pane in &mut self.panes {\n            pane.is_focused = false;\n        }\n        \n        // Set focus to the previous pane\n        let current_index = self.focused_pane_id\n            .and_then(|id| self.panes.iter().position(|p| p.id == id))\n            .unwrap_or(0);\n        \n        let prev_index = if current_index > 0 {\n            current_index - 1\n        } else {\n            self.panes.len() - 1\n        };\n        \n        self.panes[prev_index].is_focused = true;\n        self.focused_pane_id = Some(self.panes[prev_index].id);\n    }\n\n    /// Resize panes based on the terminal size\n    pub fn resize(&mut self, area: Rect) {\n        // For now, we'll just update the first pane to fill the entire area\n        // In a more advanced implementation, we would maintain the relative sizes of panes\n        if let Some(pane) = self.panes.get_mut(0) {\n            pane.area = area;\n        }\n    }\n\n    /// Render all panes\n    pub fn render(&self, f: &mut Frame) {\n        for pane in &self.panes {\n            pane.render(f, &self.style);\n        }\n    }\n}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn test_pane_creation() {\n        let rect = Rect::new(0, 0, 80, 24);\n        let pane = Pane::new(1, rect);\n        \n        assert_eq!(pane.id, 1);\n        assert_eq!(pane.area, rect);\n        assert_eq!(pane.command_blocks.len(), 0);\n        assert_eq!(pane.scroll_offset, 0);\n        assert_eq!(pane.is_focused, false);\n    }\n\n    #[test]\n    fn test_pane_manager_creation() {\n        let rect = Rect::new(0, 0, 80, 24);\n        let pane_manager = PaneManager::new(rect);\n        \n        assert_eq!(pane_manager.panes.len(), 1);\n        assert_eq!(pane_manager.focused_pane_id, Some(0));\n        assert_eq!(pane_manager.next_id, 1);\n    }\n\n    #[test]\n    fn test_split_focused_pane() {\n        let rect = Rect::new(0, 0, 80, 24);\n        let mut pane_manager = PaneManager::new(rect);\n        \n        // Split the focused pane horizontally\n        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());\n        assert_eq!(pane_manager.panes.len(), 2);\n        assert_eq!(pane_manager.focused_pane_id, Some(1));\n        \n        // Split the focused pane vertically\n        assert!(pane_manager.split_focused_pane(SplitOrientation::Vertical).is_ok());\n        assert_eq!(pane_manager.panes.len(), 3);\n        assert_eq!(pane_manager.focused_pane_id, Some(2));\n    }\n\n    #[test]\n    fn test_close_focused_pane() {\n        let rect = Rect::new(0, 0, 80, 24);\n        let mut pane_manager = PaneManager::new(rect);\n        \n        // Split to create a second pane\n        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());\n        assert_eq!(pane_manager.panes.len(), 2);\n        \n        // Close the focused pane\n        assert!(pane_manager.close_focused_pane().is_ok());\n        assert_eq!(pane_manager.panes.len(), 1);\n        assert_eq!(pane_manager.focused_pane_id, Some(0));\n    }\n\n    #[test]\n    fn test_focus_navigation() {\n        let rect = Rect::new(0, 0, 80, 24);\n        let mut pane_manager = PaneManager::new(rect);\n        \n        // Split to create a second pane\n        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());\n        assert_eq!(pane_manager.focused_pane_id, Some(1));\n        \n        // Focus the next pane (should wrap around to the first)\n        pane_manager.focus_next_pane();\n        assert_eq!(pane_manager.focused_pane_id, Some(0));\n        \n        // Focus the previous pane (should wrap around to the second)\n        pane_manager.focus_prev_pane();\n        assert_eq!(pane_manager.focused_pane_id, Some(1));\n    }\n}
\ No newline at end of file
+//! Pane management for the AI Terminal UI
+//!
+//! This module provides functionality for managing multiple panes within the terminal,
+//! including splitting, resizing, and navigation.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    Frame,
+};
+use terminal_emulator::{BlockState, CommandBlock, OutputSummary, RemoteHost};
+use terminal_emulator::impact::ImpactReport;
+use terminal_emulator::progress;
+
+use crate::hints::{self, Found, Target, TargetKind};
+use crate::hyperlink::{self, Hyperlink};
+use crate::images::{self, ImagePlacement};
+use crate::keymap::Motion;
+use crate::markdown_renderer::render_markdown_document;
+use crate::render_cache::{ImageSlot, RenderCache};
+use crate::theme::{Signal, Theme};
+use crate::widgets::{Minimap, Snapshot};
+
+/// Most paths whose existence is remembered before the memory is cleared
+const MAX_CHECKED_PATHS: usize = 512;
+
+/// Narrowest pane interior that still gets a minimap gutter
+const MIN_WIDTH_FOR_GUTTER: u16 = 20;
+
+/// Represents the orientation of a pane split
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitOrientation {
+    Horizontal,
+    Vertical,
+}
+
+/// Text being selected by dragging, from where the drag started to where it is now
+///
+/// Both ends are screen cells as `(row, column)`, so they order the way text is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Selection {
+    anchor: (u16, u16),
+    head: (u16, u16),
+}
+
+impl Selection {
+    /// Get the ends of the selection in reading order
+    fn bounds(&self) -> ((u16, u16), (u16, u16)) {
+        (self.anchor.min(self.head), self.anchor.max(self.head))
+    }
+
+    /// Get the columns selected on a row of a text area, as a start and an end past it
+    fn columns(&self, row: u16, text: Rect) -> Option<(u16, u16)> {
+        let (start, end) = self.bounds();
+        if row < start.0 || row > end.0 {
+            return None;
+        }
+        let from = if row == start.0 { start.1 } else { text.x };
+        let to = if row == end.0 { end.1 + 1 } else { text.x + text.width };
+        (from < to).then_some((from, to))
+    }
+}
+
+/// Represents a pane in the terminal UI
+#[derive(Debug)]
+pub struct Pane {
+    /// Unique identifier for the pane
+    pub id: usize,
+    /// Area occupied by this pane
+    pub area: Rect,
+    /// Command blocks displayed in this pane
+    pub command_blocks: Vec<CommandBlock>,
+    /// First line in view, kept within the output by every render
+    pub scroll_offset: u16,
+    /// Whether the view stays on the newest output as it arrives
+    follow_tail: bool,
+    /// Whether this pane is currently focused
+    pub is_focused: bool,
+    /// Wrapped lines of the blocks rendered so far
+    render_cache: RenderCache,
+    /// Minimap drawn by the last render and where, for clicks
+    minimap: Option<(Rect, Minimap)>,
+    /// First row of each block and the total height at the last render, for clicks
+    block_rows: Vec<usize>,
+    /// Text of the rows on screen at the last render, for copying a selection
+    visible_rows: Vec<String>,
+    /// Index of the block selected by a click
+    selected_block: Option<usize>,
+    /// Text being selected by dragging
+    selection: Option<Selection>,
+    /// URLs and existing paths on screen at the last render, for hint mode
+    targets: Vec<Target>,
+    /// Whether paths found in output exist, so the file system is not asked every frame
+    checked_paths: HashMap<PathBuf, bool>,
+    /// Host the pane's commands run on over SSH, if it is connected to one
+    pub remote: Option<RemoteHost>,
+    /// Images wholly on screen at the last render, for drawing them with graphics
+    image_placements: Vec<ImagePlacement>,
+}
+
+impl Pane {
+    /// Create a new pane
+    pub fn new(id: usize, area: Rect) -> Self {
+        Self {
+            id,
+            area,
+            command_blocks: Vec::new(),
+            scroll_offset: 0,
+            follow_tail: true,
+            is_focused: false,
+            render_cache: RenderCache::new(),
+            minimap: None,
+            block_rows: Vec::new(),
+            visible_rows: Vec::new(),
+            selected_block: None,
+            selection: None,
+            targets: Vec::new(),
+            checked_paths: HashMap::new(),
+            image_placements: Vec::new(),
+            remote: None,
+        }
+    }
+
+    /// Add a command block to this pane
+    pub fn add_command_block(&mut self, block: CommandBlock) {
+        self.command_blocks.push(block);
+    }
+
+    /// Fold the newest block into the one before it when it repeats it, returning whether it did
+    pub fn collapse_repeat(&mut self) -> bool {
+        let count = self.command_blocks.len();
+        if count < 2 || !self.command_blocks[count - 1].is_repeat_of(&self.command_blocks[count - 2]) {
+            return false;
+        }
+        let repeat = self.command_blocks.pop().expect("pane has at least two blocks");
+        let earlier = self.command_blocks.last_mut().expect("pane has at least one block");
+        earlier.add_repeat(&repeat);
+        true
+    }
+
+    /// List or hide the individual runs of every collapsed block, returning how many blocks changed
+    pub fn toggle_runs(&mut self) -> usize {
+        let show = !self.command_blocks.iter().any(|block| block.shows_runs());
+        let mut changed = 0;
+        for block in self.command_blocks.iter_mut().filter(|block| !block.repeats.is_empty()) {
+            block.set_show_runs(show);
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Switch every block with an enhanced view to its raw output or back, returning how many blocks changed
+    pub fn toggle_raw_output(&mut self) -> usize {
+        let raw = !self.command_blocks.iter().any(|block| block.shows_raw());
+        let mut changed = 0;
+        for block in self.command_blocks.iter_mut().filter(|block| block.view.is_some()) {
+            block.set_show_raw(raw);
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Fold every block's summary to its heading, or unfold them all if all are folded; returns how many blocks changed
+    pub fn toggle_summaries(&mut self) -> usize {
+        let summarized = |block: &&mut CommandBlock| matches!(block.summary, Some(OutputSummary::Ready { .. }));
+        let collapse = self.command_blocks.iter_mut().filter(summarized).any(|block| !block.summary_collapsed());
+        let mut changed = 0;
+        for block in self.command_blocks.iter_mut().filter(summarized) {
+            block.set_summary_collapsed(collapse);
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Drop the output of every block not running, keeping the commands as an index; returns how many blocks changed
+    pub fn clear_outputs(&mut self) -> usize {
+        let mut changed = 0;
+        for block in self.command_blocks.iter_mut().filter(|block| block.state != BlockState::Running && !block.output.is_empty()) {
+            block.clear_output();
+            changed += 1;
+        }
+        self.scroll_to_bottom();
+        changed
+    }
+
+    /// Remove the blocks of AI prompts, returning how many were removed
+    pub fn clear_ai_blocks(&mut self) -> usize {
+        self.remove_blocks(|block| block.command.starts_with('/'))
+    }
+
+    /// Remove the blocks started before `cutoff`, returning how many were removed
+    pub fn clear_before(&mut self, cutoff: DateTime<Local>) -> usize {
+        self.remove_blocks(|block| block.timestamp < cutoff)
+    }
+
+    /// Remove the blocks not running that match a predicate, returning how many were removed
+    fn remove_blocks(&mut self, remove: impl Fn(&CommandBlock) -> bool) -> usize {
+        let count = self.command_blocks.len();
+        // A running block still receives output, so it stays
+        self.command_blocks.retain(|block| block.state == BlockState::Running || !remove(block));
+        self.scroll_to_bottom();
+        self.selected_block = None;
+        count - self.command_blocks.len()
+    }
+
+    /// Follow the newest output again, as after End or new input
+    pub fn scroll_to_bottom(&mut self) {
+        self.follow_tail = true;
+    }
+
+    /// Check whether the view stays on the newest output as it arrives
+    pub fn is_following(&self) -> bool {
+        self.follow_tail
+    }
+
+    /// Move the view to a first line, following the newest output if that is the last page
+    fn scroll_to(&mut self, top: usize, max_top: usize) {
+        self.scroll_offset = top.min(max_top).min(u16::MAX as usize) as u16;
+        self.follow_tail = top >= max_top;
+    }
+
+    /// Get the first line in view, out of `total` lines with `page` of them in view
+    fn view_top(&self, total: usize, page: usize) -> usize {
+        let max_top = total.saturating_sub(page);
+        if self.follow_tail { max_top } else { (self.scroll_offset as usize).min(max_top) }
+    }
+
+    /// Build the styled lines for every command block in this pane
+    pub fn render_lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        self.render_content(theme).0
+    }
+
+    /// Build the styled lines and collect the links in block output
+    fn render_content(&self, theme: &Theme) -> (Vec<Line<'static>>, Vec<Hyperlink>) {
+        let mut lines = Vec::new();
+        let mut links = Vec::new();
+
+        for block in &self.command_blocks {
+            let (block_lines, block_links, _) = render_block(block, theme, self.content_areas().0.width);
+            lines.extend(block_lines);
+            links.extend(block_links);
+        }
+
+        (lines, links)
+    }
+
+    /// Split the pane interior into the text area and the minimap gutter, if there is room
+    fn content_areas(&self) -> (Rect, Option<Rect>) {
+        let inner = Block::default().borders(Borders::ALL).inner(self.area);
+        if self.command_blocks.is_empty() || inner.width <= MIN_WIDTH_FOR_GUTTER {
+            return (inner, None);
+        }
+        let text = Rect { width: inner.width - 1, ..inner };
+        let gutter = Rect { x: inner.x + inner.width - 1, width: 1, ..inner };
+        (text, Some(gutter))
+    }
+
+    /// Get the first row of each block, then the total height, wrapping at `width`
+    ///
+    /// Blocks that were not rendered yet are rendered into the cache.
+    fn block_starts(&mut self, width: u16, theme: &Theme, theme_revision: u64) -> Vec<usize> {
+        let mut starts = Vec::with_capacity(self.command_blocks.len() + 1);
+        let mut row = 0;
+        for command_block in &self.command_blocks {
+            starts.push(row);
+            row += self.render_cache.get_or_render(command_block, width, theme_revision, || {
+                render_block(command_block, theme, width)
+            }).lines.len();
+        }
+        starts.push(row);
+        starts
+    }
+
+    /// Move the scroll position by a motion
+    ///
+    /// Block motions need the height of every block, so blocks that were not
+    /// rendered yet are rendered into the cache first.
+    pub fn apply_motion(&mut self, motion: Motion, theme: &Theme, theme_revision: u64) {
+        let (text, _) = self.content_areas();
+        let page = text.height as usize;
+        let starts = self.block_starts(text.width, theme, theme_revision);
+
+        let total = starts[starts.len() - 1];
+        let max_top = total.saturating_sub(page);
+        let top = self.view_top(total, page);
+        // Block holding the top line of the view
+        let current = starts[..starts.len() - 1].partition_point(|&start| start <= top).saturating_sub(1);
+        let block_start = starts[current];
+        let block_end = starts.get(current + 1).copied().unwrap_or(total);
+
+        let target = match motion {
+            Motion::LineUp => top.saturating_sub(1),
+            Motion::LineDown => top + 1,
+            Motion::HalfPageUp => top.saturating_sub(page / 2),
+            Motion::HalfPageDown => top + page / 2,
+            Motion::PageUp => top.saturating_sub(page),
+            Motion::PageDown => top + page,
+            Motion::Top => 0,
+            Motion::Bottom => max_top,
+            Motion::PreviousBlock if top > block_start => block_start,
+            Motion::PreviousBlock => starts[current.saturating_sub(1)],
+            Motion::NextBlock => starts.get(current + 1).copied().filter(|&start| start < total).unwrap_or(top),
+            Motion::LastError => self.command_blocks
+                .iter()
+                .rposition(|command_block| command_block.state == BlockState::Failed)
+                .map_or(top, |index| starts[index]),
+            Motion::BlockStart => block_start,
+            Motion::BlockEnd => block_end.saturating_sub(page).max(block_start),
+        };
+        self.scroll_to(target, max_top);
+    }
+
+    /// Scroll to the part of the output under a click, returning whether the click hit the minimap
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        let Some((gutter, minimap)) = &self.minimap else {
+            return false;
+        };
+        if column != gutter.x || row < gutter.y || row >= gutter.y + gutter.height {
+            return false;
+        }
+        let top = minimap.scroll_target((row - gutter.y) as usize, gutter.height as usize);
+        let total = self.block_rows.last().copied().unwrap_or(0);
+        self.scroll_to(top, total.saturating_sub(gutter.height as usize));
+        true
+    }
+
+    /// Check whether a screen cell is inside the pane, border included
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        let area = self.area;
+        column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+    }
+
+    /// Scroll by a number of lines, up if negative, as the mouse wheel does
+    pub fn scroll_by(&mut self, lines: isize) {
+        let page = self.content_areas().0.height as usize;
+        let total = self.block_rows.last().copied().unwrap_or(0);
+        let target = self.view_top(total, page).saturating_add_signed(lines);
+        self.scroll_to(target, total.saturating_sub(page));
+        // The selection is kept in screen cells, which now show other text
+        self.selection = None;
+    }
+
+    /// Select the block drawn at a screen cell, returning its index
+    pub fn select_block_at(&mut self, column: u16, row: u16) -> Option<usize> {
+        let (text, _) = self.content_areas();
+        if column < text.x || column >= text.x + text.width || row < text.y || row >= text.y + text.height {
+            return None;
+        }
+        let line = self.scroll_offset as usize + (row - text.y) as usize;
+        let (&total, starts) = self.block_rows.split_last()?;
+        if line >= total {
+            return None;
+        }
+        let index = starts.partition_point(|&start| start <= line).checked_sub(1)?;
+        self.selected_block = Some(index);
+        Some(index)
+    }
+
+    /// Get the block selected by a click, if it is still there
+    pub fn selected_block(&self) -> Option<&CommandBlock> {
+        self.selected_block.and_then(|index| self.command_blocks.get(index))
+    }
+
+    /// Get the block selected by a click for changing it, if it is still there
+    pub fn selected_block_mut(&mut self) -> Option<&mut CommandBlock> {
+        self.selected_block.and_then(|index| self.command_blocks.get_mut(index))
+    }
+
+    /// Start selecting text at a screen cell
+    pub fn start_selection(&mut self, column: u16, row: u16) {
+        let cell = self.clamp_to_text(column, row);
+        self.selection = Some(Selection { anchor: cell, head: cell });
+    }
+
+    /// Move the free end of the selection to a screen cell, returning whether there is a selection
+    pub fn extend_selection(&mut self, column: u16, row: u16) -> bool {
+        let cell = self.clamp_to_text(column, row);
+        match &mut self.selection {
+            Some(selection) => {
+                selection.head = cell;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the text selection
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Get the selected text, one line per row, if more than one cell was dragged over
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection.filter(|selection| selection.anchor != selection.head)?;
+        let (text, _) = self.content_areas();
+        let rows: Vec<&str> = (text.y..text.y + text.height)
+            .filter_map(|row| {
+                let (from, to) = selection.columns(row, text)?;
+                let line = self.visible_rows.get((row - text.y) as usize).map_or("", String::as_str);
+                Some(crate::text::slice_columns(line, (from - text.x) as usize, (to - text.x) as usize).trim_end())
+            })
+            .collect();
+        Some(rows.join("\n"))
+    }
+
+    /// Get the URLs and existing paths on screen at the last render
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    /// Find the URLs and existing paths in the rows on screen, underlining them
+    ///
+    /// URLs become links, so terminals with OSC 8 make them clickable. Paths
+    /// are only looked for in the output of shell commands, whose working
+    /// directory they are relative to.
+    fn find_targets(&mut self, buffer: &mut ratatui::buffer::Buffer, text: Rect, row_blocks: &[usize], links: &mut Vec<Hyperlink>, theme: &Theme) {
+        if self.checked_paths.len() > MAX_CHECKED_PATHS {
+            self.checked_paths.clear();
+        }
+        self.targets.clear();
+        for ((row, line), &index) in (text.y..).zip(&self.visible_rows).zip(row_blocks) {
+            let command_block = &self.command_blocks[index];
+            let is_shell = !command_block.working_dir.is_empty() && !command_block.command.starts_with('/');
+            for (range, found) in hints::find(line) {
+                let (kind, style) = match found {
+                    Found::Url(url) => {
+                        links.push(Hyperlink { text: url.clone(), url: url.clone() });
+                        (TargetKind::Url(url), theme.styles.link)
+                    }
+                    Found::Path(path, line_number) if is_shell => {
+                        let resolved = hints::resolve(&path, Path::new(&command_block.working_dir));
+                        let exists = *self.checked_paths.entry(resolved.clone()).or_insert_with(|| resolved.exists());
+                        if !exists {
+                            continue;
+                        }
+                        (TargetKind::Path(resolved, line_number), Style::default().add_modifier(Modifier::UNDERLINED))
+                    }
+                    Found::Path(..) => continue,
+                };
+                let column = text.x + crate::text::width(&line[..range.start]) as u16;
+                let width = crate::text::width(&line[range.clone()]) as u16;
+                for x in column..(column + width).min(text.x + text.width) {
+                    if let Some(cell) = buffer.cell_mut((x, row)) {
+                        cell.set_style(style);
+                    }
+                }
+                self.targets.push(Target { text: line[range].to_string(), kind, column, row });
+            }
+        }
+    }
+
+    /// Get the images wholly on screen at the last render
+    pub fn image_placements(&self) -> &[ImagePlacement] {
+        &self.image_placements
+    }
+
+    /// Get the area the pane's text is drawn in
+    pub fn text_area(&self) -> Rect {
+        self.content_areas().0
+    }
+
+    /// Take a still copy of the pane's wrapped lines for copy mode
+    pub fn copy_snapshot(&mut self, theme: &Theme, theme_revision: u64) -> Snapshot {
+        let (text, _) = self.content_areas();
+        let block_rows = self.block_starts(text.width, theme, theme_revision);
+        let mut rows = Vec::new();
+        let mut continued = Vec::new();
+        for command_block in &self.command_blocks {
+            let cached = self.render_cache.get_or_render(command_block, text.width, theme_revision, || {
+                render_block(command_block, theme, text.width)
+            });
+            rows.extend_from_slice(&cached.lines);
+            continued.extend_from_slice(&cached.continued);
+        }
+        // Messages have no working directory and AI prompts start with a slash
+        let last_command = self.command_blocks
+            .iter()
+            .rposition(|block| !block.command.starts_with('/') && !block.working_dir.is_empty() && !block.output.is_empty());
+        Snapshot {
+            rows,
+            continued,
+            block_rows,
+            outputs: self.command_blocks.iter().map(|block| block.output.text()).collect(),
+            last_command,
+            top: self.scroll_offset as usize,
+        }
+    }
+
+    /// Keep a screen cell inside the text area, as `(row, column)`
+    fn clamp_to_text(&self, column: u16, row: u16) -> (u16, u16) {
+        let (text, _) = self.content_areas();
+        (
+            row.clamp(text.y, (text.y + text.height).saturating_sub(1).max(text.y)),
+            column.clamp(text.x, (text.x + text.width).saturating_sub(1).max(text.x)),
+        )
+    }
+
+    /// Render the pane
+    ///
+    /// Every block is looked up in the render cache so the minimap can place
+    /// it, but only the visible lines are handed to ratatui.
+    pub fn render(&mut self, f: &mut Frame, style: &PaneStyle, theme: &Theme, theme_revision: u64) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(if self.is_focused {
+                style.focused_border
+            } else {
+                style.border
+            });
+
+        self.render_cache.retain(&self.command_blocks);
+        let (text, gutter) = self.content_areas();
+        let starts = self.block_starts(text.width, theme, theme_revision);
+        let total = starts[starts.len() - 1];
+        let top = self.view_top(total, text.height as usize);
+        self.scroll_offset = top.min(u16::MAX as usize) as u16;
+        let bottom = top + text.height as usize;
+
+        // While scrolled back, the bottom border says how much newer output there is
+        let below = total.saturating_sub(bottom);
+        let block = if below > 0 {
+            block.title_bottom(Line::from(format!(" ↓ {} more line(s) · End follows the output ", below)).right_aligned())
+        } else {
+            block
+        };
+
+        let mut lines = Vec::new();
+        let mut links = Vec::new();
+        // Block each line on screen belongs to
+        let mut row_blocks = Vec::new();
+        let mut placements = Vec::new();
+        for (index, (command_block, (&row, &next))) in self.command_blocks.iter().zip(starts.iter().zip(&starts[1..])).enumerate() {
+            if row >= bottom {
+                break;
+            }
+            if next > top {
+                let cached = self.render_cache.get_or_render(command_block, text.width, theme_revision, || {
+                    render_block(command_block, theme, text.width)
+                });
+                let from = top.saturating_sub(row);
+                let to = bottom.min(next) - row;
+                let first = lines.len();
+                lines.extend_from_slice(&cached.lines[from..to]);
+                row_blocks.resize(lines.len(), index);
+                // The header of the block selected by a click is highlighted while it is on screen
+                if self.selected_block == Some(index) && from == 0 {
+                    let header = &mut lines[first];
+                    header.style = header.style.patch(theme.styles.selection);
+                }
+                links.extend(cached.links.iter().cloned());
+                // Images whose every row is on screen can be drawn with graphics
+                for slot in &cached.images {
+                    let start = row + slot.row;
+                    if start >= top && start + slot.rows as usize <= bottom
+                        && let Some(image) = command_block.images.get(slot.image).and_then(|image| images::decode_cached(image).ok())
+                    {
+                        let area = Rect::new(text.x + slot.column, text.y + (start - top) as u16, slot.columns, slot.rows);
+                        placements.push(ImagePlacement { area, image });
+                    }
+                }
+            }
+        }
+        self.image_placements = placements;
+
+        self.visible_rows = lines.iter().map(Line::to_string).collect();
+        f.render_widget(block.style(style.background), self.area);
+        f.render_widget(Paragraph::new(lines).style(style.background), text);
+        self.find_targets(f.buffer_mut(), text, &row_blocks, &mut links, theme);
+
+        if let Some(selection) = self.selection {
+            let buffer = f.buffer_mut();
+            for row in text.y..text.y + text.height {
+                if let Some((from, to)) = selection.columns(row, text) {
+                    for column in from..to {
+                        if let Some(cell) = buffer.cell_mut((column, row)) {
+                            cell.set_style(theme.styles.selection);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Make visible links clickable in terminals that support OSC 8
+        hyperlink::apply(f.buffer_mut(), text, &links, theme.styles.link);
+
+        self.minimap = gutter.map(|gutter| {
+            let blocks = self.command_blocks
+                .iter()
+                .zip(&starts)
+                .map(|(command_block, &start)| (start, command_block.state == BlockState::Failed))
+                .collect();
+            let minimap = Minimap::new(blocks, starts[starts.len() - 1], top, text.height as usize);
+            minimap.render(f.buffer_mut(), gutter, theme);
+            (gutter, minimap)
+        });
+        // Panes too narrow for the minimap get a scrollbar on their right border
+        if gutter.is_none() && total > text.height as usize {
+            let mut state = ScrollbarState::new(total.saturating_sub(text.height as usize)).position(top);
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .thumb_style(style.focused_border);
+            f.render_stateful_widget(scrollbar, self.area.inner(Margin { vertical: 1, horizontal: 0 }), &mut state);
+        }
+        self.block_rows = starts;
+    }
+}
+
+/// Build the styled lines of one command block and collect the links in its output
+fn render_block(block: &CommandBlock, theme: &Theme, width: u16) -> (Vec<Line<'static>>, Vec<Hyperlink>, Vec<ImageSlot>) {
+    let mut lines = Vec::new();
+    let mut links = Vec::new();
+    let mut slots = Vec::new();
+
+    // Command header with status icon and timestamp
+    let mut header = vec![
+        block.status_icon().to_string().bold(),
+        " ".into(),
+        block.command.clone().fg(theme.command).bold(),
+        format!(" ({})", block.timestamp.format("%H:%M:%S")).into(),
+    ];
+    // Identical runs collapsed into this block
+    if let Some(last) = block.repeats.last() {
+        header.push(Span::styled(
+            format!(" ×{} (last at {})", block.repeats.len() + 1, last.timestamp.format("%H:%M")),
+            theme.styles.ghost_text,
+        ));
+    }
+    // The commit the command ran against, in a git repository
+    if let Some(commit) = &block.commit {
+        header.push(Span::styled(format!(" @ {}", &commit[..commit.len().min(8)]), theme.styles.ghost_text));
+    }
+    // The host a remote command ran on
+    if let Some(host) = &block.host {
+        header.push(Span::styled(format!(" on {}", host), theme.styles.ghost_text));
+    }
+    // Tags the user gave the block; the automatic ones would repeat what the header shows
+    for tag in &block.tags {
+        header.push(Span::styled(format!(" #{}", tag), theme.styles.ghost_text));
+    }
+    // How far a progress bar redrawing its line has got, while the command runs
+    if block.state == BlockState::Running
+        && let Some(percent) = block.output.redrawn_line().and_then(progress::percent)
+    {
+        header.push(Span::styled(format!(" ⏳ {:.0}%", percent), theme.styles.ghost_text));
+    }
+    // Secrets masked before the output went to the AI
+    if block.redactions > 0 {
+        header.push(Span::styled(format!(" 🛡 {} redacted", block.redactions), Signal::Risk.style(theme)));
+    }
+    lines.push(Line::from(header));
+    if block.shows_runs() {
+        for (i, run) in block.runs().iter().enumerate() {
+            lines.push(Line::from(vec![
+                "  ".into(),
+                Span::styled(
+                    format!("run {} at {} (Exit: {}) (Duration: {:?})",
+                            i + 1,
+                            run.timestamp.format("%H:%M:%S"),
+                            run.exit_code.unwrap_or(-1),
+                            run.duration.unwrap_or_default()),
+                    theme.styles.ghost_text,
+                ),
+            ]));
+        }
+    }
+
+    // Note output that no longer fits in the scrollback
+    let dropped = block.output.dropped_lines();
+    if dropped > 0 {
+        lines.push(Line::from(vec![
+            "  ".into(),
+            Span::styled(format!("… output truncated, {} lines dropped", dropped), theme.styles.ghost_text),
+        ]));
+    }
+
+    // AI summary of a long output, above the output itself
+    if let Some(summary) = &block.summary {
+        lines.extend(render_summary(summary, block, theme));
+    }
+
+    // Output rendered as Markdown, indented under the command, or the processor's view of it
+    let view = block.view.as_ref().filter(|_| !block.shows_raw());
+    if let Some(view) = view {
+        lines.push(Line::from(vec![
+            "  ".into(),
+            Span::styled(format!("✨ {} view · 'Toggle Raw Output' shows the original", view.processor), theme.styles.ghost_text),
+        ]));
+    }
+    let text = match view {
+        Some(view) => view.markdown.clone(),
+        None => block.output.text(),
+    };
+    if !text.is_empty() {
+        let document = render_markdown_document(&text, theme);
+        for mut line in document.lines {
+            line.spans.insert(0, Span::raw("  "));
+            lines.push(line);
+        }
+        links.extend(document.links);
+    }
+
+    // Images the command drew, in half blocks under the output
+    for (index, image) in block.images.iter().enumerate() {
+        match images::decode_cached(image) {
+            Ok(decoded) => {
+                let (columns, rows) = decoded.size_in_cells(width.saturating_sub(2), images::MAX_ROWS);
+                slots.push(ImageSlot { row: lines.len(), column: 2, columns, rows, image: index });
+                for mut line in decoded.to_lines(columns, rows) {
+                    line.spans.insert(0, Span::raw("  "));
+                    lines.push(line);
+                }
+            }
+            Err(e) => lines.push(Line::from(vec![
+                "  ".into(),
+                Span::styled(format!("🖼 {} image not shown: {}", image.protocol.name(), e), theme.styles.ghost_text),
+            ])),
+        }
+    }
+
+    // Status line once the block has finished, marked by glyph as well as color
+    if block.is_complete() {
+        let signal = Signal::for_state(block.state);
+        let glyph = signal.map_or(" ", Signal::glyph);
+        let style = signal.map_or(Style::default().fg(theme.text), |signal| signal.style(theme));
+
+        lines.push(Line::from(vec![
+            "  ".into(),
+            Span::styled(
+                format!("{} Status: {:?} (Exit: {}) (Duration: {:?}){}",
+                        glyph,
+                        block.state,
+                        block.exit_code.unwrap_or(-1),
+                        block.duration.unwrap_or_default(),
+                        block.usage.map_or(String::new(), |usage| format!(" {}", usage.status(block.duration.unwrap_or_default())))),
+                style,
+            ),
+        ]));
+    }
+
+    // Corrected command for a failed one, run with Tab
+    if let Some(correction) = block.correction() {
+        lines.push(Line::from(vec![
+            "  ".into(),
+            Span::styled(format!("💡 Tab runs: {}", correction), theme.styles.ghost_text),
+        ]));
+    }
+
+    // Footer listing what an approved command changed on disk
+    if let Some(impact) = &block.impact {
+        lines.extend(render_impact(impact, &block.working_dir, theme));
+    }
+
+    // Empty line between blocks for readability
+    lines.push(Line::from(""));
+
+    (lines, links, slots)
+}
+
+/// Render where a block's summary stands, with the summary itself unless it is folded
+fn render_summary(summary: &OutputSummary, block: &CommandBlock, theme: &Theme) -> Vec<Line<'static>> {
+    let note = |text: String| Line::from(vec!["  ".into(), Span::styled(text, theme.styles.ghost_text)]);
+    match summary {
+        OutputSummary::Offered => vec![note(format!("💡 {} lines · /summarize asks the AI for a summary", block.output.line_count()))],
+        OutputSummary::Pending => vec![note("📋 Summarizing the output…".to_string())],
+        OutputSummary::Failed(error) => vec![note(format!("📋 Could not summarize the output: {}", error))],
+        OutputSummary::Ready { .. } if block.summary_collapsed() => {
+            vec![note("▸ 📋 Summary · 'Toggle Summaries' unfolds it".to_string())]
+        }
+        OutputSummary::Ready { model, markdown } => {
+            let mut lines = vec![note(format!("▾ 📋 Summary by {} · 'Toggle Summaries' folds it", model))];
+            for mut line in render_markdown_document(markdown, theme).lines {
+                line.spans.insert(0, Span::raw("    "));
+                lines.push(line);
+            }
+            lines.push(note("── full output ──".to_string()));
+            lines
+        }
+    }
+}
+
+/// Number of changed paths listed under a block
+const MAX_LISTED_CHANGES: usize = 10;
+
+/// Render the changes a command made, with paths relative to its working directory
+fn render_impact(impact: &ImpactReport, working_dir: &str, theme: &Theme) -> Vec<Line<'static>> {
+    let mut summary = match impact.changes.len() {
+        0 => "No changes to the target paths".to_string(),
+        1 => "1 path changed".to_string(),
+        count => format!("{} paths changed", count),
+    };
+    if impact.truncated {
+        summary.push_str(" (snapshot limit reached, some changes may be missing)");
+    }
+    let mut lines = vec![Line::from(vec!["  ".into(), Span::styled(summary, theme.styles.ghost_text)])];
+
+    for change in impact.changes.iter().take(MAX_LISTED_CHANGES) {
+        let signal = Signal::for_change(change.kind);
+        let path = change.path.strip_prefix(working_dir).unwrap_or(&change.path);
+        let mut text = format!("{} {}", signal.glyph(), path.display());
+        if !change.details.is_empty() {
+            text.push_str(&format!(" ({})", change.details.join(", ")));
+        }
+        lines.push(Line::from(vec!["    ".into(), Span::styled(text, signal.style(theme))]));
+    }
+    if impact.changes.len() > MAX_LISTED_CHANGES {
+        lines.push(Line::from(vec![
+            "    ".into(),
+            Span::styled(format!("… and {} more", impact.changes.len() - MAX_LISTED_CHANGES), theme.styles.ghost_text),
+        ]));
+    }
+    lines
+}
+
+/// Styling for panes
+#[derive(Debug, Clone)]
+pub struct PaneStyle {
+    pub background: Style,
+    pub border: Style,
+    pub focused_border: Style,
+}
+
+impl PaneStyle {
+    /// Derive pane styling from a theme
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: Style::default().bg(theme.background).fg(theme.text),
+            border: theme.styles.border,
+            focused_border: theme.styles.border_focused,
+        }
+    }
+}
+
+impl Default for PaneStyle {
+    fn default() -> Self {
+        Self {
+            background: Style::default().bg(Color::Black).fg(Color::White),
+            border: Style::default().fg(Color::DarkGray),
+            focused_border: Style::default().fg(Color::Blue),
+        }
+    }
+}
+
+/// Manages multiple panes in the terminal UI
+#[derive(Debug)]
+pub struct PaneManager {
+    /// List of panes
+    panes: Vec<Pane>,
+    /// ID of the currently focused pane
+    focused_pane_id: Option<usize>,
+    /// Next ID to assign to a new pane
+    next_id: usize,
+}
+
+impl PaneManager {
+    /// Create a new pane manager
+    pub fn new(area: Rect) -> Self {
+        let mut initial_pane = Pane::new(0, area);
+        initial_pane.is_focused = true;
+        
+        Self {
+            panes: vec![initial_pane],
+            focused_pane_id: Some(0),
+            next_id: 1,
+        }
+    }
+
+    /// Get the currently focused pane
+    pub fn focused_pane(&self) -> Option<&Pane> {
+        self.focused_pane_id.and_then(|id| {
+            self.panes.iter().find(|pane| pane.id == id)
+        })
+    }
+
+    /// Get the currently focused pane mutably
+    pub fn focused_pane_mut(&mut self) -> Option<&mut Pane> {
+        self.focused_pane_id.and_then(|id| {
+            self.panes.iter_mut().find(|pane| pane.id == id)
+        })
+    }
+
+    /// Pass a click to the pane under it, returning whether a pane handled it
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        self.panes.iter_mut().any(|pane| pane.click(column, row))
+    }
+
+    /// Get the ID of the pane under a screen cell
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.panes.iter().find(|pane| pane.contains(column, row)).map(|pane| pane.id)
+    }
+
+    /// Scroll the pane under a screen cell, as the mouse wheel does
+    pub fn scroll_at(&mut self, column: u16, row: u16, lines: isize) {
+        if let Some(pane) = self.panes.iter_mut().find(|pane| pane.contains(column, row)) {
+            pane.scroll_by(lines);
+        }
+    }
+
+    /// Press the mouse button at a screen cell
+    ///
+    /// A press on a minimap scrolls its pane; anywhere else in a pane it
+    /// focuses the pane, selects the block under the pointer and starts a
+    /// text selection. Returns whether a pane was hit.
+    pub fn press(&mut self, column: u16, row: u16) -> bool {
+        if self.click(column, row) {
+            return true;
+        }
+        let Some(id) = self.pane_at(column, row) else {
+            return false;
+        };
+        self.set_focus(id);
+        for pane in &mut self.panes {
+            pane.clear_selection();
+            if pane.id == id {
+                pane.select_block_at(column, row);
+                pane.start_selection(column, row);
+            }
+        }
+        true
+    }
+
+    /// Drag with the mouse button held, extending the selection in the pane it started in
+    pub fn drag(&mut self, column: u16, row: u16) {
+        for pane in &mut self.panes {
+            pane.extend_selection(column, row);
+        }
+    }
+
+    /// Get the text selected by dragging, if any
+    pub fn selected_text(&self) -> Option<String> {
+        self.panes.iter().find_map(Pane::selected_text)
+    }
+
+    /// Split the focused pane
+    pub fn split_focused_pane(&mut self, orientation: SplitOrientation) -> Result<(), &'static str> {
+        if let Some(focused_id) = self.focused_pane_id {
+            // Find the focused pane
+            let pane_index = self.panes.iter().position(|p| p.id == focused_id)
+                .ok_or("Focused pane not found")?;
+            
+            // Get the area of the pane to split
+            let pane_area = self.panes[pane_index].area;
+            
+            // Create two new areas by splitting the current pane's area
+            let new_areas = match orientation {
+                SplitOrientation::Horizontal => {
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(pane_area);
+                    [chunks[0], chunks[1]]
+                },
+                SplitOrientation::Vertical => {
+                    let chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(pane_area);
+                    [chunks[0], chunks[1]]
+                },
+            };
+            
+            // Update the original pane with the first area
+            self.panes[pane_index].area = new_areas[0];
+            
+            // Create a new pane with the second area
+            let new_pane_id = self.next_id;
+            self.next_id += 1;
+            let mut new_pane = Pane::new(new_pane_id, new_areas[1]);
+            
+            // Copy command blocks from the original pane to the new pane
+            new_pane.command_blocks = self.panes[pane_index].command_blocks.clone();
+            
+            // Add the new pane to the list
+            self.panes.push(new_pane);
+            
+            // Focus the new pane
+            self.set_focus(new_pane_id);
+            
+            Ok(())
+        } else {
+            Err("No focused pane to split")
+        }
+    }
+
+    /// Close the focused pane
+    pub fn close_focused_pane(&mut self) -> Result<(), &'static str> {
+        if self.panes.len() <= 1 {
+            return Err("Cannot close the last pane");
+        }
+        
+        if let Some(focused_id) = self.focused_pane_id {
+            // Find the focused pane
+            let pane_index = self.panes.iter().position(|p| p.id == focused_id)
+                .ok_or("Focused pane not found")?;
+            
+            // Remove the pane
+            self.panes.remove(pane_index);
+            
+            // Focus the previous pane (or the first pane if we removed the first one)
+            let new_focused_id = if pane_index > 0 {
+                self.panes[pane_index - 1].id
+            } else {
+                self.panes[0].id
+            };
+            
+            self.set_focus(new_focused_id);
+            
+            Ok(())
+        } else {
+            Err("No focused pane to close")
+        }
+    }
+
+    /// Move focus to the pane with the given ID
+    fn set_focus(&mut self, pane_id: usize) {
+        for pane in &mut self.panes {
+            pane.is_focused = pane.id == pane_id;
+        }
+        self.focused_pane_id = Some(pane_id);
+    }
+
+    /// Focus the next pane
+    pub fn focus_next_pane(&mut self) {
+        if self.panes.is_empty() {
+            return;
+        }
+        
+        // Clear focus from all panes
+        for pane in &mut self.panes {
+            pane.is_focused = false;
+        }
+        
+        // Set focus to the next pane
+        let current_index = self.focused_pane_id
+            .and_then(|id| self.panes.iter().position(|p| p.id == id))
+            .unwrap_or(0);
+        
+        let next_index = (current_index + 1) % self.panes.len();
+        self.panes[next_index].is_focused = true;
+        self.focused_pane_id = Some(self.panes[next_index].id);
+    }
+
+    /// Focus the previous pane
+    pub fn focus_prev_pane(&mut self) {
+        if self.panes.is_empty() {
+            return;
+        }
+        
+        // Clear focus from all panes
+        for pane in &mut self.panes {
+            pane.is_focused = false;
+        }
+        
+        // Set focus to the previous pane
+        let current_index = self.focused_pane_id
+            .and_then(|id| self.panes.iter().position(|p| p.id == id))
+            .unwrap_or(0);
+        
+        let prev_index = if current_index > 0 {
+            current_index - 1
+        } else {
+            self.panes.len() - 1
+        };
+        
+        self.panes[prev_index].is_focused = true;
+        self.focused_pane_id = Some(self.panes[prev_index].id);
+    }
+
+    /// Resize panes based on the terminal size
+    pub fn resize(&mut self, area: Rect) {
+        // For now, we'll just update the first pane to fill the entire area
+        // In a more advanced implementation, we would maintain the relative sizes of panes
+        if let Some(pane) = self.panes.get_mut(0) {
+            pane.area = area;
+        }
+    }
+
+    /// Fit every pane into a new area, keeping their places relative to each other
+    pub fn fit(&mut self, area: Rect) {
+        let Some(bounds) = self.panes.iter().map(|pane| pane.area).reduce(|a, b| a.union(b)) else {
+            return;
+        };
+        if bounds.width == 0 || bounds.height == 0 {
+            return;
+        }
+        let scale = |offset: u16, from: u16, to: u16| (u32::from(offset) * u32::from(to) / u32::from(from)) as u16;
+        for pane in &mut self.panes {
+            let old = pane.area;
+            let x = area.x + scale(old.x - bounds.x, bounds.width, area.width);
+            let y = area.y + scale(old.y - bounds.y, bounds.height, area.height);
+            let right = area.x + scale(old.right() - bounds.x, bounds.width, area.width);
+            let bottom = area.y + scale(old.bottom() - bounds.y, bounds.height, area.height);
+            pane.area = Rect::new(x, y, right - x, bottom - y);
+        }
+    }
+
+    /// Get all panes
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    /// Get all panes for modification
+    pub fn panes_mut(&mut self) -> &mut [Pane] {
+        &mut self.panes
+    }
+
+    /// Render all panes using the given theme
+    pub fn render(&mut self, f: &mut Frame, theme: &Theme, theme_revision: u64) {
+        let style = PaneStyle::from_theme(theme);
+        for pane in &mut self.panes {
+            pane.render(f, &style, theme, theme_revision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pane_creation() {
+        let rect = Rect::new(0, 0, 80, 24);
+        let pane = Pane::new(1, rect);
+        
+        assert_eq!(pane.id, 1);
+        assert_eq!(pane.area, rect);
+        assert_eq!(pane.command_blocks.len(), 0);
+        assert_eq!(pane.scroll_offset, 0);
+        assert!(!pane.is_focused);
+    }
+
+    #[test]
+    fn test_granular_clearing() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 80, 24));
+        for command in ["ls", "/explain ls", "pwd", "sleep 60"] {
+            let mut block = CommandBlock::new(command.to_string(), "/tmp".to_string());
+            block.append_output("output\n", false);
+            block.complete(0, std::time::Duration::from_millis(5));
+            pane.add_command_block(block);
+        }
+        pane.command_blocks[0].timestamp -= chrono::Duration::hours(2);
+        pane.command_blocks[3].start_execution();
+
+        assert_eq!(pane.clear_before(Local::now() - chrono::Duration::hours(1)), 1);
+        assert_eq!(pane.clear_ai_blocks(), 1);
+        assert_eq!(pane.clear_outputs(), 1);
+        let commands: Vec<&str> = pane.command_blocks.iter().map(|block| block.command.as_str()).collect();
+        assert_eq!(commands, ["pwd", "sleep 60"]);
+        assert!(pane.command_blocks[0].output.is_empty());
+        // The running block keeps its output
+        assert!(!pane.command_blocks[1].output.is_empty());
+    }
+
+    #[test]
+    fn test_motions() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 40, 7));
+        for exit_code in [0, 1, 0] {
+            let mut block = CommandBlock::new("ls".to_string(), "/tmp".to_string());
+            block.append_output(&"line\n".repeat(10), false);
+            block.complete(exit_code, std::time::Duration::from_millis(5));
+            pane.add_command_block(block);
+        }
+        let theme = Theme::default();
+        let mut go = |motion| {
+            pane.apply_motion(motion, &theme, 0);
+            pane.scroll_offset as usize
+        };
+
+        // A new pane follows the newest output
+        let bottom = go(Motion::LineUp) + 1;
+        assert_eq!(go(Motion::Top), 0);
+        assert_eq!(go(Motion::HalfPageDown), 2);
+        assert_eq!(go(Motion::Top), 0);
+        let second = go(Motion::NextBlock);
+        let third = go(Motion::NextBlock);
+        assert!(0 < second && second < third);
+        assert_eq!(go(Motion::NextBlock), third);
+        assert_eq!(go(Motion::PreviousBlock), second);
+        assert_eq!(go(Motion::LineDown), second + 1);
+        assert_eq!(go(Motion::BlockStart), second);
+        // The view is 5 lines high, so the block's last line ends the view
+        assert_eq!(go(Motion::BlockEnd), third - 5);
+        assert_eq!(go(Motion::Bottom), 3 * (third - second) - 5);
+        assert_eq!(go(Motion::Bottom), bottom);
+        assert_eq!(go(Motion::LastError), second);
+        assert_eq!(go(Motion::PageUp), second - 5);
+    }
+
+    #[test]
+    fn test_minimap_click() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 40, 12));
+        for exit_code in [0, 1] {
+            let mut block = CommandBlock::new("ls".to_string(), "/tmp".to_string());
+            block.append_output(&"line\n".repeat(40), false);
+            block.complete(exit_code, std::time::Duration::from_millis(5));
+            pane.add_command_block(block);
+        }
+        let theme = Theme::default();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 12)).unwrap();
+        terminal.draw(|f| pane.render(f, &PaneStyle::default(), &theme, 0)).unwrap();
+
+        // The gutter is the last column inside the border
+        assert!(!pane.click(20, 5));
+        assert!(pane.click(38, 10));
+        assert!(pane.scroll_offset > 0);
+        assert!(pane.click(38, 1));
+        assert_eq!(pane.scroll_offset, 0);
+    }
+
+    #[test]
+    fn test_mouse_selection() {
+        let mut manager = PaneManager::new(Rect::new(0, 0, 40, 12));
+        for command in ["ls", "pwd"] {
+            let mut block = CommandBlock::new(command.to_string(), "/tmp".to_string());
+            block.append_output(&"line\n".repeat(6), false);
+            block.complete(0, std::time::Duration::from_millis(5));
+            manager.panes_mut()[0].add_command_block(block);
+        }
+        let theme = Theme::default();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 12)).unwrap();
+        terminal.draw(|f| manager.render(f, &theme, 0)).unwrap();
+        manager.scroll_at(5, 5, -100);
+        terminal.draw(|f| manager.render(f, &theme, 0)).unwrap();
+
+        // The first row inside the border is the first block's header
+        assert!(manager.press(1, 1));
+        assert_eq!(manager.focused_pane().unwrap().selected_block().unwrap().command, "ls");
+        assert_eq!(manager.selected_text(), None);
+        manager.drag(30, 3);
+        let text = manager.selected_text().unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.lines().nth(1).unwrap().contains("line"));
+
+        // Scrolling moves the view and drops the selection
+        manager.scroll_at(5, 5, 3);
+        assert_eq!(manager.panes()[0].scroll_offset, 3);
+        assert_eq!(manager.selected_text(), None);
+        manager.scroll_at(5, 5, -10);
+        assert_eq!(manager.panes()[0].scroll_offset, 0);
+        assert!(!manager.press(50, 50));
+    }
+
+    #[test]
+    fn test_follow_tail() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 40, 7));
+        let theme = Theme::default();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 7)).unwrap();
+        let mut add = |pane: &mut Pane| {
+            let mut block = CommandBlock::new("ls".to_string(), "/tmp".to_string());
+            block.append_output(&"line\n".repeat(10), false);
+            block.complete(0, std::time::Duration::from_millis(5));
+            pane.add_command_block(block);
+            terminal.draw(|f| pane.render(f, &PaneStyle::default(), &theme, 0)).unwrap();
+            pane.scroll_offset as usize
+        };
+
+        // New output keeps the view on the newest lines
+        let first = add(&mut pane);
+        let second = add(&mut pane);
+        assert!(first > 0 && second > first);
+
+        // Scrolling up stops following, and the offset stays inside the output
+        pane.scroll_by(-3);
+        assert!(!pane.is_following());
+        assert_eq!(add(&mut pane), second - 3);
+        pane.scroll_by(-1000);
+        assert_eq!(pane.scroll_offset, 0);
+
+        // Scrolling back to the end follows again
+        pane.scroll_by(1000);
+        assert!(pane.is_following());
+        let third = add(&mut pane);
+        assert!(third > second);
+    }
+
+    #[test]
+    fn test_collapse_repeats() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 40, 12));
+        for output in ["ok\n", "ok\n", "ok\n", "down\n"] {
+            let mut block = CommandBlock::new("curl -s localhost/health".to_string(), "/tmp".to_string());
+            block.append_output(output, false);
+            block.complete(0, std::time::Duration::from_millis(5));
+            pane.add_command_block(block);
+            pane.collapse_repeat();
+        }
+        assert_eq!(pane.command_blocks.len(), 2);
+        assert_eq!(pane.command_blocks[0].runs().len(), 3);
+
+        let theme = Theme::default();
+        let header = pane.render_lines(&theme)[0].to_string();
+        assert!(header.contains("×3 (last at"));
+        assert_eq!(pane.toggle_runs(), 1);
+        assert!(pane.render_lines(&theme)[3].to_string().contains("run 3 at"));
+    }
+
+    #[test]
+    fn test_summaries() {
+        let mut pane = Pane::new(1, Rect::new(0, 0, 40, 12));
+        let mut block = CommandBlock::new("cargo build".to_string(), "/tmp".to_string());
+        block.append_output("error[E0308]: mismatched types\n", false);
+        block.complete(101, std::time::Duration::from_millis(5));
+        block.set_summary(OutputSummary::Ready { model: "llama3".to_string(), markdown: "One type error".to_string() });
+        pane.add_command_block(block);
+
+        let theme = Theme::default();
+        let lines: Vec<String> = pane.render_lines(&theme).iter().map(|line| line.to_string()).collect();
+        assert!(lines[1].contains("Summary by llama3"));
+        assert_eq!(lines[2].trim(), "One type error");
+        assert!(lines[3].contains("full output"));
+        assert!(lines[4].contains("mismatched types"));
+
+        assert_eq!(pane.toggle_summaries(), 1);
+        let lines: Vec<String> = pane.render_lines(&theme).iter().map(|line| line.to_string()).collect();
+        assert!(lines[1].contains("▸"));
+        assert!(lines[2].contains("mismatched types"));
+        pane.toggle_summaries();
+        assert!(!pane.command_blocks[0].summary_collapsed());
+    }
+
+    #[test]
+    fn test_pane_manager_creation() {
+        let rect = Rect::new(0, 0, 80, 24);
+        let pane_manager = PaneManager::new(rect);
+        
+        assert_eq!(pane_manager.panes.len(), 1);
+        assert_eq!(pane_manager.focused_pane_id, Some(0));
+        assert_eq!(pane_manager.next_id, 1);
+    }
+
+    #[test]
+    fn test_split_focused_pane() {
+        let rect = Rect::new(0, 0, 80, 24);
+        let mut pane_manager = PaneManager::new(rect);
+        
+        // Split the focused pane horizontally
+        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());
+        assert_eq!(pane_manager.panes.len(), 2);
+        assert_eq!(pane_manager.focused_pane_id, Some(1));
+        
+        // Split the focused pane vertically
+        assert!(pane_manager.split_focused_pane(SplitOrientation::Vertical).is_ok());
+        assert_eq!(pane_manager.panes.len(), 3);
+        assert_eq!(pane_manager.focused_pane_id, Some(2));
+    }
+
+    #[test]
+    fn test_close_focused_pane() {
+        let rect = Rect::new(0, 0, 80, 24);
+        let mut pane_manager = PaneManager::new(rect);
+        
+        // Split to create a second pane
+        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());
+        assert_eq!(pane_manager.panes.len(), 2);
+        
+        // Close the focused pane
+        assert!(pane_manager.close_focused_pane().is_ok());
+        assert_eq!(pane_manager.panes.len(), 1);
+        assert_eq!(pane_manager.focused_pane_id, Some(0));
+    }
+
+    #[test]
+    fn test_focus_navigation() {
+        let rect = Rect::new(0, 0, 80, 24);
+        let mut pane_manager = PaneManager::new(rect);
+        
+        // Split to create a second pane
+        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());
+        assert_eq!(pane_manager.focused_pane_id, Some(1));
+        
+        // Focus the next pane (should wrap around to the first)
+        pane_manager.focus_next_pane();
+        assert_eq!(pane_manager.focused_pane_id, Some(0));
+        
+        // Focus the previous pane (should wrap around to the second)
+        pane_manager.focus_prev_pane();
+        assert_eq!(pane_manager.focused_pane_id, Some(1));
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut pane_manager = PaneManager::new(Rect::new(0, 1, 80, 24));
+        assert!(pane_manager.split_focused_pane(SplitOrientation::Horizontal).is_ok());
+        pane_manager.fit(Rect::new(0, 1, 40, 24));
+        let areas: Vec<Rect> = pane_manager.panes().iter().map(|pane| pane.area).collect();
+        assert_eq!(areas, [Rect::new(0, 1, 20, 24), Rect::new(20, 1, 20, 24)]);
+        pane_manager.fit(Rect::new(0, 1, 80, 24));
+        assert_eq!(pane_manager.panes()[1].area, Rect::new(40, 1, 40, 24));
+    }
+}
@@ -4,13 +4,35 @@
 //! including creation, switching, and closing tabs.
 
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Style, Color},
-    widgets::{Block, Borders, Paragraph, Tabs},
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Tabs},
     Frame,
 };
 use std::collections::HashMap;
 
+/// Color and icon marking a tab, such as red for a production shell
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TabStyle {
+    /// Color of the tab's name and input border
+    pub color: Option<Color>,
+    /// Icon shown before the tab's name
+    pub icon: Option<String>,
+    /// Whether the style was set by hand, so color rules leave it alone
+    pub manual: bool,
+}
+
+impl TabStyle {
+    /// Get the tab's label: its icon, if any, and its name
+    pub fn label(&self, name: &str) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, name),
+            None => name.to_string(),
+        }
+    }
+}
+
 /// Represents a tab in the terminal UI
 #[derive(Debug, Clone)]
 pub struct Tab {
@@ -20,12 +42,18 @@ pub struct Tab {
     pub name: String,
     /// Index of the tab (for ordering)
     pub index: usize,
+    /// Model used for AI commands in this tab, if not the default
+    pub model: Option<String>,
+    /// Persona whose system prompt AI commands in this tab use, if any
+    pub persona: Option<String>,
+    /// Color and icon marking the tab
+    pub style: TabStyle,
 }
 
 impl Tab {
     /// Create a new tab
     pub fn new(id: usize, name: String, index: usize) -> Self {
-        Self { id, name, index }
+        Self { id, name, index, model: None, persona: None, style: TabStyle::default() }
     }
 }
 
@@ -171,6 +199,36 @@ impl TabManager {
         }
     }
 
+    /// Set the model used for AI commands in a tab, `None` meaning the default
+    pub fn set_tab_model(&mut self, tab_id: usize, model: Option<String>) -> Result<(), &'static str> {
+        if let Some(tab) = self.tabs.get_mut(&tab_id) {
+            tab.model = model;
+            Ok(())
+        } else {
+            Err("Tab not found")
+        }
+    }
+
+    /// Set the persona used for AI commands in a tab, `None` meaning none
+    pub fn set_tab_persona(&mut self, tab_id: usize, persona: Option<String>) -> Result<(), &'static str> {
+        if let Some(tab) = self.tabs.get_mut(&tab_id) {
+            tab.persona = persona;
+            Ok(())
+        } else {
+            Err("Tab not found")
+        }
+    }
+
+    /// Set the color and icon marking a tab
+    pub fn set_tab_style(&mut self, tab_id: usize, style: TabStyle) -> Result<(), &'static str> {
+        if let Some(tab) = self.tabs.get_mut(&tab_id) {
+            tab.style = style;
+            Ok(())
+        } else {
+            Err("Tab not found")
+        }
+    }
+
     /// Get a list of all tabs, sorted by index
     pub fn tabs(&self) -> Vec<&Tab> {
         let mut tabs: Vec<&Tab> = self.tabs.values().collect();
@@ -180,7 +238,13 @@ impl TabManager {
 
     /// Render the tab bar
     pub fn render(&self, f: &mut Frame, area: Rect) {
-        let tabs: Vec<String> = self.tabs().iter().map(|tab| tab.name.clone()).collect();
+        let tabs: Vec<Line> = self.tabs()
+            .iter()
+            .map(|tab| {
+                let style = tab.style.color.map_or(Style::default(), |color| Style::default().fg(color));
+                Line::from(Span::styled(tab.style.label(&tab.name), style))
+            })
+            .collect();
         let active_index = self.active_tab()
             .and_then(|active_tab| {
                 self.tabs().iter().position(|tab| tab.id == active_tab.id)
@@ -190,13 +254,20 @@ impl TabManager {
         let tabs_widget = Tabs::new(tabs)
             .block(Block::default().borders(Borders::ALL))
             .style(Style::default().fg(Color::White))
-            .highlight_style(Style::default().fg(Color::Yellow))
+            // Bold and underlined so the active tab does not rely on color
+            .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
             .select(active_index);
         
         f.render_widget(tabs_widget, area);
     }
 }
 
+impl Default for TabManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,4 +345,24 @@ mod tests {
         // Try to rename a non-existent tab
         assert!(tab_manager.rename_tab(999, "Non-existent".to_string()).is_err());
     }
+
+    #[test]
+    fn test_tab_model() {
+        let mut tab_manager = TabManager::new();
+        let tab_id = tab_manager.create_tab(Some("New Tab".to_string()));
+        
+        assert!(tab_manager.set_tab_model(tab_id, Some("phi3".to_string())).is_ok());
+        assert_eq!(tab_manager.active_tab().unwrap().model.as_deref(), Some("phi3"));
+        assert_eq!(tab_manager.tabs.get(&0).unwrap().model, None);
+        
+        assert!(tab_manager.set_tab_model(999, None).is_err());
+        
+        let style = TabStyle { color: Some(Color::Red), icon: Some("🔥".to_string()), manual: true };
+        assert!(tab_manager.set_tab_style(tab_id, style).is_ok());
+        assert_eq!(tab_manager.active_tab().unwrap().style.label("prod"), "🔥 prod");
+        
+        assert!(tab_manager.set_tab_persona(tab_id, Some("terse".to_string())).is_ok());
+        assert_eq!(tab_manager.active_tab().unwrap().persona.as_deref(), Some("terse"));
+        assert_eq!(tab_manager.tabs.get(&0).unwrap().persona, None);
+    }
 }
\ No newline at end of file
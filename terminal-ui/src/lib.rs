@@ -5,7 +5,7 @@
 // Existing imports
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -14,29 +14,80 @@ use ratatui::{
     layout::Rect,
     style::{Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame, Terminal,
 };
 use std::{
-    io::{self, Stdout},
-    time::Duration,
+    collections::VecDeque,
+    io::{self, Stdout, Write as _},
+    time::{Duration, Instant},
 };
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
 
-use terminal_emulator::{PtyExecutor, CommandBlock, BlockState, CommandHistory};
+use terminal_emulator::{PtyExecutor, CommandBlock, BlockState, BlockView, CommandHistory, FileLock, FileOperation, FrameStore, ImpactConfig, OutputSummary, SafeDelete, SandboxConfig, ScrollbackConfig, SessionBlock, Snapshot, Store, UsageRecord};
+use terminal_emulator::{impact, sandbox, RemoteHost, TagFilter};
 // Add ollama-client import
-use ollama_client::{OllamaClient, OllamaRequest};
-// Add futures_util import
-use futures_util::StreamExt;
+use ollama_client::{BackendConfig, BackendKind, ChatBackend, ChatMessage, ChatRequest, ChatResponse, OllamaClient, PullProgress};
+use ollama_client::error::OllamaError;
 
 // New imports for our UI/UX improvements
 use layout::manager::LayoutManager;
 use layout::pane::PaneManager;
 use layout::pane::SplitOrientation;
 use layout::tab::TabManager;
-use widgets::{CommandPalette, Command, ConfirmationModal, CommandBlock as UICommandBlock};
-use theme::ThemeManager;
-// Import our new markdown renderer
-use markdown_renderer::render_markdown;
+use widgets::{Action, AgentPanel, CommandPalette, Command, CommitEditor, ConfirmationModal, CopyMode, DiffReview, FileBrowserPanel, HintLabels, ModalButton, ModelPicker, ModelsView, PathPrompt, LockScreen, PlaceholderPopup, PersonaPicker, ProcessView, PromptPreview, PullStatus, ResourcePanel, ScrubReview, TableView, JsonViewer, LogViewer, ThemePicker, Toast, ContextPanel, ChatPanel, PinsPanel};
+use theme::{AccessibilityConfig, Signal, ThemeManager};
+use privacy::{Artifact, ArtifactKind, PendingArtifact, RedactionConfig, Redactor, Scrubber};
+use persistence::LlmLogEntry;
+use backup::{BackupPaths, Section};
+use exporter::{ExportFormat, Transcript};
+use aliases::{Alias, Aliases};
+use bookmarks::Bookmarks;
+use line_editor::LineEditor;
+use workflows::{WorkflowRun, Workflows};
+use persona::{Persona, PersonaManager};
+use stats::{RequestStats, SessionStats};
+use startup::{Lazy, StartupProfile};
+use capabilities::Capability;
+use keymap::{Keymap, KeymapConfig, Motion};
+use offline::{OfflineConfig, OfflineMode};
+use tools::{ToolCall, ToolOutput, ToolProvider, MAX_TOOL_ROUNDS};
+use processors::{OutputProcessor, ProcessorRegistry, TableProcessor};
+use agent::{Agent, AgentConfig, AgentStatus};
+use prompt_guard::{ContextPiece, GuardConfig, PromptDraft};
+use share::{ShareConfig, Upload};
+use tab_colors::{TabColorConfig, TabColorRules};
+use idle_lock::{IdleLock, LockConfig};
+use snippets::CodeSnippet;
+use trust::{TrustConfig, TrustLevel, TrustStore};
+use intent_router::{IntentRouter, RouterConfig};
+use placeholders::PlaceholderPrompt;
+use project::{Project, ProjectConfig, ProjectInterview, ProjectScan};
+use project_memory::ProjectMemory;
+use monitor::ResourceMonitor;
+use processes::{KillSignal, ProcessTable};
+use file_browser::{FileBrowser, FilePreview};
+use pins::Pins;
+use tables::Table;
+use logs::Level;
+use bench::Bench;
+use server_health::ServerHealth;
+use images::{Image, ImageConfig, ImagePlacement, ImageSupport};
+use hints::{HintAction, HintMode, Target, TargetKind};
+use edits::ProposedEdit;
+use git::GitTracker;
+use custom_commands::CustomCommand;
+use plugins::{Effect, PluginConfig, PluginHost};
+use scripting::{ScriptEffect, Scripts};
+use hooks::{HookConfig, HookContext};
+use templates::Templates;
+use file_index::{Chunk, EmbeddedBatch, IndexConfig, IndexPlan};
+use summaries::{SummaryConfig, Summarized};
+use corrections::CorrectionConfig;
+use error_kb::{ErrorSignature, PendingFix};
+use context_set::{ContextItem, ContextSet};
+use chat_pane::{ChatPane, Conversation};
 
 /// Application mode
 #[derive(Debug, Clone)]
@@ -61,13 +112,35 @@ pub enum UIState {
     Normal,
     CommandPalette,
     ConfirmationModal,
+    ThemePicker,
+    ScrubReview,
+    ExportPrompt,
+    Models,
+    ModelPicker,
+    PersonaPicker,
+    PersonaEditor,
+    PromptPreview,
+    PlaceholderPrompt,
+    ProjectInterview,
+    Processes,
+    FileBrowser,
+    DiffReview,
+    CommitEditor,
+    CopyMode,
+    Hints,
+    Pins,
+    Table,
+    Json,
+    Logs,
 }
 
+/// A processed view for the block with the given ID
+type ProcessedView = (uuid::Uuid, BlockView);
+
 /// Main terminal session struct
 pub struct TerminalSession {
     pty_executor: PtyExecutor,
-    command_blocks: Vec<CommandBlock>,
-    input: String,
+    input: LineEditor,
     mode: AppMode,
     should_quit: bool,
     is_generating: bool,
@@ -75,7 +148,9 @@ pub struct TerminalSession {
     history_index: Option<usize>,
     // Add ollama_client field
     ollama_client: OllamaClient,
-    scroll_offset: u16,
+    backend: std::sync::Arc<dyn ChatBackend>,
+    backend_kind: BackendKind,
+    system_prompt: Option<String>,
     // New fields for UI/UX improvements
     layout_manager: LayoutManager,
     pane_manager: PaneManager,
@@ -85,38 +160,322 @@ pub struct TerminalSession {
     ui_state: UIState,
     input_before_history: String,
     confirmation_modal: Option<ConfirmationModal>,
+    theme_picker: Option<ThemePicker>,
+    scrubber: Scrubber,
+    /// Masks secrets in command output sent to the AI
+    redactor: Redactor,
+    scrub_review: Option<ScrubReview>,
+    export_prompt: Option<PathPrompt>,
+    llm_log: Lazy<FrameStore<LlmLogEntry>>,
+    store: Option<Store>,
+    instance_id: Option<String>,
+    /// Lock on this instance's scrollback spill directory, held while it runs
+    spill_lock: Option<FileLock>,
+    last_heartbeat: Instant,
+    scrollback: ScrollbackConfig,
+    impact: ImpactConfig,
+    pending_command: Option<String>,
+    /// Placeholder values being asked for before a command runs
+    placeholder_prompt: Option<PlaceholderPrompt>,
+    /// Palette action the placeholder prompt fills in, if it is not for a typed shell command
+    placeholder_action: Option<Action>,
+    /// Palette commands from the configuration, registered again when plugins are reloaded
+    custom_commands: Vec<CustomCommand>,
+    /// WebAssembly plugins and the capabilities granted to them
+    plugins: PluginHost,
+    plugin_config: PluginConfig,
+    /// Lua scripts from the config directory
+    scripts: Scripts,
+    /// Commands queued by scripts and `/ssh`, run one at a time once the terminal is idle
+    queued_commands: VecDeque<String>,
+    /// Message a script is showing briefly
+    toast: Option<Toast>,
+    /// Shell commands run before and after every command block
+    hooks: HookConfig,
+    /// Prompt templates offered by `/template` and the palette
+    templates: Templates,
+    /// Model and sizes of the project index `/ask-project` answers from
+    index_config: IndexConfig,
+    /// Embeddings of a project being computed in the background
+    indexing: Option<Indexing>,
+    /// When long output is summarized, and by which model
+    summary_config: SummaryConfig,
+    /// Whether failed commands get a suggested correction, and from the model too
+    correction_config: CorrectionConfig,
+    /// Channel of summarizing requests answered in the background
+    summaries: (mpsc::UnboundedSender<Summarized>, mpsc::UnboundedReceiver<Summarized>),
+    /// Commands of the latest error explanation, one of which becomes its fix once it succeeds
+    pending_fix: Option<PendingFix>,
+    /// Conversation of the chat pane, beside the panes when shown
+    chat: ChatPane,
+    /// Chat conversations of the tabs other than the active one
+    tab_chats: std::collections::HashMap<usize, Conversation>,
+    /// Rows of the chat pane in view and of its whole conversation, as last drawn
+    chat_view: (usize, usize),
+    /// Secret placeholder values for the next command, set in its environment only
+    command_env: Vec<(String, String)>,
+    /// `.ai-terminal.toml` of the project the working directory is in
+    project: Option<Project>,
+    /// The `/init` interview and the input for the current answer
+    project_interview: Option<(ProjectInterview, PathPrompt)>,
+    /// Project configuration drafted by `/init`, written once approved
+    pending_project: Option<(std::path::PathBuf, ProjectConfig)>,
+    /// `.ai-terminal.md` of the working directory or its repository, sent along with prompts
+    project_memory: Option<ProjectMemory>,
+    /// Fact to append to the project memory file once approved
+    pending_memory: Option<(std::path::PathBuf, String)>,
+    /// Whether the command explained in the popup was approved to run
+    run_explained: bool,
+    /// CPU, memory, disk and GPU usage shown by `/monitor`
+    monitor: ResourceMonitor,
+    /// Process manager opened with `/ps`
+    process_view: Option<ProcessView>,
+    /// Table read from a block's output, opened with `/table`
+    table_view: Option<TableView>,
+    /// JSON output opened with `/json`
+    json_viewer: Option<JsonViewer>,
+    /// Block output followed as a log, opened with `/log`
+    log_viewer: Option<LogViewer>,
+    /// Latest benchmark run with `bench`, for `/bench explain`
+    last_bench: Option<Bench>,
+    pins: Option<Pins>,
+    /// How images in output are drawn
+    image_support: ImageSupport,
+    /// Images on screen after the last render, with the cells the panes drew under them
+    image_cells: Vec<(ImagePlacement, Vec<ratatui::buffer::Cell>)>,
+    /// Images drawn with the terminal's graphics, over their half blocks
+    placed_images: Vec<ImagePlacement>,
+    process_table: ProcessTable,
+    /// Process and signal waiting for confirmation in the process manager
+    pending_kill: Option<(u32, String, KillSignal)>,
+    /// Tree of the working directory, docked while F4 has it open
+    file_browser: Option<FileBrowser>,
+    /// File attached from the browser to the next AI prompt, as a label and context text
+    attached_file: Option<(String, String)>,
+    /// Files and outputs attached to every AI request with `/attach`
+    context: ContextSet,
+    /// File edit proposed by the model, shown as a diff until applied or rejected
+    diff_review: Option<DiffReview>,
+    /// Branch and status of the repository the working directory is in
+    git: GitTracker,
+    /// Commit message drafted by the model, being edited before `git commit`
+    commit_editor: Option<CommitEditor>,
+    /// Frozen view of the focused pane being selected from with the keyboard
+    copy_mode: Option<CopyMode>,
+    /// Labelled URLs and paths on screen, chosen from with the keyboard
+    hint_mode: Option<HintMode>,
+    /// File to open in the editor once the terminal is handed over to it, and the line
+    pending_editor: Option<(std::path::PathBuf, Option<u32>)>,
+    safe_delete: SafeDelete,
+    /// Restrictions for `!sandbox` commands and, if asked, agent commands
+    sandbox: SandboxConfig,
+    /// File operation waiting for confirmation, after its dry run was shown
+    pending_file_op: Option<FileOperation>,
+    models_view: Option<ModelsView>,
+    model_picker: Option<ModelPicker>,
+    model_pull: Option<ModelPull>,
+    pending_model_delete: Option<String>,
+    bookmarks: Bookmarks,
+    aliases: Aliases,
+    workflows: Workflows,
+    workflow_run: Option<WorkflowRun>,
+    /// Columns of the header each tab was drawn in, as start, end and tab ID, for clicks
+    tab_hits: Vec<(u16, u16, usize)>,
+    accessibility: AccessibilityConfig,
+    keymap: Keymap,
+    stats: SessionStats,
+    personas: PersonaManager,
+    persona_picker: Option<PersonaPicker>,
+    persona_editor: Option<PathPrompt>,
+    offline: OfflineMode,
+    /// Checks of whether the model server answers; none once its settings proved invalid
+    server_health: Option<ServerHealth>,
+    tools: Option<Box<dyn ToolProvider>>,
+    processors: ProcessorRegistry,
+    /// Views made by output processors in the background, by block id
+    processed_views: (mpsc::UnboundedSender<ProcessedView>, mpsc::UnboundedReceiver<ProcessedView>),
+    agent: Option<Agent>,
+    agent_config: AgentConfig,
+    prompt_guard: GuardConfig,
+    prompt_preview: Option<PromptPreview>,
+    share_config: ShareConfig,
+    tab_colors: TabColorRules,
+    /// Scrubbed session waiting for confirmation to be uploaded
+    pending_share: Option<PendingArtifact>,
+    /// Privacy screen covering the session when idle or on `/lock`
+    idle_lock: IdleLock,
+    /// Trust marks deciding where AI automation may act
+    trust: TrustStore,
+    /// Routes AI prompts that ask for a system tool to its command
+    router: IntentRouter,
+    /// Code block last copied or run with `/code`, for `/snippet save`
+    last_code: Option<CodeSnippet>,
+    /// Text put on the input line once a built-in command finishes
+    staged_input: Option<String>,
+    startup: StartupProfile,
+}
+
+/// A model pull running in the background
+struct ModelPull {
+    /// Latest progress, for display
+    status: PullStatus,
+    /// Progress updates from the pulling task
+    updates: mpsc::UnboundedReceiver<Result<PullProgress, String>>,
+}
+
+/// Indexing of a project, whose chunks are embedded by a background task
+struct Indexing {
+    /// Directory being indexed
+    root: std::path::PathBuf,
+    /// Start of the keys its chunks are stored under
+    prefix: String,
+    /// Chunks embedded and stored so far
+    done: usize,
+    /// Chunks to embed
+    total: usize,
+    /// Embeddings from the task, a batch at a time
+    updates: mpsc::UnboundedReceiver<EmbeddedBatch>,
 }
 
 impl TerminalSession {
     /// Create a new terminal session
     pub fn new() -> Result<Self> {
+        Self::with_startup_profile(StartupProfile::start())
+    }
+    
+    /// Create a new terminal session, adding its initialization to `startup`
+    pub fn with_startup_profile(mut startup: StartupProfile) -> Result<Self> {
         let terminal_size = crossterm::terminal::size()?;
         let layout_manager = LayoutManager::new(Rect::new(0, 0, terminal_size.0, terminal_size.1));
-        let pane_manager = PaneManager::new(Rect::new(0, 0, terminal_size.0, terminal_size.1));
+        let pane_manager = PaneManager::new(layout_manager.calculate_chat_layout()[1]);
         let tab_manager = TabManager::new();
-        let command_palette = CommandPalette::new();
-        let mut theme_manager = ThemeManager::new();
+        let mut command_palette = CommandPalette::new();
+        let theme_manager = startup.time("themes", || {
+            let mut theme_manager = ThemeManager::new();
+            
+            // Load user themes
+            if let Err(e) = theme_manager.load_user_themes() {
+                // Log the error but don't fail startup
+                tracing::warn!("Failed to load user themes: {:?}", e);
+            }
+            
+            // Restore the theme chosen in a previous session
+            if let Err(e) = theme_manager.load_saved_selection() {
+                tracing::warn!("Failed to restore saved theme: {:?}", e);
+            }
+            theme_manager
+        });
+        
+        let command_history = startup.time("command history", || CommandHistory::new(1000))?; // Max 1000 history entries
+        
+        // Sessions and usage statistics are optional; the terminal works without them
+        let store = startup.time("database", || persistence::open_store()
+            .map_err(|e| tracing::warn!("Failed to open database: {:?}", e))
+            .ok());
+        
+        // Register with the store so other running instances can see this one
+        let instance_id = startup.time("instance registration", || store.as_ref().and_then(|store| {
+            store.register_instance()
+                .map_err(|e| tracing::warn!("Failed to register instance: {:?}", e))
+                .ok()
+        }));
         
-        // Load user themes
-        if let Err(e) = theme_manager.load_user_themes() {
-            // Log the error but don't fail startup
-            tracing::warn!("Failed to load user themes: {:?}", e);
+        // Bookmarks live in the store; without it they last for the session
+        let bookmarks = startup.time("bookmarks", || store.as_ref()
+            .and_then(|store| Bookmarks::from_store(store)
+                .map_err(|e| tracing::warn!("Failed to load bookmarks: {:?}", e))
+                .ok())
+            .unwrap_or_default());
+        
+        // Aliases are run as `,name` and offered in the command palette
+        let aliases = startup.time("aliases", || {
+            let mut aliases = Aliases::new();
+            if let Err(e) = aliases.load() {
+                tracing::warn!("Failed to load aliases: {:?}", e);
+            }
+            aliases
+        });
+        command_palette.set_category("Aliases", aliases::palette_commands(&aliases));
+        if let Ok(recent) = std::fs::read_to_string(recent_commands_path()) {
+            command_palette.set_recent(recent.lines().map(str::to_string).collect());
+        }
+        
+        let workflows = startup.time("workflows", || {
+            let mut workflows = Workflows::new();
+            if let Err(e) = workflows.load() {
+                tracing::warn!("Failed to load workflows: {:?}", e);
+            }
+            workflows
+        });
+        command_palette.set_category("Workflows", workflows.palette_commands());
+        
+        // Saved snippets are offered in the command palette
+        if let Some(saved) = store.as_ref().and_then(|store| store.snippets()
+            .map_err(|e| tracing::warn!("Failed to load snippets: {:?}", e))
+            .ok())
+        {
+            command_palette.set_category("Snippets", snippets::palette_commands(&saved));
+        }
+        if let Some(known) = store.as_ref().and_then(|store| store.known_errors()
+            .map_err(|e| tracing::warn!("Failed to load known errors: {:?}", e))
+            .ok())
+        {
+            command_palette.set_category("Known Errors", error_kb::palette_commands(&known));
+        }
+        if let Some(conversations) = store.as_ref().and_then(|store| store.conversations()
+            .map_err(|e| tracing::warn!("Failed to load conversations: {:?}", e))
+            .ok())
+        {
+            command_palette.set_category(chat_history::PALETTE_CATEGORY, chat_history::palette_commands(&conversations));
         }
         
-        let command_history = CommandHistory::new(1000)?; // Max 1000 history entries
+        let trust = startup.time("trust", || TrustStore::load()
+            .map_err(|e| tracing::warn!("Failed to load directory trust: {:?}", e))
+            .unwrap_or_default());
+        
+        let personas = startup.time("personas", || {
+            let mut personas = PersonaManager::new();
+            if let Err(e) = personas.load() {
+                tracing::warn!("Failed to load personas: {:?}", e);
+            }
+            personas
+        });
+        
+        let pty_executor = startup.time("shell", PtyExecutor::new)?;
+        let project = startup.time("project", || Project::find(std::path::Path::new(pty_executor.working_dir()))
+            .map_err(|e| tracing::warn!("Failed to load the project configuration: {:?}", e))
+            .ok()
+            .flatten());
+        if let Some(project) = &project {
+            command_palette.set_category("Project", project.palette_commands());
+        }
+        let project_memory = ProjectMemory::find(std::path::Path::new(pty_executor.working_dir()))
+            .map_err(|e| tracing::warn!("Failed to load the project memory: {:?}", e))
+            .ok()
+            .flatten();
+        let ollama_client = startup.time("Ollama client", OllamaClient::new)?;
+        
+        // Instances that did not exit cleanly leave their spilled scrollback behind
+        let spill_lock = startup.time("scrollback cleanup", || {
+            terminal_emulator::scrollback::prune_spill_dirs(&persistence::data_dir().join("scrollback"));
+            FileLock::exclusive(&spill_dir(instance_id.as_deref()))
+                .map_err(|e| tracing::warn!("Failed to lock the scrollback directory: {:?}", e))
+                .ok()
+        });
         
         Ok(Self {
-            pty_executor: PtyExecutor::new()?,
-            command_blocks: Vec::new(),
-            input: String::new(),
+            pty_executor,
+            input: LineEditor::new(),
             mode: AppMode::Chat,
             should_quit: false,
             is_generating: false,
             command_history,
             history_index: None,
             // Add ollama_client initialization
-            ollama_client: OllamaClient::new()?,
-            scroll_offset: 0,
+            backend: std::sync::Arc::new(ollama_client.clone()),
+            backend_kind: BackendKind::Ollama,
+            ollama_client,
+            system_prompt: None,
             // New fields
             layout_manager,
             pane_manager,
@@ -126,14 +485,452 @@ impl TerminalSession {
             ui_state: UIState::Normal,
             input_before_history: String::new(),
             confirmation_modal: None,
+            theme_picker: None,
+            scrubber: Scrubber::new(),
+            redactor: Redactor::default(),
+            scrub_review: None,
+            export_prompt: None,
+            // The LLM log is only needed once an AI command runs
+            llm_log: Lazy::default(),
+            store,
+            instance_id,
+            spill_lock,
+            last_heartbeat: Instant::now(),
+            scrollback: ScrollbackConfig::default(),
+            impact: ImpactConfig::default(),
+            pending_command: None,
+            placeholder_prompt: None,
+            placeholder_action: None,
+            custom_commands: Vec::new(),
+            plugins: PluginHost::default(),
+            plugin_config: PluginConfig::default(),
+            scripts: Scripts::default(),
+            queued_commands: VecDeque::new(),
+            toast: None,
+            hooks: HookConfig::default(),
+            templates: Templates::default(),
+            index_config: IndexConfig::default(),
+            indexing: None,
+            summary_config: SummaryConfig::default(),
+            correction_config: CorrectionConfig::default(),
+            summaries: mpsc::unbounded_channel(),
+            pending_fix: None,
+            chat: ChatPane::default(),
+            tab_chats: std::collections::HashMap::new(),
+            chat_view: (0, 0),
+            command_env: Vec::new(),
+            project,
+            project_interview: None,
+            pending_project: None,
+            project_memory,
+            pending_memory: None,
+            run_explained: false,
+            monitor: ResourceMonitor::default(),
+            process_view: None,
+            table_view: None,
+            json_viewer: None,
+            log_viewer: None,
+            last_bench: None,
+            pins: None,
+            image_support: ImageSupport::detect(),
+            image_cells: Vec::new(),
+            placed_images: Vec::new(),
+            process_table: ProcessTable::default(),
+            pending_kill: None,
+            file_browser: None,
+            attached_file: None,
+            context: ContextSet::default(),
+            diff_review: None,
+            git: GitTracker::default(),
+            commit_editor: None,
+            copy_mode: None,
+            hint_mode: None,
+            pending_editor: None,
+            safe_delete: SafeDelete::new(),
+            sandbox: SandboxConfig::default(),
+            pending_file_op: None,
+            models_view: None,
+            model_picker: None,
+            model_pull: None,
+            pending_model_delete: None,
+            bookmarks,
+            aliases,
+            workflows,
+            workflow_run: None,
+            tab_hits: Vec::new(),
+            accessibility: AccessibilityConfig::default(),
+            keymap: Keymap::default(),
+            stats: SessionStats::new(),
+            personas,
+            persona_picker: None,
+            persona_editor: None,
+            offline: OfflineMode::default(),
+            server_health: Some(ServerHealth::new()),
+            tools: None,
+            processors: ProcessorRegistry::with_defaults(),
+            processed_views: mpsc::unbounded_channel(),
+            agent: None,
+            agent_config: AgentConfig::default(),
+            prompt_guard: GuardConfig::default(),
+            prompt_preview: None,
+            share_config: ShareConfig::default(),
+            idle_lock: IdleLock::default(),
+            trust,
+            router: IntentRouter::new(RouterConfig::default()),
+            last_code: None,
+            staged_input: None,
+            tab_colors: TabColorRules::default(),
+            pending_share: None,
+            startup,
         })
     }
     
+    /// Configure the model and optional system prompt used for AI commands
+    pub fn configure_ai(&mut self, model: String, system_prompt: Option<String>) {
+        self.ollama_client.model = model;
+        self.system_prompt = system_prompt;
+    }
+    
+    /// Offer prompts from the configuration as personas, below saved personas of the same name
+    pub fn configure_personas<I: IntoIterator<Item = (String, String)>>(&mut self, prompts: I) {
+        for (name, prompt) in prompts {
+            self.personas.add_default(Persona::new(name, prompt.trim()));
+        }
+    }
+    
+    /// Choose the server AI commands are sent to
+    pub fn configure_backend(&mut self, config: &BackendConfig) -> Result<()> {
+        let config = config.resolve()?;
+        self.backend = config.build()?;
+        self.backend_kind = config.kind;
+        // Model management talks to Ollama directly, so keep it on the same server and connection
+        if config.kind == BackendKind::Ollama {
+            let model = std::mem::take(&mut self.ollama_client.model);
+            self.ollama_client = config.ollama_client()?;
+            self.ollama_client.model = model;
+        }
+        Ok(())
+    }
+    
+    /// Turn AI features off for a server that could not be set up, leaving a plain shell
+    ///
+    /// Checking the server again cannot help until its settings are fixed, so none are made.
+    pub fn disable_backend(&mut self, reason: String) {
+        self.offline.set_unreachable(Some(reason));
+        self.server_health = None;
+    }
+    
+    /// Get the model used for AI commands in the active tab
+    fn active_model(&self) -> &str {
+        self.tab_manager.active_tab()
+            .and_then(|tab| tab.model.as_deref())
+            .unwrap_or(&self.ollama_client.model)
+    }
+    
+    /// Get the persona used for AI commands in the active tab, if any
+    fn active_persona(&self) -> Option<&Persona> {
+        self.tab_manager.active_tab()
+            .and_then(|tab| tab.persona.as_deref())
+            .and_then(|name| self.personas.get(name))
+    }
+    
+    /// Get the system prompt for AI commands in the active tab
+    ///
+    /// The tab's persona replaces the configured system prompt.
+    fn active_system_prompt(&self) -> Option<&str> {
+        self.active_persona()
+            .map(|persona| persona.prompt.as_str())
+            .or(self.system_prompt.as_deref())
+    }
+    
+    /// Configure how much command output is kept in memory
+    pub fn configure_scrollback(&mut self, scrollback: ScrollbackConfig) {
+        self.scrollback = scrollback;
+    }
+    
+    /// Configure confirmation and snapshots for commands that modify files
+    pub fn configure_impact(&mut self, impact: ImpactConfig) {
+        self.impact = impact;
+    }
+    
+    /// Rebind scrollback motions; motions the configuration leaves out keep their default keys
+    pub fn configure_keys(&mut self, config: &KeymapConfig) -> Result<()> {
+        self.keymap = Keymap::from_config(config)?;
+        for (id, motion) in [("scroll_up", Motion::HalfPageUp), ("scroll_down", Motion::HalfPageDown), ("jump_last_error", Motion::LastError)] {
+            self.command_palette.set_key(id, &self.keymap.keys(motion));
+        }
+        Ok(())
+    }
+    
+    /// Apply accessibility settings, such as a palette for color vision deficiency
+    pub fn configure_accessibility(&mut self, accessibility: AccessibilityConfig) {
+        self.accessibility = accessibility;
+        if let Some(vision) = accessibility.color_vision
+            && let Err(e) = self.theme_manager.switch_theme(vision.theme_name())
+        {
+            tracing::warn!("Failed to apply the {} theme: {:?}", vision.theme_name(), e);
+        }
+    }
+    
+    /// Configure whether the session starts offline and what happens to prompts made offline
+    pub fn configure_offline(&mut self, offline: OfflineConfig) {
+        self.offline = OfflineMode::new(offline);
+    }
+    
+    /// Register output processors from the configuration, tried before the built-in ones
+    pub fn configure_processors(&mut self, config: &std::collections::BTreeMap<String, String>) -> Result<()> {
+        self.processors = ProcessorRegistry::from_config(config)?;
+        Ok(())
+    }
+    
+    /// Run `processor` on the output of commands matching the regular expression `pattern`
+    pub fn register_processor(&mut self, pattern: &str, processor: std::sync::Arc<dyn OutputProcessor>) -> Result<()> {
+        self.processors.register(pattern, processor)
+    }
+    
+    /// Offer palette commands from the configuration, along with those of the plugin files
+    pub fn configure_commands(&mut self, commands: &[CustomCommand]) -> Result<()> {
+        // Checked here so a mistake in the configuration stops startup rather than a later reload
+        custom_commands::palette_commands(commands)?;
+        self.custom_commands = commands.to_vec();
+        self.reload_commands()?;
+        Ok(())
+    }
+    
+    /// Add a command to the palette, replacing any with the same id
+    ///
+    /// A command with an [`Action`] other than [`Action::Builtin`] runs
+    /// without the session knowing its id.
+    pub fn register_command(&mut self, command: Command) {
+        self.command_palette.register(command);
+    }
+    
+    /// Load the WebAssembly plugins, granting them the capabilities the configuration allows
+    pub fn configure_plugins(&mut self, config: PluginConfig) {
+        self.plugin_config = config;
+        if let Err(e) = self.reload_plugins() {
+            self.push_message("Plugins", &format!("{:#}", e));
+        }
+    }
+    
+    /// Load the plugins again, returning how many were loaded
+    fn reload_plugins(&mut self) -> Result<usize> {
+        let (host, problems) = match plugins::plugins_dir() {
+            Some(dir) => PluginHost::load(&dir, &self.plugin_config),
+            None => (PluginHost::default(), Vec::new()),
+        };
+        self.plugins = host;
+        self.command_palette.unregister(plugins::PALETTE_PREFIX);
+        for command in self.plugins.palette_commands() {
+            self.command_palette.register(command);
+        }
+        let effects = self.plugins.dispatch(&plugins::Event::Load);
+        self.apply_plugin_effects(effects);
+        if !problems.is_empty() {
+            anyhow::bail!("Some plugins could not be loaded:\n{}", problems.join("\n"));
+        }
+        Ok(self.plugins.plugins().len())
+    }
+    
+    /// Show the messages plugins sent and insert their text, returning the commands they asked to run
+    fn apply_plugin_effects(&mut self, effects: Vec<Effect>) -> Vec<String> {
+        let mut commands = Vec::new();
+        for effect in effects {
+            match effect {
+                Effect::Message(plugin, message) => self.push_message(&format!("🧩 {}", plugin), &message),
+                Effect::Insert(text) => self.input.set(text),
+                Effect::Run(command) => commands.push(command),
+            }
+        }
+        commands
+    }
+    
+    /// Get the host the focused pane's commands run on, if it is connected to one
+    fn focused_remote(&self) -> Option<&RemoteHost> {
+        self.pane_manager.focused_pane().and_then(|pane| pane.remote.as_ref())
+    }
+    
+    /// Connect a new pane to a host, show the focused pane's host, or make it local again
+    fn ssh(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] => Ok(match self.focused_remote() {
+                Some(remote) => format!("Commands in this pane run on {}", remote.location()),
+                None => "This pane runs commands locally. /ssh <host> opens a pane on a host.".to_string(),
+            }),
+            ["off"] => {
+                let pane = self.pane_manager.focused_pane_mut().ok_or_else(|| anyhow::anyhow!("there is no focused pane"))?;
+                let remote = pane.remote.take().ok_or_else(|| anyhow::anyhow!("this pane is not connected to a host"))?;
+                Ok(format!("Disconnected from {}; commands in this pane run locally again", remote.host))
+            }
+            [host] => {
+                let remote = RemoteHost::new(host)?;
+                self.pane_manager.split_focused_pane(SplitOrientation::Horizontal).map_err(anyhow::Error::msg)?;
+                let pane = self.pane_manager.focused_pane_mut().ok_or_else(|| anyhow::anyhow!("there is no focused pane"))?;
+                pane.command_blocks.clear();
+                pane.remote = Some(remote);
+                // Connect straight away, showing which system the host runs
+                self.queued_commands.push_back("uname -srm".to_string());
+                Ok(format!("Commands in the new pane run on {} over SSH; /ssh off makes it local again", host))
+            }
+            _ => anyhow::bail!("usage: /ssh <host> | /ssh off"),
+        }
+    }
+    
+    /// Set the shell commands run before and after every command block
+    pub fn configure_hooks(&mut self, hooks: HookConfig) {
+        self.hooks = hooks;
+    }
+    
+    /// Offer the built-in prompt templates and the configured ones, which replace those of the same name
+    pub fn configure_templates(&mut self, templates: &std::collections::BTreeMap<String, String>) {
+        self.templates = Templates::new(templates);
+        self.command_palette.set_category("Templates", self.templates.palette_commands());
+    }
+    
+    /// Set the model and sizes of the project index
+    pub fn configure_index(&mut self, config: IndexConfig) {
+        self.index_config = config;
+    }
+    
+    /// Set when long output is summarized, and by which model
+    pub fn configure_summaries(&mut self, config: SummaryConfig) {
+        self.summary_config = config;
+    }
+    
+    /// Set whether failed commands get a suggested correction
+    pub fn configure_corrections(&mut self, config: CorrectionConfig) {
+        self.correction_config = config;
+    }
+    
+    /// Run the Lua scripts in the config directory
+    pub fn configure_scripts(&mut self) {
+        if let Err(e) = self.reload_scripts() {
+            self.push_message("Scripts", &format!("{:#}", e));
+        }
+    }
+    
+    /// Run the scripts again in a fresh Lua state, returning how many were loaded
+    fn reload_scripts(&mut self) -> Result<usize> {
+        let (scripts, problems) = match scripting::scripts_dir() {
+            Some(dir) => Scripts::load_dir(&dir),
+            None => (Scripts::default(), Vec::new()),
+        };
+        self.scripts = scripts;
+        self.command_palette.unregister(scripting::PALETTE_PREFIX);
+        for command in self.scripts.palette_commands() {
+            self.command_palette.register(command);
+        }
+        self.apply_script_effects(Ok(()));
+        if !problems.is_empty() {
+            anyhow::bail!("Some scripts failed:\n{}", problems.join("\n"));
+        }
+        Ok(self.scripts.loaded().len())
+    }
+    
+    /// Act on what scripts asked for, reporting the error of the script that failed
+    fn apply_script_effects(&mut self, result: Result<()>) {
+        if let Err(e) = result {
+            self.push_message("Scripts", &format!("{:#}", e));
+        }
+        for effect in self.scripts.take_effects() {
+            match effect {
+                ScriptEffect::Run(command) => self.queued_commands.push_back(command),
+                ScriptEffect::Insert(text) => self.input.set(text),
+                ScriptEffect::Toast(message) => self.toast = Some(Toast::new(&message)),
+            }
+        }
+    }
+    
+    /// Register the configured commands and read the plugin files again, returning how many commands there are
+    fn reload_commands(&mut self) -> Result<usize> {
+        let mut commands = custom_commands::palette_commands(&self.custom_commands)?;
+        if let Some(dir) = custom_commands::plugins_dir() {
+            commands.extend(custom_commands::load_plugins(&dir)?);
+        }
+        self.command_palette.unregister(custom_commands::PALETTE_PREFIX);
+        let count = commands.len();
+        for command in commands {
+            self.command_palette.register(command);
+        }
+        Ok(count)
+    }
+    
+    /// Configure when AI requests are previewed and how long responses may be
+    pub fn configure_prompt_guard(&mut self, guard: GuardConfig) {
+        self.prompt_guard = guard;
+    }
+    
+    /// Configure the command patterns that color the tab they run in
+    pub fn configure_tab_colors(&mut self, config: &TabColorConfig) -> Result<()> {
+        self.tab_colors = TabColorRules::from_config(config)?;
+        Ok(())
+    }
+    
+    /// Configure the restrictions sandboxed commands run under
+    pub fn configure_sandbox(&mut self, config: SandboxConfig) {
+        self.sandbox = config;
+    }
+    
+    /// Configure the idle lock, reading its passphrase from the environment
+    pub fn configure_idle_lock(&mut self, config: LockConfig) -> Result<()> {
+        let passphrase = config.passphrase();
+        // Commands would otherwise inherit the passphrase, and `env` would print it
+        if let Some(var) = &config.passphrase_env {
+            self.pty_executor.hide_env(var);
+        }
+        self.idle_lock = IdleLock::new(config, passphrase.as_deref())?;
+        Ok(())
+    }
+    
+    /// Configure secret redaction, adding the user-defined patterns to the export scrubber too
+    pub fn configure_redaction(&mut self, config: &RedactionConfig) -> Result<()> {
+        self.redactor = Redactor::from_config(config)?;
+        for rule in config.rules()? {
+            self.scrubber.add_rule(rule);
+        }
+        Ok(())
+    }
+    
+    /// Configure how directories without a trust mark are treated
+    pub fn configure_trust(&mut self, config: TrustConfig) {
+        self.trust.configure(config);
+    }
+    
+    /// Route AI prompts to system tools as configured in the `[router]` section
+    pub fn configure_router(&mut self, config: RouterConfig) {
+        self.router = IntentRouter::new(config);
+    }
+    
+    /// Check whether AI automation may act in the working directory
+    fn is_trusted(&self) -> bool {
+        self.trust.is_trusted(std::path::Path::new(self.pty_executor.working_dir()))
+    }
+    
+    /// Configure where `/share` uploads sessions
+    pub fn configure_share(&mut self, config: ShareConfig) {
+        self.share_config = config;
+    }
+    
+    /// Configure how images in output are drawn, overriding what the terminal was detected to support
+    pub fn configure_images(&mut self, config: ImageConfig) {
+        self.image_support = config.support();
+    }
+    
+    /// Configure the step budget and the commands agents run without asking
+    pub fn configure_agent(&mut self, config: AgentConfig) {
+        self.agent_config = config;
+    }
+    
+    /// Offer tools, such as those of connected MCP servers, to the model
+    pub fn configure_tools(&mut self, tools: Box<dyn ToolProvider>) {
+        self.tools = Some(tools);
+    }
+    
     /// Setup the terminal for the TUI
     pub fn setup_terminal(&mut self) -> Result<Terminal<CrosstermBackend<Stdout>>> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
         
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
@@ -141,7 +938,7 @@ impl TerminalSession {
         // Update layout manager with terminal size
         let terminal_size = crossterm::terminal::size()?;
         self.layout_manager.update_size(Rect::new(0, 0, terminal_size.0, terminal_size.1));
-        self.pane_manager.resize(Rect::new(0, 0, terminal_size.0, terminal_size.1));
+        self.pane_manager.resize(self.layout_manager.calculate_chat_layout()[1]);
         
         Ok(terminal)
     }
@@ -152,7 +949,8 @@ impl TerminalSession {
         execute!(
             terminal.backend_mut(),
             LeaveAlternateScreen,
-            DisableMouseCapture
+            DisableMouseCapture,
+            DisableBracketedPaste
         )?;
         terminal.show_cursor()?;
         Ok(())
@@ -168,156 +966,731 @@ impl TerminalSession {
         
         loop {
             // Render the UI
-            terminal.draw(|f| self.render(f))?;
+            let frame = terminal.draw(|f| self.render(f))?;
+            let images = self.uncovered_images(frame.buffer);
+            self.draw_images(&mut terminal, images)?;
+            if self.startup.ready_after().is_none() {
+                self.startup.mark_ready();
+                // Load syntax definitions off the UI thread before the first code block needs them
+                #[cfg(feature = "syntax-highlighting")]
+                std::thread::spawn(syntax_highlighter::warm_up);
+            }
             
-            // Handle events
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    match self.mode {
-                        AppMode::Chat => self.handle_chat_key(key).await?,
-                        AppMode::Help => self.handle_help_key(key).await?,
+            // Handle events; with reduced motion, background updates redraw less often
+            if event::poll(self.accessibility.frame_interval())? {
+                match event::read()? {
+                    // Nothing reaches the session while it is locked
+                    Event::Key(key) if self.idle_lock.is_locked() => self.handle_lock_key(key),
+                    Event::Mouse(_) | Event::Paste(_) if self.idle_lock.is_locked() => {}
+                    Event::Key(key) => {
+                        self.idle_lock.touch();
+                        match self.mode {
+                            AppMode::Chat => self.handle_chat_key(key).await?,
+                            AppMode::Help => self.handle_help_key(key).await?,
+                        }
                     }
-                    
-                    if self.should_quit {
-                        break;
+                    Event::Mouse(mouse) if matches!(self.mode, AppMode::Chat) && matches!(self.ui_state, UIState::Normal) => {
+                        self.idle_lock.touch();
+                        self.handle_mouse(mouse);
                     }
+                    Event::Paste(text) => {
+                        self.idle_lock.touch();
+                        self.handle_paste(text).await?;
+                    }
+                    _ => {}
+                }
+                
+                if self.should_quit {
+                    break;
                 }
+                self.run_pending_editor(&mut terminal)?;
+            }
+            
+            self.idle_lock.check();
+            self.heartbeat();
+            self.monitor.tick(std::path::Path::new(self.pty_executor.working_dir()));
+            self.git.tick(std::path::Path::new(self.pty_executor.working_dir()));
+            if matches!(self.ui_state, UIState::Processes) && self.process_table.is_stale() {
+                self.refresh_processes();
+            }
+            self.poll_server_health().await;
+            self.poll_model_pull().await;
+            self.poll_indexing();
+            self.poll_processed_views();
+            self.sync_log_viewer();
+            self.poll_summaries();
+            self.poll_agent().await;
+            self.poll_workflow().await;
+            self.poll_queued_commands().await;
+            if self.toast.as_ref().is_some_and(Toast::is_expired) {
+                self.toast = None;
             }
         }
         
         // Restore terminal
         self.restore_terminal(&mut terminal)?;
+        
+        // Keep this session's blocks so they can be restored next time
+        if let Err(e) = self.save_session() {
+            tracing::warn!("Failed to save session: {:?}", e);
+        }
+        
+        // Output spilled from the scrollback is only needed while running
+        let _ = std::fs::remove_dir_all(self.spill_dir());
+        if let Some(lock) = self.spill_lock.take() {
+            let path = lock.path().to_path_buf();
+            drop(lock);
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+    
+    /// Put pasted text on the input line, offering to clean up a shell snippet
+    ///
+    /// Outside the input line, such as in prompts and pickers, the text is
+    /// typed in as the terminal sent it before bracketed paste.
+    async fn handle_paste(&mut self, text: String) -> Result<()> {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if !matches!(self.mode, AppMode::Chat) || !matches!(self.ui_state, UIState::Normal) {
+            for c in text.chars() {
+                let code = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+                match self.mode {
+                    AppMode::Chat => self.handle_chat_key(KeyEvent::new(code, KeyModifiers::NONE)).await?,
+                    AppMode::Help => self.handle_help_key(KeyEvent::new(code, KeyModifiers::NONE)).await?,
+                }
+            }
+            return Ok(());
+        }
+        self.input.insert_str(&text);
+        let cleanup = smart_paste::clean(&text);
+        if !self.chat.is_focused() && cleanup.changed() {
+            self.toast = Some(Toast::new(&format!(
+                "Pasted a snippet with {}.\nCtrl+K, then 'Smart Paste: Clean Up' or 'Adapt to This System'.",
+                cleanup.describe(),
+            )));
+        }
         Ok(())
     }
     
+    /// Clean up the snippet on the input line, returning what was changed
+    fn clean_paste(&mut self) -> String {
+        let cleanup = smart_paste::clean(self.input.as_str());
+        if !cleanup.changed() {
+            return "The input line has nothing to clean up.".to_string();
+        }
+        self.input.set(cleanup.text.clone());
+        format!("Cleaned up the snippet: {}", cleanup.describe())
+    }
+    
+    /// Clean up the snippet on the input line and have the model adapt it to this system
+    async fn adapt_paste(&mut self) -> String {
+        if self.input.is_empty() {
+            return "Paste a snippet onto the input line first.".to_string();
+        }
+        if self.offline.is_offline() {
+            return "Adapting needs the model, and the terminal is offline; 'Smart Paste: Clean Up' works offline.".to_string();
+        }
+        let cleanup = smart_paste::clean(self.input.as_str());
+        let system = smart_paste::system_description();
+        let request = ChatRequest::new(self.active_model().to_string(), smart_paste::adapt_prompt(&cleanup.text, &system));
+        self.is_generating = true;
+        let result = self.backend.generate(&request).await;
+        self.is_generating = false;
+        match result.map(|response| smart_paste::parse_adapted(&response.text)) {
+            Ok(Some(adapted)) => {
+                self.input.set(adapted);
+                format!("Adapted the snippet for {}; check it before running it.", system)
+            }
+            Ok(None) => "The model returned no commands; the snippet was left as it is.".to_string(),
+            Err(e) => format!("Could not adapt the snippet: {:#}", e),
+        }
+    }
+    
+    /// Explain the command on the input line part by part, in a popup that can run it
+    async fn explain_input(&mut self) {
+        let line = self.input.as_str().trim().to_string();
+        if line.is_empty() || line.starts_with('/') {
+            self.toast = Some(Toast::new("Type a shell command on the input line to explain it."));
+            return;
+        }
+        let breakdown = command_explainer::breakdown(&line);
+        let mut explanation = breakdown.describe();
+        // Parts the bundled tools do not cover are left to the model
+        if !breakdown.is_complete() && !self.offline.is_offline() {
+            let (masked, _) = self.redactor.redact(&line);
+            let request = command_explainer::request(self.active_model(), &masked);
+            self.is_generating = true;
+            let result = self.backend.generate(&request).await;
+            self.is_generating = false;
+            match result.map(|response| command_explainer::parse_response(&response.text)) {
+                Ok(Some(answer)) => explanation = answer,
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to explain a command: {:?}", e),
+            }
+        }
+        self.show_confirmation_modal(EXPLAIN_CONFIRMATION, &format!("{}\n\n{}\n\nRun it now?", line, explanation));
+    }
+    
+    /// Scroll, focus, select and copy with the mouse
+    ///
+    /// The wheel scrolls the pane under the pointer. A click on a tab in the
+    /// header switches to it; in a pane it focuses the pane and selects the
+    /// block under the pointer, and dragging from there selects text, which
+    /// is copied to the clipboard when the button is released.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.pane_manager.scroll_at(column, row, -MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollDown => self.pane_manager.scroll_at(column, row, MOUSE_SCROLL_LINES),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let tab = self.tab_hits
+                    .iter()
+                    .find(|&&(start, end, _)| row == 0 && (start..end).contains(&column))
+                    .map(|&(_, _, id)| id);
+                match tab {
+                    Some(id) => self.switch_tab(id),
+                    None => {
+                        self.pane_manager.press(column, row);
+                    }
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => self.pane_manager.drag(column, row),
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(text) = self.pane_manager.selected_text()
+                    && let Err(e) = share::copy_to_clipboard(&text)
+                {
+                    self.push_message("Copy", &format!("Could not copy the selection: {}", e));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Type the passphrase on the lock screen
+    fn handle_lock_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.idle_lock.submit();
+            }
+            KeyCode::Backspace => self.idle_lock.pop(),
+            KeyCode::Esc => self.idle_lock.clear(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.idle_lock.push(c),
+            _ => {}
+        }
+    }
+    
+    /// Tell other instances this one is still running
+    fn heartbeat(&mut self) {
+        if self.last_heartbeat.elapsed() < HEARTBEAT_INTERVAL {
+            return;
+        }
+        self.last_heartbeat = Instant::now();
+        
+        if let (Some(store), Some(id)) = (&self.store, &self.instance_id)
+            && let Err(e) = store.heartbeat(id)
+        {
+            tracing::warn!("Failed to update instance heartbeat: {:?}", e);
+        }
+    }
+    
     /// Add a welcome message to the terminal
     fn add_welcome_message(&mut self) {
         let mut welcome_block = CommandBlock::new(
             "Welcome to AI Terminal".to_string(),
             std::env::current_dir().unwrap_or_default().to_string_lossy().to_string()
         );
-        welcome_block.state = BlockState::Success;
+        welcome_block.set_state(BlockState::Success);
         welcome_block.append_output("Welcome to the AI Terminal! Type commands and press Enter to execute.
 Use F1 for help, F10 to quit.", false);
-        self.command_blocks.push(welcome_block);
+        self.push_block(welcome_block);
+        
+        // Let the user know when history and sessions are shared with other windows
+        let others = self.store.as_ref()
+            .and_then(|store| store.live_instances(INSTANCE_TIMEOUT).ok())
+            .map_or(0, |instances| {
+                instances.iter().filter(|i| Some(&i.id) != self.instance_id.as_ref()).count()
+            });
+        if others > 0 {
+            self.push_message(
+                "Sessions",
+                &format!("{} other AI Terminal instance(s) running; command history is shared between them.", others),
+            );
+        }
+        if let Some(reason) = self.offline.unreachable() {
+            let message = format!("AI features are off: {}. Shell commands work as usual; fix the [backend] section of config.toml and restart.", reason);
+            self.push_message("Model server", &message);
+        }
     }
     
-    /// Handle key events in chat mode
-    async fn handle_chat_key(&mut self, key: KeyEvent) -> Result<()> {
-        match self.ui_state {
-            UIState::Normal => {
-                match key.code {
-                    KeyCode::Enter => {
-                        if !self.input.is_empty() {
-                            // Add to history
-                            self.command_history.add_command(self.input.clone())?;
-                            
-                            // Check if it's an AI command (starts with /)
-                            if self.input.starts_with('/') {
-                                // Handle AI command
-                                self.handle_ai_command().await?;
-                            } else {
-                                // Create command block
-                                let working_dir = self.pty_executor.working_dir().to_string();
-                                let block = CommandBlock::new(self.input.clone(), working_dir);
-                                
-                                // Add block to the focused pane
-                                if let Some(pane) = self.pane_manager.focused_pane_mut() {
-                                    pane.add_command_block(block.clone());
-                                }
-                                
-                                // Clear input
-                                self.input.clear();
-                                self.history_index = None;
-                                
-                                // Execute command
-                                if let Some(pane) = self.pane_manager.focused_pane_mut() {
-                                    if let Some(last_block) = pane.command_blocks.last_mut() {
-                                        self.is_generating = true;
-                                        self.pty_executor.execute_block(last_block).await?;
-                                        self.is_generating = false;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.ui_state = UIState::CommandPalette;
-                    }
-                    KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Show confirmation modal when trying to quit
-                        self.show_confirmation_modal("Confirm Exit", "Are you sure you want to exit the AI Terminal?");
-                    }
-                    KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Split pane horizontally
-                        let _ = self.pane_manager.split_focused_pane(SplitOrientation::Horizontal);
-                    }
-                    KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Split pane vertically
-                        let _ = self.pane_manager.split_focused_pane(SplitOrientation::Vertical);
-                    }
-                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Close focused pane
-                        let _ = self.pane_manager.close_focused_pane();
-                    }
-                    KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Focus next pane
-                        self.pane_manager.focus_next_pane();
-                    }
-                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        // Focus previous pane
-                        self.pane_manager.focus_prev_pane();
-                    }
-                    KeyCode::Char(c) => {
-                        self.input.push(c);
-                        self.history_index = None; // Reset history navigation when typing
-                    }
-                    KeyCode::Backspace => {
-                        self.input.pop();
-                        self.history_index = None; // Reset history navigation when typing
-                    }
-                    KeyCode::Up => {
-                        if key.modifiers.contains(KeyModifiers::CONTROL) {
-                            self.scroll_offset = self.scroll_offset.saturating_add(1);
-                        } else {
-                            self.navigate_history_up();
-                        }
-                    }
-                    KeyCode::Down => {
-                        if key.modifiers.contains(KeyModifiers::CONTROL) {
-                            self.scroll_offset = self.scroll_offset.saturating_sub(1);
-                        } else {
-                            self.navigate_history_down();
+    /// Add a block to the focused pane
+    fn push_block(&mut self, mut block: CommandBlock) {
+        block.set_scrollback(self.scrollback.block, Some(&self.spill_dir()));
+        if let Some(pane) = self.pane_manager.focused_pane_mut() {
+            pane.add_command_block(block);
+        }
+        self.enforce_scrollback();
+    }
+    
+    /// Get the directory receiving this instance's dropped scrollback
+    fn spill_dir(&self) -> std::path::PathBuf {
+        spill_dir(self.instance_id.as_deref())
+    }
+    
+    /// Trim the oldest output across all panes to the global scrollback limit
+    fn enforce_scrollback(&mut self) {
+        let mut blocks: Vec<&mut CommandBlock> = self.pane_manager.panes_mut()
+            .iter_mut()
+            .flat_map(|pane| pane.command_blocks.iter_mut())
+            .collect();
+        blocks.sort_by_key(|block| block.timestamp);
+        terminal_emulator::scrollback::enforce_total(blocks, self.scrollback.total);
+    }
+    
+    /// Show a short informational block in the focused pane
+    fn push_message(&mut self, title: &str, message: &str) {
+        let mut block = CommandBlock::new(title.to_string(), "".to_string());
+        block.append_output(message, false);
+        self.push_block(block);
+    }
+    
+    /// Move the scroll position of the focused pane
+    fn apply_motion(&mut self, motion: Motion) {
+        let theme = self.theme_manager.current_theme();
+        let theme_revision = self.theme_manager.revision();
+        if let Some(pane) = self.pane_manager.focused_pane_mut() {
+            pane.apply_motion(motion, theme, theme_revision);
+        }
+    }
+    
+    /// Handle a key while the chat pane has the input line, returning whether it was used
+    ///
+    /// Enter asks the model, except for `/` commands, Esc hands the input
+    /// line back to the terminal and motions scroll the conversation. Other
+    /// keys edit the input line as usual.
+    async fn handle_chat_pane_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let moves_cursor = matches!(key.code, KeyCode::Home | KeyCode::End) && key.modifiers.is_empty() && !self.input.is_empty();
+        if let Some(motion) = self.keymap.motion(&key).filter(|_| !moves_cursor) {
+            let (page, total) = self.chat_view;
+            self.chat.scroll(motion, page, total);
+            return Ok(true);
+        }
+        match key.code {
+            KeyCode::Enter if key.modifiers.is_empty() && !self.input.is_empty() && !self.input.as_str().starts_with('/') => {
+                let question = self.input.take();
+                self.send_chat(question).await;
+            }
+            KeyCode::Esc => {
+                self.chat.unfocus(&mut self.input);
+                self.history_index = None;
+            }
+            // The command history belongs to the terminal, so Up and Down only move between lines
+            KeyCode::Up => {
+                self.input.move_up();
+            }
+            KeyCode::Down => {
+                self.input.move_down();
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    
+    /// Show the chat pane with the input line, or hide it
+    fn toggle_chat(&mut self) {
+        self.chat.toggle(&mut self.input);
+        self.history_index = None;
+    }
+    
+    /// Ask the chat pane's model a question, with the conversation before it, and add the answer
+    async fn send_chat(&mut self, question: String) {
+        self.chat.ask(question);
+        if self.offline.is_offline() {
+            self.chat.fail(format!("AI requests are held back: {}.", self.offline.reason()));
+            return;
+        }
+        // The project memory follows the system prompt, as it does in AI commands
+        let memory = self.project_memory.as_ref().and_then(ProjectMemory::prompt_context);
+        let system = match (self.active_system_prompt(), memory) {
+            (Some(system), Some(memory)) => Some(format!("{}\n\n{}", system, memory)),
+            (system, memory) => system.map(str::to_string).or(memory),
+        };
+        let Some(request) = self.chat.request(self.active_model(), system) else {
+            return;
+        };
+        self.is_generating = true;
+        let started = Instant::now();
+        match self.backend.generate(&request).await {
+            Ok(response) => {
+                self.stats.record(RequestStats { model: response.model.clone(), usage: response.usage, latency: started.elapsed() });
+                self.chat.reply(response.model, response.text);
+            }
+            Err(e) => self.chat.fail(format!("AI request failed: {:#}", e)),
+        }
+        self.is_generating = false;
+        self.save_chat();
+    }
+    
+    /// Save the chat pane's conversation, listing it in the command palette
+    fn save_chat(&mut self) {
+        let tab = self.tab_manager.active_tab().map(|tab| tab.name.clone()).unwrap_or_default();
+        let Some(store) = &mut self.store else {
+            return;
+        };
+        let conversation = self.chat.conversation();
+        if conversation.messages.is_empty() {
+            return;
+        }
+        let messages = chat_history::to_saved(&conversation.messages);
+        match store.save_conversation(conversation.id, &chat_history::title(conversation), &tab, &messages) {
+            Ok(id) => {
+                self.chat.set_saved(id);
+                self.refresh_chat_commands();
+            }
+            Err(e) => tracing::warn!("Failed to save the chat: {:?}", e),
+        }
+    }
+    
+    /// List the saved conversations in the command palette
+    fn refresh_chat_commands(&mut self) {
+        if let Some(conversations) = self.store.as_ref().and_then(|store| store.conversations().ok()) {
+            self.command_palette.set_category(chat_history::PALETTE_CATEGORY, chat_history::palette_commands(&conversations));
+        }
+    }
+    
+    /// Switch to a tab, bringing up its chat conversation
+    fn switch_tab(&mut self, id: usize) {
+        let previous = self.tab_manager.active_tab_id();
+        if previous == Some(id) || self.tab_manager.switch_to_tab(id).is_err() {
+            return;
+        }
+        let conversation = self.chat.replace(self.tab_chats.remove(&id).unwrap_or_default());
+        if let Some(previous) = previous {
+            self.tab_chats.insert(previous, conversation);
+        }
+    }
+    
+    /// Freeze the focused pane in copy mode, selecting the last command's output if `last_output`
+    fn enter_copy_mode(&mut self, last_output: bool) {
+        let theme = self.theme_manager.current_theme();
+        let theme_revision = self.theme_manager.revision();
+        let Some(pane) = self.pane_manager.focused_pane_mut() else {
+            return;
+        };
+        let height = pane.text_area().height as usize;
+        let mut copy = CopyMode::new(pane.copy_snapshot(theme, theme_revision), height);
+        if last_output && !copy.select_last_output() {
+            self.push_message("Copy", "No command in this pane has output to copy.");
+            return;
+        }
+        self.copy_mode = Some(copy);
+        self.ui_state = UIState::CopyMode;
+    }
+    
+    /// Label the URLs and paths on screen so one can be chosen from the keyboard
+    fn enter_hint_mode(&mut self) {
+        let targets = self.pane_manager.panes().iter().flat_map(|pane| pane.targets().iter().cloned()).collect();
+        match HintMode::new(targets) {
+            Some(hints) => {
+                self.hint_mode = Some(hints);
+                self.ui_state = UIState::Hints;
+            }
+            None => self.push_message("Hints", "There are no links or paths on screen."),
+        }
+    }
+    
+    /// Open or insert a URL or path chosen in hint mode
+    fn use_target(&mut self, target: Target, action: HintAction) {
+        match (target.kind, action) {
+            (TargetKind::Url(url), HintAction::Open) => {
+                if let Err(e) = hints::open_url(&url) {
+                    self.push_message("Hints", &format!("Could not open {}: {}", url, e));
+                }
+            }
+            (TargetKind::Path(path, line), HintAction::Open) => {
+                self.pending_editor = Some((path, line));
+            }
+            (TargetKind::Url(url), HintAction::Insert) => self.insert_word(&url),
+            (TargetKind::Path(path, _), HintAction::Insert) => {
+                let path = file_browser::insertable_path(&path, std::path::Path::new(self.pty_executor.working_dir()));
+                self.insert_word(&path);
+            }
+        }
+    }
+    
+    /// Get the images on screen that nothing was drawn over since the panes drew them
+    fn uncovered_images(&self, buffer: &ratatui::buffer::Buffer) -> Vec<ImagePlacement> {
+        self.image_cells
+            .iter()
+            .filter(|(placement, cells)| image_cells(buffer, placement.area) == *cells)
+            .map(|(placement, _)| placement.clone())
+            .collect()
+    }
+    
+    /// Draw images with the terminal's graphics over their half blocks, when they moved or appeared
+    fn draw_images(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>, images: Vec<ImagePlacement>) -> Result<()> {
+        if images == self.placed_images {
+            return Ok(());
+        }
+        // Take the images drawn before off the screen
+        if !self.placed_images.is_empty() {
+            if self.image_support == ImageSupport::Kitty {
+                write!(terminal.backend_mut(), "\x1b_Ga=d,q=2\x1b\\")?;
+            } else {
+                terminal.clear()?;
+                terminal.draw(|f| self.render(f))?;
+            }
+        }
+        let cell = crossterm::terminal::window_size()
+            .ok()
+            .filter(|size| size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0)
+            .map_or((8, 16), |size| (size.width / size.columns, size.height / size.rows));
+        let backend = terminal.backend_mut();
+        crossterm::queue!(backend, crossterm::cursor::SavePosition)?;
+        for placement in &images {
+            if let Some(sequence) = placement.image.encode(self.image_support, placement.area.width, placement.area.height, cell) {
+                crossterm::queue!(backend, crossterm::cursor::MoveTo(placement.area.x, placement.area.y), crossterm::style::Print(sequence))?;
+            }
+        }
+        crossterm::queue!(backend, crossterm::cursor::RestorePosition)?;
+        backend.flush()?;
+        self.placed_images = images;
+        Ok(())
+    }
+    
+    /// Show an image file in a new block, as `img <path>` does
+    fn show_image(&mut self, command: String, path: &str) {
+        let working_dir = self.pty_executor.working_dir().to_string();
+        let mut block = CommandBlock::new(command, working_dir.clone());
+        let file = std::path::Path::new(&working_dir).join(path);
+        let shown = std::fs::read(&file)
+            .map_err(|e| anyhow::anyhow!("Could not read {}: {}", file.display(), e))
+            .and_then(|bytes| Ok((Image::decode(&bytes)?, bytes)));
+        match shown {
+            Ok((image, bytes)) => {
+                let name = file.file_name().map_or_else(|| path.to_string(), |name| name.to_string_lossy().into_owned());
+                block.append_output(&format!("{} · {}×{} pixels · drawn with {}", name, image.width(), image.height(), self.image_support.name()), false);
+                block.add_image(images::file_sequence(&name, &bytes));
+                block.complete(0, Duration::ZERO);
+            }
+            Err(e) => {
+                block.append_output(&format!("img: {:#}", e), true);
+                block.complete(1, Duration::ZERO);
+            }
+        }
+        self.push_block(block);
+    }
+    
+    /// Hand the terminal to the editor for the file chosen in hint mode, if there is one
+    fn run_pending_editor(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let Some((path, line)) = self.pending_editor.take() else {
+            return Ok(());
+        };
+        self.restore_terminal(terminal)?;
+        let status = hints::editor_command(&path, line).status();
+        *terminal = self.setup_terminal()?;
+        terminal.clear()?;
+        self.placed_images.clear();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.push_message("Editor", &format!("The editor exited with {}", status)),
+            Err(e) => self.push_message("Editor", &format!("Could not start the editor: {}", e)),
+        }
+        Ok(())
+    }
+    
+    /// Apply a line editing key to the input, returning whether it was one
+    fn edit_input(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let input = &mut self.input;
+        match key.code {
+            KeyCode::Char('a') if control => input.move_home(),
+            KeyCode::Char('e') if control => input.move_end(),
+            KeyCode::Char('w') if control => input.kill_word_back(),
+            KeyCode::Char('y') if control => input.yank(),
+            KeyCode::Char('y') if alt => input.yank_pop(),
+            KeyCode::Char('d') if alt => input.kill_word_forward(),
+            KeyCode::Char('b') if alt => input.move_word_left(),
+            KeyCode::Char('f') if alt => input.move_word_right(),
+            KeyCode::Backspace if alt || control => input.kill_word_back(),
+            KeyCode::Backspace => input.backspace(),
+            KeyCode::Delete => input.delete(),
+            KeyCode::Left if alt || control => input.move_word_left(),
+            KeyCode::Right if alt || control => input.move_word_right(),
+            KeyCode::Left => input.move_left(),
+            KeyCode::Right => input.move_right(),
+            KeyCode::Home => input.move_home(),
+            KeyCode::End => input.move_end(),
+            _ => return false,
+        }
+        true
+    }
+    
+    /// Run what is on the input line: a slash command, an AI prompt or a shell command
+    async fn submit_input(&mut self) -> Result<()> {
+        // New input brings the view back to the newest output
+        if let Some(pane) = self.pane_manager.focused_pane_mut() {
+            pane.scroll_to_bottom();
+        }
+        
+        // Add to history
+        self.command_history.add_command(self.input.as_str().to_string())?;
+        
+        // Check if it's an AI command (starts with /)
+        if self.input.as_str().starts_with('/') {
+            // Built-in commands take precedence over AI prompts
+            if !self.handle_builtin_command()? {
+                self.handle_ai_command().await?;
+            }
+        } else {
+            let input = self.input.take();
+            self.history_index = None;
+            let command = match self.aliases.expand(&input) {
+                Some(Ok(command)) => command,
+                Some(Err(e)) => {
+                    self.push_message("Aliases", &format!("Failed: {:#}", e));
+                    return Ok(());
+                }
+                None => input,
+            };
+            let command = self.bookmarks.expand(&command);
+            
+            // Placeholders are filled in first; the history keeps them unfilled
+            match PlaceholderPrompt::new(command.clone()) {
+                Some(prompt) if !prompt.is_complete() => {
+                    self.placeholder_prompt = Some(prompt);
+                    self.placeholder_action = None;
+                    self.ui_state = UIState::PlaceholderPrompt;
+                }
+                Some(prompt) => {
+                    let (command, env) = prompt.finish();
+                    self.submit_shell_command(command, env).await?;
+                }
+                None => self.submit_shell_command(command, Vec::new()).await?,
+            }
+        }
+        Ok(())
+    }
+    
+    /// Handle key events in chat mode
+    async fn handle_chat_key(&mut self, key: KeyEvent) -> Result<()> {
+        match self.ui_state {
+            UIState::Normal => {
+                // Keys scripts bind take precedence over every other
+                if let Some(result) = self.scripts.key(&key) {
+                    self.apply_script_effects(result);
+                    return Ok(());
+                }
+                if self.chat.is_focused() && self.handle_chat_pane_key(key).await? {
+                    return Ok(());
+                }
+                // Scrollback motions take precedence over editing keys, except Home and End while there is input
+                let moves_cursor = matches!(key.code, KeyCode::Home | KeyCode::End) && key.modifiers.is_empty() && !self.input.is_empty();
+                if let Some(motion) = self.keymap.motion(&key).filter(|_| !moves_cursor) {
+                    self.apply_motion(motion);
+                    return Ok(());
+                }
+                match key.code {
+                    KeyCode::Enter if key.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+                        self.input.newline();
+                    }
+                    KeyCode::Enter if !self.input.is_empty() => {
+                        self.submit_input().await?;
+                    }
+                    KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.ui_state = UIState::CommandPalette;
+                    }
+                    KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Show confirmation modal when trying to quit
+                        self.show_confirmation_modal("Confirm Exit", "Are you sure you want to exit the AI Terminal?");
+                    }
+                    KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Split pane horizontally
+                        let _ = self.pane_manager.split_focused_pane(SplitOrientation::Horizontal);
+                    }
+                    KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Split pane vertically
+                        let _ = self.pane_manager.split_focused_pane(SplitOrientation::Vertical);
+                    }
+                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) && self.input.is_empty() => {
+                        // Close focused pane; with input, Ctrl+W deletes a word instead
+                        let _ = self.pane_manager.close_focused_pane();
+                    }
+                    KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Focus next pane
+                        self.pane_manager.focus_next_pane();
+                    }
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Focus previous pane
+                        self.pane_manager.focus_prev_pane();
+                    }
+                    KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.write_commit_message().await?;
+                    }
+                    // Terminals without the keyboard protocol send Ctrl+/ as Ctrl+7
+                    KeyCode::Char('/' | '7') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.explain_input().await;
+                    }
+                    KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        self.explain_diff().await?;
+                    }
+                    KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        self.enter_copy_mode(false);
+                    }
+                    KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        self.enter_copy_mode(true);
+                    }
+                    KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        self.enter_hint_mode();
+                    }
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        if let Err(e) = self.open_pins() {
+                            self.push_message("Pins", &format!("Failed: {:#}", e));
                         }
                     }
+                    KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                        self.input.insert_char(c);
+                        self.history_index = None; // Reset history navigation when typing
+                    }
+                    KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete | KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End
+                        if self.edit_input(key) =>
+                    {
+                        self.history_index = None; // Reset history navigation when typing
+                    }
+                    // Up and Down move between the lines of multi-line input before walking the history
+                    KeyCode::Up if !self.input.move_up() => {
+                        self.navigate_history_up();
+                    }
+                    KeyCode::Down if !self.input.move_down() => {
+                        self.navigate_history_down();
+                    }
+                    KeyCode::Tab if self.input.is_empty() && self.run_correction().await? => {}
                     KeyCode::Tab => {
                         self.handle_tab_completion();
                     }
                     KeyCode::Esc => {
                         self.input.clear();
                         self.history_index = None;
+                        self.attached_file = None;
                     }
                     KeyCode::F(1) => {
                         self.mode = AppMode::Help;
                     }
-                    KeyCode::F(10) => {
-                        // Show confirmation modal when trying to quit
-                        self.show_confirmation_modal("Confirm Exit", "Are you sure you want to exit the AI Terminal?");
+                    KeyCode::F(2) => {
+                        self.open_model_picker().await;
                     }
-                    // Page Up/Down for scrolling
-                    KeyCode::PageUp => {
-                        self.scroll_offset = self.scroll_offset.saturating_add(10);
+                    KeyCode::F(3) => {
+                        self.toggle_offline().await?;
                     }
-                    KeyCode::PageDown => {
-                        self.scroll_offset = self.scroll_offset.saturating_sub(10);
+                    KeyCode::F(4) => {
+                        self.toggle_file_browser();
                     }
-                    // Home/End for jumping to top/bottom
-                    KeyCode::Home => {
-                        self.scroll_offset = 0;
+                    KeyCode::F(5) => {
+                        self.toggle_chat();
                     }
-                    KeyCode::End => {
-                        // Set to a large value to ensure we're at the bottom
-                        self.scroll_offset = 1000;
+                    KeyCode::F(10) => {
+                        // Show confirmation modal when trying to quit
+                        self.show_confirmation_modal("Confirm Exit", "Are you sure you want to exit the AI Terminal?");
                     }
                     _ => {}
                 }
@@ -331,11 +1704,16 @@ Use F1 for help, F10 to quit.", false);
                     KeyCode::Enter => {
                         // Clone the selected command to avoid borrowing issues
                         let selected_command = self.command_palette.get_selected_command().cloned();
+                        let argument = self.command_palette.selected_argument().map(str::to_string);
+                        // Commands may open another popup, so leave the palette first
+                        self.ui_state = UIState::Normal;
                         if let Some(command) = selected_command {
-                            self.execute_palette_command(&command)?;
+                            self.record_palette_use(&command.id);
+                            self.command_palette.reset();
+                            self.execute_palette_command(&command, argument.as_deref()).await?;
+                        } else {
+                            self.command_palette.reset();
                         }
-                        self.ui_state = UIState::Normal;
-                        self.command_palette.reset();
                     }
                     KeyCode::Backspace => {
                         self.command_palette.handle_backspace();
@@ -373,7 +1751,8 @@ Use F1 for help, F10 to quit.", false);
                             modal.select_next();
                         }
                     }
-                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    // File operations must be confirmed by selecting the button
+                    KeyCode::Char('y') | KeyCode::Char('Y') if self.pending_file_op.is_none() => {
                         self.handle_confirmation_result("yes");
                     }
                     KeyCode::Char('n') | KeyCode::Char('N') => {
@@ -381,59 +1760,3596 @@ Use F1 for help, F10 to quit.", false);
                     }
                     _ => {}
                 }
+                
+                // A command or model delete still pending once the modal closes was approved
+                if !matches!(self.ui_state, UIState::ConfirmationModal) {
+                    if let Some(command) = self.pending_command.take() {
+                        self.run_shell_command(command, true).await?;
+                    }
+                    if let Some(model) = self.pending_model_delete.take() {
+                        self.delete_model(model).await;
+                    }
+                    if let Some(pending) = self.pending_share.take() {
+                        self.upload_share(pending).await;
+                    }
+                    if std::mem::take(&mut self.run_explained) {
+                        self.submit_input().await?;
+                    }
+                }
+            }
+            UIState::ThemePicker => {
+                match key.code {
+                    KeyCode::Up => {
+                        if let Some(picker) = &mut self.theme_picker {
+                            picker.move_selection_up();
+                        }
+                        self.preview_selected_theme();
+                    }
+                    KeyCode::Down => {
+                        if let Some(picker) = &mut self.theme_picker {
+                            picker.move_selection_down();
+                        }
+                        self.preview_selected_theme();
+                    }
+                    KeyCode::Enter => {
+                        self.apply_selected_theme();
+                    }
+                    KeyCode::Esc => {
+                        // Revert the live preview to the theme in use before the picker opened
+                        if let Some(picker) = self.theme_picker.take() {
+                            let _ = self.theme_manager.switch_theme(picker.original_theme());
+                        }
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::ModelPicker => {
+                match key.code {
+                    KeyCode::Up => {
+                        if let Some(picker) = &mut self.model_picker {
+                            picker.move_selection_up();
+                        }
+                    }
+                    KeyCode::Down => {
+                        if let Some(picker) = &mut self.model_picker {
+                            picker.move_selection_down();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.apply_selected_model();
+                    }
+                    KeyCode::Esc | KeyCode::F(2) => {
+                        self.model_picker = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::PersonaPicker => {
+                match key.code {
+                    KeyCode::Up => {
+                        if let Some(picker) = &mut self.persona_picker {
+                            picker.move_selection_up();
+                        }
+                    }
+                    KeyCode::Down => {
+                        if let Some(picker) = &mut self.persona_picker {
+                            picker.move_selection_down();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.apply_selected_persona();
+                    }
+                    KeyCode::Char('e') => {
+                        let name = self.persona_picker.as_ref()
+                            .and_then(|picker| picker.selected_persona())
+                            .map(str::to_string);
+                        self.persona_picker = None;
+                        self.open_persona_editor(name.as_deref());
+                    }
+                    KeyCode::Char('n') => {
+                        self.persona_picker = None;
+                        self.open_persona_editor(None);
+                    }
+                    KeyCode::Esc => {
+                        self.persona_picker = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::PersonaEditor => {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        if let Some(editor) = &mut self.persona_editor {
+                            editor.push_char(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(editor) = &mut self.persona_editor {
+                            editor.backspace();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(editor) = self.persona_editor.take() {
+                            let message = self.save_persona(editor.input())
+                                .unwrap_or_else(|e| format!("Could not save the persona: {:#}", e));
+                            self.push_message("Persona", &message);
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        self.persona_editor = None;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::PromptPreview => {
+                match key.code {
+                    KeyCode::Up => {
+                        if let Some(preview) = &mut self.prompt_preview {
+                            preview.move_selection_up();
+                        }
+                    }
+                    KeyCode::Down => {
+                        if let Some(preview) = &mut self.prompt_preview {
+                            preview.move_selection_down();
+                        }
+                    }
+                    KeyCode::Char(' ') => {
+                        if let Some(preview) = &mut self.prompt_preview {
+                            preview.toggle_selected();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(preview) = self.prompt_preview.take() {
+                            self.send_draft(preview.into_draft()).await?;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(preview) = self.prompt_preview.take() {
+                            let command = preview.into_draft().command;
+                            self.push_message("Prompt preview", &format!("Cancelled; `{}` was not sent.", command));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            UIState::ScrubReview => {
+                match key.code {
+                    KeyCode::Up => {
+                        if let Some(review) = &mut self.scrub_review {
+                            review.move_selection_up();
+                        }
+                    }
+                    KeyCode::Down => {
+                        if let Some(review) = &mut self.scrub_review {
+                            review.move_selection_down();
+                        }
+                    }
+                    KeyCode::Char(' ') => {
+                        if let Some(review) = &mut self.scrub_review {
+                            review.toggle_selected();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(review) = self.scrub_review.take() {
+                            self.finish_artifact(review.into_pending());
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(review) = self.scrub_review.take() {
+                            let label = review.pending().artifact().kind.label();
+                            self.push_message(label, "Cancelled; nothing was written.");
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Models => {
+                let Some(view) = &mut self.models_view else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                if view.is_entering_pull() {
+                    match key.code {
+                        KeyCode::Char(c) => view.push_pull_char(c),
+                        KeyCode::Backspace => view.pull_backspace(),
+                        KeyCode::Enter => {
+                            if let Some(model) = view.take_pull_input() {
+                                self.start_model_pull(model);
+                            }
+                        }
+                        KeyCode::Esc => {
+                            view.take_pull_input();
+                        }
+                        _ => {}
+                    }
+                    return Ok(());
+                }
+                
+                match key.code {
+                    KeyCode::Up => view.move_selection_up(),
+                    KeyCode::Down => view.move_selection_down(),
+                    KeyCode::Char('p') => view.start_pull_input(),
+                    KeyCode::Char('r') => self.refresh_models().await,
+                    KeyCode::Enter => {
+                        if let Some(model) = view.selected_model().map(|model| model.name.clone()) {
+                            match self.ollama_client.show_model(&model).await {
+                                Ok(details) => view.set_details(&model, details),
+                                Err(e) => view.set_message(format!("Could not load details of {}: {}", model, e)),
+                            }
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        if let Some(model) = view.selected_model().map(|model| model.name.clone()) {
+                            self.show_confirmation_modal(
+                                MODEL_DELETE_CONFIRMATION,
+                                &format!("Delete the model {}? It will have to be pulled again to be used.", model),
+                            );
+                            self.pending_model_delete = Some(model);
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.models_view = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Processes => {
+                let Some(view) = &mut self.process_view else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                if view.is_filtering() {
+                    match key.code {
+                        KeyCode::Char(c) => view.push_filter_char(c),
+                        KeyCode::Backspace => view.filter_backspace(),
+                        KeyCode::Enter | KeyCode::Esc => view.end_filter(),
+                        KeyCode::Up => view.move_selection_up(),
+                        KeyCode::Down => view.move_selection_down(),
+                        _ => {}
+                    }
+                    return Ok(());
+                }
+                
+                match key.code {
+                    KeyCode::Up => view.move_selection_up(),
+                    KeyCode::Down => view.move_selection_down(),
+                    KeyCode::Char('/') => view.start_filter(),
+                    KeyCode::Char('s') => view.cycle_sort(),
+                    KeyCode::Tab => view.cycle_signal(),
+                    KeyCode::Char('r') => self.refresh_processes(),
+                    KeyCode::Char('k') => {
+                        let signal = view.signal();
+                        if let Some(process) = view.selected().cloned() {
+                            // Protected and exited processes are refused before asking
+                            match self.process_table.describe(process.pid, signal) {
+                                Ok(description) => {
+                                    self.show_confirmation_modal(PROCESS_SIGNAL_CONFIRMATION, &description);
+                                    self.pending_kill = Some((process.pid, process.name, signal));
+                                }
+                                Err(e) => view.set_message(format!("{:#}", e)),
+                            }
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.process_view = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Table => {
+                let Some(view) = &mut self.table_view else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                if view.is_filtering() {
+                    match key.code {
+                        KeyCode::Char(c) => view.push_filter_char(c),
+                        KeyCode::Backspace => view.filter_backspace(),
+                        KeyCode::Enter | KeyCode::Esc => view.end_filter(),
+                        KeyCode::Up => view.move_selection_up(1),
+                        KeyCode::Down => view.move_selection_down(1),
+                        _ => {}
+                    }
+                    return Ok(());
+                }
+                
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => view.move_selection_up(1),
+                    KeyCode::Down | KeyCode::Char('j') => view.move_selection_down(1),
+                    KeyCode::PageUp => view.move_selection_up(TABLE_PAGE),
+                    KeyCode::PageDown => view.move_selection_down(TABLE_PAGE),
+                    KeyCode::Home | KeyCode::Char('g') => view.move_selection_up(usize::MAX),
+                    KeyCode::End | KeyCode::Char('G') => view.move_selection_down(usize::MAX),
+                    KeyCode::Left | KeyCode::Char('h') => view.move_column_left(),
+                    KeyCode::Right | KeyCode::Char('l') => view.move_column_right(),
+                    KeyCode::Char('s') => view.cycle_sort(),
+                    KeyCode::Char('/') => view.start_filter(),
+                    KeyCode::Char('c') => view.clear_filters(),
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let (what, text) = if key.code == KeyCode::Enter {
+                            let Some(row) = view.selected() else {
+                                return Ok(());
+                            };
+                            (format!("the {} cell", view.headers()[view.column()]), row[view.column()].clone())
+                        } else {
+                            (format!("{} row(s) as TSV", view.visible().len()), view.to_tsv())
+                        };
+                        view.set_message(match share::copy_to_clipboard(&text) {
+                            Ok(()) => format!("Copied {}", what),
+                            Err(e) => format!("Could not copy {}: {}", what, e),
+                        });
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.table_view = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Json => {
+                let Some(viewer) = &mut self.json_viewer else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                if viewer.is_editing() {
+                    match key.code {
+                        KeyCode::Char(c) => viewer.push_query_char(c),
+                        KeyCode::Backspace => viewer.query_backspace(),
+                        KeyCode::Enter | KeyCode::Esc => viewer.end_query(),
+                        KeyCode::Up => viewer.move_selection_up(1),
+                        KeyCode::Down => viewer.move_selection_down(1),
+                        _ => {}
+                    }
+                    return Ok(());
+                }
+                
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => viewer.move_selection_up(1),
+                    KeyCode::Down | KeyCode::Char('j') => viewer.move_selection_down(1),
+                    KeyCode::PageUp => viewer.move_selection_up(TABLE_PAGE),
+                    KeyCode::PageDown => viewer.move_selection_down(TABLE_PAGE),
+                    KeyCode::Home | KeyCode::Char('g') => viewer.move_selection_up(usize::MAX),
+                    KeyCode::End | KeyCode::Char('G') => viewer.move_selection_down(usize::MAX),
+                    KeyCode::Enter | KeyCode::Char(' ') => viewer.toggle(),
+                    KeyCode::Left | KeyCode::Char('h') => viewer.fold(),
+                    KeyCode::Right | KeyCode::Char('l') => viewer.unfold(),
+                    KeyCode::Char('e') => viewer.expand_all(),
+                    KeyCode::Char('c') => viewer.collapse_all(),
+                    KeyCode::Char('/') => viewer.start_query(),
+                    KeyCode::Char('f') => viewer.query_selected(),
+                    KeyCode::Char('y') => {
+                        viewer.set_message(match share::copy_to_clipboard(&viewer.result_text()) {
+                            Ok(()) => "Copied the result".to_string(),
+                            Err(e) => format!("Could not copy the result: {}", e),
+                        });
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('|') => match save_json_result(&viewer.result_text()) {
+                        Ok(path) => {
+                            // The result is read from a file, as it may be too long for the command line
+                            let path = shlex::try_quote(&path.to_string_lossy()).map(|quoted| quoted.into_owned()).unwrap_or_default();
+                            self.json_viewer = None;
+                            self.ui_state = UIState::Normal;
+                            self.input.set(format!("cat {} | ", path));
+                        }
+                        Err(e) => viewer.set_message(format!("Could not save the result: {:#}", e)),
+                    },
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.json_viewer = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Logs => {
+                let Some(viewer) = &mut self.log_viewer else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                if viewer.is_filtering() {
+                    match key.code {
+                        KeyCode::Char(c) => viewer.push_filter_char(c),
+                        KeyCode::Backspace => viewer.filter_backspace(),
+                        KeyCode::Enter | KeyCode::Esc => viewer.end_filter(),
+                        _ => {}
+                    }
+                    return Ok(());
+                }
+                
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => viewer.move_selection_up(1),
+                    KeyCode::Down | KeyCode::Char('j') => viewer.move_selection_down(1),
+                    KeyCode::PageUp => viewer.move_selection_up(TABLE_PAGE),
+                    KeyCode::PageDown => viewer.move_selection_down(TABLE_PAGE),
+                    KeyCode::Home | KeyCode::Char('g') => viewer.move_selection_up(usize::MAX),
+                    KeyCode::End | KeyCode::Char('G') => viewer.move_selection_down(usize::MAX),
+                    KeyCode::Char('n') => viewer.jump_to_error(false),
+                    KeyCode::Char('N') => viewer.jump_to_error(true),
+                    KeyCode::Char('l') => viewer.cycle_level(),
+                    KeyCode::Char('/') => viewer.start_filter(),
+                    KeyCode::Char('c') => viewer.clear_filters(),
+                    KeyCode::Char('y') => {
+                        viewer.set_message(match share::copy_to_clipboard(&viewer.text()) {
+                            Ok(()) => format!("Copied {} line(s)", viewer.visible().len()),
+                            Err(e) => format!("Could not copy the lines: {}", e),
+                        });
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.log_viewer = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Hints => {
+                let Some(hints) = &mut self.hint_mode else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Esc => {
+                        self.hint_mode = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    KeyCode::Backspace => {
+                        hints.pop();
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some((target, action)) = hints.push(c) {
+                            self.hint_mode = None;
+                            self.ui_state = UIState::Normal;
+                            self.use_target(target, action);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            UIState::CopyMode => {
+                let Some(copy) = &mut self.copy_mode else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                let control = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.copy_mode = None;
+                        self.ui_state = UIState::Normal;
+                    }
+                    KeyCode::Enter | KeyCode::Char('y') => {
+                        let text = copy.text_to_yank();
+                        self.copy_mode = None;
+                        self.ui_state = UIState::Normal;
+                        if let Err(e) = share::copy_to_clipboard(&text) {
+                            self.push_message("Copy", &format!("Could not copy the selection: {}", e));
+                        }
+                    }
+                    KeyCode::Char('v') if control => copy.toggle_rectangle(),
+                    KeyCode::Char('u') if control => copy.move_half_page(true),
+                    KeyCode::Char('d') if control => copy.move_half_page(false),
+                    KeyCode::Char('v' | ' ') => copy.toggle_selection(),
+                    KeyCode::Char('r') => copy.toggle_rectangle(),
+                    KeyCode::Char('h') | KeyCode::Left => copy.move_left(),
+                    KeyCode::Char('l') | KeyCode::Right => copy.move_right(),
+                    KeyCode::Char('k') | KeyCode::Up => copy.move_lines(-1),
+                    KeyCode::Char('j') | KeyCode::Down => copy.move_lines(1),
+                    KeyCode::Char('w') => copy.move_word_forward(),
+                    KeyCode::Char('b') => copy.move_word_back(),
+                    KeyCode::Char('0') | KeyCode::Home => copy.move_line_start(),
+                    KeyCode::Char('$') | KeyCode::End => copy.move_line_end(),
+                    KeyCode::Char('g') => copy.move_top(),
+                    KeyCode::Char('G') => copy.move_bottom(),
+                    KeyCode::Char('{') => copy.move_previous_block(),
+                    KeyCode::Char('}') => copy.move_next_block(),
+                    KeyCode::PageUp => copy.move_page(true),
+                    KeyCode::PageDown => copy.move_page(false),
+                    KeyCode::Char('o') => {
+                        copy.select_block_output();
+                    }
+                    KeyCode::Char('O') => {
+                        copy.select_last_output();
+                    }
+                    _ => {}
+                }
+            }
+            UIState::CommitEditor => {
+                let Some(editor) = &mut self.commit_editor else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let message = editor.message().to_string();
+                        self.commit_editor = None;
+                        self.ui_state = UIState::Normal;
+                        self.commit_with_message(&message).await?;
+                    }
+                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => editor.delete_word(),
+                    KeyCode::Char(c) => editor.push_char(c),
+                    KeyCode::Enter => editor.newline(),
+                    KeyCode::Backspace => editor.backspace(),
+                    KeyCode::Esc => {
+                        self.commit_editor = None;
+                        self.ui_state = UIState::Normal;
+                        self.push_message("Git", "Nothing was committed; the staged changes are unchanged.");
+                    }
+                    _ => {}
+                }
+            }
+            UIState::DiffReview => {
+                let Some(review) = &mut self.diff_review else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Up => review.scroll(-1),
+                    KeyCode::Down => review.scroll(1),
+                    KeyCode::PageUp => review.scroll(-10),
+                    KeyCode::PageDown => review.scroll(10),
+                    KeyCode::Char('a') => {
+                        let message = match review.edit().apply() {
+                            Ok(()) => format!("Wrote {}", review.edit().shown),
+                            Err(e) => format!("Failed: {:#}", e),
+                        };
+                        self.diff_review = None;
+                        self.ui_state = UIState::Normal;
+                        self.push_message("Edit", &message);
+                    }
+                    KeyCode::Char('r') | KeyCode::Esc => {
+                        if let Some(review) = self.diff_review.take() {
+                            self.push_message("Edit", &format!("Rejected; {} was not changed.", review.edit().shown));
+                        }
+                        self.ui_state = UIState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::FileBrowser => {
+                let Some(browser) = &mut self.file_browser else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Up => browser.move_selection_up(),
+                    KeyCode::Down => browser.move_selection_down(),
+                    KeyCode::PageUp => browser.scroll_preview(-10),
+                    KeyCode::PageDown => browser.scroll_preview(10),
+                    KeyCode::Left => browser.collapse(),
+                    KeyCode::Enter | KeyCode::Right => {
+                        if let Err(e) = browser.open() {
+                            self.push_message("Files", &format!("Failed: {:#}", e));
+                        }
+                    }
+                    KeyCode::Backspace if !browser.close_preview() => browser.go_up(),
+                    KeyCode::Char('.') => {
+                        browser.toggle_hidden();
+                    }
+                    KeyCode::Char('i') => {
+                        if let Some(path) = browser.selected().map(|entry| entry.path.clone()) {
+                            self.insert_path(&path);
+                        }
+                    }
+                    KeyCode::Char('a') => {
+                        if let Some(entry) = browser.selected().filter(|entry| !entry.is_dir).cloned() {
+                            let message = self.attach_file(&entry.path).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                            self.push_message("Files", &message);
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::F(4) | KeyCode::Char('q') => {
+                        self.toggle_file_browser();
+                    }
+                    _ => {}
+                }
+            }
+            UIState::Pins => {
+                let Some(pins) = &mut self.pins else {
+                    self.ui_state = UIState::Normal;
+                    return Ok(());
+                };
+                match key.code {
+                    KeyCode::Up => pins.move_selection_up(),
+                    KeyCode::Down => pins.move_selection_down(),
+                    KeyCode::Enter => {
+                        if let Some(command) = pins.selected().map(|pin| pin.block.command.clone()) {
+                            self.close_pins();
+                            self.input.set(command);
+                            self.submit_input().await?;
+                        }
+                    }
+                    KeyCode::Char('c') | KeyCode::Char('y') => {
+                        if let Some(pin) = pins.selected() {
+                            let (what, text) = if key.code == KeyCode::Char('c') {
+                                ("command", pin.block.command.clone())
+                            } else {
+                                ("output", pin.block.output.text())
+                            };
+                            self.toast = Some(Toast::new(&match share::copy_to_clipboard(&text) {
+                                Ok(()) => format!("Copied the {} of `{}`", what, pin.block.command),
+                                Err(e) => format!("Could not copy the {}: {}", what, e),
+                            }));
+                        }
+                    }
+                    KeyCode::Char('a') => {
+                        if let Some(block) = pins.selected().map(|pin| pin.block.clone()) {
+                            // Context goes to the model, so secrets are masked as in other output it sees
+                            let (output, _) = self.redactor.redact(&block.output.text());
+                            let item = ContextItem::block(&block, &output);
+                            let message = format!("Attached the {} to every AI request; /context lists what is attached", item.label.to_lowercase());
+                            let message = self.context.attach(item).map(|()| message).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                            self.close_pins();
+                            self.push_message("Pins", &message);
+                        }
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete => {
+                        if let Some(pin) = pins.remove_selected()
+                            && let Some(store) = &self.store
+                            && let Err(e) = store.unpin_block(pin.id)
+                        {
+                            self.push_message("Pins", &format!("Could not unpin `{}`: {:#}", pin.block.command, e));
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => self.close_pins(),
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => self.close_pins(),
+                    _ => {}
+                }
+            }
+            UIState::ProjectInterview => {
+                match key.code {
+                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        if let Some((_, prompt)) = &mut self.project_interview {
+                            prompt.delete_component();
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some((_, prompt)) = &mut self.project_interview {
+                            prompt.push_char(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some((_, prompt)) = &mut self.project_interview {
+                            prompt.backspace();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.ui_state = UIState::Normal;
+                        if let Some((mut interview, prompt)) = self.project_interview.take() {
+                            interview.answer(prompt.input());
+                            if interview.question().is_some() {
+                                self.ask_project_question(interview);
+                            } else {
+                                self.draft_project(interview).await;
+                            }
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        self.project_interview = None;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::PlaceholderPrompt => {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        if let Some(prompt) = &mut self.placeholder_prompt {
+                            prompt.push(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(prompt) = &mut self.placeholder_prompt {
+                            prompt.pop();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        if let Some(prompt) = &mut self.placeholder_prompt {
+                            prompt.submit();
+                        }
+                        if let Some(prompt) = self.placeholder_prompt.take_if(|prompt| prompt.is_complete()) {
+                            self.ui_state = UIState::Normal;
+                            let (command, env) = prompt.finish();
+                            match self.placeholder_action.take() {
+                                Some(action) => self.perform_action(action.with_text(command), env).await?,
+                                None => self.submit_shell_command(command, env).await?,
+                            }
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        self.placeholder_prompt = None;
+                        self.placeholder_action = None;
+                    }
+                    _ => {}
+                }
+            }
+            UIState::ExportPrompt => {
+                match key.code {
+                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        if let Some(prompt) = &mut self.export_prompt {
+                            prompt.delete_component();
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(prompt) = &mut self.export_prompt {
+                            prompt.push_char(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(prompt) = &mut self.export_prompt {
+                            prompt.backspace();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        self.ui_state = UIState::Normal;
+                        if let Some(prompt) = self.export_prompt.take() {
+                            self.export_transcript(prompt.input().trim());
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.ui_state = UIState::Normal;
+                        self.export_prompt = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handle key events in help mode
+    async fn handle_help_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::F(1) | KeyCode::Esc => {
+                self.mode = AppMode::Chat;
+            }
+            KeyCode::F(10) => {
+                self.should_quit = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    
+    /// Open the model picker with the models the backend offers
+    async fn open_model_picker(&mut self) {
+        match self.backend.list_models().await {
+            Ok(names) => {
+                self.model_picker = Some(ModelPicker::new(names, self.active_model()));
+                self.ui_state = UIState::ModelPicker;
+            }
+            Err(e) => self.push_message("Models", &format!("Could not list models: {}", e)),
+        }
+    }
+    
+    /// Use the model highlighted in the picker for AI commands in the active tab
+    fn apply_selected_model(&mut self) {
+        self.ui_state = UIState::Normal;
+        let Some(model) = self.model_picker.take().and_then(|picker| picker.selected_model().map(str::to_string)) else {
+            return;
+        };
+        let Some(tab_id) = self.tab_manager.active_tab_id() else {
+            return;
+        };
+        
+        // The default model needs no override, so tabs follow later changes to it
+        let model_override = (model != self.ollama_client.model).then(|| model.clone());
+        if self.tab_manager.set_tab_model(tab_id, model_override).is_ok() {
+            self.push_message("Model", &format!("AI commands in this tab now use '{}'", model));
+        }
+    }
+    
+    /// Open the persona picker with the saved personas
+    fn open_persona_picker(&mut self) {
+        let current = self.active_persona().map(|persona| persona.name.clone());
+        self.persona_picker = Some(PersonaPicker::new(self.personas.names(), current.as_deref()));
+        self.ui_state = UIState::PersonaPicker;
+    }
+    
+    /// Use the persona highlighted in the picker for AI commands in the active tab
+    fn apply_selected_persona(&mut self) {
+        self.ui_state = UIState::Normal;
+        let Some(picker) = self.persona_picker.take() else {
+            return;
+        };
+        let persona = picker.selected_persona().map(str::to_string);
+        let message = match self.use_persona(persona) {
+            Ok(message) => message,
+            Err(e) => format!("Could not switch persona: {:#}", e),
+        };
+        self.push_message("Persona", &message);
+    }
+    
+    /// Set the persona of the active tab, `None` turning it off
+    fn use_persona(&mut self, persona: Option<String>) -> Result<String> {
+        if let Some(name) = &persona
+            && self.personas.get(name).is_none()
+        {
+            anyhow::bail!("no persona named '{}'", name);
+        }
+        let tab_id = self.tab_manager.active_tab_id()
+            .ok_or_else(|| anyhow::anyhow!("there is no active tab"))?;
+        self.tab_manager.set_tab_persona(tab_id, persona.clone()).map_err(anyhow::Error::msg)?;
+        Ok(match persona {
+            Some(name) => format!("AI commands in this tab now use the '{}' persona", name),
+            None => "AI commands in this tab no longer use a persona".to_string(),
+        })
+    }
+    
+    /// Open the persona editor on an existing persona, or empty for a new one
+    fn open_persona_editor(&mut self, name: Option<&str>) {
+        let text = name
+            .and_then(|name| self.personas.get(name))
+            .map(|persona| format!("{}: {}", persona.name, persona.prompt))
+            .unwrap_or_default();
+        self.persona_editor = Some(PathPrompt::new(
+            "Edit Persona",
+            "Write the name, a colon and the system prompt, e.g. terse: Answer in one line.",
+            text,
+        ));
+        self.ui_state = UIState::PersonaEditor;
+    }
+    
+    /// Save the persona written as `name: prompt` and use it in the active tab
+    fn save_persona(&mut self, text: &str) -> Result<String> {
+        let (name, prompt) = text.split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("write the persona as name: system prompt"))?;
+        let (name, prompt) = (name.trim(), prompt.trim());
+        if prompt.is_empty() {
+            anyhow::bail!("the system prompt of '{}' is empty", name);
+        }
+        self.personas.save(Persona::new(name, prompt))?;
+        self.use_persona(Some(name.to_string()))
+            .map(|message| format!("Saved persona '{}'. {}", name, message))
+    }
+    
+    /// Handle `/persona [list|use <name>|off|remove <name>]`
+    fn persona(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.personas.is_empty() {
+                    return Ok("No personas. Create one with 'Edit Persona' in the command palette.".to_string());
+                }
+                let current = self.active_persona().map(|persona| persona.name.clone());
+                let lines: Vec<String> = self.personas.names()
+                    .into_iter()
+                    .map(|name| {
+                        let marker = if current.as_deref() == Some(name.as_str()) { "*" } else { " " };
+                        let prompt = self.personas.get(&name).map_or("", |persona| persona.prompt.as_str());
+                        format!("{} {}  {}", marker, name, prompt)
+                    })
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["use", name] => self.use_persona(Some(name.to_string())),
+            ["off"] => self.use_persona(None),
+            ["remove", name] => {
+                if !self.personas.remove(name)? {
+                    anyhow::bail!("no persona named '{}'", name);
+                }
+                // Tabs using the persona fall back to the configured system prompt
+                let tab_ids: Vec<usize> = self.tab_manager.tabs()
+                    .into_iter()
+                    .filter(|tab| tab.persona.as_deref() == Some(*name))
+                    .map(|tab| tab.id)
+                    .collect();
+                for tab_id in tab_ids {
+                    let _ = self.tab_manager.set_tab_persona(tab_id, None);
+                }
+                Ok(format!("Removed persona '{}'", name))
+            }
+            _ => anyhow::bail!("usage: /persona [list] | /persona use <name> | /persona off | /persona remove <name>"),
+        }
+    }
+    
+    /// Open the models view with the models installed in Ollama
+    async fn open_models_view(&mut self) {
+        match self.ollama_client.get_models().await {
+            Ok(models) => {
+                self.models_view = Some(ModelsView::new(models, self.active_model()));
+                self.ui_state = UIState::Models;
+            }
+            Err(e) => self.push_message("Models", &format!("Could not list models: {}", e)),
+        }
+    }
+    
+    /// Reload the models shown in the models view
+    async fn refresh_models(&mut self) {
+        let result = self.ollama_client.get_models().await;
+        if let Some(view) = &mut self.models_view {
+            match result {
+                Ok(models) => view.set_models(models),
+                Err(e) => view.set_message(format!("Could not list models: {}", e)),
+            }
+        }
+    }
+    
+    /// Delete a model after the user confirmed it
+    async fn delete_model(&mut self, model: String) {
+        let message = match self.ollama_client.delete_model(&model).await {
+            Ok(()) => format!("Deleted {}", model),
+            Err(e) => format!("Could not delete {}: {}", model, e),
+        };
+        self.refresh_models().await;
+        if let Some(view) = &mut self.models_view {
+            view.set_message(message);
+        }
+    }
+    
+    /// Attach the views output processors finished to their blocks
+    fn poll_processed_views(&mut self) {
+        while let Ok((id, view)) = self.processed_views.1.try_recv() {
+            if let Some(block) = self.pane_manager.panes_mut()
+                .iter_mut()
+                .flat_map(|pane| pane.command_blocks.iter_mut())
+                .find(|block| block.id == id)
+            {
+                block.set_view(view);
+            }
+        }
+    }
+    
+    /// Read the output the block followed by the log viewer printed since the last frame
+    fn sync_log_viewer(&mut self) {
+        let Some(viewer) = &mut self.log_viewer else {
+            return;
+        };
+        if let Some(block) = self.pane_manager.panes()
+            .iter()
+            .flat_map(|pane| pane.command_blocks.iter())
+            .find(|block| block.id == viewer.block_id())
+        {
+            viewer.sync(&block.output, &block.state);
+        }
+    }
+    
+    /// Start pulling a model in the background
+    fn start_model_pull(&mut self, model: String) {
+        if self.offline.is_offline() {
+            if let Some(view) = &mut self.models_view {
+                view.set_message(format!("Not pulling {}: {}", model, self.offline.reason()));
+            }
+            return;
+        }
+        if let Some(pull) = &self.model_pull {
+            if let Some(view) = &mut self.models_view {
+                view.set_message(format!("Already pulling {}", pull.status.model));
+            }
+            return;
+        }
+        
+        let client = self.ollama_client.clone();
+        let (tx, updates) = mpsc::unbounded_channel();
+        let name = model.clone();
+        tokio::spawn(async move {
+            match client.pull_model(&name).await {
+                Ok(stream) => {
+                    let mut stream = Box::pin(stream);
+                    while let Some(update) = stream.next().await {
+                        if tx.send(update.map_err(|e| e.to_string())).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e.to_string()));
+                }
+            }
+        });
+        
+        self.model_pull = Some(ModelPull {
+            status: PullStatus { model, status: "starting".to_string(), fraction: None },
+            updates,
+        });
+    }
+    
+    /// Apply progress from a running model pull, reporting the result once it ends
+    async fn poll_model_pull(&mut self) {
+        let Some(pull) = &mut self.model_pull else {
+            return;
+        };
+        
+        let outcome = loop {
+            match pull.updates.try_recv() {
+                Ok(Ok(progress)) if progress.is_success() => break Some(Ok(())),
+                Ok(Ok(progress)) => {
+                    pull.status.fraction = progress.fraction()
+                        .map(|fraction| self.accessibility.progress_step(fraction));
+                    pull.status.status = progress.status;
+                }
+                Ok(Err(e)) => break Some(Err(e)),
+                Err(mpsc::error::TryRecvError::Empty) => break None,
+                Err(mpsc::error::TryRecvError::Disconnected) => break Some(Err("the download stopped unexpectedly".to_string())),
+            }
+        };
+        let Some(outcome) = outcome else {
+            return;
+        };
+        
+        let model = pull.status.model.clone();
+        self.model_pull = None;
+        let message = match outcome {
+            Ok(()) => format!("Pulled {}", model),
+            Err(e) => format!("Could not pull {}: {}", model, e),
+        };
+        if self.models_view.is_some() {
+            self.refresh_models().await;
+            if let Some(view) = &mut self.models_view {
+                view.set_message(message);
+            }
+        } else {
+            self.push_message("Models", &message);
+        }
+    }
+    
+    /// Run a command typed on the input line, with `env` holding its secrets
+    ///
+    /// `cd` changes the working directory, and commands that modify files
+    /// run once approved, recording what they change.
+    async fn submit_shell_command(&mut self, command: String, env: Vec<(String, String)>) -> Result<()> {
+        // On a remote host `cd` runs there, and changes to its files cannot be recorded
+        if self.focused_remote().is_some() {
+            self.command_env = env;
+            return self.run_shell_command(command, false).await;
+        }
+        if let Some(path) = image_target(&command) {
+            let path = path.to_string();
+            self.show_image(command, &path);
+            return Ok(());
+        }
+        if let Some((runs, target)) = bench::target(&command) {
+            let target = target.to_string();
+            self.command_env = env;
+            return self.run_bench(command, runs, target).await;
+        }
+        if let Some(dir) = change_dir_target(&command) {
+            let message = match self.change_dir(dir) {
+                Ok(dir) => format!("Working directory is now {}", dir),
+                Err(e) => format!("Failed: {:#}", e),
+            };
+            self.push_message("cd", &message);
+            return Ok(());
+        }
+        self.command_env = env;
+        if self.impact.enabled && impact::is_high_risk(&command) {
+            // Run it once approved, recording what it changes
+            self.show_confirmation_modal(
+                COMMAND_CONFIRMATION,
+                &format!("{} `{}` modifies files. Run it and record what it changes?", Signal::Risk.glyph(), command),
+            );
+            self.pending_command = Some(command);
+            Ok(())
+        } else {
+            self.run_shell_command(command, false).await
+        }
+    }
+    
+    /// Run a command `runs` times for `bench`, showing the times in a new block
+    async fn run_bench(&mut self, command: String, runs: usize, target: String) -> Result<()> {
+        let working_dir = self.pty_executor.working_dir().to_string();
+        let executor = self.pty_executor.clone().with_env(std::mem::take(&mut self.command_env));
+        let mut bench = Bench::new(target.clone(), runs);
+        let started = Instant::now();
+        self.is_generating = true;
+        for run in 1..=runs {
+            let mut block = CommandBlock::new(target.clone(), working_dir.clone());
+            let result = executor.execute_block(&mut block).await;
+            match (result, block.state, block.duration) {
+                (Ok(()), BlockState::Success, Some(duration)) => bench.times.push(duration),
+                (result, _, _) => {
+                    let lines: Vec<&str> = block.output.lines().collect();
+                    let mut output = lines[lines.len().saturating_sub(bench::FAILURE_LINES)..].join("\n");
+                    if let Err(e) = result {
+                        output.push_str(&format!("\n{:#}", e));
+                    }
+                    bench.failure = Some(bench::Failure { run, exit_code: block.exit_code, output });
+                    break;
+                }
+            }
+        }
+        self.is_generating = false;
+        let mut block = CommandBlock::new(command, working_dir);
+        block.append_output(&bench.report(), bench.failure.is_some());
+        block.complete(if bench.failure.is_some() { 1 } else { 0 }, started.elapsed());
+        self.push_block(block);
+        self.last_bench = (!bench.times.is_empty()).then_some(bench);
+        Ok(())
+    }
+    
+    /// Check the model server, the model in use, the PTY and the terminal for `/doctor`
+    async fn run_doctor(&mut self) {
+        let models = vec![self.active_model().to_string()];
+        let ollama = (self.backend_kind == BackendKind::Ollama).then_some(&self.ollama_client);
+        let mut checks = doctor::backend_checks(self.backend.as_ref(), ollama, &models).await;
+        checks.push(doctor::pty_check().await);
+        checks.extend(doctor::terminal_checks());
+        self.push_message("Doctor", &doctor::report(&checks));
+    }
+    
+    /// Ask the model to interpret the latest benchmark
+    async fn explain_bench(&mut self, ai_command: String) -> Result<()> {
+        let Some(bench) = &self.last_bench else {
+            self.push_message("Benchmark", "No benchmark to explain yet; run `bench <n> <command>` first");
+            return Ok(());
+        };
+        let prompt = bench::interpretation_prompt(bench);
+        if self.offline.is_offline() {
+            let message = self.offline.hold(ai_command);
+            self.push_message("Offline", &message);
+            return Ok(());
+        }
+        let mut draft = self.prompt_draft(ai_command);
+        draft.pieces[0].text = prompt;
+        self.send_or_preview(draft).await
+    }
+    
+    /// Run a shell command in a new block, optionally recording what it changes on disk
+    async fn run_shell_command(&mut self, command: String, snapshot: bool) -> Result<()> {
+        let working_dir = self.pty_executor.working_dir().to_string();
+        self.run_shell_command_in(command, snapshot, working_dir).await
+    }
+    
+    /// Run a shell command in a new block in `working_dir`, without changing the session's directory
+    ///
+    /// In a pane connected to a host the command runs there instead, in the pane's remote directory.
+    async fn run_shell_command_in(&mut self, command: String, snapshot: bool, working_dir: String) -> Result<()> {
+        let remote = self.focused_remote().cloned();
+        let snapshot = snapshot && remote.is_none();
+        let mut base = self.pty_executor.clone();
+        base.set_working_dir(working_dir.clone());
+        if let Some(remote) = &remote {
+            base = base.on_host(remote.clone());
+        }
+        // `!sandbox cmd` runs `cmd` under the configured restrictions
+        let (command, executor) = match sandbox::strip_prefix(&command) {
+            Some(inner) => (inner.to_string(), base.sandboxed(self.sandbox.profile())),
+            None => (command, base),
+        };
+        let executor = executor.with_env(std::mem::take(&mut self.command_env));
+        let before = snapshot.then(|| {
+            let targets = impact::target_paths(&command, std::path::Path::new(&working_dir));
+            Snapshot::capture(targets, self.impact)
+        });
+        
+        // Mark the tab as soon as a command such as `ssh prod` starts, not when it ends
+        if let Some(style) = self.tab_colors.style_for(&command)
+            && let Some(tab) = self.tab_manager.active_tab().filter(|tab| !tab.style.manual)
+        {
+            let _ = self.tab_manager.set_tab_style(tab.id, style.clone());
+        }
+        
+        let effects = self.plugins.dispatch(&plugins::Event::CommandSubmit { command: command.clone(), cwd: working_dir.clone() });
+        self.apply_plugin_effects(effects);
+        let result = self.scripts.command_submitted(&command);
+        self.apply_script_effects(result);
+        let hook_context = HookContext { command: command.clone(), cwd: working_dir.clone(), finished: None };
+        hooks::run_all(&self.hooks.pre_exec, &hook_context, Duration::from_secs(self.hooks.timeout_secs)).await;
+        
+        // Add block to the focused pane, noting the commit or the host it runs against
+        let block = match &remote {
+            Some(remote) => {
+                let mut block = CommandBlock::new(command, remote.dir.clone().unwrap_or_else(|| "~".to_string()));
+                block.set_host(Some(remote.host.clone()));
+                block
+            }
+            None => {
+                let mut block = CommandBlock::new(command, working_dir);
+                block.set_commit(self.git.context().and_then(|git| git.head.clone()));
+                block
+            }
+        };
+        self.push_block(block);
+        
+        // Execute command
+        let mut summarize = None;
+        if let Some(pane) = self.pane_manager.focused_pane_mut()
+            && let Some(last_block) = pane.command_blocks.last_mut()
+        {
+            self.is_generating = true;
+            executor.execute_block(last_block).await?;
+            self.is_generating = false;
+            
+            // The next command on the host starts where this one left the shell
+            if let Some(remote) = &mut pane.remote
+                && let Some(dir) = last_block.end_dir.clone()
+            {
+                remote.dir = Some(dir);
+            }
+            
+            if let Some(before) = before {
+                last_block.set_impact(before.diff(&before.recapture()));
+            }
+            let processing = self.processors
+                .find(&last_block.command)
+                .map(|processor| (processor, last_block.id, last_block.output.text()));
+            
+            // Polling the same command again only adds a run to the earlier block
+            if !pane.collapse_repeat()
+                && let Some((processor, id, output)) = processing
+            {
+                let views = self.processed_views.0.clone();
+                tokio::task::spawn_blocking(move || {
+                    if let Some(markdown) = processor.process(&output) {
+                        let _ = views.send((id, BlockView { processor: processor.name().to_string(), markdown }));
+                    }
+                });
+            }
+            
+            // Long output of a new block is summarized, or a summary offered, as when offline
+            if let Some(last_block) = pane.command_blocks.last_mut()
+                && last_block.summary.is_none()
+                && let Some(summary) = self.summary_config.on_finish(last_block.output.line_count())
+            {
+                if summary == OutputSummary::Pending && !self.offline.is_offline() {
+                    summarize = Some(last_block.id);
+                }
+                last_block.set_summary(OutputSummary::Offered);
+            }
+        }
+        if let Some(id) = summarize
+            && let Err(e) = self.start_summary(id)
+        {
+            self.push_message("Summary", &format!("{:#}", e));
+        }
+        self.check_known_error();
+        self.suggest_correction().await;
+        // Plugins see the output with secrets masked, as the model does
+        let finished = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.last())
+            .filter(|_| self.plugins.any_has(plugins::Capability::Commands))
+            .map(|block| plugins::Event::BlockComplete {
+                command: block.command.clone(),
+                exit_code: block.exit_code.unwrap_or(-1),
+                duration_ms: block.duration.map_or(0, |duration| duration.as_millis() as u64),
+                output: self.plugins.any_has(plugins::Capability::Output).then(|| self.redactor.redact(&block.output.text()).0),
+            });
+        if let Some(event) = finished {
+            let effects = self.plugins.dispatch(&event);
+            self.apply_plugin_effects(effects);
+        }
+        let finished = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.last())
+            .map(|block| scripting::Finished {
+                command: block.command.clone(),
+                exit_code: block.exit_code.unwrap_or(-1),
+                output: self.redactor.redact(&block.output.text()).0,
+                duration_ms: block.duration.map_or(0, |duration| duration.as_millis() as u64),
+            });
+        if let Some(finished) = finished {
+            let result = self.scripts.block_finished(finished);
+            self.apply_script_effects(result);
+        }
+        // Post-exec hooks run in the background so a slow one does not hold up the next command
+        let finished = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.last())
+            .filter(|_| !self.hooks.post_exec.is_empty())
+            .map(|block| (block.exit_code.unwrap_or(-1), block.duration.unwrap_or_default()));
+        if let Some(finished) = finished {
+            let context = HookContext { finished: Some(finished), ..hook_context };
+            let hooks = self.hooks.post_exec.clone();
+            let timeout = Duration::from_secs(self.hooks.timeout_secs);
+            tokio::spawn(async move { hooks::run_all(&hooks, &context, timeout).await });
+        }
+        // The command may have committed, switched branches or changed files
+        self.git.invalidate();
+        self.enforce_scrollback();
+        Ok(())
+    }
+    
+    /// Handle AI commands (starting with /)
+    async fn handle_ai_command(&mut self) -> Result<()> {
+        // Clear input
+        let ai_command = self.input.take();
+        self.history_index = None;
+        
+        // Known errors are explained from the database, offline too
+        if let Some(args) = ai_command.strip_prefix(error_kb::EXPLAIN_COMMAND) {
+            let args = args.to_string();
+            return self.explain_error(ai_command, &args).await;
+        }
+        if ai_command.trim() == doctor::COMMAND {
+            self.run_doctor().await;
+            return Ok(());
+        }
+        if ai_command.trim() == bench::EXPLAIN_COMMAND {
+            return self.explain_bench(ai_command).await;
+        }
+        if ai_command.trim() == project_memory::LEARN_COMMAND {
+            self.learn_project_fact().await;
+            return Ok(());
+        }
+        if let Some(query) = history_search::query(&ai_command) {
+            let query = query.to_string();
+            self.is_generating = true;
+            let message = self.semantic_history(&query).await.unwrap_or_else(|e| format!("Failed: {:#}", e));
+            self.is_generating = false;
+            self.push_message("History", &message);
+            return Ok(());
+        }
+        if self.offline.is_offline() {
+            let message = self.offline.hold(ai_command);
+            self.push_message("Offline", &message);
+            return Ok(());
+        }
+        // Alias drafts ask the model with their own prompt, for /alias save to read
+        if let Some(task) = ai_command.strip_prefix(aliases::NEW_COMMAND) {
+            if task.trim().is_empty() {
+                self.push_message("Aliases", "Describe the task, as in /alias new deploy the web app to an environment");
+                return Ok(());
+            }
+            let mut draft = self.prompt_draft(ai_command.clone());
+            draft.pieces[0].text = aliases::creation_prompt(task);
+            return self.send_or_preview(draft).await;
+        }
+        if let Some(question) = file_index::question(&ai_command) {
+            let question = question.to_string();
+            return self.ask_project(ai_command, question).await;
+        }
+        // Prompts asking for a system tool run its command instead; routing is automation, so not in untrusted directories
+        if self.router.is_enabled() && self.is_trusted() {
+            let backend = self.backend.clone();
+            match self.router.route(backend.as_ref(), ai_command[1..].trim()).await {
+                Ok(Some(route)) => {
+                    tracing::info!("Routed `{}` to the {} tool", ai_command, route.name);
+                    return self.run_shell_command(route.command, false).await;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Intent routing failed, asking the model instead: {:?}", e),
+            }
+        }
+        self.send_ai_command(ai_command).await
+    }
+    
+    /// Send an AI command typed as `/prompt`, previewing it first when it is large
+    async fn send_ai_command(&mut self, ai_command: String) -> Result<()> {
+        let mut draft = self.prompt_draft(ai_command);
+        if let Some((label, text)) = self.attached_file.take() {
+            draft.pieces.push(ContextPiece::context(label, text));
+        }
+        self.send_or_preview(draft).await
+    }
+    
+    /// Send a request, or preview it first when it is large
+    async fn send_or_preview(&mut self, draft: PromptDraft) -> Result<()> {
+        if !self.prompt_guard.needs_preview(&draft) {
+            return self.send_draft(draft).await;
+        }
+        if self.prompt_preview.is_some() {
+            self.push_message(
+                "Prompt preview",
+                &format!("`{}` is large and another request is being previewed; send it again afterwards.", draft.command),
+            );
+            return Ok(());
+        }
+        self.prompt_preview = Some(PromptPreview::new(draft, self.prompt_guard.preview_tokens));
+        self.ui_state = UIState::PromptPreview;
+        Ok(())
+    }
+    
+    /// Ask the model for a commit message for the staged diff, to be edited and committed
+    async fn write_commit_message(&mut self) -> Result<()> {
+        let request = git::commit_message_prompt(std::path::Path::new(self.pty_executor.working_dir()));
+        self.send_git_request(git::COMMIT_MESSAGE_COMMAND, request).await
+    }
+    
+    /// Ask the model to explain the staged diff, or the unstaged one
+    async fn explain_diff(&mut self) -> Result<()> {
+        let request = git::explain_diff_prompt(std::path::Path::new(self.pty_executor.working_dir()));
+        self.send_git_request(git::EXPLAIN_DIFF_COMMAND, request).await
+    }
+    
+    /// Send a prompt about a diff, with the diff as a piece of context
+    async fn send_git_request(&mut self, command: &str, request: Result<(String, String)>) -> Result<()> {
+        if self.offline.is_offline() {
+            self.push_message("Git", "This needs the model, and the terminal is offline.");
+            return Ok(());
+        }
+        let (prompt, diff) = match request {
+            Ok(request) => request,
+            Err(e) => {
+                self.push_message("Git", &format!("Failed: {:#}", e));
+                return Ok(());
+            }
+        };
+        let mut draft = self.prompt_draft(command.to_string());
+        draft.pieces[0].text = prompt;
+        draft.pieces.push(ContextPiece::context("Diff", diff));
+        self.send_or_preview(draft).await
+    }
+    
+    /// Commit the staged changes with the edited message, showing `git commit` in a block
+    async fn commit_with_message(&mut self, message: &str) -> Result<()> {
+        if message.trim().is_empty() {
+            self.push_message("Git", "The commit message is empty; nothing was committed.");
+            return Ok(());
+        }
+        let path = persistence::data_dir().join("COMMIT_EDITMSG");
+        if let Err(e) = std::fs::create_dir_all(persistence::data_dir()).and_then(|()| std::fs::write(&path, format!("{}\n", message.trim_end()))) {
+            self.push_message("Git", &format!("Failed: could not write {}: {}", path.display(), e));
+            return Ok(());
+        }
+        self.run_shell_command(git::commit_command(&path), false).await
+    }
+    
+    /// Split an AI command into the pieces of its request
+    fn prompt_draft(&self, ai_command: String) -> PromptDraft {
+        // The prompt is the command without the leading '/'
+        let mut pieces = vec![ContextPiece::prompt(ai_command[1..].trim().to_string())];
+        if let Some(system) = self.active_system_prompt() {
+            let label = self.active_persona()
+                .map_or("System prompt".to_string(), |persona| format!("Persona '{}'", persona.name));
+            pieces.push(ContextPiece::context(label, system.to_string()));
+        }
+        if let Some(system) = self.project.as_ref().and_then(|project| project.config.system_prompt.as_ref()) {
+            pieces.push(ContextPiece::context("Project", system.clone()));
+        }
+        if let Some(memory) = self.project_memory.as_ref().and_then(ProjectMemory::prompt_context) {
+            pieces.push(ContextPiece::context("Project memory", memory));
+        }
+        // Commands of a remote pane run on another system, which suggestions should fit
+        if let Some(remote) = self.focused_remote() {
+            pieces.push(ContextPiece::context("Remote host", format!(
+                "The user is on host {} over SSH, in directory {}. Commands run there, not on the local machine, so suggest commands for that host's system.",
+                remote.host, remote.dir.as_deref().unwrap_or("~"),
+            )));
+        }
+        // Tell the model about bookmarks so it can use them in suggested commands
+        if let Some(context) = self.bookmarks.prompt_context() {
+            pieces.push(ContextPiece::context("Bookmarks", context));
+        }
+        // Files and outputs attached with /attach, until they are removed
+        pieces.extend(self.context.pieces());
+        // Offer the tools of connected MCP servers; backends with native tool calling get them as definitions
+        if !self.backend.supports_tools()
+            && let Some(tools) = self.tools.as_ref().map(|provider| provider.tools()).filter(|tools| !tools.is_empty())
+        {
+            pieces.push(ContextPiece::context("Tool descriptions", tools::tool_prompt(tools)));
+        }
+        PromptDraft::new(ai_command, pieces)
+    }
+    
+    /// Send the included pieces of a request and show the response in a new block
+    async fn send_draft(&mut self, draft: PromptDraft) -> Result<()> {
+        // Create a command block for the AI interaction
+        let working_dir = self.pty_executor.working_dir().to_string();
+        let mut block = CommandBlock::new(draft.command.clone(), working_dir);
+        
+        let prompt = draft.prompt();
+        let mut request = ChatRequest::new(self.active_model().to_string(), prompt.clone());
+        request.system = draft.system();
+        
+        // Process the AI command
+        self.is_generating = true;
+        block.start_execution();
+        let start_time = Instant::now();
+        
+        let mut log_entry = LlmLogEntry {
+            timestamp: chrono::Local::now(),
+            model: self.active_model().to_string(),
+            prompt,
+            response: None,
+            error: None,
+            duration_ms: 0,
+            usage: None,
+        };
+        
+        match self.generate_with_tools(&mut request).await {
+            Ok(mut response) => {
+                if prompt_guard::truncate_response(&mut response.text, self.prompt_guard.max_response_bytes) {
+                    tracing::warn!("AI response cut short at {} bytes", self.prompt_guard.max_response_bytes);
+                }
+                // Say which model answered when it was not the one asked
+                if let Some(fallback) = &response.fallback {
+                    block.append_output(&format!(
+                        "> {} `{}` could not answer ({}); this answer is from `{}`.\n\n",
+                        Signal::Risk.glyph(), fallback.primary, fallback.reason, response.model,
+                    ), false);
+                    log_entry.model = response.model.clone();
+                } else if response.retries > 0 {
+                    tracing::info!("AI request succeeded after {} retries", response.retries);
+                }
+                block.append_output(&response.text, false);
+                if let Some(footer) = &draft.footer {
+                    block.append_output(footer, false);
+                }
+                block.complete(0, start_time.elapsed());
+                self.stats.record(RequestStats {
+                    model: log_entry.model.clone(),
+                    usage: response.usage,
+                    latency: start_time.elapsed(),
+                });
+                log_entry.response = Some(response.text);
+                log_entry.usage = response.usage;
+            }
+            Err(e) => {
+                block.append_output(&format!("AI request failed: {}", e), true);
+                block.complete(1, start_time.elapsed());
+                log_entry.error = Some(e.to_string());
+            }
+        }
+        
+        log_entry.duration_ms = start_time.elapsed().as_millis() as u64;
+        if let Some(store) = &self.store {
+            let usage = UsageRecord {
+                timestamp: log_entry.timestamp,
+                model: log_entry.model.clone(),
+                prompt_chars: log_entry.prompt.chars().count(),
+                response_chars: log_entry.response.as_ref().map_or(0, |r| r.chars().count()),
+                duration_ms: log_entry.duration_ms,
+                success: log_entry.error.is_none(),
+            };
+            if let Err(e) = store.record_usage(&usage) {
+                tracing::warn!("Failed to record usage: {:?}", e);
+            }
+        }
+        let Self { llm_log, startup, .. } = self;
+        if let Some(log) = llm_log.get_or_init("LLM log", || startup.time_deferred("LLM log", persistence::open_llm_log))
+            && let Err(e) = log.append(&log_entry)
+        {
+            tracing::warn!("Failed to write LLM log: {:?}", e);
+        }
+        
+        self.push_block(block);
+        self.is_generating = false;
+        
+        // An explained error is saved, and a command of the explanation that works becomes its fix
+        if let Some(signature) = &draft.known_error
+            && let Some(response) = &log_entry.response
+            && let Some(store) = &self.store
+        {
+            match store.put_known_error(&signature.hash, &signature.command, &signature.lines, response) {
+                Ok(id) => {
+                    self.pending_fix = Some(PendingFix::new(id, response));
+                    self.refresh_known_error_commands();
+                }
+                Err(e) => tracing::warn!("Failed to save the explanation of an error: {:?}", e),
+            }
+        }
+        
+        // A drafted commit message opens for editing; nothing is committed until it is accepted
+        if draft.command == git::COMMIT_MESSAGE_COMMAND
+            && let Some(response) = &log_entry.response
+        {
+            self.commit_editor = Some(CommitEditor::new(git::extract_message(response)));
+            self.ui_state = UIState::CommitEditor;
+        }
+        Ok(())
+    }
+    
+    /// Generate a response, first running the tool calls the model makes
+    ///
+    /// Each call is shown as a tool block. On backends with native tool
+    /// calling the results follow the model's calls as tool messages;
+    /// otherwise the model's fenced call and its result are added to the prompt.
+    async fn generate_with_tools(&mut self, request: &mut ChatRequest) -> Result<ChatResponse, OllamaError> {
+        let native = self.backend.supports_tools();
+        if native && let Some(provider) = &self.tools {
+            request.tools = tools::definitions(provider.tools());
+        }
+        let mut rounds = 0;
+        loop {
+            let started = Instant::now();
+            let response = self.backend.generate(request).await?;
+            let calls: Vec<ToolCall> = match &self.tools {
+                Some(_) if rounds < MAX_TOOL_ROUNDS && native => {
+                    response.tool_calls.iter().cloned().map(ToolCall::from).collect()
+                }
+                Some(_) if rounds < MAX_TOOL_ROUNDS => tools::parse_tool_call(&response.text).into_iter().collect(),
+                _ => Vec::new(),
+            };
+            if calls.is_empty() {
+                return Ok(response);
+            }
+            
+            rounds += 1;
+            self.stats.record(RequestStats {
+                model: response.model.clone(),
+                usage: response.usage,
+                latency: started.elapsed(),
+            });
+            if native {
+                request.turns.push(ChatMessage::assistant(response.text.clone(), &response.tool_calls));
+                for call in &calls {
+                    let output = self.run_tool(call).await;
+                    request.turns.push(ChatMessage::tool(tools::result_text(&output)));
+                }
+            } else {
+                let output = self.run_tool(&calls[0]).await;
+                request.prompt = tools::continue_prompt(&request.prompt, &response.text, &output);
+            }
+        }
+    }
+    
+    /// Run a tool call from the model and show it as a tool block
+    async fn run_tool(&mut self, call: &ToolCall) -> ToolOutput {
+        let working_dir = self.pty_executor.working_dir().to_string();
+        let mut block = CommandBlock::new(format!("🔧 {} {}", call.name, call.arguments), working_dir);
+        block.start_execution();
+        let start_time = Instant::now();
+        
+        let trusted = self.is_trusted();
+        let output = match &mut self.tools {
+            Some(_) if !trusted => ToolOutput::error(anyhow::anyhow!(
+                "tools are disabled in untrusted directories; run /trust allow to enable them here"
+            )),
+            Some(provider) => provider.call(&call.name, call.arguments.clone()).await.unwrap_or_else(ToolOutput::error),
+            None => ToolOutput::error(anyhow::anyhow!("no tools are configured")),
+        };
+        block.append_output(&output.text, output.is_error);
+        block.complete(i32::from(output.is_error), start_time.elapsed());
+        // The block keeps the result as it was; the model gets it with secrets masked
+        let (text, redactions) = self.redactor.redact(&output.text);
+        block.set_redactions(redactions);
+        self.push_block(block);
+        ToolOutput { text, ..output }
+    }
+    
+    /// Handle `/tab`, `/tab name <name>`, `/tab color <color|none>` and `/tab icon <icon|none>`
+    fn tab(&mut self, args: Vec<&str>) -> Result<String> {
+        let tab = self.tab_manager.active_tab().ok_or_else(|| anyhow::anyhow!("there is no active tab"))?;
+        let (id, mut style) = (tab.id, tab.style.clone());
+        match args.as_slice() {
+            [] => {
+                let color = style.color.map_or("none".to_string(), |color| color.to_string().to_lowercase());
+                let origin = if style.manual { "set with /tab" } else { "from [tab_colors]" };
+                return Ok(format!("{}: color {}, icon {} ({})", tab.name, color, style.icon.as_deref().unwrap_or("none"), origin));
+            }
+            ["name", name @ ..] if !name.is_empty() => {
+                self.tab_manager.rename_tab(id, name.join(" ")).map_err(anyhow::Error::msg)?;
+                return Ok(format!("Renamed the tab to {}", name.join(" ")));
+            }
+            ["color", "none"] => style.color = None,
+            ["color", color] => {
+                style.color = Some(ThemeManager::parse_color(color).map_err(|e| anyhow::anyhow!("{}", e))?);
+            }
+            ["icon", "none"] => style.icon = None,
+            ["icon", icon] => style.icon = Some(icon.to_string()),
+            _ => anyhow::bail!("usage: /tab [name <name> | color <color|none> | icon <icon|none>]"),
+        }
+        // Clearing both hands the tab back to the color rules
+        style.manual = style.color.is_some() || style.icon.is_some();
+        self.tab_manager.set_tab_style(id, style).map_err(anyhow::Error::msg)?;
+        Ok("Updated the tab's marking".to_string())
+    }
+    
+    /// Handle `/clear [outputs | ai | before <time>]` in the focused pane
+    fn clear(&mut self, args: Vec<&str>) -> Result<String> {
+        let pane = self.pane_manager.focused_pane_mut().ok_or_else(|| anyhow::anyhow!("there is no focused pane"))?;
+        let message = match args.as_slice() {
+            [] => {
+                let count = pane.command_blocks.len();
+                pane.command_blocks.clear();
+                format!("Cleared {} blocks", count)
+            }
+            ["outputs"] => format!("Cleared the output of {} blocks; their commands are kept", pane.clear_outputs()),
+            ["ai"] => format!("Removed {} AI blocks", pane.clear_ai_blocks()),
+            ["before", time @ ..] if !time.is_empty() => {
+                let cutoff = parse_cutoff(&time.join(" "))?;
+                format!("Removed {} blocks started before {}", pane.clear_before(cutoff), cutoff.format("%Y-%m-%d %H:%M"))
+            }
+            _ => anyhow::bail!("usage: /clear [outputs | ai | before <HH:MM | YYYY-MM-DD [HH:MM]>]"),
+        };
+        Ok(message)
+    }
+    
+    /// Handle `/code [copy|run] [n]` for the code blocks of the latest AI answer
+    fn code(&mut self, args: Vec<&str>) -> Result<String> {
+        let blocks = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.iter().rev().find(|block| block.command.starts_with('/') && block.is_complete()))
+            .map(|block| snippets::code_blocks(&block.output.text(), block.command[1..].trim()))
+            .unwrap_or_default();
+        if blocks.is_empty() {
+            anyhow::bail!("the latest AI answer has no code blocks");
+        }
+        let (action, number) = match args.as_slice() {
+            [] => {
+                let lines: Vec<String> = blocks.iter().enumerate()
+                    .map(|(i, block)| match edits::fence_path(&block.language) {
+                        Some(path) => format!("{}. [{}] edits {}", i + 1, block.language, path),
+                        None => format!("{}. [{}] {}", i + 1, block.language, block.code.lines().next().unwrap_or_default()),
+                    })
+                    .collect();
+                return Ok(format!("{}\n/code copy <n>, /code run <n> or /code edit <n> [path] uses one.", lines.join("\n")));
+            }
+            [action] => (*action, "1"),
+            [action, number] => (*action, *number),
+            ["edit", number, path] => {
+                let block = number.parse::<usize>().ok()
+                    .and_then(|n| blocks.get(n.wrapping_sub(1)))
+                    .ok_or_else(|| anyhow::anyhow!("there is no code block {}", number))?;
+                return self.review_edit(block, Some(path));
+            }
+            _ => anyhow::bail!("usage: /code [copy|run|edit] [n]"),
+        };
+        let block = number.parse::<usize>().ok()
+            .and_then(|n| blocks.get(n.wrapping_sub(1)))
+            .ok_or_else(|| anyhow::anyhow!("there is no code block {}", number))?
+            .clone();
+        let message = match action {
+            "copy" => {
+                share::copy_to_clipboard(&block.code)?;
+                "Copied the code block to the clipboard".to_string()
+            }
+            "run" => {
+                let command = runners::command_for(&block, &persistence::data_dir().join("runners"))?;
+                let runner = runners::Runner::for_language(&block.language).unwrap_or(runners::Runner::Shell);
+                let packages = runner.requirements(&block.code);
+                let installs = if packages.is_empty() {
+                    String::new()
+                } else {
+                    format!("\n\nThis installs into the shared {:?} environment: {}", runner, packages.join(", "))
+                };
+                // Code from the model runs only once approved
+                self.show_confirmation_modal(
+                    COMMAND_CONFIRMATION,
+                    &format!("Run this code from the AI answer?{}\n\n{}", installs, block.code),
+                );
+                self.pending_command = Some(command);
+                "Waiting for approval to run the code block".to_string()
+            }
+            "edit" => return self.review_edit(&block, None),
+            _ => anyhow::bail!("usage: /code [copy|run|edit] [n]"),
+        };
+        self.last_code = Some(block);
+        Ok(format!("{}. Keep it with /snippet save [tags].", message))
+    }
+    
+    /// Show the diff of the file edit a code block proposes, to be applied or rejected
+    fn review_edit(&mut self, block: &CodeSnippet, path: Option<&str>) -> Result<String> {
+        let edit = ProposedEdit::from_snippet(block, path, std::path::Path::new(self.pty_executor.working_dir()))?;
+        if edit.is_empty() {
+            return Ok(format!("{} already holds the proposed contents", edit.shown));
+        }
+        let (added, removed) = edit.counts();
+        let message = format!("Reviewing the edit of {} (+{} -{}); a applies it, r rejects it.", edit.shown, added, removed);
+        self.diff_review = Some(DiffReview::new(edit));
+        self.ui_state = UIState::DiffReview;
+        Ok(message)
+    }
+    
+    /// Handle `/snippet save [tags]`, `/snippet list [tag]`, `/snippet insert <id>` and `/snippet remove <id>`
+    fn snippet(&mut self, args: Vec<&str>) -> Result<String> {
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("snippets need the database, which could not be opened"))?;
+        let message = match args.as_slice() {
+            ["save", tags @ ..] => {
+                let code = self.last_code.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("copy or run a code block with /code first"))?;
+                let tags = snippets::parse_tags(tags);
+                let id = store.add_snippet(&code.code, &code.language, &tags, &code.prompt)?;
+                format!("Saved snippet {}", id)
+            }
+            [] | ["list"] | ["list", _] => {
+                let tag = args.get(1).map(|tag| tag.trim_start_matches('#').to_lowercase());
+                let lines: Vec<String> = store.snippets()?
+                    .iter()
+                    .filter(|snippet| tag.as_ref().is_none_or(|tag| snippet.tags.contains(tag)))
+                    .map(|snippet| format!("{}. {}", snippet.id, snippets::summary(snippet)))
+                    .collect();
+                if lines.is_empty() {
+                    return Ok("No snippets. Save one with /snippet save [tags] after /code copy or /code run.".to_string());
+                }
+                return Ok(lines.join("\n"));
+            }
+            ["insert", id] => return self.insert_snippet(id.parse()?),
+            ["remove", id] => {
+                if !store.remove_snippet(id.parse()?)? {
+                    anyhow::bail!("there is no snippet {}", id);
+                }
+                format!("Removed snippet {}", id)
+            }
+            _ => anyhow::bail!("usage: /snippet [save [tags] | list [tag] | insert <id> | remove <id>]"),
+        };
+        self.refresh_snippet_commands();
+        Ok(message)
+    }
+    
+    /// Put a saved snippet on the input line
+    fn insert_snippet(&mut self, id: i64) -> Result<String> {
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("snippets need the database, which could not be opened"))?;
+        let snippet = store.snippets()?
+            .into_iter()
+            .find(|snippet| snippet.id == id)
+            .ok_or_else(|| anyhow::anyhow!("there is no snippet {}", id))?;
+        self.staged_input = Some(snippet.code);
+        Ok(format!("Snippet {} is on the input line", id))
+    }
+    
+    /// Offer the saved snippets in the command palette
+    fn refresh_snippet_commands(&mut self) {
+        if let Some(saved) = self.store.as_ref().and_then(|store| store.snippets().ok()) {
+            self.command_palette.set_category("Snippets", snippets::palette_commands(&saved));
+        }
+    }
+    
+    /// Describe the router and the tools it knows, for `/router`
+    fn router_status(&self) -> String {
+        let state = if !Capability::Embeddings.is_available() {
+            "Routing needs the `embeddings` feature, which this build leaves out. Tools:".to_string()
+        } else if self.router.is_enabled() {
+            format!("Prompts matching a tool with a similarity of at least {:.2} run its command:", self.router.threshold())
+        } else {
+            "Routing is off; enable it in the [router] section. Tools:".to_string()
+        };
+        let lines: Vec<String> = self.router.routes().iter()
+            .map(|route| format!("  {:<14} {}  ({})", route.name, route.command, route.description))
+            .collect();
+        format!("{}\n{}", state, lines.join("\n"))
+    }
+    
+    /// Handle `/trust [allow|deny|forget] [dir]` and `/trust list`
+    fn trust_command(&mut self, args: Vec<&str>) -> Result<String> {
+        let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let target = |dir: Option<&&str>| -> Result<std::path::PathBuf> {
+            let path = dir.map_or(working_dir.clone(), |dir| working_dir.join(expand_home(dir)));
+            path.canonicalize().map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+        };
+        match args.as_slice() {
+            [] => {
+                let (level, source) = self.trust.level(&working_dir);
+                let state = match level {
+                    TrustLevel::Trusted => "trusted: tools and auto-approved agent commands may run",
+                    TrustLevel::Untrusted => "untrusted: tools are refused and agent commands always ask",
+                };
+                let origin = source.map_or("no mark, the default".to_string(), |dir| format!("marked at {}", dir.display()));
+                Ok(format!("{} is {} ({})", working_dir.display(), state, origin))
+            }
+            ["list"] => {
+                if self.trust.marks().is_empty() {
+                    return Ok("No directories are marked. Use /trust allow or /trust deny.".to_string());
+                }
+                let lines: Vec<String> = self.trust.marks().iter()
+                    .map(|(dir, level)| format!("{:<10} {}", level.name(), dir.display()))
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["allow" | "deny", rest @ ..] if rest.len() <= 1 => {
+                let dir = target(rest.first())?;
+                let level = if args[0] == "allow" { TrustLevel::Trusted } else { TrustLevel::Untrusted };
+                self.trust.set(dir.clone(), level)?;
+                Ok(format!("{} and the directories below it are now {}", dir.display(), level.name()))
+            }
+            ["forget", rest @ ..] if rest.len() <= 1 => {
+                let dir = target(rest.first())?;
+                if !self.trust.forget(&dir)? {
+                    anyhow::bail!("{} has no trust mark", dir.display());
+                }
+                Ok(format!("Removed the trust mark of {}", dir.display()))
+            }
+            _ => anyhow::bail!("usage: /trust [allow | deny | forget] [dir] | /trust list"),
+        }
+    }
+    
+    /// Handle `/monitor`, showing or hiding the resource panel
+    fn toggle_monitor(&mut self) -> String {
+        if self.monitor.toggle() {
+            let gpu = if cfg!(feature = "gpu") { "" } else { " GPU usage needs a build with the gpu feature." };
+            format!("Showing resource usage.{}", gpu)
+        } else {
+            "Hid resource usage".to_string()
+        }
+    }
+    
+    /// Open the file browser at the working directory, or close it
+    fn toggle_file_browser(&mut self) {
+        if self.file_browser.take().is_some() {
+            self.ui_state = UIState::Normal;
+        } else {
+            self.file_browser = Some(FileBrowser::new(std::path::Path::new(self.pty_executor.working_dir())));
+            self.ui_state = UIState::FileBrowser;
+        }
+    }
+    
+    /// Close the file browser and add a path to the input, as typed in a command
+    fn insert_path(&mut self, path: &std::path::Path) {
+        let path = file_browser::insertable_path(path, std::path::Path::new(self.pty_executor.working_dir()));
+        self.insert_word(&path);
+        self.toggle_file_browser();
+    }
+    
+    /// Insert text at the cursor, apart from the word before it
+    fn insert_word(&mut self, text: &str) {
+        let before = &self.input.as_str()[..self.input.cursor()];
+        if !before.is_empty() && !before.ends_with(char::is_whitespace) {
+            self.input.insert_char(' ');
+        }
+        self.input.insert_str(text);
+        self.history_index = None;
+    }
+    
+    /// Close the file browser and attach a file to the next AI prompt, starting one in the input
+    fn attach_file(&mut self, path: &std::path::Path) -> Result<String> {
+        let preview = FilePreview::read(path)?;
+        let shown = file_browser::insertable_path(path, std::path::Path::new(self.pty_executor.working_dir()));
+        let Some(context) = file_browser::file_context(&preview, &shown) else {
+            anyhow::bail!("{} is a binary file", shown);
+        };
+        self.attached_file = Some((format!("File {}", shown), context));
+        if !self.input.as_str().starts_with('/') {
+            self.input.set(format!("/Explain {}", shown));
+        }
+        self.toggle_file_browser();
+        Ok(format!("Attached {} to your next AI prompt; edit the question and press Enter, or Esc to drop it.", shown))
+    }
+    
+    /// Handle `/ps`, opening the process manager
+    fn open_process_view(&mut self) -> String {
+        let processes = self.process_table.refresh();
+        let message = format!("Listing {} processes; press k to send the selected one a signal", processes.len());
+        self.process_view = Some(ProcessView::new(processes));
+        self.ui_state = UIState::Processes;
+        message
+    }
+    
+    /// Handle `/table` and `/table inline`, reading the clicked block's output as a table
+    fn table(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] => {
+                // Without a clicked block, the latest block with a table is shown
+                let pane = self.pane_manager.focused_pane()
+                    .ok_or_else(|| anyhow::anyhow!("there is no pane"))?;
+                let (block, table) = match pane.selected_block() {
+                    Some(block) => Table::detect(&block.output.text())
+                        .map(|table| (block, table))
+                        .ok_or_else(|| anyhow::anyhow!("the output of `{}` is not a table", block.command))?,
+                    None => pane.command_blocks
+                        .iter()
+                        .rev()
+                        .find_map(|block| Table::detect(&block.output.text()).map(|table| (block, table)))
+                        .ok_or_else(|| anyhow::anyhow!("no block has output that reads as a table"))?,
+                };
+                let message = format!("Showing {} row(s) of `{}`; s sorts and / filters the selected column", table.rows.len(), block.command);
+                self.table_view = Some(TableView::new(block.command.clone(), table));
+                self.ui_state = UIState::Table;
+                Ok(message)
+            }
+            ["inline"] => {
+                let block = self.target_block_mut()
+                    .ok_or_else(|| anyhow::anyhow!("there is no block to show as a table"))?;
+                // A block already showing a table goes back to its raw output, and then to the table again
+                if block.view.as_ref().is_some_and(|view| view.processor == TableProcessor.name()) {
+                    let raw = !block.shows_raw();
+                    block.set_show_raw(raw);
+                    let shown = if raw { "raw output" } else { "table" };
+                    return Ok(format!("Showing the {} of `{}`", shown, block.command));
+                }
+                let markdown = TableProcessor.process(&block.output.text())
+                    .ok_or_else(|| anyhow::anyhow!("the output of `{}` is not a table", block.command))?;
+                block.set_view(BlockView { processor: TableProcessor.name().to_string(), markdown });
+                block.set_show_raw(false);
+                Ok(format!("Showing the output of `{}` as a table; /table inline again shows it raw", block.command))
+            }
+            _ => anyhow::bail!("usage: /table | /table inline"),
+        }
+    }
+    
+    /// Handle `/json [filter]`, opening the clicked block's JSON output, or the latest JSON output
+    fn json(&mut self, args: Vec<&str>) -> Result<String> {
+        let pane = self.pane_manager.focused_pane()
+            .ok_or_else(|| anyhow::anyhow!("there is no pane"))?;
+        let (block, document) = match pane.selected_block() {
+            Some(block) => json_query::parse_document(&block.output.text())
+                .map(|document| (block, document))
+                .ok_or_else(|| anyhow::anyhow!("the output of `{}` is not JSON", block.command))?,
+            None => pane.command_blocks
+                .iter()
+                .rev()
+                .find_map(|block| json_query::parse_document(&block.output.text()).map(|document| (block, document)))
+                .ok_or_else(|| anyhow::anyhow!("no block has JSON output"))?,
+        };
+        let mut viewer = JsonViewer::new(block.command.clone(), document);
+        let message = format!("Showing the JSON output of `{}`; / queries it as jq would", block.command);
+        for c in args.join(" ").chars() {
+            viewer.push_query_char(c);
+        }
+        self.json_viewer = Some(viewer);
+        self.ui_state = UIState::Json;
+        Ok(message)
+    }
+    
+    /// Handle `/log [level] [regex]`, following the clicked block's output, or the latest block's, as a log
+    fn log(&mut self, args: Vec<&str>) -> Result<String> {
+        let (level, pattern) = match args.split_first() {
+            Some((first, rest)) if Level::parse(first).is_some() => (Level::parse(first), rest.join(" ")),
+            _ => (None, args.join(" ")),
+        };
+        // Messages from the terminal itself have no working directory
+        let block = self.target_block()
+            .filter(|block| !block.working_dir.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("there is no block to follow"))?;
+        let mut viewer = LogViewer::new(block.id, block.command.clone());
+        viewer.sync(&block.output, &block.state);
+        let message = format!("Following the output of `{}` as a log; n jumps to the next error", block.command);
+        viewer.set_min_level(level);
+        viewer.set_pattern(&pattern);
+        self.log_viewer = Some(viewer);
+        self.ui_state = UIState::Logs;
+        Ok(message)
+    }
+    
+    /// Handle `/pin`, pinning the clicked block, or the latest one, to the pins panel
+    fn pin_block(&mut self) -> Result<String> {
+        let Some(store) = &self.store else {
+            anyhow::bail!("pins need the database, which could not be opened");
+        };
+        // Messages from the terminal itself have no working directory
+        let block = self.target_block()
+            .filter(|block| !block.working_dir.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("there is no block to pin"))?;
+        store.pin_block(block)?;
+        Ok(format!("Pinned `{}`; Alt+P opens the pinned blocks", block.command))
+    }
+    
+    /// Handle `/pins`, opening the pins panel
+    fn open_pins(&mut self) -> Result<String> {
+        let Some(store) = &self.store else {
+            anyhow::bail!("pins need the database, which could not be opened");
+        };
+        let pins = store.pinned_blocks()?;
+        let message = format!("Showing {} pinned block(s)", pins.len());
+        self.pins = Some(Pins::new(pins));
+        self.ui_state = UIState::Pins;
+        Ok(message)
+    }
+    
+    /// Close the pins panel
+    fn close_pins(&mut self) {
+        self.pins = None;
+        self.ui_state = UIState::Normal;
+    }
+    
+    /// Read the processes again for the open process manager
+    fn refresh_processes(&mut self) {
+        if let Some(view) = &mut self.process_view {
+            view.set_processes(self.process_table.refresh());
+        }
+    }
+    
+    /// Handle `/lock`, covering the session until it is unlocked
+    fn lock(&mut self) -> String {
+        self.idle_lock.lock();
+        format!("Locked the session at {}", chrono::Local::now().format("%H:%M"))
+    }
+    
+    /// Handle `/tools`, listing the tools offered to the model
+    fn list_tools(&self) -> Result<String> {
+        let tools = self.tools.as_ref().map_or(&[][..], |provider| provider.tools());
+        if tools.is_empty() {
+            return Ok("No tools. Add MCP servers to the [mcp_servers] section of config.toml.".to_string());
+        }
+        let lines: Vec<String> = tools.iter().map(|tool| format!("{}  {}", tool.name, tool.description)).collect();
+        Ok(lines.join("\n"))
+    }
+    
+    /// Go offline or back online, sending the prompts queued while offline
+    async fn toggle_offline(&mut self) -> Result<()> {
+        let going_offline = !self.offline.is_enabled();
+        let queued = self.offline.set_offline(going_offline);
+        if going_offline {
+            self.push_message("Offline", "You are offline. AI prompts and model downloads are held back; press F3 to go online.");
+            return Ok(());
+        }
+        
+        // The server may have gone away while offline mode kept the checks from running
+        if let Some(health) = &mut self.server_health {
+            health.check_now();
+        }
+        self.push_message("Offline", &match queued.len() {
+            _ if self.offline.is_offline() => format!("Offline mode is off, but {}.", self.offline.reason()),
+            0 => "You are online.".to_string(),
+            count => format!("You are online. Sending {} queued prompt(s).", count),
+        });
+        for prompt in queued {
+            self.send_ai_command(prompt.command).await?;
+        }
+        Ok(())
+    }
+    
+    /// Follow whether the model server answers, pausing AI features while it does not
+    async fn poll_server_health(&mut self) {
+        // Offline mode promises that nothing is sent to the server
+        if self.offline.is_enabled() {
+            return;
+        }
+        let Some(health) = &mut self.server_health else {
+            return;
+        };
+        match health.tick(&self.backend) {
+            Some(server_health::Change::Lost(error)) => {
+                self.offline.set_unreachable(Some(error.clone()));
+                let message = format!(
+                    "The {} server is not answering ({}). Shell commands work as usual; AI prompts are held back and AI features come back once the server answers, which is checked in the background.",
+                    self.backend.name(),
+                    error,
+                );
+                self.push_message("Model server", &message);
+            }
+            Some(server_health::Change::Restored) => {
+                let queued = self.offline.set_unreachable(None);
+                let message = match queued.len() {
+                    0 => format!("The {} server answers again; AI features are back.", self.backend.name()),
+                    count => format!("The {} server answers again; AI features are back. Sending {} queued prompt(s).", self.backend.name(), count),
+                };
+                self.push_message("Model server", &message);
+                for prompt in queued {
+                    if let Err(e) = self.send_ai_command(prompt.command).await {
+                        self.push_message("Model server", &format!("Could not send a queued prompt: {:#}", e));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+    
+    /// Handle `/queue [list]` and `/queue clear` for prompts held back while offline
+    fn queue(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.offline.queued().is_empty() {
+                    return Ok("No prompts are queued.".to_string());
+                }
+                let lines: Vec<String> = self.offline.queued()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, queued)| format!("#{} {}  {}", i + 1, queued.queued_at.format("%H:%M"), queued.command))
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["clear"] => Ok(format!("Dropped {} queued prompt(s)", self.offline.clear_queue())),
+            _ => anyhow::bail!("usage: /queue [list] | /queue clear"),
+        }
+    }
+    
+    /// Handle `/agent <goal>`, `/agent stop` and `/agent` for the goal being worked on
+    fn agent(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] => Ok(match &self.agent {
+                Some(agent) => format!(
+                    "Working on: {} ({} of at most {} commands run)",
+                    agent.goal(),
+                    agent.steps().len(),
+                    agent.max_steps(),
+                ),
+                None => "No agent is working. Start one with /agent <goal>.".to_string(),
+            }),
+            ["stop"] => {
+                let mut agent = self.agent.take().ok_or_else(|| anyhow::anyhow!("no agent is working"))?;
+                agent.stop("stopped by the user");
+                Ok(agent.report())
+            }
+            goal => {
+                if let Some(agent) = &self.agent {
+                    anyhow::bail!("an agent is already working on '{}'; use /agent stop first", agent.goal());
+                }
+                if self.offline.is_offline() {
+                    anyhow::bail!("{}", self.offline.reason());
+                }
+                let goal = goal.join(" ");
+                self.agent = Some(Agent::new(&goal, self.agent_config.max_steps));
+                Ok(format!("Working on: {}", goal))
+            }
+        }
+    }
+    
+    /// Take the agent's next step: ask the model, run an approved command or report the outcome
+    async fn poll_agent(&mut self) {
+        // Wait for popups to close, so an approval never replaces one
+        if !matches!(self.ui_state, UIState::Normal) {
+            return;
+        }
+        let Some(status) = self.agent.as_ref().map(|agent| agent.status().clone()) else {
+            return;
+        };
+        match status {
+            AgentStatus::Thinking => self.agent_turn().await,
+            AgentStatus::Approved(command) => self.run_agent_command(command).await,
+            AgentStatus::AwaitingApproval(command) => {
+                // Commands on a host cannot be previewed from here
+                let preview = if self.agent_config.preview && self.focused_remote().is_none() {
+                    let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+                    Some(dry_run::preview(&command, &working_dir).await).filter(|preview| !preview.is_empty())
+                } else {
+                    None
+                };
+                self.show_agent_approval(&command, preview.as_ref());
+            }
+            AgentStatus::Done(_) | AgentStatus::Stopped(_) => {
+                if let Some(agent) = self.agent.take() {
+                    let mut block = CommandBlock::new(format!("🤖 {}", agent.goal()), self.pty_executor.working_dir().to_string());
+                    block.set_state(if matches!(status, AgentStatus::Done(_)) { BlockState::Success } else { BlockState::Cancelled });
+                    block.append_output(&agent.report(), false);
+                    self.push_block(block);
+                }
+            }
+        }
+    }
+    
+    /// Ask the model for the agent's next command, running it straight away if it is safe
+    async fn agent_turn(&mut self) {
+        let working_dir = match self.focused_remote() {
+            Some(remote) => format!("{} on host {} over SSH", remote.dir.as_deref().unwrap_or("~"), remote.host),
+            None => self.pty_executor.working_dir().to_string(),
+        };
+        let Some(prompt) = self.agent.as_ref().map(|agent| agent.prompt(&working_dir)) else {
+            return;
+        };
+        if self.offline.is_offline() {
+            if let Some(agent) = &mut self.agent {
+                agent.stop("the terminal went offline");
+            }
+            return;
+        }
+        
+        let mut request = ChatRequest::new(self.active_model().to_string(), prompt);
+        request.system = Some(agent::SYSTEM_PROMPT.to_string());
+        self.is_generating = true;
+        let started = Instant::now();
+        let result = self.backend.generate(&request).await;
+        self.is_generating = false;
+        if let Ok(response) = &result {
+            self.stats.record(RequestStats {
+                model: response.model.clone(),
+                usage: response.usage,
+                latency: started.elapsed(),
+            });
+        }
+        
+        let Some(agent) = &mut self.agent else {
+            return;
+        };
+        match result.map(|response| (agent::parse_reply(&response.text), response.text)) {
+            Ok((Some(reply), _)) => {
+                // In untrusted directories every command asks
+                // A project's own list counts only where the user marked the project trusted
+                let project_approves = |command: &str| self.project.as_ref().is_some_and(|project| {
+                    matches!(self.trust.level(&project.root), (TrustLevel::Trusted, Some(_)))
+                        && agent::is_auto_approved(command, &project.config.auto_approve)
+                });
+                if let Some(command) = agent.apply(reply)
+                    && self.trust.is_trusted(std::path::Path::new(self.pty_executor.working_dir()))
+                    && (agent::is_auto_approved(&command, &self.agent_config.auto_approve) || project_approves(&command))
+                {
+                    agent.approve();
+                }
+            }
+            Ok((None, text)) => {
+                let excerpt: String = text.trim().chars().take(200).collect();
+                agent.stop(&format!("the model replied without a plan: {}", excerpt));
+            }
+            Err(e) => agent.stop(&format!("AI request failed: {}", e)),
+        }
+    }
+    
+    /// Ask the user whether the agent may run a command, showing what a dry run found
+    fn show_agent_approval(&mut self, command: &str, preview: Option<&dry_run::Preview>) {
+        let Some(agent) = &self.agent else {
+            return;
+        };
+        let risk = if impact::is_high_risk(command) {
+            format!(" {} It modifies files.", Signal::Risk.glyph())
+        } else {
+            String::new()
+        };
+        let modal = ConfirmationModal::new(
+            AGENT_CONFIRMATION,
+            &format!(
+                "Command {} of at most {}: `{}`{}{}",
+                agent.steps().len() + 1,
+                agent.max_steps(),
+                command,
+                risk,
+                preview.map(|preview| format!("\n\n{}", preview.describe())).unwrap_or_default(),
+            ),
+            vec![
+                ModalButton::new("yes", "Run", true),
+                ModalButton::new("skip", "Skip", false),
+                ModalButton::new("no", "Stop agent", false),
+            ],
+        );
+        self.confirmation_modal = Some(modal);
+        self.ui_state = UIState::ConfirmationModal;
+    }
+    
+    /// Run a command the agent asked for and pass its result on to the next turn
+    async fn run_agent_command(&mut self, command: String) {
+        // The user already approved the command, so only record what it changes
+        let snapshot = self.impact.enabled && impact::is_high_risk(&command);
+        let command_line = if self.sandbox.ai_commands {
+            format!("{}{}", sandbox::SANDBOX_PREFIX, command)
+        } else {
+            command.clone()
+        };
+        let result = self.run_shell_command(command_line, snapshot).await;
+        // Secrets in the output are masked before the model sees them
+        let redactor = &self.redactor;
+        let finished = self.pane_manager.focused_pane_mut()
+            .and_then(|pane| pane.command_blocks.last_mut())
+            .map(|block| {
+                let (output, redactions) = redactor.redact(&block.output.text());
+                block.set_redactions(redactions);
+                (block.exit_code.unwrap_or(-1), output)
+            });
+        
+        let Some(agent) = &mut self.agent else {
+            return;
+        };
+        match (result, finished) {
+            (Ok(()), Some((exit_code, output))) => agent.record(command, exit_code, &output),
+            (Err(e), _) => agent.stop(&format!("could not run `{}`: {:#}", command, e)),
+            (Ok(()), None) => agent.stop(&format!("could not find the output of `{}`", command)),
+        }
+    }
+    
+    /// Run a built-in slash command, returning false if the input is an AI prompt
+    fn handle_builtin_command(&mut self) -> Result<bool> {
+        let input = self.input.as_str().to_string();
+        let mut words = input[1..].split_whitespace();
+        let (title, result) = match words.next() {
+            Some("backup") => ("Backup", self.backup(words.collect())),
+            Some("restore") => ("Restore", self.restore(words.collect())),
+            Some("delete") => ("Delete", self.delete(words.collect())),
+            Some("undo-delete") => ("Delete", self.undo_delete()),
+            Some("copy") => ("Copy", self.copy_or_move(false, words.collect())),
+            Some("move") => ("Move", self.copy_or_move(true, words.collect())),
+            Some("write") => ("Write", self.write_output(words.collect())),
+            Some("capabilities") => ("Capabilities", Ok(capabilities::report())),
+            Some("startup-report") => ("Startup", Ok(self.startup.report())),
+            Some("bookmark") => ("Bookmarks", self.bookmark(words.collect())),
+            Some("tag") => ("Tags", self.tag(words.collect())),
+            Some("blocks") => ("Blocks", self.blocks(words.collect())),
+            Some("persona") => ("Persona", self.persona(words.collect())),
+            Some("queue") => ("Offline", self.queue(words.collect())),
+            Some("tools") => ("Tools", self.list_tools()),
+            Some("agent") => ("Agent", self.agent(words.collect())),
+            Some("share") => (ArtifactKind::WebShare.label(), self.share(words.collect())),
+            Some("tab") => ("Tab", self.tab(words.collect())),
+            Some("ssh") => ("SSH", self.ssh(words.collect())),
+            Some("lock") => ("Lock", Ok(self.lock())),
+            Some("clear") => ("Clear", self.clear(words.collect())),
+            Some("code") => ("Code", self.code(words.collect())),
+            Some("trust") => ("Trust", self.trust_command(words.collect())),
+            Some("router") => ("Router", Ok(self.router_status())),
+            Some("init") => ("Init", self.init_project()),
+            Some("monitor") => ("Monitor", Ok(self.toggle_monitor())),
+            Some("ps") => ("Processes", Ok(self.open_process_view())),
+            Some("table") => ("Tables", self.table(words.collect())),
+            Some("json") => ("JSON", self.json(words.collect())),
+            Some("log") => ("Logs", self.log(words.collect())),
+            Some("pin") => ("Pins", self.pin_block()),
+            Some("pins") => ("Pins", self.open_pins()),
+            Some("snippet") => ("Snippets", self.snippet(words.collect())),
+            Some("workflow") => ("Workflows", self.workflow(words.collect())),
+            Some("commands") => ("Commands", self.commands(words.collect())),
+            Some("plugins") => ("Plugins", self.plugins_command(words.collect())),
+            Some("scripts") => ("Scripts", self.scripts_command(words.collect())),
+            Some("template") => ("Templates", self.template(words.collect())),
+            Some("index") => ("Index", self.index(words.collect())),
+            Some("attach") => ("Context", self.attach(words.collect())),
+            Some("attach-block") => ("Context", self.attach_block(words.collect())),
+            Some("context") => ("Context", self.context_command(words.collect())),
+            Some("summarize") => ("Summary", self.summarize(words.collect())),
+            Some("chat") => ("Chat", self.chat_command(words.collect())),
+            Some("chats") => ("Chats", self.chats(words.collect())),
+            Some("memory") if words.clone().next() != Some("learn") => ("Memory", self.memory(words.collect())),
+            Some("history") if words.clone().next() != Some("semantic") => ("History", self.history_command(words.collect())),
+            Some("kb") if words.clone().next() != Some("explain") => ("Known Errors", self.kb(words.collect())),
+            Some("alias") if words.clone().next() != Some("new") => ("Aliases", self.alias(words.collect())),
+            _ => return Ok(false),
+        };
+        
+        self.input.set(self.staged_input.take().unwrap_or_default());
+        self.history_index = None;
+        let message = result.unwrap_or_else(|e| format!("Failed: {:#}", e));
+        self.push_message(title, &message);
+        Ok(true)
+    }
+    
+    /// Handle `/delete <path>... [--permanent]`, moving files to the trash unless told otherwise
+    fn delete(&mut self, args: Vec<&str>) -> Result<String> {
+        let permanent = args.contains(&"--permanent");
+        let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let paths: Vec<std::path::PathBuf> = args.iter()
+            .filter(|arg| !arg.starts_with("--"))
+            .map(|arg| working_dir.join(expand_home(arg)))
+            .collect();
+        if paths.is_empty() {
+            anyhow::bail!("usage: /delete <path>... [--permanent]");
+        }
+        if !permanent && !Capability::Trash.is_available() {
+            anyhow::bail!("this build has no trash support; use /delete <path>... --permanent");
+        }
+        
+        if permanent {
+            let operation = FileOperation::Delete { paths }.resolved(&working_dir)?;
+            return self.confirm_file_operation(operation);
+        }
+        
+        let batch = self.safe_delete.trash(&paths)?;
+        Ok(format!("Moved {} item(s) to the trash. Use /undo-delete to restore them.", batch.paths.len()))
+    }
+    
+    /// Handle `/copy <source>... <destination>` and `/move <source>... <destination>`
+    fn copy_or_move(&mut self, is_move: bool, args: Vec<&str>) -> Result<String> {
+        let Some((destination, sources)) = args.split_last().filter(|(_, sources)| !sources.is_empty()) else {
+            anyhow::bail!("usage: /{} <source>... <destination>", if is_move { "move" } else { "copy" });
+        };
+        let sources = sources.iter().map(|source| expand_home(source)).collect();
+        let destination = expand_home(destination);
+        let operation = if is_move {
+            FileOperation::Move { sources, destination }
+        } else {
+            FileOperation::Copy { sources, destination }
+        };
+        let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+        self.confirm_file_operation(operation.resolved(&working_dir)?)
+    }
+    
+    /// Handle `/write <path>`, saving the full output of the focused pane's last command to a file
+    fn write_output(&mut self, args: Vec<&str>) -> Result<String> {
+        let [path] = args.as_slice() else {
+            anyhow::bail!("usage: /write <path>");
+        };
+        // Messages from the terminal itself have no working directory
+        let block = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.iter().rev().find(|block| !block.working_dir.is_empty()))
+            .ok_or_else(|| anyhow::anyhow!("there is no output to write"))?;
+        let contents = block.full_output()?.into_bytes();
+        let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let operation = FileOperation::Write { path: expand_home(path), contents }.resolved(&working_dir)?;
+        self.confirm_file_operation(operation)
+    }
+    
+    /// Show the dry run of a file operation and wait for the user to confirm it
+    fn confirm_file_operation(&mut self, operation: FileOperation) -> Result<String> {
+        let preview = operation.preview()?;
+        let (title, action, warning) = match operation {
+            FileOperation::Delete { .. } => (PERMANENT_DELETE_CONFIRMATION, "Delete permanently", "\nThis cannot be undone."),
+            _ => (FILE_OPERATION_CONFIRMATION, operation.name(), ""),
+        };
+        let modal = ConfirmationModal::new(
+            title,
+            &format!("{}: {}{}", operation.name(), preview.describe(FILE_PREVIEW_LINES), warning),
+            vec![
+                ModalButton::new("no", "Cancel", true),
+                ModalButton::new("yes", action, false),
+            ],
+        );
+        self.confirmation_modal = Some(modal);
+        self.ui_state = UIState::ConfirmationModal;
+        let message = format!("Waiting for confirmation to {}…", action.to_lowercase());
+        self.pending_file_op = Some(operation);
+        Ok(message)
+    }
+    
+    /// Handle `/undo-delete`, restoring the most recent delete from the trash
+    fn undo_delete(&mut self) -> Result<String> {
+        let batch = self.safe_delete.undo()?;
+        let listing: Vec<String> = batch.paths.iter().map(|path| path.display().to_string()).collect();
+        Ok(format!("Restored {}", listing.join(", ")))
+    }
+    
+    /// Handle `/bookmark [list]`, `/bookmark add <name> [path]` and `/bookmark remove <name>`
+    fn bookmark(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.bookmarks.is_empty() {
+                    return Ok("No bookmarks. Add one with /bookmark add <name> [path].".to_string());
+                }
+                let lines: Vec<String> = self.bookmarks.iter()
+                    .map(|(name, path)| format!("@{}  {}", name, path.display()))
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["add", name, rest @ ..] if rest.len() <= 1 => {
+                let name = name.trim_start_matches('@');
+                if !Bookmarks::is_valid_name(name) {
+                    anyhow::bail!("bookmark names may only contain letters, digits, '-', '_' and '.'");
+                }
+                let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+                let path = match rest.first() {
+                    Some(path) => working_dir.join(expand_home(path)),
+                    None => working_dir,
+                };
+                let path = path.canonicalize()
+                    .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+                if let Some(store) = &self.store {
+                    store.set_bookmark(name, &path.to_string_lossy())?;
+                }
+                let message = format!("@{} now points to {}", name, path.display());
+                self.bookmarks.insert(name, path);
+                Ok(message)
+            }
+            ["remove", name] => {
+                let name = name.trim_start_matches('@');
+                if let Some(store) = &self.store {
+                    store.remove_bookmark(name)?;
+                }
+                if self.bookmarks.remove(name) {
+                    Ok(format!("Removed @{}", name))
+                } else {
+                    anyhow::bail!("no bookmark named @{}", name)
+                }
+            }
+            _ => anyhow::bail!("usage: /bookmark [list] | /bookmark add <name> [path] | /bookmark remove <name>"),
+        }
+    }
+    
+    /// Handle `/alias [list]`, `/alias add <name> <command>`, `/alias save [name]` and `/alias remove <name>`
+    fn alias(&mut self, args: Vec<&str>) -> Result<String> {
+        let message = match args.as_slice() {
+            [] | ["list"] => {
+                if self.aliases.is_empty() {
+                    return Ok("No aliases. Add one with /alias add <name> <command>, or have one drafted with /alias new <task>.".to_string());
+                }
+                let lines: Vec<String> = self.aliases.iter()
+                    .map(|(name, alias)| format!("{}{}  {}", aliases::PREFIX, name, alias.command))
+                    .collect();
+                return Ok(lines.join("\n"));
+            }
+            ["add", name, command @ ..] if !command.is_empty() => {
+                self.aliases.insert(name, Alias { command: command.join(" "), description: String::new() })?;
+                format!("{}{} now runs {}", aliases::PREFIX, name, command.join(" "))
+            }
+            ["save", rest @ ..] if rest.len() <= 1 => {
+                let answer = self.pane_manager.focused_pane()
+                    .and_then(|pane| pane.command_blocks.iter().rev().find(|block| block.command.starts_with(aliases::NEW_COMMAND) && block.is_complete()))
+                    .map(|block| block.output.text())
+                    .ok_or_else(|| anyhow::anyhow!("there is no drafted alias; ask for one with /alias new <task>"))?;
+                let (drafted, alias) = aliases::from_answer(&answer)
+                    .ok_or_else(|| anyhow::anyhow!("the answer holds no alias; ask again with /alias new <task>"))?;
+                let name = rest.first().copied().unwrap_or(&drafted);
+                let message = format!("{}{} now runs {}", aliases::PREFIX, name, alias.command);
+                self.aliases.insert(name, alias)?;
+                message
+            }
+            ["remove", name] => {
+                if !self.aliases.remove(name)? {
+                    anyhow::bail!("there is no alias {}", name);
+                }
+                format!("Removed {}{}", aliases::PREFIX, name)
+            }
+            _ => anyhow::bail!("usage: /alias [list] | /alias add <name> <command> | /alias new <task> | /alias save [name] | /alias remove <name>"),
+        };
+        self.command_palette.set_category("Aliases", aliases::palette_commands(&self.aliases));
+        Ok(message)
+    }
+    
+    /// Handle `/workflow [list]`, `/workflow run <name>`, `/workflow stop` and `/workflow reload`
+    fn workflow(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.workflows.is_empty() {
+                    let dir = self.workflows.dir().map_or("the workflows directory of the config directory".to_string(), |dir| dir.display().to_string());
+                    return Ok(format!("No workflows. Add a TOML or YAML file per workflow to {}, then /workflow reload.", dir));
+                }
+                let lines: Vec<String> = self.workflows.iter()
+                    .map(|workflow| {
+                        let steps: Vec<&str> = workflow.steps.iter().map(workflows::Step::label).collect();
+                        format!("{}  {}", workflow.name, steps.join(" → "))
+                    })
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["run", name] => self.start_workflow(name),
+            ["stop"] => {
+                let run = self.workflow_run.as_mut().ok_or_else(|| anyhow::anyhow!("no workflow is running"))?;
+                run.stop("stopped with /workflow stop");
+                Ok(format!("Stopping {} before its next step", run.workflow().name))
+            }
+            ["reload"] => {
+                let count = self.workflows.load()?;
+                self.command_palette.set_category("Workflows", self.workflows.palette_commands());
+                Ok(format!("Loaded {} workflow(s)", count))
+            }
+            _ => anyhow::bail!("usage: /workflow [list] | /workflow run <name> | /workflow stop | /workflow reload"),
+        }
+    }
+    
+    /// List the plugins and their capabilities, or load them again
+    fn plugins_command(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.plugins.plugins().is_empty() {
+                    let dir = plugins::plugins_dir().map_or("the plugins directory of the config directory".to_string(), |dir| dir.display().to_string());
+                    return Ok(format!("No plugins. Add a directory with a plugin.toml and its module to {}, then /plugins reload.", dir));
+                }
+                Ok(format!("{}\n\nGrant capabilities in the [plugins.grants] section of the configuration.", self.plugins.report()))
+            }
+            ["reload"] => Ok(format!("Loaded {} plugin(s)", self.reload_plugins()?)),
+            _ => anyhow::bail!("usage: /plugins [list] | /plugins reload"),
+        }
+    }
+    
+    /// Handle `/template [list]` and `/template <name> [text]`, putting a filled template on the input line
+    fn template(&mut self, args: Vec<&str>) -> Result<String> {
+        let (name, text) = match args.split_first() {
+            None | Some((&"list", [])) => {
+                let lines: Vec<String> = self.templates.iter()
+                    .map(|(name, text)| format!("{}  {}", name, text.lines().next().unwrap_or_default()))
+                    .collect();
+                return Ok(lines.join("\n"));
+            }
+            Some((name, text)) => (*name, text.join(" ")),
+        };
+        let template = self.templates.get(name)
+            .ok_or_else(|| anyhow::anyhow!("no template named '{}'; /template list shows them", name))?
+            .to_string();
+        let last = self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.iter().rev().find(|block| !block.command.starts_with('/') && block.is_complete()));
+        let mut prompt = templates::render(&template, |variable| match variable {
+            "output" | "command" | "exit_code" => {
+                let block = last.ok_or_else(|| anyhow::anyhow!("'{}' uses the last command, and none has run in this pane", name))?;
+                Ok(match variable {
+                    // The prompt goes to the model, so secrets are masked as in other context
+                    "output" => self.redactor.redact(&block.output.text()).0,
+                    "command" => block.command.clone(),
+                    _ => block.exit_code.map_or("unknown".to_string(), |code| code.to_string()),
+                })
+            }
+            "cwd" => Ok(self.focused_remote().map_or_else(|| self.pty_executor.working_dir().to_string(), |remote| remote.location())),
+            _ => templates::read_clipboard(),
+        })?;
+        if !text.is_empty() {
+            prompt = format!("{}\n\n{}", prompt.trim_end(), text);
+        }
+        self.staged_input = Some(format!("/{}", prompt));
+        Ok(format!("'{}' is on the input line; edit the prompt and press Enter to send it", name))
+    }
+    
+    /// Handle `/attach <path>...`, adding files to the context of every AI request
+    fn attach(&mut self, args: Vec<&str>) -> Result<String> {
+        if args.is_empty() {
+            anyhow::bail!("usage: /attach <path>...");
+        }
+        let working_dir = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let mut attached = Vec::new();
+        for arg in args {
+            let path = working_dir.join(expand_home(arg));
+            let preview = FilePreview::read(&path)?;
+            let shown = file_browser::insertable_path(&path, &working_dir);
+            let text = file_browser::file_context(&preview, &shown).ok_or_else(|| anyhow::anyhow!("{} is a binary file", shown))?;
+            self.context.attach(ContextItem::file(&shown, text))?;
+            attached.push(shown);
+        }
+        Ok(format!("Attached {} to every AI request; /context lists what is attached", attached.join(", ")))
+    }
+    
+    /// Handle `/attach-block [n]`, adding the output of the n-th latest block of the pane to the context
+    fn attach_block(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /attach-block [n], n counting back from the latest block, which is 1";
+        let block = self.nth_block_back(&args, usage)?;
+        // Context goes to the model, so secrets are masked as in other output it sees
+        let (output, _) = self.redactor.redact(&block.output.text());
+        let item = ContextItem::block(block, &output);
+        let message = format!("Attached the {} to every AI request; /context lists what is attached", item.label.to_lowercase());
+        self.context.attach(item)?;
+        Ok(message)
+    }
+    
+    /// Handle `/chat [show|hide|clear]` and `/chat send [n]`, sending the output of the n-th latest block to the chat pane
+    fn chat_command(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /chat [show|hide|clear] | /chat send [n], n counting back from the latest block, which is 1";
+        // The command itself leaves the input line before it changes hands
+        self.input.clear();
+        let message = match args.as_slice() {
+            [] | ["show"] => {
+                self.chat.focus(&mut self.input);
+                "The chat pane has the input line; Esc hands it back to the terminal".to_string()
+            }
+            ["hide"] => {
+                self.chat.hide(&mut self.input);
+                "Hid the chat pane; /chat or F5 shows it again".to_string()
+            }
+            ["clear"] => format!("Removed {} message(s) from the chat", self.chat.clear()),
+            ["send", rest @ ..] => {
+                let block = self.nth_block_back(rest, usage)?;
+                // The chat goes to the model, so secrets are masked as in other output it sees
+                let (output, _) = self.redactor.redact(&block.output.text());
+                let item = ContextItem::block(block, &output);
+                let message = format!("Sent the {} to the chat pane", item.label.to_lowercase());
+                self.chat.attach(item.label, item.text);
+                self.chat.focus(&mut self.input);
+                self.save_chat();
+                message
+            }
+            _ => anyhow::bail!(usage),
+        };
+        self.staged_input = Some(self.input.take());
+        Ok(message)
+    }
+    
+    /// Handle `/chats [list]`, `/chats search <text>`, `/chats open|delete <id>`, `/chats rename <id> <title>` and `/chats export <id> <path>`
+    fn chats(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /chats [list] | /chats search <text> | /chats open|delete <id> | /chats rename <id> <title> | /chats export <id> <path>";
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("conversations are saved in the database, which could not be opened"))?;
+        let parse_id = |arg: &str| arg.parse::<i64>().map_err(|_| anyhow::anyhow!(usage));
+        let list = |found: Vec<terminal_emulator::ConversationInfo>, none: &str| if found.is_empty() {
+            none.to_string()
+        } else {
+            found.iter().map(chat_history::summary).collect::<Vec<_>>().join("\n")
+        };
+        match args.as_slice() {
+            [] | ["list"] => Ok(list(store.conversations()?, "No conversations are saved yet; F5 opens the chat pane")),
+            ["search", words @ ..] if !words.is_empty() => {
+                let query = words.join(" ");
+                Ok(list(store.search_conversations(&query)?, &format!("No saved conversation mentions \"{}\"", query)))
+            }
+            ["open", id] => {
+                let id = parse_id(id)?;
+                let info = store.conversation(id)?.ok_or_else(|| anyhow::anyhow!("there is no conversation {}", id))?;
+                let conversation = chat_history::from_saved(&info, store.conversation_messages(id)?);
+                self.chat.replace(conversation);
+                self.chat_command(vec!["show"])?;
+                Ok(format!("Reopened \"{}\" in the chat pane", info.title))
+            }
+            ["rename", id, title @ ..] if !title.is_empty() => {
+                let id = parse_id(id)?;
+                let title = title.join(" ");
+                if !store.rename_conversation(id, &title)? {
+                    anyhow::bail!("there is no conversation {}", id);
+                }
+                if self.chat.conversation().id == Some(id) {
+                    self.chat.set_title(title.clone());
+                }
+                self.refresh_chat_commands();
+                Ok(format!("Renamed conversation {} to \"{}\"", id, title))
+            }
+            ["delete", id] => {
+                let id = parse_id(id)?;
+                if !store.delete_conversation(id)? {
+                    anyhow::bail!("there is no conversation {}", id);
+                }
+                if self.chat.conversation().id == Some(id) {
+                    self.chat.clear();
+                }
+                self.refresh_chat_commands();
+                Ok(format!("Deleted conversation {}", id))
+            }
+            ["export", id, path] => {
+                let id = parse_id(id)?;
+                let info = store.conversation(id)?.ok_or_else(|| anyhow::anyhow!("there is no conversation {}", id))?;
+                let conversation = chat_history::from_saved(&info, store.conversation_messages(id)?);
+                let destination = std::path::Path::new(self.pty_executor.working_dir()).join(expand_home(path));
+                let message = format!("Exporting \"{}\" to {}", info.title, destination.display());
+                let content = chat_history::to_markdown(&info.title, &conversation.messages);
+                self.write_artifact(Artifact::new(ArtifactKind::ChatExport, destination, content));
+                Ok(message)
+            }
+            _ => anyhow::bail!(usage),
+        }
+    }
+    
+    /// Handle `/memory [show]` and `/memory add <fact>`
+    fn memory(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /memory [show] | /memory add <fact> | /memory learn";
+        match args.as_slice() {
+            [] | ["show"] => Ok(match &self.project_memory {
+                Some(memory) => format!("{}:\n\n{}", memory.path.display(), memory.content.trim_end()),
+                None => format!(
+                    "There is no {} here or at the repository root; /memory add <fact> starts one",
+                    project_memory::FILE_NAME,
+                ),
+            }),
+            ["add", fact @ ..] if !fact.is_empty() => {
+                let path = project_memory::target_path(std::path::Path::new(self.pty_executor.working_dir()), self.project_memory.as_ref());
+                project_memory::append(&path, &fact.join(" "))?;
+                self.load_project_memory();
+                Ok(format!("Added to {}", path.display()))
+            }
+            _ => anyhow::bail!(usage),
+        }
+    }
+    
+    /// Have the model propose a fact from the chat or the latest blocks for the project memory, appended once approved
+    async fn learn_project_fact(&mut self) {
+        if self.offline.is_offline() {
+            self.push_message("Memory", "This needs the model, and the terminal is offline.");
+            return;
+        }
+        // The chat is the recent work when there is one, else the latest blocks of the pane, with secrets masked
+        let recent = if self.chat.messages().is_empty() {
+            let blocks: Vec<String> = self.pane_manager.focused_pane()
+                .into_iter()
+                .flat_map(|pane| pane.command_blocks.iter().rev())
+                .filter(|block| !block.working_dir.is_empty())
+                .take(LEARNED_BLOCKS)
+                .map(|block| project_memory::describe_block(&block.command, &self.redactor.redact(&block.output.text()).0))
+                .collect();
+            blocks.into_iter().rev().collect::<Vec<_>>().join("\n\n")
+        } else {
+            chat_history::to_markdown("Chat", self.chat.messages())
+        };
+        if recent.trim().is_empty() {
+            self.push_message("Memory", "There is nothing to learn from yet; run some commands or chat first.");
+            return;
+        }
+        let request = ChatRequest::new(self.active_model().to_string(), project_memory::learn_prompt(self.project_memory.as_ref(), &recent));
+        self.is_generating = true;
+        let result = self.backend.generate(&request).await;
+        self.is_generating = false;
+        match result.map(|response| project_memory::parse_fact(&response.text)) {
+            Ok(Some(fact)) => {
+                let path = project_memory::target_path(std::path::Path::new(self.pty_executor.working_dir()), self.project_memory.as_ref());
+                self.show_confirmation_modal(MEMORY_CONFIRMATION, &format!("Add to {}?\n\n- {}", path.display(), fact));
+                self.pending_memory = Some((path, fact));
+            }
+            Ok(None) => self.push_message("Memory", "The model found nothing new worth remembering."),
+            Err(e) => self.push_message("Memory", &format!("Failed: {:#}", e)),
+        }
+    }
+    
+    /// Handle `/history search <text>`, finding commands of the whole history that contain the text
+    fn history_command(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /history search <text> | /history semantic <query>";
+        let ["search", words @ ..] = args.as_slice() else {
+            anyhow::bail!(usage);
+        };
+        if words.is_empty() {
+            anyhow::bail!(usage);
+        }
+        let text = words.join(" ");
+        let found = self.command_history.search_all(&text, history_search::RESULTS)?;
+        if found.is_empty() {
+            return Ok(format!("No command contains \"{}\"; /history semantic {} searches by meaning", text, text));
+        }
+        let lines: Vec<String> = found.iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{}. {} ({})", i + 1, entry.command, entry.timestamp.format("%Y-%m-%d %H:%M")))
+            .collect();
+        Ok(lines.join("\n"))
+    }
+    
+    /// Find the commands of the history nearest in meaning to a query, embedding those not indexed yet
+    async fn semantic_history(&self, query: &str) -> Result<String> {
+        Capability::Embeddings.require()?;
+        if query.is_empty() {
+            anyhow::bail!("describe the command, as in {} fixed the docker network", history_search::SEMANTIC_COMMAND);
+        }
+        if self.offline.is_offline() {
+            anyhow::bail!("searching by meaning needs the embedding model, and the terminal is offline");
+        }
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("the history index needs the database, which could not be opened"))?;
+        let model = &self.index_config.model;
+        let prefix = history_search::prefix(model);
+        // Summaries of the outputs in view are embedded with their commands
+        let summaries = self.pane_manager.panes()
+            .iter()
+            .flat_map(|pane| pane.command_blocks.iter())
+            .filter_map(|block| match &block.summary {
+                Some(OutputSummary::Ready { markdown, .. }) => Some((block.command.clone(), markdown.clone())),
+                _ => None,
+            })
+            .collect();
+        let items = history_search::items(&store.recent_history(history_search::MAX_ENTRIES)?, &summaries);
+        let (mut pending, stale) = history_search::plan(items, &store.embedding_keys(&prefix)?, &prefix);
+        for key in &stale {
+            store.remove_embedding(key)?;
+        }
+        let remaining = pending.len().saturating_sub(history_search::MAX_EMBEDDED_PER_SEARCH);
+        pending.truncate(history_search::MAX_EMBEDDED_PER_SEARCH);
+        for batch in pending.chunks(history_search::BATCH_SIZE) {
+            let inputs: Vec<String> = batch.iter().map(history_search::HistoryItem::embedding_input).collect();
+            let vectors = self.backend.embeddings(model, &inputs).await?;
+            if vectors.len() != batch.len() {
+                anyhow::bail!("{} returned {} embeddings for {} commands", model, vectors.len(), batch.len());
+            }
+            for (item, vector) in batch.iter().zip(vectors) {
+                store.put_embedding(&item.key(&prefix), history_search::SOURCE, &vector)?;
+            }
+        }
+        let vector = self.backend.embeddings(model, &[query.to_string()]).await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("{} returned no embedding for the query", model))?;
+        let found: Vec<(String, f32)> = store.nearest_embeddings_in(&prefix, &vector, history_search::RESULTS)?
+            .into_iter()
+            .filter_map(|(key, similarity)| Some((history_search::command_from_key(&key, &prefix)?.to_string(), similarity)))
+            .collect();
+        if found.is_empty() {
+            return Ok("The history is empty; there is nothing to search yet.".to_string());
+        }
+        let mut message = format!("Commands nearest to \"{}\":\n{}", query, history_search::describe(&found));
+        if remaining > 0 {
+            message.push_str(&format!("\n{} older command(s) are not indexed yet; the next searches add them.", remaining));
+        }
+        Ok(message)
+    }
+    
+    /// Handle `/kb explain [n]`, explaining why the latest failed command, or the n-th latest command, failed
+    ///
+    /// A known error is explained from the knowledge base; others are sent
+    /// to the model, and its answer is saved for the next time.
+    async fn explain_error(&mut self, ai_command: String, args: &str) -> Result<()> {
+        let found = self.failed_command(args).and_then(|found| match &self.store {
+            Some(store) => Ok((store.known_error(&found.0.hash)?, found)),
+            None => anyhow::bail!("the knowledge base needs the database, which could not be opened"),
+        });
+        let (known, (signature, context)) = match found {
+            Ok(found) => found,
+            Err(e) => {
+                self.push_message("Known Errors", &format!("Failed: {:#}", e));
+                return Ok(());
+            }
+        };
+        if let Some(known) = known {
+            self.show_known_error(known);
+            return Ok(());
+        }
+        if self.offline.is_offline() {
+            let message = self.offline.hold(ai_command);
+            self.push_message("Offline", &message);
+            return Ok(());
+        }
+        let mut draft = self.prompt_draft(ai_command);
+        draft.pieces[0].text = error_kb::explain_prompt(&context);
+        draft.known_error = Some(signature);
+        self.send_or_preview(draft).await
+    }
+    
+    /// Find the error of the latest failed command of the pane, or of the n-th latest command, with its output as sent to the model
+    fn failed_command(&self, args: &str) -> Result<(ErrorSignature, String)> {
+        let usage = "usage: /kb explain [n], n counting back from the latest command, which is 1";
+        let back: Option<usize> = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => None,
+            [n] => Some(n.parse().ok().filter(|n| *n > 0).ok_or_else(|| anyhow::anyhow!(usage))?),
+            _ => anyhow::bail!(usage),
+        };
+        // Shell commands only: messages have no working directory, and AI and tool blocks are not commands
+        let mut commands = self.pane_manager.focused_pane()
+            .into_iter()
+            .flat_map(|pane| pane.command_blocks.iter().rev())
+            .filter(|block| !block.working_dir.is_empty() && !block.command.starts_with('/') && !block.command.starts_with('🔧'));
+        let block = match back {
+            Some(back) => commands.nth(back - 1).ok_or_else(|| anyhow::anyhow!("the pane has no command {} back", back))?,
+            None => commands.find(|block| block.state == BlockState::Failed).ok_or_else(|| anyhow::anyhow!("no command in the pane failed"))?,
+        };
+        if block.state != BlockState::Failed {
+            anyhow::bail!("`{}` did not fail", block.command);
+        }
+        // The error goes to the model and the database, so secrets are masked as in other output it sees
+        let (output, _) = self.redactor.redact(&error_kb::error_output(block));
+        let signature = ErrorSignature::new(&block.command, &output)
+            .ok_or_else(|| anyhow::anyhow!("`{}` printed no error to explain", block.command))?;
+        let (all_output, _) = self.redactor.redact(&block.output.text());
+        Ok((signature, ContextItem::block(block, &all_output).text))
+    }
+    
+    /// Show a known error with its explanation and fix, putting the fix on an empty input line
+    fn show_known_error(&mut self, mut known: terminal_emulator::KnownError) {
+        if let Some(store) = &self.store {
+            match store.record_known_error_hit(known.id) {
+                Ok(()) => known.hits += 1,
+                Err(e) => tracing::warn!("Failed to count a known error: {:?}", e),
+            }
+        }
+        self.pending_fix = Some(PendingFix::new(known.id, &known.explanation));
+        if let Some(fix) = &known.fix
+            && self.input.is_empty()
+        {
+            self.input.set(fix.clone());
+        }
+        self.push_message("Known Error", &error_kb::describe(&known));
+        self.refresh_known_error_commands();
+    }
+    
+    /// Show the explanation when the pane's latest command failed with a known error, or save it as the fix of the latest explained one
+    fn check_known_error(&mut self) {
+        let Some(block) = self.pane_manager.focused_pane().and_then(|pane| pane.command_blocks.last()) else {
+            return;
+        };
+        let Some(store) = &self.store else {
+            return;
+        };
+        if block.state == BlockState::Success {
+            let command = block.command.trim().to_string();
+            if let Some(pending) = self.pending_fix.take_if(|pending| pending.accepts(&command)) {
+                match store.set_known_error_fix(pending.id, &command) {
+                    Ok(_) => {
+                        self.toast = Some(Toast::new(&format!("Saved `{}` as the fix of known error {}", command, pending.id)));
+                        self.refresh_known_error_commands();
+                    }
+                    Err(e) => tracing::warn!("Failed to save the fix of a known error: {:?}", e),
+                }
+            }
+            return;
+        }
+        if block.state != BlockState::Failed {
+            return;
+        }
+        let (output, _) = self.redactor.redact(&error_kb::error_output(block));
+        let known = ErrorSignature::new(&block.command, &output)
+            .and_then(|signature| store.known_error(&signature.hash).map_err(|e| tracing::warn!("Failed to look up an error: {:?}", e)).ok().flatten());
+        if let Some(known) = known {
+            self.show_known_error(known);
+        }
+    }
+    
+    /// Suggest a correction under the latest block of the focused pane if it failed
+    async fn suggest_correction(&mut self) {
+        let Some(pane) = self.pane_manager.focused_pane_mut() else {
+            return;
+        };
+        // Only the latest block's correction runs with Tab
+        for block in pane.command_blocks.iter_mut().filter(|block| block.correction().is_some()) {
+            block.set_correction(None);
+        }
+        let Some(block) = pane.command_blocks.last() else {
+            return;
+        };
+        if !self.correction_config.enabled || block.state != BlockState::Failed {
+            return;
+        }
+        let command = block.command.clone();
+        let (output, _) = self.redactor.redact(&error_kb::error_output(block));
+        let mut correction = corrections::suggest(&command, &output, corrections::path_programs);
+        if correction.is_none() && self.correction_config.ai && !self.offline.is_offline() {
+            let request = corrections::request(self.active_model(), &command, &output);
+            self.is_generating = true;
+            let result = self.backend.generate(&request).await;
+            self.is_generating = false;
+            match result {
+                Ok(response) => correction = corrections::parse_response(&response.text, &command),
+                Err(e) => tracing::warn!("Failed to ask for a correction: {:?}", e),
+            }
+        }
+        if let Some(block) = self.pane_manager.focused_pane_mut().and_then(|pane| pane.command_blocks.last_mut()) {
+            block.set_correction(correction);
+        }
+    }
+    
+    /// Run the correction suggested under the latest block of the focused pane, if there is one
+    async fn run_correction(&mut self) -> Result<bool> {
+        let correction = self.pane_manager.focused_pane_mut()
+            .and_then(|pane| pane.command_blocks.last_mut())
+            .and_then(|block| {
+                let correction = block.correction()?.to_string();
+                block.set_correction(None);
+                Some(correction)
+            });
+        let Some(correction) = correction else {
+            return Ok(false);
+        };
+        self.input.set(correction);
+        self.submit_input().await?;
+        Ok(true)
+    }
+    
+    /// Handle `/kb [list]`, `/kb show <id>`, `/kb fix <id> <command>`, `/kb remove <id>` and `/kb clear`
+    fn kb(&mut self, args: Vec<&str>) -> Result<String> {
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("the knowledge base needs the database, which could not be opened"))?;
+        let find = |id: &str| -> Result<terminal_emulator::KnownError> {
+            let id: i64 = id.parse().map_err(|_| anyhow::anyhow!("'{}' is not a known error id", id))?;
+            store.known_errors()?.into_iter().find(|known| known.id == id).ok_or_else(|| anyhow::anyhow!("there is no known error {}", id))
+        };
+        let message = match args.as_slice() {
+            [] | ["list"] => {
+                let lines: Vec<String> = store.known_errors()?.iter().map(error_kb::summary).collect();
+                if lines.is_empty() {
+                    return Ok(format!("No known errors. {} explains the latest failed command and saves the answer.", error_kb::EXPLAIN_COMMAND));
+                }
+                return Ok(format!("{}
+
+/kb show <id> shows one with its explanation and fix.", lines.join("
+")));
+            }
+            ["show", id] => {
+                let known = find(id)?;
+                if let Some(fix) = &known.fix {
+                    self.staged_input = Some(fix.clone());
+                }
+                return Ok(error_kb::describe(&known));
+            }
+            ["fix", id, command @ ..] if !command.is_empty() => {
+                let known = find(id)?;
+                store.set_known_error_fix(known.id, &command.join(" "))?;
+                format!("Saved `{}` as the fix of known error {}", command.join(" "), known.id)
+            }
+            ["remove", id] => {
+                let known = find(id)?;
+                store.remove_known_error(known.id)?;
+                format!("Removed known error {}", known.id)
+            }
+            ["clear"] => format!("Removed {} known error(s)", store.clear_known_errors()?),
+            _ => anyhow::bail!("usage: /kb [list | explain [n] | show <id> | fix <id> <command> | remove <id> | clear]"),
+        };
+        self.refresh_known_error_commands();
+        Ok(message)
+    }
+    
+    /// Offer the known errors in the command palette
+    fn refresh_known_error_commands(&mut self) {
+        if let Some(known) = self.store.as_ref().and_then(|store| store.known_errors().ok()) {
+            self.command_palette.set_category("Known Errors", error_kb::palette_commands(&known));
+        }
+    }
+    
+    /// Handle `/summarize [n]`, summarizing the output of the n-th latest block
+    fn summarize(&mut self, args: Vec<&str>) -> Result<String> {
+        let usage = "usage: /summarize [n], n counting back from the latest block, which is 1";
+        let block = self.nth_block_back(&args, usage)?;
+        let (id, command) = (block.id, block.command.clone());
+        self.start_summary(id)?;
+        Ok(format!("Summarizing the output of `{}`; the summary appears above it", command))
+    }
+    
+    /// Ask the model in the background to summarize the output of the block with the given ID
+    fn start_summary(&mut self, id: uuid::Uuid) -> Result<()> {
+        if self.offline.is_offline() {
+            anyhow::bail!("summarizing needs the model, and the terminal is offline; /summarize works once it is online");
+        }
+        let model = self.summary_config.model.clone().unwrap_or_else(|| self.active_model().to_string());
+        let block = self.pane_manager.panes_mut()
+            .iter_mut()
+            .flat_map(|pane| pane.command_blocks.iter_mut())
+            .find(|block| block.id == id)
+            .ok_or_else(|| anyhow::anyhow!("the block is gone"))?;
+        if block.state == BlockState::Running {
+            anyhow::bail!("`{}` is still running", block.command);
+        }
+        if block.output.is_empty() {
+            anyhow::bail!("`{}` printed nothing to summarize", block.command);
+        }
+        if block.summary == Some(OutputSummary::Pending) {
+            anyhow::bail!("the output of `{}` is already being summarized", block.command);
+        }
+        // The output goes to the model, so secrets are masked as in other output it sees
+        let (output, _) = self.redactor.redact(&block.output.text());
+        let request = summaries::request(&model, &block.command, block.exit_code, &output);
+        block.set_summary(OutputSummary::Pending);
+        tokio::spawn(summaries::summarize(self.backend.clone(), id, request, self.summaries.0.clone()));
+        Ok(())
+    }
+    
+    /// Attach the summaries the model finished to their blocks
+    fn poll_summaries(&mut self) {
+        while let Ok((id, result, latency)) = self.summaries.1.try_recv() {
+            let summary = match result {
+                Ok(response) => {
+                    self.stats.record(RequestStats { model: response.model.clone(), usage: response.usage, latency });
+                    OutputSummary::Ready { model: response.model, markdown: response.text }
+                }
+                Err(e) => OutputSummary::Failed(e),
+            };
+            if let Some(block) = self.pane_manager.panes_mut()
+                .iter_mut()
+                .flat_map(|pane| pane.command_blocks.iter_mut())
+                .find(|block| block.id == id)
+            {
+                block.set_summary(summary);
+            }
+        }
+    }
+    
+    /// Handle `/context [list]`, `/context remove <n>` and `/context clear`
+    fn context_command(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => Ok(self.context.describe()),
+            ["remove", n] => {
+                let item = n.parse().ok()
+                    .and_then(|n| self.context.remove(n))
+                    .ok_or_else(|| anyhow::anyhow!("there is no item {} in the context", n))?;
+                Ok(format!("Removed {} from the context", item.label))
+            }
+            ["clear"] => Ok(format!("Removed {} item(s) from the context", self.context.clear())),
+            _ => anyhow::bail!("usage: /context [list] | /context remove <n> | /context clear"),
+        }
+    }
+    
+    /// Handle `/index [status | clear]`, indexing the files of the working directory for `/ask-project`
+    fn index(&mut self, args: Vec<&str>) -> Result<String> {
+        Capability::Embeddings.require()?;
+        let root = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let prefix = self.index_config.prefix(&root);
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("the index needs the database, which could not be opened"))?;
+        match args.as_slice() {
+            [] => {}
+            ["status"] => {
+                let chunks = store.embedding_keys(&prefix)?.len();
+                return Ok(match &self.indexing {
+                    Some(indexing) => format!("Indexing {}: {} of {} chunks embedded", indexing.root.display(), indexing.done, indexing.total),
+                    None if chunks == 0 => format!("{} is not indexed; /index indexes it", root.display()),
+                    None => format!("{} chunk(s) of {} are indexed with {}", chunks, root.display(), self.index_config.model),
+                });
+            }
+            ["clear"] => return Ok(format!("Removed {} chunk(s) of {} from the index", store.remove_embeddings_in(&prefix)?, root.display())),
+            _ => anyhow::bail!("usage: /index [status | clear]"),
+        }
+        if let Some(indexing) = &self.indexing {
+            anyhow::bail!("already indexing {}; /index status shows how far it got", indexing.root.display());
+        }
+        if self.offline.is_offline() {
+            anyhow::bail!("indexing needs the embedding model, and the terminal is offline");
+        }
+        if self.focused_remote().is_some() {
+            anyhow::bail!("the files of a remote host cannot be indexed");
+        }
+        let plan = IndexPlan::new(&self.index_config, &root, &store.embedding_keys(&prefix)?)?;
+        for key in &plan.stale {
+            store.remove_embedding(key)?;
+        }
+        if plan.pending.is_empty() {
+            return Ok(format!("The index of {} is up to date: {} chunk(s) of {} file(s)", root.display(), plan.chunks, plan.files));
+        }
+        let (tx, updates) = mpsc::unbounded_channel();
+        let total = plan.pending.len();
+        tokio::spawn(file_index::embed(self.backend.clone(), self.index_config.model.clone(), plan.pending, tx));
+        let message = format!(
+            "Indexing {} chunk(s) of {} file(s) in {} with {}, {} unchanged; /ask-project answers from it once done",
+            total, plan.files, root.display(), self.index_config.model, plan.chunks - total,
+        );
+        self.indexing = Some(Indexing { root, prefix, done: 0, total, updates });
+        Ok(message)
+    }
+    
+    /// Store the embeddings computed by the indexing task, reporting once it ends
+    fn poll_indexing(&mut self) {
+        let Some(indexing) = &mut self.indexing else {
+            return;
+        };
+        let outcome = loop {
+            match indexing.updates.try_recv() {
+                Ok(Ok(batch)) => {
+                    let stored = self.store.as_ref().map_or(Ok(()), |store| {
+                        batch.iter().try_for_each(|(chunk, vector)| store.put_embedding(&chunk.key(&indexing.prefix), file_index::SOURCE, vector))
+                    });
+                    if let Err(e) = stored {
+                        break Some(Err(format!("{:#}", e)));
+                    }
+                    indexing.done += batch.len();
+                }
+                Ok(Err(e)) => break Some(Err(e)),
+                Err(mpsc::error::TryRecvError::Empty) => break None,
+                Err(mpsc::error::TryRecvError::Disconnected) => break Some(Ok(())),
+            }
+        };
+        let Some(outcome) = outcome else {
+            return;
+        };
+        let Some(indexing) = self.indexing.take() else {
+            return;
+        };
+        let message = match outcome {
+            Ok(()) => format!("Indexed {}; ask about it with /ask-project <question>", indexing.root.display()),
+            Err(e) => format!(
+                "Indexing {} stopped after {} of {} chunks: {}\n/index carries on from there.",
+                indexing.root.display(), indexing.done, indexing.total, e,
+            ),
+        };
+        self.push_message("Index", &message);
+    }
+    
+    /// Answer a question about the project from the indexed chunks nearest to it
+    async fn ask_project(&mut self, ai_command: String, question: String) -> Result<()> {
+        let root = std::path::PathBuf::from(self.pty_executor.working_dir());
+        let prefix = self.index_config.prefix(&root);
+        let found = match self.nearest_chunks(&root, &prefix, &question).await {
+            Ok(found) => found,
+            Err(e) => {
+                self.push_message("Index", &format!("Failed: {:#}", e));
+                return Ok(());
+            }
+        };
+        let (prompt, excerpts) = match file_index::prompt(&question, &found) {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                self.push_message("Index", &format!("Failed: {:#}", e));
+                return Ok(());
+            }
+        };
+        let mut draft = self.prompt_draft(ai_command);
+        draft.pieces[0].text = prompt;
+        draft.pieces.push(ContextPiece::context("Project files", excerpts));
+        draft.footer = Some(file_index::sources(&found));
+        self.send_or_preview(draft).await
+    }
+    
+    /// Find the indexed chunks of `root` nearest to a question, read from the files as they are now
+    async fn nearest_chunks(&self, root: &std::path::Path, prefix: &str, question: &str) -> Result<Vec<Chunk>> {
+        Capability::Embeddings.require()?;
+        if question.is_empty() {
+            anyhow::bail!("ask a question, as in {} where is the configuration read?", file_index::ASK_COMMAND);
+        }
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("the index needs the database, which could not be opened"))?;
+        if store.embedding_keys(prefix)?.is_empty() {
+            anyhow::bail!("{} is not indexed yet; /index indexes it", root.display());
+        }
+        let model = &self.index_config.model;
+        let query = self.backend.embeddings(model, &[question.to_string()]).await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("{} returned no embedding for the question", model))?;
+        let nearest = store.nearest_embeddings_in(prefix, &query, self.index_config.results)?;
+        Ok(nearest.iter().filter_map(|(key, _)| Chunk::from_key(key, prefix, root)).collect())
+    }
+    
+    /// List the scripts and the keys they bound, or run them again
+    fn scripts_command(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                if self.scripts.loaded().is_empty() {
+                    let dir = scripting::scripts_dir().map_or("the scripts directory of the config directory".to_string(), |dir| dir.display().to_string());
+                    return Ok(format!("No scripts. Add .lua files to {}, then /scripts reload.", dir));
+                }
+                let keys = self.scripts.bound_keys();
+                let keys = if keys.is_empty() { "none".to_string() } else { keys.join(", ") };
+                Ok(format!("Scripts: {}\nKeys bound: {}", self.scripts.loaded().join(", "), keys))
             }
+            ["reload"] => Ok(format!("Loaded {} script(s)", self.reload_scripts()?)),
+            _ => anyhow::bail!("usage: /scripts [list] | /scripts reload"),
         }
-        
-        Ok(())
     }
     
-    /// Handle key events in help mode
-    async fn handle_help_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::F(1) | KeyCode::Esc => {
-                self.mode = AppMode::Chat;
+    /// Run the queued commands, one at a time once nothing else is running
+    async fn poll_queued_commands(&mut self) {
+        if !matches!(self.ui_state, UIState::Normal) || self.is_generating || self.workflow_run.is_some() {
+            return;
+        }
+        if let Some(command) = self.queued_commands.pop_front()
+            && let Err(e) = self.submit_shell_command(command, Vec::new()).await
+        {
+            self.push_message("Shell", &format!("{:#}", e));
+        }
+    }
+    
+    /// List the custom palette commands, or read the plugin files again
+    fn commands(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            [] | ["list"] => {
+                let lines: Vec<String> = self.command_palette.commands()
+                    .iter()
+                    .filter(|command| command.id.starts_with(custom_commands::PALETTE_PREFIX))
+                    .map(|command| format!("{}: {}  {}", command.category, command.name, command.action.text().unwrap_or_default()))
+                    .collect();
+                if lines.is_empty() {
+                    let dir = custom_commands::plugins_dir().map_or("the commands directory of the config directory".to_string(), |dir| dir.display().to_string());
+                    return Ok(format!("No custom commands. Add [[commands]] to the configuration or a plugin file to {}, then /commands reload.", dir));
+                }
+                Ok(lines.join("\n"))
             }
-            KeyCode::F(10) => {
-                self.should_quit = true;
+            ["reload"] => Ok(format!("Loaded {} custom command(s)", self.reload_commands()?)),
+            _ => anyhow::bail!("usage: /commands [list] | /commands reload"),
+        }
+    }
+    
+    /// Start running a workflow; its steps run from the event loop, one block each
+    fn start_workflow(&mut self, name: &str) -> Result<String> {
+        if let Some(run) = &self.workflow_run {
+            anyhow::bail!("{} is still running; stop it with /workflow stop", run.workflow().name);
+        }
+        let workflow = self.workflows.get(name).cloned().ok_or_else(|| anyhow::anyhow!("there is no workflow {}", name))?;
+        let message = format!("Running {} in {} steps", workflow.name, workflow.steps.len());
+        self.workflow_run = Some(WorkflowRun::new(workflow, std::path::Path::new(self.pty_executor.working_dir())));
+        Ok(message)
+    }
+    
+    /// Run the next step of the running workflow, or report on it once it is finished
+    async fn poll_workflow(&mut self) {
+        // Steps wait for popups to close and for other commands to finish
+        if !matches!(self.ui_state, UIState::Normal) || self.is_generating {
+            return;
+        }
+        let Some(run) = &self.workflow_run else {
+            return;
+        };
+        let Some(step) = run.next_step().cloned() else {
+            if let Some(run) = self.workflow_run.take() {
+                let mut block = CommandBlock::new(format!("▶ Workflow {}", run.workflow().name), self.pty_executor.working_dir().to_string());
+                block.set_state(if run.succeeded() { BlockState::Success } else { BlockState::Failed });
+                block.append_output(&run.report(), false);
+                self.push_block(block);
             }
-            _ => {}
+            return;
+        };
+        let dir = run.step_dir(&step);
+        self.command_env = run.step_env(&step);
+        let snapshot = self.impact.enabled && impact::is_high_risk(&step.run);
+        let started = Instant::now();
+        let result = self.run_shell_command_in(step.run.clone(), snapshot, dir.to_string_lossy().into_owned()).await;
+        let exit_code = match result {
+            Ok(()) => self.pane_manager.focused_pane()
+                .and_then(|pane| pane.command_blocks.last())
+                .and_then(|block| block.exit_code)
+                .unwrap_or(-1),
+            Err(e) => {
+                tracing::warn!("Workflow step `{}` could not run: {:?}", step.run, e);
+                -1
+            }
+        };
+        if let Some(run) = &mut self.workflow_run {
+            run.record(exit_code, started.elapsed());
         }
-        Ok(())
     }
     
-    /// Handle AI commands (starting with /)
-    async fn handle_ai_command(&mut self) -> Result<()> {
-        // Create a command block for the AI interaction
-        let working_dir = self.pty_executor.working_dir().to_string();
-        let block = CommandBlock::new(self.input.clone(), working_dir);
-        
-        // Add block to UI
-        self.command_blocks.push(block.clone());
+    /// Change the directory commands run in, returning the new directory
+    fn change_dir(&mut self, dir: Option<&str>) -> Result<String> {
+        let target = match dir {
+            Some(dir) => std::path::Path::new(self.pty_executor.working_dir()).join(expand_home(dir)),
+            None => dirs::home_dir().ok_or_else(|| anyhow::anyhow!("no home directory"))?,
+        };
+        let target = target.canonicalize()
+            .map_err(|e| anyhow::anyhow!("{}: {}", target.display(), e))?;
+        if !target.is_dir() {
+            anyhow::bail!("{} is not a directory", target.display());
+        }
+        let target = target.to_string_lossy().into_owned();
+        self.pty_executor.set_working_dir(target.clone());
+        self.load_project();
+        Ok(target)
+    }
+    
+    /// Load the configuration of the project the working directory is in
+    fn load_project(&mut self) {
+        self.project = Project::find(std::path::Path::new(self.pty_executor.working_dir()))
+            .map_err(|e| tracing::warn!("Failed to load the project configuration: {:?}", e))
+            .ok()
+            .flatten();
+        let commands = self.project.as_ref().map(Project::palette_commands).unwrap_or_default();
+        self.command_palette.set_category("Project", commands);
+        self.load_project_memory();
+    }
+    
+    /// Load the memory file of the working directory or its repository
+    fn load_project_memory(&mut self) {
+        self.project_memory = ProjectMemory::find(std::path::Path::new(self.pty_executor.working_dir()))
+            .map_err(|e| tracing::warn!("Failed to load the project memory: {:?}", e))
+            .ok()
+            .flatten();
+    }
+    
+    /// Handle `/init`: scan the working directory and start the interview for its `.ai-terminal.toml`
+    fn init_project(&mut self) -> Result<String> {
+        let scan = ProjectScan::scan(std::path::Path::new(self.pty_executor.working_dir()));
+        let found = if scan.build_files.is_empty() {
+            "No build files found".to_string()
+        } else {
+            format!("Found {} ({})", scan.languages.join(", "), scan.build_files.join(", "))
+        };
+        let interview = ProjectInterview::new(scan);
+        let exists = if interview.path().exists() { "; the existing file is replaced once the new one is approved" } else { "" };
+        let message = format!("{}. Answer a few questions to draft {}{}.", found, interview.path().display(), exists);
+        self.ask_project_question(interview);
+        Ok(message)
+    }
+    
+    /// Ask the next `/init` question
+    fn ask_project_question(&mut self, interview: ProjectInterview) {
+        if let Some((question, suggestion)) = interview.question() {
+            let prompt = PathPrompt::new("Project setup (/init)", question, suggestion);
+            self.project_interview = Some((interview, prompt));
+            self.ui_state = UIState::ProjectInterview;
+        }
+    }
+    
+    /// Have the model draft the project configuration from the interview, then preview it
+    async fn draft_project(&mut self, interview: ProjectInterview) {
+        let response = if self.offline.is_offline() {
+            None
+        } else {
+            let request = ChatRequest::new(self.active_model().to_string(), interview.prompt());
+            self.is_generating = true;
+            let result = self.backend.generate(&request).await;
+            self.is_generating = false;
+            result.map_err(|e| tracing::warn!("Failed to draft the project configuration: {:?}", e)).ok()
+        };
+        let config = interview.finish(response.as_ref().map(|response| response.text.as_str()));
+        match project::to_toml(&config) {
+            Ok(toml) => {
+                let path = interview.path();
+                self.show_confirmation_modal(PROJECT_CONFIRMATION, &format!("Write {}?\n\n{}", path.display(), toml));
+                self.pending_project = Some((path, config));
+            }
+            Err(e) => self.push_message("Init", &format!("Failed: {:#}", e)),
+        }
+    }
+    
+    /// Handle `/backup [path] [--encrypt]`
+    fn backup(&mut self, args: Vec<&str>) -> Result<String> {
+        let encrypt = args.contains(&"--encrypt");
+        let destination = match args.iter().find(|arg| !arg.starts_with("--")) {
+            Some(path) => expand_home(path),
+            None => persistence::data_dir()
+                .join("backups")
+                .join(format!("backup-{}.aitb", chrono::Local::now().format("%Y%m%d-%H%M%S"))),
+        };
+        let passphrase = if encrypt {
+            Some(std::env::var(backup::PASSPHRASE_VAR)
+                .map_err(|_| anyhow::anyhow!("set {} to encrypt backups", backup::PASSPHRASE_VAR))?)
+        } else {
+            None
+        };
+        let Some(store) = &self.store else {
+            anyhow::bail!("the database is not available");
+        };
         
-        // Clear input
-        let ai_command = self.input.clone();
-        self.input.clear();
-        self.history_index = None;
+        let manifest = backup::create_backup(store, &BackupPaths::current(), &destination, passphrase.as_deref())?;
+        Ok(format!(
+            "Backed up {} file(s) to {}{}",
+            manifest.files.len(),
+            destination.display(),
+            if encrypt { " (encrypted)" } else { "" },
+        ))
+    }
+    
+    /// Handle `/restore <path> [--only section,...] [--verify]`
+    fn restore(&mut self, args: Vec<&str>) -> Result<String> {
+        let mut path = None;
+        let mut sections = Section::ALL.to_vec();
+        let mut verify_only = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg {
+                "--verify" => verify_only = true,
+                "--only" => {
+                    let names = args.next().ok_or_else(|| anyhow::anyhow!("--only needs a list of sections"))?;
+                    sections = names
+                        .split(',')
+                        .map(|name| Section::parse(name).ok_or_else(|| anyhow::anyhow!(
+                            "unknown section '{}' (expected one of: {})",
+                            name,
+                            Section::ALL.map(Section::name).join(", "),
+                        )))
+                        .collect::<Result<_>>()?;
+                }
+                _ => path = Some(expand_home(arg)),
+            }
+        }
+        let path = path.ok_or_else(|| anyhow::anyhow!("usage: /restore <file> [--only history,themes] [--verify]"))?;
         
-        // Process the AI command
-        self.is_generating = true;
+        let passphrase = std::env::var(backup::PASSPHRASE_VAR).ok();
+        let backup = backup::read_backup(&path, passphrase.as_deref())?;
+        let available: Vec<&str> = backup.sections().into_iter().map(Section::name).collect();
+        if verify_only {
+            return Ok(format!(
+                "Backup from {} verified: {} file(s), sections: {}",
+                backup.manifest.created_at.format("%Y-%m-%d %H:%M"),
+                backup.manifest.files.len(),
+                available.join(", "),
+            ));
+        }
         
-        // For now, we'll just simulate an AI response
-        // In a real implementation, this would call the Ollama API
-        let ai_response = format!("This is a simulated response to: {}\n\nIn a real implementation, this would call the Ollama API.", &ai_command[1..]);
+        let Some(store) = &mut self.store else {
+            anyhow::bail!("the database is not available");
+        };
+        let restored = backup::restore_backup(&backup, store, &BackupPaths::current(), &sections)?;
         
-        // Update the last block with the AI response
-        if let Some(last_block) = self.command_blocks.last_mut() {
-            last_block.append_output(&ai_response, false);
-            last_block.complete(0, std::time::Duration::from_millis(100));
+        // Pick up the restored data in the running session
+        self.command_history.reload()?;
+        if restored.contains(&Section::Themes) {
+            if let Err(e) = self.theme_manager.load_user_themes() {
+                tracing::warn!("Failed to load user themes: {:?}", e);
+            }
+            if let Err(e) = self.theme_manager.load_saved_selection() {
+                tracing::warn!("Failed to restore saved theme: {:?}", e);
+            }
+        }
+        if restored.contains(&Section::Bookmarks) {
+            self.bookmarks = Bookmarks::from_store(store)?;
+        }
+        if restored.contains(&Section::Logs) {
+            self.llm_log.reset();
         }
         
-        self.is_generating = false;
-        Ok(())
+        if restored.is_empty() {
+            Ok("Nothing to restore: the backup has none of the requested sections.".to_string())
+        } else {
+            Ok(format!("Restored {}", restored.into_iter().map(Section::name).collect::<Vec<_>>().join(", ")))
+        }
     }
     
     /// Navigate to the previous command in history
     fn navigate_history_up(&mut self) {
+        // Pick up commands run in other instances before navigating
+        if self.history_index.is_none()
+            && let Err(e) = self.command_history.reload()
+        {
+            tracing::warn!("Failed to reload history: {:?}", e);
+        }
+        
         let history_len = self.command_history.entries().len();
         if history_len == 0 {
             return;
@@ -441,14 +5357,14 @@ Use F1 for help, F10 to quit.", false);
         
         // Save current input if we're just starting to navigate
         if self.history_index.is_none() {
-            self.input_before_history = self.input.clone();
+            self.input_before_history = self.input.as_str().to_string();
         }
         
         let current_index = self.history_index.unwrap_or(0);
         if current_index < history_len {
             self.history_index = Some(current_index + 1);
             if let Some(entry) = self.command_history.get_command(current_index) {
-                self.input = entry.command.clone();
+                self.input.set(entry.command.clone());
             }
         }
     }
@@ -459,12 +5375,12 @@ Use F1 for help, F10 to quit.", false);
             Some(index) if index > 0 => {
                 self.history_index = Some(index - 1);
                 if let Some(entry) = self.command_history.get_command(index - 1) {
-                    self.input = entry.command.clone();
+                    self.input.set(entry.command.clone());
                 }
             }
             Some(0) => {
                 // We've reached the end of history, restore the input
-                self.input = self.input_before_history.clone();
+                self.input.set(self.input_before_history.clone());
                 self.history_index = None;
             }
             _ => {
@@ -473,16 +5389,37 @@ Use F1 for help, F10 to quit.", false);
         }
     }
     
-    /// Handle tab completion for file paths
+    /// Handle tab completion for file paths and bookmarks in the last word of the input
     fn handle_tab_completion(&mut self) {
-        if self.input.is_empty() {
+        let cursor = self.input.cursor();
+        let start = self.input.as_str()[..cursor].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &self.input.as_str()[start..cursor];
+        if word.is_empty() {
             return;
         }
         
-        // Try to complete the file path
-        let completed = self.complete_file_path(&self.input);
+        let completed = match word.strip_prefix('@') {
+            // Bookmark names complete to the bookmark's directory
+            Some(prefix) if !prefix.contains('/') => match self.bookmarks.complete_name(prefix).first() {
+                Some(name) => format!("@{}/", name),
+                None => return,
+            },
+            // Paths under a bookmark complete like paths, keeping the @name form
+            Some(rest) => {
+                let name = rest.split('/').next().unwrap_or_default();
+                let (Some(base), Some(resolved)) = (self.bookmarks.get(name), self.bookmarks.resolve(word)) else {
+                    return;
+                };
+                let completed = self.complete_file_path(&resolved.to_string_lossy());
+                match completed.strip_prefix(base.to_string_lossy().as_ref()) {
+                    Some(sub_path) => format!("@{}{}", name, sub_path),
+                    None => return,
+                }
+            }
+            None => self.complete_file_path(word),
+        };
         if !completed.is_empty() {
-            self.input = completed;
+            self.input.replace_range(start..cursor, &completed);
         }
     }
     
@@ -592,15 +5529,93 @@ Use F1 for help, F10 to quit.", false);
     }
     
     /// Execute commands from the palette
-    fn execute_palette_command(&mut self, command: &Command) -> Result<()> {
+    /// Remember a command run from the palette, so it is listed first next time
+    fn record_palette_use(&mut self, id: &str) {
+        self.command_palette.record_use(id);
+        let path = recent_commands_path();
+        let recent = self.command_palette.recent().join("\n");
+        if let Err(e) = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(&path, recent)) {
+            tracing::warn!("Failed to save recently used commands to {}: {:?}", path.display(), e);
+        }
+    }
+    
+    /// Start the action of a palette command, asking for the arguments its placeholders stand for
+    ///
+    /// The argument typed after the command's name fills the placeholders,
+    /// or is added to the end of a text without any.
+    async fn start_action(&mut self, action: Action, argument: Option<&str>) -> Result<()> {
+        let text = action.text().unwrap_or_default().to_string();
+        match PlaceholderPrompt::with_arguments(text.clone(), argument.unwrap_or_default()) {
+            Some(prompt) if !prompt.is_complete() => {
+                self.placeholder_prompt = Some(prompt);
+                self.placeholder_action = Some(action);
+                self.ui_state = UIState::PlaceholderPrompt;
+                Ok(())
+            }
+            Some(prompt) => {
+                let (text, env) = prompt.finish();
+                self.perform_action(action.with_text(text), env).await
+            }
+            None => {
+                let text = match argument {
+                    Some(argument) => format!("{} {}", text.trim_end(), argument),
+                    None => text,
+                };
+                self.perform_action(action.with_text(text), Vec::new()).await
+            }
+        }
+    }
+    
+    /// Perform an action with its placeholders filled in; `env` holds the secrets of a shell command
+    async fn perform_action(&mut self, action: Action, env: Vec<(String, String)>) -> Result<()> {
+        match action {
+            Action::Builtin => {}
+            Action::Run(command) => self.submit_shell_command(command, env).await?,
+            Action::Insert(text) => self.input.set(text),
+            // Prompts and slash commands go through the input line, which is given back afterwards
+            Action::Ask(prompt) => {
+                let typed = self.input.take();
+                self.input.set(format!("/{}", prompt));
+                self.handle_ai_command().await?;
+                if self.input.is_empty() {
+                    self.input.set(typed);
+                }
+            }
+            Action::Slash(command) => {
+                let typed = self.input.take();
+                self.input.set(command);
+                if !self.handle_builtin_command()? {
+                    self.handle_ai_command().await?;
+                }
+                if self.input.is_empty() {
+                    self.input.set(typed);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Run a command chosen in the palette, with the argument typed after its name
+    async fn execute_palette_command(&mut self, command: &Command, argument: Option<&str>) -> Result<()> {
+        if command.action != Action::Builtin {
+            return self.start_action(command.action.clone(), argument).await;
+        }
         match command.id.as_str() {
             "new_session" => {
                 // Implement new session logic
-                self.command_blocks.clear();
+                self.clear_focused_pane();
                 self.add_welcome_message();
             }
             "clear_screen" => {
-                self.command_blocks.clear();
+                self.clear_focused_pane();
+            }
+            "clear_outputs" => {
+                let message = self.clear(vec!["outputs"]).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message("Clear", &message);
+            }
+            "clear_ai" => {
+                let message = self.clear(vec!["ai"]).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message("Clear", &message);
             }
             "toggle_help" => {
                 self.mode = match self.mode {
@@ -613,10 +5628,13 @@ Use F1 for help, F10 to quit.", false);
                 self.show_confirmation_modal("Confirm Exit", "Are you sure you want to exit the AI Terminal?");
             }
             "scroll_up" => {
-                self.scroll_offset = self.scroll_offset.saturating_add(5);
+                self.apply_motion(Motion::HalfPageUp);
             }
             "scroll_down" => {
-                self.scroll_offset = self.scroll_offset.saturating_sub(5);
+                self.apply_motion(Motion::HalfPageDown);
+            }
+            "jump_last_error" => {
+                self.apply_motion(Motion::LastError);
             }
             "toggle_theme" => {
                 // Simple theme toggle between default and dark
@@ -631,9 +5649,7 @@ Use F1 for help, F10 to quit.", false);
             "save_theme" => {
                 // For now, we'll just show a message that the theme was saved
                 // In a real implementation, you would prompt for a theme name and save it
-                let mut block = CommandBlock::new("Theme".to_string(), "".to_string());
-                block.append_output("Current theme saved successfully!", false);
-                self.command_blocks.push(block);
+                self.push_message("Theme", "Current theme saved successfully!");
                 
                 // In a full implementation, we would do something like:
                 // self.theme_manager.save_current_theme("my-theme")?;
@@ -647,16 +5663,603 @@ Use F1 for help, F10 to quit.", false);
 ", name));
                 }
                 
-                let mut block = CommandBlock::new("Themes".to_string(), "".to_string());
-                block.append_output(&output, false);
-                self.command_blocks.push(block);
+                self.push_message("Themes", &output);
+            }
+            "select_theme" if argument.is_some() => {
+                self.set_theme(argument.unwrap_or_default());
+            }
+            "select_theme" => {
+                let names = self.theme_manager.available_theme_names()
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                let current = self.theme_manager.current_theme().name.clone();
+                self.theme_picker = Some(ThemePicker::new(names, &current));
+                self.ui_state = UIState::ThemePicker;
+            }
+            "restore_session" => {
+                let message = match self.restore_session() {
+                    Ok(0) => "No saved session to restore.".to_string(),
+                    Ok(count) => format!("Restored {} block(s) from the last session", count),
+                    Err(e) => format!("Could not restore the last session: {}", e),
+                };
+                self.push_message("Session", &message);
+            }
+            "copy_block_output" => {
+                let message = match self.target_block() {
+                    Some(block) => match share::copy_to_clipboard(&block.output.text()) {
+                        Ok(()) => None,
+                        Err(e) => Some(format!("Could not copy the output of `{}`: {}", block.command, e)),
+                    },
+                    None => Some("There is no block to copy.".to_string()),
+                };
+                if let Some(message) = message {
+                    self.push_message("Copy", &message);
+                }
+            }
+            "save_block_output" => {
+                let message = self.save_block_output()
+                    .unwrap_or_else(|e| format!("Could not save the block output: {:#}", e));
+                self.push_message("Output", &message);
+            }
+            "usage_stats" => {
+                let message = self.usage_summary()
+                    .unwrap_or_else(|e| format!("Could not read usage statistics: {}", e));
+                self.push_message("Usage", &message);
+            }
+            "export_transcript" => {
+                let suggestion = std::path::Path::new(self.pty_executor.working_dir()).join(match argument {
+                    Some(path) => expand_home(path),
+                    None => format!("session-{}.md", chrono::Local::now().format("%Y%m%d-%H%M%S")).into(),
+                });
+                self.export_prompt = Some(PathPrompt::new(
+                    "Export Session Transcript",
+                    "Format follows the extension: .md, .html or .json",
+                    suggestion.to_string_lossy(),
+                ));
+                self.ui_state = UIState::ExportPrompt;
+            }
+            "select_persona" if argument.is_some() => {
+                let message = self.use_persona(argument.map(str::to_string))
+                    .unwrap_or_else(|e| format!("Could not switch persona: {:#}", e));
+                self.push_message("Persona", &message);
+            }
+            "select_persona" => {
+                self.open_persona_picker();
+            }
+            "edit_persona" => {
+                let current = self.active_persona().map(|persona| persona.name.clone());
+                self.open_persona_editor(current.as_deref());
+            }
+            "models" if self.backend_kind != BackendKind::Ollama => {
+                self.push_message("Models", &format!(
+                    "Managing models needs the Ollama backend; the {} backend is configured. Use F2 to pick a model.",
+                    self.backend.name(),
+                ));
+            }
+            "models" => {
+                self.open_models_view().await;
+            }
+            "stats" => {
+                self.push_message("Token Usage", &self.stats.report());
+            }
+            "toggle_raw_output" => {
+                let changed = self.pane_manager.focused_pane_mut().map_or(0, |pane| pane.toggle_raw_output());
+                if changed == 0 {
+                    self.push_message("Output", "No block in this pane has an enhanced view.");
+                }
+            }
+            "toggle_chat" => {
+                self.toggle_chat();
+            }
+            "chat_send_block" => {
+                let message = self.chat_command([Some("send"), argument].into_iter().flatten().collect())
+                    .unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.input.set(self.staged_input.take().unwrap_or_default());
+                self.push_message("Chat", &message);
+            }
+            "smart_paste_clean" => {
+                let message = self.clean_paste();
+                self.toast = Some(Toast::new(&message));
+            }
+            "smart_paste_adapt" => {
+                let message = self.adapt_paste().await;
+                self.toast = Some(Toast::new(&message));
+            }
+            "memory_learn" => {
+                self.learn_project_fact().await;
+            }
+            "doctor" => {
+                self.run_doctor().await;
+            }
+            "bench_explain" => {
+                self.explain_bench(bench::EXPLAIN_COMMAND.to_string()).await?;
+            }
+            "explain_command" => {
+                self.explain_input().await;
+            }
+            "toggle_summaries" => {
+                let changed = self.pane_manager.focused_pane_mut().map_or(0, |pane| pane.toggle_summaries());
+                if changed == 0 {
+                    self.push_message("Summary", "No block in this pane has a summary; /summarize writes one.");
+                }
+            }
+            "toggle_runs" => {
+                let changed = self.pane_manager.focused_pane_mut().map_or(0, |pane| pane.toggle_runs());
+                if changed == 0 {
+                    self.push_message("Repeats", "No repeated commands were collapsed in this pane.");
+                }
+            }
+            "toggle_offline" => {
+                self.toggle_offline().await?;
+            }
+            "toggle_reduced_motion" => {
+                self.accessibility.reduced_motion = !self.accessibility.reduced_motion;
+                let state = if self.accessibility.reduced_motion { "on" } else { "off" };
+                self.push_message("Accessibility", &format!("Reduced motion is {}", state));
+            }
+            "capabilities" => {
+                self.push_message("Capabilities", &capabilities::report());
+            }
+            "toggle_monitor" => {
+                let message = self.toggle_monitor();
+                self.push_message("Monitor", &message);
+            }
+            "git_commit_message" => {
+                self.write_commit_message().await?;
+            }
+            "git_explain_diff" => {
+                self.explain_diff().await?;
+            }
+            "copy_mode" => {
+                self.enter_copy_mode(false);
+            }
+            "hints" => {
+                self.enter_hint_mode();
+            }
+            "copy_last_output" => {
+                self.enter_copy_mode(true);
+            }
+            "toggle_file_browser" => {
+                self.toggle_file_browser();
+            }
+            "pins_panel" => {
+                if let Err(e) = self.open_pins() {
+                    self.push_message("Pins", &format!("Failed: {:#}", e));
+                }
+            }
+            "pin_block" => {
+                let message = self.pin_block().unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message("Pins", &message);
+            }
+            "process_manager" => {
+                let message = self.open_process_view();
+                self.push_message("Processes", &message);
+            }
+            "table_view" => {
+                if let Err(e) = self.table(Vec::new()) {
+                    self.push_message("Tables", &format!("Failed: {:#}", e));
+                }
+            }
+            "json_viewer" => {
+                if let Err(e) = self.json(Vec::new()) {
+                    self.push_message("JSON", &format!("Failed: {:#}", e));
+                }
+            }
+            "log_viewer" => {
+                if let Err(e) = self.log(Vec::new()) {
+                    self.push_message("Logs", &format!("Failed: {:#}", e));
+                }
+            }
+            "table_inline" => {
+                let message = self.table(vec!["inline"]).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message("Tables", &message);
+            }
+            "lock_session" => {
+                let message = self.lock();
+                self.push_message("Lock", &message);
+            }
+            "share_session" => {
+                let message = self.share(Vec::new()).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message(ArtifactKind::WebShare.label(), &message);
+            }
+            "share_block" => {
+                let message = self.share(vec!["block"]).unwrap_or_else(|e| format!("Failed: {:#}", e));
+                self.push_message(ArtifactKind::BlockShare.label(), &message);
+            }
+            "bug_report" => {
+                let destination = reports_dir()
+                    .join(format!("bug-report-{}.md", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+                let artifact = Artifact::new(ArtifactKind::BugReport, destination, self.bug_report());
+                self.write_artifact(artifact);
+            }
+            id if id.starts_with(scripting::PALETTE_PREFIX) => {
+                let result = self.scripts.run_command(id, argument);
+                self.apply_script_effects(result);
+            }
+            id if id.starts_with(plugins::PALETTE_PREFIX) => {
+                let effects = self.plugins.run_command(id, argument);
+                for command in self.apply_plugin_effects(effects) {
+                    self.submit_shell_command(command, Vec::new()).await?;
+                }
             }
             _ => {
                 // Handle unknown commands
             }
         }
         
-        Ok(())
+        Ok(())
+    }
+    
+    /// Save the blocks of every pane as this instance's session
+    fn save_session(&mut self) -> Result<()> {
+        let (Some(store), Some(id)) = (&mut self.store, &self.instance_id) else {
+            return Ok(());
+        };
+        let blocks: Vec<SessionBlock> = self.pane_manager.panes()
+            .iter()
+            .enumerate()
+            .flat_map(|(pane, p)| {
+                p.command_blocks.iter().map(move |block| SessionBlock { pane, block: block.clone() })
+            })
+            .collect();
+        
+        store.save_instance_session(id, &blocks, KEPT_SESSIONS)?;
+        store.unregister_instance(id)
+    }
+    
+    /// Load the most recent session saved by another instance into the focused pane
+    fn restore_session(&mut self) -> Result<usize> {
+        let Some(store) = &self.store else {
+            anyhow::bail!("the database is not available");
+        };
+        let blocks = match store.latest_session(self.instance_id.as_deref())? {
+            Some(name) => store.load_session(&name)?.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let count = blocks.len();
+        for saved in blocks {
+            self.push_block(saved.block);
+        }
+        Ok(count)
+    }
+    
+    /// Write the complete output of the focused pane's latest block to a file
+    fn save_block_output(&self) -> Result<String> {
+        let Some(block) = self.target_block() else {
+            anyhow::bail!("there is no block to save");
+        };
+        
+        let destination = persistence::data_dir()
+            .join("output")
+            .join(format!("output-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&destination, block.full_output()?)?;
+        Ok(format!("Saved the full output of `{}` to {}", block.command, destination.display()))
+    }
+    
+    /// Get the block clicked in the focused pane, or its latest block
+    fn target_block(&self) -> Option<&CommandBlock> {
+        let pane = self.pane_manager.focused_pane()?;
+        pane.selected_block().or_else(|| pane.command_blocks.last())
+    }
+    
+    /// Get the block `[n]` back from the latest command of the focused pane, 1 by default
+    fn nth_block_back(&self, args: &[&str], usage: &str) -> Result<&CommandBlock> {
+        let back = match args {
+            [] => 1,
+            [n] => n.parse().ok().filter(|n| *n > 0).ok_or_else(|| anyhow::anyhow!(usage.to_string()))?,
+            _ => anyhow::bail!(usage.to_string()),
+        };
+        // Messages from the terminal itself have no working directory
+        self.pane_manager.focused_pane()
+            .and_then(|pane| pane.command_blocks.iter().rev().filter(|block| !block.working_dir.is_empty()).nth(back - 1))
+            .ok_or_else(|| anyhow::anyhow!("the pane has no block {} back", back))
+    }
+    
+    /// Get the clicked block of the focused pane for changing it, or its latest one
+    fn target_block_mut(&mut self) -> Option<&mut CommandBlock> {
+        let pane = self.pane_manager.focused_pane_mut()?;
+        if pane.selected_block().is_some() {
+            return pane.selected_block_mut();
+        }
+        pane.command_blocks.last_mut()
+    }
+    
+    /// Handle `/tag <tag>...` and `/tag remove <tag>...` on the clicked block, or the latest one
+    fn tag(&mut self, args: Vec<&str>) -> Result<String> {
+        let (remove, tags) = match args.as_slice() {
+            ["remove", tags @ ..] if !tags.is_empty() => (true, tags),
+            [] | ["remove"] => anyhow::bail!("usage: /tag <tag>... | /tag remove <tag>..."),
+            tags => (false, tags),
+        };
+        let block = self.target_block_mut()
+            .filter(|block| !block.working_dir.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("there is no block to tag"))?;
+        let changed: Vec<&str> = tags.iter()
+            .copied()
+            .filter(|tag| if remove { block.remove_tag(tag) } else { block.add_tag(tag) })
+            .collect();
+        if changed.is_empty() {
+            anyhow::bail!("nothing changed; tags are single words and each is given once");
+        }
+        let all = block.all_tags();
+        Ok(format!(
+            "`{}` is tagged {}",
+            block.command,
+            if all.is_empty() { "with nothing".to_string() } else { hashtags(&all) },
+        ))
+    }
+    
+    /// Handle `/blocks [tag]...`, `/blocks saved [tag]...` and `/blocks export <path> [tag]...`
+    fn blocks(&mut self, args: Vec<&str>) -> Result<String> {
+        match args.as_slice() {
+            ["saved", tags @ ..] => {
+                let Some(store) = &self.store else {
+                    anyhow::bail!("the database is not available");
+                };
+                let filter = TagFilter::parse(tags.iter().copied());
+                let found = store.find_blocks(&filter)?;
+                if found.is_empty() {
+                    return Ok(format!("No saved blocks{}.", describe_filter(&filter)));
+                }
+                let lines: Vec<String> = found.iter()
+                    .map(|(session, saved)| format!("{}  {}", session, describe_tagged_block(&saved.block)))
+                    .collect();
+                Ok(lines.join("\n"))
+            }
+            ["export", path, tags @ ..] => {
+                let filter = TagFilter::parse(tags.iter().copied());
+                let destination = std::path::Path::new(self.pty_executor.working_dir()).join(expand_home(path));
+                let title = format!("AI Terminal session{}", describe_filter(&filter));
+                let transcript = Transcript::from_blocks(
+                    title,
+                    self.pane_manager.panes().iter().flat_map(|pane| pane.command_blocks.iter()).filter(|block| filter.matches(block)),
+                );
+                if transcript.entries.is_empty() {
+                    anyhow::bail!("there are no blocks{} to export", describe_filter(&filter));
+                }
+                let count = transcript.entries.len();
+                let content = transcript.render(ExportFormat::from_path(&destination));
+                self.write_artifact(Artifact::new(ArtifactKind::SessionExport, destination.clone(), content));
+                Ok(format!("Exporting {} block(s){} to {}", count, describe_filter(&filter), destination.display()))
+            }
+            ["export"] => anyhow::bail!("usage: /blocks export <path> [tag]..."),
+            tags => {
+                let filter = TagFilter::parse(tags.iter().copied());
+                let lines: Vec<String> = self.pane_manager.panes().iter()
+                    .enumerate()
+                    .flat_map(|(i, pane)| pane.command_blocks.iter().map(move |block| (i, block)))
+                    .filter(|(_, block)| !block.working_dir.is_empty() && filter.matches(block))
+                    .map(|(i, block)| format!("pane {}  {}", i + 1, describe_tagged_block(block)))
+                    .collect();
+                if lines.is_empty() {
+                    return Ok(format!("No blocks{}. /blocks saved searches saved sessions.", describe_filter(&filter)));
+                }
+                Ok(lines.join("\n"))
+            }
+        }
+    }
+    
+    /// Describe AI usage per model
+    fn usage_summary(&self) -> Result<String> {
+        let Some(store) = &self.store else {
+            anyhow::bail!("the database is not available");
+        };
+        let usage = store.usage_by_model()?;
+        if usage.is_empty() {
+            return Ok("No AI requests recorded yet.".to_string());
+        }
+        
+        let mut output = "AI requests by model:\n".to_string();
+        for model in usage {
+            output.push_str(&format!(
+                "- {}: {} request(s), {} failed, {:.1}s total, {} characters received\n",
+                model.model,
+                model.requests,
+                model.failures,
+                model.total_duration_ms as f64 / 1000.0,
+                model.response_chars,
+            ));
+        }
+        Ok(output)
+    }
+    
+    /// Write an outgoing artifact, letting the user review anything the scrubber flags
+    fn write_artifact(&mut self, artifact: Artifact) {
+        let pending = self.scrubber.prepare(artifact);
+        if pending.needs_review() {
+            self.scrub_review = Some(ScrubReview::new(pending));
+            self.ui_state = UIState::ScrubReview;
+        } else {
+            self.finish_artifact(pending);
+        }
+    }
+    
+    /// Write a reviewed artifact and report the outcome
+    fn finish_artifact(&mut self, pending: PendingArtifact) {
+        // Web shares leave the machine, so they are confirmed every time
+        if pending.artifact().kind.is_upload() {
+            self.confirm_share(pending);
+            return;
+        }
+        let label = pending.artifact().kind.label();
+        let message = match pending.write() {
+            Ok(path) => format!(
+                "Written to {} ({} item(s) redacted)",
+                path.display(),
+                pending.redaction_count()
+            ),
+            Err(e) => format!("Could not write {}: {}", pending.artifact().destination.display(), e),
+        };
+        self.push_message(label, &message);
+    }
+    
+    /// Export the blocks of every pane as a transcript, in the format given by the path's extension
+    fn export_transcript(&mut self, path: &str) {
+        if path.is_empty() {
+            self.push_message(ArtifactKind::SessionExport.label(), "No file given; nothing was written.");
+            return;
+        }
+        
+        let destination = expand_home(path);
+        let transcript = Transcript::from_blocks(
+            "AI Terminal session",
+            self.pane_manager.panes().iter().flat_map(|pane| pane.command_blocks.iter()),
+        );
+        if transcript.entries.is_empty() {
+            self.push_message(ArtifactKind::SessionExport.label(), "There is nothing to export yet.");
+            return;
+        }
+        
+        let content = transcript.render(ExportFormat::from_path(&destination));
+        self.write_artifact(Artifact::new(ArtifactKind::SessionExport, destination, content));
+    }
+    
+    /// Handle `/share` and `/share block`, uploading the session transcript, or the clicked or latest block, once it is scrubbed and confirmed
+    fn share(&mut self, args: Vec<&str>) -> Result<String> {
+        if self.offline.is_offline() {
+            anyhow::bail!("{}", self.offline.reason());
+        }
+        let (kind, prefix, transcript) = match args.as_slice() {
+            [] => {
+                let transcript = Transcript::from_blocks(
+                    "AI Terminal session",
+                    self.pane_manager.panes().iter().flat_map(|pane| pane.command_blocks.iter()),
+                );
+                (ArtifactKind::WebShare, "session", transcript)
+            }
+            ["block"] => {
+                let block = self.target_block().ok_or_else(|| anyhow::anyhow!("there is no block to share"))?;
+                (ArtifactKind::BlockShare, "block", Transcript::from_blocks("AI Terminal block", [block]))
+            }
+            _ => anyhow::bail!("usage: /share | /share block"),
+        };
+        if transcript.entries.is_empty() {
+            anyhow::bail!("there is nothing to share yet");
+        }
+        
+        let file_name = format!("{}-{}.md", prefix, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        self.write_artifact(Artifact::new(kind, file_name, transcript.to_markdown()));
+        Ok(format!("Waiting for confirmation to upload to {}…", self.share_config.describe()))
+    }
+    
+    /// Ask before uploading a scrubbed session or block
+    fn confirm_share(&mut self, pending: PendingArtifact) {
+        let what = match pending.artifact().kind {
+            ArtifactKind::BlockShare => "the block",
+            _ => "the session",
+        };
+        let modal = ConfirmationModal::new(
+            SHARE_CONFIRMATION,
+            &format!(
+                "Upload {} ({} item(s) redacted) to {}? Anyone with the link can read it.",
+                what,
+                pending.redaction_count(),
+                self.share_config.describe(),
+            ),
+            vec![
+                ModalButton::new("no", "Cancel", true),
+                ModalButton::new("yes", "Upload", false),
+            ],
+        );
+        self.confirmation_modal = Some(modal);
+        self.ui_state = UIState::ConfirmationModal;
+        self.pending_share = Some(pending);
+    }
+    
+    /// Upload a confirmed session or block, showing the link and copying it to the clipboard
+    async fn upload_share(&mut self, pending: PendingArtifact) {
+        let upload = Upload {
+            file_name: pending.artifact().destination.to_string_lossy().to_string(),
+            description: match pending.artifact().kind {
+                ArtifactKind::BlockShare => "AI Terminal block",
+                _ => "AI Terminal session",
+            }.to_string(),
+            content: pending.scrubbed_content(),
+        };
+        let result = match self.share_config.provider() {
+            Ok(provider) => provider.upload(upload).await,
+            Err(e) => Err(e),
+        };
+        let message = match result {
+            Ok(link) => {
+                let copied = match share::copy_to_clipboard(&link) {
+                    Ok(()) => "; the link was copied to the clipboard",
+                    Err(_) => "",
+                };
+                format!("Shared at {} ({} item(s) redacted){}", link, pending.redaction_count(), copied)
+            }
+            Err(e) => format!("Could not upload: {:#}", e),
+        };
+        self.push_message(pending.artifact().kind.label(), &message);
+    }
+    
+    /// Build a bug report from the environment and the focused pane's recent blocks
+    fn bug_report(&self) -> String {
+        let mut report = format!(
+            "# AI Terminal bug report\n\n- Version: {}\n- OS: {} ({})\n- Theme: {}\n- Model: {}\n\n## Recent blocks\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            self.theme_manager.current_theme().name,
+            self.active_model(),
+        );
+        
+        let blocks = self.pane_manager.focused_pane()
+            .map(|pane| pane.command_blocks.as_slice())
+            .unwrap_or_default();
+        for block in blocks.iter().rev().take(BUG_REPORT_BLOCKS).rev() {
+            report.push_str(&format!(
+                "\n### `{}` ({:?}, exit {})\n\n```\n{}\n```\n",
+                block.command,
+                block.state,
+                block.exit_code.map_or("-".to_string(), |code| code.to_string()),
+                block.output.text().trim_end(),
+            ));
+        }
+        
+        report
+    }
+    
+    /// Remove all blocks from the focused pane
+    fn clear_focused_pane(&mut self) {
+        if let Some(pane) = self.pane_manager.focused_pane_mut() {
+            pane.command_blocks.clear();
+        }
+    }
+    
+    /// Apply the theme highlighted in the picker as a live preview
+    fn preview_selected_theme(&mut self) {
+        if let Some(name) = self.theme_picker.as_ref().and_then(|picker| picker.selected_theme()) {
+            let _ = self.theme_manager.switch_theme(name);
+        }
+    }
+    
+    /// Commit the theme highlighted in the picker and persist it to config
+    fn apply_selected_theme(&mut self) {
+        self.ui_state = UIState::Normal;
+        let Some(picker) = self.theme_picker.take() else {
+            return;
+        };
+        let Some(name) = picker.selected_theme().map(str::to_string) else {
+            return;
+        };
+        self.set_theme(&name);
+    }
+    
+    /// Switch to a theme by name and persist it to config
+    fn set_theme(&mut self, name: &str) {
+        let message = match self.theme_manager.switch_theme(name) {
+            Err(e) => format!("Could not switch to theme '{}': {}", name, e),
+            Ok(()) => match self.theme_manager.persist_selection(name) {
+                Ok(()) => format!("Theme set to '{}'", name),
+                Err(e) => format!("Theme set to '{}' but could not be saved: {}", name, e),
+            },
+        };
+        self.push_message("Theme", &message);
     }
     
     /// Show a confirmation modal
@@ -672,31 +6275,149 @@ Use F1 for help, F10 to quit.", false);
         let should_quit = self.confirmation_modal.as_ref()
             .map(|modal| modal.title() == "Confirm Exit")
             .unwrap_or(false);
+        let is_command = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == COMMAND_CONFIRMATION);
+        let is_agent = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == AGENT_CONFIRMATION);
+        let is_share = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == SHARE_CONFIRMATION);
+        let is_project = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == PROJECT_CONFIRMATION);
+        let is_memory = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == MEMORY_CONFIRMATION);
+        let is_explanation = self.confirmation_modal.as_ref()
+            .is_some_and(|modal| modal.title() == EXPLAIN_CONFIRMATION);
+        let file_op = self.pending_file_op.take();
         
         // Reset the modal state
         self.confirmation_modal = None;
         self.ui_state = UIState::Normal;
         
-        // Handle the result
+        // The agent runs an approved command on its next step
+        if is_agent {
+            if let Some(agent) = &mut self.agent {
+                match result {
+                    "yes" => agent.approve(),
+                    "skip" => agent.skip(),
+                    _ => agent.stop("stopped by the user"),
+                }
+            }
+            return;
+        }
+        
+        // An approved upload is run by the caller
+        if is_share {
+            if result != "yes"
+                && let Some(pending) = self.pending_share.take()
+            {
+                self.push_message(pending.artifact().kind.label(), "Cancelled; nothing was uploaded.");
+            }
+            return;
+        }
+        
+        // Write an approved project configuration and start using it
+        if is_project {
+            if let Some((path, config)) = self.pending_project.take() {
+                let message = match result {
+                    "yes" => match project::write(&path, &config) {
+                        Ok(()) => {
+                            self.load_project();
+                            format!("Wrote {}", path.display())
+                        }
+                        Err(e) => format!("Failed: {:#}", e),
+                    },
+                    _ => "Cancelled; nothing was written.".to_string(),
+                };
+                self.push_message("Init", &message);
+            }
+            return;
+        }
+        
+        // An explained command approved to run is run by the caller; otherwise it stays on the input line
+        if is_explanation {
+            self.run_explained = result == "yes";
+            return;
+        }
+        
+        // Append an approved fact to the project memory
+        if is_memory {
+            if let Some((path, fact)) = self.pending_memory.take() {
+                let message = match result {
+                    "yes" => match project_memory::append(&path, &fact) {
+                        Ok(()) => {
+                            self.load_project_memory();
+                            format!("Added to {}", path.display())
+                        }
+                        Err(e) => format!("Failed: {:#}", e),
+                    },
+                    _ => "Cancelled; nothing was written.".to_string(),
+                };
+                self.push_message("Memory", &message);
+            }
+            return;
+        }
+        
+        // Signals return to the process manager, which shows the outcome
+        if let Some((pid, name, signal)) = self.pending_kill.take() {
+            self.ui_state = UIState::Processes;
+            let message = match result {
+                "yes" => match self.process_table.kill(pid, &name, signal) {
+                    Ok(()) => format!("Sent {} to {} (pid {})", signal.name(), name, pid),
+                    Err(e) => format!("Failed: {:#}", e),
+                },
+                _ => format!("Left {} (pid {}) alone", name, pid),
+            };
+            self.refresh_processes();
+            if let Some(view) = &mut self.process_view {
+                view.set_message(message);
+            }
+            return;
+        }
+        
+        // Model deletes return to the models view; an approved one is run by the caller
+        if self.pending_model_delete.is_some() {
+            self.ui_state = UIState::Models;
+            if result != "yes"
+                && let (Some(model), Some(view)) = (self.pending_model_delete.take(), &mut self.models_view)
+            {
+                view.set_message(format!("Kept {}", model));
+            }
+            return;
+        }
+        
+        // Apply a confirmed file operation; it is checked again as files may have changed meanwhile
+        if let Some(operation) = file_op {
+            let message = match result {
+                "yes" => match operation.apply() {
+                    Ok(preview) => format!("Done: {}", preview.describe(0)),
+                    Err(e) => format!("Failed: {:#}", e),
+                },
+                _ => "Cancelled; nothing was changed.".to_string(),
+            };
+            self.push_message(operation.name(), &message);
+            return;
+        }
+        
         match result {
+            // The approved command is run by the caller
+            "yes" if is_command => {}
+            "no" if is_command => {
+                self.command_env.clear();
+                if let Some(command) = self.pending_command.take() {
+                    self.push_message("Confirmation", &format!("`{}` was not run.", command));
+                }
+            }
             "yes" => {
                 if should_quit {
                     self.should_quit = true;
                 } else {
                     // For other confirmations, show a message that the action was confirmed
-                    let mut block = CommandBlock::new("Confirmation".to_string(), "".to_string());
-                    block.append_output("Action confirmed!", false);
-                    self.command_blocks.push(block);
+                    self.push_message("Confirmation", "Action confirmed!");
                 }
             }
-            "no" => {
-                // User cancelled the action
-                if !should_quit {
-                    let mut block = CommandBlock::new("Confirmation".to_string(), "".to_string());
-                    block.append_output("Action cancelled.", false);
-                    self.command_blocks.push(block);
-                }
-                // If it was a quit confirmation, we just close the modal without quitting
+            // User cancelled the action; a cancelled quit just closes the modal
+            "no" if !should_quit => {
+                self.push_message("Confirmation", "Action cancelled.");
             }
             _ => {}
         }
@@ -704,18 +6425,15 @@ Use F1 for help, F10 to quit.", false);
     
     /// Render the UI
     fn render(&mut self, f: &mut Frame) {
-        let ui_data = UIData {
-            mode: self.mode.clone(),
-            command_blocks: self.command_blocks.clone(),
-            input: self.input.clone(),
-            is_generating: self.is_generating,
-            scroll_offset: self.scroll_offset,
-        };
-        
+        self.image_cells.clear();
+        // A locked session draws nothing but the lock screen
+        if self.idle_lock.is_locked() {
+            LockScreen::new(&self.idle_lock).render(f, f.area(), self.theme_manager.current_theme());
+            return;
+        }
         match self.mode {
             AppMode::Chat => {
-                // Render panes
-                self.pane_manager.render(f);
+                self.render_chat_ui(f);
                 
                 // Render command palette if in that state
                 if let UIState::CommandPalette = self.ui_state {
@@ -725,162 +6443,799 @@ Use F1 for help, F10 to quit.", false);
                     render_command_palette(f, command_palette, layout_manager);
                 }
                 
+                // Render the models view, or keep it visible behind its delete confirmation
+                if (matches!(self.ui_state, UIState::Models) || self.pending_model_delete.is_some())
+                    && let Some(view) = &self.models_view
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(80, 70, f.area());
+                    view.render(f, popup_area, self.theme_manager.current_theme(), self.model_pull.as_ref().map(|pull| &pull.status));
+                }
+                
+                // Render the process manager, or keep it visible behind its signal confirmation
+                if (matches!(self.ui_state, UIState::Processes) || self.pending_kill.is_some())
+                    && let Some(view) = &self.process_view
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(90, 80, f.area());
+                    view.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the table view
+                if let UIState::Table = self.ui_state
+                    && let Some(view) = &self.table_view
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(90, 80, f.area());
+                    view.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the JSON viewer
+                if let UIState::Json = self.ui_state
+                    && let Some(viewer) = &self.json_viewer
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(90, 80, f.area());
+                    viewer.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the log viewer
+                if let UIState::Logs = self.ui_state
+                    && let Some(viewer) = &self.log_viewer
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(90, 80, f.area());
+                    viewer.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
                 // Render confirmation modal if in that state
-                if let UIState::ConfirmationModal = self.ui_state {
-                    if let Some(modal) = &self.confirmation_modal {
-                        let layout_manager = &self.layout_manager;
-                        let popup_area = layout_manager.calculate_centered_rect(60, 20, f.area());
-                        modal.render(f, popup_area);
-                    }
+                if let UIState::ConfirmationModal = self.ui_state
+                    && let Some(modal) = &self.confirmation_modal
+                {
+                    let layout_manager = &self.layout_manager;
+                    // File operations list the files they change, explanations the parts of the command and agent commands their preview
+                    let tall = self.pending_file_op.is_some() || [EXPLAIN_CONFIRMATION, AGENT_CONFIRMATION].contains(&modal.title());
+                    let height = if tall { 40 } else { 20 };
+                    let popup_area = layout_manager.calculate_centered_rect(60, height, f.area());
+                    modal.render(f, popup_area);
+                }
+                
+                // Render theme picker over the live-previewed UI
+                if let UIState::ThemePicker = self.ui_state
+                    && let Some(picker) = &self.theme_picker
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(40, 50, f.area());
+                    picker.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the model picker
+                if let UIState::ModelPicker = self.ui_state
+                    && let Some(picker) = &self.model_picker
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(40, 50, f.area());
+                    picker.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the persona picker and editor
+                if let UIState::PersonaPicker = self.ui_state
+                    && let Some(picker) = &self.persona_picker
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(50, 50, f.area());
+                    picker.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                if let UIState::PersonaEditor = self.ui_state
+                    && let Some(editor) = &self.persona_editor
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(70, 20, f.area());
+                    editor.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the preview of a large AI request
+                if let UIState::PromptPreview = self.ui_state
+                    && let Some(preview) = &self.prompt_preview
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(70, 50, f.area());
+                    preview.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the commit message editor
+                if let UIState::CommitEditor = self.ui_state
+                    && let Some(editor) = &self.commit_editor
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(70, 50, f.area());
+                    editor.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the diff of a proposed edit
+                if let UIState::DiffReview = self.ui_state
+                    && let Some(review) = &self.diff_review
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(85, 80, f.area());
+                    review.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the scrub review before an export or share is written
+                if let UIState::ScrubReview = self.ui_state
+                    && let Some(review) = &self.scrub_review
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(70, 60, f.area());
+                    review.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the /init interview
+                if let UIState::ProjectInterview = self.ui_state
+                    && let Some((_, prompt)) = &self.project_interview
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(60, 20, f.area());
+                    prompt.render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the placeholder prompt
+                if let UIState::PlaceholderPrompt = self.ui_state
+                    && let Some(prompt) = &self.placeholder_prompt
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(60, 20, f.area());
+                    PlaceholderPopup::new(prompt).render(f, popup_area, self.theme_manager.current_theme());
+                }
+                
+                // Render the export path prompt
+                if let UIState::ExportPrompt = self.ui_state
+                    && let Some(prompt) = &self.export_prompt
+                {
+                    let popup_area = self.layout_manager.calculate_centered_rect(60, 20, f.area());
+                    prompt.render(f, popup_area, self.theme_manager.current_theme());
                 }
             }
             AppMode::Help => {
-                render_help_ui(f, &self.layout_manager);
+                render_help_ui(f, &self.layout_manager, &self.keymap);
             }
         }
     }
-}
-
-/// Render the chat UI
-fn render_chat_ui(f: &mut Frame, ui_data: &UIData, theme_manager: &ThemeManager) {
-    let theme = theme_manager.current_theme();
     
-    // Use the layout manager for calculating layout
-    let layout_manager = LayoutManager::new(f.area());
-    let main_layout = layout_manager.calculate_chat_layout();
-    
-    // Header
-    let header_text = vec![
-        Line::from(vec![
+    /// Render the chat UI
+    fn render_chat_ui(&mut self, f: &mut Frame) {
+        let theme = self.theme_manager.current_theme();
+        
+        // Use the layout manager for calculating layout
+        let layout_manager = LayoutManager::new(f.area());
+        let main_layout = layout_manager.calculate_chat_layout();
+        
+        // Header, with each tab marked by its color and icon and the active one highlighted
+        let mut header_spans = vec![
             "AI Terminal".fg(theme.primary),
             " v0.1.0".fg(theme.secondary),
-        ])
-    ];
-    
-    let header = Paragraph::new(header_text)
-        .style(Style::default().bg(theme.background).fg(theme.text))
-        .block(Block::default().borders(Borders::NONE));
-    
-    f.render_widget(header, main_layout[0]);
-    
-    // Messages area with proper word wrapping
-    let mut messages_text = Vec::new();
-    
-    for block in &ui_data.command_blocks {
-        // Display command with theme colors
-        messages_text.push(Line::from(vec![
-            block.status_icon().bold(),
-            " ".into(),
-            block.command.clone().fg(theme.command).bold(),
-            format!(" ({})", block.timestamp.format("%H:%M:%S")).into(),
-        ]));
+            " |".fg(theme.secondary),
+        ];
+        let active_tab = self.tab_manager.active_tab_id();
+        let tab_style = self.tab_manager.active_tab().map(|tab| tab.style.clone()).unwrap_or_default();
+        let mut column = main_layout[0].x + header_spans.iter().map(|span| text::width(&span.content)).sum::<usize>() as u16;
+        self.tab_hits.clear();
+        for tab in self.tab_manager.tabs() {
+            let label = tab.style.label(&tab.name);
+            let tab_span = match (tab.style.color, Some(tab.id) == active_tab) {
+                (Some(color), true) => format!(" {} ", label).fg(theme.background).bg(color).bold(),
+                (Some(color), false) => format!(" {} ", label).fg(color),
+                (None, true) => format!(" {} ", label).fg(theme.text).bold(),
+                (None, false) => format!(" {} ", label).fg(theme.secondary),
+            };
+            let end = column + text::width(&tab_span.content) as u16;
+            self.tab_hits.push((column, end, tab.id));
+            column = end;
+            header_spans.push(tab_span);
+        }
+        let header_text = vec![Line::from(header_spans)];
         
-        // Display output with Markdown rendering
-        if !block.output.is_empty() {
-            // Render the output as Markdown
-            let rendered_lines = render_markdown(&block.output);
-            
-            // Add each rendered line with proper indentation
-            for mut line in rendered_lines {
-                // Prepend indentation to the first span of each line
-                if let Some(first_span) = line.spans.first_mut() {
-                    let mut indented_text = "  ".to_string();  // Indentation
-                    indented_text.push_str(&first_span.content);
-                    first_span.content = indented_text.into();
-                } else {
-                    // If the line has no spans, add the indentation as a new span
-                    line.spans.insert(0, Span::raw("  "));
-                }
-                messages_text.push(line);
-            }
+        let header = Paragraph::new(header_text)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(Block::default().borders(Borders::NONE));
+        
+        f.render_widget(header, main_layout[0]);
+        
+        // Messages area, one bordered region per pane, beside the chat pane when it is shown
+        let (panes_area, chat_area) = chat_pane::split(main_layout[1], self.chat.is_visible());
+        self.pane_manager.fit(panes_area);
+        self.pane_manager.render(f, theme, self.theme_manager.revision());
+        // What the panes drew under their images, to tell later whether anything covers them
+        if self.image_support != ImageSupport::Blocks {
+            self.image_cells = self.pane_manager
+                .panes()
+                .iter()
+                .flat_map(|pane| pane.image_placements())
+                .map(|placement| (placement.clone(), image_cells(f.buffer_mut(), placement.area)))
+                .collect();
+        }
+        if let Some(area) = chat_area {
+            self.chat_view = ChatPanel::new(&self.chat, self.active_model()).render(f, area, theme);
         }
         
-        // Display status if completed
-        if block.is_complete() {
-            let status_color = match block.state {
-                BlockState::Success => theme.success,
-                BlockState::Failed => theme.error,
-                _ => theme.text,
-            };
-            
-            messages_text.push(Line::from(vec![
-                "  ".into(),
-                format!("Status: {:?} (Exit: {}) (Duration: {:?})", 
-                         block.state, 
-                         block.exit_code.unwrap_or(-1), 
-                         block.duration.unwrap_or_default()).fg(status_color),
-            ]));
-        }
-        
-        // Add empty line between blocks for readability
-        messages_text.push(Line::from(""));
-    }
-    
-    let messages_paragraph = Paragraph::new(messages_text)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Messages")
-                .border_style(Style::default().fg(theme.secondary))
-        )
-        .style(Style::default().bg(theme.background).fg(theme.text))
-        .wrap(Wrap { trim: true })
-        .scroll((ui_data.scroll_offset, 0));
-    
-    f.render_widget(messages_paragraph, main_layout[1]);
-    
-    // Input area
-    let input = Paragraph::new(ui_data.input.as_str())
-        .style(Style::default().bg(theme.background).fg(theme.text))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Input (Press Enter to execute, /command for AI, Ctrl+K for command palette)")
-                .border_style(Style::default().fg(theme.secondary))
-        );
-    
-    f.render_widget(input, main_layout[2]);
-    
-    // Status bar
-    let status_text = if ui_data.is_generating {
-        "⏳ EXECUTING (ESC to cancel) | F1: Help | F10: Exit | Ctrl+K: Command Palette"
+        // Copy mode draws its frozen lines over the focused pane
+        if let Some(copy) = &mut self.copy_mode
+            && let Some(pane) = self.pane_manager.focused_pane()
+        {
+            copy.render(f, pane.text_area(), theme);
+        }
+        
+        // Hint mode labels the links and paths it can open or insert
+        if let Some(hints) = &self.hint_mode {
+            HintLabels::new(hints).render(f, theme);
+        }
+        
+        // The plan of a working agent, over the top right of the panes
+        if let Some(agent) = &self.agent {
+            AgentPanel::new(agent).render(f, panes_area, theme);
+        }
+        
+        // Attached context, over the right of the panes below the agent's plan
+        if !self.context.is_empty() {
+            let above = self.agent.as_ref().map(|agent| AgentPanel::new(agent).area(panes_area));
+            ContextPanel::new(&self.context).render(f, panes_area, above, theme);
+        }
+        
+        // Resource usage, over the bottom right of the panes
+        if self.monitor.is_visible() {
+            ResourcePanel::new(&self.monitor).render(f, panes_area, theme);
+        }
+        
+        // The file browser, over the left of the panes
+        if let Some(browser) = &self.file_browser {
+            FileBrowserPanel::new(browser).render(f, panes_area, theme);
+        }
+        
+        // Pinned blocks, over the right of the panes
+        if let Some(pins) = &self.pins {
+            PinsPanel::new(pins).render(f, panes_area, theme);
+        }
+        
+        // A script's message, over everything else on the panes
+        if let Some(toast) = &self.toast {
+            toast.render(f, panes_area, theme);
+        }
+        
+        // Input area, growing up over the panes as lines are added
+        let (rows, (cursor_row, cursor_column)) = self.input.wrap(main_layout[2].width.saturating_sub(2) as usize);
+        let shown = rows.len().clamp(1, MAX_INPUT_ROWS.min(main_layout[1].height as usize / 2).max(1));
+        let grown = shown as u16 - 1;
+        let input_area = Rect { y: main_layout[2].y - grown, height: main_layout[2].height + grown, ..main_layout[2] };
+        // Keep the row holding the cursor in view
+        let first_row = (cursor_row + 1).saturating_sub(shown);
+        let lines: Vec<Line> = rows.into_iter().skip(first_row).take(shown).map(Line::from).collect();
+        let input = Paragraph::new(lines)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(if self.chat.is_focused() {
+                        "Chat (Enter asks, /command runs it, Esc returns to the terminal)"
+                    } else {
+                        "Input (Press Enter to execute, /command for AI, Ctrl+K for command palette)"
+                    })
+                    // The tab's color frames the input so commands are typed into the right environment
+                    .border_style(tab_style.color.map_or(theme.styles.border, |color| Style::default().fg(color)))
+            );
+        
+        f.render_widget(Clear, input_area);
+        f.render_widget(input, input_area);
+        if matches!(self.ui_state, UIState::Normal) && !self.idle_lock.is_locked() {
+            f.set_cursor_position((input_area.x + 1 + (cursor_column as u16).min(input_area.width.saturating_sub(3)), input_area.y + 1 + (cursor_row - first_row) as u16));
+        }
+        
+        // Status bar: mode segment, the tab's model, then key hints
+        let mode = if let Some(copy) = &self.copy_mode {
+            copy.status_segment()
+        } else if self.hint_mode.is_some() {
+            " 🔗 HINTS ".to_string()
+        } else if self.is_generating {
+            " ⏳ EXECUTING ".to_string()
+        } else if self.agent.is_some() {
+            " 🤖 AGENT ".to_string()
+        } else if self.chat.is_focused() {
+            " 💬 CHAT ".to_string()
+        } else {
+            " READY ".to_string()
+        };
+        let model = match self.active_persona() {
+            Some(persona) => format!(" 🧠 {} · {} ", self.active_model(), persona.name),
+            None => format!(" 🧠 {} ", self.active_model()),
+        };
+        let hints = if self.hint_mode.is_some() {
+            " Type a label to open it, in capitals to insert it into the input | Backspace: Undo | Esc: Cancel"
+        } else if self.copy_mode.is_some() {
+            " v: Select | r: Rectangle | o/O: Block/Last Output | y/Enter: Yank | q/Esc: Leave"
+        } else if self.is_generating {
+            " ESC to cancel | F1: Help | F2: Model | F10: Exit | Ctrl+K: Command Palette"
+        } else if self.chat.is_focused() {
+            " Enter: Ask | Esc: Terminal | F5: Hide Chat | F2: Model | Ctrl+K: Command Palette"
+        } else {
+            " F1: Help | F2: Model | F3: Offline | F4: Files | F5: Chat | F10: Exit | Ctrl+K: Command Palette"
+        };
+        
+        // Tokens of the last request and of the session, once a backend reported them
+        let tokens = self.stats.status_segment().unwrap_or_default();
+        let offline = self.offline.status_segment().unwrap_or_default();
+        let working_dir = std::path::Path::new(self.pty_executor.working_dir());
+        let trust = self.trust.status_segment(working_dir).unwrap_or_default();
+        let trust_style = if self.trust.is_trusted(working_dir) { theme.styles.status_info } else { Signal::Risk.style(theme).reversed() };
+        let git = self.git.context().map(|git| git.status_segment()).unwrap_or_default();
+        let workflow = self.workflow_run.as_ref().map(WorkflowRun::status_segment).unwrap_or_default();
+        let plugins = self.plugins.status_segment();
+        let remote = self.focused_remote().map(|remote| format!(" ⇄ {} ", remote.location())).unwrap_or_default();
+        let indexing = self.indexing.as_ref().map(|indexing| format!(" ⟳ index {}/{} ", indexing.done, indexing.total)).unwrap_or_default();
+        
+        let status = Paragraph::new(Line::from(vec![
+            Span::styled(mode, theme.styles.status_mode),
+            Span::styled(remote, theme.styles.status_mode),
+            Span::styled(offline, Signal::Risk.style(theme).reversed()),
+            Span::styled(trust, trust_style),
+            Span::styled(git, theme.styles.status_info.fg(theme.secondary)),
+            Span::styled(workflow, theme.styles.status_mode),
+            Span::styled(indexing, theme.styles.status_mode),
+            Span::styled(plugins, theme.styles.status_info.fg(theme.secondary)),
+            Span::styled(model, theme.styles.status_info.fg(theme.accent)),
+            Span::styled(tokens, theme.styles.status_info),
+            Span::styled(hints, theme.styles.status_info),
+        ]))
+            .style(theme.styles.status_bar);
+        
+        f.render_widget(status, main_layout[3]);
+    }
+}
+
+/// Lines one notch of the mouse wheel scrolls
+const MOUSE_SCROLL_LINES: isize = 3;
+
+/// Most rows the input box grows to before scrolling
+const MAX_INPUT_ROWS: usize = 8;
+
+/// Number of recent blocks included in a bug report
+const BUG_REPORT_BLOCKS: usize = 20;
+
+/// How often a running instance reports in to the store
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Instances that have not reported in for this long are considered gone
+const INSTANCE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Title of the modal approving a model delete
+const MODEL_DELETE_CONFIRMATION: &str = "Delete Model";
+
+/// Title of the confirmation before the process manager sends a signal
+const PROCESS_SIGNAL_CONFIRMATION: &str = "Send Signal";
+
+/// Title of the modal approving a permanent delete
+const PERMANENT_DELETE_CONFIRMATION: &str = "Confirm Permanent Delete";
+
+/// Title of the modal approving a write, copy or move
+const FILE_OPERATION_CONFIRMATION: &str = "Confirm File Operation";
+
+/// Files listed in the dry run shown before a file operation
+const FILE_PREVIEW_LINES: usize = 8;
+
+/// Rows moved by Page Up and Page Down in the table view
+const TABLE_PAGE: usize = 10;
+
+/// Title of the modal approving a command that modifies files
+const COMMAND_CONFIRMATION: &str = "Confirm Command";
+
+/// Title of the modal approving a command an agent asked for
+const AGENT_CONFIRMATION: &str = "Agent Command";
+
+/// Title of the modal approving a session upload
+const SHARE_CONFIRMATION: &str = "Share Session";
+
+/// Title of the modal approving the project configuration drafted by `/init`
+const PROJECT_CONFIRMATION: &str = "Write Project Configuration";
+
+/// Title of the modal approving a fact the model proposed for the project memory
+const MEMORY_CONFIRMATION: &str = "Remember Project Fact";
+
+/// Title of the popup explaining the command on the input line
+const EXPLAIN_CONFIRMATION: &str = "Explain Command";
+
+/// Number of the latest blocks the model learns project facts from
+const LEARNED_BLOCKS: usize = 6;
+
+/// Number of instance sessions kept in the store
+const KEPT_SESSIONS: usize = 10;
+
+/// File listing the commands last run from the palette, most recent first
+fn recent_commands_path() -> std::path::PathBuf {
+    persistence::data_dir().join("recent_commands")
+}
+
+/// Directory receiving the dropped scrollback of an instance, by its id or else the process id
+fn spill_dir(instance_id: Option<&str>) -> std::path::PathBuf {
+    let instance = instance_id.map_or_else(|| std::process::id().to_string(), str::to_string);
+    persistence::data_dir().join("scrollback").join(instance)
+}
+
+/// Directory where bug reports are written
+fn reports_dir() -> std::path::PathBuf {
+    persistence::data_dir().join("reports")
+}
+
+/// Write the result of a JSON query to a file for piping into a command
+fn save_json_result(result: &str) -> Result<std::path::PathBuf> {
+    let dir = persistence::data_dir().join("json");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("result-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    std::fs::write(&path, format!("{}\n", result))?;
+    Ok(path)
+}
+
+/// Get the target of a plain `cd` command, `Some(None)` meaning the home directory
+fn change_dir_target(command: &str) -> Option<Option<&str>> {
+    let mut words = command.split_whitespace();
+    if words.next() != Some("cd") {
+        return None;
+    }
+    match (words.next(), words.next()) {
+        (None, _) => Some(None),
+        (Some(dir), None) => Some(Some(dir)),
+        // Anything more, like `cd dir && make`, is left to the shell
+        _ => None,
+    }
+}
+
+/// Get the file of an `img <path>` command
+fn image_target(command: &str) -> Option<&str> {
+    let path = command.trim().strip_prefix("img ")?.trim();
+    (!path.is_empty()).then_some(path)
+}
+
+/// Copy the cells of a buffer in an area, row by row
+fn image_cells(buffer: &ratatui::buffer::Buffer, area: Rect) -> Vec<ratatui::buffer::Cell> {
+    area.rows()
+        .flat_map(|row| row.columns())
+        .filter_map(|position| buffer.cell(position).cloned())
+        .collect()
+}
+
+/// Parse the time of `/clear before`: `HH:MM` today, `YYYY-MM-DD` or `YYYY-MM-DD HH:MM`
+fn parse_cutoff(text: &str) -> Result<chrono::DateTime<chrono::Local>> {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)))
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M").map(|time| chrono::Local::now().date_naive().and_time(time)))
+        .map_err(|_| anyhow::anyhow!("'{}' is not a time like 14:30, 2024-05-01 or 2024-05-01 14:30", text))?;
+    naive.and_local_timezone(chrono::Local)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("{} does not exist in the local time zone", text))
+}
+
+/// Expand a leading `~` in a path typed by the user
+fn expand_home(path: &str) -> std::path::PathBuf {
+    match path.strip_prefix('~').and_then(|rest| Some((dirs::home_dir()?, rest))) {
+        Some((home, rest)) if rest.is_empty() || rest.starts_with('/') => home.join(rest.trim_start_matches('/')),
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+/// Write tags as `#tag #tag`
+fn hashtags(tags: &[String]) -> String {
+    tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ")
+}
+
+/// Describe a tag filter for the end of a sentence, as in " tagged #git #failed"
+fn describe_filter(filter: &TagFilter) -> String {
+    if filter.is_empty() {
+        String::new()
     } else {
-        "F1: Help | F10: Exit | Ctrl+K: Command Palette"
-    };
-    
-    let status = Paragraph::new(status_text)
-        .style(Style::default().bg(theme.background).fg(theme.secondary))
-        .block(Block::default().borders(Borders::ALL));
-    
-    f.render_widget(status, main_layout[3]);
+        format!(" tagged {}", hashtags(filter.tags()))
+    }
+}
+
+/// Describe a block on one line of a block list, with its tags
+fn describe_tagged_block(block: &CommandBlock) -> String {
+    let tags = block.all_tags();
+    let mut line = format!("{} {} ({})", block.status_icon(), block.command, block.timestamp.format("%Y-%m-%d %H:%M"));
+    if !tags.is_empty() {
+        line.push_str(&format!("  {}", hashtags(&tags)));
+    }
+    line
 }
 
 /// Render the help UI
-fn render_help_ui(f: &mut Frame, layout_manager: &LayoutManager) {
+fn render_help_ui(f: &mut Frame, layout_manager: &LayoutManager, keymap: &Keymap) {
     let layout = layout_manager.calculate_chat_layout();
     
-    let help_text = vec![
+    let mut help_text: Vec<Line> = vec![
         "AI Terminal - Help".into(),
         "".into(),
         "Controls:".into(),
         "  Enter        - Execute command (/command for AI)".into(),
-        "  Up/Down      - Navigate command history".into(),
+        "  Shift/Alt+Enter - Start a new line in the input".into(),
+        "  Up/Down      - Move between input lines, then navigate command history".into(),
         "  Tab          - File path completion".into(),
-        "  Page Up/Down - Scroll through messages".into(),
+        "  Tab (empty)  - Run the correction suggested under a failed command".into(),
+        "  Ctrl+/       - Explain the command on the input line part by part before running it".into(),
+        "  Pasting a shell snippet with $ prompts, \\ continuations or <values> offers".into(),
+        "  'Smart Paste: Clean Up' and 'Smart Paste: Adapt to This System' in the palette".into(),
+        "  /history search <text>       - Find commands of the whole history containing the text".into(),
+        "  /history semantic <query>    - Find commands by what they did, as in 'fixed the docker network'".into(),
+        "".into(),
+        "Editing the input:".into(),
+        "  Left/Right, Home/End, Ctrl+A/E - Move the cursor; Home/End scroll when the input is empty".into(),
+        "  Alt+Left/Right, Alt+B/F        - Move by word".into(),
+        "  Ctrl+W, Alt+Backspace, Alt+D   - Delete the word before or after the cursor".into(),
+        "  Ctrl+Y, then Alt+Y             - Paste the last deleted word, then older ones".into(),
+        "".into(),
         "  Ctrl+K       - Open command palette".into(),
         "  Ctrl+Q       - Quit with confirmation".into(),
         "  F1           - Toggle help".into(),
+        "  F2           - Choose the AI model for the current tab".into(),
+        "  F3           - Go offline or back online".into(),
+        "  Ctrl+G       - Have the AI write a commit message for the staged diff, then edit and commit it".into(),
+        "  Alt+G        - Have the AI explain the staged diff, or the unstaged one".into(),
+        "  F4           - Browse files: Enter open, i insert path, a ask AI, . dotfiles".into(),
         "  F10          - Quit with confirmation".into(),
         "".into(),
+        "Scrolling (rebind in the [keys] section of config.toml):".into(),
+    ];
+    help_text.extend(Motion::ALL.iter().map(|motion| {
+        format!("  {:<16} - {}", keymap.keys(*motion), motion.description()).into()
+    }));
+    help_text.extend(vec![
+        "  The right edge of each pane maps its whole output: ┃ is the view,".into(),
+        "  ┿ a block boundary, ✗ a failed block. Click it to jump there.".into(),
+        "  Mouse: the wheel scrolls the pane under it, a click focuses the pane".into(),
+        "  and selects a block or switches tabs, and a drag copies text.".into(),
+        "  Alt+V / Alt+O  - Copy mode / copy mode with the last output selected:".into(),
+        "    v select, r rectangle, o/O block/last output, y yank, q leave".into(),
+        "  Alt+L          - Label links and paths: a label opens, a capital label inserts".into(),
+        "".into(),
         "Command Palette:".into(),
         "  Use Ctrl+K to open the command palette".into(),
-        "  Type to search for commands".into(),
+        "  Type to search for commands; letters match in order, as in fzf".into(),
         "  Up/Down to navigate, Enter to select".into(),
+        "  Words after a command are its argument: 'theme dark', 'persona terse'".into(),
+        "  Recently used commands are listed first".into(),
+        "  [[commands]] in the configuration and plugin files add commands; /commands lists them".into(),
+        "  WebAssembly plugins add commands and status segments; /plugins lists them".into(),
+        "  Lua scripts bind keys, add commands and react to commands; /scripts lists them".into(),
+        "  Prompt templates are under Templates; /template <name> fills one in to edit".into(),
+        "  /index indexes the project's files; /ask-project <question> answers from them".into(),
         "".into(),
         "Theming:".into(),
         "  The AI Terminal supports custom themes.".into(),
         "  Themes can be created as TOML files in the config directory.".into(),
         "  Use the command palette to list, toggle, and save themes.".into(),
+        "  'Select Theme' previews each theme as you move through the list;".into(),
+        "  Enter keeps the highlighted theme, Esc restores the previous one.".into(),
+        "  The deuteranopia, protanopia and tritanopia themes suit color vision".into(),
+        "  deficiencies; set [accessibility] color_vision to use one at startup.".into(),
+        "  States also differ by glyph: ✓ success, ✗ failure, + - ~ changes, ⚠ risk.".into(),
+        "  'Toggle Reduced Motion' (or [accessibility] reduced_motion = true) redraws".into(),
+        "  less often and moves progress bars in steps, e.g. over slow SSH links.".into(),
+        "".into(),
+        "Privacy:".into(),
+        "  Exports, shares and bug reports are scanned for secrets first.".into(),
+        "  Flagged items are listed for review: Space keeps or redacts an item,".into(),
+        "  Enter writes the file and Esc cancels.".into(),
+        "  Output sent to the AI by agents and tools has secrets masked (🛡 badge).".into(),
+        "  /lock                        - Cover the session until the passphrase is entered".into(),
+        "  /monitor                     - Show or hide CPU, memory, disk and GPU usage".into(),
+        "  /ps                          - Manage processes: / filter, s sort, Tab signal, k send".into(),
+        "  With [idle_lock] enabled, idle sessions are covered after idle_minutes.".into(),
+        "".into(),
+        "Exporting:".into(),
+        "  'Export: Session Transcript' writes all blocks and AI exchanges to a file.".into(),
+        "  The extension picks the format: .md (default), .html or .json.".into(),
+        "  /share                       - Upload the session to a gist or paste service".into(),
+        "                                 set in [share], after asking, and copy the link".into(),
+        "  /share block                 - Upload only the clicked block, or the latest one".into(),
+        "".into(),
+        "Models:".into(),
+        "  'Manage Models' in the command palette lists installed Ollama models.".into(),
+        "  Enter shows details, p pulls a model, d deletes one, r refreshes.".into(),
+        "".into(),
+        "Diagnostics:".into(),
+        "  /startup-report              - Show how long startup took, per subsystem".into(),
+        "  /capabilities                - Show which optional features this build includes".into(),
+        "  /doctor                      - Check the model server, the model, the PTY and the terminal".into(),
+        "  The status bar shows the last request's tokens and tokens/sec, and Σ the".into(),
+        "  session total; 'Stats: Token Usage' in the command palette has details.".into(),
+        "".into(),
+        "Bookmarks:".into(),
+        "  /bookmark add <name> [path]  - Bookmark a directory (default: current)".into(),
+        "  /bookmark remove <name>      - Remove a bookmark".into(),
+        "  /bookmark                    - List bookmarks".into(),
+        "  Write @name or @name/sub/dir in commands; Tab completes bookmark names.".into(),
+        "  cd [dir] changes the directory later commands run in.".into(),
+        "".into(),
+        "Tags:".into(),
+        "  /tag <tag>...                - Tag the clicked block, or the latest one".into(),
+        "  /tag remove <tag>...         - Take tags off it".into(),
+        "  /blocks [tag]...             - List this session's blocks with all the tags".into(),
+        "  /blocks saved [tag]...       - Search the blocks of saved sessions".into(),
+        "  /blocks export <path> [tag]... - Export the tagged blocks as a transcript".into(),
+        "  Blocks are also tagged git, docker, network and failed automatically.".into(),
+        "".into(),
+        "Pins:".into(),
+        "  /pin                         - Pin the clicked block, or the latest one".into(),
+        "  Alt+P, /pins                 - Open the pinned blocks: Enter runs one again, c copies".into(),
+        "                                 the command, y the output, a attaches the output to".into(),
+        "                                 AI requests and d unpins it".into(),
+        "".into(),
+        "Images:".into(),
+        "  img <path>                   - Show a PNG or PPM image in a block".into(),
+        "  Sixel, kitty and iTerm2 images printed by commands show under their output,".into(),
+        "  with the terminal's own graphics where it has them and half blocks elsewhere.".into(),
+        "".into(),
+        "Tables:".into(),
+        "  /table                       - Sort and filter the clicked block's columnar output,".into(),
+        "                                 or the latest table: ←→ pick a column, s sorts by it,".into(),
+        "                                 / filters it (>n and <n compare numbers), c clears".into(),
+        "                                 the filters, Enter copies the cell, y the rows as TSV".into(),
+        "  /table inline                - Show the block's output as an aligned table, or raw again".into(),
+        "".into(),
+        "JSON:".into(),
+        "  /json [filter]               - Browse the clicked block's JSON or NDJSON output, or the".into(),
+        "                                 latest JSON: Space folds, / runs a jq filter such as".into(),
+        "                                 .items[] | select(.ready == true), f filters to the".into(),
+        "                                 selected line, y copies the result and p pipes it".into(),
+        "                                 into a new command".into(),
+        "".into(),
+        "Logs:".into(),
+        "  /log [level] [regex]         - Follow the clicked block's output, or the latest one, as".into(),
+        "                                 a log colored by level, while the command runs: n and N".into(),
+        "                                 jump between errors, l raises the least level shown,".into(),
+        "                                 / filters by regular expression, y copies what is shown".into(),
+        "".into(),
+        "Benchmarks:".into(),
+        "  bench <n> <command>          - Run a command n times and show the mean, spread and a".into(),
+        "                                 histogram of its times".into(),
+        "  /bench explain               - Ask the model what the latest benchmark's times say".into(),
+        "".into(),
+        "Personas:".into(),
+        "  /persona                     - List personas (named system prompts)".into(),
+        "  /persona use <name>          - Use a persona for AI commands in this tab".into(),
+        "  /persona off                 - Go back to the configured system prompt".into(),
+        "  /persona remove <name>       - Delete a persona".into(),
+        "  'Select Persona' and 'Edit Persona' in the command palette pick and write them.".into(),
+        "".into(),
+        "Trust:".into(),
+        "  /trust                       - Show whether AI automation may act here".into(),
+        "  /trust allow|deny [dir]      - Mark a directory and those below it".into(),
+        "  /trust forget [dir] | list   - Remove a mark, or list the marks".into(),
+        "  In untrusted directories tool calls are refused and agent commands always ask.".into(),
+        "".into(),
+        "Project:".into(),
+        "  /init                        - Draft .ai-terminal.toml for this project".into(),
+        "  Its system prompt, palette commands and agent allowlist apply below its directory.".into(),
+        "  .ai-terminal.md here or at the repository root is sent along with prompts.".into(),
+        "  /memory [show]               - Show the project memory file".into(),
+        "  /memory add <fact>           - Add a fact to it".into(),
+        "  /memory learn                - Have the model propose a fact from recent work".into(),
+        "".into(),
+        "Router:".into(),
+        "  /router                      - List the system tools prompts can be routed to".into(),
+        "".into(),
+        "Snippets:".into(),
+        "  /code                        - List the code blocks of the latest AI answer".into(),
+        "  /code copy|run [n]           - Copy code block n (default 1), or run it once approved".into(),
+        "  /code edit [n] [path]        - Review the file edit block n proposes as a diff, then apply or reject it".into(),
+        "                                 (python and js blocks run with cached dependencies)".into(),
+        "  /snippet save [tags]         - Save the code block last copied or run".into(),
+        "  /snippet list [tag]          - List saved snippets".into(),
+        "  /snippet insert|remove <id>  - Put a snippet on the input line, or delete it".into(),
+        "  Saved snippets are also in the command palette; picking one inserts it.".into(),
+        "  {{NAME}} and {{NAME:secret}} in a command are asked for when it runs,".into(),
+        "  unless set in the environment; secret values never enter the command.".into(),
+        "".into(),
+        "Workflows:".into(),
+        "  /workflow [list]             - List workflows and their steps".into(),
+        "  /workflow run <name>         - Run a workflow's steps in order, one block each".into(),
+        "  /workflow stop               - Stop the running workflow before its next step".into(),
+        "  /workflow reload             - Read the workflow files again".into(),
+        "  Workflows are TOML or YAML files in the workflows directory of the config directory.".into(),
+        "".into(),
+        "Aliases:".into(),
+        "  ,<name> [args]               - Run an alias; args fill its {placeholders} in order or as name=value".into(),
+        "  /alias [list]                - List aliases".into(),
+        "  /alias add <name> <command>  - Add an alias, such as /alias add deploy ./deploy.sh {env}".into(),
+        "  /alias new <task>            - Have the model draft an alias for a task".into(),
+        "  /alias save [name]           - Save the alias drafted last, optionally renamed".into(),
+        "  /alias remove <name>         - Delete an alias".into(),
+        "  Aliases are kept in aliases.toml in the config directory and listed in the command palette.".into(),
+        "".into(),
+        "Clearing:".into(),
+        "  /clear                       - Remove every block in the pane".into(),
+        "  /clear outputs               - Drop outputs, keeping the commands as an index".into(),
+        "  /clear ai                    - Remove AI prompts and responses".into(),
+        "  /clear before <time>         - Remove blocks started before 14:30, 2024-05-01, ...".into(),
+        "".into(),
+        "Remote panes:".into(),
+        "  /ssh <host>                  - Open a pane whose commands run on the host".into(),
+        "  /ssh off                     - Run the focused pane's commands locally again".into(),
+        "".into(),
+        "Tabs:".into(),
+        "  /tab                         - Show the tab's color and icon".into(),
+        "  /tab name <name>             - Rename the tab".into(),
+        "  /tab color <color|none>      - Color the tab and its input border".into(),
+        "  /tab icon <icon|none>        - Show an icon before the tab's name".into(),
+        "  Patterns in [tab_colors] color the tab when a matching command runs,".into(),
+        "  such as \"^ssh .*prod\" = \"red 🔥\".".into(),
+        "".into(),
+        "Tools:".into(),
+        "  Tools of the MCP servers in [mcp_servers] are offered to the model. Its".into(),
+        "  calls run automatically and show as 🔧 blocks before the answer.".into(),
+        "  /tools                       - List the tools on offer".into(),
+        "".into(),
+        "Large requests:".into(),
+        "  AI requests above [prompt_guard] preview_tokens open a preview listing".into(),
+        "  their pieces with sizes. Space drops a piece, Enter sends, Esc cancels.".into(),
+        "".into(),
+        "Agent:".into(),
+        "  /agent <goal>                - Let the AI work towards a goal one command at a".into(),
+        "                                 time, asking before each command it runs".into(),
+        "  /agent stop                  - Stop the agent".into(),
+        "  Read-only commands in [agent] auto_approve run without asking.".into(),
+        "".into(),
+        "Output views:".into(),
+        "  Output of commands such as `git log` and `kubectl get ... -o json` is".into(),
+        "  shown as a table. 'Toggle Raw Output' switches back to the original.".into(),
+        "  Add patterns in the [processors] section of config.toml.".into(),
+        "".into(),
+        "Summaries:".into(),
+        "  Output of 200 lines or more gets a note offering an AI summary, or one".into(),
+        "  straight away with mode = \"auto\" in [summaries]. 'Toggle Summaries' folds them.".into(),
+        "  /summarize [n]               - Summarize the output of the n-th latest block".into(),
+        "".into(),
+        "Chat pane:".into(),
+        "  F5 or 'Toggle Chat Pane' opens a conversation with the model beside the".into(),
+        "  panes. Enter asks, follow-ups keep the context, Esc returns to the terminal.".into(),
+        "  /chat send [n]               - Send the output of the n-th latest block to the chat".into(),
+        "  /chat [show|hide|clear]      - Show or hide the chat pane, or start over".into(),
+        "  Each tab has its own conversation, saved as it goes; type chats: in the".into(),
+        "  command palette to reopen one.".into(),
+        "  /chats [list]                - List saved conversations".into(),
+        "  /chats search <text>         - Find conversations by the text of any message".into(),
+        "  /chats open <id>             - Reopen one in the chat pane to carry on".into(),
+        "  /chats rename <id> <title>   - Retitle one".into(),
+        "  /chats delete <id>           - Delete one".into(),
+        "  /chats export <id> <path>    - Write one as Markdown, scrubbed of secrets".into(),
+        "".into(),
+        "Known errors:".into(),
+        "  /kb explain [n]              - Explain why the latest failed command failed,".into(),
+        "                                 saving the answer for when the error comes back".into(),
+        "  /kb [list]                   - List known errors, also under Known Errors in the palette".into(),
+        "  /kb show <id>                - Show one, putting its fix on the input line".into(),
+        "  /kb fix <id> <command>       - Save the command that fixes it".into(),
+        "  /kb remove <id> | /kb clear  - Forget one or all of them".into(),
+        "".into(),
+        "Repeated commands:".into(),
+        "  Running a command again with identical output adds a run to the earlier".into(),
+        "  block, shown as ×N. 'Toggle Repeated Runs' lists the individual runs.".into(),
+        "".into(),
+        "Context:".into(),
+        "  /attach <path>...            - Send files along with every AI request".into(),
+        "  /attach-block [n]            - Send the output of the n-th latest block along".into(),
+        "  /context [remove <n>|clear]  - List attached context, or drop some or all of it".into(),
+        "".into(),
+        "Offline:".into(),
+        "  F3 or 'Toggle Offline Mode' holds back AI prompts and model downloads.".into(),
+        "  Prompts made offline are queued and sent when you go back online.".into(),
+        "  While the model server is unreachable, the status bar shows AI UNAVAILABLE and".into(),
+        "  prompts are queued the same way until it answers again.".into(),
+        "  /queue                       - List queued prompts".into(),
+        "  /queue clear                 - Drop queued prompts".into(),
+        "".into(),
+        "Deleting:".into(),
+        "  /delete <path>...            - Move files to the trash".into(),
+        "  /undo-delete                 - Restore the most recent delete".into(),
+        "  /delete <path>... --permanent - Delete for good, after confirmation".into(),
+        "".into(),
+        "Files:".into(),
+        "  /copy <source>... <dest>     - Copy files or directories".into(),
+        "  /move <source>... <dest>     - Move files or directories".into(),
+        "  /write <path>                - Save the last command's full output to a file".into(),
+        "  Each lists the files it would create, replace or remove and waits for you".into(),
+        "  to select the button. System directories and ~/.ssh are refused.".into(),
+        "".into(),
+        "File changes:".into(),
+        "  Commands that modify files (rm, mv, chmod, > file, ...) ask for approval.".into(),
+        "  Approved commands list what they created, removed or modified.".into(),
+        "  !sandbox <command>           - Run in bubblewrap: read-only, no network".into(),
+        "".into(),
+        "Backups:".into(),
+        "  /backup [file] [--encrypt]  - Back up history, sessions, logs and themes".into(),
+        "  /restore <file> [--only history,themes] [--verify]".into(),
+        format!("  Encrypted backups use the passphrase in ${}.", backup::PASSPHRASE_VAR).into(),
         "".into(),
         "Confirmation Dialogs:".into(),
         "  Some actions require confirmation (like quitting).".into(),
@@ -888,7 +7243,7 @@ fn render_help_ui(f: &mut Frame, layout_manager: &LayoutManager) {
         "  You can also press Y/N for quick confirmation.".into(),
         "".into(),
         "Press F1 or Esc to return to the terminal".into(),
-    ];
+    ]);
     
     let help = Paragraph::new(help_text)
         .block(Block::default().borders(Borders::ALL).title("Help"))
@@ -908,4 +7263,69 @@ fn render_command_palette(f: &mut Frame, command_palette: &CommandPalette, layou
 pub mod layout;
 pub mod widgets;
 pub mod theme;
-pub mod markdown_renderer;
\ No newline at end of file
+pub mod markdown_renderer;
+#[cfg(feature = "syntax-highlighting")]
+pub mod syntax_highlighter;
+pub mod hyperlink;
+pub mod render_cache;
+pub mod privacy;
+pub mod persistence;
+pub mod backup;
+pub mod exporter;
+pub mod aliases;
+pub mod bookmarks;
+pub mod workflows;
+pub mod startup;
+pub mod capabilities;
+pub mod persona;
+pub mod stats;
+pub mod keymap;
+pub mod line_editor;
+pub mod offline;
+pub mod tools;
+pub mod processors;
+pub mod agent;
+pub mod prompt_guard;
+pub mod share;
+pub mod tab_colors;
+pub mod text;
+pub mod idle_lock;
+pub mod snippets;
+pub mod runners;
+pub mod trust;
+pub mod intent_router;
+pub mod placeholders;
+pub mod project;
+pub mod monitor;
+pub mod processes;
+pub mod file_browser;
+pub mod hints;
+pub mod edits;
+pub mod git;
+pub mod custom_commands;
+pub mod plugins;
+pub mod scripting;
+pub mod hooks;
+pub mod protocol;
+pub mod templates;
+pub mod file_index;
+pub mod context_set;
+pub mod summaries;
+pub mod error_kb;
+pub mod chat_pane;
+pub mod chat_history;
+pub mod project_memory;
+pub mod history_search;
+pub mod smart_paste;
+pub mod corrections;
+pub mod command_explainer;
+pub mod dry_run;
+pub mod pins;
+pub mod images;
+pub mod tables;
+pub mod json_query;
+pub mod logs;
+pub mod bench;
+pub mod doctor;
+pub mod onboarding;
+pub mod server_health;
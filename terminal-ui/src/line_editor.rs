@@ -0,0 +1,441 @@
+//! Input line editing for the AI Terminal
+//!
+//! The input box is a small editor rather than an append-only string: the
+//! cursor moves by character, word and line, Ctrl+W and Alt+D kill words
+//! into a kill ring that Ctrl+Y yanks back (Alt+Y cycling through older
+//! kills), and Shift+Enter or Alt+Enter starts a new line for multi-line
+//! commands and prompts. [`LineEditor::wrap`] lays the text out in rows of
+//! the box's width and finds the cursor among them, so the box can grow with
+//! its contents. The cursor moves over whole grapheme clusters and columns
+//! are counted as drawn, so emoji and CJK text edit and display correctly.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::text;
+
+/// Most kills kept for yanking
+const KILL_RING_SIZE: usize = 16;
+
+/// Text of the input box and the cursor in it
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor, always on a grapheme cluster boundary
+    cursor: usize,
+    /// Killed text, most recent last
+    kill_ring: Vec<String>,
+    /// Text the last command yanked and the kill it came from, for Alt+Y
+    yanked: Option<(Range<usize>, usize)>,
+}
+
+impl LineEditor {
+    /// Create an empty editor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the text
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether there is no text
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Get the byte offset of the cursor
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replace the text, with the cursor at its end
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+        self.yanked = None;
+    }
+
+    /// Remove the text
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    /// Take the text, leaving the editor empty
+    pub fn take(&mut self) -> String {
+        let text = std::mem::take(&mut self.text);
+        self.clear();
+        text
+    }
+
+    /// Insert a character at the cursor
+    pub fn insert_char(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self.yanked = None;
+    }
+
+    /// Insert text at the cursor
+    pub fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        self.yanked = None;
+    }
+
+    /// Replace a range of the text, leaving the cursor after the replacement
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.text.replace_range(range.clone(), text);
+        self.cursor = range.start + text.len();
+        self.yanked = None;
+    }
+
+    /// Start a new line at the cursor
+    pub fn newline(&mut self) {
+        self.insert_char('\n');
+    }
+
+    /// Remove the grapheme cluster before the cursor
+    pub fn backspace(&mut self) {
+        let start = self.prev_boundary(self.cursor);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        self.yanked = None;
+    }
+
+    /// Remove the grapheme cluster after the cursor
+    pub fn delete(&mut self) {
+        let end = self.next_boundary(self.cursor);
+        self.text.replace_range(self.cursor..end, "");
+        self.yanked = None;
+    }
+
+    /// Move one character left
+    pub fn move_left(&mut self) {
+        self.cursor = self.prev_boundary(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move one character right
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move to the start of the word before the cursor
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_start(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move to the end of the word after the cursor
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.word_end(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move to the start of the line
+    pub fn move_home(&mut self) {
+        self.cursor = self.line_start(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move to the end of the line
+    pub fn move_end(&mut self) {
+        self.cursor = self.line_end(self.cursor);
+        self.yanked = None;
+    }
+
+    /// Move to the line above, keeping the display column; false on the first line
+    pub fn move_up(&mut self) -> bool {
+        let start = self.line_start(self.cursor);
+        if start == 0 {
+            return false;
+        }
+        let column = text::width(&self.text[start..self.cursor]);
+        let above = self.line_start(start - 1);
+        self.cursor = self.column_offset(above, column);
+        self.yanked = None;
+        true
+    }
+
+    /// Move to the line below, keeping the display column; false on the last line
+    pub fn move_down(&mut self) -> bool {
+        let end = self.line_end(self.cursor);
+        if end == self.text.len() {
+            return false;
+        }
+        let column = text::width(&self.text[self.line_start(self.cursor)..self.cursor]);
+        self.cursor = self.column_offset(self.next_boundary(end), column);
+        self.yanked = None;
+        true
+    }
+
+    /// Kill the word before the cursor, as Ctrl+W does in shells
+    pub fn kill_word_back(&mut self) {
+        let start = self.text[..self.cursor]
+            .trim_end_matches([' ', '\t'])
+            .rfind([' ', '\t', '\n'])
+            .map_or(0, |i| i + 1);
+        // A line break alone before the cursor is killed by itself
+        let start = if start == self.cursor { self.prev_boundary(self.cursor) } else { start };
+        self.kill(start..self.cursor);
+    }
+
+    /// Kill from the cursor to the end of the next word
+    pub fn kill_word_forward(&mut self) {
+        let end = self.word_end(self.cursor);
+        self.kill(self.cursor..end);
+    }
+
+    /// Remove a range of the text into the kill ring
+    fn kill(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let killed: String = self.text.drain(range.clone()).collect();
+        self.cursor = range.start;
+        self.kill_ring.push(killed);
+        if self.kill_ring.len() > KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+        self.yanked = None;
+    }
+
+    /// Insert the most recent kill at the cursor
+    pub fn yank(&mut self) {
+        let Some(index) = self.kill_ring.len().checked_sub(1) else {
+            return;
+        };
+        self.yank_at(index);
+    }
+
+    /// Replace the text just yanked with the kill before it
+    pub fn yank_pop(&mut self) {
+        let Some((range, index)) = self.yanked.take() else {
+            return;
+        };
+        self.text.replace_range(range.clone(), "");
+        self.cursor = range.start;
+        let previous = if index == 0 { self.kill_ring.len() - 1 } else { index - 1 };
+        self.yank_at(previous);
+    }
+
+    /// Insert a kill at the cursor, remembering it for [`Self::yank_pop`]
+    fn yank_at(&mut self, index: usize) {
+        let start = self.cursor;
+        let text = self.kill_ring[index].clone();
+        self.insert_str(&text);
+        self.yanked = Some((start..self.cursor, index));
+    }
+
+    /// Lay the text out in rows at most `width` columns wide, returning the rows and the cursor's row and column
+    pub fn wrap(&self, width: usize) -> (Vec<String>, (usize, usize)) {
+        let width = width.max(1);
+        let mut rows = vec![String::new()];
+        let mut column = 0;
+        let mut cursor = (0, 0);
+        for (offset, grapheme) in self.text.grapheme_indices(true) {
+            if offset == self.cursor {
+                cursor = (rows.len() - 1, column);
+            }
+            if grapheme == "\n" || grapheme == "\r\n" {
+                rows.push(String::new());
+                column = 0;
+                continue;
+            }
+            let grapheme_width = text::width(grapheme);
+            if column + grapheme_width > width {
+                rows.push(String::new());
+                column = 0;
+                if offset == self.cursor {
+                    cursor = (rows.len() - 1, 0);
+                }
+            }
+            rows.last_mut().expect("there is always a row").push_str(grapheme);
+            column += grapheme_width;
+        }
+        if self.cursor == self.text.len() {
+            // The cursor after a full row goes to the start of the next
+            if column >= width {
+                rows.push(String::new());
+                column = 0;
+            }
+            cursor = (rows.len() - 1, column);
+        }
+        (rows, cursor)
+    }
+
+    /// Get the offset of the grapheme cluster before `offset`
+    fn prev_boundary(&self, offset: usize) -> usize {
+        self.text[..offset].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+    }
+
+    /// Get the offset of the grapheme cluster after `offset`
+    fn next_boundary(&self, offset: usize) -> usize {
+        text::graphemes(&self.text[offset..]).next().map_or(offset, |grapheme| offset + grapheme.len())
+    }
+
+    /// Get the start of the word before `offset`
+    fn word_start(&self, offset: usize) -> usize {
+        let mut start = offset;
+        let mut in_word = false;
+        for (i, grapheme) in self.text[..offset].grapheme_indices(true).rev() {
+            match (is_word(grapheme), in_word) {
+                (true, _) => in_word = true,
+                (false, true) => break,
+                (false, false) => {}
+            }
+            start = i;
+        }
+        if in_word { start } else { 0 }
+    }
+
+    /// Get the end of the word after `offset`
+    fn word_end(&self, offset: usize) -> usize {
+        let mut end = offset;
+        let mut in_word = false;
+        for grapheme in text::graphemes(&self.text[offset..]) {
+            match (is_word(grapheme), in_word) {
+                (true, _) => in_word = true,
+                (false, true) => break,
+                (false, false) => {}
+            }
+            end += grapheme.len();
+        }
+        end
+    }
+
+    /// Get the start of the line holding `offset`
+    fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Get the end of the line holding `offset`, before its line break
+    fn line_end(&self, offset: usize) -> usize {
+        let end = self.text[offset..].find('\n').map_or(self.text.len(), |i| offset + i);
+        // "\r\n" is one line break, which the cursor does not go into
+        if end > offset && self.text[..end].ends_with('\r') { end - 1 } else { end }
+    }
+
+    /// Get the offset at display column `column` of the line starting at `start`, or of its end if shorter
+    ///
+    /// A wide character covering the column puts the offset before it.
+    fn column_offset(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+        let mut width = 0;
+        for (i, grapheme) in self.text[start..end].grapheme_indices(true) {
+            width += text::width(grapheme);
+            if width > column {
+                return start + i;
+            }
+        }
+        end
+    }
+}
+
+/// Whether a grapheme cluster is part of a word, judged by its base character
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editing_kills_and_wrapping() {
+        let mut editor = LineEditor::new();
+        "git commit -m wip".chars().for_each(|c| editor.insert_char(c));
+        editor.move_word_left();
+        editor.move_word_left();
+        assert_eq!(&editor.as_str()[editor.cursor()..], "m wip");
+        editor.kill_word_back();
+        assert_eq!(editor.as_str(), "git commit m wip");
+        editor.move_end();
+        editor.kill_word_back();
+        assert_eq!(editor.as_str(), "git commit m ");
+        editor.yank();
+        editor.yank_pop();
+        assert_eq!(editor.as_str(), "git commit m -");
+        editor.move_home();
+        editor.kill_word_forward();
+        assert_eq!(editor.as_str(), " commit m -");
+
+        editor.set("héllo");
+        editor.move_left();
+        editor.backspace();
+        assert_eq!(editor.as_str(), "hélo");
+        editor.move_home();
+        editor.delete();
+        assert_eq!(editor.as_str(), "élo");
+
+        // Lines keep their column when moving between them
+        editor.set("first line");
+        editor.newline();
+        editor.insert_str("two");
+        assert!(editor.move_up());
+        assert!(!editor.move_up());
+        assert_eq!(&editor.as_str()[..editor.cursor()], "fir");
+        assert!(editor.move_down());
+        assert_eq!(editor.cursor(), editor.as_str().len());
+
+        // Combining marks, joined emoji and wide characters are edited and laid out whole
+        editor.set("cafe\u{301} 👍🏽漢");
+        editor.backspace();
+        editor.backspace();
+        assert_eq!(editor.as_str(), "cafe\u{301} ");
+        editor.move_word_left();
+        assert_eq!(editor.cursor(), 0);
+        editor.move_word_right();
+        assert_eq!(&editor.as_str()[editor.cursor()..], " ");
+        editor.set("漢字👍🏽ab");
+        assert_eq!(editor.wrap(5), (vec!["漢字".to_string(), "👍🏽ab".to_string()], (1, 4)));
+        editor.set("first line\ntwo");
+
+        let (rows, cursor) = editor.wrap(6);
+        assert_eq!(rows, vec!["first ", "line", "two"]);
+        assert_eq!(cursor, (2, 3));
+        editor.set("abcdef");
+        assert_eq!(editor.wrap(3), (vec!["abc".to_string(), "def".to_string(), String::new()], (2, 0)));
+        editor.set("one\r\ntwo");
+        assert_eq!(editor.wrap(10), (vec!["one".to_string(), "two".to_string()], (1, 3)));
+    }
+
+    #[test]
+    fn test_vertical_moves_keep_the_display_column() {
+        // Wide characters take two columns, so the cursor lands under the same place
+        let mut editor = LineEditor::new();
+        editor.set("漢字abc\nabcdef");
+        assert!(editor.move_up());
+        assert_eq!(&editor.as_str()[..editor.cursor()], "漢字ab");
+        editor.set("abcd\n漢字");
+        editor.move_home();
+        editor.move_right();
+        assert!(editor.move_up());
+        assert_eq!(&editor.as_str()[..editor.cursor()], "ab");
+        assert!(editor.move_down());
+        assert_eq!(&editor.as_str()[..editor.cursor()], "abcd\n漢");
+
+        // A column inside a wide character goes before it
+        editor.set("漢字\nabc");
+        editor.move_home();
+        editor.move_right();
+        assert!(editor.move_up());
+        assert_eq!(editor.cursor(), 0);
+
+        // "\r\n" is one line break
+        editor.set("one\r\ntwo\r\nx");
+        editor.move_home();
+        assert!(editor.move_up());
+        assert!(editor.move_up());
+        editor.move_end();
+        assert_eq!(&editor.as_str()[..editor.cursor()], "one");
+        assert!(editor.move_down());
+        assert_eq!(&editor.as_str()[..editor.cursor()], "one\r\ntwo");
+        assert!(editor.move_down());
+        assert_eq!(editor.cursor(), editor.as_str().len());
+        assert!(!editor.move_down());
+    }
+}
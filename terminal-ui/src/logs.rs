@@ -0,0 +1,125 @@
+//! Log levels for the AI Terminal
+//!
+//! Finds the level of a log line in the formats commands commonly print:
+//! `ERROR` and `[warn]` words, `level=info` in logfmt, `"level":"debug"` in
+//! JSON logs, and the `E0611 12:00:00` prefix of glog and Kubernetes
+//! components. Lowercase words alone are not taken as levels, so "0 errors"
+//! in ordinary output is not an error.
+
+use std::fmt;
+
+/// How much of a line is searched for its level
+const LEVEL_PREFIX: usize = 160;
+
+/// Severity of a log line, from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// All levels, from least to most severe
+    pub const ALL: [Level; 5] = [Level::Trace, Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    /// Parse a level name, such as `warn`, `WARNING` or `fatal`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Some(Self::Trace),
+            "debug" | "dbg" => Some(Self::Debug),
+            "info" | "inf" | "notice" => Some(Self::Info),
+            "warn" | "wrn" | "warning" => Some(Self::Warn),
+            "error" | "err" | "fatal" | "crit" | "critical" | "panic" | "alert" | "emerg" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    /// Find the level of a log line, if it has one
+    pub fn detect(line: &str) -> Option<Self> {
+        let end = line.char_indices().nth(LEVEL_PREFIX).map_or(line.len(), |(at, _)| at);
+        let line = &line[..end];
+        glog(line).or_else(|| {
+            line.split(|c: char| !(c.is_alphanumeric() || c == '=' || c == ':' || c == '"'))
+                .find_map(|word| {
+                    // Keys and quotes around the value say the word is a level whatever its case
+                    let value = word
+                        .strip_prefix("level=")
+                        .or_else(|| word.strip_prefix("lvl="))
+                        .or_else(|| word.strip_prefix("\"level\":"))
+                        .or_else(|| word.strip_prefix("\"severity\":"))
+                        .map(|value| value.trim_matches('"'));
+                    let word = word.trim_matches([':', '"']);
+                    match value {
+                        Some(value) => Self::parse(value),
+                        None if word.len() > 2 && word.chars().all(|c| c.is_ascii_uppercase()) => Self::parse(word),
+                        None => None,
+                    }
+                })
+                .or_else(|| bracketed(line))
+        })
+    }
+
+    /// Get the name shown for the level
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Read the level of a glog line, such as `W0611 12:00:00.123456 1 main.go:42] ...`
+fn glog(line: &str) -> Option<Level> {
+    let mut chars = line.chars();
+    let level = match chars.next()? {
+        'I' => Level::Info,
+        'W' => Level::Warn,
+        'E' | 'F' => Level::Error,
+        _ => return None,
+    };
+    let date: String = chars.by_ref().take(5).collect();
+    (date.len() == 5 && date[..4].chars().all(|c| c.is_ascii_digit()) && date.ends_with(' ')).then_some(level)
+}
+
+/// Read a level in any case between brackets, such as `[warn]` or `<error>`
+fn bracketed(line: &str) -> Option<Level> {
+    line.split(['[', '<'])
+        .skip(1)
+        .find_map(|rest| rest.split([']', '>']).next().and_then(|name| Level::parse(name.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_are_detected() {
+        assert_eq!(Level::detect("2024-05-03T10:00:00Z ERROR server: connection refused"), Some(Level::Error));
+        assert_eq!(Level::detect("[2024-05-03 10:00:00] [warn] disk almost full"), Some(Level::Warn));
+        assert_eq!(Level::detect("time=10:00 level=debug msg=\"polling\""), Some(Level::Debug));
+        assert_eq!(Level::detect("{\"level\":\"info\",\"msg\":\"ready\"}"), Some(Level::Info));
+        assert_eq!(Level::detect("E0611 12:00:00.123456       1 reflector.go:138] failed to list"), Some(Level::Error));
+        assert_eq!(Level::detect("  WARNING: deprecated flag"), Some(Level::Warn));
+        assert_eq!(Level::detect("test result: ok. 0 errors, 2 warnings"), None);
+        assert_eq!(Level::detect("Info about the build"), None);
+    }
+
+    #[test]
+    fn test_levels_are_ordered() {
+        assert!(Level::Warn > Level::Info);
+        assert_eq!(Level::parse("Fatal"), Some(Level::Error));
+        assert_eq!(Level::ALL.iter().filter(|level| **level >= Level::Warn).count(), 2);
+    }
+}
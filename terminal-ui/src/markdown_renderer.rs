@@ -1,157 +1,487 @@
 //! Markdown renderer for the AI Terminal UI
-//! 
+//!
 //! This module provides functionality to parse Markdown text and render it
-//! as styled text using ratatui widgets.
+//! as styled text using ratatui widgets. Tables are drawn with box-drawing
+//! characters, lists and blockquotes are indented with markers and a gutter,
+//! and links are collected so they can be made clickable with OSC 8.
 
-use pulldown_cmark::{Parser, Event, Tag, CodeBlockKind};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use syntect::parsing::SyntaxSet;
-use syntect::highlighting::{ThemeSet, Style};
-use syntect::util::LinesWithEndings;
-use syntect::easy::HighlightLines;
-use std::borrow::Cow;
+
+use crate::hyperlink::Hyperlink;
+use crate::text;
+#[cfg(feature = "syntax-highlighting")]
+use crate::syntax_highlighter;
+use crate::theme::Theme;
+
+/// Widest a table column may grow before its cells are truncated
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Width of a horizontal rule
+const RULE_WIDTH: usize = 40;
+
+/// Bullets for unordered lists, by nesting depth
+const BULLETS: [&str; 3] = ["• ", "◦ ", "▪ "];
+
+/// Rendered Markdown together with the links it contains
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownDocument {
+    /// Styled lines ready for a Paragraph
+    pub lines: Vec<Line<'static>>,
+    /// Links in the order they appear
+    pub links: Vec<Hyperlink>,
+}
 
 /// Renders Markdown text as styled ratatui text
-/// 
+///
 /// # Arguments
-/// 
+///
 /// * `markdown` - The Markdown text to render
-/// 
+///
 /// # Returns
-/// 
+///
 /// A vector of ratatui Lines representing the rendered Markdown
 pub fn render_markdown(markdown: &str) -> Vec<ratatui::text::Line<'static>> {
-    let mut lines: Vec<Line> = Vec::new();
-    let parser = Parser::new(markdown);
-    
-    let mut current_spans: Vec<Span> = Vec::new();
-    let mut in_code_block = false;
-    let mut code_language = String::new();
-    let mut code_block_content = String::new();
-    
-    for event in parser {
+    render_markdown_with_theme(markdown, &Theme::default())
+}
+
+/// Renders Markdown text using the semantic styles of a theme
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown text to render
+/// * `theme` - The theme providing heading and code styles
+///
+/// # Returns
+///
+/// A vector of ratatui Lines representing the rendered Markdown
+pub fn render_markdown_with_theme(markdown: &str, theme: &Theme) -> Vec<ratatui::text::Line<'static>> {
+    render_markdown_document(markdown, theme).lines
+}
+
+/// Renders Markdown text and collects its hyperlinks
+///
+/// # Arguments
+///
+/// * `markdown` - The Markdown text to render
+/// * `theme` - The theme providing heading, code, link and quote styles
+///
+/// # Returns
+///
+/// The rendered lines and the links found in the text
+pub fn render_markdown_document(markdown: &str, theme: &Theme) -> MarkdownDocument {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer::new(theme);
+    for event in Parser::new_ext(markdown, options) {
+        renderer.handle(event);
+    }
+    renderer.finish()
+}
+
+/// Nesting state of one list
+struct ListState {
+    /// Number of the next item for ordered lists
+    next_number: Option<u64>,
+    /// Width of the current item's marker, used to indent continuation lines
+    marker_width: usize,
+}
+
+/// A table being collected until all column widths are known
+struct TableState {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>,
+    /// Number of header rows at the start of `rows`
+    header_rows: usize,
+}
+
+/// Event-driven Markdown to ratatui converter
+struct Renderer<'t> {
+    theme: &'t Theme,
+    lines: Vec<Line<'static>>,
+    links: Vec<Hyperlink>,
+    current: Vec<Span<'static>>,
+    /// Inline styles; the last entry applies to text
+    styles: Vec<Style>,
+    quote_depth: usize,
+    lists: Vec<ListState>,
+    /// Marker for the first line of the current list item
+    pending_marker: Option<String>,
+    /// Language and content of the open code block
+    code: Option<(String, String)>,
+    table: Option<TableState>,
+    /// Target and first span index of the open link
+    link: Option<(String, usize)>,
+}
+
+impl<'t> Renderer<'t> {
+    fn new(theme: &'t Theme) -> Self {
+        Self {
+            theme,
+            lines: Vec::new(),
+            links: Vec::new(),
+            current: Vec::new(),
+            styles: vec![Style::default()],
+            quote_depth: 0,
+            lists: Vec::new(),
+            pending_marker: None,
+            code: None,
+            table: None,
+            link: None,
+        }
+    }
+
+    /// Style for inline text at the current nesting
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    /// Push a style derived from the current one
+    fn push_style(&mut self, patch: Style) {
+        let style = self.style().patch(patch);
+        self.styles.push(style);
+    }
+
+    fn pop_style(&mut self) {
+        if self.styles.len() > 1 {
+            self.styles.pop();
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
         match event {
-            Event::Start(tag) => match tag {
-                Tag::Paragraph => {
-                    // Start of a new paragraph
-                    current_spans.clear();
-                },
-                Tag::Heading(level, _, _) => {
-                    // Handle heading styling
-                    let prefix = match level {
-                        pulldown_cmark::HeadingLevel::H1 => "██ ",
-                        pulldown_cmark::HeadingLevel::H2 => "▓▓▓ ",
-                        pulldown_cmark::HeadingLevel::H3 => "▒▒▒▒ ",
-                        _ => "░░░░░ ",
-                    };
-                    current_spans.push(Span::styled(prefix, ratatui::style::Style::default().fg(ratatui::style::Color::Blue)));
-                },
-                Tag::CodeBlock(kind) => {
-                    in_code_block = true;
-                    code_language = match kind {
-                        CodeBlockKind::Fenced(lang) => lang.to_string(),
-                        CodeBlockKind::Indented => String::new(),
-                    };
-                    code_block_content.clear();
-                },
-                Tag::Emphasis => {
-                    // Handle emphasis styling
-                },
-                Tag::Strong => {
-                    // Handle strong styling
-                },
-                _ => {}
-            },
-            Event::End(tag) => match tag {
-                Tag::Paragraph => {
-                    // End of paragraph, add to lines
-                    if !current_spans.is_empty() {
-                        lines.push(Line::from(current_spans.clone()));
-                        current_spans.clear();
-                    }
-                },
-                Tag::CodeBlock(_) => {
-                    in_code_block = false;
-                    // Render code block with syntax highlighting
-                    let highlighted_lines = highlight_code(&code_block_content, &code_language);
-                    lines.extend(highlighted_lines);
-                },
-                _ => {}
-            },
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
             Event::Text(text) => {
-                if in_code_block {
-                    code_block_content.push_str(&text);
+                if let Some((_, content)) = &mut self.code {
+                    content.push_str(&text);
                 } else {
-                    current_spans.push(Span::raw(text.to_string()));
+                    let style = self.style();
+                    self.current.push(Span::styled(text.to_string(), style));
                 }
-            },
+            }
             Event::Code(code) => {
-                // Handle inline code
-                current_spans.push(Span::styled(code.to_string(), ratatui::style::Style::default().fg(ratatui::style::Color::Yellow)));
-            },
-            _ => {}
+                let style = self.style().patch(self.theme.styles.inline_code);
+                self.current.push(Span::styled(code.to_string(), style));
+            }
+            Event::Html(html) => {
+                let style = self.style();
+                self.current.push(Span::styled(html.trim_end_matches('\n').to_string(), style));
+            }
+            Event::FootnoteReference(name) => {
+                self.current.push(Span::styled(format!("[^{}]", name), self.theme.styles.link));
+            }
+            // Command output is line oriented, so soft breaks keep their line
+            Event::SoftBreak | Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                let rule = Span::styled("─".repeat(RULE_WIDTH), self.theme.styles.border);
+                self.emit(vec![rule]);
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+                self.current.push(Span::styled(marker, Style::default().fg(self.theme.accent)));
+            }
         }
     }
-    
-    lines
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {}
+            Tag::Heading(level, _, _) => {
+                self.flush();
+                let prefix = match level {
+                    HeadingLevel::H1 => "██ ",
+                    HeadingLevel::H2 => "▓▓▓ ",
+                    HeadingLevel::H3 => "▒▒▒▒ ",
+                    _ => "░░░░░ ",
+                };
+                let style = self.theme.styles.heading(level as usize);
+                self.current.push(Span::styled(prefix, style));
+                self.push_style(style);
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let language = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                // Text of a tight parent item goes on its own line
+                self.flush();
+                self.lists.push(ListState { next_number: start, marker_width: 0 });
+            }
+            Tag::Item => {
+                self.flush();
+                // Bullets cycle with the number of enclosing unordered lists
+                let depth = self.lists.iter().filter(|list| list.next_number.is_none()).count().saturating_sub(1);
+                if let Some(list) = self.lists.last_mut() {
+                    let marker = match &mut list.next_number {
+                        Some(number) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        }
+                        None => BULLETS[depth % BULLETS.len()].to_string(),
+                    };
+                    list.marker_width = marker.chars().count();
+                    self.pending_marker = Some(marker);
+                }
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.flush();
+                self.current.push(Span::styled(format!("[^{}]: ", name), self.theme.styles.link));
+            }
+            Tag::Table(alignments) => {
+                self.flush();
+                self.table = Some(TableState { alignments, rows: Vec::new(), header_rows: 0 });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => self.current.clear(),
+            Tag::Emphasis => self.push_style(Style::default().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.push_style(Style::default().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link(_, url, _) => {
+                self.link = Some((url.to_string(), self.current.len()));
+                self.push_style(self.theme.styles.link);
+            }
+            Tag::Image(_, url, _) => {
+                self.current.push(Span::styled("🖼 ", self.style()));
+                self.link = Some((url.to_string(), self.current.len()));
+                self.push_style(self.theme.styles.link);
+            }
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::FootnoteDefinition(_) => self.flush(),
+            Tag::Heading(..) => {
+                self.flush();
+                self.pop_style();
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            Tag::CodeBlock(_) => {
+                if let Some((language, content)) = self.code.take() {
+                    // Render code block with syntax highlighting in the theme's colors
+                    #[cfg(feature = "syntax-highlighting")]
+                    let lines = syntax_highlighter::highlight(&content, &language, self.theme);
+                    #[cfg(not(feature = "syntax-highlighting"))]
+                    let lines = plain_code_lines(&content, &language, self.theme);
+                    for line in lines {
+                        self.emit(line.spans);
+                    }
+                }
+            }
+            Tag::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            Tag::Item => {
+                // An empty item still shows its marker
+                if self.current.is_empty() && self.pending_marker.is_some() {
+                    self.emit(Vec::new());
+                }
+                self.flush();
+            }
+            Tag::TableHead => {
+                if let Some(table) = &mut self.table {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            Tag::TableRow => {}
+            Tag::TableCell => {
+                let cell = std::mem::take(&mut self.current);
+                if let Some(row) = self.table.as_mut().and_then(|table| table.rows.last_mut()) {
+                    row.push(cell);
+                }
+            }
+            Tag::Table(_) => {
+                if let Some(table) = self.table.take() {
+                    for line in self.table_lines(table) {
+                        self.emit(line);
+                    }
+                }
+            }
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough => self.pop_style(),
+            Tag::Link(..) | Tag::Image(..) => {
+                self.pop_style();
+                if let Some((url, first_span)) = self.link.take() {
+                    let text: String = self.current[first_span.min(self.current.len())..]
+                        .iter()
+                        .map(|span| span.content.as_ref())
+                        .collect();
+                    if text != url {
+                        self.current.push(Span::styled(format!(" <{}>", url), Style::default().fg(self.theme.secondary)));
+                    }
+                    self.links.push(Hyperlink { text, url });
+                }
+            }
+        }
+    }
+
+    /// Emit the collected inline spans as a line
+    fn flush(&mut self) {
+        if !self.current.is_empty() && self.table.is_none() {
+            let spans = std::mem::take(&mut self.current);
+            self.emit(spans);
+        }
+    }
+
+    /// Add a line, prefixed with quote gutters and list indentation
+    fn emit(&mut self, spans: Vec<Span<'static>>) {
+        let mut line = Vec::with_capacity(spans.len() + 3);
+
+        for _ in 0..self.quote_depth {
+            line.push(Span::styled("│ ", self.theme.styles.blockquote));
+        }
+
+        if let Some((innermost, outer)) = self.lists.split_last() {
+            let indent: usize = outer.iter().map(|list| list.marker_width).sum();
+            if indent > 0 {
+                line.push(Span::raw(" ".repeat(indent)));
+            }
+            match self.pending_marker.take() {
+                Some(marker) => line.push(Span::styled(marker, Style::default().fg(self.theme.accent))),
+                None => line.push(Span::raw(" ".repeat(innermost.marker_width))),
+            }
+        }
+
+        line.extend(spans);
+        self.lines.push(Line::from(line));
+    }
+
+    /// Draw a collected table with box-drawing characters
+    fn table_lines(&self, table: TableState) -> Vec<Vec<Span<'static>>> {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return Vec::new();
+        }
+
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                table.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| spans_width(cell))
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(1, MAX_COLUMN_WIDTH)
+            })
+            .collect();
+
+        let border = self.theme.styles.border;
+        let rule = |left: &str, middle: &str, right: &str| -> Vec<Span<'static>> {
+            let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+            vec![Span::styled(format!("{}{}{}", left, segments.join(middle), right), border)]
+        };
+
+        let mut lines = vec![rule("┌", "┬", "┐")];
+        for (index, row) in table.rows.iter().enumerate() {
+            let mut line = vec![Span::styled("│", border)];
+            for (column, width) in widths.iter().enumerate() {
+                let alignment = table.alignments.get(column).copied().unwrap_or(Alignment::None);
+                let cell = row.get(column).cloned().unwrap_or_default();
+                let mut cell = fit_cell(cell, *width, alignment);
+                if index < table.header_rows {
+                    for span in &mut cell {
+                        span.style = span.style.add_modifier(Modifier::BOLD);
+                    }
+                }
+                line.push(Span::raw(" "));
+                line.extend(cell);
+                line.push(Span::raw(" "));
+                line.push(Span::styled("│", border));
+            }
+            lines.push(line);
+
+            if index + 1 == table.header_rows && table.rows.len() > table.header_rows {
+                lines.push(rule("├", "┼", "┤"));
+            }
+        }
+        lines.push(rule("└", "┴", "┘"));
+        lines
+    }
+
+    fn finish(mut self) -> MarkdownDocument {
+        self.flush();
+        MarkdownDocument { lines: self.lines, links: self.links }
+    }
 }
 
-/// Highlights code syntax using syntect
-/// 
-/// # Arguments
-/// 
-/// * `code` - The code to highlight
-/// * `language` - The language of the code (e.g., "rust", "python")
-/// 
-/// # Returns
-/// 
-/// A vector of ratatui Lines with syntax highlighting applied
-fn highlight_code(code: &str, language: &str) -> Vec<ratatui::text::Line<'static>> {
-    // Load syntax and theme sets
-    let ss = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
-    
-    // Find syntax for the language or use plain text as fallback
-    let syntax = ss.find_syntax_by_token(language)
-        .unwrap_or_else(|| ss.find_syntax_plain_text());
-    
-    // Create highlighter with a dark theme
-    let mut h = HighlightLines::new(syntax, &ts.themes["base16-ocean.dark"]);
-    let mut lines: Vec<Line> = Vec::new();
-    
-    // Process each line of the code
-    for line in LinesWithEndings::from(code) {
-        // Highlight the line
-        let ranges: Vec<(Style, &str)> = h.highlight_line(line, &ss).unwrap();
-        let mut spans: Vec<Span> = Vec::new();
-        
-        // Convert highlighted ranges to ratatui spans
-        for (style, text) in ranges {
-            // Convert syntect color to ratatui color
-            let fg_color = ratatui::style::Color::Rgb(
-                style.foreground.r,
-                style.foreground.g,
-                style.foreground.b,
-            );
-            
-            // Create span with the appropriate styling
-            spans.push(Span::styled(
-                Cow::Owned(text.to_string()), 
-                ratatui::style::Style::default().fg(fg_color)
-            ));
+/// Display width of a run of spans
+fn spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(Span::width).sum()
+}
+
+/// Truncate or pad a table cell to exactly `width` columns
+fn fit_cell(spans: Vec<Span<'static>>, width: usize, alignment: Alignment) -> Vec<Span<'static>> {
+    let mut fitted = Vec::with_capacity(spans.len() + 2);
+    let mut used = 0;
+
+    if spans_width(&spans) > width {
+        // Leave room for the ellipsis
+        let budget = width.saturating_sub(1);
+        for span in spans {
+            let content = text::truncate_to_width(&span.content, budget - used).to_string();
+            used += text::width(&content);
+            if !content.is_empty() {
+                fitted.push(Span::styled(content, span.style));
+            }
+            if used >= budget {
+                break;
+            }
         }
-        
-        // Add the line to our result
-        lines.push(Line::from(spans));
+        fitted.push(Span::raw("…"));
+        used += 1;
+    } else {
+        used = spans_width(&spans);
+        fitted = spans;
+    }
+
+    let padding = width.saturating_sub(used);
+    let (left, right) = match alignment {
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        Alignment::None | Alignment::Left => (0, padding),
+    };
+    if left > 0 {
+        fitted.insert(0, Span::raw(" ".repeat(left)));
+    }
+    if right > 0 {
+        fitted.push(Span::raw(" ".repeat(right)));
     }
-    
-    lines
+    fitted
+}
+
+/// Render a code block without highlighting, for builds without syntect
+#[cfg(not(feature = "syntax-highlighting"))]
+fn plain_code_lines(code: &str, _language: &str, theme: &Theme) -> Vec<Line<'static>> {
+    code.lines()
+        .map(|line| Line::from(Span::styled(line.to_string(), theme.styles.code_block)))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
     #[test]
     fn test_render_markdown() {
         let markdown = "# Heading\n\nThis is a paragraph.";
@@ -160,9 +490,87 @@ mod tests {
     }
 
     #[test]
+    fn test_headings_use_theme_styles() {
+        let theme = Theme::dark();
+        let lines = render_markdown_with_theme("## Setup\n\nRun `make`.", &theme);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans[1].content, "Setup");
+        assert_eq!(lines[0].spans[1].style, theme.styles.heading_2);
+        assert_eq!(lines[1].spans[1].style, theme.styles.inline_code);
+    }
+
+    #[test]
+    #[cfg(feature = "syntax-highlighting")]
     fn test_highlight_code() {
-        let code = "fn main() {\n    println!(\"Hello, world!\");\n}";
-        let lines = highlight_code(code, "rust");
-        assert!(!lines.is_empty());
+        let markdown = "```rust\nfn main() {\n    println!(\"Hello, world!\");\n}\n```";
+        let lines = render_markdown(markdown);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].spans.len() > 1);
+    }
+
+    #[test]
+    fn test_table_is_boxed_and_aligned() {
+        let markdown = "| Name | Size |\n|------|-----:|\n| a.txt | 12 |\n| longer.rs | 3 |";
+        let lines: Vec<String> = render_markdown(markdown).iter().map(text).collect();
+        assert_eq!(lines, vec![
+            "┌───────────┬──────┐",
+            "│ Name      │ Size │",
+            "├───────────┼──────┤",
+            "│ a.txt     │   12 │",
+            "│ longer.rs │    3 │",
+            "└───────────┴──────┘",
+        ]);
+    }
+
+    #[test]
+    fn test_wide_table_cells_are_truncated() {
+        let long = "x".repeat(MAX_COLUMN_WIDTH + 10);
+        let markdown = format!("| h |\n|---|\n| {} |", long);
+        let lines = render_markdown(&markdown);
+        let row = text(&lines[3]);
+        assert!(row.contains('…'));
+        assert_eq!(row.chars().count(), MAX_COLUMN_WIDTH + 4);
+    }
+
+    #[test]
+    fn test_nested_and_ordered_lists() {
+        let markdown = "1. first\n2. second\n   - inner\n     - deeper\n3. third";
+        let lines: Vec<String> = render_markdown(markdown).iter().map(text).collect();
+        assert_eq!(lines, vec![
+            "1. first",
+            "2. second",
+            "   • inner",
+            "     ◦ deeper",
+            "3. third",
+        ]);
+    }
+
+    #[test]
+    fn test_blockquote_gutter() {
+        let theme = Theme::dark();
+        let lines = render_markdown_with_theme("> quoted\n>\n> > nested", &theme);
+        assert_eq!(text(&lines[0]), "│ quoted");
+        assert_eq!(text(&lines[1]), "│ │ nested");
+        assert_eq!(lines[0].spans[0].style, theme.styles.blockquote);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_links_are_collected() {
+        let theme = Theme::dark();
+        let document = render_markdown_document("See [the docs](https://example.com) or <https://x.dev>.", &theme);
+        assert_eq!(document.links, vec![
+            Hyperlink { text: "the docs".to_string(), url: "https://example.com".to_string() },
+            Hyperlink { text: "https://x.dev".to_string(), url: "https://x.dev".to_string() },
+        ]);
+        assert_eq!(text(&document.lines[0]), "See the docs <https://example.com> or https://x.dev.");
+        assert_eq!(document.lines[0].spans[1].style, theme.styles.link);
+    }
+
+    #[test]
+    fn test_emphasis_and_strong() {
+        let lines = render_markdown("*soft* **loud** ~~gone~~");
+        assert!(lines[0].spans[0].style.add_modifier.contains(Modifier::ITALIC));
+        assert!(lines[0].spans[2].style.add_modifier.contains(Modifier::BOLD));
+        assert!(lines[0].spans[4].style.add_modifier.contains(Modifier::CROSSED_OUT));
+    }
+}
@@ -0,0 +1,251 @@
+//! Resource monitor for the AI Terminal
+//!
+//! Local models compete with everything else for CPU, memory and, above
+//! all, GPU memory. `/monitor` toggles a panel over the panes showing CPU,
+//! memory and disk usage, and GPU load and VRAM when built with the `gpu`
+//! feature on a machine with an NVIDIA driver. While the panel is shown the
+//! event loop samples every [`SAMPLE_INTERVAL`] and keeps the last
+//! [`HISTORY_LEN`] samples for its sparklines; hidden, it samples nothing.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use sysinfo::{Disks, System};
+
+/// Time between samples while the panel is shown
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of samples kept for the sparklines
+pub const HISTORY_LEN: usize = 60;
+
+/// Used and total amounts of a resource, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Amount in use
+    pub used: u64,
+    /// Amount there is
+    pub total: u64,
+}
+
+impl Usage {
+    /// Get the share in use, in percent
+    pub fn percent(self) -> f32 {
+        if self.total == 0 { 0.0 } else { self.used as f32 * 100.0 / self.total as f32 }
+    }
+}
+
+/// GPU load and memory at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuSample {
+    /// Time the GPU was busy over the driver's last period, in percent
+    pub load: f32,
+    /// Video memory
+    pub vram: Usage,
+}
+
+/// Resource usage at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sample {
+    /// CPU load over all cores, in percent
+    pub cpu: f32,
+    /// Memory
+    pub memory: Usage,
+    /// Space on the disk holding the working directory
+    pub disk: Usage,
+    /// First GPU, if one could be read
+    pub gpu: Option<GpuSample>,
+}
+
+/// A resource shown in the panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// CPU load
+    Cpu,
+    /// Memory use
+    Memory,
+    /// Disk use
+    Disk,
+    /// GPU load
+    Gpu,
+    /// Video memory use
+    Vram,
+}
+
+impl Metric {
+    /// Every metric, in the order shown
+    pub const ALL: [Metric; 5] = [Metric::Cpu, Metric::Memory, Metric::Disk, Metric::Gpu, Metric::Vram];
+
+    /// Label shown before the value
+    pub fn label(self) -> &'static str {
+        match self {
+            Metric::Cpu => "CPU",
+            Metric::Memory => "Memory",
+            Metric::Disk => "Disk",
+            Metric::Gpu => "GPU",
+            Metric::Vram => "VRAM",
+        }
+    }
+
+    /// Get the metric's value in a sample, in percent, if the sample has it
+    pub fn percent(self, sample: &Sample) -> Option<f32> {
+        match self {
+            Metric::Cpu => Some(sample.cpu),
+            Metric::Memory => Some(sample.memory.percent()),
+            Metric::Disk => Some(sample.disk.percent()),
+            Metric::Gpu => sample.gpu.map(|gpu| gpu.load),
+            Metric::Vram => sample.gpu.map(|gpu| gpu.vram.percent()),
+        }
+    }
+
+    /// Describe the metric's value in a sample, such as `42% (6.1/15.5 GiB)`
+    pub fn describe(self, sample: &Sample) -> Option<String> {
+        let usage = match self {
+            Metric::Cpu | Metric::Gpu => None,
+            Metric::Memory => Some(sample.memory),
+            Metric::Disk => Some(sample.disk),
+            Metric::Vram => sample.gpu.map(|gpu| gpu.vram),
+        };
+        let percent = self.percent(sample)?;
+        Some(match usage {
+            Some(usage) => format!("{:>3.0}% ({:.1}/{:.1} GiB)", percent, gib(usage.used), gib(usage.total)),
+            None => format!("{:>3.0}%", percent),
+        })
+    }
+}
+
+/// Convert bytes to GiB
+fn gib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+}
+
+/// Samples resource usage for the monitor panel
+pub struct ResourceMonitor {
+    visible: bool,
+    /// Opened the first time the panel is shown
+    system: Option<System>,
+    #[cfg(feature = "gpu")]
+    nvml: Option<nvml_wrapper::Nvml>,
+    history: VecDeque<Sample>,
+    last_sample: Option<Instant>,
+}
+
+impl Default for ResourceMonitor {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            system: None,
+            #[cfg(feature = "gpu")]
+            nvml: None,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            last_sample: None,
+        }
+    }
+}
+
+impl ResourceMonitor {
+    /// Whether the panel is shown
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the panel, returning whether it is now shown
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        if self.visible && self.system.is_none() {
+            self.system = Some(System::new());
+            #[cfg(feature = "gpu")]
+            {
+                self.nvml = nvml_wrapper::Nvml::init()
+                    .map_err(|e| tracing::info!("No GPU usage in the resource monitor: {}", e))
+                    .ok();
+            }
+        }
+        self.visible
+    }
+
+    /// Get the samples, oldest first
+    pub fn history(&self) -> &VecDeque<Sample> {
+        &self.history
+    }
+
+    /// Take a sample if the panel is shown and the last one is old enough
+    pub fn tick(&mut self, working_dir: &Path) {
+        if !self.visible || self.last_sample.is_some_and(|last| last.elapsed() < SAMPLE_INTERVAL) {
+            return;
+        }
+        self.last_sample = Some(Instant::now());
+        let Some(system) = &mut self.system else {
+            return;
+        };
+        system.refresh_cpu_usage();
+        system.refresh_memory();
+        let sample = Sample {
+            cpu: system.global_cpu_usage(),
+            memory: Usage { used: system.used_memory(), total: system.total_memory() },
+            disk: disk_usage(working_dir),
+            gpu: self.gpu_sample(),
+        };
+        self.push(sample);
+    }
+
+    /// Add a sample, dropping the oldest beyond [`HISTORY_LEN`]
+    fn push(&mut self, sample: Sample) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+    }
+
+    /// Read the load and memory of the first GPU
+    #[cfg(feature = "gpu")]
+    fn gpu_sample(&self) -> Option<GpuSample> {
+        let device = self.nvml.as_ref()?.device_by_index(0).ok()?;
+        let load = device.utilization_rates().ok()?.gpu as f32;
+        let memory = device.memory_info().ok()?;
+        Some(GpuSample { load, vram: Usage { used: memory.used, total: memory.total } })
+    }
+
+    /// Read the load and memory of the first GPU; without the `gpu` feature there is none
+    #[cfg(not(feature = "gpu"))]
+    fn gpu_sample(&self) -> Option<GpuSample> {
+        None
+    }
+}
+
+/// Get the space used on the disk holding a directory: the one with the longest mount point above it
+fn disk_usage(dir: &Path) -> Usage {
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| Usage { used: disk.total_space() - disk.available_space(), total: disk.total_space() })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_and_metrics() {
+        let mut monitor = ResourceMonitor::default();
+        for cpu in 0..HISTORY_LEN + 5 {
+            monitor.push(Sample { cpu: cpu as f32, ..Default::default() });
+        }
+        assert_eq!(monitor.history().len(), HISTORY_LEN);
+        assert_eq!(monitor.history().front().unwrap().cpu, 5.0);
+
+        let sample = Sample {
+            cpu: 12.0,
+            memory: Usage { used: 3 << 30, total: 12 << 30 },
+            ..Default::default()
+        };
+        assert_eq!(Metric::Memory.describe(&sample).unwrap(), " 25% (3.0/12.0 GiB)");
+        assert_eq!(Metric::Cpu.describe(&sample).unwrap(), " 12%");
+        assert_eq!(Metric::Vram.percent(&sample), None);
+        assert_eq!(Usage::default().percent(), 0.0);
+    }
+}
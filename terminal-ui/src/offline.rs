@@ -0,0 +1,197 @@
+//! Offline mode for the AI Terminal
+//!
+//! While offline, AI prompts and model downloads are not sent anywhere; shell
+//! commands and built-in slash commands keep working. A prompt typed while
+//! offline is either refused with a short explanation or, when
+//! `queue_prompts` is set, kept in a queue and sent in order once the terminal
+//! goes back online. Offline mode is toggled with F3 or the command palette
+//! and can start enabled from the `[offline]` section of the configuration.
+//! The terminal is also offline, without being switched, while the model
+//! server cannot be reached; it goes back online by itself once the server
+//! answers again.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// Offline settings from the `[offline]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OfflineConfig {
+    /// Start in offline mode
+    pub enabled: bool,
+    /// Queue AI prompts made while offline instead of refusing them
+    pub queue_prompts: bool,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            queue_prompts: true,
+        }
+    }
+}
+
+/// An AI prompt waiting to be sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedPrompt {
+    /// The prompt as typed, including the leading '/'
+    pub command: String,
+    /// When the prompt was queued
+    pub queued_at: DateTime<Local>,
+}
+
+/// Whether AI calls are allowed, and the prompts held back while they are not
+#[derive(Debug, Clone, Default)]
+pub struct OfflineMode {
+    /// Settings, with `enabled` tracking the current state
+    config: OfflineConfig,
+    /// Prompts to send once online, oldest first
+    queue: Vec<QueuedPrompt>,
+    /// Why the model server cannot be reached, while it cannot
+    unreachable: Option<String>,
+}
+
+impl OfflineMode {
+    /// Create the offline state from the configuration
+    pub fn new(config: OfflineConfig) -> Self {
+        Self {
+            config,
+            queue: Vec::new(),
+            unreachable: None,
+        }
+    }
+
+    /// Check whether AI calls are blocked
+    pub fn is_offline(&self) -> bool {
+        self.config.enabled || self.unreachable.is_some()
+    }
+
+    /// Check whether offline mode was switched on, rather than the server being unreachable
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Get why the model server cannot be reached, while it cannot
+    pub fn unreachable(&self) -> Option<&str> {
+        self.unreachable.as_deref()
+    }
+
+    /// Go offline or online, returning the queued prompts to send when going online
+    pub fn set_offline(&mut self, offline: bool) -> Vec<QueuedPrompt> {
+        self.config.enabled = offline;
+        self.take_queue_if_online()
+    }
+
+    /// Record whether the model server can be reached, returning the queued prompts to send once it can
+    pub fn set_unreachable(&mut self, reason: Option<String>) -> Vec<QueuedPrompt> {
+        self.unreachable = reason;
+        self.take_queue_if_online()
+    }
+
+    /// Take the queued prompts if nothing blocks AI calls any more
+    fn take_queue_if_online(&mut self) -> Vec<QueuedPrompt> {
+        if self.is_offline() {
+            Vec::new()
+        } else {
+            std::mem::take(&mut self.queue)
+        }
+    }
+
+    /// Say why AI calls are blocked and how they come back, for refusals
+    pub fn reason(&self) -> String {
+        match &self.unreachable {
+            Some(reason) if !self.config.enabled => {
+                format!("the model server is unavailable ({})", reason)
+            }
+            _ => "you are offline; press F3 to go online".to_string(),
+        }
+    }
+
+    /// Hold back an AI prompt made while offline, returning what to tell the user
+    pub fn hold(&mut self, command: String) -> String {
+        let waiting = !self.config.enabled && self.unreachable.is_some();
+        if !self.config.queue_prompts {
+            if waiting {
+                return format!("AI requests are not sent: {}.", self.reason());
+            }
+            return "You are offline, so AI requests are not sent. Press F3 to go online.".to_string();
+        }
+        self.queue.push(QueuedPrompt {
+            command,
+            queued_at: Local::now(),
+        });
+        if waiting {
+            return format!(
+                "The model server is unreachable, so the prompt was queued as #{}. It will be sent once the server answers; /queue lists the queue.",
+                self.queue.len(),
+            );
+        }
+        format!(
+            "You are offline, so the prompt was queued as #{}. It will be sent when you go online (F3); /queue lists the queue.",
+            self.queue.len(),
+        )
+    }
+
+    /// Get the prompts waiting to be sent
+    pub fn queued(&self) -> &[QueuedPrompt] {
+        &self.queue
+    }
+
+    /// Drop every queued prompt, returning how many there were
+    pub fn clear_queue(&mut self) -> usize {
+        std::mem::take(&mut self.queue).len()
+    }
+
+    /// Short label for the status bar while offline
+    pub fn status_segment(&self) -> Option<String> {
+        let label = match (self.config.enabled, &self.unreachable) {
+            (true, _) => "✈ OFFLINE",
+            (false, Some(_)) => "⚠ AI UNAVAILABLE",
+            (false, None) => return None,
+        };
+        Some(match self.queue.len() {
+            0 => format!(" {} ", label),
+            queued => format!(" {} · {} queued ", label, queued),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_until_online() {
+        let mut offline = OfflineMode::new(OfflineConfig { enabled: true, ..Default::default() });
+        assert!(offline.hold("/explain ls".to_string()).contains("#1"));
+        offline.hold("/explain cd".to_string());
+        assert_eq!(offline.status_segment().unwrap(), " ✈ OFFLINE · 2 queued ");
+
+        let sent = offline.set_offline(false);
+        assert_eq!(sent.iter().map(|queued| queued.command.as_str()).collect::<Vec<_>>(), ["/explain ls", "/explain cd"]);
+        assert!(offline.queued().is_empty());
+        assert_eq!(offline.status_segment(), None);
+
+        // Without queueing, prompts are refused
+        let mut offline = OfflineMode::new(OfflineConfig { enabled: true, queue_prompts: false });
+        assert!(offline.hold("/explain ls".to_string()).contains("not sent"));
+        assert!(offline.queued().is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_server() {
+        let mut offline = OfflineMode::default();
+        assert!(offline.set_unreachable(Some("connection refused".to_string())).is_empty());
+        assert!(offline.is_offline() && !offline.is_enabled());
+        assert!(offline.hold("/explain ls".to_string()).contains("once the server answers"));
+        assert_eq!(offline.status_segment().unwrap(), " ⚠ AI UNAVAILABLE · 1 queued ");
+        assert!(offline.reason().contains("connection refused"));
+
+        // Prompts wait while offline mode is also on, whichever ends first
+        offline.set_offline(true);
+        assert!(offline.set_unreachable(None).is_empty());
+        assert_eq!(offline.set_offline(false).len(), 1);
+        assert!(!offline.is_offline());
+    }
+}
@@ -0,0 +1,768 @@
+//! First-run setup of the AI Terminal
+//!
+//! When the terminal starts for the first time, with no `config.toml` in the
+//! working directory and no config directory yet, a wizard asks what the
+//! defaults cannot know before the UI opens: where the Ollama server is,
+//! which model answers by default (pulling it with progress if it is not
+//! installed), the color theme, the scrollback keys, and whether the model
+//! may run commands without asking. The answers are written as a commented
+//! `config.toml` in the config directory, with the theme in `theme.toml`
+//! beside it as the theme picker keeps it.
+
+use std::fs;
+use std::io::{self, Stdout};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures_util::StreamExt;
+use ollama_client::{ChatBackend, OllamaClient, PullProgress};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+use tokio::sync::mpsc;
+
+use crate::agent::AgentConfig;
+use crate::theme::{Theme, ThemeManager};
+use crate::widgets::PullStatus;
+
+/// Address of a local Ollama server
+pub const DEFAULT_URL: &str = "http://localhost:11434";
+
+/// Model offered for pulling when none is installed
+const SUGGESTED_MODEL: &str = "llama3.2";
+
+/// How long the wizard waits for the server to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the wizard redraws while waiting for keys or the server
+const FRAME: Duration = Duration::from_millis(50);
+
+/// System prompt written to the configuration
+const SYSTEM_PROMPT: &str = "You are an expert terminal assistant. You help users with terminal commands,
+shell scripting, and system administration tasks. Provide concise, accurate
+responses and always consider the context of the user's operating system.";
+
+/// Get the directory holding the configuration and the other files of the terminal
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ai-terminal"))
+}
+
+/// Check whether the terminal has never been set up: no `config.toml` here and no config directory
+pub fn is_first_run() -> bool {
+    !Path::new("config.toml").exists() && config_dir().is_some_and(|dir| !dir.exists())
+}
+
+/// Scrollback keys offered by the wizard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPreset {
+    /// The built-in keys
+    Default,
+    /// The built-in keys, with lines also on alt+j and alt+k and blocks on alt+{ and alt+}
+    Vi,
+}
+
+impl KeyPreset {
+    /// Every preset, in the order offered
+    pub const ALL: [KeyPreset; 2] = [KeyPreset::Default, KeyPreset::Vi];
+
+    /// Name of the preset
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyPreset::Default => "default",
+            KeyPreset::Vi => "vi",
+        }
+    }
+
+    /// Short description for the wizard
+    pub fn description(self) -> &'static str {
+        match self {
+            KeyPreset::Default => "ctrl+up/down scroll lines, alt+up/down jump between blocks",
+            KeyPreset::Vi => "as default, plus alt+k/j for lines and alt+{/} for blocks",
+        }
+    }
+
+    /// Bindings written to `[keys]`, by motion name
+    pub fn bindings(self) -> &'static [(&'static str, &'static [&'static str])] {
+        match self {
+            KeyPreset::Default => &[],
+            KeyPreset::Vi => &[
+                ("line_up", &["ctrl+up", "alt+k"]),
+                ("line_down", &["ctrl+down", "alt+j"]),
+                ("previous_block", &["alt+up", "alt+{"]),
+                ("next_block", &["alt+down", "alt+}"]),
+            ],
+        }
+    }
+}
+
+/// Choices made in the wizard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answers {
+    /// Address of the Ollama server, without `/api`
+    pub url: String,
+    /// Model answering by default
+    pub model: String,
+    /// Name of the color theme
+    pub theme: String,
+    pub keys: KeyPreset,
+    /// Whether the model may run commands unasked: its tool calls, and read-only agent commands
+    pub ai_commands: bool,
+}
+
+impl Answers {
+    /// Write the answers as a commented `config.toml`
+    pub fn config_toml(&self) -> String {
+        let quote = |text: &str| toml::Value::String(text.to_string()).to_string();
+        let mut lines = vec![
+            "# AI-Terminal Configuration File".to_string(),
+            "# Written by the setup wizard. A config.toml in the working directory".to_string(),
+            "# is used instead of this one; the README describes every section.".to_string(),
+            String::new(),
+            "[ollama]".to_string(),
+            "# The default model to use for Ollama requests".to_string(),
+            format!("model = {}", quote(&self.model)),
+            String::new(),
+            "# Optional system prompt to guide the model's behavior".to_string(),
+            format!("system_prompt = \"\"\"\n{}\n\"\"\"", SYSTEM_PROMPT),
+            String::new(),
+            "# Server that AI commands are sent to".to_string(),
+            "[backend]".to_string(),
+            format!("base_url = {}", quote(&api_url(&self.url))),
+            String::new(),
+            "# Scrollback keys by motion, such as previous_block = [\"alt+up\"];".to_string(),
+            "# motions left out keep their default keys".to_string(),
+            "[keys]".to_string(),
+        ];
+        for (motion, keys) in self.keys.bindings() {
+            let keys: Vec<String> = keys.iter().map(|key| quote(key)).collect();
+            lines.push(format!("{} = [{}]", motion, keys.join(", ")));
+        }
+        lines.push(String::new());
+        if self.ai_commands {
+            lines.extend([
+                "# The model's tool calls run, and agent commands starting with an".to_string(),
+                "# auto_approve entry run without asking; other commands ask first.".to_string(),
+                "# Directories marked untrusted with /trust always ask.".to_string(),
+                "[trust]".to_string(),
+                "untrusted_by_default = false".to_string(),
+                String::new(),
+                "[agent]".to_string(),
+            ]);
+            let approved: Vec<String> = AgentConfig::default().auto_approve.iter().map(|command| quote(command)).collect();
+            lines.push(format!("auto_approve = [{}]", approved.join(", ")));
+        } else {
+            lines.extend([
+                "# The model runs no commands on its own: its tool calls are refused and".to_string(),
+                "# every agent command asks first. Mark a directory trusted with /trust".to_string(),
+                "# to let tool calls run there.".to_string(),
+                "[trust]".to_string(),
+                "untrusted_by_default = true".to_string(),
+                String::new(),
+                "[agent]".to_string(),
+                "auto_approve = []".to_string(),
+            ]);
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Write the configuration and the theme to `dir`, giving the path of `config.toml`
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).with_context(|| format!("could not create {}", dir.display()))?;
+        let path = dir.join("config.toml");
+        fs::write(&path, self.config_toml()).with_context(|| format!("could not write {}", path.display()))?;
+        ThemeManager::with_config_dir(dir.to_str().map(str::to_string))
+            .persist_selection(&self.theme)
+            .map_err(|e| anyhow::anyhow!("could not save the theme: {}", e))?;
+        Ok(path)
+    }
+}
+
+/// Get the API address of an Ollama server
+fn api_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if url.ends_with("/api") { url.to_string() } else { format!("{}/api", url) }
+}
+
+/// Run the wizard, writing the answers to `dir`
+///
+/// Gives the path of the written `config.toml`, or `None` when the wizard was cancelled.
+pub async fn run(dir: &Path) -> Result<Option<PathBuf>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let answers = Wizard::new(dir.join("config.toml")).run(&mut terminal).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    match answers? {
+        Some(answers) => answers.save(dir).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Pages of the wizard, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Server,
+    Model,
+    Theme,
+    Keys,
+    Commands,
+    Review,
+}
+
+impl Step {
+    const ALL: [Step; 6] = [Step::Server, Step::Model, Step::Theme, Step::Keys, Step::Commands, Step::Review];
+
+    /// Title shown above the page
+    fn title(self) -> &'static str {
+        match self {
+            Step::Server => "Ollama server",
+            Step::Model => "Default model",
+            Step::Theme => "Theme",
+            Step::Keys => "Scrollback keys",
+            Step::Commands => "AI command execution",
+            Step::Review => "Review",
+        }
+    }
+
+    /// Get the position of the page, counting from 1
+    fn number(self) -> usize {
+        Step::ALL.iter().position(|step| *step == self).unwrap_or(0) + 1
+    }
+}
+
+/// What the background requests of the wizard report
+enum Update {
+    /// Version and models of the server at `url`, or why it could not be reached
+    Checked { url: String, result: Result<(String, Vec<String>), String> },
+    Progress(PullProgress),
+    /// End of a pull
+    Pulled(Result<(), String>),
+}
+
+/// What a key press does to the wizard
+enum Flow {
+    Continue,
+    Cancel,
+    Finish,
+}
+
+/// State of the wizard between frames
+struct Wizard {
+    step: Step,
+    /// Where the configuration will be written, for display
+    path: PathBuf,
+    url: String,
+    /// Version and models of the server at `url`, or why it could not be reached, once checked
+    server: Option<Result<(String, Vec<String>), String>>,
+    checking: bool,
+    /// Selected entry of the model list; the entry after the installed models pulls another
+    model_index: usize,
+    /// Name typed for a model to pull
+    model_input: Option<String>,
+    pull: Option<PullStatus>,
+    /// Why the last pull failed
+    message: Option<String>,
+    model: String,
+    themes: ThemeManager,
+    theme_names: Vec<String>,
+    theme_index: usize,
+    keys_index: usize,
+    ai_commands: bool,
+    sender: mpsc::UnboundedSender<Update>,
+    updates: mpsc::UnboundedReceiver<Update>,
+}
+
+impl Wizard {
+    /// Start the wizard at the first page
+    fn new(path: PathBuf) -> Self {
+        let themes = ThemeManager::with_config_dir(None);
+        let mut theme_names: Vec<String> = themes.available_theme_names().into_iter().cloned().collect();
+        theme_names.sort();
+        let theme_index = theme_names.iter().position(|name| *name == Theme::default().name).unwrap_or(0);
+        let (sender, updates) = mpsc::unbounded_channel();
+        Self {
+            step: Step::Server,
+            path,
+            url: DEFAULT_URL.to_string(),
+            server: None,
+            checking: false,
+            model_index: 0,
+            model_input: None,
+            pull: None,
+            message: None,
+            model: String::new(),
+            themes,
+            theme_names,
+            theme_index,
+            keys_index: 0,
+            ai_commands: true,
+            sender,
+            updates,
+        }
+    }
+
+    /// Show the wizard until it is finished or cancelled
+    async fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<Option<Answers>> {
+        loop {
+            while let Ok(update) = self.updates.try_recv() {
+                self.apply(update);
+            }
+            terminal.draw(|f| self.render(f))?;
+            // Polling without blocking lets the requests run on any runtime
+            if !event::poll(Duration::ZERO)? {
+                tokio::time::sleep(FRAME).await;
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match self.handle_key(key) {
+                    Flow::Continue => {}
+                    Flow::Cancel => return Ok(None),
+                    Flow::Finish => return Ok(Some(self.answers())),
+                }
+            }
+        }
+    }
+
+    /// Collect the answers given
+    fn answers(&self) -> Answers {
+        Answers {
+            url: self.url.trim().to_string(),
+            model: self.model.clone(),
+            theme: self.theme_names.get(self.theme_index).cloned().unwrap_or_else(|| Theme::default().name),
+            keys: KeyPreset::ALL[self.keys_index],
+            ai_commands: self.ai_commands,
+        }
+    }
+
+    /// Get the models installed on the server, if it was reached
+    fn models(&self) -> &[String] {
+        match &self.server {
+            Some(Ok((_, models))) => models,
+            _ => &[],
+        }
+    }
+
+    /// Ask the server at the typed address for its version and models
+    fn check_server(&mut self) {
+        self.checking = true;
+        self.server = None;
+        let url = self.url.trim().to_string();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let mut client = OllamaClient::new().map_err(|e| e.to_string())?;
+                client.base_url = api_url(&url);
+                let version = within(client.version()).await?;
+                let models = within(client.list_models()).await?;
+                Ok((version, models))
+            }
+            .await;
+            let _ = sender.send(Update::Checked { url, result });
+        });
+    }
+
+    /// Pull a model, reporting its progress
+    fn start_pull(&mut self, model: String) {
+        let mut client = match OllamaClient::new() {
+            Ok(client) => client,
+            Err(e) => {
+                self.message = Some(e.to_string());
+                return;
+            }
+        };
+        client.base_url = api_url(&self.url);
+        self.message = None;
+        self.pull = Some(PullStatus { model: model.clone(), status: "starting".to_string(), fraction: None });
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let mut stream = Box::pin(client.pull_model(&model).await.map_err(|e| e.to_string())?);
+                while let Some(progress) = stream.next().await {
+                    let progress = progress.map_err(|e| e.to_string())?;
+                    if progress.is_success() {
+                        return Ok(());
+                    }
+                    let _ = sender.send(Update::Progress(progress));
+                }
+                Err("the download stopped unexpectedly".to_string())
+            }
+            .await;
+            let _ = sender.send(Update::Pulled(result));
+        });
+    }
+
+    /// Apply what a background request reported
+    fn apply(&mut self, update: Update) {
+        match update {
+            // An answer for an address since edited is stale
+            Update::Checked { url, result } if url == self.url.trim() => {
+                self.checking = false;
+                let reached = result.is_ok();
+                self.server = Some(result);
+                if reached {
+                    self.enter_model_step();
+                }
+            }
+            Update::Checked { .. } => {}
+            Update::Progress(progress) => {
+                if let Some(pull) = &mut self.pull {
+                    pull.fraction = progress.fraction();
+                    pull.status = progress.status;
+                }
+            }
+            Update::Pulled(result) => {
+                let Some(pull) = self.pull.take() else {
+                    return;
+                };
+                match result {
+                    Ok(()) => {
+                        if let Some(Ok((_, models))) = &mut self.server {
+                            models.push(pull.model.clone());
+                        }
+                        self.model = pull.model;
+                        self.model_input = None;
+                        self.step = Step::Theme;
+                    }
+                    Err(e) => self.message = Some(format!("Could not pull {}: {}", pull.model, e)),
+                }
+            }
+        }
+    }
+
+    /// Go to the model page, typing a name when there is nothing to pick
+    fn enter_model_step(&mut self) {
+        self.step = Step::Model;
+        self.message = None;
+        let models = self.models();
+        let model_index = models.iter().position(|model| *model == self.model).unwrap_or(0);
+        let model_input = models.is_empty().then(|| {
+            if self.model.is_empty() { SUGGESTED_MODEL.to_string() } else { self.model.clone() }
+        });
+        self.model_index = model_index;
+        self.model_input = model_input;
+    }
+
+    /// Handle a key press on the current page
+    fn handle_key(&mut self, key: KeyEvent) -> Flow {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Flow::Cancel;
+        }
+        // Keys wait while a model downloads
+        if self.pull.is_some() {
+            return Flow::Continue;
+        }
+        match self.step {
+            Step::Server => match key.code {
+                KeyCode::Char(c) => {
+                    self.url.push(c);
+                    self.server = None;
+                }
+                KeyCode::Backspace => {
+                    self.url.pop();
+                    self.server = None;
+                }
+                KeyCode::Enter if !self.url.trim().is_empty() && !self.checking => match &self.server {
+                    Some(Ok(_)) => self.enter_model_step(),
+                    _ => self.check_server(),
+                },
+                // Setting up for a server that is not running yet
+                KeyCode::Tab if matches!(self.server, Some(Err(_))) => self.enter_model_step(),
+                KeyCode::Esc => return Flow::Cancel,
+                _ => {}
+            },
+            Step::Model => self.handle_model_key(key),
+            Step::Theme => match key.code {
+                KeyCode::Up => self.theme_index = self.theme_index.saturating_sub(1),
+                KeyCode::Down => self.theme_index = (self.theme_index + 1).min(self.theme_names.len().saturating_sub(1)),
+                KeyCode::Enter => self.step = Step::Keys,
+                KeyCode::Esc => self.enter_model_step(),
+                _ => {}
+            },
+            Step::Keys => match key.code {
+                KeyCode::Up => self.keys_index = self.keys_index.saturating_sub(1),
+                KeyCode::Down => self.keys_index = (self.keys_index + 1).min(KeyPreset::ALL.len() - 1),
+                KeyCode::Enter => self.step = Step::Commands,
+                KeyCode::Esc => self.step = Step::Theme,
+                _ => {}
+            },
+            Step::Commands => match key.code {
+                KeyCode::Up => self.ai_commands = true,
+                KeyCode::Down => self.ai_commands = false,
+                KeyCode::Enter => self.step = Step::Review,
+                KeyCode::Esc => self.step = Step::Keys,
+                _ => {}
+            },
+            Step::Review => match key.code {
+                KeyCode::Enter => return Flow::Finish,
+                KeyCode::Esc => self.step = Step::Commands,
+                _ => {}
+            },
+        }
+        if self.step == Step::Theme
+            && let Some(name) = self.theme_names.get(self.theme_index)
+        {
+            let _ = self.themes.switch_theme(name);
+        }
+        Flow::Continue
+    }
+
+    /// Handle a key press on the model page
+    fn handle_model_key(&mut self, key: KeyEvent) {
+        let reached = matches!(self.server, Some(Ok(_)));
+        if let Some(input) = &mut self.model_input {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter if !input.trim().is_empty() => {
+                    let model = input.trim().to_string();
+                    if self.models().contains(&model) {
+                        self.model = model;
+                        self.model_input = None;
+                        self.step = Step::Theme;
+                    } else if reached {
+                        self.start_pull(model);
+                    } else {
+                        // Without a server the model is pulled later
+                        self.model = model;
+                        self.step = Step::Theme;
+                    }
+                }
+                KeyCode::Esc if self.models().is_empty() => self.step = Step::Server,
+                KeyCode::Esc => {
+                    self.model_input = None;
+                    self.message = None;
+                }
+                _ => {}
+            }
+            return;
+        }
+        let count = self.models().len();
+        match key.code {
+            KeyCode::Up => self.model_index = self.model_index.saturating_sub(1),
+            KeyCode::Down => self.model_index = (self.model_index + 1).min(count),
+            KeyCode::Enter if self.model_index < count => {
+                self.model = self.models()[self.model_index].clone();
+                self.step = Step::Theme;
+            }
+            KeyCode::Enter => self.model_input = Some(SUGGESTED_MODEL.to_string()),
+            KeyCode::Esc => self.step = Step::Server,
+            _ => {}
+        }
+    }
+
+    /// Draw the current page
+    fn render(&self, f: &mut Frame) {
+        let theme = self.themes.current_theme();
+        let area = centered(f.area(), 76, 22);
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!(" AI Terminal setup — step {} of {}: {} ", self.step.number(), Step::ALL.len(), self.step.title()));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(inner);
+        let text = Style::default().fg(theme.text);
+        f.render_widget(Paragraph::new(self.intro()).style(text).wrap(Wrap { trim: true }), chunks[0]);
+
+        match self.step {
+            Step::Server => {
+                f.render_widget(Paragraph::new(format!("URL: {}▏", self.url)).style(text), chunks[1]);
+            }
+            Step::Model => match &self.model_input {
+                Some(input) => {
+                    f.render_widget(Paragraph::new(format!("Model: {}▏", input)).style(text), chunks[1]);
+                }
+                None => {
+                    let mut items: Vec<String> = self.models().to_vec();
+                    items.push("Pull another model…".to_string());
+                    render_list(f, chunks[1], theme, items, self.model_index);
+                }
+            },
+            Step::Theme => render_list(f, chunks[1], theme, self.theme_names.clone(), self.theme_index),
+            Step::Keys => {
+                let items = KeyPreset::ALL.iter().map(|preset| format!("{:<8} {}", preset.name(), preset.description())).collect();
+                render_list(f, chunks[1], theme, items, self.keys_index);
+            }
+            Step::Commands => {
+                let items = vec![
+                    "Enabled: tool calls and read-only agent commands run without asking".to_string(),
+                    "Disabled: the model runs nothing without asking".to_string(),
+                ];
+                render_list(f, chunks[1], theme, items, if self.ai_commands { 0 } else { 1 });
+            }
+            Step::Review => {
+                let answers = self.answers();
+                let lines: Vec<Line> = [
+                    format!("Server       {}", answers.url),
+                    format!("Model        {}", answers.model),
+                    format!("Theme        {}", answers.theme),
+                    format!("Keys         {}", answers.keys.name()),
+                    format!("AI commands  {}", if answers.ai_commands { "enabled" } else { "disabled" }),
+                ]
+                .into_iter()
+                .map(Line::from)
+                .collect();
+                f.render_widget(Paragraph::new(lines).style(text), chunks[1]);
+            }
+        }
+
+        // Pull progress, or what went wrong
+        if let Some(pull) = &self.pull {
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(theme.accent).bg(theme.background))
+                .ratio(pull.fraction.unwrap_or(0.0))
+                .label(format!("{}: {}", pull.model, pull.status));
+            f.render_widget(gauge, chunks[2]);
+        } else if let Some(status) = self.status() {
+            f.render_widget(Paragraph::new(status.0).style(Style::default().fg(status.1)), chunks[2]);
+        }
+        f.render_widget(Paragraph::new(self.hints()).style(theme.styles.status_info), chunks[3]);
+    }
+
+    /// Explain the current page
+    fn intro(&self) -> String {
+        match self.step {
+            Step::Server => format!(
+                "Welcome! A few questions set up the AI Terminal; the answers are written to {}. \
+                 Which Ollama server should AI requests go to?",
+                self.path.display()
+            ),
+            Step::Model => match &self.server {
+                Some(Ok((version, models))) if models.is_empty() => {
+                    format!("Ollama {} has no models installed. Which model should it pull to answer by default?", version)
+                }
+                Some(Ok((version, models))) => {
+                    format!("Ollama {} has {} model(s) installed. Which one should answer by default?", version, models.len())
+                }
+                _ => "Without the server, type the model to use; pull it later with `ollama pull` or /models.".to_string(),
+            },
+            Step::Theme => "Pick a color theme; this page previews it. /theme switches it later.".to_string(),
+            Step::Keys => "Pick the keys scrolling through output. The [keys] section of config.toml can rebind every motion.".to_string(),
+            Step::Commands => {
+                "May the model run commands without asking? Either way, commands it suggests in chat only run \
+                 when you run them, and /trust marks directories where it may or may not."
+                    .to_string()
+            }
+            Step::Review => format!("Enter writes {}; edit it any time.", self.path.display()),
+        }
+    }
+
+    /// Describe the state of the server check or a failed pull, with its color
+    fn status(&self) -> Option<(String, ratatui::style::Color)> {
+        let theme = self.themes.current_theme();
+        if let Some(message) = &self.message {
+            return Some((message.clone(), theme.error));
+        }
+        match (self.step, &self.server) {
+            (Step::Server, _) if self.checking => Some(("Connecting…".to_string(), theme.secondary)),
+            (Step::Server, Some(Err(e))) => Some((format!("Could not reach the server: {}", e), theme.error)),
+            _ => None,
+        }
+    }
+
+    /// Keys of the current page
+    fn hints(&self) -> &'static str {
+        match self.step {
+            _ if self.pull.is_some() => "Downloading… | Ctrl+C: quit",
+            Step::Server if matches!(self.server, Some(Err(_))) => "Enter: retry | Tab: continue without the server | Esc: quit",
+            Step::Server => "Enter: connect | Esc: quit",
+            Step::Model if self.model_input.is_some() => "Enter: use or pull | Esc: back",
+            Step::Review => "Enter: save and start | Esc: back | Ctrl+C: quit without saving",
+            _ => "↑/↓: choose | Enter: next | Esc: back | Ctrl+C: quit",
+        }
+    }
+}
+
+/// Wait for a request to the server, giving up after [`TIMEOUT`]
+async fn within<T, E: std::fmt::Display>(request: impl std::future::Future<Output = Result<T, E>>) -> Result<T, String> {
+    match tokio::time::timeout(TIMEOUT, request).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("no answer within {}s", TIMEOUT.as_secs())),
+    }
+}
+
+/// Draw a list with the selected entry highlighted and kept in view
+fn render_list(f: &mut Frame, area: Rect, theme: &Theme, items: Vec<String>, selected: usize) {
+    let items: Vec<ListItem> = items.into_iter().map(ListItem::new).collect();
+    let list = List::new(items)
+        .style(Style::default().fg(theme.text))
+        .highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD))
+        .highlight_symbol("› ");
+    let mut state = ListState::default().with_selected(Some(selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Get a rectangle of at most `width` by `height` in the middle of `area`
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Keymap, KeymapConfig};
+
+    #[test]
+    fn test_config_is_valid_toml() {
+        let mut answers = Answers {
+            url: "http://gpu.local:11434/".to_string(),
+            model: "qwen2.5-coder:7b".to_string(),
+            theme: "dark".to_string(),
+            keys: KeyPreset::Vi,
+            ai_commands: false,
+        };
+        let config: toml::Value = toml::from_str(&answers.config_toml()).unwrap();
+        assert_eq!(config["ollama"]["model"].as_str(), Some("qwen2.5-coder:7b"));
+        assert_eq!(config["backend"]["base_url"].as_str(), Some("http://gpu.local:11434/api"));
+        assert_eq!(config["trust"]["untrusted_by_default"].as_bool(), Some(true));
+        assert_eq!(config["agent"]["auto_approve"].as_array().map(Vec::len), Some(0));
+        let keys: KeymapConfig = config["keys"].clone().try_into().unwrap();
+        assert_eq!(keys.bindings.len(), 4);
+
+        answers.ai_commands = true;
+        let config: toml::Value = toml::from_str(&answers.config_toml()).unwrap();
+        let agent: AgentConfig = config["agent"].clone().try_into().unwrap();
+        assert_eq!(agent, AgentConfig::default());
+    }
+
+    #[test]
+    fn test_key_presets_are_valid() {
+        for preset in KeyPreset::ALL {
+            let bindings = preset
+                .bindings()
+                .iter()
+                .map(|(motion, keys)| (motion.to_string(), toml::Value::from(keys.to_vec())))
+                .collect::<toml::Table>();
+            let config: KeymapConfig = toml::Value::Table(bindings).try_into().unwrap();
+            assert!(Keymap::from_config(&config).is_ok(), "{} is invalid", preset.name());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Persisted data for the AI Terminal UI
+//!
+//! Saved sessions and usage statistics live in the shared [`Store`]
+//! database; the LLM request log is a compressed [`FrameStore`] record file.
+//! Both are kept in the application data directory.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local};
+use ollama_client::TokenUsage;
+use serde::{Deserialize, Serialize};
+use terminal_emulator::{FileLock, FrameStore, SessionBlock, Store};
+
+/// Name of the session saved when the terminal exits
+pub const LAST_SESSION: &str = "last";
+
+/// One AI request and its outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmLogEntry {
+    /// When the request was sent
+    pub timestamp: DateTime<Local>,
+    /// Model that handled the request
+    pub model: String,
+    /// Prompt sent to the model
+    pub prompt: String,
+    /// Response text, if the request succeeded
+    pub response: Option<String>,
+    /// Error message, if the request failed
+    pub error: Option<String>,
+    /// Time taken in milliseconds
+    pub duration_ms: u64,
+    /// Token counts and timings, if the backend reported them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+/// Get the application data directory
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ai-terminal")
+}
+
+/// Open the LLM request log
+pub fn open_llm_log() -> anyhow::Result<FrameStore<LlmLogEntry>> {
+    FrameStore::open(data_dir().join("logs").join("llm.aitz"))
+}
+
+/// Open the shared database, importing a last session saved by older versions
+pub fn open_store() -> anyhow::Result<Store> {
+    let mut store = Store::open_default()?;
+
+    let sessions_dir = data_dir().join("sessions");
+    let legacy_session = sessions_dir.join("last.aitz");
+    // Another instance starting at the same time may be importing it too
+    let _lock = legacy_session.exists().then(|| FileLock::exclusive(&sessions_dir)).transpose()?;
+    if legacy_session.exists() {
+        let blocks = FrameStore::<SessionBlock>::open(&legacy_session)?.read_all()?;
+        store.save_session(LAST_SESSION, &blocks)?;
+        std::fs::remove_file(&legacy_session)?;
+        let _ = std::fs::remove_file(legacy_session.with_extension("aitz.idx"));
+        let _ = std::fs::remove_file(legacy_session.with_extension("aitz.lock"));
+    }
+
+    Ok(store)
+}
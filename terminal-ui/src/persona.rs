@@ -0,0 +1,174 @@
+//! Personas for the AI Terminal
+//!
+//! A persona is a named system prompt, such as a terse shell expert or a
+//! patient teacher. Personas are stored as TOML files in the `personas`
+//! directory of the config directory, one file per persona, so they can also
+//! be written by hand. Prompts from the `[custom_prompts]` section of the
+//! configuration are offered as personas too. Each tab can use a different
+//! persona; its prompt is sent as the system message of every AI request made
+//! from that tab.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A named system prompt
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Persona {
+    /// Name of the persona, also its file name
+    pub name: String,
+    /// System prompt sent with AI requests
+    pub prompt: String,
+}
+
+impl Persona {
+    /// Create a persona
+    pub fn new(name: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prompt: prompt.into(),
+        }
+    }
+}
+
+/// Loads, saves and looks up personas
+#[derive(Debug, Clone, Default)]
+pub struct PersonaManager {
+    /// Known personas by name
+    personas: BTreeMap<String, Persona>,
+    /// Directory holding the persona files, if there is a config directory
+    dir: Option<PathBuf>,
+}
+
+impl PersonaManager {
+    /// Create a manager for the personas in the user's config directory
+    pub fn new() -> Self {
+        Self::with_dir(dirs::config_dir().map(|path| path.join("ai-terminal").join("personas")))
+    }
+
+    /// Create a manager that reads and writes persona files in `dir`
+    pub fn with_dir(dir: Option<PathBuf>) -> Self {
+        Self {
+            personas: BTreeMap::new(),
+            dir,
+        }
+    }
+
+    /// Check whether `name` can be used as a persona name
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    /// Load every persona file, returning how many were loaded
+    ///
+    /// Files that cannot be parsed are skipped with a warning.
+    pub fn load(&mut self) -> Result<usize> {
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        self.personas.clear();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            match fs::read_to_string(&path).map_err(anyhow::Error::from)
+                .and_then(|content| toml::from_str::<Persona>(&content).map_err(anyhow::Error::from))
+            {
+                Ok(persona) if Self::is_valid_name(&persona.name) => {
+                    self.personas.insert(persona.name.clone(), persona);
+                }
+                Ok(persona) => tracing::warn!("Skipping persona with invalid name '{}' in {}", persona.name, path.display()),
+                Err(e) => tracing::warn!("Skipping persona file {}: {:?}", path.display(), e),
+            }
+        }
+        Ok(self.personas.len())
+    }
+
+    /// Add or replace a persona and write it to its file
+    pub fn save(&mut self, persona: Persona) -> Result<()> {
+        if !Self::is_valid_name(&persona.name) {
+            anyhow::bail!("persona names may only contain letters, digits, '-', '_' and '.'");
+        }
+        if let Some(dir) = &self.dir {
+            fs::create_dir_all(dir)?;
+            let path = dir.join(format!("{}.toml", persona.name));
+            fs::write(&path, toml::to_string(&persona)?)
+                .with_context(|| format!("could not write {}", path.display()))?;
+        }
+        self.personas.insert(persona.name.clone(), persona);
+        Ok(())
+    }
+
+    /// Add a persona that is not written to a file, unless one by that name exists
+    pub fn add_default(&mut self, persona: Persona) {
+        if Self::is_valid_name(&persona.name) {
+            self.personas.entry(persona.name.clone()).or_insert(persona);
+        }
+    }
+
+    /// Remove a persona and its file, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        if self.personas.remove(name).is_none() {
+            return Ok(false);
+        }
+        if let Some(dir) = &self.dir {
+            let path = dir.join(format!("{}.toml", name));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Get a persona by name
+    pub fn get(&self, name: &str) -> Option<&Persona> {
+        self.personas.get(name)
+    }
+
+    /// Get the names of all personas, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.personas.keys().cloned().collect()
+    }
+
+    /// Check whether there are no personas
+    pub fn is_empty(&self) -> bool {
+        self.personas.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_and_remove() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-persona-test-{}", std::process::id()));
+        let mut manager = PersonaManager::with_dir(Some(dir.clone()));
+        manager.save(Persona::new("shell-expert", "Answer with a single command.")).unwrap();
+        assert!(manager.save(Persona::new("../escape", "no")).is_err());
+        fs::write(dir.join("broken.toml"), "name = ").unwrap();
+
+        let mut reloaded = PersonaManager::with_dir(Some(dir.clone()));
+        assert_eq!(reloaded.load().unwrap(), 1);
+        assert_eq!(reloaded.get("shell-expert").unwrap().prompt, "Answer with a single command.");
+
+        reloaded.add_default(Persona::new("shell-expert", "From the configuration."));
+        assert_eq!(reloaded.get("shell-expert").unwrap().prompt, "Answer with a single command.");
+
+        assert!(reloaded.remove("shell-expert").unwrap());
+        assert!(!reloaded.remove("shell-expert").unwrap());
+        assert!(!dir.join("shell-expert.toml").exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,92 @@
+//! Pinned blocks
+//!
+//! `/pin` keeps a copy of the clicked block, or the pane's latest one, in
+//! the database next to the saved sessions, so a gnarly one-liner or a key
+//! error outlives the session it came from. Alt+P opens the pins panel over
+//! the right of the panes, listing the pinned blocks newest first with the
+//! selected one's output, to run a command again, copy it or its output,
+//! attach the output to AI requests, or unpin it.
+
+use terminal_emulator::PinnedBlock;
+
+/// The pinned blocks shown in the pins panel, with the selection
+#[derive(Debug, Clone, Default)]
+pub struct Pins {
+    pins: Vec<PinnedBlock>,
+    selected: usize,
+}
+
+impl Pins {
+    /// Show pinned blocks, most recently pinned first
+    pub fn new(pins: Vec<PinnedBlock>) -> Self {
+        Self { pins, selected: 0 }
+    }
+
+    /// Get the pinned blocks
+    pub fn pins(&self) -> &[PinnedBlock] {
+        &self.pins
+    }
+
+    /// Get the index of the selected pin
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Get the selected pin
+    pub fn selected(&self) -> Option<&PinnedBlock> {
+        self.pins.get(self.selected)
+    }
+
+    /// Select the pin above
+    pub fn move_selection_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Select the pin below
+    pub fn move_selection_down(&mut self) {
+        if self.selected + 1 < self.pins.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Take the selected pin out of the list, selecting the one after it
+    pub fn remove_selected(&mut self) -> Option<PinnedBlock> {
+        if self.selected >= self.pins.len() {
+            return None;
+        }
+        let removed = self.pins.remove(self.selected);
+        self.selected = self.selected.min(self.pins.len().saturating_sub(1));
+        Some(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use terminal_emulator::CommandBlock;
+
+    fn pin(id: i64, command: &str) -> PinnedBlock {
+        PinnedBlock { id, block: CommandBlock::new(command.to_string(), "/tmp".to_string()), pinned_at: Local::now() }
+    }
+
+    #[test]
+    fn test_selection_and_removal() {
+        let mut pins = Pins::new(vec![pin(3, "c"), pin(2, "b"), pin(1, "a")]);
+        pins.move_selection_up();
+        assert_eq!(pins.selected().unwrap().id, 3);
+        pins.move_selection_down();
+        pins.move_selection_down();
+        pins.move_selection_down();
+        assert_eq!(pins.selected_index(), 2);
+
+        assert_eq!(pins.remove_selected().unwrap().id, 1);
+        assert_eq!(pins.selected().unwrap().id, 2);
+        pins.move_selection_up();
+        assert_eq!(pins.remove_selected().unwrap().id, 3);
+        assert_eq!(pins.selected().unwrap().id, 2);
+        assert_eq!(pins.remove_selected().unwrap().id, 2);
+        assert!(pins.selected().is_none());
+        assert!(pins.remove_selected().is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Command placeholders for the AI Terminal
+//!
+//! Commands, and the snippets they come from, may hold placeholders such as
+//! `{{HOST}}` or `{{DB_PASSWORD:secret}}`, which makes them safe to share.
+//! When such a command is run, each placeholder is filled from the
+//! environment variable of the same name or, failing that, asked for in a
+//! prompt; secret ones are typed masked. Plain values are written into the
+//! command. Secret values never are: the placeholder becomes `${NAME}` and the
+//! value is set in the environment of that one command, so it stays out of
+//! the block, the history, saved sessions and anything sent to the model.
+
+use regex::Regex;
+
+/// A placeholder found in a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    /// Name, also of the environment variable that can fill it
+    pub name: String,
+    /// Whether the value is typed masked and kept out of the command
+    pub secret: bool,
+}
+
+/// Get the pattern matching `{{NAME}}` and `{{NAME:secret}}`
+fn pattern() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*(?::\s*(secret)\s*)?\}\}").expect("placeholder pattern is valid")
+}
+
+/// Find the placeholders of a command, each name once, in order
+pub fn find(command: &str) -> Vec<Placeholder> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    for captures in pattern().captures_iter(command) {
+        let name = captures[1].to_string();
+        let secret = captures.get(2).is_some();
+        match placeholders.iter_mut().find(|placeholder| placeholder.name == name) {
+            // A name marked secret anywhere is secret everywhere
+            Some(existing) => existing.secret |= secret,
+            None => placeholders.push(Placeholder { name, secret }),
+        }
+    }
+    placeholders
+}
+
+/// Values being collected for the placeholders of a command
+#[derive(Debug)]
+pub struct PlaceholderPrompt {
+    command: String,
+    placeholders: Vec<Placeholder>,
+    /// Values of the placeholders before `current`
+    values: Vec<String>,
+    /// Value being typed for the current placeholder
+    entry: String,
+}
+
+impl PlaceholderPrompt {
+    /// Start filling the placeholders of a command, or get `None` if it has none
+    pub fn new(command: String) -> Option<Self> {
+        Self::with_arguments(command, "")
+    }
+
+    /// Start filling the placeholders of a command from arguments, such as typed in the palette
+    ///
+    /// The arguments are split like shell words and fill the placeholders in
+    /// order, the last taking any words left over; placeholders without an
+    /// argument are filled from the environment or asked for.
+    pub fn with_arguments(command: String, arguments: &str) -> Option<Self> {
+        let placeholders = find(&command);
+        if placeholders.is_empty() {
+            return None;
+        }
+        let mut values = shlex::split(arguments).unwrap_or_else(|| arguments.split_whitespace().map(str::to_string).collect());
+        if values.len() > placeholders.len() {
+            let rest = values.split_off(placeholders.len() - 1).join(" ");
+            values.push(rest);
+        }
+        let mut prompt = Self { command, placeholders, values, entry: String::new() };
+        prompt.fill_from_env();
+        Some(prompt)
+    }
+
+    /// Get the command being filled in
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Get the placeholder being asked for, if any are left
+    pub fn current(&self) -> Option<&Placeholder> {
+        self.placeholders.get(self.values.len())
+    }
+
+    /// Get the position of the current placeholder and the number of placeholders
+    pub fn progress(&self) -> (usize, usize) {
+        (self.values.len() + 1, self.placeholders.len())
+    }
+
+    /// Get the value being typed, masked if it is secret
+    pub fn entry(&self) -> String {
+        match self.current() {
+            Some(placeholder) if placeholder.secret => "•".repeat(crate::text::grapheme_count(&self.entry)),
+            _ => self.entry.clone(),
+        }
+    }
+
+    /// Whether every placeholder has a value
+    pub fn is_complete(&self) -> bool {
+        self.current().is_none()
+    }
+
+    /// Add a character to the value being typed
+    pub fn push(&mut self, c: char) {
+        self.entry.push(c);
+    }
+
+    /// Remove the last character of the value being typed
+    pub fn pop(&mut self) {
+        crate::text::pop_grapheme(&mut self.entry);
+    }
+
+    /// Accept the typed value and move on to the next placeholder
+    pub fn submit(&mut self) {
+        if !self.is_complete() {
+            self.values.push(std::mem::take(&mut self.entry));
+            self.fill_from_env();
+        }
+    }
+
+    /// Take values from the environment until a placeholder needs asking for
+    fn fill_from_env(&mut self) {
+        while let Some(value) = self.current().and_then(|placeholder| std::env::var(&placeholder.name).ok()) {
+            self.values.push(value);
+        }
+    }
+
+    /// Get the command to run and the environment variables holding its secrets
+    pub fn finish(self) -> (String, Vec<(String, String)>) {
+        let mut env = Vec::new();
+        let command = pattern()
+            .replace_all(&self.command, |captures: &regex::Captures| {
+                let index = self.placeholders.iter().position(|placeholder| placeholder.name == captures[1]);
+                match index.map(|index| (&self.placeholders[index], self.values.get(index))) {
+                    Some((placeholder, Some(_))) if placeholder.secret => format!("${{{}}}", placeholder.name),
+                    Some((_, Some(value))) => value.clone(),
+                    _ => captures[0].to_string(),
+                }
+            })
+            .into_owned();
+        for (placeholder, value) in self.placeholders.iter().zip(self.values) {
+            if placeholder.secret {
+                env.push((placeholder.name.clone(), value));
+            }
+        }
+        (command, env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_placeholders() {
+        let command = "PGHOST={{AI_TERMINAL_TEST_HOST}} psql -U {{USER_NAME}} \"password={{DB_PASSWORD:secret}}\" # {{USER_NAME}}";
+        assert_eq!(find(command).len(), 3);
+        assert!(PlaceholderPrompt::new("ls {{ not a placeholder }}".to_string()).is_none());
+
+        let mut prompt = PlaceholderPrompt::new(command.to_string()).unwrap();
+        assert_eq!(prompt.current().unwrap().name, "AI_TERMINAL_TEST_HOST");
+        "db.local".chars().for_each(|c| prompt.push(c));
+        prompt.submit();
+        "admin".chars().for_each(|c| prompt.push(c));
+        prompt.submit();
+        assert!(prompt.current().unwrap().secret);
+        "hunter22".chars().for_each(|c| prompt.push(c));
+        prompt.pop();
+        assert_eq!(prompt.entry(), "•••••••");
+        prompt.submit();
+        assert!(prompt.is_complete());
+
+        let (command, env) = prompt.finish();
+        assert_eq!(command, "PGHOST=db.local psql -U admin \"password=${DB_PASSWORD}\" # admin");
+        assert_eq!(env, vec![("DB_PASSWORD".to_string(), "hunter2".to_string())]);
+
+        // Arguments fill placeholders in order, the last taking the rest
+        let prompt = PlaceholderPrompt::with_arguments("./deploy.sh {{TARGET}} --note '{{NOTE}}'".to_string(), "\"eu west\" fix the build").unwrap();
+        assert!(prompt.is_complete());
+        assert_eq!(prompt.finish().0, "./deploy.sh eu west --note 'fix the build'");
+    }
+}
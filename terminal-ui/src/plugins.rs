@@ -0,0 +1,556 @@
+//! Plugins for the AI Terminal
+//!
+//! Plugins are WebAssembly modules, loaded from the `plugins` directory of
+//! the config directory: one directory per plugin, named after it, holding a
+//! `plugin.toml` manifest and the module it names (`plugin.wasm` by
+//! default; the text format, `.wat`, works too). A plugin is told about
+//! events as JSON, such as a command being submitted or finishing, and
+//! answers with JSON asking the terminal to show a message, set its status
+//! bar segment, insert text into the input line or run a command.
+//!
+//! Plugins can do nothing on their own. A module may not import anything,
+//! so it has no files, network or clock, each event it handles is limited in
+//! the instructions it may run, and its memory is capped. What it may see
+//! and do is given by capabilities: the manifest lists those the plugin asks
+//! for and the `[plugins.grants]` section of the configuration those the
+//! user allows, and a plugin gets the ones in both.
+//!
+//! The module exports `memory`, `alloc(len: i32) -> i32`, returning where
+//! an event of `len` bytes may be written, and `handle(ptr: i32, len: i32)
+//! -> i64`, returning the pointer of its response in the high 32 bits and
+//! its length in the low ones, or 0 for no response.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::command_palette::Command;
+
+/// Prefix of the command palette ids of plugin commands
+pub const PALETTE_PREFIX: &str = "plugin:";
+
+/// Something a plugin may see or do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Be told of commands as they are submitted and when they finish
+    Commands,
+    /// See the output of finished commands, with secrets masked
+    Output,
+    /// Add commands to the command palette
+    Palette,
+    /// Show a segment in the status bar
+    Status,
+    /// Insert text into the input line
+    Input,
+    /// Run shell commands from its palette commands, approved like typed ones
+    Run,
+}
+
+impl Capability {
+    /// Get the name used in manifests and the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Commands => "commands",
+            Capability::Output => "output",
+            Capability::Palette => "palette",
+            Capability::Status => "status",
+            Capability::Input => "input",
+            Capability::Run => "run",
+        }
+    }
+}
+
+/// Plugin settings from the `[plugins]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Whether plugins are loaded at all
+    pub enabled: bool,
+    /// Capabilities allowed to each plugin, by name
+    pub grants: BTreeMap<String, Vec<Capability>>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grants: BTreeMap::new(),
+        }
+    }
+}
+
+/// A command a plugin adds to the palette
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PluginCommand {
+    /// Id sent back to the plugin when the command is picked
+    pub id: String,
+    /// Name shown and searched in the palette
+    pub name: String,
+    /// What the command does
+    #[serde(default)]
+    pub description: String,
+    /// What the command takes after its name, if anything
+    #[serde(default)]
+    pub argument: Option<String>,
+}
+
+/// The `plugin.toml` of a plugin
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Manifest {
+    /// Name of the plugin, from its directory
+    #[serde(skip)]
+    pub name: String,
+    /// What the plugin does
+    #[serde(default)]
+    pub description: String,
+    /// File of the module, relative to the plugin's directory
+    #[serde(default = "default_module")]
+    pub module: String,
+    /// Capabilities the plugin asks for
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    /// Commands the plugin adds to the palette
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+}
+
+fn default_module() -> String {
+    "plugin.wasm".to_string()
+}
+
+/// Something that happened, sent to plugins as JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The plugin was loaded
+    Load,
+    /// A shell command is about to run
+    CommandSubmit {
+        command: String,
+        cwd: String,
+    },
+    /// A shell command finished
+    BlockComplete {
+        command: String,
+        exit_code: i32,
+        duration_ms: u64,
+        /// Output, for plugins allowed to see it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    /// One of the plugin's palette commands was picked
+    PaletteCommand {
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        argument: Option<String>,
+    },
+}
+
+impl Event {
+    /// Get the capability a plugin needs to be told of the event
+    fn capability(&self) -> Option<Capability> {
+        match self {
+            Event::Load => None,
+            Event::CommandSubmit { .. } | Event::BlockComplete { .. } => Some(Capability::Commands),
+            Event::PaletteCommand { .. } => Some(Capability::Palette),
+        }
+    }
+}
+
+/// What a plugin answers an event with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+struct Response {
+    message: Option<String>,
+    status: Option<String>,
+    insert: Option<String>,
+    run: Option<String>,
+}
+
+/// What the terminal is asked to do by a plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Show a message from the named plugin
+    Message(String, String),
+    /// Insert text into the input line
+    Insert(String),
+    /// Run a shell command
+    Run(String),
+}
+
+/// A loaded plugin
+#[derive(Debug)]
+pub struct Plugin {
+    pub manifest: Manifest,
+    /// Capabilities asked for and granted
+    pub granted: Vec<Capability>,
+    runtime: runtime::Runtime,
+    /// Status bar segment it last set
+    status: Option<String>,
+    /// Why it stopped being called, after it failed
+    failure: Option<String>,
+}
+
+impl Plugin {
+    /// Load a plugin from its manifest and module
+    pub fn new(manifest: Manifest, module: &[u8], config: &PluginConfig) -> Result<Self> {
+        let allowed = config.grants.get(&manifest.name).cloned().unwrap_or_default();
+        let mut granted: Vec<Capability> = manifest.capabilities.iter().copied().filter(|capability| allowed.contains(capability)).collect();
+        granted.sort();
+        granted.dedup();
+        let runtime = runtime::Runtime::new(module)?;
+        Ok(Self { manifest, granted, runtime, status: None, failure: None })
+    }
+
+    /// Whether the plugin was granted a capability
+    pub fn has(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+
+    /// Tell the plugin of an event, returning what it asks for and is allowed
+    fn handle(&mut self, event: &Event) -> Vec<Effect> {
+        if self.failure.is_some() || event.capability().is_some_and(|capability| !self.has(capability)) {
+            return Vec::new();
+        }
+        let mut event = event.clone();
+        if let Event::BlockComplete { output, .. } = &mut event
+            && !self.has(Capability::Output)
+        {
+            *output = None;
+        }
+        let response = serde_json::to_vec(&event)
+            .map_err(anyhow::Error::from)
+            .and_then(|input| self.runtime.call(&input))
+            .and_then(|output| match output {
+                Some(output) => serde_json::from_slice(&output).context("the response is not valid JSON"),
+                None => Ok(Response::default()),
+            });
+        let response: Response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                let failure = format!("{:#}", e);
+                self.failure = Some(failure.clone());
+                self.status = None;
+                return vec![Effect::Message(self.manifest.name.clone(), format!("Stopped after failing: {}", failure))];
+            }
+        };
+
+        let mut effects = Vec::new();
+        let name = self.manifest.name.clone();
+        let refuse = |what: &str, capability: Capability| {
+            tracing::warn!("Plugin {} tried to {} without the {} capability", name, what, capability.name());
+        };
+        if let Some(message) = response.message {
+            effects.push(Effect::Message(name.clone(), message));
+        }
+        match response.status {
+            Some(_) if !self.granted.contains(&Capability::Status) => refuse("set a status", Capability::Status),
+            Some(status) => self.status = Some(status).filter(|status| !status.trim().is_empty()),
+            None => {}
+        }
+        match response.insert {
+            Some(_) if !self.granted.contains(&Capability::Input) => refuse("insert input", Capability::Input),
+            Some(text) => effects.push(Effect::Insert(text)),
+            None => {}
+        }
+        // Commands only run when the user picked the plugin's command, so hooks cannot start one another
+        match response.run {
+            Some(_) if !self.granted.contains(&Capability::Run) => refuse("run a command", Capability::Run),
+            Some(_) if !matches!(event, Event::PaletteCommand { .. }) => tracing::warn!("Plugin {} tried to run a command from a hook", name),
+            Some(command) => effects.push(Effect::Run(command)),
+            None => {}
+        }
+        effects
+    }
+}
+
+/// The loaded plugins
+#[derive(Debug, Default)]
+pub struct PluginHost {
+    plugins: Vec<Plugin>,
+}
+
+impl PluginHost {
+    /// Load every plugin in `dir`, returning the host and why some plugins could not be loaded
+    pub fn load(dir: &Path, config: &PluginConfig) -> (Self, Vec<String>) {
+        let mut host = Self::default();
+        let mut problems = Vec::new();
+        if !config.enabled || !dir.exists() {
+            return (host, problems);
+        }
+        let mut dirs: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).filter(|path| path.is_dir()).collect(),
+            Err(e) => return (host, vec![format!("{}: {}", dir.display(), e)]),
+        };
+        dirs.sort();
+        for dir in dirs {
+            match load_plugin(&dir, config) {
+                Ok(plugin) => host.plugins.push(plugin),
+                Err(e) => problems.push(format!("{}: {:#}", dir.display(), e)),
+            }
+        }
+        (host, problems)
+    }
+
+    /// Add a plugin
+    pub fn add(&mut self, plugin: Plugin) {
+        self.plugins.push(plugin);
+    }
+
+    /// Get the loaded plugins
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    /// Whether any plugin was granted a capability
+    pub fn any_has(&self, capability: Capability) -> bool {
+        self.plugins.iter().any(|plugin| plugin.has(capability))
+    }
+
+    /// Tell every plugin allowed to know of an event, returning what they ask for
+    pub fn dispatch(&mut self, event: &Event) -> Vec<Effect> {
+        self.plugins.iter_mut().flat_map(|plugin| plugin.handle(event)).collect()
+    }
+
+    /// Tell a plugin one of its palette commands was picked, by the command's palette id
+    pub fn run_command(&mut self, palette_id: &str, argument: Option<&str>) -> Vec<Effect> {
+        let Some((name, id)) = palette_id.strip_prefix(PALETTE_PREFIX).and_then(|rest| rest.split_once(':')) else {
+            return Vec::new();
+        };
+        let event = Event::PaletteCommand { id: id.to_string(), argument: argument.map(str::to_string) };
+        match self.plugins.iter_mut().find(|plugin| plugin.manifest.name == name) {
+            Some(plugin) => plugin.handle(&event),
+            None => Vec::new(),
+        }
+    }
+
+    /// Build the palette commands of the plugins allowed to add them
+    pub fn palette_commands(&self) -> Vec<Command> {
+        self.plugins
+            .iter()
+            .filter(|plugin| plugin.has(Capability::Palette))
+            .flat_map(|plugin| {
+                plugin.manifest.commands.iter().map(|command| {
+                    let id = format!("{}{}:{}", PALETTE_PREFIX, plugin.manifest.name, command.id);
+                    let palette_command = Command::new(&id, &command.name, &command.description, &plugin.manifest.name, "🧩");
+                    match &command.argument {
+                        Some(argument) => palette_command.with_argument(argument),
+                        None => palette_command,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Format the status bar segments of the plugins
+    pub fn status_segment(&self) -> String {
+        self.plugins
+            .iter()
+            .filter_map(|plugin| plugin.status.as_deref())
+            .map(|status| format!(" {} ", status.trim()))
+            .collect()
+    }
+
+    /// Describe each plugin and the capabilities it asked for and was granted
+    pub fn report(&self) -> String {
+        self.plugins
+            .iter()
+            .map(|plugin| {
+                let capabilities: Vec<String> = plugin.manifest.capabilities
+                    .iter()
+                    .map(|capability| if plugin.has(*capability) { capability.name().to_string() } else { format!("{} (not granted)", capability.name()) })
+                    .collect();
+                let state = match &plugin.failure {
+                    Some(failure) => format!("  stopped: {}", failure),
+                    None => String::new(),
+                };
+                format!("{}  {}\n  capabilities: {}{}", plugin.manifest.name, plugin.manifest.description, capabilities.join(", "), state)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Get the directory holding plugins, if there is a config directory
+pub fn plugins_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|path| path.join("ai-terminal").join("plugins"))
+}
+
+/// Load the plugin in a directory
+fn load_plugin(dir: &Path, config: &PluginConfig) -> Result<Plugin> {
+    let Some(name) = dir.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+        bail!("the plugin directory has no name");
+    };
+    let mut manifest: Manifest = toml::from_str(&fs::read_to_string(dir.join("plugin.toml")).context("reading plugin.toml")?)
+        .context("parsing plugin.toml")?;
+    manifest.name = name;
+    let module = fs::read(dir.join(&manifest.module)).with_context(|| format!("reading {}", manifest.module))?;
+    Plugin::new(manifest, &module, config)
+}
+
+#[cfg(feature = "plugins")]
+mod runtime {
+    use std::fmt;
+    use std::sync::OnceLock;
+
+    use anyhow::{bail, Context, Result};
+    use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+    /// Instructions, roughly, a plugin may run per event
+    const FUEL_PER_EVENT: u64 = 50_000_000;
+
+    /// Most memory a plugin may use
+    const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+    /// Longest response read from a plugin
+    const MAX_RESPONSE: usize = 1024 * 1024;
+
+    /// Get the engine compiling plugins, shared by all of them
+    fn engine() -> &'static Engine {
+        static ENGINE: OnceLock<Engine> = OnceLock::new();
+        ENGINE.get_or_init(|| {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            Engine::new(&config).expect("engine configuration is valid")
+        })
+    }
+
+    /// An instance of a plugin's module
+    pub struct Runtime {
+        store: Store<StoreLimits>,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        handle: TypedFunc<(i32, i32), i64>,
+    }
+
+    impl fmt::Debug for Runtime {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Runtime").finish_non_exhaustive()
+        }
+    }
+
+    impl Runtime {
+        /// Compile and instantiate a module, without giving it any imports
+        pub fn new(module: &[u8]) -> Result<Self> {
+            let module = Module::new(engine(), module).context("compiling the module")?;
+            if let Some(import) = module.imports().next() {
+                bail!("the module imports {}::{}, but plugins may not import anything", import.module(), import.name());
+            }
+            let mut store = Store::new(engine(), StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build());
+            store.limiter(|limits| limits);
+            store.set_fuel(FUEL_PER_EVENT)?;
+            let instance = Instance::new(&mut store, &module, &[]).context("starting the module")?;
+            let memory = instance.get_memory(&mut store, "memory").context("the module exports no memory")?;
+            let alloc = instance.get_typed_func(&mut store, "alloc").context("the module exports no alloc(i32) -> i32")?;
+            let handle = instance.get_typed_func(&mut store, "handle").context("the module exports no handle(i32, i32) -> i64")?;
+            Ok(Self { store, memory, alloc, handle })
+        }
+
+        /// Pass an event to the module, returning its response, if any
+        pub fn call(&mut self, input: &[u8]) -> Result<Option<Vec<u8>>> {
+            self.store.set_fuel(FUEL_PER_EVENT)?;
+            let len = i32::try_from(input.len()).context("the event is too large")?;
+            let ptr = self.alloc.call(&mut self.store, len)?;
+            self.memory.write(&mut self.store, ptr as u32 as usize, input).context("writing the event")?;
+            let packed = self.handle.call(&mut self.store, (ptr, len))?;
+            if packed == 0 {
+                return Ok(None);
+            }
+            let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+            if len > MAX_RESPONSE {
+                bail!("the response is {} bytes, more than the {} allowed", len, MAX_RESPONSE);
+            }
+            let mut output = vec![0; len];
+            self.memory.read(&self.store, ptr, &mut output).context("reading the response")?;
+            Ok(Some(output))
+        }
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+mod runtime {
+    use anyhow::{bail, Result};
+
+    /// Stand-in for builds without the `plugins` feature, which load no plugins
+    #[derive(Debug)]
+    pub enum Runtime {}
+
+    impl Runtime {
+        pub fn new(_module: &[u8]) -> Result<Self> {
+            bail!("this build has no plugin support; it needs the `plugins` feature")
+        }
+
+        pub fn call(&mut self, _input: &[u8]) -> Result<Option<Vec<u8>>> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "plugins"))]
+mod tests {
+    use super::*;
+
+    const SAMPLE_MANIFEST: &str = include_str!("../../examples/plugins/command-stats/plugin.toml");
+    const SAMPLE_MODULE: &str = include_str!("../../examples/plugins/command-stats/plugin.wat");
+
+    fn sample(grants: &[Capability]) -> PluginHost {
+        let mut manifest: Manifest = toml::from_str(SAMPLE_MANIFEST).unwrap();
+        manifest.name = "command-stats".to_string();
+        let config = PluginConfig { enabled: true, grants: BTreeMap::from([("command-stats".to_string(), grants.to_vec())]) };
+        let mut host = PluginHost::default();
+        host.add(Plugin::new(manifest, SAMPLE_MODULE.as_bytes(), &config).unwrap());
+        host
+    }
+
+    fn finished(exit_code: i32) -> Event {
+        Event::BlockComplete { command: "make".to_string(), exit_code, duration_ms: 10, output: Some("secret".to_string()) }
+    }
+
+    #[test]
+    fn test_sample_plugin() {
+        let mut host = sample(&[Capability::Commands, Capability::Status, Capability::Palette, Capability::Run]);
+        assert!(host.dispatch(&Event::Load).is_empty());
+        assert_eq!(host.status_segment(), " ▣ 0 run, 0 failed ");
+        host.dispatch(&finished(0));
+        host.dispatch(&finished(2));
+        assert_eq!(host.status_segment(), " ▣ 2 run, 1 failed ");
+
+        let commands = host.palette_commands();
+        assert_eq!(commands[0].id, "plugin:command-stats:summary");
+        assert_eq!(host.run_command(&commands[0].id, None), vec![Effect::Message(
+            "command-stats".to_string(),
+            "2 command(s) ran in this session and 1 failed.".to_string(),
+        )]);
+        assert!(host.report().starts_with("command-stats  Count the commands"));
+    }
+
+    #[test]
+    fn test_capabilities_are_enforced() {
+        // Without a grant the plugin hears of no commands and sets no status
+        let mut host = sample(&[]);
+        host.dispatch(&Event::Load);
+        host.dispatch(&finished(1));
+        assert_eq!(host.status_segment(), "");
+        assert!(host.palette_commands().is_empty());
+        assert!(host.report().contains("commands (not granted)"));
+
+        // Modules may not import anything, such as WASI
+        let config = PluginConfig::default();
+        let importing = r#"(module (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32))))"#;
+        let manifest = Manifest { name: "io".to_string(), description: String::new(), module: default_module(), capabilities: Vec::new(), commands: Vec::new() };
+        assert!(Plugin::new(manifest.clone(), importing.as_bytes(), &config).unwrap_err().to_string().contains("may not import"));
+
+        // A module that never returns runs out of fuel and is stopped
+        let spinning = r#"(module (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "handle") (param i32 i32) (result i64) (loop $spin (br $spin)) (i64.const 0)))"#;
+        let mut host = PluginHost::default();
+        host.add(Plugin::new(manifest, spinning.as_bytes(), &config).unwrap());
+        assert!(matches!(host.dispatch(&Event::Load).as_slice(), [Effect::Message(_, message)] if message.starts_with("Stopped")));
+        assert!(host.dispatch(&Event::Load).is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Process manager for the AI Terminal
+//!
+//! Lists, inspects and signals the processes of the machine for the process
+//! manager view (`/ps`, or "ps: Process Manager" in the command palette),
+//! the usual way to find and stop a runaway build or a model server that
+//! holds on to GPU memory. The view only holds display state; this module
+//! reads the process table and sends the signals.
+//!
+//! Signals go through [`ProcessTable::describe`], a dry run shown in the
+//! confirmation, and [`ProcessTable::kill`], which checks again before
+//! sending. Both refuse PID 1, the terminal itself and the shell that started
+//! it, and `kill` refuses a process whose id now belongs to another program.
+//! On Unix the signal is sent with `kill(2)`, so a process of another user
+//! fails with a permission error instead of a silent no-op.
+
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// Time between refreshes while the view is open
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A process, as listed in the view
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    /// Process id
+    pub pid: u32,
+    /// Id of the parent process
+    pub parent: Option<u32>,
+    /// Executable name
+    pub name: String,
+    /// Full command line, if it could be read
+    pub command: String,
+    /// CPU usage since the previous refresh, in percent of one core
+    pub cpu: f32,
+    /// Resident memory, in bytes
+    pub memory: u64,
+    /// Seconds since the process started
+    pub run_time: u64,
+    /// Scheduler state, such as "Runnable" or "Sleeping"
+    pub status: String,
+}
+
+/// Column the process table is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Highest CPU usage first
+    #[default]
+    Cpu,
+    /// Most memory first
+    Memory,
+    /// Lowest process id first
+    Pid,
+    /// Name, alphabetically
+    Name,
+}
+
+impl SortKey {
+    /// Get the next column, wrapping around
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Cpu => SortKey::Memory,
+            SortKey::Memory => SortKey::Pid,
+            SortKey::Pid => SortKey::Name,
+            SortKey::Name => SortKey::Cpu,
+        }
+    }
+
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Cpu => "CPU",
+            SortKey::Memory => "memory",
+            SortKey::Pid => "PID",
+            SortKey::Name => "name",
+        }
+    }
+
+    /// Compare two processes by this column
+    pub fn compare(self, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+        match self {
+            SortKey::Cpu => b.cpu.total_cmp(&a.cpu),
+            SortKey::Memory => b.memory.cmp(&a.memory),
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        }
+        .then(a.pid.cmp(&b.pid))
+    }
+}
+
+/// Signal the view can send to a process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KillSignal {
+    /// Ask the process to terminate
+    #[default]
+    Term,
+    /// Interrupt it, as Ctrl+C does
+    Int,
+    /// Hang up, which makes many daemons reload
+    Hup,
+    /// Kill it outright; it cannot clean up
+    Kill,
+}
+
+impl KillSignal {
+    /// Get the next signal, wrapping around
+    pub fn next(self) -> Self {
+        match self {
+            KillSignal::Term => KillSignal::Int,
+            KillSignal::Int => KillSignal::Hup,
+            KillSignal::Hup => KillSignal::Kill,
+            KillSignal::Kill => KillSignal::Term,
+        }
+    }
+
+    /// Name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            KillSignal::Term => "SIGTERM",
+            KillSignal::Int => "SIGINT",
+            KillSignal::Hup => "SIGHUP",
+            KillSignal::Kill => "SIGKILL",
+        }
+    }
+
+    /// Say what the signal usually does to a process
+    pub fn effect(self) -> &'static str {
+        match self {
+            KillSignal::Term => "asks it to exit, letting it clean up",
+            KillSignal::Int => "interrupts it, as Ctrl+C does",
+            KillSignal::Hup => "hangs it up; many daemons reload their configuration, others exit",
+            KillSignal::Kill => "kills it at once; it cannot clean up or save anything",
+        }
+    }
+
+    /// Get the signal as nix knows it
+    #[cfg(unix)]
+    fn signal(self) -> nix::sys::signal::Signal {
+        use nix::sys::signal::Signal;
+        match self {
+            KillSignal::Term => Signal::SIGTERM,
+            KillSignal::Int => Signal::SIGINT,
+            KillSignal::Hup => Signal::SIGHUP,
+            KillSignal::Kill => Signal::SIGKILL,
+        }
+    }
+
+    /// Get the signal as sysinfo knows it
+    #[cfg(not(unix))]
+    fn signal(self) -> sysinfo::Signal {
+        match self {
+            KillSignal::Term => sysinfo::Signal::Term,
+            KillSignal::Int => sysinfo::Signal::Interrupt,
+            KillSignal::Hup => sysinfo::Signal::Hangup,
+            KillSignal::Kill => sysinfo::Signal::Kill,
+        }
+    }
+}
+
+/// Get why a process may not be signalled from the terminal, if it may not
+pub fn protected(pid: u32) -> Option<&'static str> {
+    if pid <= 1 {
+        Some("it is the init process, and stopping it brings the whole system down")
+    } else if pid == std::process::id() {
+        Some("it is the AI Terminal itself; use /quit")
+    } else if parent_id() == Some(pid) {
+        Some("it is the shell the AI Terminal was started from")
+    } else {
+        None
+    }
+}
+
+/// Get the id of the process that started the terminal
+#[cfg(unix)]
+fn parent_id() -> Option<u32> {
+    Some(std::os::unix::process::parent_id())
+}
+
+/// Get the id of the process that started the terminal; only known on Unix
+#[cfg(not(unix))]
+fn parent_id() -> Option<u32> {
+    None
+}
+
+/// Reads the process table and signals processes
+pub struct ProcessTable {
+    system: System,
+    refreshed: Option<Instant>,
+}
+
+impl Default for ProcessTable {
+    fn default() -> Self {
+        Self { system: System::new(), refreshed: None }
+    }
+}
+
+impl ProcessTable {
+    /// Read the processes anew
+    ///
+    /// CPU usage is measured between refreshes, so it reads 0 on the first one.
+    pub fn refresh(&mut self) -> Vec<ProcessInfo> {
+        self.refreshed = Some(Instant::now());
+        let refresh = ProcessRefreshKind::nothing().with_cpu().with_memory().with_cmd(UpdateKind::OnlyIfNotSet);
+        self.system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+        self.system
+            .processes()
+            .values()
+            // Threads are listed as processes on Linux; the view only wants processes
+            .filter(|process| process.thread_kind().is_none())
+            .map(info)
+            .collect()
+    }
+
+    /// Whether the last refresh is older than [`REFRESH_INTERVAL`]
+    pub fn is_stale(&self) -> bool {
+        self.refreshed.is_none_or(|refreshed| refreshed.elapsed() >= REFRESH_INTERVAL)
+    }
+
+    /// Describe what sending a signal would do, without sending it
+    ///
+    /// Fails for protected processes and ones that are gone, so the caller
+    /// never asks to confirm a signal that would be refused.
+    pub fn describe(&self, pid: u32, signal: KillSignal) -> Result<String> {
+        if let Some(reason) = protected(pid) {
+            bail!("will not signal process {}: {}", pid, reason);
+        }
+        let Some(process) = self.system.process(Pid::from_u32(pid)) else {
+            bail!("process {} is gone", pid);
+        };
+        let process = info(process);
+        let children = self.system.processes().values()
+            .filter(|child| child.thread_kind().is_none() && child.parent() == Some(Pid::from_u32(pid)))
+            .count();
+        let mut lines = vec![
+            format!("Send {} to {} (pid {})? This {}.", signal.name(), process.name, pid, signal.effect()),
+            format!("Command: {}", if process.command.is_empty() { &process.name } else { &process.command }),
+        ];
+        if children > 0 {
+            lines.push(format!("It has {} child process{}, which may be left running or stopped with it.", children, if children == 1 { "" } else { "es" }));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Send a signal to a process, if it is still the program named `name`
+    ///
+    /// Process ids are reused, so the process is read again first: one that
+    /// exited since the table was shown may have made room for another.
+    pub fn kill(&mut self, pid: u32, name: &str, signal: KillSignal) -> Result<()> {
+        if let Some(reason) = protected(pid) {
+            bail!("will not signal process {}: {}", pid, reason);
+        }
+        let target = Pid::from_u32(pid);
+        self.system.refresh_processes_specifics(ProcessesToUpdate::Some(&[target]), true, ProcessRefreshKind::nothing());
+        match self.system.process(target) {
+            Some(process) if process.name().to_string_lossy() == name => send(pid, signal, process),
+            Some(process) => bail!("process {} is now {}, not {}; refresh and try again", pid, process.name().to_string_lossy(), name),
+            None => bail!("process {} ({}) is gone", pid, name),
+        }
+    }
+}
+
+/// Read the listed fields of a process
+fn info(process: &Process) -> ProcessInfo {
+    ProcessInfo {
+        pid: process.pid().as_u32(),
+        parent: process.parent().map(Pid::as_u32),
+        name: process.name().to_string_lossy().into_owned(),
+        command: process.cmd().iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" "),
+        cpu: process.cpu_usage(),
+        memory: process.memory(),
+        run_time: process.run_time(),
+        status: process.status().to_string(),
+    }
+}
+
+/// Send a signal with `kill(2)`, surfacing why it failed
+#[cfg(unix)]
+fn send(pid: u32, signal: KillSignal, _process: &Process) -> Result<()> {
+    use nix::errno::Errno;
+    use nix::unistd::Pid;
+    match nix::sys::signal::kill(Pid::from_raw(pid as i32), signal.signal()) {
+        Ok(()) => Ok(()),
+        Err(Errno::EPERM) => bail!("permission denied sending {} to process {}: it belongs to another user", signal.name(), pid),
+        Err(Errno::ESRCH) => bail!("process {} exited before {} was sent", pid, signal.name()),
+        Err(e) => bail!("could not send {} to process {}: {}", signal.name(), pid, e),
+    }
+}
+
+/// Send a signal through sysinfo, which cannot tell why it failed
+#[cfg(not(unix))]
+fn send(pid: u32, signal: KillSignal, process: &Process) -> Result<()> {
+    match process.kill_with(signal.signal()) {
+        Some(true) => Ok(()),
+        Some(false) => bail!("could not send {} to process {}; it may belong to another user", signal.name(), pid),
+        None => bail!("{} is not supported on this platform", signal.name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, cpu: f32, memory: u64) -> ProcessInfo {
+        ProcessInfo { pid, parent: None, name: name.to_string(), command: String::new(), cpu, memory, run_time: 0, status: String::new() }
+    }
+
+    #[test]
+    fn test_sorting_and_protection() {
+        let mut processes = [process(3, "b", 1.0, 10), process(1, "C", 5.0, 5), process(2, "a", 5.0, 20)];
+        processes.sort_by(|a, b| SortKey::Cpu.compare(a, b));
+        assert_eq!(processes.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![1, 2, 3]);
+        processes.sort_by(|a, b| SortKey::Name.compare(a, b));
+        assert_eq!(processes.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![2, 3, 1]);
+        assert_eq!(SortKey::Name.next(), SortKey::Cpu);
+        assert_eq!(KillSignal::Kill.next().name(), "SIGTERM");
+
+        let own = std::process::id();
+        let mut table = ProcessTable::default();
+        assert!(table.is_stale());
+        let listed = table.refresh();
+        assert!(!table.is_stale());
+        assert!(listed.iter().any(|process| process.pid == own));
+
+        // The terminal, its parent and init are never signalled
+        assert!(protected(1).is_some());
+        assert!(table.describe(own, KillSignal::Term).is_err());
+        assert!(table.kill(own, "anything", KillSignal::Kill).is_err());
+        if let Some(parent) = parent_id() {
+            assert!(table.kill(parent, "anything", KillSignal::Kill).unwrap_err().to_string().contains("shell"));
+        }
+    }
+}
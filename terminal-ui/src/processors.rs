@@ -0,0 +1,335 @@
+//! Output processors for the AI Terminal
+//!
+//! An output processor turns the raw output of a command into an enhanced
+//! view, such as a table of the objects in `kubectl get pods -o json` or of
+//! the commits in `git log`. Processors are registered against regular
+//! expressions matched on the command, by the `[processors]` section of the
+//! configuration or by code embedding the terminal through
+//! [`ProcessorRegistry::register`]. They run in the background once a block
+//! completes, and the view replaces the raw output until 'Toggle Raw Output'
+//! switches back.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::tables::Table;
+
+/// Most rows shown in a table view
+const MAX_ROWS: usize = 50;
+
+/// Most columns shown in a table view of generic JSON objects
+const MAX_COLUMNS: usize = 6;
+
+/// Transforms command output into an enhanced view
+pub trait OutputProcessor: Send + Sync {
+    /// Name shown above the view
+    fn name(&self) -> &str;
+
+    /// Build the view as Markdown, or `None` when the output does not suit the processor
+    fn process(&self, output: &str) -> Option<String>;
+}
+
+/// Processors keyed by command pattern
+#[derive(Clone, Default)]
+pub struct ProcessorRegistry {
+    /// Patterns and their processors, tried in order
+    entries: Vec<(Regex, Arc<dyn OutputProcessor>)>,
+}
+
+impl std::fmt::Debug for ProcessorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|(pattern, processor)| (pattern.as_str(), processor.name())))
+            .finish()
+    }
+}
+
+impl ProcessorRegistry {
+    /// Create a registry with the built-in processors on their usual commands
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::default();
+        registry.register(r"^kubectl\s+get\s.*-o\s*json", Arc::new(JsonProcessor)).expect("valid pattern");
+        registry.register(r"^git\s+log\b", Arc::new(GitLogProcessor)).expect("valid pattern");
+        registry
+    }
+
+    /// Create the registry from the `[processors]` section, which maps patterns to built-in processor names
+    ///
+    /// Configured patterns are tried before the defaults.
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self> {
+        let mut registry = Self::default();
+        for (pattern, name) in config {
+            let processor = builtin(name).with_context(|| format!("unknown output processor '{}'", name))?;
+            registry.register(pattern, processor)?;
+        }
+        registry.entries.extend(Self::with_defaults().entries);
+        Ok(registry)
+    }
+
+    /// Run `processor` on the output of commands matching `pattern`
+    pub fn register(&mut self, pattern: &str, processor: Arc<dyn OutputProcessor>) -> Result<()> {
+        let pattern = Regex::new(pattern).with_context(|| format!("invalid command pattern '{}'", pattern))?;
+        self.entries.push((pattern, processor));
+        Ok(())
+    }
+
+    /// Get the processor for a command, if one matches
+    pub fn find(&self, command: &str) -> Option<Arc<dyn OutputProcessor>> {
+        let command = command.trim();
+        self.entries
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(command))
+            .map(|(_, processor)| processor.clone())
+    }
+}
+
+/// Get a built-in processor by name
+fn builtin(name: &str) -> Option<Arc<dyn OutputProcessor>> {
+    match name {
+        "json" => Some(Arc::new(JsonProcessor)),
+        "git_log" => Some(Arc::new(GitLogProcessor)),
+        "table" => Some(Arc::new(TableProcessor)),
+        _ => None,
+    }
+}
+
+/// Shows lists of JSON objects as a table and anything else as formatted JSON
+pub struct JsonProcessor;
+
+impl OutputProcessor for JsonProcessor {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn process(&self, output: &str) -> Option<String> {
+        let value: Value = serde_json::from_str(output.trim()).ok()?;
+        let rows = match &value {
+            Value::Array(rows) => Some(rows),
+            Value::Object(object) => object.get("items").and_then(Value::as_array),
+            _ => None,
+        };
+        match rows {
+            Some(rows) if rows.first().is_some_and(Value::is_object) => Some(json_table(rows)),
+            _ => Some(format!("```json\n{}\n```", serde_json::to_string_pretty(&value).ok()?)),
+        }
+    }
+}
+
+/// Build a table of JSON objects, with columns from the first object
+fn json_table(rows: &[Value]) -> String {
+    // Kubernetes objects are best known by kind, namespace, name and phase
+    let columns: Vec<String> = if rows[0].pointer("/metadata/name").is_some() {
+        ["kind", "metadata.namespace", "metadata.name", "status.phase"]
+            .into_iter()
+            .filter(|path| rows.iter().any(|row| lookup(row, path).is_some()))
+            .map(str::to_string)
+            .collect()
+    } else {
+        scalar_paths(&rows[0]).into_iter().take(MAX_COLUMNS).collect()
+    };
+
+    let mut table = format!("{} item(s)\n\n| {} |\n|{}\n", rows.len(), columns.join(" | "), "---|".repeat(columns.len()));
+    for row in rows.iter().take(MAX_ROWS) {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|path| lookup(row, path).map_or(String::new(), |value| match value {
+                Value::String(text) => escape_cell(text),
+                other => escape_cell(&other.to_string()),
+            }))
+            .collect();
+        let _ = writeln!(table, "| {} |", cells.join(" | "));
+    }
+    if rows.len() > MAX_ROWS {
+        let _ = writeln!(table, "\n… {} more", rows.len() - MAX_ROWS);
+    }
+    table
+}
+
+/// Get the value at a dotted path such as `metadata.name`
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+/// Get the dotted paths of the scalar values of an object and of the objects directly inside it
+fn scalar_paths(value: &Value) -> Vec<String> {
+    let Value::Object(object) = value else {
+        return Vec::new();
+    };
+    let mut paths = Vec::new();
+    for (key, value) in object {
+        match value {
+            Value::Object(inner) => paths.extend(inner
+                .iter()
+                .filter(|(_, value)| !value.is_object() && !value.is_array())
+                .map(|(inner_key, _)| format!("{}.{}", key, inner_key))),
+            Value::Array(_) => {}
+            _ => paths.push(key.clone()),
+        }
+    }
+    paths
+}
+
+/// Shows columnar output, such as that of `ps` or `docker ps`, and CSV as an aligned table
+pub struct TableProcessor;
+
+impl OutputProcessor for TableProcessor {
+    fn name(&self) -> &str {
+        "table"
+    }
+
+    fn process(&self, output: &str) -> Option<String> {
+        let table = Table::detect(output)?;
+        let mut view = format!(
+            "{} row(s)\n\n| {} |\n|{}\n",
+            table.rows.len(),
+            table.headers.iter().map(|header| escape_cell(header)).collect::<Vec<_>>().join(" | "),
+            "---|".repeat(table.headers.len()),
+        );
+        for row in table.rows.iter().take(MAX_ROWS) {
+            let cells: Vec<String> = row.iter().map(|cell| escape_cell(cell)).collect();
+            let _ = writeln!(view, "| {} |", cells.join(" | "));
+        }
+        if table.rows.len() > MAX_ROWS {
+            let _ = writeln!(view, "\n… {} more; /table shows them all", table.rows.len() - MAX_ROWS);
+        }
+        Some(view)
+    }
+}
+
+/// Escape text for a Markdown table cell
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Shows `git log` output as a table of commits
+pub struct GitLogProcessor;
+
+/// One commit read from `git log` output
+#[derive(Debug, Default)]
+struct Commit {
+    hash: String,
+    author: String,
+    date: String,
+    subject: String,
+}
+
+impl OutputProcessor for GitLogProcessor {
+    fn name(&self) -> &str {
+        "git log"
+    }
+
+    fn process(&self, output: &str) -> Option<String> {
+        let lines: Vec<&str> = output.lines().map(|line| line.trim_end_matches('\r')).collect();
+        let commits = if lines.iter().any(|line| line.starts_with("commit ")) {
+            parse_full_log(&lines)
+        } else {
+            parse_oneline_log(&lines)?
+        };
+        if commits.is_empty() {
+            return None;
+        }
+
+        let mut table = format!("{} commit(s)\n\n| Commit | Author | Date | Subject |\n|---|---|---|---|\n", commits.len());
+        for commit in commits.iter().take(MAX_ROWS) {
+            let _ = writeln!(
+                table,
+                "| `{}` | {} | {} | {} |",
+                commit.hash,
+                escape_cell(&commit.author),
+                escape_cell(&commit.date),
+                escape_cell(&commit.subject),
+            );
+        }
+        if commits.len() > MAX_ROWS {
+            let _ = writeln!(table, "\n… {} more", commits.len() - MAX_ROWS);
+        }
+        Some(table)
+    }
+}
+
+/// Read the default `git log` format, with `commit`, `Author:` and `Date:` lines
+fn parse_full_log(lines: &[&str]) -> Vec<Commit> {
+    let mut commits: Vec<Commit> = Vec::new();
+    for line in lines {
+        if let Some(hash) = line.strip_prefix("commit ") {
+            let hash = hash.split_whitespace().next().unwrap_or_default();
+            commits.push(Commit { hash: hash.chars().take(10).collect(), ..Default::default() });
+        } else if let Some(commit) = commits.last_mut() {
+            if let Some(author) = line.strip_prefix("Author:") {
+                // Drop the e-mail address
+                commit.author = author.split('<').next().unwrap_or_default().trim().to_string();
+            } else if let Some(date) = line.strip_prefix("Date:") {
+                commit.date = date.trim().to_string();
+            } else if commit.subject.is_empty() && line.starts_with("    ") {
+                commit.subject = line.trim().to_string();
+            }
+        }
+    }
+    commits
+}
+
+/// Read `git log --oneline` output, or give up if any line is not a commit
+fn parse_oneline_log(lines: &[&str]) -> Option<Vec<Commit>> {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (hash, subject) = line.split_once(' ')?;
+            (hash.len() >= 7 && hash.chars().all(|c| c.is_ascii_hexdigit())).then(|| Commit {
+                hash: hash.to_string(),
+                subject: subject.to_string(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_matches_commands() {
+        let registry = ProcessorRegistry::with_defaults();
+        assert_eq!(registry.find("kubectl get pods -o json").unwrap().name(), "json");
+        assert_eq!(registry.find("git log -5").unwrap().name(), "git log");
+        assert!(registry.find("git status").is_none());
+
+        let config = BTreeMap::from([("^cat .*\\.json$".to_string(), "json".to_string())]);
+        let registry = ProcessorRegistry::from_config(&config).unwrap();
+        assert_eq!(registry.find("cat package.json").unwrap().name(), "json");
+        assert!(registry.find("git log").is_some());
+        let config = BTreeMap::from([("^ls".to_string(), "hexdump".to_string())]);
+        assert!(ProcessorRegistry::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_builtin_processors() {
+        let pods = r#"{"items": [
+            {"kind": "Pod", "metadata": {"name": "web-1", "namespace": "prod"}, "status": {"phase": "Running"}},
+            {"kind": "Pod", "metadata": {"name": "web-2", "namespace": "prod"}, "status": {"phase": "Pending"}}
+        ]}"#;
+        let view = JsonProcessor.process(pods).unwrap();
+        assert!(view.starts_with("2 item(s)"));
+        assert!(view.contains("| kind | metadata.namespace | metadata.name | status.phase |"));
+        assert!(view.contains("| Pod | prod | web-2 | Pending |"));
+        assert!(JsonProcessor.process("{\"a\": 1}").unwrap().starts_with("```json"));
+        assert_eq!(JsonProcessor.process("not json"), None);
+
+        let log = "commit 0123456789abcdef\r\nAuthor: Ada <ada@example.com>\r\nDate:   Mon Jan 1\r\n\r\n    Fix the | parser\r\n\r\n    Details\r\n";
+        let view = GitLogProcessor.process(log).unwrap();
+        assert!(view.contains("| `0123456789` | Ada | Mon Jan 1 | Fix the \\| parser |"));
+        let oneline = GitLogProcessor.process("abc1234 First\ndef5678 Second\n").unwrap();
+        assert!(oneline.starts_with("2 commit(s)"));
+        assert_eq!(GitLogProcessor.process("fatal: not a git repository"), None);
+
+        let view = TableProcessor.process("NAME    READY   STATUS\nweb-1   1/1     Running\nweb-2   0/1     Pending\n").unwrap();
+        assert!(view.starts_with("2 row(s)"));
+        assert!(view.contains("| web-2 | 0/1 | Pending |"));
+    }
+}
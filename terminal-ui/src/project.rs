@@ -0,0 +1,290 @@
+//! Project configuration for the AI Terminal
+//!
+//! A `.ai-terminal.toml` file in a project directory tunes the terminal for
+//! that project while the working directory is in it or below it: its
+//! `system_prompt` is sent along with AI prompts, its `[commands]` are listed
+//! in the command palette under "Project", and its `auto_approve` commands
+//! may run without asking when an agent works there. As anyone can ship such
+//! a file in a repository, `auto_approve` only applies in directories marked
+//! trusted with `/trust allow`.
+//!
+//! `/init` writes the file: it looks at the project's languages, build files
+//! and scripts, asks a few questions, and has the model draft the file from
+//! both, which is previewed before it is written.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::{Action, Command};
+
+/// Name of the project configuration file
+pub const FILE_NAME: &str = ".ai-terminal.toml";
+
+/// Prefix of the palette command ids of project commands, followed by the command's name
+pub const PALETTE_PREFIX: &str = "project:";
+
+/// Contents of `.ai-terminal.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Sent along with AI prompts made in the project
+    pub system_prompt: Option<String>,
+    /// Common commands by name, listed in the command palette
+    pub commands: BTreeMap<String, String>,
+    /// Commands an agent may run without asking, added to `[agent] auto_approve`
+    pub auto_approve: Vec<String>,
+}
+
+/// A project configuration and the directory it applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    /// Directory holding the configuration file
+    pub root: PathBuf,
+    /// The configuration
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    /// Find the configuration of the project `dir` is in, looking in `dir` and its ancestors
+    pub fn find(dir: &Path) -> Result<Option<Self>> {
+        let Some(root) = dir.ancestors().find(|ancestor| ancestor.join(FILE_NAME).is_file()) else {
+            return Ok(None);
+        };
+        let path = root.join(FILE_NAME);
+        let content = fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))?;
+        let config = toml::from_str(&content).with_context(|| format!("could not parse {}", path.display()))?;
+        Ok(Some(Self { root: root.to_path_buf(), config }))
+    }
+
+    /// Build the palette entries of the project's commands
+    pub fn palette_commands(&self) -> Vec<Command> {
+        self.config
+            .commands
+            .iter()
+            .map(|(name, command)| {
+                let description = format!("Insert `{}`", command);
+                Command::new(&format!("{}{}", PALETTE_PREFIX, name), name, &description, "Project", "📁")
+                    .with_action(Action::Insert(command.clone()))
+            })
+            .collect()
+    }
+}
+
+/// What `/init` found out about a project from its files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectScan {
+    /// Directory scanned
+    pub root: PathBuf,
+    /// Languages the build files point to
+    pub languages: Vec<String>,
+    /// Build and dependency files found
+    pub build_files: Vec<String>,
+    /// Commands suggested by the build files, by name
+    pub commands: BTreeMap<String, String>,
+    /// Read-only commands suggested for `auto_approve`
+    pub auto_approve: Vec<String>,
+}
+
+impl ProjectScan {
+    /// Look at the build files in a directory
+    pub fn scan(root: &Path) -> Self {
+        let mut scan = Self {
+            root: root.to_path_buf(),
+            auto_approve: ["git status", "git diff", "git log"].map(str::to_string).to_vec(),
+            ..Default::default()
+        };
+        if scan.found("Cargo.toml") {
+            scan.add_language("Rust", &[("build", "cargo build"), ("test", "cargo test"), ("lint", "cargo clippy --all-targets")], &["cargo check", "cargo tree"]);
+        }
+        if scan.found("package.json") {
+            scan.add_language("JavaScript", &[], &["npm ls"]);
+            let scripts = fs::read_to_string(root.join("package.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .and_then(|package| package.get("scripts").and_then(|scripts| scripts.as_object()).cloned())
+                .unwrap_or_default();
+            for name in scripts.keys() {
+                scan.commands.entry(name.clone()).or_insert_with(|| format!("npm run {}", name));
+            }
+        }
+        if ["pyproject.toml", "requirements.txt", "setup.py"].iter().any(|file| scan.found(file)) {
+            scan.add_language("Python", &[("test", "pytest")], &["pip show"]);
+        }
+        if scan.found("go.mod") {
+            scan.add_language("Go", &[("build", "go build ./..."), ("test", "go test ./...")], &["go vet ./...", "go list"]);
+        }
+        if scan.found("Makefile") {
+            let target = Regex::new(r"(?m)^([A-Za-z][\w-]*):").expect("target pattern is valid");
+            let makefile = fs::read_to_string(root.join("Makefile")).unwrap_or_default();
+            for captures in target.captures_iter(&makefile).take(10) {
+                scan.commands.entry(captures[1].to_string()).or_insert_with(|| format!("make {}", &captures[1]));
+            }
+        }
+        scan
+    }
+
+    /// Record a build file if it exists, returning whether it does
+    fn found(&mut self, file: &str) -> bool {
+        let exists = self.root.join(file).is_file();
+        if exists {
+            self.build_files.push(file.to_string());
+        }
+        exists
+    }
+
+    /// Record a language with its usual commands
+    fn add_language(&mut self, language: &str, commands: &[(&str, &str)], auto_approve: &[&str]) {
+        self.languages.push(language.to_string());
+        for (name, command) in commands {
+            self.commands.entry(name.to_string()).or_insert_with(|| command.to_string());
+        }
+        self.auto_approve.extend(auto_approve.iter().map(|command| command.to_string()));
+    }
+}
+
+/// Questions `/init` asks
+const QUESTIONS: &[&str] = &[
+    "What is this project, in a sentence?",
+    "Conventions the assistant should follow (optional)",
+    "Commands an agent may run without asking (comma separated)",
+];
+
+/// The `/init` interview: the scan and the answers so far
+#[derive(Debug, Clone)]
+pub struct ProjectInterview {
+    scan: ProjectScan,
+    answers: Vec<String>,
+}
+
+impl ProjectInterview {
+    /// Start an interview about a scanned project
+    pub fn new(scan: ProjectScan) -> Self {
+        Self { scan, answers: Vec::new() }
+    }
+
+    /// Get the path the configuration will be written to
+    pub fn path(&self) -> PathBuf {
+        self.scan.root.join(FILE_NAME)
+    }
+
+    /// Get the next question and its suggested answer, if any are left
+    pub fn question(&self) -> Option<(&'static str, String)> {
+        let question = QUESTIONS.get(self.answers.len())?;
+        let suggestion = match self.answers.len() {
+            2 => self.scan.auto_approve.join(", "),
+            _ => String::new(),
+        };
+        Some((question, suggestion))
+    }
+
+    /// Answer the current question
+    pub fn answer(&mut self, answer: &str) {
+        self.answers.push(answer.trim().to_string());
+    }
+
+    /// Get the answer to a question, empty if it was skipped
+    fn answer_to(&self, index: usize) -> &str {
+        self.answers.get(index).map_or("", String::as_str)
+    }
+
+    /// Get the commands the user allowed to run without asking
+    fn allowed(&self) -> Vec<String> {
+        self.answer_to(2).split(',').map(str::trim).filter(|command| !command.is_empty()).map(str::to_string).collect()
+    }
+
+    /// Build the prompt asking the model to draft the configuration
+    pub fn prompt(&self) -> String {
+        let commands: Vec<String> = self.scan.commands.iter().map(|(name, command)| format!("{} = {}", name, command)).collect();
+        format!(
+            "Write a project configuration for the AI Terminal as TOML in a fenced `toml` block, with only these keys:\n\
+             - system_prompt: a short system prompt telling the assistant what the project is and how to help with it\n\
+             - commands: a table of the project's common shell commands, name = \"command\"\n\
+             - auto_approve: a list of read-only commands an agent may run without asking\n\n\
+             Project directory: {}\nLanguages: {}\nBuild files: {}\nCommands found:\n{}\n\n\
+             The user says:\n- What the project is: {}\n- Conventions: {}\n- Commands an agent may run without asking: {}",
+            self.scan.root.display(),
+            self.scan.languages.join(", "),
+            self.scan.build_files.join(", "),
+            commands.join("\n"),
+            self.answer_to(0),
+            self.answer_to(1),
+            self.answer_to(2),
+        )
+    }
+
+    /// Get the configuration from the model's draft, or from the scan and answers alone without one
+    ///
+    /// The user's answer decides `auto_approve` either way.
+    pub fn finish(&self, response: Option<&str>) -> ProjectConfig {
+        let mut config = response.and_then(parse_draft).unwrap_or_else(|| {
+            let languages = if self.scan.languages.is_empty() { "software".to_string() } else { self.scan.languages.join(" and ") };
+            let prompt = format!("This is a {} project. {} {}", languages, self.answer_to(0), self.answer_to(1));
+            ProjectConfig { system_prompt: Some(prompt.trim().to_string()), commands: self.scan.commands.clone(), auto_approve: Vec::new() }
+        });
+        config.auto_approve = self.allowed();
+        config
+    }
+}
+
+/// Read the configuration from a model response, with or without a `toml` fence
+fn parse_draft(response: &str) -> Option<ProjectConfig> {
+    let body = match response.find("```toml") {
+        Some(start) => {
+            let body = &response[start + "```toml".len()..];
+            &body[..body.find("```")?]
+        }
+        None => response,
+    };
+    toml::from_str(body.trim()).ok()
+}
+
+/// Write a configuration to `path`
+pub fn write(path: &Path, config: &ProjectConfig) -> Result<()> {
+    fs::write(path, to_toml(config)?).with_context(|| format!("could not write {}", path.display()))
+}
+
+/// Format a configuration as TOML
+pub fn to_toml(config: &ProjectConfig) -> Result<String> {
+    Ok(toml::to_string_pretty(config)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_interview_and_find() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-project-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(dir.join("Makefile"), ".PHONY: docs\ndocs:\n\tcargo doc\n").unwrap();
+
+        let scan = ProjectScan::scan(&dir);
+        assert_eq!(scan.languages, vec!["Rust"]);
+        assert_eq!(scan.commands["docs"], "make docs");
+
+        let mut interview = ProjectInterview::new(scan);
+        interview.answer("A demo CLI");
+        interview.answer("");
+        assert!(interview.question().unwrap().1.starts_with("git status"));
+        interview.answer("git status, cargo check");
+        assert!(interview.question().is_none());
+
+        let draft = "Here it is:\n```toml\nsystem_prompt = \"Demo CLI in Rust\"\nauto_approve = [\"rm -rf /\"]\n[commands]\nbuild = \"cargo build\"\n```";
+        let config = interview.finish(Some(draft));
+        assert_eq!(config.system_prompt.as_deref(), Some("Demo CLI in Rust"));
+        assert_eq!(config.auto_approve, vec!["git status", "cargo check"]);
+        assert_eq!(interview.finish(Some("no toml here")).commands["test"], "cargo test");
+
+        write(&interview.path(), &config).unwrap();
+        let project = Project::find(&dir.join("src")).unwrap().unwrap();
+        assert_eq!(project.root, dir);
+        assert_eq!(project.config, config);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+//! Per-project AI memory
+//!
+//! A `.ai-terminal.md` file holds what the model should know about a project
+//! in plain Markdown, like an `AGENTS.md`. The file in the working directory
+//! is used, or else the one at the root of the git repository it is in, and
+//! its text is sent along with AI prompts and chat questions.
+//!
+//! `/memory` shows the file, and `/memory add <fact>` appends a fact the user
+//! typed. `/memory learn`, or 'AI: Remember Project Fact' in the command
+//! palette, has the model pick one lasting fact from the chat conversation or
+//! the latest blocks; the fact is appended only once the user approves it.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Name of the project memory file
+pub const FILE_NAME: &str = ".ai-terminal.md";
+
+/// Command having the model propose a fact to remember
+pub const LEARN_COMMAND: &str = "/memory learn";
+
+/// Most characters of the file sent with a prompt; the start is kept
+const MAX_PROMPT_CHARS: usize = 16 * 1024;
+
+/// Heading of a memory file created by adding the first fact
+const NEW_FILE_HEADING: &str = "# Project memory\n";
+
+/// Most characters of a block's output described to the model; the end is kept
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+/// Answer of the model when there is nothing worth remembering
+const NOTHING_TO_REMEMBER: &str = "NONE";
+
+/// The memory file of a project and its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectMemory {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Text of the file
+    pub content: String,
+}
+
+impl ProjectMemory {
+    /// Find the memory file for `dir`, in it or at the root of its git repository
+    pub fn find(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(FILE_NAME);
+        let path = if path.is_file() {
+            path
+        } else {
+            match repo_root(dir).map(|root| root.join(FILE_NAME)).filter(|path| path.is_file()) {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        };
+        let content = fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))?;
+        Ok(Some(Self { path, content }))
+    }
+
+    /// Get the text sent along with prompts, or `None` if the file is empty
+    pub fn prompt_context(&self) -> Option<String> {
+        let content = self.content.trim();
+        if content.is_empty() {
+            return None;
+        }
+        match content.char_indices().nth(MAX_PROMPT_CHARS) {
+            Some((end, _)) => Some(format!("{}\n[... the rest of {} was left out]", &content[..end], FILE_NAME)),
+            None => Some(content.to_string()),
+        }
+    }
+}
+
+/// Find the root of the git repository `dir` is in
+fn repo_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|ancestor| ancestor.join(".git").exists())
+}
+
+/// Get the path facts are added to: the file in use, or a new one at the repository root or in `dir`
+pub fn target_path(dir: &Path, memory: Option<&ProjectMemory>) -> PathBuf {
+    match memory {
+        Some(memory) => memory.path.clone(),
+        None => repo_root(dir).unwrap_or(dir).join(FILE_NAME),
+    }
+}
+
+/// Append a fact to the memory file as a list item, creating the file if needed
+pub fn append(path: &Path, fact: &str) -> Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("could not read {}", path.display())),
+    };
+    let prefix = match &existing {
+        None => format!("{}\n", NEW_FILE_HEADING),
+        Some(content) if !content.is_empty() && !content.ends_with('\n') => "\n".to_string(),
+        Some(_) => String::new(),
+    };
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("could not open {}", path.display()))?;
+    writeln!(file, "{}- {}", prefix, fact.trim()).with_context(|| format!("could not write {}", path.display()))
+}
+
+/// Build the prompt asking the model for one fact worth remembering from recent work
+pub fn learn_prompt(memory: Option<&ProjectMemory>, recent: &str) -> String {
+    let known = memory.and_then(ProjectMemory::prompt_context).unwrap_or_else(|| "(nothing yet)".to_string());
+    format!(
+        "You keep a memory file of facts about a software project that help an assistant work in it: \
+         build and test commands, conventions, layout, pitfalls. From the recent work below, state the one \
+         most useful lasting fact that the memory does not already hold, as a single short sentence with \
+         no list marker. Leave out secrets and one-off details. If there is nothing worth remembering, \
+         answer {}.\n\nThe memory holds:\n{}\n\nRecent work:\n{}",
+        NOTHING_TO_REMEMBER, known, recent,
+    )
+}
+
+/// Describe a block as recent work, keeping the end of long output
+pub fn describe_block(command: &str, output: &str) -> String {
+    let output = output.trim_end();
+    let skipped = output.chars().count().saturating_sub(MAX_OUTPUT_CHARS);
+    let tail = match output.char_indices().nth(skipped) {
+        Some((start, _)) if skipped > 0 => &output[start..],
+        _ => output,
+    };
+    format!("$ {}\n{}", command, tail)
+}
+
+/// Read the proposed fact from the model's answer, or `None` if it found nothing to remember
+pub fn parse_fact(response: &str) -> Option<String> {
+    let line = response.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let fact = line.trim_start_matches(['-', '*', '•']).trim().trim_matches(['"', '`']).trim();
+    if fact.is_empty() || fact.trim_end_matches('.').eq_ignore_ascii_case(NOTHING_TO_REMEMBER) {
+        return None;
+    }
+    Some(fact.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_append() {
+        let root = std::env::temp_dir().join(format!("ai-terminal-memory-{}", std::process::id()));
+        let dir = root.join("crates").join("core");
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        assert!(ProjectMemory::find(&dir).unwrap().is_none());
+
+        // Without a file, facts go to the repository root
+        let path = target_path(&dir, None);
+        assert_eq!(path, root.join(FILE_NAME));
+        append(&path, "Tests run with `cargo test --workspace`").unwrap();
+        let memory = ProjectMemory::find(&dir).unwrap().unwrap();
+        assert_eq!(memory.content, "# Project memory\n\n- Tests run with `cargo test --workspace`\n");
+
+        // A file in the working directory comes first
+        fs::write(dir.join(FILE_NAME), "Core crate, no unsafe code").unwrap();
+        let memory = ProjectMemory::find(&dir).unwrap().unwrap();
+        append(&target_path(&dir, Some(&memory)), " Errors use anyhow ").unwrap();
+        assert_eq!(fs::read_to_string(dir.join(FILE_NAME)).unwrap(), "Core crate, no unsafe code\n- Errors use anyhow\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_prompt_context_and_facts() {
+        let memory = |content: String| ProjectMemory { path: PathBuf::from(FILE_NAME), content };
+        assert_eq!(memory("\n \n".to_string()).prompt_context(), None);
+        let long = memory("x".repeat(MAX_PROMPT_CHARS + 10)).prompt_context().unwrap();
+        assert!(long.starts_with(&"x".repeat(MAX_PROMPT_CHARS)));
+        assert!(long.ends_with("left out]"));
+        assert!(learn_prompt(None, "$ make").contains("(nothing yet)"));
+        let block = describe_block("make", &format!("{}done\n", "a".repeat(MAX_OUTPUT_CHARS)));
+        assert_eq!(block, format!("$ make\n{}done", "a".repeat(MAX_OUTPUT_CHARS - 4)));
+
+        assert_eq!(parse_fact("\n- \"Run `make check` before pushing.\"\n\nIt lints too."), Some("Run `make check` before pushing.".to_string()));
+        assert_eq!(parse_fact("NONE."), None);
+        assert_eq!(parse_fact("  "), None);
+    }
+}
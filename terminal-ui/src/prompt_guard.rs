@@ -0,0 +1,177 @@
+//! Request and response size guards for the AI Terminal
+//!
+//! An AI request is made of pieces: the prompt itself, the system prompt or
+//! persona, the bookmarks and the tool descriptions. Before it is sent, the
+//! pieces are measured; above `preview_tokens`, a preview lists each piece
+//! with its size so oversized ones can be dropped, or the request cancelled,
+//! before anything leaves the machine. Responses longer than
+//! `max_response_bytes` are cut short before they are shown and logged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error_kb::ErrorSignature;
+
+/// Size guard settings from the `[prompt_guard]` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GuardConfig {
+    /// Preview requests above the threshold before sending them
+    pub enabled: bool,
+    /// Estimated tokens above which a request is previewed
+    pub preview_tokens: usize,
+    /// Longest response kept, in bytes
+    pub max_response_bytes: usize,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            preview_tokens: 4000,
+            max_response_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl GuardConfig {
+    /// Check whether a request must be previewed before it is sent
+    pub fn needs_preview(&self, draft: &PromptDraft) -> bool {
+        self.enabled && draft.tokens() > self.preview_tokens
+    }
+}
+
+/// Estimate the tokens in a text, at about four bytes per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Cut a response down to `max_bytes`, returning whether it was cut
+pub fn truncate_response(text: &mut String, max_bytes: usize) -> bool {
+    if text.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let dropped = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!("\n\n[… response cut short: {} more bytes were dropped]", dropped));
+    true
+}
+
+/// One piece of an AI request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextPiece {
+    /// What the piece is, such as "Bookmarks"
+    pub label: String,
+    /// Text of the piece
+    pub text: String,
+    /// Whether the piece is the prompt itself, which cannot be dropped
+    pub is_prompt: bool,
+    /// Whether the piece will be sent
+    pub included: bool,
+}
+
+impl ContextPiece {
+    /// Create the piece holding the prompt itself
+    pub fn prompt(text: String) -> Self {
+        Self { label: "Prompt".to_string(), text, is_prompt: true, included: true }
+    }
+
+    /// Create a piece of context sent as part of the system prompt
+    pub fn context(label: impl Into<String>, text: String) -> Self {
+        Self { label: label.into(), text, is_prompt: false, included: true }
+    }
+
+    /// Estimate the tokens in the piece
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.text)
+    }
+}
+
+/// An AI request waiting to be sent, as pieces that may be dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptDraft {
+    /// The command as typed, including the leading '/'
+    pub command: String,
+    /// Pieces of the request, the prompt first
+    pub pieces: Vec<ContextPiece>,
+    /// Text shown after the response, such as the sources it was given
+    pub footer: Option<String>,
+    /// Error the response explains, saved with it in the knowledge base
+    pub known_error: Option<ErrorSignature>,
+}
+
+impl PromptDraft {
+    /// Create a draft of a typed command and its pieces
+    pub fn new(command: String, pieces: Vec<ContextPiece>) -> Self {
+        Self { command, pieces, footer: None, known_error: None }
+    }
+
+    /// Include or drop a piece; the prompt itself is always sent
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(piece) = self.pieces.get_mut(index).filter(|piece| !piece.is_prompt) {
+            piece.included = !piece.included;
+        }
+    }
+
+    /// Get the included pieces
+    fn included(&self) -> impl Iterator<Item = &ContextPiece> {
+        self.pieces.iter().filter(|piece| piece.included)
+    }
+
+    /// Estimate the tokens of the included pieces
+    pub fn tokens(&self) -> usize {
+        self.included().map(ContextPiece::tokens).sum()
+    }
+
+    /// Get the bytes of the included pieces
+    pub fn bytes(&self) -> usize {
+        self.included().map(|piece| piece.text.len()).sum()
+    }
+
+    /// Get the prompt to send
+    pub fn prompt(&self) -> String {
+        self.included().filter(|piece| piece.is_prompt).map(|piece| piece.text.as_str()).collect::<Vec<_>>().join("\n\n")
+    }
+
+    /// Get the system prompt to send: the included context pieces, in order
+    pub fn system(&self) -> Option<String> {
+        let context: Vec<&str> = self.included().filter(|piece| !piece.is_prompt).map(|piece| piece.text.as_str()).collect();
+        (!context.is_empty()).then(|| context.join("\n\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draft_drops_pieces() {
+        let log = "x".repeat(40_000);
+        let mut draft = PromptDraft::new("/summarize".to_string(), vec![
+            ContextPiece::prompt("summarize".to_string()),
+            ContextPiece::context("System prompt", "Be brief.".to_string()),
+            ContextPiece::context("Attached log", log),
+        ]);
+        let config = GuardConfig::default();
+        assert!(config.needs_preview(&draft));
+        assert!(!GuardConfig { enabled: false, ..config }.needs_preview(&draft));
+
+        draft.toggle(2);
+        draft.toggle(0);
+        assert!(!config.needs_preview(&draft));
+        assert_eq!(draft.prompt(), "summarize");
+        assert_eq!(draft.system().as_deref(), Some("Be brief."));
+        assert_eq!(draft.bytes(), "summarize".len() + "Be brief.".len());
+    }
+
+    #[test]
+    fn test_truncate_response() {
+        let mut text = "héllo".to_string();
+        assert!(!truncate_response(&mut text, 10));
+        assert!(truncate_response(&mut text, 2));
+        assert!(text.starts_with("h\n\n[… response cut short: 5 more bytes were dropped]"));
+    }
+}
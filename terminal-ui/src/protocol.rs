@@ -0,0 +1,196 @@
+//! Machine-readable output of the headless commands
+//!
+//! With `--json`, `ai-terminal ask`, `run` and `exec` print one JSON
+//! document to stdout instead of streaming text, so other programs can use
+//! the AI Terminal as a backend. The types here are that document. Fields
+//! are only ever added to them, and a change that breaks readers bumps
+//! [`VERSION`]:
+//!
+//! ```json
+//! {"version":1,"exit_code":0,"kind":"run","request":{…},"response":{…},"command":"du -sh *","result":{…}}
+//! ```
+//!
+//! `kind` tells which command printed the document, and is `error` when it
+//! failed before producing a result.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use ollama_client::{ChatResponse, FunctionCall, TokenUsage};
+
+/// Version of the output format
+pub const VERSION: u32 = 1;
+
+/// A document printed by a headless command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    /// Version of the format, [`VERSION`] when written by this build
+    pub version: u32,
+    /// Code the process exits with
+    pub exit_code: i32,
+    #[serde(flatten)]
+    pub output: Output,
+}
+
+impl Document {
+    /// Wrap the output of a command exiting with `exit_code`
+    pub fn new(exit_code: i32, output: Output) -> Self {
+        Self { version: VERSION, exit_code, output }
+    }
+}
+
+/// What a command produced, by command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Output {
+    Ask(AskOutput),
+    Run(RunOutput),
+    Exec(ExecOutput),
+    Error(ErrorOutput),
+}
+
+/// A request sent to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelRequest {
+    pub model: String,
+    pub system: Option<String>,
+    pub prompt: String,
+}
+
+/// The model's answer to a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelResponse {
+    /// Model that answered, which differs from the requested one after a fallback
+    pub model: String,
+    pub text: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: Option<Usage>,
+    /// Model asked first, when another one answered
+    pub fallback_from: Option<String>,
+    /// Time from sending the request to the full answer, in milliseconds
+    pub duration_ms: u64,
+}
+
+impl ModelResponse {
+    /// Describe a response that took `duration_ms` to arrive
+    pub fn new(response: &ChatResponse, duration_ms: u64) -> Self {
+        Self {
+            model: response.model.clone(),
+            text: response.text.clone(),
+            tool_calls: response.tool_calls.iter().map(ToolCall::from).collect(),
+            usage: response.usage.map(Usage::from),
+            fallback_from: response.fallback.as_ref().map(|fallback| fallback.primary.clone()),
+            duration_ms,
+        }
+    }
+}
+
+/// A function the model called
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+impl From<&FunctionCall> for ToolCall {
+    fn from(call: &FunctionCall) -> Self {
+        Self { name: call.name.clone(), arguments: call.arguments.clone() }
+    }
+}
+
+/// Tokens a request used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl From<TokenUsage> for Usage {
+    fn from(usage: TokenUsage) -> Self {
+        Self { prompt_tokens: usage.prompt_tokens, completion_tokens: usage.completion_tokens }
+    }
+}
+
+/// A shell command that ran
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandResult {
+    pub command: String,
+    pub cwd: String,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    /// Output of the command, stdout and stderr together, without escape sequences
+    pub output: String,
+}
+
+/// Output of `ask`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AskOutput {
+    pub request: ModelRequest,
+    pub response: ModelResponse,
+}
+
+/// Output of `run`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunOutput {
+    pub request: ModelRequest,
+    pub response: ModelResponse,
+    /// Command taken out of the response
+    pub command: String,
+    /// How the command went, or `None` when it was not confirmed
+    pub result: Option<CommandResult>,
+}
+
+/// Output of `exec`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecOutput {
+    pub workflow: String,
+    /// Steps that ran, in order
+    pub steps: Vec<StepOutput>,
+    /// Whether every step ran and none failed the workflow
+    pub succeeded: bool,
+    /// Why the workflow stopped before its last step
+    pub stopped: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// A workflow step that ran
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepOutput {
+    pub label: String,
+    #[serde(flatten)]
+    pub result: CommandResult,
+}
+
+/// Why a command failed without a result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorOutput {
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_round_trips() {
+        let mut response = ChatResponse::new("llama3", "```sh\nls\n```", Some(TokenUsage { prompt_tokens: 12, completion_tokens: 5, ..Default::default() }));
+        response.tool_calls.push(FunctionCall { name: "read_file".to_string(), arguments: serde_json::json!({"path": "a.txt"}) });
+        let document = Document::new(0, Output::Run(RunOutput {
+            request: ModelRequest { model: "llama3".to_string(), system: None, prompt: "list files".to_string() },
+            response: ModelResponse::new(&response, 250),
+            command: "ls".to_string(),
+            result: Some(CommandResult { command: "ls".to_string(), cwd: "/tmp".to_string(), exit_code: 0, duration_ms: 3, output: "a.txt\n".to_string() }),
+        }));
+        let json: Value = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["version"], VERSION);
+        assert_eq!(json["kind"], "run");
+        assert_eq!(json["response"]["usage"]["completion_tokens"], 5);
+        assert_eq!(json["response"]["tool_calls"][0]["arguments"]["path"], "a.txt");
+        assert_eq!(json["result"]["exit_code"], 0);
+        assert_eq!(serde_json::from_value::<Document>(json).unwrap(), document);
+
+        let error = Document::new(1, Output::Error(ErrorOutput { message: "no workflow named 'x'".to_string() }));
+        assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"version":1,"exit_code":1,"kind":"error","message":"no workflow named 'x'"}"#);
+    }
+}
@@ -0,0 +1,275 @@
+//! Render cache for the AI Terminal UI
+//!
+//! Rendering Markdown and wrapping every block on every frame makes frame
+//! time grow with the scrollback. The cache keeps the wrapped, styled lines
+//! of each command block, keyed by block id and invalidated when the block's
+//! revision, the wrap width or the theme revision changes, so a frame only
+//! has to re-render blocks that actually changed.
+
+use std::collections::HashMap;
+
+use ratatui::text::{Line, Span};
+use terminal_emulator::CommandBlock;
+use uuid::Uuid;
+
+use crate::hyperlink::Hyperlink;
+use crate::text;
+
+/// The inputs a cached rendering depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheKey {
+    /// Revision of the block when it was rendered
+    block_revision: u64,
+    /// Width the lines were wrapped to
+    width: u16,
+    /// Revision of the theme the lines were styled with
+    theme_revision: u64,
+}
+
+/// Where an image sits in the rendering of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSlot {
+    /// Line the image starts at as rendered, and row once wrapped in the cache
+    pub row: usize,
+    /// Column the image starts at
+    pub column: u16,
+    /// Cells the image is wide
+    pub columns: u16,
+    /// Rows the image is high
+    pub rows: u16,
+    /// Index of the image among the block's images
+    pub image: usize,
+}
+
+/// The rendering of one block
+#[derive(Debug, Clone)]
+pub struct CachedBlock {
+    /// What the rendering was made from
+    key: CacheKey,
+    /// Lines already wrapped to the cached width
+    pub lines: Vec<Line<'static>>,
+    /// Whether each line continues the one before it, broken there by wrapping
+    pub continued: Vec<bool>,
+    /// Links found in the block
+    pub links: Vec<Hyperlink>,
+    /// Images drawn in the block, by wrapped row
+    pub images: Vec<ImageSlot>,
+}
+
+/// Per-block cache of wrapped, styled lines
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    /// Cached renderings by block id
+    entries: HashMap<Uuid, CachedBlock>,
+}
+
+impl RenderCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the rendering of a block, rendering it with `render` if the cached one is stale
+    pub fn get_or_render(
+        &mut self,
+        block: &CommandBlock,
+        width: u16,
+        theme_revision: u64,
+        render: impl FnOnce() -> (Vec<Line<'static>>, Vec<Hyperlink>, Vec<ImageSlot>),
+    ) -> &CachedBlock {
+        let key = CacheKey { block_revision: block.revision(), width, theme_revision };
+        let entry = self.entries.entry(block.id).or_insert_with(|| CachedBlock {
+            // Never matches a real key, so the block is rendered below
+            key: CacheKey { block_revision: u64::MAX, width: 0, theme_revision: u64::MAX },
+            lines: Vec::new(),
+            continued: Vec::new(),
+            links: Vec::new(),
+            images: Vec::new(),
+        });
+
+        if entry.key != key {
+            let (lines, links, mut images) = render();
+            entry.lines.clear();
+            entry.continued.clear();
+            let mut line_rows = Vec::with_capacity(lines.len());
+            for line in &lines {
+                line_rows.push(entry.lines.len());
+                let rows = wrap_line(line, width);
+                entry.continued.extend((0..rows.len()).map(|row| row > 0));
+                entry.lines.extend(rows);
+            }
+            for slot in &mut images {
+                slot.row = line_rows.get(slot.row).copied().unwrap_or(entry.lines.len());
+            }
+            entry.links = links;
+            entry.images = images;
+            entry.key = key;
+        }
+        entry
+    }
+
+    /// Drop the renderings of blocks that are no longer shown
+    pub fn retain(&mut self, blocks: &[CommandBlock]) {
+        if self.entries.len() > blocks.len() {
+            self.entries.retain(|id, _| blocks.iter().any(|block| block.id == *id));
+        }
+    }
+
+    /// Get the number of cached blocks
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Wrap a line to `width` columns, breaking after whitespace where possible
+///
+/// Lines are cut between grapheme clusters, so a wide character or an emoji
+/// is never split across rows.
+pub fn wrap_line(line: &Line<'static>, width: u16) -> Vec<Line<'static>> {
+    let width = width as usize;
+    let cells: Vec<(&str, usize, usize)> = line
+        .spans
+        .iter()
+        .enumerate()
+        .flat_map(|(index, span)| {
+            text::graphemes(&span.content).map(move |grapheme| (grapheme, text::width(grapheme), index))
+        })
+        .collect();
+
+    let total: usize = cells.iter().map(|(_, w, _)| w).sum();
+    if width == 0 || total <= width {
+        return vec![line.clone()];
+    }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < cells.len() {
+        let mut end = start;
+        let mut used = 0;
+        let mut last_space = None;
+        while end < cells.len() && used + cells[end].1 <= width {
+            if is_space(cells[end].0) {
+                last_space = Some(end);
+            }
+            used += cells[end].1;
+            end += 1;
+        }
+
+        if end == start {
+            // A character wider than the line still has to go somewhere
+            end += 1;
+        } else if end < cells.len()
+            && !is_space(cells[end].0)
+            && let Some(space) = last_space
+        {
+            end = space + 1;
+        }
+
+        rows.push(row_line(line, &cells[start..end]));
+        start = end;
+    }
+    rows
+}
+
+/// Whether a grapheme cluster is whitespace a line may break after
+fn is_space(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+/// Build a line from a run of cells, merging cells of the same span
+fn row_line(line: &Line<'static>, cells: &[(&str, usize, usize)]) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for &(grapheme, _, index) in cells {
+        match &mut current {
+            Some((span_index, text)) if *span_index == index => text.push_str(grapheme),
+            _ => {
+                if let Some((span_index, text)) = current.take() {
+                    spans.push(Span::styled(text, line.spans[span_index].style));
+                }
+                current = Some((index, grapheme.to_string()));
+            }
+        }
+    }
+    if let Some((span_index, text)) = current {
+        spans.push(Span::styled(text, line.spans[span_index].style));
+    }
+
+    let mut row = Line::from(spans).style(line.style);
+    row.alignment = line.alignment;
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Style};
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_wrap_prefers_word_boundaries() {
+        let line = Line::from("the quick brown fox");
+        let rows: Vec<String> = wrap_line(&line, 10).iter().map(text).collect();
+        assert_eq!(rows, vec!["the quick ", "brown fox"]);
+
+        let rows: Vec<String> = wrap_line(&Line::from("abcdefghij"), 4).iter().map(text).collect();
+        assert_eq!(rows, vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_wrap_keeps_span_styles() {
+        let red = Style::default().fg(Color::Red);
+        let line = Line::from(vec![Span::raw("ab"), Span::styled("cdef", red)]);
+        let rows = wrap_line(&line, 3);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].spans[1].content, "c");
+        assert_eq!(rows[0].spans[1].style, red);
+        assert_eq!(rows[1].spans[0].content, "def");
+        assert_eq!(rows[1].spans[0].style, red);
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let mut cache = RenderCache::new();
+        let mut block = CommandBlock::new("ls".to_string(), "/".to_string());
+        let mut renders = 0;
+        let mut render = |cache: &mut RenderCache, block: &CommandBlock, width, theme| {
+            cache.get_or_render(block, width, theme, || {
+                renders += 1;
+                (vec![Line::from(block.output.text())], Vec::new(), Vec::new())
+            }).lines.len()
+        };
+
+        render(&mut cache, &block, 80, 0);
+        render(&mut cache, &block, 80, 0);
+        block.append_output("output", false);
+        render(&mut cache, &block, 80, 0);
+        render(&mut cache, &block, 40, 0);
+        render(&mut cache, &block, 40, 1);
+        assert_eq!(render(&mut cache, &block, 3, 1), 2);
+        assert_eq!(cache.get_or_render(&block, 3, 1, || unreachable!()).continued, [false, true]);
+        assert_eq!(renders, 5);
+
+        cache.retain(&[]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_image_slots_follow_wrapping() {
+        let mut cache = RenderCache::new();
+        let block = CommandBlock::new("plot".to_string(), "/".to_string());
+        let slot = ImageSlot { row: 1, column: 2, columns: 3, rows: 2, image: 0 };
+        let cached = cache.get_or_render(&block, 4, 0, || {
+            (vec![Line::from("abcdefgh"), Line::from("▀▀▀"), Line::from("▀▀▀")], Vec::new(), vec![slot])
+        });
+        assert_eq!(cached.images, [ImageSlot { row: 2, ..slot }]);
+    }
+}
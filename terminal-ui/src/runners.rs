@@ -0,0 +1,215 @@
+//! Language runners for code snippets in the AI Terminal
+//!
+//! `/code run` picks a runner from the language of the code fence. Shell
+//! code runs as it is. Python runs in a virtual environment and JavaScript
+//! next to a `node_modules` directory, both kept in the data directory and
+//! shared by every snippet, so a library is installed once and examples that
+//! import `requests` or `lodash` run instead of failing on the import. The
+//! packages to install are found from the snippet's imports, plus any named
+//! on a `# requires: pkg ...` (or `// requires:`) line.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::snippets::CodeSnippet;
+
+/// Standard library modules of Python, which are never installed
+const PYTHON_STDLIB: &[&str] = &[
+    "__future__", "abc", "argparse", "array", "ast", "asyncio", "base64", "bisect", "calendar", "collections",
+    "concurrent", "contextlib", "copy", "csv", "ctypes", "dataclasses", "datetime", "decimal", "difflib", "email",
+    "enum", "errno", "fnmatch", "fractions", "functools", "getpass", "gettext", "glob", "gzip", "hashlib", "heapq",
+    "hmac", "html", "http", "importlib", "inspect", "io", "ipaddress", "itertools", "json", "locale", "logging",
+    "lzma", "math", "mimetypes", "multiprocessing", "operator", "os", "pathlib", "pickle", "platform", "pprint",
+    "queue", "random", "re", "secrets", "select", "shlex", "shutil", "signal", "socket", "sqlite3", "ssl", "stat",
+    "statistics", "string", "struct", "subprocess", "sys", "tarfile", "tempfile", "textwrap", "threading", "time",
+    "timeit", "tkinter", "tomllib", "traceback", "types", "typing", "unittest", "urllib", "uuid", "venv",
+    "warnings", "weakref", "xml", "zipfile", "zlib", "zoneinfo",
+];
+
+/// Python modules installed from a package of another name
+const PYTHON_PACKAGES: &[(&str, &str)] = &[
+    ("bs4", "beautifulsoup4"),
+    ("cv2", "opencv-python"),
+    ("dateutil", "python-dateutil"),
+    ("dotenv", "python-dotenv"),
+    ("PIL", "pillow"),
+    ("sklearn", "scikit-learn"),
+    ("yaml", "pyyaml"),
+];
+
+/// Built-in modules of Node.js, which are never installed
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "buffer", "child_process", "cluster", "crypto", "dgram", "dns", "events", "fs", "http", "http2",
+    "https", "net", "os", "path", "perf_hooks", "process", "querystring", "readline", "stream", "string_decoder",
+    "timers", "tls", "tty", "url", "util", "v8", "vm", "worker_threads", "zlib",
+];
+
+/// How a snippet is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    /// Run by the shell, as a typed command
+    Shell,
+    /// Run by Python in the shared virtual environment
+    Python,
+    /// Run by Node.js next to the shared `node_modules`
+    Node,
+}
+
+impl Runner {
+    /// Pick the runner for the language of a code fence
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language.trim().to_lowercase().as_str() {
+            "" | "sh" | "bash" | "shell" | "zsh" | "console" => Some(Runner::Shell),
+            "python" | "py" | "python3" => Some(Runner::Python),
+            "javascript" | "js" | "node" | "mjs" => Some(Runner::Node),
+            _ => None,
+        }
+    }
+
+    /// Find the packages a snippet needs installed
+    pub fn requirements(self, code: &str) -> Vec<String> {
+        let mut packages: BTreeSet<String> = explicit_requirements(code).into_iter().collect();
+        match self {
+            Runner::Shell => {}
+            Runner::Python => {
+                let import = Regex::new(r"(?m)^\s*(?:from\s+([A-Za-z_][\w]*)[\w.]*\s+import|import\s+([A-Za-z_][\w]*))")
+                    .expect("import pattern is valid");
+                for captures in import.captures_iter(code) {
+                    let module = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+                    if PYTHON_STDLIB.contains(&module) {
+                        continue;
+                    }
+                    let package = PYTHON_PACKAGES
+                        .iter()
+                        .find(|(name, _)| *name == module)
+                        .map_or(module, |(_, package)| package);
+                    packages.insert(package.to_string());
+                }
+            }
+            Runner::Node => {
+                let import = Regex::new(r#"(?:require\(\s*|import\s+(?:[^'"]*?\s+from\s+)?|import\(\s*)['"]([^'"]+)['"]"#)
+                    .expect("import pattern is valid");
+                for captures in import.captures_iter(code) {
+                    let specifier = &captures[1];
+                    if specifier.starts_with('.') || specifier.starts_with('/') || specifier.starts_with("node:") {
+                        continue;
+                    }
+                    // `@scope/pkg/sub` and `pkg/sub` install `@scope/pkg` and `pkg`
+                    let parts = if specifier.starts_with('@') { 2 } else { 1 };
+                    let package = specifier.split('/').take(parts).collect::<Vec<_>>().join("/");
+                    if !NODE_BUILTINS.contains(&package.as_str()) {
+                        packages.insert(package);
+                    }
+                }
+            }
+        }
+        packages.into_iter().collect()
+    }
+
+    /// Write the snippet under `cache_dir` if needed and build the command line running it
+    pub fn command(self, snippet: &CodeSnippet, cache_dir: &Path) -> Result<String> {
+        let packages: Vec<String> = self.requirements(&snippet.code).iter().map(|package| quote(package)).collect();
+        match self {
+            Runner::Shell => Ok(snippet.code.clone()),
+            Runner::Python => {
+                let dir = cache_dir.join("python");
+                let script = write_script(&dir, "py", &snippet.code)?;
+                let venv = dir.join("venv");
+                let python = quote(&venv.join("bin").join("python").to_string_lossy());
+                let mut steps = vec![format!("{{ [ -x {python} ] || python3 -m venv {}; }}", quote(&venv.to_string_lossy()))];
+                if !packages.is_empty() {
+                    steps.push(format!(
+                        "{{ {python} -m pip install -q {} || echo '[some packages could not be installed]'; }}",
+                        packages.join(" ")
+                    ));
+                }
+                steps.push(format!("{python} {}", quote(&script.to_string_lossy())));
+                Ok(steps.join(" && "))
+            }
+            Runner::Node => {
+                let dir = cache_dir.join("node");
+                // ES module syntax needs the .mjs extension without a package.json saying so
+                let module = Regex::new(r"(?m)^\s*(?:import|export)\s").expect("module pattern is valid");
+                let extension = if module.is_match(&snippet.code) { "mjs" } else { "js" };
+                let script = write_script(&dir, extension, &snippet.code)?;
+                let mut steps = Vec::new();
+                if !packages.is_empty() {
+                    steps.push(format!(
+                        "{{ npm install --prefix {} --no-save --silent {} || echo '[some packages could not be installed]'; }}",
+                        quote(&dir.to_string_lossy()),
+                        packages.join(" ")
+                    ));
+                }
+                steps.push(format!("node {}", quote(&script.to_string_lossy())));
+                Ok(steps.join(" && "))
+            }
+        }
+    }
+}
+
+/// Get the packages named on `requires:` comment lines
+fn explicit_requirements(code: &str) -> Vec<String> {
+    code.lines()
+        .filter_map(|line| {
+            let comment = line.trim_start().strip_prefix('#').or_else(|| line.trim_start().strip_prefix("//"))?;
+            comment.trim_start().strip_prefix("requires:")
+        })
+        .flat_map(|packages| packages.split([' ', ',']).filter(|package| !package.is_empty()).map(str::to_string))
+        .collect()
+}
+
+/// Write a snippet to a script file named after its content, returning the file's path
+fn write_script(dir: &Path, extension: &str, code: &str) -> Result<PathBuf> {
+    let digest = Sha256::digest(code.as_bytes());
+    let name: String = digest.iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    let scripts = dir.join("scripts");
+    fs::create_dir_all(&scripts).with_context(|| format!("could not create {}", scripts.display()))?;
+    let path = scripts.join(format!("{}.{}", name, extension));
+    fs::write(&path, code).with_context(|| format!("could not write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Quote a word for the shell
+fn quote(word: &str) -> String {
+    shlex::try_quote(word).map_or_else(|_| word.to_string(), |quoted| quoted.into_owned())
+}
+
+/// Build the command line running a snippet, with the runner of its language
+pub fn command_for(snippet: &CodeSnippet, cache_dir: &Path) -> Result<String> {
+    let Some(runner) = Runner::for_language(&snippet.language) else {
+        bail!("there is no runner for {} code; copy it with /code copy instead", snippet.language);
+    };
+    runner.command(snippet, cache_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirements_are_detected() {
+        let python = "import os\nimport requests\nfrom PIL import Image\nfrom collections.abc import Mapping\n# requires: lxml\n";
+        assert_eq!(Runner::Python.requirements(python), vec!["lxml", "pillow", "requests"]);
+
+        let node = "const fs = require('fs');\nimport _ from \"lodash\";\nimport { x } from '@scope/pkg/sub';\nimport './local.js';\n";
+        assert_eq!(Runner::Node.requirements(node), vec!["@scope/pkg", "lodash"]);
+        assert_eq!(Runner::for_language("Python3"), Some(Runner::Python));
+        assert_eq!(Runner::for_language("rust"), None);
+    }
+
+    #[test]
+    fn test_python_command_uses_cached_venv() {
+        let cache = std::env::temp_dir().join(format!("ai-terminal-runners-{}", std::process::id()));
+        let snippet = CodeSnippet { language: "python".to_string(), code: "import requests".to_string(), prompt: String::new() };
+        let command = command_for(&snippet, &cache).unwrap();
+        assert!(command.contains("python3 -m venv"));
+        assert!(command.contains("pip install -q requests"));
+        assert!(command.ends_with(".py"));
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+//! Lua scripting for the AI Terminal
+//!
+//! Every `.lua` file in the `scripts` directory of the config directory runs
+//! at startup, in file name order, with a `terminal` table to automate the
+//! terminal from:
+//!
+//! ```lua
+//! terminal.run("git status")             -- run a command in a new block
+//! terminal.insert("git commit -m ''")    -- put text on the input line
+//! terminal.toast("Deployed")             -- show a message for a few seconds
+//! terminal.last_output()                 -- output of the last command, or nil
+//! terminal.last_exit_code()              -- its exit code, or nil
+//!
+//! terminal.bind("alt+d", function() terminal.run("date") end)
+//! terminal.command("Disk Usage", "Show disk usage", function(argument) terminal.run("df -h") end)
+//! terminal.on("command_submit", function(command) end)
+//! terminal.on("block_complete", function(block)
+//!   if block.exit_code ~= 0 then terminal.toast(block.command .. " failed") end
+//! end)
+//! ```
+//!
+//! Keys are written as in the `[keys]` section, and keys scripts bind take
+//! precedence over the terminal's own. Commands scripts run are queued and
+//! run one after another once the terminal is idle. A script running for
+//! more than a couple of seconds at a time is stopped, so a loop cannot
+//! hang the terminal.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use crossterm::event::KeyEvent;
+
+use crate::keymap::KeyChord;
+use crate::widgets::command_palette::Command;
+
+/// Prefix of the command palette ids of script commands
+pub const PALETTE_PREFIX: &str = "script:";
+
+/// Something a script asked the terminal to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptEffect {
+    /// Run a shell command in a new block
+    Run(String),
+    /// Put text on the input line
+    Insert(String),
+    /// Show a message briefly
+    Toast(String),
+}
+
+/// A finished command, as scripts see it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finished {
+    pub command: String,
+    pub exit_code: i32,
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+/// Get the directory holding scripts, if there is a config directory
+pub fn scripts_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|path| path.join("ai-terminal").join("scripts"))
+}
+
+#[cfg(feature = "lua")]
+mod engine {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use anyhow::{anyhow, Result};
+    use mlua::{Function, HookTriggers, IntoLuaMulti, Lua, RegistryKey};
+
+    use super::{Finished, ScriptEffect};
+    use crate::keymap::KeyChord;
+
+    /// Longest a script may run at a time
+    const TIME_LIMIT: Duration = Duration::from_secs(2);
+
+    /// Lua instructions between checks of the time limit
+    const CHECK_EVERY: u32 = 10_000;
+
+    /// What the `terminal` functions share with the terminal
+    #[derive(Default)]
+    struct Shared {
+        effects: Vec<ScriptEffect>,
+        last: Option<Finished>,
+        bindings: Vec<(KeyChord, String, RegistryKey)>,
+        commands: Vec<(String, String, RegistryKey)>,
+        submit_hooks: Vec<RegistryKey>,
+        complete_hooks: Vec<RegistryKey>,
+    }
+
+    /// The Lua state scripts run in
+    pub struct Engine {
+        lua: Lua,
+        shared: Rc<RefCell<Shared>>,
+        deadline: Rc<Cell<Option<Instant>>>,
+    }
+
+    impl std::fmt::Debug for Engine {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Engine").finish_non_exhaustive()
+        }
+    }
+
+    impl Engine {
+        /// Create a Lua state with the `terminal` table
+        pub fn new() -> Result<Self> {
+            let lua = Lua::new();
+            let shared = Rc::new(RefCell::new(Shared::default()));
+            let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+            let limit = deadline.clone();
+            lua.set_hook(HookTriggers::new().every_nth_instruction(CHECK_EVERY), move |_, _| match limit.get() {
+                Some(deadline) if Instant::now() > deadline => Err(mlua::Error::runtime("the script ran too long and was stopped")),
+                _ => Ok(()),
+            });
+            install(&lua, &shared).map_err(|e| anyhow!("{}", e))?;
+            Ok(Self { lua, shared, deadline })
+        }
+
+        /// Run a script
+        pub fn load(&self, name: &str, source: &str) -> Result<()> {
+            self.limited(|| self.lua.load(source).set_name(name).exec())
+        }
+
+        pub fn take_effects(&self) -> Vec<ScriptEffect> {
+            std::mem::take(&mut self.shared.borrow_mut().effects)
+        }
+
+        /// Call the function bound to a key, if a script bound it
+        pub fn key(&self, chord: &KeyChord) -> Option<Result<()>> {
+            let function = self.function(|shared| shared.bindings.iter().find(|(bound, _, _)| bound == chord).map(|(_, _, key)| key))?;
+            Some(function.and_then(|function| self.limited(|| function.call::<_, ()>(()))))
+        }
+
+        pub fn bound_keys(&self) -> Vec<String> {
+            self.shared.borrow().bindings.iter().map(|(_, key, _)| key.clone()).collect()
+        }
+
+        pub fn commands(&self) -> Vec<(String, String)> {
+            self.shared.borrow().commands.iter().map(|(name, description, _)| (name.clone(), description.clone())).collect()
+        }
+
+        /// Call a script command by name
+        pub fn run_command(&self, name: &str, argument: &str) -> Result<()> {
+            let function = self
+                .function(|shared| shared.commands.iter().find(|(existing, _, _)| existing == name).map(|(_, _, key)| key))
+                .ok_or_else(|| anyhow!("no script command named '{}'", name))??;
+            self.limited(|| function.call::<_, ()>(argument.to_string()))
+        }
+
+        pub fn command_submitted(&self, command: &str) -> Result<()> {
+            self.call_hooks(|shared, index| shared.submit_hooks.get(index), || Ok(command.to_string()))
+        }
+
+        pub fn block_finished(&self, finished: Finished) -> Result<()> {
+            self.shared.borrow_mut().last = Some(finished.clone());
+            let block = || {
+                let block = self.lua.create_table()?;
+                block.set("command", finished.command.clone())?;
+                block.set("exit_code", finished.exit_code)?;
+                block.set("output", finished.output.clone())?;
+                block.set("duration_ms", finished.duration_ms)?;
+                Ok(block)
+            };
+            self.call_hooks(|shared, index| shared.complete_hooks.get(index), block)
+        }
+
+        /// Call every hook `get` finds, in order, with the arguments `args` builds
+        fn call_hooks<'lua, A: IntoLuaMulti<'lua>>(
+            &'lua self,
+            get: impl Fn(&Shared, usize) -> Option<&RegistryKey>,
+            args: impl Fn() -> mlua::Result<A>,
+        ) -> Result<()> {
+            let mut index = 0;
+            while let Some(function) = self.function(|shared| get(shared, index)) {
+                let function = function?;
+                self.limited(|| function.call::<_, ()>(args()?))?;
+                index += 1;
+            }
+            Ok(())
+        }
+
+        /// Look up a stored function, releasing the shared state before it is called
+        fn function(&self, find: impl FnOnce(&Shared) -> Option<&RegistryKey>) -> Option<Result<Function<'_>>> {
+            let shared = self.shared.borrow();
+            let key = find(&shared)?;
+            Some(self.lua.registry_value(key).map_err(|e| anyhow!("{}", e)))
+        }
+
+        /// Run Lua code with the time limit, making its error readable
+        fn limited<T>(&self, run: impl FnOnce() -> mlua::Result<T>) -> Result<T> {
+            self.deadline.set(Some(Instant::now() + TIME_LIMIT));
+            let result = run();
+            self.deadline.set(None);
+            result.map_err(|e| anyhow!("{}", e))
+        }
+    }
+
+    /// Set up the `terminal` table
+    fn install(lua: &Lua, shared: &Rc<RefCell<Shared>>) -> mlua::Result<()> {
+        let terminal = lua.create_table()?;
+        for (name, effect) in [("run", ScriptEffect::Run as fn(String) -> ScriptEffect), ("insert", ScriptEffect::Insert), ("toast", ScriptEffect::Toast)] {
+            let shared = shared.clone();
+            terminal.set(name, lua.create_function(move |_, text: String| {
+                shared.borrow_mut().effects.push(effect(text));
+                Ok(())
+            })?)?;
+        }
+
+        let state = shared.clone();
+        terminal.set("last_output", lua.create_function(move |_, ()| Ok(state.borrow().last.as_ref().map(|last| last.output.clone())))?)?;
+        let state = shared.clone();
+        terminal.set("last_exit_code", lua.create_function(move |_, ()| Ok(state.borrow().last.as_ref().map(|last| last.exit_code)))?)?;
+
+        let state = shared.clone();
+        terminal.set("bind", lua.create_function(move |lua, (key, callback): (String, Function)| {
+            let chord = KeyChord::parse(&key).map_err(|e| mlua::Error::runtime(format!("{:#}", e)))?;
+            let callback = lua.create_registry_value(callback)?;
+            let mut state = state.borrow_mut();
+            state.bindings.retain(|(bound, _, _)| *bound != chord);
+            state.bindings.push((chord, key, callback));
+            Ok(())
+        })?)?;
+        let state = shared.clone();
+        terminal.set("command", lua.create_function(move |lua, (name, description, callback): (String, String, Function)| {
+            let callback = lua.create_registry_value(callback)?;
+            let mut state = state.borrow_mut();
+            state.commands.retain(|(existing, _, _)| *existing != name);
+            state.commands.push((name, description, callback));
+            Ok(())
+        })?)?;
+        let state = shared.clone();
+        terminal.set("on", lua.create_function(move |lua, (event, callback): (String, Function)| {
+            let callback = lua.create_registry_value(callback)?;
+            let mut state = state.borrow_mut();
+            match event.as_str() {
+                "command_submit" => state.submit_hooks.push(callback),
+                "block_complete" => state.complete_hooks.push(callback),
+                other => return Err(mlua::Error::runtime(format!("unknown event '{}'; use command_submit or block_complete", other))),
+            }
+            Ok(())
+        })?)?;
+        lua.globals().set("terminal", terminal)
+    }
+}
+
+#[cfg(not(feature = "lua"))]
+mod engine {
+    use anyhow::{bail, Result};
+
+    use super::{Finished, ScriptEffect};
+    use crate::keymap::KeyChord;
+
+    /// Stand-in for builds without the `lua` feature, which run no scripts
+    #[derive(Debug)]
+    pub enum Engine {}
+
+    impl Engine {
+        pub fn new() -> Result<Self> {
+            bail!("this build has no Lua support; it needs the `lua` feature")
+        }
+
+        pub fn load(&self, _name: &str, _source: &str) -> Result<()> {
+            match *self {}
+        }
+
+        pub fn take_effects(&self) -> Vec<ScriptEffect> {
+            match *self {}
+        }
+
+        pub fn key(&self, _chord: &KeyChord) -> Option<Result<()>> {
+            match *self {}
+        }
+
+        pub fn bound_keys(&self) -> Vec<String> {
+            match *self {}
+        }
+
+        pub fn commands(&self) -> Vec<(String, String)> {
+            match *self {}
+        }
+
+        pub fn run_command(&self, _name: &str, _argument: &str) -> Result<()> {
+            match *self {}
+        }
+
+        pub fn command_submitted(&self, _command: &str) -> Result<()> {
+            match *self {}
+        }
+
+        pub fn block_finished(&self, _finished: Finished) -> Result<()> {
+            match *self {}
+        }
+    }
+}
+
+/// The user's scripts, and what they bound and registered
+#[derive(Debug, Default)]
+pub struct Scripts {
+    /// The Lua state, created with the first script
+    engine: Option<engine::Engine>,
+    /// File names of the scripts loaded
+    loaded: Vec<String>,
+}
+
+impl Scripts {
+    /// Run every script in `dir`, returning the scripts and why some failed
+    pub fn load_dir(dir: &Path) -> (Self, Vec<String>) {
+        let mut scripts = Self::default();
+        let mut problems = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (scripts, problems);
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let result = std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|source| scripts.load(&name, &source));
+            if let Err(e) = result {
+                problems.push(format!("{}: {:#}", name, e));
+            }
+        }
+        (scripts, problems)
+    }
+
+    /// Run a script
+    pub fn load(&mut self, name: &str, source: &str) -> Result<()> {
+        let engine = match &self.engine {
+            Some(engine) => engine,
+            None => self.engine.insert(engine::Engine::new()?),
+        };
+        engine.load(name, source)?;
+        self.loaded.push(name.to_string());
+        Ok(())
+    }
+
+    /// Get the file names of the scripts loaded
+    pub fn loaded(&self) -> &[String] {
+        &self.loaded
+    }
+
+    /// Take what the scripts asked for since the last time
+    pub fn take_effects(&mut self) -> Vec<ScriptEffect> {
+        self.engine.as_ref().map(engine::Engine::take_effects).unwrap_or_default()
+    }
+
+    /// Call the function bound to a key, returning `None` if no script bound it
+    pub fn key(&mut self, key: &KeyEvent) -> Option<Result<()>> {
+        self.engine.as_ref()?.key(&KeyChord::from_event(key))
+    }
+
+    /// Get the keys scripts bound, as written
+    pub fn bound_keys(&self) -> Vec<String> {
+        self.engine.as_ref().map(engine::Engine::bound_keys).unwrap_or_default()
+    }
+
+    /// Tell the scripts a command is about to run
+    pub fn command_submitted(&mut self, command: &str) -> Result<()> {
+        self.engine.as_ref().map_or(Ok(()), |engine| engine.command_submitted(command))
+    }
+
+    /// Tell the scripts a command finished, which becomes the last one they read
+    pub fn block_finished(&mut self, finished: Finished) -> Result<()> {
+        self.engine.as_ref().map_or(Ok(()), |engine| engine.block_finished(finished))
+    }
+
+    /// Call a script's palette command, by its palette id
+    pub fn run_command(&mut self, palette_id: &str, argument: Option<&str>) -> Result<()> {
+        match (&self.engine, palette_id.strip_prefix(PALETTE_PREFIX)) {
+            (Some(engine), Some(name)) => engine.run_command(name, argument.unwrap_or_default()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the palette commands scripts registered
+    pub fn palette_commands(&self) -> Vec<Command> {
+        let commands = self.engine.as_ref().map(engine::Engine::commands).unwrap_or_default();
+        commands
+            .into_iter()
+            .map(|(name, description)| Command::new(&format!("{}{}", PALETTE_PREFIX, name), &name, &description, "Scripts", "📜").with_argument("argument"))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "lua"))]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_scripts() {
+        let mut scripts = Scripts::default();
+        let source = r##"
+            terminal.bind("alt+d", function() terminal.run("date") end)
+            terminal.command("Greet", "Say hello", function(name) terminal.toast("hello " .. name) end)
+            terminal.on("command_submit", function(command) terminal.insert("# ran " .. command) end)
+            terminal.on("block_complete", function(block)
+              if block.exit_code ~= 0 then terminal.toast(block.command .. " failed: " .. block.output) end
+            end)
+        "##;
+        scripts.load("init.lua", source).unwrap();
+        assert_eq!(scripts.loaded(), ["init.lua"]);
+        assert_eq!(scripts.bound_keys(), ["alt+d"]);
+
+        assert!(scripts.key(&KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT)).is_none());
+        scripts.key(&KeyEvent::new(KeyCode::Char('d'), KeyModifiers::ALT)).unwrap().unwrap();
+        scripts.command_submitted("make").unwrap();
+        let finished = Finished { command: "make".to_string(), exit_code: 2, output: "no rule".to_string(), duration_ms: 5 };
+        scripts.block_finished(finished).unwrap();
+        let commands = scripts.palette_commands();
+        assert_eq!(commands[0].id, "script:Greet");
+        scripts.run_command(&commands[0].id, Some("world")).unwrap();
+        assert_eq!(scripts.take_effects(), [
+            ScriptEffect::Run("date".to_string()),
+            ScriptEffect::Insert("# ran make".to_string()),
+            ScriptEffect::Toast("make failed: no rule".to_string()),
+            ScriptEffect::Toast("hello world".to_string()),
+        ]);
+        assert!(scripts.take_effects().is_empty());
+
+        scripts.load("state.lua", "assert(terminal.last_exit_code() == 2 and terminal.last_output() == 'no rule')").unwrap();
+        let error = scripts.load("loop.lua", "while true do end").unwrap_err();
+        assert!(format!("{:#}", error).contains("ran too long"));
+        assert!(scripts.load("bad.lua", "terminal.bind('hyper+q', function() end)").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Reachability of the model server
+//!
+//! The terminal starts whether or not the model server is up. A check in the
+//! background asks the server for its models once the UI is running, then
+//! every [`CHECK_INTERVAL`] while it answers and, while it does not, again
+//! after a delay doubling from [`FIRST_RETRY`] up to [`MAX_RETRY`]. While the
+//! server is unreachable the terminal works as a plain shell, with AI prompts
+//! held back as in offline mode; once it answers AI features come back by
+//! themselves.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ollama_client::ChatBackend;
+use tokio::sync::oneshot;
+
+/// How often a server that answers is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Delay before checking an unreachable server again, doubled after each failure
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+
+/// Longest delay between checks of an unreachable server
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+/// How long a check waits for the server
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Change in whether the server answers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The server stopped answering, or did not answer the first check, with why
+    Lost(String),
+    /// The server answers again
+    Restored,
+}
+
+/// Background checks of whether the model server answers
+#[derive(Debug)]
+pub struct ServerHealth {
+    /// Whether the last check reached the server, once one finished
+    reachable: Option<bool>,
+    /// Checks failed in a row
+    failures: u32,
+    next_check: Instant,
+    /// Result of the check running in the background
+    pending: Option<oneshot::Receiver<Result<(), String>>>,
+}
+
+impl Default for ServerHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerHealth {
+    /// Start following the server, checking it on the first tick
+    pub fn new() -> Self {
+        Self { reachable: None, failures: 0, next_check: Instant::now(), pending: None }
+    }
+
+    /// Check the server on the next tick, as after going back online
+    pub fn check_now(&mut self) {
+        self.next_check = Instant::now();
+    }
+
+    /// Start a check of `backend` when one is due, reporting a change once a check finishes
+    pub fn tick(&mut self, backend: &Arc<dyn ChatBackend>) -> Option<Change> {
+        if let Some(pending) = &mut self.pending {
+            let result = match pending.try_recv() {
+                Ok(result) => result,
+                Err(oneshot::error::TryRecvError::Empty) => return None,
+                Err(oneshot::error::TryRecvError::Closed) => Err("the check stopped unexpectedly".to_string()),
+            };
+            self.pending = None;
+            return self.record(result);
+        }
+        if Instant::now() < self.next_check {
+            return None;
+        }
+        let backend = Arc::clone(backend);
+        let (sender, receiver) = oneshot::channel();
+        tokio::spawn(async move {
+            let result = match tokio::time::timeout(TIMEOUT, backend.list_models()).await {
+                Ok(result) => result.map(|_| ()).map_err(|e| e.to_string()),
+                Err(_) => Err(format!("no answer within {}s", TIMEOUT.as_secs())),
+            };
+            let _ = sender.send(result);
+        });
+        self.pending = Some(receiver);
+        None
+    }
+
+    /// Record the result of a check and schedule the next one
+    fn record(&mut self, result: Result<(), String>) -> Option<Change> {
+        let was_reachable = self.reachable;
+        match result {
+            Ok(()) => {
+                self.failures = 0;
+                self.reachable = Some(true);
+                self.next_check = Instant::now() + CHECK_INTERVAL;
+                (was_reachable == Some(false)).then_some(Change::Restored)
+            }
+            Err(error) => {
+                self.failures += 1;
+                self.reachable = Some(false);
+                self.next_check = Instant::now() + retry_delay(self.failures);
+                (was_reachable != Some(false)).then_some(Change::Lost(error))
+            }
+        }
+    }
+}
+
+/// Get how long to wait before checking again after some failed checks in a row
+fn retry_delay(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    FIRST_RETRY.saturating_mul(1 << doublings).min(MAX_RETRY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ollama_client::OllamaClient;
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), FIRST_RETRY);
+        assert_eq!(retry_delay(3), FIRST_RETRY * 4);
+        assert_eq!(retry_delay(100), MAX_RETRY);
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_reported_once() {
+        let mut client = OllamaClient::new().unwrap();
+        client.base_url = "http://127.0.0.1:9/api".to_string();
+        let backend: Arc<dyn ChatBackend> = Arc::new(client);
+        let mut health = ServerHealth::new();
+        assert_eq!(health.tick(&backend), None);
+        let change = loop {
+            if let Some(change) = health.tick(&backend) {
+                break change;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert!(matches!(change, Change::Lost(_)));
+
+        // Only the change back is reported
+        assert_eq!(health.record(Err("refused".to_string())), None);
+        assert_eq!(health.record(Ok(())), Some(Change::Restored));
+        assert_eq!(health.record(Ok(())), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Session sharing for the AI Terminal
+//!
+//! `/share` turns the session into a Markdown transcript and uploads it to a
+//! GitHub gist or a paste service, so a session can be handed over with a
+//! link; `/share block` uploads only the clicked block, or the latest one.
+//! Each service is a [`ShareProvider`], chosen by the `[share]` section.
+//! The text goes through the privacy scrubber first, and every
+//! upload waits for an explicit confirmation naming where it is going. The
+//! link is shown in a message and copied to the clipboard with OSC 52, which
+//! terminals without clipboard access ignore.
+
+use std::io::{self, Write};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+/// Where the GitHub API creates gists
+const GIST_API_URL: &str = "https://api.github.com/gists";
+
+/// Service a session is uploaded to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareService {
+    /// A GitHub gist, created with the token in `token_env`
+    #[default]
+    Gist,
+    /// A paste service taking the text as the request body and answering with the link
+    Paste,
+}
+
+/// Sharing settings from the `[share]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShareConfig {
+    /// Service to upload to
+    pub service: ShareService,
+    /// URL of the paste service, or of the gist API on GitHub Enterprise
+    pub url: Option<String>,
+    /// Environment variable holding the API token
+    pub token_env: Option<String>,
+    /// Whether gists are listed publicly instead of being secret
+    pub public: bool,
+}
+
+impl Default for ShareConfig {
+    fn default() -> Self {
+        Self {
+            service: ShareService::Gist,
+            url: None,
+            token_env: Some("GITHUB_TOKEN".to_string()),
+            public: false,
+        }
+    }
+}
+
+impl ShareConfig {
+    /// Describe where uploads go, for the confirmation
+    pub fn describe(&self) -> String {
+        match (self.service, &self.url) {
+            (ShareService::Gist, None) => {
+                format!("a {} GitHub gist", if self.public { "public" } else { "secret" })
+            }
+            (ShareService::Gist, Some(url)) => format!("a gist at {}", url),
+            (ShareService::Paste, Some(url)) => url.clone(),
+            (ShareService::Paste, None) => "a paste service (none is configured)".to_string(),
+        }
+    }
+
+    /// Read the API token, if one is configured
+    pub fn token(&self) -> Result<Option<String>> {
+        self.token_env
+            .as_deref()
+            .map(|var| std::env::var(var).with_context(|| format!("environment variable {} is not set", var)))
+            .transpose()
+    }
+
+    /// Build the provider for the configured service, reading its token
+    pub fn provider(&self) -> Result<Box<dyn ShareProvider>> {
+        let token = self.token()?;
+        Ok(match self.service {
+            ShareService::Gist => Box::new(GistProvider {
+                url: self.url.clone().unwrap_or_else(|| GIST_API_URL.to_string()),
+                token: token.context("set share.token_env to a variable holding a GitHub token")?,
+                public: self.public,
+            }),
+            ShareService::Paste => Box::new(PasteProvider {
+                url: self.url.clone().context("set share.url to the paste service's URL")?,
+                token,
+            }),
+        })
+    }
+}
+
+/// Text to upload, already scrubbed
+#[derive(Debug, Clone)]
+pub struct Upload {
+    /// Name of the uploaded file, whose extension services use for highlighting
+    pub file_name: String,
+    /// What the upload is, as in "AI Terminal session"
+    pub description: String,
+    /// The Markdown to upload
+    pub content: String,
+}
+
+/// A service that takes an upload and answers with a link to it
+#[async_trait]
+pub trait ShareProvider: Send + Sync {
+    /// Upload the text, returning its link
+    async fn upload(&self, upload: Upload) -> Result<String>;
+}
+
+/// Creates GitHub gists through the gist API
+#[derive(Debug, Clone)]
+pub struct GistProvider {
+    /// URL of the gist API
+    pub url: String,
+    /// GitHub token allowed to create gists
+    pub token: String,
+    /// Whether gists are listed publicly instead of being secret
+    pub public: bool,
+}
+
+/// Response of the gist API
+#[derive(Debug, Deserialize)]
+struct Gist {
+    /// Link to the gist's page
+    html_url: String,
+}
+
+#[async_trait]
+impl ShareProvider for GistProvider {
+    async fn upload(&self, upload: Upload) -> Result<String> {
+        let body = serde_json::json!({
+            "description": upload.description,
+            "public": self.public,
+            "files": { upload.file_name: { "content": upload.content } },
+        });
+        let response = reqwest::Client::new()
+            .post(&self.url)
+            .bearer_auth(&self.token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            // GitHub rejects requests without a user agent
+            .header(reqwest::header::USER_AGENT, concat!("ai-terminal/", env!("CARGO_PKG_VERSION")))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json::<Gist>().await?.html_url)
+    }
+}
+
+/// Posts to a paste service taking the text as the request body and answering with the link
+#[derive(Debug, Clone)]
+pub struct PasteProvider {
+    /// URL the text is posted to
+    pub url: String,
+    /// Token sent as a bearer token, if the service needs one
+    pub token: Option<String>,
+}
+
+#[async_trait]
+impl ShareProvider for PasteProvider {
+    async fn upload(&self, upload: Upload) -> Result<String> {
+        let mut request = reqwest::Client::new()
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "text/markdown; charset=utf-8");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.body(upload.content).send().await?.error_for_status()?;
+        let link = response.text().await?.trim().to_string();
+        anyhow::ensure!(link.starts_with("http"), "the paste service did not answer with a link");
+        Ok(link)
+    }
+}
+
+/// Build the OSC 52 sequence that puts text on the clipboard
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))
+}
+
+/// Copy text to the clipboard through the terminal
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52(text).as_bytes())?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_partial_json, body_string, header, method}};
+
+    #[tokio::test]
+    async fn test_upload_to_gist_and_paste() {
+        let upload = || Upload { file_name: "session.md".to_string(), description: "AI Terminal session".to_string(), content: "# hi".to_string() };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer t0ken"))
+            .and(body_partial_json(serde_json::json!({ "public": false, "files": { "session.md": { "content": "# hi" } } })))
+            .respond_with(ResponseTemplate::new(201).set_body_string(r#"{"html_url":"https://gist.example.com/abc"}"#))
+            .mount(&server)
+            .await;
+
+        let gist = GistProvider { url: server.uri(), token: "t0ken".to_string(), public: false };
+        assert_eq!(gist.upload(upload()).await.unwrap(), "https://gist.example.com/abc");
+        let wrong_token = GistProvider { token: "other".to_string(), ..gist };
+        assert!(wrong_token.upload(upload()).await.is_err());
+        assert!(ShareConfig { token_env: None, ..Default::default() }.provider().is_err());
+
+        let paste_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string("# hi"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("https://paste.example.com/xyz\n"))
+            .mount(&paste_server)
+            .await;
+        let paste = ShareConfig { service: ShareService::Paste, url: Some(paste_server.uri()), token_env: None, public: false };
+        let link = paste.provider().unwrap().upload(upload()).await.unwrap();
+        assert_eq!(link, "https://paste.example.com/xyz");
+        assert_eq!(paste.describe(), paste_server.uri());
+    }
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+}
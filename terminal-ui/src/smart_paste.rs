@@ -0,0 +1,200 @@
+//! Clean-up of shell snippets pasted from the web
+//!
+//! Snippets copied from documentation often carry the `$ ` prompts of the
+//! page, the example output printed under each command, lines split with a
+//! trailing `\` and values to fill in such as `<your-token>` or
+//! `YOUR_API_KEY`. Pasted text is put on the input line as it is; when it
+//! has any of these, a toast offers 'Smart Paste: Clean Up' in the command
+//! palette, which strips the prompts and output, joins the continued lines
+//! and turns the values to fill in into placeholders such as `{{TOKEN}}`,
+//! asked for when the command runs. 'Smart Paste: Adapt to This System' also
+//! has the model rewrite the snippet for the local system and package
+//! manager before it goes back on the input line.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::snippets;
+
+/// Prompts that start the command lines of a snippet
+const PROMPTS: [&str; 3] = ["$ ", "% ", "> "];
+
+/// Words in a placeholder name that make its value secret
+const SECRET_WORDS: [&str; 4] = ["TOKEN", "PASSWORD", "SECRET", "KEY"];
+
+/// Package managers looked for on `PATH`, in order of preference
+const PACKAGE_MANAGERS: [&str; 8] = ["apt", "dnf", "yum", "pacman", "zypper", "apk", "brew", "winget"];
+
+/// A pasted snippet cleaned up, and what was changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cleanup {
+    /// The cleaned-up snippet
+    pub text: String,
+    /// Command lines whose prompt was stripped
+    pub prompts: usize,
+    /// Lines of example output dropped
+    pub output_lines: usize,
+    /// Lines joined to the one before for ending with `\`
+    pub joined: usize,
+    /// Names of the placeholders values to fill in became
+    pub placeholders: Vec<String>,
+}
+
+impl Cleanup {
+    /// Check whether anything was changed
+    pub fn changed(&self) -> bool {
+        self.prompts + self.output_lines + self.joined + self.placeholders.len() > 0
+    }
+
+    /// Describe what was changed in one line
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.prompts > 0 {
+            parts.push(format!("{} prompt(s) stripped", self.prompts));
+        }
+        if self.output_lines > 0 {
+            parts.push(format!("{} output line(s) dropped", self.output_lines));
+        }
+        if self.joined > 0 {
+            parts.push(format!("{} continued line(s) joined", self.joined));
+        }
+        if !self.placeholders.is_empty() {
+            parts.push(format!("placeholders {}", self.placeholders.join(", ")));
+        }
+        if parts.is_empty() {
+            return "nothing to clean up".to_string();
+        }
+        parts.join(", ")
+    }
+}
+
+/// Clean up a pasted snippet
+pub fn clean(text: &str) -> Cleanup {
+    let mut cleanup = Cleanup::default();
+    let lines: Vec<&str> = text.lines().collect();
+    // With prompts, only the lines that have one are commands; the rest is example output
+    let prompted = lines.iter().any(|line| prompt_len(line).is_some());
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+    for line in lines {
+        if continued {
+            let last = commands.last_mut().expect("a continued line follows a command");
+            last.push(' ');
+            last.push_str(line.trim());
+            cleanup.joined += 1;
+        } else if let Some(len) = prompt_len(line) {
+            commands.push(line.trim_start()[len..].to_string());
+            cleanup.prompts += 1;
+        } else if prompted && !line.trim().is_empty() {
+            cleanup.output_lines += 1;
+            continue;
+        } else {
+            commands.push(line.trim_end().to_string());
+        }
+        let last = commands.last_mut().expect("a command was just added");
+        continued = last.ends_with('\\');
+        if continued {
+            last.truncate(last.len() - 1);
+            last.truncate(last.trim_end().len());
+        }
+    }
+    let joined = commands.join("\n");
+    let (text, placeholders) = replace_values(joined.trim_matches('\n'));
+    cleanup.text = text;
+    cleanup.placeholders = placeholders;
+    cleanup
+}
+
+/// Get the length of the prompt a line starts with, if it is a command line
+fn prompt_len(line: &str) -> Option<usize> {
+    let line = line.trim_start();
+    PROMPTS.iter().find(|prompt| line.starts_with(*prompt) && line.len() > prompt.len()).map(|prompt| prompt.len())
+}
+
+/// Turn `<your-token>` and `YOUR_API_KEY` into `{{TOKEN}}` and `{{API_KEY:secret}}`, returning the names
+fn replace_values(text: &str) -> (String, Vec<String>) {
+    let pattern = Regex::new(r"<([A-Za-z][A-Za-z0-9_-]*)>|\b[Yy][Oo][Uu][Rr][_-]([A-Za-z0-9_-]+)\b")
+        .expect("value pattern is valid");
+    let mut names: Vec<String> = Vec::new();
+    let replaced = pattern.replace_all(text, |captures: &regex::Captures| {
+        let raw = captures.get(1).or_else(|| captures.get(2)).map_or("", |m| m.as_str());
+        let raw = raw.strip_prefix("your-").or_else(|| raw.strip_prefix("your_")).unwrap_or(raw);
+        let name = raw.to_uppercase().replace('-', "_");
+        if !names.contains(&name) {
+            names.push(name.clone());
+        }
+        if SECRET_WORDS.iter().any(|word| name.contains(word)) {
+            format!("{{{{{}:secret}}}}", name)
+        } else {
+            format!("{{{{{}}}}}", name)
+        }
+    });
+    (replaced.into_owned(), names)
+}
+
+/// Describe the local system for adapting snippets, as in "linux with apt"
+pub fn system_description() -> String {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let dirs: Vec<_> = std::env::split_paths(&path).collect();
+    let manager = PACKAGE_MANAGERS.iter().find(|manager| dirs.iter().any(|dir| is_program(&dir.join(manager))));
+    match manager {
+        Some(manager) => format!("{} with {}", std::env::consts::OS, manager),
+        None => std::env::consts::OS.to_string(),
+    }
+}
+
+/// Check whether a path is a program, with or without a Windows extension
+fn is_program(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+/// Build the prompt asking the model to adapt a snippet to the local system
+pub fn adapt_prompt(snippet: &str, system: &str) -> String {
+    format!(
+        "Adapt this shell snippet to run on {} with its shell and package manager. Keep what it does and \
+         keep placeholders such as {{{{NAME}}}} as they are. Answer with only the adapted commands in one \
+         fenced code block.\n\n```\n{}\n```",
+        system, snippet,
+    )
+}
+
+/// Read the adapted snippet from the model's answer, the first code block or the whole answer
+pub fn parse_adapted(response: &str) -> Option<String> {
+    let code = snippets::code_blocks(response, "")
+        .into_iter()
+        .next()
+        .map_or_else(|| response.trim().to_string(), |block| block.code);
+    (!code.trim().is_empty()).then_some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let pasted = "$ curl -H \"Authorization: Bearer <your-token>\" \\\n    https://api.example.com/v1/items\n{\"items\": []}\n$ export KEY=YOUR_API_KEY\n";
+        let cleanup = clean(pasted);
+        assert_eq!(
+            cleanup.text,
+            "curl -H \"Authorization: Bearer {{TOKEN:secret}}\" https://api.example.com/v1/items\nexport KEY={{API_KEY:secret}}",
+        );
+        assert_eq!((cleanup.prompts, cleanup.output_lines, cleanup.joined), (2, 1, 1));
+        assert_eq!(cleanup.placeholders, ["TOKEN", "API_KEY"]);
+        assert_eq!(cleanup.describe(), "2 prompt(s) stripped, 1 output line(s) dropped, 1 continued line(s) joined, placeholders TOKEN, API_KEY");
+
+        // Without prompts every line is a command, and redirections are left alone
+        let plain = clean("sort < input.txt > <output-file>\necho done");
+        assert_eq!(plain.text, "sort < input.txt > {{OUTPUT_FILE}}\necho done");
+        assert!(!clean("ls -la\n").changed());
+    }
+
+    #[test]
+    fn test_adapt() {
+        assert!(adapt_prompt("apt install jq", "macos with brew").contains("run on macos with brew"));
+        assert_eq!(parse_adapted("Here:\n```sh\nbrew install jq\n```\nDone."), Some("brew install jq".to_string()));
+        assert_eq!(parse_adapted("brew install jq\n"), Some("brew install jq".to_string()));
+        assert_eq!(parse_adapted(" "), None);
+    }
+}
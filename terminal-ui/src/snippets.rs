@@ -0,0 +1,101 @@
+//! Code snippets library for the AI Terminal
+//!
+//! Fenced code blocks in AI answers can be copied or run with `/code`. The
+//! block last copied or run can then be saved with `/snippet save [tags]`,
+//! together with the prompt of the answer it came from, building up a
+//! personal cookbook. Saved snippets live in the store, show up in the
+//! command palette under "Snippets", and are put back on the input line by
+//! `/snippet insert <id>` or by picking them in the palette.
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+use terminal_emulator::Snippet;
+
+use crate::widgets::{Action, Command};
+
+/// Prefix of the palette command ids of snippets, followed by the snippet id
+pub const PALETTE_PREFIX: &str = "snippet:";
+
+/// A fenced code block found in an AI answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeSnippet {
+    /// Language given after the opening fence, empty if none
+    pub language: String,
+    /// The code, without the fences
+    pub code: String,
+    /// Prompt of the answer the block came from
+    pub prompt: String,
+}
+
+/// Find the fenced code blocks in an AI answer to `prompt`
+pub fn code_blocks(markdown: &str, prompt: &str) -> Vec<CodeSnippet> {
+    let mut blocks = Vec::new();
+    let mut open: Option<CodeSnippet> = None;
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(language))) => {
+                open = Some(CodeSnippet { language: language.to_string(), code: String::new(), prompt: prompt.to_string() });
+            }
+            Event::Text(text) => {
+                if let Some(block) = &mut open {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(mut block) = open.take() {
+                    block.code.truncate(block.code.trim_end().len());
+                    blocks.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Turn words such as `#docker cleanup` into tags
+pub fn parse_tags(words: &[&str]) -> Vec<String> {
+    words
+        .iter()
+        .map(|word| word.trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Get a one-line summary of a snippet: its tags and first line
+pub fn summary(snippet: &Snippet) -> String {
+    let first_line = snippet.code.lines().next().unwrap_or_default();
+    let tags: Vec<String> = snippet.tags.iter().map(|tag| format!("#{}", tag)).collect();
+    format!("{} {}", tags.join(" "), first_line).trim().to_string()
+}
+
+/// Build the palette entries of saved snippets
+pub fn palette_commands(snippets: &[Snippet]) -> Vec<Command> {
+    snippets
+        .iter()
+        .map(|snippet| {
+            let name: String = summary(snippet).chars().take(50).collect();
+            let description = format!("Insert snippet {} (from \"{}\")", snippet.id, snippet.prompt);
+            Command::new(&format!("{}{}", PALETTE_PREFIX, snippet.id), &name, &description, "Snippets", "📋")
+                .with_action(Action::Slash(format!("/snippet insert {}", snippet.id)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_blocks_and_tags() {
+        let answer = "Use this:\n\n```sh\ndocker system prune -af\n```\n\nor\n\n```\nls\n```\n\nand `inline` code.";
+        let blocks = code_blocks(answer, "clean docker");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0], CodeSnippet {
+            language: "sh".to_string(),
+            code: "docker system prune -af".to_string(),
+            prompt: "clean docker".to_string(),
+        });
+        assert_eq!(blocks[1].language, "");
+        assert_eq!(parse_tags(&["#Docker", "cleanup", "#"]), vec!["docker", "cleanup"]);
+    }
+}
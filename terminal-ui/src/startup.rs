@@ -0,0 +1,193 @@
+//! Startup profiling for the AI Terminal
+//!
+//! Records how long each subsystem takes to initialize so slow starts can be
+//! diagnosed with `/startup-report`. Subsystems that are not needed to show
+//! the prompt are wrapped in [`Lazy`] and initialized on first use; their
+//! timings are recorded as deferred spans when that happens.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+/// Time from launch to the first frame that startup should stay under
+pub const STARTUP_TARGET: Duration = Duration::from_millis(100);
+
+/// Time spent initializing one subsystem
+#[derive(Debug, Clone)]
+pub struct StartupSpan {
+    /// Name of the subsystem
+    pub name: &'static str,
+    /// How long initialization took
+    pub duration: Duration,
+    /// Whether it was initialized on first use rather than at startup
+    pub deferred: bool,
+}
+
+/// Timings of startup and of lazily initialized subsystems
+#[derive(Debug, Clone)]
+pub struct StartupProfile {
+    /// When the process started initializing
+    started: Instant,
+    /// Recorded spans in the order they finished
+    spans: Vec<StartupSpan>,
+    /// Time from start to the first frame, once it was drawn
+    ready_after: Option<Duration>,
+}
+
+impl StartupProfile {
+    /// Start profiling now
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            spans: Vec::new(),
+            ready_after: None,
+        }
+    }
+
+    /// Run a startup step, recording how long it took
+    pub fn time<T>(&mut self, name: &'static str, init: impl FnOnce() -> T) -> T {
+        self.record(name, false, init)
+    }
+
+    /// Run the first-use initialization of a deferred subsystem, recording how long it took
+    pub fn time_deferred<T>(&mut self, name: &'static str, init: impl FnOnce() -> T) -> T {
+        self.record(name, true, init)
+    }
+
+    /// Note that the first frame was drawn, ending startup
+    pub fn mark_ready(&mut self) {
+        if self.ready_after.is_some() {
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        self.ready_after = Some(elapsed);
+        if elapsed > STARTUP_TARGET {
+            tracing::warn!("Startup took {:?}, over the {:?} target", elapsed, STARTUP_TARGET);
+        } else {
+            tracing::info!("Startup took {:?}", elapsed);
+        }
+    }
+
+    /// Get the time from start to the first frame, if it was drawn
+    pub fn ready_after(&self) -> Option<Duration> {
+        self.ready_after
+    }
+
+    /// Get the recorded spans
+    pub fn spans(&self) -> &[StartupSpan] {
+        &self.spans
+    }
+
+    /// Describe the recorded timings for display
+    pub fn report(&self) -> String {
+        let mut report = match self.ready_after {
+            Some(ready) => format!(
+                "Prompt ready after {} (target {})\n",
+                format_duration(ready),
+                format_duration(STARTUP_TARGET),
+            ),
+            None => "Startup has not finished\n".to_string(),
+        };
+
+        let width = self.spans.iter().map(|span| span.name.len()).max().unwrap_or(0);
+        let (eager, deferred): (Vec<_>, Vec<_>) = self.spans.iter().partition(|span| !span.deferred);
+        for span in eager {
+            let _ = writeln!(report, "  {:width$}  {:>9}", span.name, format_duration(span.duration));
+        }
+        if !deferred.is_empty() {
+            report.push_str("Initialized on first use:\n");
+            for span in deferred {
+                let _ = writeln!(report, "  {:width$}  {:>9}", span.name, format_duration(span.duration));
+            }
+        }
+        report
+    }
+
+    /// Time `init` and keep the span
+    fn record<T>(&mut self, name: &'static str, deferred: bool, init: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let value = init();
+        let duration = start.elapsed();
+        tracing::debug!("Initialized {} in {:?}", name, duration);
+        self.spans.push(StartupSpan { name, duration, deferred });
+        value
+    }
+}
+
+/// A subsystem initialized the first time it is used
+#[derive(Debug, Default)]
+pub enum Lazy<T> {
+    /// Not needed yet
+    #[default]
+    Pending,
+    /// Initialized and usable
+    Ready(T),
+    /// Initialization failed; it is not retried until reset
+    Failed,
+}
+
+impl<T> Lazy<T> {
+    /// Get the value, initializing it on first use
+    ///
+    /// Failures are logged once and leave the subsystem unavailable.
+    pub fn get_or_init(&mut self, name: &str, init: impl FnOnce() -> Result<T>) -> Option<&mut T> {
+        if let Lazy::Pending = self {
+            *self = match init() {
+                Ok(value) => Lazy::Ready(value),
+                Err(e) => {
+                    tracing::warn!("Failed to initialize {}: {:?}", name, e);
+                    Lazy::Failed
+                }
+            };
+        }
+        match self {
+            Lazy::Ready(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Drop the value so it is initialized again on next use
+    pub fn reset(&mut self) {
+        *self = Lazy::Pending;
+    }
+}
+
+/// Format a duration in milliseconds
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_report() {
+        let mut profile = StartupProfile::start();
+        assert_eq!(profile.time("themes", || 42), 42);
+        profile.mark_ready();
+        profile.time_deferred("LLM log", || ());
+
+        let report = profile.report();
+        assert!(report.starts_with("Prompt ready after "));
+        assert!(report.contains("  themes "));
+        assert!(report.contains("Initialized on first use:\n  LLM log"));
+        assert_eq!(profile.spans().len(), 2);
+    }
+
+    #[test]
+    fn test_lazy_initializes_once() {
+        let mut calls = 0;
+        let mut lazy = Lazy::default();
+        assert_eq!(lazy.get_or_init("test", || { calls += 1; Ok(1) }).copied(), Some(1));
+        assert_eq!(lazy.get_or_init("test", || { calls += 1; Ok(2) }).copied(), Some(1));
+        assert_eq!(calls, 1);
+
+        let mut failing: Lazy<u32> = Lazy::default();
+        assert!(failing.get_or_init("test", || anyhow::bail!("no")).is_none());
+        assert!(failing.get_or_init("test", || Ok(3)).is_none());
+        failing.reset();
+        assert_eq!(failing.get_or_init("test", || Ok(3)).copied(), Some(3));
+    }
+}
@@ -0,0 +1,179 @@
+//! Token usage statistics for the AI Terminal
+//!
+//! Backends report how many tokens a request used and, for Ollama, how long
+//! generation took. This module keeps those figures for the running session:
+//! the latest request is summarised in the status bar, and the `stats`
+//! palette command shows totals and the most recent requests.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use ollama_client::TokenUsage;
+
+/// Number of recent requests listed in the stats view
+const MAX_RECENT: usize = 10;
+
+/// Figures for one AI request
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestStats {
+    /// Model that handled the request
+    pub model: String,
+    /// Token counts and timings, if the backend reported them
+    pub usage: Option<TokenUsage>,
+    /// Time from sending the request to receiving the response
+    pub latency: Duration,
+}
+
+impl RequestStats {
+    /// Generation speed in tokens per second
+    ///
+    /// Uses the server's generation time when reported, otherwise the latency.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let usage = self.usage?;
+        usage.tokens_per_second().or_else(|| {
+            let seconds = self.latency.as_secs_f64();
+            (seconds > 0.0 && usage.completion_tokens > 0).then(|| usage.completion_tokens as f64 / seconds)
+        })
+    }
+}
+
+/// Token usage of the running session
+#[derive(Debug, Clone, Default)]
+pub struct SessionStats {
+    /// Requests recorded, with or without token counts
+    requests: usize,
+    /// Prompt tokens over all requests
+    prompt_tokens: u64,
+    /// Generated tokens over all requests
+    completion_tokens: u64,
+    /// Latency over all requests
+    total_latency: Duration,
+    /// Most recent requests, newest last
+    recent: VecDeque<RequestStats>,
+}
+
+impl SessionStats {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a completed request
+    pub fn record(&mut self, request: RequestStats) {
+        self.requests += 1;
+        if let Some(usage) = request.usage {
+            self.prompt_tokens += usage.prompt_tokens;
+            self.completion_tokens += usage.completion_tokens;
+        }
+        self.total_latency += request.latency;
+        if self.recent.len() == MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(request);
+    }
+
+    /// Get the most recent request
+    pub fn last(&self) -> Option<&RequestStats> {
+        self.recent.back()
+    }
+
+    /// Tokens used by the session so far
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Short summary for the status bar, once a request reported token counts
+    pub fn status_segment(&self) -> Option<String> {
+        let last = self.last()?;
+        let usage = last.usage?;
+        let mut segment = format!(" {} tok", format_tokens(usage.total_tokens()));
+        if let Some(rate) = last.tokens_per_second() {
+            let _ = write!(segment, " · {:.1} tok/s", rate);
+        }
+        let _ = write!(segment, " · Σ {} ", format_tokens(self.total_tokens()));
+        Some(segment)
+    }
+
+    /// Describe the session's usage for display
+    pub fn report(&self) -> String {
+        if self.requests == 0 {
+            return "No AI requests in this session yet.".to_string();
+        }
+
+        let mut report = format!(
+            "Session: {} request(s), {} tokens ({} prompt, {} generated), average latency {:.1}s\n",
+            self.requests,
+            self.total_tokens(),
+            self.prompt_tokens,
+            self.completion_tokens,
+            self.total_latency.as_secs_f64() / self.requests as f64,
+        );
+        report.push_str("Recent requests, newest first:\n");
+        for request in self.recent.iter().rev() {
+            let _ = write!(report, "- {}: {:.1}s", request.model, request.latency.as_secs_f64());
+            match request.usage {
+                Some(usage) => {
+                    let _ = write!(report, ", {} prompt + {} generated tokens", usage.prompt_tokens, usage.completion_tokens);
+                }
+                None => report.push_str(", no token counts reported"),
+            }
+            if let Some(rate) = request.tokens_per_second() {
+                let _ = write!(report, ", {:.1} tok/s", rate);
+            }
+            if let Some(load) = request.usage.and_then(|usage| {
+                Some(usage.total_duration?.saturating_sub(usage.prompt_duration? + usage.generation_duration?))
+            }) && load >= Duration::from_millis(100)
+            {
+                let _ = write!(report, " ({:.1}s loading)", load.as_secs_f64());
+            }
+            report.push('\n');
+        }
+        report
+    }
+}
+
+/// Format a token count compactly, such as `950` or `12.3k`
+fn format_tokens(count: u64) -> String {
+    if count < 1000 {
+        count.to_string()
+    } else {
+        format!("{:.1}k", count as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(completion_tokens: u64, generation_ms: Option<u64>) -> RequestStats {
+        RequestStats {
+            model: "llama3".to_string(),
+            usage: Some(TokenUsage {
+                prompt_tokens: 100,
+                completion_tokens,
+                generation_duration: generation_ms.map(Duration::from_millis),
+                ..Default::default()
+            }),
+            latency: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn test_session_totals_and_status() {
+        let mut stats = SessionStats::new();
+        assert_eq!(stats.status_segment(), None);
+
+        stats.record(request(900, Some(1000)));
+        stats.record(request(200, None));
+        assert_eq!(stats.total_tokens(), 1300);
+        // Without a server timing the rate falls back to the latency
+        assert_eq!(stats.status_segment().unwrap(), " 300 tok · 100.0 tok/s · Σ 1.3k ");
+        assert!(stats.report().starts_with("Session: 2 request(s), 1300 tokens"));
+
+        for _ in 0..MAX_RECENT {
+            stats.record(request(1, None));
+        }
+        assert_eq!(stats.recent.len(), MAX_RECENT);
+    }
+}
@@ -0,0 +1,153 @@
+//! AI summaries of long command output
+//!
+//! A build or test run can print thousands of lines with the few that
+//! matter buried in them. Once a command's output reaches `min_lines`, the
+//! `[summaries]` section decides what happens to it:
+//!
+//! ```toml
+//! [summaries]
+//! mode = "auto"      # "offer", the default, only notes /summarize under the block; "off" does neither
+//! min_lines = 200
+//! model = "llama3"   # the active model when left out
+//! ```
+//!
+//! The summary, leading with what failed and where, is shown above the full
+//! output, and 'Toggle Summaries' folds it to its heading. `/summarize [n]`
+//! summarizes any block, however short. The output is sent with secrets
+//! masked, and only its start and end once it is past [`MAX_INPUT_BYTES`].
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ollama_client::{ChatBackend, ChatRequest, ChatResponse};
+use serde::Deserialize;
+use terminal_emulator::OutputSummary;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Most bytes of output sent to the model
+pub const MAX_INPUT_BYTES: usize = 24 * 1024;
+
+/// Part of the bytes sent taken from the start of the output, the rest coming from its end, where errors usually are
+const HEAD_DIVISOR: usize = 4;
+
+/// System prompt of summarizing requests
+pub const SYSTEM_PROMPT: &str = "You summarize terminal output for a developer. Start with whether the command \
+succeeded. For a failure, list each distinct error with its file and line and the likely cause, most important \
+first. Leave out progress lines, repeated lines and warnings that do not matter. Answer in at most ten short \
+Markdown lines.";
+
+/// What happens to long output once its command finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMode {
+    /// Nothing
+    Off,
+    /// A note under the block offers `/summarize`
+    #[default]
+    Offer,
+    /// The output is summarized straight away
+    Auto,
+}
+
+/// Settings from the `[summaries]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SummaryConfig {
+    pub mode: SummaryMode,
+    /// Fewest output lines counted as long
+    pub min_lines: usize,
+    /// Model writing summaries, instead of the active one
+    pub model: Option<String>,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            mode: SummaryMode::default(),
+            min_lines: 200,
+            model: None,
+        }
+    }
+}
+
+impl SummaryConfig {
+    /// Decide where the summary of a finished command's output of `lines` lines starts out
+    pub fn on_finish(&self, lines: usize) -> Option<OutputSummary> {
+        if lines < self.min_lines {
+            return None;
+        }
+        match self.mode {
+            SummaryMode::Off => None,
+            SummaryMode::Offer => Some(OutputSummary::Offered),
+            SummaryMode::Auto => Some(OutputSummary::Pending),
+        }
+    }
+}
+
+/// A finished summarizing request for the block with the given ID, and how long it took
+pub type Summarized = (Uuid, Result<ChatResponse, String>, Duration);
+
+/// Build the request summarizing a command's output, which should already be masked for secrets
+pub fn request(model: &str, command: &str, exit_code: Option<i32>, output: &str) -> ChatRequest {
+    let exit = exit_code.map_or(String::new(), |code| format!(", which exited with {}", code));
+    let prompt = format!("Summarize the output of `{}`{}:\n```\n{}\n```", command, exit, excerpt(output.trim_end()));
+    let mut request = ChatRequest::new(model, prompt);
+    request.system = Some(SYSTEM_PROMPT.to_string());
+    request
+}
+
+/// Cut output past [`MAX_INPUT_BYTES`] down to its start and end, noting how many lines are left out
+fn excerpt(output: &str) -> String {
+    if output.len() <= MAX_INPUT_BYTES {
+        return output.to_string();
+    }
+    let head_end = line_start(output, MAX_INPUT_BYTES / HEAD_DIVISOR);
+    let tail_start = line_start(output, output.len() - (MAX_INPUT_BYTES - MAX_INPUT_BYTES / HEAD_DIVISOR));
+    let left_out = output[head_end..tail_start].lines().count();
+    format!("{}[… {} lines left out …]\n{}", &output[..head_end], left_out, &output[tail_start..])
+}
+
+/// Get the start of the line holding byte `at`
+fn line_start(text: &str, mut at: usize) -> usize {
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    text[..at].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// Send a summarizing request, reporting its response on `results`
+pub async fn summarize(backend: Arc<dyn ChatBackend>, id: Uuid, request: ChatRequest, results: mpsc::UnboundedSender<Summarized>) {
+    let started = Instant::now();
+    let result = backend.generate(&request).await.map_err(|e| format!("{:#}", e));
+    let _ = results.send((id, result, started.elapsed()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summaries() {
+        let config = SummaryConfig::default();
+        assert_eq!(config.on_finish(10), None);
+        assert_eq!(config.on_finish(200), Some(OutputSummary::Offered));
+        let config = SummaryConfig { mode: SummaryMode::Auto, ..SummaryConfig::default() };
+        assert_eq!(config.on_finish(500), Some(OutputSummary::Pending));
+
+        let short = request("llama3", "cargo build", Some(101), "error: oops\n");
+        assert_eq!(short.prompt, "Summarize the output of `cargo build`, which exited with 101:\n```\nerror: oops\n```");
+        assert_eq!(short.system.as_deref(), Some(SYSTEM_PROMPT));
+
+        let lines: Vec<String> = (0..5000).map(|i| format!("line {}", i)).collect();
+        let long = request("llama3", "make", None, &lines.join("\n"));
+        assert!(long.prompt.len() < MAX_INPUT_BYTES + 200);
+        assert!(long.prompt.contains("\nline 0\n"));
+        assert!(long.prompt.contains("\nline 4999\n```"));
+        assert!(long.prompt.contains(" lines left out …]\nline "));
+
+        // The cuts fall inside box-drawing characters
+        let rule = format!("x{}", "─".repeat(20_000));
+        assert!(request("llama3", "make", None, &rule).prompt.contains("[… 0 lines left out …]"));
+    }
+}
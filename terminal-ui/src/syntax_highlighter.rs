@@ -0,0 +1,288 @@
+//! Syntax highlighting for code blocks in the AI Terminal
+//!
+//! Code blocks are highlighted with syntect, but instead of one of syntect's
+//! bundled color schemes the token scopes are mapped onto the active
+//! [`Theme`], so code follows the terminal theme (including named and
+//! 256-color palette entries). Highlighted blocks are cached because every
+//! pane is re-rendered on every frame.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{
+    Color as SyntectColor, FontStyle, ScopeSelectors, StyleModifier, Theme as SyntectTheme,
+    ThemeItem, ThemeSettings,
+};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::theme::Theme;
+
+/// Maximum number of highlighted blocks kept in the cache
+const CACHE_CAPACITY: usize = 256;
+
+/// Palette slots that token scopes are mapped to
+///
+/// syntect only understands RGBA colors, so the generated syntect theme
+/// stores the slot index in the red channel with a zero alpha. The slot is
+/// resolved against the active theme when spans are built, which keeps the
+/// syntect theme independent of the terminal theme.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Plain,
+    Comment,
+    Keyword,
+    String,
+    Constant,
+    Function,
+    Type,
+    Invalid,
+    Inserted,
+    Deleted,
+    Heading,
+}
+
+/// Number of palette slots
+const SLOT_COUNT: usize = 11;
+
+/// Scope selectors for each slot, with the font style applied on top
+const SCOPE_RULES: [(&str, Slot, FontStyle); 10] = [
+    ("comment, punctuation.definition.comment", Slot::Comment, FontStyle::ITALIC),
+    ("keyword, storage, keyword.operator.word", Slot::Keyword, FontStyle::empty()),
+    ("string, constant.character, punctuation.definition.string", Slot::String, FontStyle::empty()),
+    ("constant.numeric, constant.language, constant.other, variable.other.constant", Slot::Constant, FontStyle::empty()),
+    ("entity.name.function, support.function, variable.function", Slot::Function, FontStyle::empty()),
+    ("entity.name.type, entity.name.class, entity.name.struct, support.type, support.class", Slot::Type, FontStyle::empty()),
+    ("invalid", Slot::Invalid, FontStyle::UNDERLINE),
+    ("markup.inserted", Slot::Inserted, FontStyle::empty()),
+    ("markup.deleted", Slot::Deleted, FontStyle::empty()),
+    ("markup.heading, meta.diff.range, meta.diff.header", Slot::Heading, FontStyle::BOLD),
+];
+
+/// Cached highlight results, evicted oldest first
+#[derive(Default)]
+struct HighlightCache {
+    entries: HashMap<u64, Vec<Line<'static>>>,
+    order: VecDeque<u64>,
+}
+
+impl HighlightCache {
+    /// Remember a highlighted block, evicting the oldest entry when full
+    fn insert(&mut self, key: u64, lines: Vec<Line<'static>>) {
+        if self.entries.insert(key, lines).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Load the syntax definitions ahead of the first highlight
+pub fn warm_up() {
+    syntax_set();
+}
+
+/// Get the shared syntax definitions
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Get the syntect theme that maps scopes to palette slots
+fn slot_theme() -> &'static SyntectTheme {
+    static SLOT_THEME: OnceLock<SyntectTheme> = OnceLock::new();
+    SLOT_THEME.get_or_init(|| SyntectTheme {
+        name: Some("ai-terminal".to_string()),
+        author: None,
+        settings: ThemeSettings {
+            foreground: Some(slot_color(Slot::Plain)),
+            ..ThemeSettings::default()
+        },
+        scopes: SCOPE_RULES
+            .iter()
+            .map(|(selectors, slot, font_style)| ThemeItem {
+                scope: ScopeSelectors::from_str(selectors).expect("built-in scope selector is valid"),
+                style: StyleModifier {
+                    foreground: Some(slot_color(*slot)),
+                    background: None,
+                    font_style: Some(*font_style),
+                },
+            })
+            .collect(),
+    })
+}
+
+/// Get the shared highlight cache
+fn cache() -> &'static Mutex<HighlightCache> {
+    static CACHE: OnceLock<Mutex<HighlightCache>> = OnceLock::new();
+    CACHE.get_or_init(Mutex::default)
+}
+
+/// Encode a palette slot as a syntect color
+fn slot_color(slot: Slot) -> SyntectColor {
+    SyntectColor { r: slot as u8, g: 0, b: 0, a: 0 }
+}
+
+/// Resolve every palette slot against a theme
+fn slot_styles(theme: &Theme) -> [Style; SLOT_COUNT] {
+    let plain = theme.styles.code_block;
+    [
+        plain,
+        plain.fg(theme.secondary),
+        plain.fg(theme.primary),
+        plain.fg(theme.success),
+        plain.fg(theme.warning),
+        plain.fg(theme.accent),
+        plain.fg(theme.command),
+        plain.fg(theme.error),
+        theme.styles.diff_added,
+        theme.styles.diff_removed,
+        theme.styles.heading_1,
+    ]
+}
+
+/// Convert a syntect style produced by the slot theme into a ratatui style
+fn resolve_style(style: syntect::highlighting::Style, slots: &[Style; SLOT_COUNT]) -> Style {
+    let foreground = style.foreground;
+    let mut resolved = if foreground.a == 0 {
+        slots.get(foreground.r as usize).copied().unwrap_or(slots[0])
+    } else {
+        // Only reachable if a syntax embeds its own colors
+        slots[0].fg(ratatui::style::Color::Rgb(foreground.r, foreground.g, foreground.b))
+    };
+
+    if style.font_style.contains(FontStyle::BOLD) {
+        resolved = resolved.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        resolved = resolved.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        resolved = resolved.add_modifier(Modifier::UNDERLINED);
+    }
+    resolved
+}
+
+/// Highlight a code block using the colors of a theme
+///
+/// # Arguments
+///
+/// * `code` - The code to highlight
+/// * `language` - The info string of the fenced block (e.g. "rust", "py title=x")
+/// * `theme` - The active theme
+///
+/// # Returns
+///
+/// One ratatui Line per line of code; unknown languages are rendered plain
+pub fn highlight(code: &str, language: &str, theme: &Theme) -> Vec<Line<'static>> {
+    let slots = slot_styles(theme);
+    let token = language
+        .split(|c: char| c == ',' || c == '{' || c.is_whitespace())
+        .next()
+        .unwrap_or("");
+
+    let mut hasher = DefaultHasher::new();
+    (code, token, &slots).hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(lines) = cache().lock().ok().and_then(|cache| cache.entries.get(&key).cloned()) {
+        return lines;
+    }
+
+    let lines = highlight_uncached(code, token, &slots);
+    if let Ok(mut cache) = cache().lock() {
+        cache.insert(key, lines.clone());
+    }
+    lines
+}
+
+/// Highlight a code block without consulting the cache
+fn highlight_uncached(code: &str, token: &str, slots: &[Style; SLOT_COUNT]) -> Vec<Line<'static>> {
+    let syntax_set = syntax_set();
+    let syntax = syntax_set
+        .find_syntax_by_token(token)
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, slot_theme());
+
+    LinesWithEndings::from(code)
+        .map(|line| {
+            let spans = match highlighter.highlight_line(line, syntax_set) {
+                Ok(ranges) => ranges
+                    .into_iter()
+                    .map(|(style, text)| Span::styled(strip_newline(text).to_string(), resolve_style(style, slots)))
+                    .filter(|span| !span.content.is_empty())
+                    .collect(),
+                Err(_) => vec![Span::styled(strip_newline(line).to_string(), slots[0])],
+            };
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Remove a trailing line ending
+fn strip_newline(text: &str) -> &str {
+    text.trim_end_matches(['\n', '\r'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style_of<'a>(lines: &'a [Line<'static>], text: &str) -> Option<&'a Style> {
+        lines
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .find(|span| span.content.trim() == text)
+            .map(|span| &span.style)
+    }
+
+    #[test]
+    fn test_tokens_use_theme_colors() {
+        let theme = Theme::dark();
+        let lines = highlight("fn main() {\n    let x = \"hi\";\n}\n", "rust", &theme);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(style_of(&lines, "fn").unwrap().fg, Some(theme.primary));
+        assert_eq!(style_of(&lines, "main").unwrap().fg, Some(theme.accent));
+        assert!(lines.iter().all(|line| line.spans.iter().all(|span| !span.content.contains('\n'))));
+    }
+
+    #[test]
+    fn test_theme_change_rehighlights() {
+        let dark = Theme::dark();
+        let light = Theme::light();
+        let code = "# comment\nx = 1\n";
+        let first = highlight(code, "python", &dark);
+        let cached = highlight(code, "python", &dark);
+        assert_eq!(first, cached);
+
+        let relit = highlight(code, "python", &light);
+        assert_eq!(style_of(&relit, "comment").unwrap().fg, Some(light.secondary));
+        assert!(style_of(&relit, "comment").unwrap().add_modifier.contains(Modifier::ITALIC));
+    }
+
+    #[test]
+    fn test_unknown_language_is_plain() {
+        let theme = Theme::default();
+        let lines = highlight("just text", "not-a-language", &theme);
+        assert_eq!(lines, vec![Line::from(Span::styled("just text", theme.styles.code_block))]);
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest() {
+        let mut cache = HighlightCache::default();
+        for key in 0..(CACHE_CAPACITY as u64 + 1) {
+            cache.insert(key, Vec::new());
+        }
+        assert_eq!(cache.entries.len(), CACHE_CAPACITY);
+        assert!(!cache.entries.contains_key(&0));
+    }
+}
@@ -0,0 +1,94 @@
+//! Tab color rules for the AI Terminal
+//!
+//! Tabs can be marked with a color and an icon so a production shell does not
+//! look like a scratch one. Besides setting them by hand with `/tab`, rules in
+//! the `[tab_colors]` section map command patterns to a style, such as
+//! `"^ssh .*prod" = "red 🔥"`: when a command matching the pattern runs in a
+//! tab, the tab takes the style until another rule matches. Rules are tried
+//! in the order they are written, so a broad rule written first wins over a
+//! narrower one after it. Styles set by hand are left alone.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use regex::Regex;
+
+use crate::layout::TabStyle;
+use crate::theme::ThemeManager;
+
+/// Parse a style: a color, optionally followed by an icon
+///
+/// `none` as the color leaves the color unset, so `"none 🧪"` only sets an icon.
+pub fn parse_style(value: &str) -> Result<TabStyle> {
+    let mut words = value.split_whitespace();
+    let color = match words.next() {
+        None | Some("none") => None,
+        Some(color) => Some(ThemeManager::parse_color(color).map_err(|e| anyhow::anyhow!("{}", e))?),
+    };
+    let icon = words.collect::<Vec<_>>().join(" ");
+    Ok(TabStyle {
+        color,
+        icon: (!icon.is_empty()).then_some(icon),
+        manual: false,
+    })
+}
+
+/// The `[tab_colors]` section: command patterns and their styles, in the order written
+pub type TabColorConfig = IndexMap<String, String>;
+
+/// Command patterns and the tab styles they select
+#[derive(Debug, Default)]
+pub struct TabColorRules {
+    /// Rules in configuration order; the first match wins
+    rules: Vec<(Regex, TabStyle)>,
+}
+
+impl TabColorRules {
+    /// Build the rules from the `[tab_colors]` section
+    pub fn from_config(config: &TabColorConfig) -> Result<Self> {
+        let rules = config
+            .iter()
+            .map(|(pattern, style)| {
+                let regex = Regex::new(pattern).with_context(|| format!("invalid command pattern '{}'", pattern))?;
+                let style = parse_style(style).with_context(|| format!("invalid tab style '{}'", style))?;
+                Ok((regex, style))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Get the style for a command, if a rule matches
+    pub fn style_for(&self, command: &str) -> Option<&TabStyle> {
+        let command = command.trim();
+        self.rules.iter().find(|(pattern, _)| pattern.is_match(command)).map(|(_, style)| style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    #[test]
+    fn test_rules_match_commands() {
+        let config = TabColorConfig::from([
+            ("^ssh .*prod".to_string(), "red 🔥".to_string()),
+            ("^kubectl .*--context staging".to_string(), "#ffaa00".to_string()),
+        ]);
+        let rules = TabColorRules::from_config(&config).unwrap();
+        let prod = rules.style_for("ssh deploy@prod-db-1").unwrap();
+        assert_eq!(prod.color, Some(Color::Red));
+        assert_eq!(prod.icon.as_deref(), Some("🔥"));
+        assert_eq!(rules.style_for("kubectl get pods --context staging").unwrap().color, Some(Color::Rgb(255, 170, 0)));
+        assert_eq!(rules.style_for("ssh dev-box"), None);
+
+        assert_eq!(parse_style("none 🧪").unwrap().color, None);
+        assert!(TabColorRules::from_config(&TabColorConfig::from([("^ssh".to_string(), "ultraviolet".to_string())])).is_err());
+    }
+
+    #[test]
+    fn test_first_rule_written_wins() {
+        let config: TabColorConfig = toml::from_str("\"ssh\" = \"blue\"\n\"^ssh .*prod\" = \"red 🔥\"\n").unwrap();
+        let rules = TabColorRules::from_config(&config).unwrap();
+        assert_eq!(rules.style_for("ssh deploy@prod-db-1").unwrap().color, Some(Color::Blue));
+    }
+}
@@ -0,0 +1,294 @@
+//! Tables in command output
+//!
+//! Output laid out in columns, as printed by `ps`, `docker ps`, `kubectl
+//! get`, `df` or `ls -l`, and CSV or TSV text, is read into a [`Table`].
+//! `/table` opens the clicked block's table, or the latest one found, in a
+//! table view that scrolls, sorts by any column and filters on each column,
+//! and `/table inline` switches a block between its output and an aligned
+//! table in the pane. The `table` output processor shows the inline table
+//! straight away for the commands mapped to it in `[processors]`.
+
+use std::cmp::Ordering;
+
+/// Fewest data rows read as a table
+const MIN_ROWS: usize = 2;
+
+/// Longest header word of a table laid out in columns
+const MAX_HEADER_WORD: usize = 24;
+
+/// Output read as a table, every row as long as the headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// Column names
+    pub headers: Vec<String>,
+    /// Cells of each row
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A word of a line, by column and byte offsets
+#[derive(Debug, Clone, Copy)]
+struct Word {
+    start: usize,
+    end: usize,
+    byte_start: usize,
+    byte_end: usize,
+}
+
+impl Table {
+    /// Read output as a table, if it is one
+    pub fn detect(output: &str) -> Option<Table> {
+        let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).filter(|line| !line.is_empty()).collect();
+        if lines.len() < MIN_ROWS + 1 && !lines.first().is_some_and(|line| line.starts_with("total ")) {
+            return None;
+        }
+        long_listing(&lines)
+            .or_else(|| delimited(&lines, '\t'))
+            .or_else(|| delimited(&lines, ','))
+            .or_else(|| aligned(&lines))
+    }
+}
+
+/// Compare two cells, as numbers when both are, sizes such as `1.5G` and percentages included
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Read a cell as a number, scaling sizes by their unit
+pub fn number(cell: &str) -> Option<f64> {
+    let cell = cell.trim().trim_end_matches('%');
+    let lower = cell.to_ascii_lowercase();
+    let digits = lower.trim_end_matches('b').trim_end_matches('i');
+    let (digits, scale) = match digits.chars().last() {
+        Some('k') => (&digits[..digits.len() - 1], 1024f64),
+        Some('m') => (&digits[..digits.len() - 1], 1024f64.powi(2)),
+        Some('g') => (&digits[..digits.len() - 1], 1024f64.powi(3)),
+        Some('t') => (&digits[..digits.len() - 1], 1024f64.powi(4)),
+        _ if digits.len() == lower.len() => (digits, 1.0),
+        // A lone `b` or `ib` is not a size
+        _ => return None,
+    };
+    digits.parse::<f64>().ok().filter(|value| value.is_finite()).map(|value| value * scale)
+}
+
+/// Read `ls -l` output, whose rows start with file modes
+fn long_listing(lines: &[&str]) -> Option<Table> {
+    let lines = match lines.first() {
+        Some(line) if line.starts_with("total ") => &lines[1..],
+        _ => lines,
+    };
+    let is_mode = |word: &str| {
+        let mut chars = word.chars();
+        chars.next().is_some_and(|kind| "-dlcbpsD".contains(kind))
+            && chars.by_ref().take(9).all(|c| "-rwxsStT".contains(c))
+            && word.chars().count() >= 10
+    };
+    if lines.is_empty() {
+        return None;
+    }
+    let mut rows = Vec::new();
+    for line in lines {
+        let words = words(line);
+        if words.len() < 8 || !is_mode(&line[words[0].byte_start..words[0].byte_end]) {
+            return None;
+        }
+        let text = |word: &Word| line[word.byte_start..word.byte_end].to_string();
+        // `--time-style=long-iso` prints a date and a time, the default a month, a day and a time or year
+        let iso = line[words[5].byte_start..].starts_with(|c: char| c.is_ascii_digit());
+        let name = if iso { 7 } else { 8 };
+        if words.len() <= name {
+            return None;
+        }
+        rows.push(vec![
+            text(&words[0]),
+            text(&words[1]),
+            text(&words[2]),
+            text(&words[3]),
+            text(&words[4]),
+            line[words[5].byte_start..words[name - 1].byte_end].to_string(),
+            line[words[name].byte_start..].to_string(),
+        ]);
+    }
+    let headers = ["Mode", "Links", "Owner", "Group", "Size", "Modified", "Name"].map(String::from).to_vec();
+    Some(Table { headers, rows })
+}
+
+/// Read CSV or TSV, where every line has the same number of fields
+fn delimited(lines: &[&str], separator: char) -> Option<Table> {
+    let mut records = lines.iter().map(|line| split_record(line, separator));
+    let headers = records.next()?;
+    if headers.len() < 2 || headers.iter().any(|header| header.trim().is_empty()) {
+        return None;
+    }
+    let rows: Vec<Vec<String>> = records.collect();
+    (rows.len() >= MIN_ROWS && rows.iter().all(|row| row.len() == headers.len())).then(|| Table {
+        headers: headers.into_iter().map(|header| header.trim().to_string()).collect(),
+        rows,
+    })
+}
+
+/// Split a CSV line into fields, honouring double quotes
+fn split_record(line: &str, separator: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("there is always a field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted || field.trim().is_empty() => quoted = !quoted,
+            c if c == separator && !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Read output whose columns line up under a header row, as `ps`, `docker ps` and `kubectl get` print
+fn aligned(lines: &[&str]) -> Option<Table> {
+    let (header, data) = lines.split_first()?;
+    let header_words = words(header);
+    let is_header_word = |word: &Word| {
+        let text = &header[word.byte_start..word.byte_end];
+        text.len() <= MAX_HEADER_WORD
+            && text.starts_with(|c: char| c.is_alphabetic() || c == '%' || c == '#')
+            && text.chars().all(|c| c.is_alphanumeric() || "%#_-():".contains(c))
+    };
+    if header_words.len() < 2 || data.len() < MIN_ROWS || !header_words.iter().all(is_header_word) {
+        return None;
+    }
+    // Prose runs words together with single spaces; columns are kept apart by wider gaps
+    let gapped = |line: &&str| line.trim_start().contains("  ") || line.contains('\t');
+    if !gapped(header) || !data.iter().all(gapped) {
+        return None;
+    }
+
+    // Each word goes to the header it sits under, or else to the column it starts in
+    let mut cells: Vec<Vec<Option<(usize, usize)>>> = Vec::with_capacity(data.len());
+    for line in data {
+        let mut row: Vec<Option<(usize, usize)>> = vec![None; header_words.len()];
+        for word in words(line) {
+            let overlap = |column: &Word| word.end.min(column.end).saturating_sub(word.start.max(column.start));
+            let under = (0..header_words.len())
+                .filter(|&i| overlap(&header_words[i]) > 0)
+                .max_by_key(|&i| overlap(&header_words[i]));
+            let column = under.unwrap_or_else(|| header_words.iter().rposition(|column| column.start <= word.start).unwrap_or(0));
+            // Columns only move right along a line
+            let column = row.iter().rposition(Option::is_some).map_or(column, |last| column.max(last));
+            let cell = &mut row[column];
+            *cell = Some(cell.map_or((word.byte_start, word.byte_end), |(start, _)| (start, word.byte_end)));
+        }
+        cells.push(row);
+    }
+
+    // A header of two words, as in `CONTAINER ID`, leaves its second word without cells
+    let mut headers: Vec<String> = Vec::new();
+    let mut keep = Vec::new();
+    for (i, word) in header_words.iter().enumerate() {
+        let text = &header[word.byte_start..word.byte_end];
+        let empty = cells.iter().all(|row| row[i].is_none());
+        if i > 0 && empty && word.start == header_words[i - 1].end + 1 {
+            if let Some(last) = headers.last_mut() {
+                last.push(' ');
+                last.push_str(text);
+            }
+        } else {
+            headers.push(text.to_string());
+            keep.push(i);
+        }
+    }
+    let rows: Vec<Vec<String>> = data
+        .iter()
+        .zip(&cells)
+        .map(|(line, row)| {
+            keep.iter()
+                .enumerate()
+                .map(|(n, &i)| {
+                    // Cells of merged headers join the column they were merged into
+                    let end = keep.get(n + 1).copied().unwrap_or(row.len());
+                    let spans: Vec<(usize, usize)> = row[i..end].iter().flatten().copied().collect();
+                    match (spans.first(), spans.last()) {
+                        (Some(first), Some(last)) => line[first.0..last.1].to_string(),
+                        _ => String::new(),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    // Most rows fill the first column of a real table
+    let filled = rows.iter().filter(|row| !row[0].is_empty()).count();
+    (headers.len() >= 2 && filled * 5 >= rows.len() * 4).then_some(Table { headers, rows })
+}
+
+/// Split a line into words with their columns and byte offsets
+fn words(line: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    for (column, (byte, c)) in line.char_indices().enumerate() {
+        if c.is_whitespace() {
+            if let Some(word) = current.take() {
+                words.push(word);
+            }
+        } else {
+            let end = byte + c.len_utf8();
+            match &mut current {
+                Some(word) => {
+                    word.end = column + 1;
+                    word.byte_end = end;
+                }
+                None => current = Some(Word { start: column, end: column + 1, byte_start: byte, byte_end: end }),
+            }
+        }
+    }
+    words.extend(current);
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_aligned_and_listed_output() {
+        let ps = "    PID TTY          TIME CMD\n   4242 pts/0    00:00:00 bash\n   4300 pts/0    00:00:01 cargo build --release\n";
+        let table = Table::detect(ps).unwrap();
+        assert_eq!(table.headers, ["PID", "TTY", "TIME", "CMD"]);
+        assert_eq!(table.rows[1], ["4300", "pts/0", "00:00:01", "cargo build --release"]);
+
+        let docker = "CONTAINER ID   IMAGE          STATUS         NAMES\n\
+                      a1b2c3d4e5f6   nginx:latest   Up 2 hours     web\n\
+                      0f9e8d7c6b5a   redis:7        Exited (0)     cache\n";
+        let table = Table::detect(docker).unwrap();
+        assert_eq!(table.headers, ["CONTAINER ID", "IMAGE", "STATUS", "NAMES"]);
+        assert_eq!(table.rows[1], ["0f9e8d7c6b5a", "redis:7", "Exited (0)", "cache"]);
+
+        let ls = "total 8\n-rw-r--r-- 1 me staff 1234 May  3 10:00 Cargo.toml\ndrwxr-xr-x 4 me staff  128 May  3 09:59 my src\n";
+        let table = Table::detect(ls).unwrap();
+        assert_eq!(table.rows[1], ["drwxr-xr-x", "4", "me", "staff", "128", "May  3 09:59", "my src"]);
+
+        let csv = "name,size,note\nlog.txt,12K,\"rotated, daily\"\ncore,1.5G,\n";
+        let table = Table::detect(csv).unwrap();
+        assert_eq!(table.rows[0], ["log.txt", "12K", "rotated, daily"]);
+
+        assert!(Table::detect("Compiling terminal-ui v0.1.0\nFinished dev profile in 3.2s\nRunning tests\n").is_none());
+        assert!(Table::detect("hello\n").is_none());
+    }
+
+    #[test]
+    fn test_cells_compare_as_numbers() {
+        assert_eq!(compare_cells("9", "10"), Ordering::Less);
+        assert_eq!(compare_cells("1.5G", "900M"), Ordering::Greater);
+        assert_eq!(compare_cells("12%", "3%"), Ordering::Greater);
+        assert_eq!(compare_cells("10", "bash"), Ordering::Less);
+        assert_eq!(compare_cells("Bash", "cargo"), Ordering::Less);
+        assert_eq!(number("4KiB"), Some(4096.0));
+        assert_eq!(number("b"), None);
+    }
+}
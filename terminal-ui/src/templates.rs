@@ -0,0 +1,147 @@
+//! Prompt templates for the AI Terminal
+//!
+//! A template is a named AI prompt with variables filled in from the
+//! session, such as a request to explain the last command's output. The
+//! `[templates]` section adds templates and replaces the built-in ones:
+//!
+//! ```toml
+//! [templates]
+//! code-review = "Review this code for bugs and unclear names:\n\n```\n{{clipboard}}\n```"
+//! ```
+//!
+//! `/template <name> [text]`, or the template in the command palette, puts
+//! the filled prompt on the input line to be edited before it is sent, with
+//! any text given added to its end. The variables are:
+//!
+//! - `{{output}}`: output of the last command in the pane, secrets masked
+//! - `{{command}}` and `{{exit_code}}`: that command and how it ended
+//! - `{{cwd}}`: the working directory, `host:dir` in a remote pane
+//! - `{{clipboard}}`: text on the system clipboard
+//!
+//! Other `{{...}}` text is left as it is.
+
+use std::collections::BTreeMap;
+use std::process::{Command as Process, Stdio};
+
+use anyhow::{bail, Result};
+
+use crate::widgets::command_palette::{Action, Command};
+
+/// Prefix of the command palette ids of templates
+pub const PALETTE_PREFIX: &str = "template:";
+
+/// Names of the variables filled in from the session
+pub const VARIABLES: [&str; 5] = ["output", "command", "exit_code", "cwd", "clipboard"];
+
+/// Templates available without configuring any
+const BUILTIN: [(&str, &str); 3] = [
+    ("explain-output", "Explain the output of `{{command}}` (exit code {{exit_code}}), run in {{cwd}}:\n\n```\n{{output}}\n```"),
+    ("fix-command", "`{{command}}` failed with exit code {{exit_code}} in {{cwd}}:\n\n```\n{{output}}\n```\n\nWhat went wrong, and what command would work?"),
+    ("code-review", "Review this code for bugs, edge cases and unclear names:\n\n```\n{{clipboard}}\n```"),
+];
+
+/// The built-in and configured templates, by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Templates {
+    templates: BTreeMap<String, String>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl Templates {
+    /// Combine the built-in templates with configured ones, which replace those of the same name
+    pub fn new(configured: &BTreeMap<String, String>) -> Self {
+        let mut templates: BTreeMap<String, String> = BUILTIN.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect();
+        templates.extend(configured.clone());
+        Self { templates }
+    }
+
+    /// Get a template's text
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.templates.get(name).map(String::as_str)
+    }
+
+    /// Iterate over the templates by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.templates.iter().map(|(name, text)| (name.as_str(), text.as_str()))
+    }
+
+    /// Build the palette commands putting each template on the input line
+    pub fn palette_commands(&self) -> Vec<Command> {
+        self.iter()
+            .map(|(name, text)| {
+                let description: String = text.lines().next().unwrap_or_default().chars().take(80).collect();
+                Command::new(&format!("{}{}", PALETTE_PREFIX, name), name, &description, "Templates", "📝")
+                    .with_action(Action::Slash(format!("/template {}", name)))
+                    .with_argument("text to add")
+            })
+            .collect()
+    }
+}
+
+/// Fill in the variables a template uses, getting each value from `value` once
+pub fn render(template: &str, mut value: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut rendered = template.to_string();
+    for name in VARIABLES {
+        let placeholder = format!("{{{{{}}}}}", name);
+        if rendered.contains(&placeholder) {
+            let value = value(name)?;
+            rendered = rendered.replace(&placeholder, value.trim_end());
+        }
+    }
+    Ok(rendered)
+}
+
+/// Read the text on the system clipboard with the platform's tool
+pub fn read_clipboard() -> Result<String> {
+    let tools: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbpaste"]]
+    } else if cfg!(windows) {
+        &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]]
+    } else {
+        &[&["wl-paste", "--no-newline"], &["xclip", "-selection", "clipboard", "-o"], &["xsel", "--clipboard", "--output"]]
+    };
+    for tool in tools {
+        let Ok(output) = Process::new(tool[0]).args(&tool[1..]).stdin(Stdio::null()).stderr(Stdio::null()).output() else {
+            continue;
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    let names: Vec<&str> = tools.iter().map(|tool| tool[0]).collect();
+    bail!("could not read the clipboard; install {}", names.join(" or "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates() {
+        let configured = BTreeMap::from([
+            ("explain-output".to_string(), "What does this mean?\n{{output}}".to_string()),
+            ("summarize".to_string(), "Summarize {{output}} from {{cwd}} for {{NAME}}".to_string()),
+        ]);
+        let templates = Templates::new(&configured);
+        assert_eq!(templates.get("explain-output"), Some("What does this mean?\n{{output}}"));
+        assert!(templates.get("code-review").is_some());
+        let commands = templates.palette_commands();
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[0].action, Action::Slash("/template code-review".to_string()));
+
+        let mut asked = Vec::new();
+        let rendered = render(templates.get("summarize").unwrap(), |name| {
+            asked.push(name.to_string());
+            Ok(format!("<{}>\n", name))
+        })
+        .unwrap();
+        assert_eq!(rendered, "Summarize <output> from <cwd> for {{NAME}}");
+        assert_eq!(asked, ["output", "cwd"]);
+        assert!(render("{{clipboard}}", |_| bail!("no clipboard")).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Text measuring for the AI Terminal
+//!
+//! What the user sees as one character may be several `char`s: an accented
+//! letter written with a combining mark, a flag, or an emoji joined from
+//! several. Editing and layout work on grapheme clusters so such characters
+//! are deleted and moved over whole, and measure them in terminal columns so
+//! wide CJK characters and emoji take the two cells they are drawn in. The
+//! widths are those ratatui draws with, so the cursor and the wrapping agree
+//! with what is on screen.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Iterate over the grapheme clusters of a text
+pub fn graphemes(text: &str) -> impl DoubleEndedIterator<Item = &str> {
+    text.graphemes(true)
+}
+
+/// Get the columns a grapheme cluster or text takes on screen
+pub fn width(text: &str) -> usize {
+    graphemes(text).map(UnicodeWidthStr::width).sum()
+}
+
+/// Count the grapheme clusters of a text
+pub fn grapheme_count(text: &str) -> usize {
+    graphemes(text).count()
+}
+
+/// Remove the last grapheme cluster of a text, returning whether there was one
+pub fn pop_grapheme(text: &mut String) -> bool {
+    let Some(len) = graphemes(text).next_back().map(str::len) else {
+        return false;
+    };
+    text.truncate(text.len() - len);
+    true
+}
+
+/// Get the longest start of a text that fits in `columns`
+pub fn truncate_to_width(text: &str, columns: usize) -> &str {
+    let mut used = 0;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > columns {
+            return &text[..offset];
+        }
+    }
+    text
+}
+
+/// Get the longest end of a text that fits in `columns`
+pub fn tail_to_width(text: &str, columns: usize) -> &str {
+    let mut used = 0;
+    for (offset, grapheme) in text.grapheme_indices(true).rev() {
+        used += grapheme.width();
+        if used > columns {
+            return &text[offset + grapheme.len()..];
+        }
+    }
+    text
+}
+
+/// Get the part of a text drawn from column `from` up to column `to`
+///
+/// A wide character is included if it starts inside the range.
+pub fn slice_columns(text: &str, from: usize, to: usize) -> &str {
+    let mut column = 0;
+    let (mut start, mut end) = (text.len(), text.len());
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        if column >= to {
+            end = offset;
+            break;
+        }
+        if column >= from && start == text.len() {
+            start = offset;
+        }
+        column += grapheme.width();
+    }
+    &text[start.min(end)..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphemes_and_widths() {
+        // "e" with a combining acute accent, a family emoji joined with ZWJs, and CJK
+        let text = "e\u{301}👨\u{200d}👩\u{200d}👧漢字";
+        assert_eq!(grapheme_count(text), 4);
+        assert_eq!(width("漢字"), 4);
+        assert_eq!(width("e\u{301}"), 1);
+
+        let mut typed = text.to_string();
+        assert!(pop_grapheme(&mut typed));
+        assert!(pop_grapheme(&mut typed));
+        assert_eq!(typed, "e\u{301}👨\u{200d}👩\u{200d}👧");
+        assert!(pop_grapheme(&mut typed));
+        assert_eq!(typed, "e\u{301}");
+
+        assert_eq!(truncate_to_width("漢字abc", 3), "漢");
+        assert_eq!(tail_to_width("abc漢字", 5), "c漢字");
+        assert_eq!(tail_to_width("ab", 5), "ab");
+        assert_eq!(slice_columns("ab漢字cd", 2, 6), "漢字");
+        assert_eq!(slice_columns("abc", 1, 10), "bc");
+        assert_eq!(slice_columns("abc", 5, 10), "");
+    }
+}
@@ -0,0 +1,228 @@
+//! Color-vision accessibility for the AI Terminal
+//!
+//! Color alone must never be the only way to tell states apart. Every
+//! [`Signal`] pairs its theme color with a glyph and a text modifier, so
+//! success and failure, file changes and risk warnings stay distinguishable
+//! with any palette (diff lines already carry their `+` and `-` prefixes).
+//! Three built-in themes use palettes chosen for the common kinds of color
+//! vision deficiency; [`AccessibilityConfig`] selects one at startup.
+//!
+//! Reduced motion slows background redraws and shows progress in coarse
+//! steps, for users who are distracted by movement and for slow SSH links
+//! where frequent redraws tear. AI responses are never streamed token by
+//! token, so there is no typing animation to turn off.
+
+use std::time::Duration;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+use terminal_emulator::BlockState;
+use terminal_emulator::impact::ChangeKind;
+
+use crate::theme::Theme;
+
+/// A state distinction shown to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Finished successfully
+    Success,
+    /// Finished with an error
+    Failure,
+    /// Still running
+    Running,
+    /// Stopped before finishing
+    Cancelled,
+    /// A path was created
+    Created,
+    /// A path was removed
+    Removed,
+    /// A path was modified
+    Modified,
+    /// An action that may be hard to undo
+    Risk,
+}
+
+impl Signal {
+    /// Signal for the state of a block, if it has finished or is running
+    pub fn for_state(state: BlockState) -> Option<Self> {
+        match state {
+            BlockState::Success => Some(Signal::Success),
+            BlockState::Failed => Some(Signal::Failure),
+            BlockState::Running => Some(Signal::Running),
+            BlockState::Cancelled | BlockState::TimedOut => Some(Signal::Cancelled),
+            BlockState::Editing => None,
+        }
+    }
+
+    /// Signal for a change to a path
+    pub fn for_change(kind: ChangeKind) -> Self {
+        match kind {
+            ChangeKind::Created => Signal::Created,
+            ChangeKind::Removed => Signal::Removed,
+            ChangeKind::Modified => Signal::Modified,
+        }
+    }
+
+    /// Glyph shown with the signal, distinct for every signal
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Signal::Success => "✓",
+            Signal::Failure => "✗",
+            Signal::Running => "…",
+            Signal::Cancelled => "⊘",
+            Signal::Created => "+",
+            Signal::Removed => "-",
+            Signal::Modified => "~",
+            Signal::Risk => "⚠",
+        }
+    }
+
+    /// Style of the signal: the theme color plus a modifier as a second cue
+    pub fn style(self, theme: &Theme) -> Style {
+        let (color, modifier) = match self {
+            Signal::Success => (theme.success, Modifier::empty()),
+            Signal::Failure => (theme.error, Modifier::BOLD),
+            Signal::Running => (theme.warning, Modifier::ITALIC),
+            Signal::Cancelled => (theme.secondary, Modifier::ITALIC),
+            Signal::Created => (theme.success, Modifier::empty()),
+            Signal::Removed => (theme.error, Modifier::CROSSED_OUT),
+            Signal::Modified => (theme.warning, Modifier::ITALIC),
+            Signal::Risk => (theme.warning, Modifier::BOLD),
+        };
+        Style::default().fg(color).add_modifier(modifier)
+    }
+}
+
+/// A kind of color vision deficiency with a built-in theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorVision {
+    /// Reduced sensitivity to green
+    Deuteranopia,
+    /// Reduced sensitivity to red
+    Protanopia,
+    /// Reduced sensitivity to blue
+    Tritanopia,
+}
+
+impl ColorVision {
+    /// Name of the built-in theme for this color vision
+    pub fn theme_name(self) -> &'static str {
+        match self {
+            ColorVision::Deuteranopia => "deuteranopia",
+            ColorVision::Protanopia => "protanopia",
+            ColorVision::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// Build the theme for this color vision
+    pub fn theme(self) -> Theme {
+        // Okabe-Ito colors for red-green deficiencies, Paul Tol's for blue-yellow
+        let (success, error, warning, accent, command) = match self {
+            ColorVision::Deuteranopia => (rgb(0x0072B2), rgb(0xE69F00), rgb(0xF0E442), rgb(0x56B4E9), rgb(0xCC79A7)),
+            ColorVision::Protanopia => (rgb(0x56B4E9), rgb(0xD55E00), rgb(0xF0E442), rgb(0x0072B2), rgb(0xCC79A7)),
+            ColorVision::Tritanopia => (rgb(0x009988), rgb(0xCC3311), rgb(0xEE3377), rgb(0x33BBEE), rgb(0xEE7733)),
+        };
+        Theme {
+            name: self.theme_name().to_string(),
+            primary: accent,
+            secondary: Color::Gray,
+            background: Color::Black,
+            text: Color::White,
+            accent,
+            error,
+            success,
+            warning,
+            command,
+            ai_response: Color::White,
+            styles: Default::default(),
+        }
+        .with_derived_styles()
+    }
+}
+
+/// Time between redraws for background updates
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time between redraws for background updates with reduced motion
+pub const REDUCED_MOTION_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Accessibility settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    /// Use the built-in theme for this color vision, overriding the saved theme
+    pub color_vision: Option<ColorVision>,
+
+    /// Redraw less often and move progress indicators in coarse steps
+    pub reduced_motion: bool,
+}
+
+impl AccessibilityConfig {
+    /// How long to wait for input before redrawing for background updates
+    pub fn frame_interval(&self) -> Duration {
+        if self.reduced_motion { REDUCED_MOTION_FRAME_INTERVAL } else { FRAME_INTERVAL }
+    }
+
+    /// Round a progress fraction down to tenths when motion is reduced
+    pub fn progress_step(&self, fraction: f64) -> f64 {
+        if self.reduced_motion { (fraction * 10.0).floor() / 10.0 } else { fraction }
+    }
+}
+
+/// Build a color from a `0xRRGGBB` value
+fn rgb(value: u32) -> Color {
+    Color::Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals_differ_without_color() {
+        let all = [
+            Signal::Success,
+            Signal::Failure,
+            Signal::Running,
+            Signal::Cancelled,
+            Signal::Created,
+            Signal::Removed,
+            Signal::Modified,
+            Signal::Risk,
+        ];
+        let theme = Theme::default();
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                assert_ne!(a.glyph(), b.glyph(), "{:?} and {:?} share a glyph", a, b);
+            }
+        }
+        // Opposite outcomes differ in texture as well as glyph
+        assert_ne!(Signal::Success.style(&theme).add_modifier, Signal::Failure.style(&theme).add_modifier);
+        assert_ne!(Signal::Created.style(&theme).add_modifier, Signal::Removed.style(&theme).add_modifier);
+    }
+
+    #[test]
+    fn test_color_vision_themes() {
+        for vision in [ColorVision::Deuteranopia, ColorVision::Protanopia, ColorVision::Tritanopia] {
+            let theme = vision.theme();
+            assert_eq!(theme.name, vision.theme_name());
+            assert_ne!(theme.success, theme.error);
+        }
+        let config: AccessibilityConfig = toml::from_str("color_vision = \"tritanopia\"").unwrap();
+        assert_eq!(config.color_vision, Some(ColorVision::Tritanopia));
+        assert!(!config.reduced_motion);
+    }
+
+    #[test]
+    fn test_reduced_motion() {
+        let config = AccessibilityConfig::default();
+        assert_eq!(config.frame_interval(), FRAME_INTERVAL);
+        assert_eq!(config.progress_step(0.47), 0.47);
+
+        let reduced: AccessibilityConfig = toml::from_str("reduced_motion = true").unwrap();
+        assert_eq!(reduced.frame_interval(), REDUCED_MOTION_FRAME_INTERVAL);
+        assert_eq!(reduced.progress_step(0.47), 0.4);
+        assert_eq!(reduced.progress_step(1.0), 1.0);
+    }
+}
@@ -1,12 +1,15 @@
 //! Theme manager for the AI Terminal
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 
+use ratatui::style::{Modifier, Style};
 use serde::{Deserialize, Serialize};
 
 use crate::theme::presets::Theme;
+use crate::theme::accessibility::ColorVision;
+use crate::theme::styles::SemanticStyles;
 
 /// Error types for theme operations
 #[derive(Debug)]
@@ -16,6 +19,7 @@ pub enum ThemeError {
     SerializeError(toml::ser::Error),
     ThemeNotFound(String),
     InvalidColor(String),
+    InvalidStyle(String),
 }
 
 impl std::fmt::Display for ThemeError {
@@ -26,6 +30,7 @@ impl std::fmt::Display for ThemeError {
             ThemeError::SerializeError(e) => write!(f, "Serialize error: {}", e),
             ThemeError::ThemeNotFound(name) => write!(f, "Theme not found: {}", name),
             ThemeError::InvalidColor(color) => write!(f, "Invalid color: {}", color),
+            ThemeError::InvalidStyle(style) => write!(f, "Invalid style: {}", style),
         }
     }
 }
@@ -38,6 +43,7 @@ impl std::error::Error for ThemeError {
             ThemeError::SerializeError(e) => Some(e),
             ThemeError::ThemeNotFound(_) => None,
             ThemeError::InvalidColor(_) => None,
+            ThemeError::InvalidStyle(_) => None,
         }
     }
 }
@@ -68,6 +74,37 @@ struct ThemeConfig {
     warning: String,
     command: String,
     ai_response: String,
+    /// Overrides for semantic style groups, keyed by group name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    styles: BTreeMap<String, StyleConfig>,
+}
+
+/// A single semantic style in a theme file
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct StyleConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bg: Option<String>,
+    /// Text modifiers such as "bold", "italic" or "underlined"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    modifiers: Vec<String>,
+}
+
+/// Modifier names accepted in theme files
+const MODIFIER_NAMES: [(&str, Modifier); 6] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underlined", Modifier::UNDERLINED),
+    ("reversed", Modifier::REVERSED),
+    ("crossed_out", Modifier::CROSSED_OUT),
+];
+
+/// Persisted theme selection, stored alongside the user themes
+#[derive(Debug, Deserialize, Serialize)]
+struct ThemeSelection {
+    theme: String,
 }
 
 /// Manages themes for the terminal UI
@@ -75,11 +112,22 @@ pub struct ThemeManager {
     current_theme: Theme,
     available_themes: HashMap<String, Theme>,
     config_dir: Option<String>,
+    revision: u64,
 }
 
 impl ThemeManager {
     /// Create a new theme manager with default themes
     pub fn new() -> Self {
+        // Try to get config directory
+        let config_dir = dirs::config_dir()
+            .map(|path| path.join("ai-terminal"))
+            .and_then(|path| path.to_str().map(|s| s.to_string()));
+        
+        Self::with_config_dir(config_dir)
+    }
+    
+    /// Create a new theme manager that reads and writes its files in `config_dir`
+    pub fn with_config_dir(config_dir: Option<String>) -> Self {
         let mut themes = HashMap::new();
         
         let default_theme = Theme::default();
@@ -92,15 +140,17 @@ impl ThemeManager {
         themes.insert(light_theme.name.clone(), light_theme.clone());
         themes.insert(high_contrast_theme.name.clone(), high_contrast_theme.clone());
         
-        // Try to get config directory
-        let config_dir = dirs::config_dir()
-            .map(|path| path.join("ai-terminal"))
-            .and_then(|path| path.to_str().map(|s| s.to_string()));
+        // Palettes for color vision deficiencies
+        for vision in [ColorVision::Deuteranopia, ColorVision::Protanopia, ColorVision::Tritanopia] {
+            let theme = vision.theme();
+            themes.insert(theme.name.clone(), theme);
+        }
         
         Self {
             current_theme: default_theme,
             available_themes: themes,
             config_dir,
+            revision: 0,
         }
     }
     
@@ -120,7 +170,7 @@ impl ThemeManager {
                     let entry = entry?;
                     let path = entry.path();
                     
-                    if path.extension().map_or(false, |ext| ext == "toml") {
+                    if path.extension().is_some_and(|ext| ext == "toml") {
                         self.load_from_file(path.to_str().unwrap_or(""))?;
                     }
                 }
@@ -136,7 +186,7 @@ impl ThemeManager {
         let config: ThemeConfig = toml::from_str(&content)?;
         
         // Convert string colors to ratatui colors
-        let theme = Theme {
+        let mut theme = Theme {
             name: config.name,
             primary: Self::parse_color(&config.primary)?,
             secondary: Self::parse_color(&config.secondary)?,
@@ -148,10 +198,20 @@ impl ThemeManager {
             warning: Self::parse_color(&config.warning)?,
             command: Self::parse_color(&config.command)?,
             ai_response: Self::parse_color(&config.ai_response)?,
-        };
+            styles: SemanticStyles::default(),
+        }.with_derived_styles();
+        
+        // Apply semantic style overrides on top of the derived styles
+        for (name, style_config) in &config.styles {
+            let style = Self::parse_style(style_config)?;
+            if !theme.styles.set(name, style) {
+                return Err(ThemeError::InvalidStyle(name.clone()));
+            }
+        }
         
         self.available_themes.insert(theme.name.clone(), theme.clone());
         self.current_theme = theme;
+        self.revision += 1;
         
         Ok(())
     }
@@ -177,11 +237,18 @@ impl ThemeManager {
                 warning: Self::color_to_string(self.current_theme.warning),
                 command: Self::color_to_string(self.current_theme.command),
                 ai_response: Self::color_to_string(self.current_theme.ai_response),
+                styles: SemanticStyles::NAMES
+                    .iter()
+                    .filter_map(|name| {
+                        let style = self.current_theme.styles.get(name)?;
+                        Some((name.to_string(), Self::style_to_config(style)))
+                    })
+                    .collect(),
             };
             
             // Serialize to TOML
             let toml_string = toml::to_string(&config)
-                .map_err(|e| ThemeError::SerializeError(e))?;
+                .map_err(ThemeError::SerializeError)?;
             
             // Write to file
             let file_path = themes_dir.join(format!("{}.toml", name));
@@ -196,8 +263,43 @@ impl ThemeManager {
         }
     }
     
+    /// Persist the name of the selected theme so it is restored on next start
+    pub fn persist_selection(&self, name: &str) -> Result<(), ThemeError> {
+        let config_dir = self.config_dir.as_ref().ok_or_else(|| {
+            ThemeError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Config directory not found",
+            ))
+        })?;
+        
+        fs::create_dir_all(config_dir)?;
+        
+        let selection = ThemeSelection { theme: name.to_string() };
+        let toml_string = toml::to_string(&selection)
+            .map_err(ThemeError::SerializeError)?;
+        fs::write(Path::new(config_dir).join("theme.toml"), toml_string)?;
+        
+        Ok(())
+    }
+    
+    /// Switch to the theme persisted by `persist_selection`, if any
+    pub fn load_saved_selection(&mut self) -> Result<(), ThemeError> {
+        let Some(config_dir) = &self.config_dir else {
+            return Ok(());
+        };
+        
+        let path = Path::new(config_dir).join("theme.toml");
+        if !path.exists() {
+            return Ok(());
+        }
+        
+        let content = fs::read_to_string(path)?;
+        let selection: ThemeSelection = toml::from_str(&content)?;
+        self.switch_theme(&selection.theme)
+    }
+    
     /// Parse a color string into a ratatui Color
-    fn parse_color(color_str: &str) -> Result<ratatui::style::Color, ThemeError> {
+    pub fn parse_color(color_str: &str) -> Result<ratatui::style::Color, ThemeError> {
         match color_str.to_lowercase().as_str() {
             "black" => Ok(ratatui::style::Color::Black),
             "red" => Ok(ratatui::style::Color::Red),
@@ -215,15 +317,26 @@ impl ThemeManager {
             "lightmagenta" => Ok(ratatui::style::Color::LightMagenta),
             "lightcyan" => Ok(ratatui::style::Color::LightCyan),
             "white" => Ok(ratatui::style::Color::White),
+            "reset" => Ok(ratatui::style::Color::Reset),
             _ => {
-                // Try to parse as RGB hex color (#RRGGBB)
-                if color_str.starts_with('#') && color_str.len() == 7 {
-                    let hex = &color_str[1..];
-                    if let (Ok(r), Ok(g), Ok(b)) = (
-                        u8::from_str_radix(&hex[0..2], 16),
-                        u8::from_str_radix(&hex[2..4], 16),
-                        u8::from_str_radix(&hex[4..6], 16),
-                    ) {
+                // Try to parse as a 256-color palette index (0-255)
+                if let Ok(index) = color_str.parse::<u8>() {
+                    return Ok(ratatui::style::Color::Indexed(index));
+                }
+                
+                // Try to parse as a truecolor hex color (#RRGGBB or #RGB)
+                if let Some(hex) = color_str.strip_prefix('#').filter(|hex| hex.is_ascii()) {
+                    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+                    let rgb = match hex.len() {
+                        6 => (channel(&hex[0..2]), channel(&hex[2..4]), channel(&hex[4..6])),
+                        3 => (
+                            channel(&hex[0..1]).map(|c| c * 17),
+                            channel(&hex[1..2]).map(|c| c * 17),
+                            channel(&hex[2..3]).map(|c| c * 17),
+                        ),
+                        _ => (None, None, None),
+                    };
+                    if let (Some(r), Some(g), Some(b)) = rgb {
                         return Ok(ratatui::style::Color::Rgb(r, g, b));
                     }
                 }
@@ -252,7 +365,43 @@ impl ThemeManager {
             ratatui::style::Color::LightCyan => "lightcyan".to_string(),
             ratatui::style::Color::White => "white".to_string(),
             ratatui::style::Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
-            _ => "white".to_string(), // Default to white for unknown colors
+            ratatui::style::Color::Indexed(index) => index.to_string(),
+            ratatui::style::Color::Reset => "reset".to_string(),
+        }
+    }
+    
+    /// Parse a style from its theme file representation
+    fn parse_style(config: &StyleConfig) -> Result<Style, ThemeError> {
+        let mut style = Style::default();
+        
+        if let Some(fg) = &config.fg {
+            style = style.fg(Self::parse_color(fg)?);
+        }
+        if let Some(bg) = &config.bg {
+            style = style.bg(Self::parse_color(bg)?);
+        }
+        for name in &config.modifiers {
+            let modifier = MODIFIER_NAMES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                .map(|(_, modifier)| *modifier)
+                .ok_or_else(|| ThemeError::InvalidStyle(name.clone()))?;
+            style = style.add_modifier(modifier);
+        }
+        
+        Ok(style)
+    }
+    
+    /// Convert a style to its theme file representation
+    fn style_to_config(style: Style) -> StyleConfig {
+        StyleConfig {
+            fg: style.fg.map(Self::color_to_string),
+            bg: style.bg.map(Self::color_to_string),
+            modifiers: MODIFIER_NAMES
+                .iter()
+                .filter(|(_, modifier)| style.add_modifier.contains(*modifier))
+                .map(|(name, _)| name.to_string())
+                .collect(),
         }
     }
     
@@ -261,10 +410,16 @@ impl ThemeManager {
         &self.current_theme
     }
     
+    /// Get the revision of the current theme, which changes whenever the theme does
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+    
     /// Switch to a different theme
     pub fn switch_theme(&mut self, theme_name: &str) -> Result<(), ThemeError> {
         if let Some(theme) = self.available_themes.get(theme_name) {
             self.current_theme = theme.clone();
+            self.revision += 1;
             Ok(())
         } else {
             Err(ThemeError::ThemeNotFound(theme_name.to_string()))
@@ -282,9 +437,16 @@ impl ThemeManager {
     }
 }
 
+impl Default for ThemeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ratatui::style::Color;
     
     #[test]
     fn test_theme_manager_creation() {
@@ -301,4 +463,81 @@ mod tests {
         
         assert!(manager.switch_theme("nonexistent").is_err());
     }
+    
+    #[test]
+    fn test_parse_extended_colors() {
+        assert_eq!(ThemeManager::parse_color("#1e2030").unwrap(), Color::Rgb(0x1e, 0x20, 0x30));
+        assert_eq!(ThemeManager::parse_color("#fa0").unwrap(), Color::Rgb(0xff, 0xaa, 0x00));
+        assert_eq!(ThemeManager::parse_color("236").unwrap(), Color::Indexed(236));
+        assert!(ThemeManager::parse_color("256").is_err());
+        assert!(ThemeManager::parse_color("#12345").is_err());
+        
+        for color in [Color::Rgb(1, 2, 3), Color::Indexed(42), Color::Reset, Color::LightCyan] {
+            let text = ThemeManager::color_to_string(color);
+            assert_eq!(ThemeManager::parse_color(&text).unwrap(), color);
+        }
+    }
+    
+    #[test]
+    fn test_style_round_trip() {
+        let style = Style::default()
+            .fg(Color::Rgb(200, 100, 0))
+            .bg(Color::Indexed(17))
+            .add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let config = ThemeManager::style_to_config(style);
+        assert_eq!(config.modifiers, vec!["bold", "italic"]);
+        assert_eq!(ThemeManager::parse_style(&config).unwrap(), style);
+    }
+    
+    #[test]
+    fn test_load_theme_with_style_overrides() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-style-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ocean.toml");
+        fs::write(&path, r##"
+name = "ocean"
+primary = "#268bd2"
+secondary = "244"
+background = "#002b36"
+text = "#eee8d5"
+accent = "cyan"
+error = "#dc322f"
+success = "#859900"
+warning = "#b58900"
+command = "#2aa198"
+ai_response = "#859900"
+
+[styles.heading_1]
+fg = "#cb4b16"
+modifiers = ["bold", "underlined"]
+"##).unwrap();
+        
+        let mut manager = ThemeManager::with_config_dir(None);
+        manager.load_from_file(path.to_str().unwrap()).unwrap();
+        let theme = manager.current_theme();
+        assert_eq!(theme.secondary, Color::Indexed(244));
+        assert_eq!(
+            theme.styles.heading_1,
+            Style::default().fg(Color::Rgb(0xcb, 0x4b, 0x16)).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+        // Groups without overrides are derived from the palette
+        assert_eq!(theme.styles.border, Style::default().fg(Color::Indexed(244)));
+        
+        let _ = fs::remove_dir_all(dir);
+    }
+    
+    #[test]
+    fn test_persist_and_restore_selection() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-theme-test-{}", std::process::id()));
+        let config_dir = dir.to_str().map(|s| s.to_string());
+        
+        let manager = ThemeManager::with_config_dir(config_dir.clone());
+        assert!(manager.persist_selection("light").is_ok());
+        
+        let mut restored = ThemeManager::with_config_dir(config_dir);
+        assert!(restored.load_saved_selection().is_ok());
+        assert_eq!(restored.current_theme().name, "light");
+        
+        let _ = fs::remove_dir_all(dir);
+    }
 }
\ No newline at end of file
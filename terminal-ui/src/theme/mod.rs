@@ -4,6 +4,10 @@
 
 pub mod presets;
 pub mod manager;
+pub mod styles;
+pub mod accessibility;
 
 pub use presets::Theme;
-pub use manager::ThemeManager;
\ No newline at end of file
+pub use manager::ThemeManager;
+pub use styles::SemanticStyles;
+pub use accessibility::{AccessibilityConfig, ColorVision, Signal};
\ No newline at end of file
@@ -2,6 +2,8 @@
 
 use ratatui::style::Color;
 
+use crate::theme::styles::SemanticStyles;
+
 /// Represents a color theme for the terminal UI
 #[derive(Debug, Clone)]
 pub struct Theme {
@@ -16,11 +18,13 @@ pub struct Theme {
     pub warning: Color,
     pub command: Color,
     pub ai_response: Color,
+    /// Semantic styles, derived from the palette unless overridden
+    pub styles: SemanticStyles,
 }
 
-impl Theme {
+impl Default for Theme {
     /// Create the default theme
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self {
             name: "default".to_string(),
             primary: Color::Blue,
@@ -33,7 +37,16 @@ impl Theme {
             warning: Color::Yellow,
             command: Color::Cyan,
             ai_response: Color::Green,
-        }
+            styles: SemanticStyles::default(),
+        }.with_derived_styles()
+    }
+}
+
+impl Theme {
+    /// Recompute the semantic styles from the palette colors
+    pub fn with_derived_styles(mut self) -> Self {
+        self.styles = SemanticStyles::derive(&self);
+        self
     }
     
     /// Create a dark theme
@@ -50,7 +63,8 @@ impl Theme {
             warning: Color::LightYellow,
             command: Color::Cyan,
             ai_response: Color::LightGreen,
-        }
+            styles: SemanticStyles::default(),
+        }.with_derived_styles()
     }
     
     /// Create a light theme
@@ -67,7 +81,8 @@ impl Theme {
             warning: Color::Yellow,
             command: Color::Blue,
             ai_response: Color::Green,
-        }
+            styles: SemanticStyles::default(),
+        }.with_derived_styles()
     }
     
     /// Create a high contrast theme
@@ -84,7 +99,8 @@ impl Theme {
             warning: Color::Yellow,
             command: Color::Magenta,
             ai_response: Color::Blue,
-        }
+            styles: SemanticStyles::default(),
+        }.with_derived_styles()
     }
 }
 
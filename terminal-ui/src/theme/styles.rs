@@ -0,0 +1,191 @@
+//! Semantic style groups for the AI Terminal
+//!
+//! Themes define a small palette of named colors. Widgets should not pick
+//! colors from that palette directly; instead they use the semantic styles
+//! below, which a theme file can override individually (including
+//! modifiers such as bold or italic).
+
+use ratatui::style::{Modifier, Style};
+
+use crate::theme::Theme;
+
+/// Styles for every semantic UI element
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SemanticStyles {
+    /// Border of unfocused panes and popups
+    pub border: Style,
+    /// Border of the focused pane
+    pub border_focused: Style,
+    /// Highlighted row in lists and selected blocks
+    pub selection: Style,
+    /// Inline suggestions rendered after the cursor
+    pub ghost_text: Style,
+    /// Added lines in diffs
+    pub diff_added: Style,
+    /// Removed lines in diffs
+    pub diff_removed: Style,
+    /// Markdown level 1 heading
+    pub heading_1: Style,
+    /// Markdown level 2 heading
+    pub heading_2: Style,
+    /// Markdown level 3+ headings
+    pub heading_3: Style,
+    /// Fenced code blocks
+    pub code_block: Style,
+    /// Inline `code` spans
+    pub inline_code: Style,
+    /// Markdown hyperlinks
+    pub link: Style,
+    /// Gutter drawn beside Markdown blockquotes
+    pub blockquote: Style,
+    /// Base style of the status bar
+    pub status_bar: Style,
+    /// Mode segment at the start of the status bar
+    pub status_mode: Style,
+    /// Informational segments of the status bar
+    pub status_info: Style,
+}
+
+impl SemanticStyles {
+    /// Names of all style groups, as used in theme files
+    pub const NAMES: [&'static str; 16] = [
+        "border",
+        "border_focused",
+        "selection",
+        "ghost_text",
+        "diff_added",
+        "diff_removed",
+        "heading_1",
+        "heading_2",
+        "heading_3",
+        "code_block",
+        "inline_code",
+        "link",
+        "blockquote",
+        "status_bar",
+        "status_mode",
+        "status_info",
+    ];
+
+    /// Derive sensible styles from a theme's base palette
+    pub fn derive(theme: &Theme) -> Self {
+        let Theme { primary, secondary, background, text, accent, error, success, warning, .. } = *theme;
+
+        Self {
+            border: Style::default().fg(secondary),
+            border_focused: Style::default().fg(primary),
+            selection: Style::default().bg(primary).fg(background),
+            ghost_text: Style::default().fg(secondary).add_modifier(Modifier::ITALIC),
+            diff_added: Style::default().fg(success),
+            diff_removed: Style::default().fg(error),
+            heading_1: Style::default().fg(primary).add_modifier(Modifier::BOLD),
+            heading_2: Style::default().fg(accent).add_modifier(Modifier::BOLD),
+            heading_3: Style::default().fg(accent),
+            code_block: Style::default().fg(text),
+            inline_code: Style::default().fg(warning),
+            link: Style::default().fg(accent).add_modifier(Modifier::UNDERLINED),
+            blockquote: Style::default().fg(secondary),
+            status_bar: Style::default().bg(background).fg(secondary),
+            status_mode: Style::default().bg(primary).fg(background).add_modifier(Modifier::BOLD),
+            status_info: Style::default().fg(accent),
+        }
+    }
+
+    /// Look up a style group by its theme file name
+    pub fn get(&self, name: &str) -> Option<Style> {
+        self.slot(name).copied()
+    }
+
+    /// Replace a style group by its theme file name
+    pub fn set(&mut self, name: &str, style: Style) -> bool {
+        match self.slot_mut(name) {
+            Some(slot) => {
+                *slot = style;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the style for a markdown heading level (1-based)
+    pub fn heading(&self, level: usize) -> Style {
+        match level {
+            1 => self.heading_1,
+            2 => self.heading_2,
+            _ => self.heading_3,
+        }
+    }
+
+    fn slot(&self, name: &str) -> Option<&Style> {
+        Some(match name {
+            "border" => &self.border,
+            "border_focused" => &self.border_focused,
+            "selection" => &self.selection,
+            "ghost_text" => &self.ghost_text,
+            "diff_added" => &self.diff_added,
+            "diff_removed" => &self.diff_removed,
+            "heading_1" => &self.heading_1,
+            "heading_2" => &self.heading_2,
+            "heading_3" => &self.heading_3,
+            "code_block" => &self.code_block,
+            "inline_code" => &self.inline_code,
+            "link" => &self.link,
+            "blockquote" => &self.blockquote,
+            "status_bar" => &self.status_bar,
+            "status_mode" => &self.status_mode,
+            "status_info" => &self.status_info,
+            _ => return None,
+        })
+    }
+
+    fn slot_mut(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "border" => &mut self.border,
+            "border_focused" => &mut self.border_focused,
+            "selection" => &mut self.selection,
+            "ghost_text" => &mut self.ghost_text,
+            "diff_added" => &mut self.diff_added,
+            "diff_removed" => &mut self.diff_removed,
+            "heading_1" => &mut self.heading_1,
+            "heading_2" => &mut self.heading_2,
+            "heading_3" => &mut self.heading_3,
+            "code_block" => &mut self.code_block,
+            "inline_code" => &mut self.inline_code,
+            "link" => &mut self.link,
+            "blockquote" => &mut self.blockquote,
+            "status_bar" => &mut self.status_bar,
+            "status_mode" => &mut self.status_mode,
+            "status_info" => &mut self.status_info,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ratatui::style::Color;
+
+    fn sample() -> SemanticStyles {
+        SemanticStyles::derive(&Theme::dark())
+    }
+
+    #[test]
+    fn test_every_name_resolves() {
+        let styles = sample();
+        for name in SemanticStyles::NAMES {
+            assert!(styles.get(name).is_some(), "missing style {}", name);
+        }
+        assert!(styles.get("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_set_style() {
+        let mut styles = sample();
+        let style = Style::default().fg(Color::Rgb(1, 2, 3)).add_modifier(Modifier::ITALIC);
+        assert!(styles.set("heading_2", style));
+        assert_eq!(styles.heading(2), style);
+        assert!(!styles.set("nonexistent", style));
+    }
+}
@@ -0,0 +1,159 @@
+//! Tool calling for the AI Terminal
+//!
+//! External tools, such as those published by MCP servers, are offered to the
+//! model as function definitions on backends with native tool calling, such
+//! as Ollama, which report the calls the model makes. Other backends get the
+//! tools described in the system prompt instead, and the model calls one by
+//! replying with a fenced `tool` block holding a JSON object with `name` and
+//! `arguments`. Either way the terminal runs the call, shows the result as a
+//! tool block and sends the result back to the model, which may call further
+//! tools before answering, up to [`MAX_TOOL_ROUNDS`] rounds per prompt.
+
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use ollama_client::{FunctionCall, ToolDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Most rounds of tool calls made while answering one prompt
+pub const MAX_TOOL_ROUNDS: usize = 4;
+
+/// Longest tool result sent back to the model, in characters
+const MAX_RESULT_CHARS: usize = 8000;
+
+/// A tool the model may call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSpec {
+    /// Name the model calls the tool by
+    pub name: String,
+    /// What the tool does
+    pub description: String,
+    /// JSON schema of the arguments
+    pub input_schema: Value,
+}
+
+/// What a tool call returned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolOutput {
+    /// Text of the result
+    pub text: String,
+    /// Whether the tool reported a failure
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    /// A failed call, with the error as its text
+    pub fn error(error: anyhow::Error) -> Self {
+        Self {
+            text: format!("{:#}", error),
+            is_error: true,
+        }
+    }
+}
+
+/// A tool call emitted by the model
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ToolCall {
+    /// Name of the tool
+    pub name: String,
+    /// Arguments matching the tool's schema
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl From<FunctionCall> for ToolCall {
+    fn from(call: FunctionCall) -> Self {
+        Self { name: call.name, arguments: call.arguments }
+    }
+}
+
+/// A source of tools, such as a set of connected MCP servers
+pub trait ToolProvider: Send {
+    /// Get the tools on offer
+    fn tools(&self) -> &[ToolSpec];
+
+    /// Call a tool by name
+    fn call<'a>(&'a mut self, name: &'a str, arguments: Value) -> BoxFuture<'a, Result<ToolOutput>>;
+}
+
+/// Define the tools as functions, for backends with native tool calling
+pub fn definitions(tools: &[ToolSpec]) -> Vec<ToolDefinition> {
+    tools
+        .iter()
+        .map(|tool| ToolDefinition::function(tool.name.clone(), tool.description.clone(), tool.input_schema.clone()))
+        .collect()
+}
+
+/// Describe the tools and how to call them, for the system prompt
+pub fn tool_prompt(tools: &[ToolSpec]) -> String {
+    let mut prompt = String::from(
+        "You can call tools. To call one, reply with only a fenced block tagged `tool` holding a JSON \
+         object with the tool's name and arguments, for example:\n\
+         ```tool\n{\"name\": \"example\", \"arguments\": {}}\n```\n\
+         The result will be sent back to you. Answer normally when no tool is needed.\n\nTools:\n",
+    );
+    for tool in tools {
+        prompt.push_str(&format!("- {}: {}\n  arguments: {}\n", tool.name, tool.description, tool.input_schema));
+    }
+    prompt
+}
+
+/// Find a tool call in a model response
+pub fn parse_tool_call(response: &str) -> Option<ToolCall> {
+    let start = response.find("```tool")? + "```tool".len();
+    let body = &response[start..];
+    let end = body.find("```")?;
+    serde_json::from_str(body[..end].trim()).ok()
+}
+
+/// Shorten the text of a tool result to what is sent back to the model
+fn shortened(output: &ToolOutput) -> String {
+    let mut text: String = output.text.chars().take(MAX_RESULT_CHARS).collect();
+    if text.len() < output.text.len() {
+        text.push_str("\n[… result truncated]");
+    }
+    text
+}
+
+/// Get the text of a tool result for the tool message following a native call
+pub fn result_text(output: &ToolOutput) -> String {
+    let text = shortened(output);
+    if output.is_error { format!("Error: {}", text) } else { text }
+}
+
+/// Build the prompt continuing a conversation after a tool call
+pub fn continue_prompt(prompt: &str, response: &str, output: &ToolOutput) -> String {
+    let text = shortened(output);
+    format!(
+        "{}\n\nAssistant:\n{}\n\nTool {}:\n```\n{}\n```\n\nContinue: call another tool or answer the original request.",
+        prompt.trim_end(),
+        response.trim(),
+        if output.is_error { "error" } else { "result" },
+        text,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_continue() {
+        let response = "Let me look.\n```tool\n{\"name\": \"files.read\", \"arguments\": {\"path\": \"a.txt\"}}\n```";
+        let call = parse_tool_call(response).unwrap();
+        assert_eq!(call.name, "files.read");
+        assert_eq!(call.arguments["path"], "a.txt");
+        assert_eq!(parse_tool_call("```tool\nnot json\n```"), None);
+        assert_eq!(parse_tool_call("```rust\nfn main() {}\n```"), None);
+
+        let output = ToolOutput { text: "x".repeat(MAX_RESULT_CHARS + 1), is_error: false };
+        let prompt = continue_prompt("Read a.txt", response, &output);
+        assert!(prompt.starts_with("Read a.txt\n\nAssistant:\nLet me look."));
+        assert!(prompt.contains("[… result truncated]"));
+
+        let tools = [ToolSpec { name: "files.read".to_string(), description: "Read a file".to_string(), input_schema: serde_json::json!({}) }];
+        assert!(tool_prompt(&tools).contains("- files.read: Read a file"));
+        assert_eq!(definitions(&tools)[0].function.name, "files.read");
+        assert_eq!(result_text(&ToolOutput { text: "denied".to_string(), is_error: true }), "Error: denied");
+    }
+}
@@ -0,0 +1,163 @@
+//! Directory trust for the AI Terminal
+//!
+//! Like workspace trust in editors, directories can be marked trusted or
+//! untrusted with `/trust`. A mark covers the directory and everything below
+//! it, the nearest mark winning, and unmarked directories follow the
+//! `untrusted_by_default` setting. In an untrusted directory the model's tool
+//! calls are refused and every command an agent proposes needs approval,
+//! even the read-only ones in `agent.auto_approve`. Marks are kept in
+//! `trust.toml` in the config directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Whether AI automation may act in a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustLevel {
+    /// Tools and auto-approved agent commands may run
+    Trusted,
+    /// Tools are refused and agent commands always ask
+    Untrusted,
+}
+
+impl TrustLevel {
+    /// Get the name shown to the user
+    pub fn name(self) -> &'static str {
+        match self {
+            TrustLevel::Trusted => "trusted",
+            TrustLevel::Untrusted => "untrusted",
+        }
+    }
+}
+
+/// Trust settings from the `[trust]` section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrustConfig {
+    /// Treat directories without a mark as untrusted
+    pub untrusted_by_default: bool,
+}
+
+/// Contents of `trust.toml`
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct TrustFile {
+    #[serde(default)]
+    directories: BTreeMap<PathBuf, TrustLevel>,
+}
+
+/// Trust marks by directory
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    directories: BTreeMap<PathBuf, TrustLevel>,
+    /// Level of directories without a mark
+    default: Option<TrustLevel>,
+    /// File the marks are saved to, if there is a config directory
+    path: Option<PathBuf>,
+}
+
+impl TrustStore {
+    /// Load the marks from the user's config directory
+    pub fn load() -> Result<Self> {
+        Self::with_path(dirs::config_dir().map(|dir| dir.join("ai-terminal").join("trust.toml")))
+    }
+
+    /// Load the marks from `path`, which need not exist yet
+    pub fn with_path(path: Option<PathBuf>) -> Result<Self> {
+        let file = match path.as_deref().filter(|path| path.exists()) {
+            Some(path) => {
+                let content = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+                toml::from_str(&content).with_context(|| format!("could not parse {}", path.display()))?
+            }
+            None => TrustFile::default(),
+        };
+        Ok(Self { directories: file.directories, default: None, path })
+    }
+
+    /// Apply the `[trust]` settings
+    pub fn configure(&mut self, config: TrustConfig) {
+        self.default = config.untrusted_by_default.then_some(TrustLevel::Untrusted);
+    }
+
+    /// Get the level of a directory and the marked directory it comes from, if any
+    pub fn level(&self, dir: &Path) -> (TrustLevel, Option<&Path>) {
+        dir.ancestors()
+            .find_map(|ancestor| self.directories.get_key_value(ancestor))
+            .map(|(marked, level)| (*level, Some(marked.as_path())))
+            .unwrap_or((self.default.unwrap_or(TrustLevel::Trusted), None))
+    }
+
+    /// Check whether AI automation may act in a directory
+    pub fn is_trusted(&self, dir: &Path) -> bool {
+        self.level(dir).0 == TrustLevel::Trusted
+    }
+
+    /// Mark a directory and save the marks
+    pub fn set(&mut self, dir: PathBuf, level: TrustLevel) -> Result<()> {
+        self.directories.insert(dir, level);
+        self.save()
+    }
+
+    /// Remove the mark of a directory and save the marks, returning whether it had one
+    pub fn forget(&mut self, dir: &Path) -> Result<bool> {
+        let removed = self.directories.remove(dir).is_some();
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// Get the marked directories
+    pub fn marks(&self) -> &BTreeMap<PathBuf, TrustLevel> {
+        &self.directories
+    }
+
+    /// Get the status bar segment for a directory: untrusted ones, and those trusted by a mark
+    pub fn status_segment(&self, dir: &Path) -> Option<&'static str> {
+        match self.level(dir) {
+            (TrustLevel::Untrusted, _) => Some(" ⚠ UNTRUSTED "),
+            (TrustLevel::Trusted, Some(_)) => Some(" ✓ TRUSTED "),
+            (TrustLevel::Trusted, None) => None,
+        }
+    }
+
+    /// Write the marks to `trust.toml`
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = TrustFile { directories: self.directories.clone() };
+        fs::write(path, toml::to_string(&file)?).with_context(|| format!("could not write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_cover_subdirectories_and_persist() {
+        let dir = std::env::temp_dir().join(format!("ai-terminal-trust-{}", std::process::id()));
+        let path = dir.join("trust.toml");
+        let mut store = TrustStore::with_path(Some(path.clone())).unwrap();
+        store.set(PathBuf::from("/work"), TrustLevel::Trusted).unwrap();
+        store.set(PathBuf::from("/work/downloads"), TrustLevel::Untrusted).unwrap();
+
+        let store = TrustStore::with_path(Some(path)).unwrap();
+        assert!(store.is_trusted(Path::new("/work/project")));
+        assert_eq!(store.level(Path::new("/work/downloads/repo")), (TrustLevel::Untrusted, Some(Path::new("/work/downloads"))));
+        assert_eq!(store.status_segment(Path::new("/elsewhere")), None);
+
+        let mut strict = store;
+        strict.configure(TrustConfig { untrusted_by_default: true });
+        assert!(!strict.is_trusted(Path::new("/elsewhere")));
+        assert!(strict.forget(Path::new("/work")).unwrap());
+        assert!(!strict.is_trusted(Path::new("/work/project")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
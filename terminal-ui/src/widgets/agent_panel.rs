@@ -0,0 +1,126 @@
+//! Agent plan panel widget for the AI Terminal
+//!
+//! While an agent works towards a goal, this panel sits over the top right
+//! of the panes and shows the goal, the plan with the step being worked on,
+//! and what the agent is waiting for.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::agent::{Agent, AgentStatus};
+use crate::theme::{Signal, Theme};
+
+/// Narrowest the panel gets
+const MIN_WIDTH: u16 = 30;
+
+/// Widest the panel gets
+const MAX_WIDTH: u16 = 60;
+
+/// Panel showing the plan of a working agent
+pub struct AgentPanel<'a> {
+    agent: &'a Agent,
+}
+
+impl<'a> AgentPanel<'a> {
+    /// Create a panel for an agent
+    pub fn new(agent: &'a Agent) -> Self {
+        Self { agent }
+    }
+
+    /// Get the area of the panel, docked to the top right of the pane area
+    pub fn area(&self, panes: Rect) -> Rect {
+        let width = (panes.width * 2 / 5).clamp(MIN_WIDTH, MAX_WIDTH).min(panes.width);
+        // Goal, blank line, plan and status, inside the border
+        let rows = self.agent.plan().len().max(1) as u16 + 5;
+        let height = rows.min(panes.height);
+        Rect::new(panes.right() - width, panes.y, width, height)
+    }
+
+    /// Build the lines of the panel
+    fn lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        let mut lines = vec![
+            Line::from(Span::styled(self.agent.goal().to_string(), Style::default().fg(theme.primary).add_modifier(Modifier::BOLD))),
+            Line::from(""),
+        ];
+        if self.agent.plan().is_empty() {
+            lines.push(Line::from(Span::styled("Planning…", theme.styles.ghost_text)));
+        }
+        let current = self.agent.current_step();
+        for (i, item) in self.agent.plan().iter().enumerate() {
+            let (marker, style) = match current {
+                Some(step) if i < step => (Signal::Success.glyph(), Signal::Success.style(theme)),
+                Some(step) if i == step => ("▶", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+                _ => ("·", Style::default().fg(theme.text)),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", marker), style),
+                Span::styled(format!("{}. {}", i + 1, item), style),
+            ]));
+        }
+
+        let status = match self.agent.status() {
+            AgentStatus::Thinking => "Thinking…".to_string(),
+            AgentStatus::AwaitingApproval(command) => format!("Waiting for approval: {}", command),
+            AgentStatus::Approved(command) => format!("Running: {}", command),
+            AgentStatus::Done(_) => "Done".to_string(),
+            AgentStatus::Stopped(reason) => format!("Stopped: {}", reason),
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(status, theme.styles.ghost_text)));
+        lines
+    }
+
+    /// Render the panel over the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, theme: &Theme) {
+        let area = self.area(panes);
+        f.render_widget(Clear, area);
+
+        let title = format!(
+            " 🤖 Agent · {}/{} commands · /agent stop ",
+            self.agent.steps().len(),
+            self.agent.max_steps(),
+        );
+        let panel = Paragraph::new(self.lines(theme))
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title(title)
+            );
+        f.render_widget(panel, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentReply;
+
+    #[test]
+    fn test_panel_docks_top_right() {
+        let mut agent = Agent::new("set up a venv", 10);
+        let reply = AgentReply {
+            plan: vec!["Make venv".to_string(), "Install deps".to_string()],
+            step: Some(2),
+            command: Some("pip install -r requirements.txt".to_string()),
+            ..Default::default()
+        };
+        agent.apply(reply);
+
+        let panel = AgentPanel::new(&agent);
+        assert_eq!(panel.area(Rect::new(0, 1, 100, 30)), Rect::new(60, 1, 40, 7));
+        // Narrow pane areas are covered entirely rather than overflowed
+        assert_eq!(panel.area(Rect::new(0, 1, 20, 4)), Rect::new(0, 1, 20, 4));
+
+        let lines = panel.lines(&Theme::default());
+        assert_eq!(lines[2].spans[0].content, "✓ ");
+        assert_eq!(lines[3].spans[0].content, "▶ ");
+    }
+}
@@ -0,0 +1,120 @@
+//! Chat pane widget for the AI Terminal
+//!
+//! Draws the conversation of the chat pane to the right of the panes: each
+//! message under a line saying who wrote it, answers rendered as Markdown,
+//! and sent blocks as their label. The newest messages are at the bottom,
+//! unless the conversation was scrolled back.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::chat_pane::{ChatPane, Role};
+use crate::markdown_renderer::render_markdown_document;
+use crate::render_cache::wrap_line;
+use crate::theme::{Signal, Theme};
+
+/// Panel showing the chat pane's conversation
+pub struct ChatPanel<'a> {
+    chat: &'a ChatPane,
+    model: &'a str,
+}
+
+impl<'a> ChatPanel<'a> {
+    /// Create a panel for a conversation held with `model`
+    pub fn new(chat: &'a ChatPane, model: &'a str) -> Self {
+        Self { chat, model }
+    }
+
+    /// Build the lines of the conversation, wrapped to `width` columns
+    pub fn lines(&self, width: u16, theme: &Theme) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        if self.chat.messages().is_empty() {
+            lines.push(Line::from(Span::styled(
+                "Ask the model anything; follow-up questions keep the conversation. /chat send [n] adds a block's output.",
+                theme.styles.ghost_text,
+            )));
+        }
+        for message in self.chat.messages() {
+            let bold = Modifier::BOLD;
+            match message.role {
+                Role::User => lines.push(Line::from(Span::styled("You", Style::default().fg(theme.accent).add_modifier(bold)))),
+                Role::Assistant => lines.push(Line::from(Span::styled(
+                    format!("🤖 {}", message.model.as_deref().unwrap_or(self.model)),
+                    Style::default().fg(theme.primary).add_modifier(bold),
+                ))),
+                Role::Error => lines.push(Line::from(Span::styled(
+                    format!("{} {}", Signal::Failure.glyph(), message.text),
+                    Signal::Failure.style(theme),
+                ))),
+            }
+            if let Some(label) = &message.label {
+                lines.push(Line::from(vec![
+                    "  ".into(),
+                    Span::styled(format!("📎 {} · {} lines", label, message.text.lines().count()), theme.styles.ghost_text),
+                ]));
+            } else if message.role != Role::Error {
+                for mut line in render_markdown_document(&message.text, theme).lines {
+                    line.spans.insert(0, Span::raw("  "));
+                    lines.push(line);
+                }
+            }
+            lines.push(Line::from(""));
+        }
+        lines.iter().flat_map(|line| wrap_line(line, width)).collect()
+    }
+
+    /// Render the panel in its area, returning the rows in view and the rows of the whole conversation
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) -> (usize, usize) {
+        let border = if self.chat.is_focused() { theme.styles.border_focused } else { theme.styles.border };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border)
+            .title(format!(" 💬 Chat · {} ", self.model));
+        let inner = block.inner(area);
+        let lines = self.lines(inner.width, theme);
+        let (page, total) = (inner.height as usize, lines.len());
+        let back = self.chat.scroll_back().min(lines.len().saturating_sub(page));
+        let top = lines.len().saturating_sub(page + back);
+        let block = if back > 0 {
+            block.title_bottom(Line::from(format!(" ↓ {} more line(s) ", back)).right_aligned())
+        } else {
+            block
+        };
+        let shown: Vec<Line> = lines.into_iter().skip(top).take(page).collect();
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(shown).style(Style::default().bg(theme.background).fg(theme.text)).block(block),
+            area,
+        );
+        (page, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_lines() {
+        let theme = Theme::default();
+        let mut chat = ChatPane::default();
+        assert_eq!(ChatPanel::new(&chat, "llama3").lines(40, &theme).len(), 3);
+
+        chat.attach("Output of cargo test".to_string(), "a\nb\n".to_string());
+        chat.ask("Why did it fail?".to_string());
+        chat.reply("llama3".to_string(), "Because **b**.".to_string());
+        chat.fail("connection refused".to_string());
+        let lines: Vec<String> = ChatPanel::new(&chat, "llama3").lines(40, &theme).iter().map(Line::to_string).collect();
+        assert_eq!(lines[0], "You");
+        assert_eq!(lines[1], "  📎 Output of cargo test · 2 lines");
+        assert_eq!(lines[4], "  Why did it fail?");
+        assert_eq!(lines[6], "🤖 llama3");
+        assert_eq!(lines[7], "  Because b.");
+        assert!(lines[9].ends_with("connection refused"));
+    }
+}
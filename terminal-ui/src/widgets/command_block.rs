@@ -230,8 +230,8 @@ mod tests {
         assert_eq!(block.command, "ls -la");
         assert_eq!(block.working_dir, "/home/user");
         assert_eq!(block.state, BlockState::Editing);
-        assert_eq!(block.is_editing, false);
-        assert_eq!(block.is_selected, false);
+        assert!(!block.is_editing);
+        assert!(!block.is_selected);
     }
     
     #[test]
@@ -241,12 +241,12 @@ mod tests {
         // Start execution
         block.start_execution();
         assert_eq!(block.state, BlockState::Running);
-        assert_eq!(block.is_editing, false);
+        assert!(!block.is_editing);
         
         // Complete successfully
         block.complete(0, Duration::from_secs(1));
         assert_eq!(block.state, BlockState::Success);
-        assert_eq!(block.is_editing, false);
+        assert!(!block.is_editing);
         assert_eq!(block.exit_code, Some(0));
         assert!(block.duration.is_some());
         
@@ -264,12 +264,12 @@ mod tests {
         
         // Start editing
         block.start_editing();
-        assert_eq!(block.is_editing, true);
+        assert!(block.is_editing);
         assert_eq!(block.state, BlockState::Editing);
         
         // Finish editing
         block.finish_editing();
-        assert_eq!(block.is_editing, false);
+        assert!(!block.is_editing);
     }
     
     #[test]
@@ -278,11 +278,11 @@ mod tests {
         
         // Select the block
         block.set_selected(true);
-        assert_eq!(block.is_selected, true);
+        assert!(block.is_selected);
         
         // Deselect the block
         block.set_selected(false);
-        assert_eq!(block.is_selected, false);
+        assert!(!block.is_selected);
     }
     
     #[test]
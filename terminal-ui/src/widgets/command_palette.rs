@@ -5,7 +5,7 @@
 
 use ratatui::{
     layout::Rect,
-    style::{Style, Color},
+    style::{Style, Color, Modifier},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, Clear},
     Frame,
@@ -13,6 +13,14 @@ use ratatui::{
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 
+use crate::capabilities::Capability;
+
+/// Most recently used commands listed first
+const RECENT_LIMIT: usize = 5;
+
+/// Score added per place a command is from the end of the recently used list
+const RECENT_BONUS: i64 = 4;
+
 /// Represents a command in the palette
 #[derive(Debug, Clone)]
 pub struct Command {
@@ -21,6 +29,63 @@ pub struct Command {
     pub description: String,
     pub category: String,
     pub icon: String,
+    /// Key that runs the command outside the palette, shown next to it
+    pub key: Option<String>,
+    /// What the command takes after its name, as in `theme dark`, if anything
+    pub argument: Option<String>,
+    /// What picking the command does
+    pub action: Action,
+}
+
+/// What picking a command does
+///
+/// Commands the terminal implements itself are [`Action::Builtin`] and are
+/// told apart by their id; the others carry their text, in which
+/// `{{NAME}}` placeholders stand for arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Action {
+    /// Handled by the terminal by the command's id
+    #[default]
+    Builtin,
+    /// Run a shell command
+    Run(String),
+    /// Put text on the input line to edit and run
+    Insert(String),
+    /// Send a prompt to the AI
+    Ask(String),
+    /// Run a slash command, such as `/workflow run deploy`
+    Slash(String),
+}
+
+impl Action {
+    /// Get the text the action works with, unless it is built in
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Action::Builtin => None,
+            Action::Run(text) | Action::Insert(text) | Action::Ask(text) | Action::Slash(text) => Some(text),
+        }
+    }
+
+    /// Get the same action on another text, such as with its placeholders filled in
+    pub fn with_text(&self, text: String) -> Self {
+        match self {
+            Action::Builtin => Action::Builtin,
+            Action::Run(_) => Action::Run(text),
+            Action::Insert(_) => Action::Insert(text),
+            Action::Ask(_) => Action::Ask(text),
+            Action::Slash(_) => Action::Slash(text),
+        }
+    }
+}
+
+/// A command that matched the palette input
+#[derive(Debug, Clone)]
+struct PaletteMatch {
+    command: Command,
+    /// Argument typed after the command's name
+    argument: Option<String>,
+    /// Characters of the name that matched, to highlight
+    indices: Vec<usize>,
 }
 
 impl Command {
@@ -32,44 +97,131 @@ impl Command {
             description: description.to_string(),
             category: category.to_string(),
             icon: icon.to_string(),
+            key: None,
+            argument: None,
+            action: Action::Builtin,
         }
     }
+
+    /// Make picking the command do something other than a built-in command
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Show the key that runs the command outside the palette
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Let the command take an argument after its name, described by `hint`
+    pub fn with_argument(mut self, hint: &str) -> Self {
+        self.argument = Some(hint.to_string());
+        self
+    }
 }
 
 /// Command palette widget
 pub struct CommandPalette {
     commands: Vec<Command>,
-    filtered_commands: Vec<Command>,
+    filtered_commands: Vec<PaletteMatch>,
     input: String,
     selected_index: usize,
     matcher: SkimMatcherV2,
+    /// IDs of the commands used last, most recent first
+    recent: Vec<String>,
 }
 
 impl CommandPalette {
     /// Create a new command palette with default commands
     pub fn new() -> Self {
-        let commands = vec![
+        let mut commands = vec![
             Command::new("new_session", "New Session", "Create a new AI session", "Session", "📝"),
             Command::new("clear_screen", "Clear Screen", "Clear the terminal screen", "View", "🧹"),
-            Command::new("toggle_help", "Toggle Help", "Show/hide the help modal", "View", "❓"),
-            Command::new("quit", "Quit", "Exit the application", "Session", "🚪"),
-            Command::new("scroll_up", "Scroll Up", "Scroll the chat up by 5 lines", "Navigation", "⬆️"),
-            Command::new("scroll_down", "Scroll Down", "Scroll the chat down by 5 lines", "Navigation", "⬇️"),
+            Command::new("clear_outputs", "Clear Outputs", "Drop block outputs but keep the commands as a compact index", "View", "🧽"),
+            Command::new("clear_ai", "Clear AI Messages", "Remove AI prompts and responses, keeping shell commands", "View", "🗑"),
+            Command::new("toggle_help", "Toggle Help", "Show/hide the help modal", "View", "❓").with_key("F1"),
+            Command::new("quit", "Quit", "Exit the application", "Session", "🚪").with_key("F10"),
+            Command::new("restore_session", "Restore Last Session", "Reload the blocks saved when the last session ended", "Session", "♻️"),
+            Command::new("scroll_up", "Scroll Up", "Scroll the chat up half a page", "Navigation", "⬆️"),
+            Command::new("scroll_down", "Scroll Down", "Scroll the chat down half a page", "Navigation", "⬇️"),
+            Command::new("jump_last_error", "Jump to Last Error", "Scroll to the most recent failed block", "Navigation", "🎯"),
             Command::new("toggle_theme", "Toggle Theme", "Switch between light and dark themes", "View", "🎨"),
             Command::new("test_confirmation", "Test Confirmation", "Show a test confirmation modal", "Test", "✅"),
             Command::new("save_theme", "Save Theme", "Save the current theme to a file", "View", "💾"),
             Command::new("list_themes", "List Themes", "Show all available themes", "View", "📋"),
+            Command::new("select_theme", "Select Theme", "Preview and pick a theme interactively, or name one, as in 'theme dark'", "View", "🖌️").with_argument("theme"),
+            Command::new("toggle_offline", "Toggle Offline Mode", "Hold back AI prompts and model downloads, queueing prompts until back online", "AI", "✈️").with_key("F3"),
+            Command::new("toggle_raw_output", "Toggle Raw Output", "Switch blocks between enhanced views, such as tables, and their raw output", "View", "🧾"),
+            Command::new("table_view", "Table: Sort and Filter Output", "Open the clicked block's columnar output, or the latest table, as a table to sort and filter by column", "View", "▦"),
+            Command::new("json_viewer", "JSON: Browse Output", "Fold and query the clicked block's JSON or NDJSON output, or the latest JSON, as jq would", "View", "{}"),
+            Command::new("log_viewer", "Logs: Follow Block Output", "Follow the clicked block's output, or the latest one, as a log colored and filtered by level while it runs", "View", "📜"),
+            Command::new("table_inline", "Table: Toggle Block Table", "Show the clicked block's columnar output as an aligned table, or raw again", "View", "▦"),
+            Command::new("toggle_chat", "Toggle Chat Pane", "Show a multi-turn conversation with the model beside the panes, or hide it", "AI", "💬").with_key("F5"),
+            Command::new("chat_send_block", "Send Block to Chat", "Send the output of the latest block, or the n-th latest, to the chat pane", "AI", "💬").with_argument("n"),
+            Command::new("memory_learn", "AI: Remember Project Fact", "Have the model pick a fact from the chat or latest blocks to add to .ai-terminal.md, once approved", "AI", "📌"),
+            Command::new("doctor", "Doctor: Check Environment", "Check that the model server answers and has the model, that commands run in a PTY, and what the terminal supports", "Session", "🩺"),
+            Command::new("bench_explain", "AI: Explain Benchmark", "Ask the model what the times of the latest `bench` run say, such as whether the spread is noise", "AI", "⏱"),
+            Command::new("explain_command", "AI: Explain Command Line", "Break the command on the input line down into its flags and arguments before running it", "AI", "❔").with_key("Ctrl+/"),
+            Command::new("smart_paste_clean", "Smart Paste: Clean Up", "Strip prompts and output from the snippet on the input line, join continued lines and turn values to fill in into placeholders", "Session", "🧹"),
+            Command::new("smart_paste_adapt", "Smart Paste: Adapt to This System", "Clean up the snippet on the input line and have the model adapt it to this OS and package manager", "AI", "🛠"),
+            Command::new("history_semantic", "history semantic: Search History", "Find past commands by what they did, as in 'fixed the docker network'", "Session", "🔎")
+                .with_action(Action::Slash("/history semantic".to_string()))
+                .with_argument("query"),
+            Command::new("toggle_summaries", "Toggle Summaries", "Fold or unfold the AI summaries above long outputs", "View", "📋"),
+            Command::new("toggle_runs", "Toggle Repeated Runs", "List or hide the individual runs of commands collapsed as ×N", "View", "🔁"),
+            Command::new("toggle_reduced_motion", "Toggle Reduced Motion", "Redraw less often and move progress in steps", "View", "🐢"),
+            Command::new("copy_mode", "Copy Mode", "Freeze the pane and select text with the keyboard, by lines or as a rectangle", "Session", "✂").with_key("Alt+V"),
+            Command::new("copy_last_output", "Copy Mode: Last Output", "Freeze the pane with the last command's output selected, ready to yank", "Session", "✂").with_key("Alt+O"),
+            Command::new("hints", "Hints: Links and Paths", "Label the URLs and file paths on screen to open them or insert them into the input", "Session", "🔗").with_key("Alt+L"),
+            Command::new("copy_block_output", "Copy Block Output", "Copy the output of the clicked block, or the latest one, to the clipboard", "Session", "📋"),
+            Command::new("save_block_output", "Save Full Block Output", "Write the complete output of the clicked block, or the latest one, including truncated lines, to a file", "Session", "💾"),
+            Command::new("pin_block", "Pin Block", "Pin the clicked block, or the latest one, to keep it across sessions", "Session", "📌"),
+            Command::new("pins_panel", "Pins: Pinned Blocks", "Run, copy or attach the blocks you pinned", "Session", "📌").with_key("Alt+P"),
+            Command::new("export_transcript", "Export: Session Transcript", "Save blocks and AI exchanges as Markdown, HTML or JSON, chosen by file extension", "Session", "📤").with_argument("path"),
+            Command::new("blocks_by_tag", "blocks: Filter by Tag", "List the blocks with all the given tags, such as #git #failed", "Session", "🏷")
+                .with_action(Action::Slash("/blocks".to_string()))
+                .with_argument("tags"),
+            Command::new("tag_block", "tag: Tag Block", "Tag the clicked block, or the latest one", "Session", "🏷")
+                .with_action(Action::Slash("/tag".to_string()))
+                .with_argument("tags"),
+            Command::new("export_tagged_blocks", "Export: Tagged Blocks", "Save the blocks with all the given tags as a transcript: <path> #tag...", "Session", "📤")
+                .with_action(Action::Slash("/blocks export".to_string()))
+                .with_argument("path and tags"),
+            Command::new("git_commit_message", "AI: Write Commit Message", "Draft a commit message for the staged diff, edit it and commit", "Git", "⎇").with_key("Ctrl+G"),
+            Command::new("git_explain_diff", "AI: Explain Diff", "Explain the staged diff, or the unstaged one if nothing is staged", "Git", "🔍").with_key("Alt+G"),
+            Command::new("toggle_file_browser", "Toggle File Browser", "Browse the working directory, preview files and attach them to AI prompts", "View", "📁").with_key("F4"),
+            Command::new("process_manager", "ps: Process Manager", "List, filter and sort processes and send them signals", "View", "⚙"),
+            Command::new("toggle_monitor", "Toggle Resource Monitor", "Show or hide CPU, memory, disk and GPU usage over the panes", "View", "📊"),
+            Command::new("lock_session", "Lock Session", "Cover the session until the lock passphrase is entered", "Session", "🔒"),
+            Command::new("share_session", "Share Session", "Upload the scrubbed session to a gist or paste service and copy the link", "Session", "🔗"),
+            Command::new("share_block", "Share Block", "Upload the clicked block, or the latest one, with secrets masked, and copy the link", "Session", "🔗"),
+            Command::new("select_persona", "Select Persona", "Pick the system prompt persona for this tab, or name one", "AI", "🎭").with_argument("persona"),
+            Command::new("edit_persona", "Edit Persona", "Create or change a persona's system prompt", "AI", "✏️"),
+            Command::new("models", "Manage Models", "List, inspect, pull and delete Ollama models", "AI", "🧠"),
+            Command::new("stats", "Stats: Token Usage", "Show token counts, tokens/sec and latency for this session", "AI", "📈"),
+            Command::new("usage_stats", "Show Usage Statistics", "Summarise AI requests per model", "Help", "📊"),
+            Command::new("capabilities", "Show Build Capabilities", "List the optional features compiled into this build", "Help", "🧩"),
+            Command::new("bug_report", "Create Bug Report", "Write a scrubbed bug report with recent blocks", "Help", "🐞"),
         ];
+        // Leave out what this build cannot do
+        if !Capability::Embeddings.is_available() {
+            commands.retain(|command| command.id != "history_semantic");
+        }
         
         let matcher = SkimMatcherV2::default();
         
-        Self {
-            commands: commands.clone(),
-            filtered_commands: commands,
+        let mut palette = Self {
+            commands,
+            filtered_commands: Vec::new(),
             input: String::new(),
             selected_index: 0,
             matcher,
-        }
+            recent: Vec::new(),
+        };
+        palette.update_filter();
+        palette
     }
     
     /// Get the number of commands
@@ -93,32 +245,89 @@ impl CommandPalette {
     }
     
     /// Update the filter based on user input
+    ///
+    /// Commands are ranked the way fzf ranks lines: by how well the input
+    /// matches as a subsequence, with matches at word starts and in a row
+    /// scoring higher. The name counts twice as much as the description,
+    /// recently used commands get a little more, and shorter names win ties.
+    /// A command taking an argument also matches when only the first word
+    /// of the input matches it, and the rest is its argument.
     pub fn update_filter(&mut self) {
-        if self.input.is_empty() {
-            self.filtered_commands = self.commands.clone();
+        let recency = |command: &Command| {
+            self.recent.iter().position(|id| *id == command.id).map_or(0, |i| (RECENT_LIMIT - i) as i64 * RECENT_BONUS)
+        };
+        let query = self.input.trim();
+        let mut scored: Vec<(i64, PaletteMatch)> = self.commands
+            .iter()
+            .filter_map(|command| {
+                if query.is_empty() {
+                    return Some((recency(command), PaletteMatch { command: command.clone(), argument: None, indices: Vec::new() }));
+                }
+                let whole = self.score(command, query).map(|(score, indices)| (score, None, indices));
+                let with_argument = query
+                    .split_once(char::is_whitespace)
+                    .filter(|_| command.argument.is_some())
+                    .and_then(|(head, tail)| {
+                        let (score, indices) = self.score(command, head)?;
+                        Some((score, Some(tail.trim().to_string()), indices))
+                    });
+                let (score, argument, indices) = match (whole, with_argument) {
+                    (Some(whole), Some(with_argument)) => if with_argument.0 >= whole.0 { with_argument } else { whole },
+                    (whole, with_argument) => whole.or(with_argument)?,
+                };
+                Some((score + recency(command), PaletteMatch { command: command.clone(), argument, indices }))
+            })
+            .collect();
+
+        // The sort is stable, so without input the commands keep their order after the recent ones
+        if query.is_empty() {
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
         } else {
-            let mut scored_commands: Vec<(i64, Command)> = self.commands
-                .iter()
-                .filter_map(|cmd| {
-                    // Match against both name and description
-                    let match_text = format!("{} {}", cmd.name, cmd.description);
-                    self.matcher.fuzzy_match(&match_text, &self.input)
-                        .map(|score| (score, cmd.clone()))
-                })
-                .collect();
-                
-            // Sort by score (highest first)
-            scored_commands.sort_by(|a, b| b.0.cmp(&a.0));
-            
-            self.filtered_commands = scored_commands
-                .into_iter()
-                .map(|(_, cmd)| cmd)
-                .collect();
+            scored.sort_by_key(|(score, found)| (std::cmp::Reverse(*score), found.command.name.len()));
         }
+        self.filtered_commands = scored.into_iter().map(|(_, found)| found).collect();
         
         // Reset selection to top
         self.selected_index = 0;
     }
+
+    /// Score a command against a query, with the characters of its name that matched
+    fn score(&self, command: &Command, query: &str) -> Option<(i64, Vec<usize>)> {
+        let name = self.matcher.fuzzy_indices(&command.name, query);
+        let description = self.matcher.fuzzy_match(&command.description, query);
+        match (name, description) {
+            (Some((score, _)), Some(description)) if score * 2 < description => Some((description, Vec::new())),
+            (Some((score, indices)), _) => Some((score * 2, indices)),
+            (None, description) => description.map(|score| (score, Vec::new())),
+        }
+    }
+
+    /// Remember that a command was used, so it is listed first next time
+    pub fn record_use(&mut self, id: &str) {
+        self.recent.retain(|recent| recent != id);
+        self.recent.insert(0, id.to_string());
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// Get the IDs of the commands used last, most recent first
+    pub fn recent(&self) -> &[String] {
+        &self.recent
+    }
+
+    /// Restore the commands used last, such as in a previous session
+    pub fn set_recent(&mut self, recent: Vec<String>) {
+        self.recent = recent;
+        self.recent.truncate(RECENT_LIMIT);
+        self.update_filter();
+    }
+
+    /// Show the key that runs a command, such as a rebindable scrolling key
+    pub fn set_key(&mut self, id: &str, key: &str) {
+        for command in self.commands.iter_mut().filter(|command| command.id == id) {
+            command.key = Some(key.to_string());
+        }
+        self.update_filter();
+    }
     
     /// Handle user input
     pub fn handle_input(&mut self, input: &str) {
@@ -128,7 +337,7 @@ impl CommandPalette {
     
     /// Handle backspace
     pub fn handle_backspace(&mut self) {
-        self.input.pop();
+        crate::text::pop_grapheme(&mut self.input);
         self.update_filter();
     }
     
@@ -152,7 +361,15 @@ impl CommandPalette {
     
     /// Get the currently selected command
     pub fn get_selected_command(&self) -> Option<&Command> {
-        self.filtered_commands.get(self.selected_index)
+        self.filtered_commands.get(self.selected_index).map(|found| &found.command)
+    }
+
+    /// Get the argument typed after the selected command's name, if any
+    pub fn selected_argument(&self) -> Option<&str> {
+        self.filtered_commands
+            .get(self.selected_index)
+            .and_then(|found| found.argument.as_deref())
+            .filter(|argument| !argument.is_empty())
     }
     
     /// Render the command palette
@@ -164,23 +381,37 @@ impl CommandPalette {
         let items: Vec<ListItem> = self.filtered_commands
             .iter()
             .enumerate()
-            .map(|(i, cmd)| {
+            .map(|(i, found)| {
+                let cmd = &found.command;
                 let is_selected = i == self.selected_index;
                 let style = if is_selected {
                     Style::default().bg(Color::Blue).fg(Color::White)
                 } else {
                     Style::default()
                 };
+                let matched = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                let hint = Style::default().fg(Color::DarkGray);
                 
-                let line = Line::from(vec![
+                let mut spans = vec![
                     Span::styled(cmd.icon.clone(), Style::default().fg(Color::Cyan)),
                     Span::raw(" "),
-                    Span::styled(cmd.name.clone(), style),
-                    Span::raw(" - "),
-                    Span::styled(cmd.description.clone(), style),
-                ]);
+                ];
+                // Characters the input matched stand out in the name
+                spans.extend(cmd.name.chars().enumerate().map(|(index, c)| {
+                    Span::styled(c.to_string(), if found.indices.contains(&index) { matched } else { style })
+                }));
+                match (&found.argument, &cmd.argument) {
+                    (Some(argument), _) if !argument.is_empty() => spans.push(Span::styled(format!(" → {}", argument), matched)),
+                    (_, Some(argument)) => spans.push(Span::styled(format!(" <{}>", argument), hint)),
+                    _ => {}
+                }
+                if let Some(key) = &cmd.key {
+                    spans.push(Span::styled(format!(" [{}]", key), hint));
+                }
+                spans.push(Span::raw(" - "));
+                spans.push(Span::styled(cmd.description.clone(), style));
                 
-                ListItem::new(line)
+                ListItem::new(Line::from(spans))
             })
             .collect();
         
@@ -195,11 +426,45 @@ impl CommandPalette {
         f.render_widget(list, area);
     }
     
+    /// Add a command, replacing any with the same id
+    pub fn register(&mut self, command: Command) {
+        match self.commands.iter_mut().find(|existing| existing.id == command.id) {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command),
+        }
+        self.update_filter();
+    }
+
+    /// Remove the commands whose ids start with `prefix`, returning how many were removed
+    pub fn unregister(&mut self, prefix: &str) -> usize {
+        let count = self.commands.len();
+        self.commands.retain(|command| !command.id.starts_with(prefix));
+        self.update_filter();
+        count - self.commands.len()
+    }
+
+    /// Get every command, in the order they are listed without input
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+    
+    /// Replace the commands of a category, such as the saved snippets
+    pub fn set_category(&mut self, category: &str, commands: Vec<Command>) {
+        self.commands.retain(|command| command.category != category);
+        self.commands.extend(commands);
+        self.reset();
+    }
+    
     /// Reset the command palette
     pub fn reset(&mut self) {
         self.input.clear();
-        self.filtered_commands = self.commands.clone();
-        self.selected_index = 0;
+        self.update_filter();
+    }
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -237,4 +502,45 @@ mod tests {
         palette.reset();
         assert_eq!(palette.filtered_commands.len(), palette.commands.len());
     }
+    
+    #[test]
+    fn test_fuzzy_ranking_arguments_and_recent() {
+        let mut palette = CommandPalette::new();
+        
+        // Initials of the name rank the command first
+        palette.handle_input("tfb");
+        assert_eq!(palette.get_selected_command().unwrap().id, "toggle_file_browser");
+        assert_eq!(palette.get_selected_command().unwrap().key.as_deref(), Some("F4"));
+        
+        // The words after a command taking an argument are its argument
+        palette.reset();
+        palette.handle_input("theme solarized dark");
+        assert_eq!(palette.get_selected_command().unwrap().id, "select_theme");
+        assert_eq!(palette.selected_argument(), Some("solarized dark"));
+        
+        // Recently used commands come first without input
+        palette.record_use("stats");
+        palette.record_use("quit");
+        palette.reset();
+        let first: Vec<&str> = palette.filtered_commands.iter().take(3).map(|found| found.command.id.as_str()).collect();
+        assert_eq!(first, ["quit", "stats", "new_session"]);
+    }
+    
+    #[test]
+    fn test_register_commands() {
+        let mut palette = CommandPalette::new();
+        let count = palette.commands().len();
+        let deploy = Command::new("custom:deploy", "Deploy", "Ship it", "Custom", "⚙").with_action(Action::Run("./deploy.sh {{ENV}}".to_string()));
+        palette.register(deploy.clone());
+        palette.register(deploy.with_argument("env"));
+        assert_eq!(palette.commands().len(), count + 1);
+        
+        palette.handle_input("deploy prod");
+        assert_eq!(palette.get_selected_command().unwrap().action.with_text("x".to_string()), Action::Run("x".to_string()));
+        assert_eq!(palette.selected_argument(), Some("prod"));
+        
+        assert_eq!(palette.unregister("custom:"), 1);
+        assert_eq!(palette.commands().len(), count);
+        assert_eq!(Action::Builtin.text(), None);
+    }
 }
\ No newline at end of file
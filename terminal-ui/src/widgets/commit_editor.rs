@@ -0,0 +1,101 @@
+//! Commit message editor widget for the AI Terminal
+//!
+//! A multi-line input in a popup holding the commit message the model
+//! drafted. Typing appends at the end, as in the input line; the subject
+//! line turns to the warning color once it is longer than git's customary
+//! 72 characters.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::text;
+use crate::theme::Theme;
+
+/// Longest subject line shown without a warning
+const SUBJECT_LEN: usize = 72;
+
+/// Popup editing a commit message
+pub struct CommitEditor {
+    message: String,
+}
+
+impl CommitEditor {
+    /// Create an editor holding a drafted message
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into() }
+    }
+
+    /// Get the message as edited
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Add a character at the end
+    pub fn push_char(&mut self, c: char) {
+        self.message.push(c);
+    }
+
+    /// Start a new line
+    pub fn newline(&mut self) {
+        self.message.push('\n');
+    }
+
+    /// Remove the last character
+    pub fn backspace(&mut self) {
+        text::pop_grapheme(&mut self.message);
+    }
+
+    /// Remove the last word, or the line break before it
+    pub fn delete_word(&mut self) {
+        if self.message.ends_with('\n') {
+            self.message.pop();
+            return;
+        }
+        let kept = self.message.trim_end_matches([' ', '\t']);
+        let cut = kept.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        self.message.truncate(cut);
+    }
+
+    /// Render the editor
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title("Commit Message");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        let subject_len = self.message.lines().next().map_or(0, text::width);
+        let mut lines: Vec<Line> = self.message
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let style = if i == 0 && subject_len > SUBJECT_LEN { Style::default().fg(theme.warning) } else { Style::default().fg(theme.text) };
+                Line::from(Span::styled(line.to_string(), style))
+            })
+            .collect();
+        if let Some(last) = lines.last_mut() {
+            last.spans.push(Span::raw("▏"));
+        }
+        // Keep the end of long messages, where the cursor is, in view
+        let height = chunks[0].height as usize;
+        let skip = lines.len().saturating_sub(height);
+        let text = Paragraph::new(lines.split_off(skip)).wrap(Wrap { trim: false });
+        f.render_widget(text, chunks[0]);
+
+        let hints = Paragraph::new(format!("Ctrl+S: git commit | Enter: new line | Ctrl+W: delete word | Esc: cancel | subject {}/{}", subject_len, SUBJECT_LEN))
+            .style(theme.styles.status_info);
+        f.render_widget(hints, chunks[1]);
+    }
+}
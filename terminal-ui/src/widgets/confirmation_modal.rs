@@ -39,13 +39,14 @@ pub struct ConfirmationModal {
 }
 
 impl ConfirmationModal {
-    /// Create a new confirmation modal
+    /// Create a new confirmation modal with the default button selected
     pub fn new(title: &str, message: &str, buttons: Vec<ModalButton>) -> Self {
+        let selected_button = buttons.iter().position(|button| button.is_default).unwrap_or(0);
         Self {
             title: title.to_string(),
             message: message.to_string(),
             buttons,
-            selected_button: 0,
+            selected_button,
         }
     }
 
@@ -179,7 +180,7 @@ mod tests {
         let button = ModalButton::new("test", "Test Button", true);
         assert_eq!(button.id, "test");
         assert_eq!(button.text, "Test Button");
-        assert_eq!(button.is_default, true);
+        assert!(button.is_default);
     }
 
     #[test]
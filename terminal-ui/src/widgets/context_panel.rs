@@ -0,0 +1,90 @@
+//! Attached context panel widget for the AI Terminal
+//!
+//! While files or command output are attached to AI requests, this panel
+//! sits over the right of the panes, below the agent's plan when there is
+//! one, and lists them with their numbers and sizes.
+
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::context_set::{ContextSet, MAX_TOTAL_BYTES};
+use crate::theme::Theme;
+use crate::widgets::models_view::format_size;
+
+/// Width of the panel, border included
+const WIDTH: u16 = 40;
+
+/// Panel listing the attached context
+pub struct ContextPanel<'a> {
+    context: &'a ContextSet,
+}
+
+impl<'a> ContextPanel<'a> {
+    /// Create a panel for a context set
+    pub fn new(context: &'a ContextSet) -> Self {
+        Self { context }
+    }
+
+    /// Get the area of the panel, docked to the right of the pane area below `above`, if given
+    pub fn area(&self, panes: Rect, above: Option<Rect>) -> Rect {
+        let top = above.map_or(panes.y, |above| above.bottom().min(panes.bottom()));
+        let width = WIDTH.min(panes.width);
+        let height = (self.context.items().len() as u16 + 2).min(panes.bottom() - top);
+        Rect::new(panes.right() - width, top, width, height)
+    }
+
+    /// Render the panel over the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, above: Option<Rect>, theme: &Theme) {
+        let area = self.area(panes, above);
+        if area.height < 3 {
+            return;
+        }
+        f.render_widget(Clear, area);
+        let title = format!(
+            " 📎 Context · {} of {} ",
+            format_size(self.context.bytes() as u64),
+            format_size(MAX_TOTAL_BYTES as u64),
+        );
+        let lines: Vec<Line> = self.context.items()
+            .iter()
+            .enumerate()
+            .map(|(i, item)| Line::from(vec![
+                Span::styled(format!("{}. ", i + 1), theme.styles.ghost_text),
+                Span::raw(item.label.clone()),
+                Span::styled(format!(" {}", format_size(item.text.len() as u64)), theme.styles.ghost_text),
+            ]))
+            .collect();
+        let panel = Paragraph::new(lines)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title(title)
+            );
+        f.render_widget(panel, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_set::ContextItem;
+
+    #[test]
+    fn test_panel_docks_below() {
+        let mut context = ContextSet::default();
+        context.attach(ContextItem::file("a.txt", "a".to_string())).unwrap();
+        context.attach(ContextItem::file("b.txt", "b".to_string())).unwrap();
+        let panes = Rect::new(0, 1, 100, 30);
+        let panel = ContextPanel::new(&context);
+        assert_eq!(panel.area(panes, None), Rect::new(60, 1, 40, 4));
+        assert_eq!(panel.area(panes, Some(Rect::new(50, 1, 50, 8))), Rect::new(60, 9, 40, 4));
+        assert_eq!(panel.area(panes, Some(Rect::new(50, 1, 50, 40))).height, 0);
+    }
+}
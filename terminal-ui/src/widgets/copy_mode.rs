@@ -0,0 +1,411 @@
+//! Copy mode widget for the AI Terminal
+//!
+//! Copy mode freezes the focused pane the way tmux's does: the output stops
+//! moving while a cursor is moved over it from the keyboard, a region is
+//! selected, line by line or as a rectangle, and yanked to the clipboard.
+//! Lines broken by wrapping are joined again when yanked, so a long line
+//! comes out as it was printed.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::text;
+use crate::theme::Theme;
+
+/// The wrapped lines of a pane, taken when copy mode starts
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Every line of the pane, wrapped to its width
+    pub rows: Vec<Line<'static>>,
+    /// Whether each line continues the one before it, broken there by wrapping
+    pub continued: Vec<bool>,
+    /// First line of each block, then the total number of lines
+    pub block_rows: Vec<usize>,
+    /// Output of each block as it was printed
+    pub outputs: Vec<String>,
+    /// Index of the most recent shell command with output
+    pub last_command: Option<usize>,
+    /// Line at the top of the view
+    pub top: usize,
+}
+
+/// A frozen pane with a cursor and a selection
+pub struct CopyMode {
+    snapshot: Snapshot,
+    /// Plain text of each line
+    texts: Vec<String>,
+    /// Cursor as a line and a column
+    cursor: (usize, usize),
+    /// Where the selection started, if a selection is being made
+    anchor: Option<(usize, usize)>,
+    /// Whether the selection is the rectangle between the anchor and the cursor
+    rectangle: bool,
+    /// Block whose whole output is selected, yanked as printed rather than as drawn
+    whole_output: Option<usize>,
+    /// Line at the top of the view
+    top: usize,
+    /// Height of the view at the last render, for page motions
+    height: usize,
+}
+
+impl CopyMode {
+    /// Start copy mode on a snapshot, with the cursor on the last line in view
+    pub fn new(snapshot: Snapshot, height: usize) -> Self {
+        let texts: Vec<String> = snapshot.rows.iter().map(Line::to_string).collect();
+        let top = snapshot.top.min(texts.len().saturating_sub(1));
+        let line = (top + height).min(texts.len()).saturating_sub(1);
+        Self { snapshot, texts, cursor: (line, 0), anchor: None, rectangle: false, whole_output: None, top, height }
+    }
+
+    /// Get the cursor as a line and a column
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Move the cursor one character left
+    pub fn move_left(&mut self) {
+        let column = self.columns(self.cursor.0).into_iter().rev().find(|&start| start < self.cursor.1).unwrap_or(0);
+        self.move_to(self.cursor.0, column);
+    }
+
+    /// Move the cursor one character right
+    pub fn move_right(&mut self) {
+        let column = self.columns(self.cursor.0).into_iter().find(|&start| start > self.cursor.1).unwrap_or(self.cursor.1);
+        self.move_to(self.cursor.0, column);
+    }
+
+    /// Move the cursor down by a number of lines, up if negative, keeping its column where it can
+    pub fn move_lines(&mut self, lines: isize) {
+        let line = self.cursor.0.saturating_add_signed(lines).min(self.texts.len().saturating_sub(1));
+        self.move_to(line, self.cursor.1);
+    }
+
+    /// Move the cursor by half the height of the view, up if `up`
+    pub fn move_half_page(&mut self, up: bool) {
+        let half = (self.height / 2).max(1) as isize;
+        self.move_lines(if up { -half } else { half });
+    }
+
+    /// Move the cursor by the height of the view, up if `up`
+    pub fn move_page(&mut self, up: bool) {
+        let page = self.height.max(1) as isize;
+        self.move_lines(if up { -page } else { page });
+    }
+
+    /// Move the cursor to the start of its line
+    pub fn move_line_start(&mut self) {
+        self.move_to(self.cursor.0, 0);
+    }
+
+    /// Move the cursor to the last character of its line
+    pub fn move_line_end(&mut self) {
+        let column = self.columns(self.cursor.0).last().copied().unwrap_or(0);
+        self.move_to(self.cursor.0, column);
+    }
+
+    /// Move the cursor to the first line
+    pub fn move_top(&mut self) {
+        self.move_to(0, 0);
+    }
+
+    /// Move the cursor to the last line
+    pub fn move_bottom(&mut self) {
+        self.move_to(self.texts.len().saturating_sub(1), 0);
+    }
+
+    /// Move the cursor to the start of the next word, on a later line if none is left on this one
+    pub fn move_word_forward(&mut self) {
+        let (line, column) = self.cursor;
+        let target = self.word_starts(line).into_iter().find(|&start| start > column).map(|start| (line, start)).or_else(|| {
+            (line + 1..self.texts.len()).find_map(|next| self.word_starts(next).first().map(|&start| (next, start)))
+        });
+        if let Some((line, column)) = target {
+            self.move_to(line, column);
+        }
+    }
+
+    /// Move the cursor to the start of the previous word, on an earlier line if none is before it on this one
+    pub fn move_word_back(&mut self) {
+        let (line, column) = self.cursor;
+        let target = self.word_starts(line).into_iter().rev().find(|&start| start < column).map(|start| (line, start)).or_else(|| {
+            (0..line).rev().find_map(|previous| self.word_starts(previous).last().map(|&start| (previous, start)))
+        });
+        if let Some((line, column)) = target {
+            self.move_to(line, column);
+        }
+    }
+
+    /// Move the cursor to the start of the current block, or of the previous one if it is there already
+    pub fn move_previous_block(&mut self) {
+        let (index, start) = self.block_at(self.cursor.0);
+        let line = if self.cursor.0 > start || index == 0 { start } else { self.snapshot.block_rows[index - 1] };
+        self.move_to(line, 0);
+    }
+
+    /// Move the cursor to the start of the next block
+    pub fn move_next_block(&mut self) {
+        let (index, _) = self.block_at(self.cursor.0);
+        if let Some(&line) = self.snapshot.block_rows.get(index + 1).filter(|&&line| line < self.texts.len()) {
+            self.move_to(line, 0);
+        }
+    }
+
+    /// Start a selection at the cursor, or drop the one being made
+    pub fn toggle_selection(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+        self.whole_output = None;
+    }
+
+    /// Switch between selecting lines and selecting a rectangle, starting a selection if there is none
+    pub fn toggle_rectangle(&mut self) {
+        self.rectangle = !self.rectangle;
+        self.anchor.get_or_insert(self.cursor);
+        self.whole_output = None;
+    }
+
+    /// Select the output of the block under the cursor, returning whether it has any
+    pub fn select_block_output(&mut self) -> bool {
+        let (index, _) = self.block_at(self.cursor.0);
+        self.select_output(index)
+    }
+
+    /// Select the output of the most recent shell command, returning whether there is one
+    pub fn select_last_output(&mut self) -> bool {
+        self.snapshot.last_command.is_some_and(|index| self.select_output(index))
+    }
+
+    /// Get the selected text, if a selection is being made
+    pub fn selected_text(&self) -> Option<String> {
+        if let Some(index) = self.whole_output {
+            return self.snapshot.outputs.get(index).cloned();
+        }
+        let (start, end) = self.bounds()?;
+        let mut selected = String::new();
+        for line in start.0..=end.0 {
+            let piece = match self.selected_columns(line) {
+                Some((from, to)) => text::slice_columns(&self.texts[line], from, to),
+                None => "",
+            };
+            if line > start.0 && (self.rectangle || !self.snapshot.continued.get(line).copied().unwrap_or(false)) {
+                selected.truncate(selected.trim_end().len());
+                selected.push('\n');
+            }
+            selected.push_str(piece);
+        }
+        selected.truncate(selected.trim_end().len());
+        Some(selected)
+    }
+
+    /// Get the text to yank: the selection, or the cursor's line if nothing is selected
+    pub fn text_to_yank(&self) -> String {
+        self.selected_text().unwrap_or_else(|| self.texts.get(self.cursor.0).map_or("", |line| line.trim_end()).to_string())
+    }
+
+    /// Format the status bar segment, such as ` 📋 COPY · RECTANGLE `
+    pub fn status_segment(&self) -> String {
+        match (self.anchor, self.rectangle) {
+            (Some(_), true) => " 📋 COPY · RECTANGLE ".to_string(),
+            (Some(_), false) => " 📋 COPY · SELECTING ".to_string(),
+            (None, _) => " 📋 COPY ".to_string(),
+        }
+    }
+
+    /// Render the frozen lines over the pane's text area
+    pub fn render(&mut self, f: &mut Frame, area: Rect, theme: &Theme) {
+        self.height = area.height as usize;
+        // Keep the cursor in view
+        if self.cursor.0 < self.top {
+            self.top = self.cursor.0;
+        } else if self.cursor.0 >= self.top + self.height {
+            self.top = self.cursor.0 + 1 - self.height;
+        }
+
+        let lines: Vec<Line> = self.snapshot.rows.iter().skip(self.top).take(self.height).cloned().collect();
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines).style(Style::default().bg(theme.background).fg(theme.text)), area);
+
+        let buffer = f.buffer_mut();
+        for (y, line) in (area.y..area.y + area.height).zip(self.top..self.texts.len()) {
+            if let Some((from, to)) = self.selected_columns(line) {
+                for column in from..to.min(area.width as usize) {
+                    if let Some(cell) = buffer.cell_mut((area.x + column as u16, y)) {
+                        cell.set_style(theme.styles.selection);
+                    }
+                }
+            }
+            if line == self.cursor.0
+                && let Some(cell) = buffer.cell_mut((area.x + (self.cursor.1 as u16).min(area.width.saturating_sub(1)), y))
+            {
+                cell.set_style(Style::default().add_modifier(Modifier::REVERSED));
+            }
+        }
+
+        // Where the cursor is in the scrollback, in the top right corner as tmux shows it
+        let position = format!("[{}/{}]", self.cursor.0 + 1, self.texts.len());
+        let width = position.len() as u16;
+        if width < area.width {
+            let corner = Rect { x: area.x + area.width - width, y: area.y, width, height: 1 };
+            f.render_widget(Paragraph::new(position).style(theme.styles.selection), corner);
+        }
+    }
+
+    /// Move the cursor, snapping the column to the start of a character
+    fn move_to(&mut self, line: usize, column: usize) {
+        let column = self.columns(line).into_iter().take_while(|&start| start <= column).last().unwrap_or(0);
+        self.cursor = (line, column);
+        // Once the cursor moves the selection is no longer exactly the printed output
+        self.whole_output = None;
+    }
+
+    /// Get the column each character of a line starts at
+    fn columns(&self, line: usize) -> Vec<usize> {
+        let Some(text) = self.texts.get(line) else {
+            return Vec::new();
+        };
+        let mut column = 0;
+        text::graphemes(text)
+            .map(|grapheme| {
+                let start = column;
+                column += text::width(grapheme);
+                start
+            })
+            .collect()
+    }
+
+    /// Get the columns words start at on a line
+    fn word_starts(&self, line: usize) -> Vec<usize> {
+        let Some(text) = self.texts.get(line) else {
+            return Vec::new();
+        };
+        let mut starts = Vec::new();
+        let (mut column, mut after_space) = (0, true);
+        for grapheme in text::graphemes(text) {
+            let space = grapheme.chars().all(char::is_whitespace);
+            if after_space && !space {
+                starts.push(column);
+            }
+            after_space = space;
+            column += text::width(grapheme);
+        }
+        starts
+    }
+
+    /// Get the index and first line of the block holding a line
+    fn block_at(&self, line: usize) -> (usize, usize) {
+        let starts = &self.snapshot.block_rows[..self.snapshot.block_rows.len().saturating_sub(1)];
+        let index = starts.partition_point(|&start| start <= line).saturating_sub(1);
+        (index, starts.get(index).copied().unwrap_or(0))
+    }
+
+    /// Select the lines of a block below its header, returning whether there are any
+    fn select_output(&mut self, index: usize) -> bool {
+        let rows = &self.snapshot.block_rows;
+        let (Some(&start), Some(&end)) = (rows.get(index), rows.get(index + 1)) else {
+            return false;
+        };
+        let header = 1 + self.snapshot.continued[start + 1..end].iter().take_while(|&&continued| continued).count();
+        // The last line of a block is the blank one separating it from the next
+        let last = end.saturating_sub(2);
+        if start + header > last || self.snapshot.outputs[index].is_empty() {
+            return false;
+        }
+        self.rectangle = false;
+        self.anchor = Some((start + header, 0));
+        self.cursor = (last, 0);
+        self.move_line_end();
+        self.whole_output = Some(index);
+        true
+    }
+
+    /// Get the selection's ends in reading order
+    fn bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.anchor.map(|anchor| (anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    /// Get the columns selected on a line, as a start and an end past it
+    fn selected_columns(&self, line: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.bounds()?;
+        if line < start.0 || line > end.0 {
+            return None;
+        }
+        let width = |line: usize, column: usize| {
+            self.texts.get(line).map_or(1, |text| text::width(text::slice_columns(text, column, column + 1)).max(1))
+        };
+        if self.rectangle {
+            let (left, right) = (start.1.min(end.1), start.1.max(end.1));
+            return Some((left, right + width(end.0, right)));
+        }
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 + width(line, end.1) } else { text::width(&self.texts[line]) };
+        (from < to).then_some((from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        let rows = ["✔ cat notes (10:00:00)", "  alpha beta", "  gamma delta ep", "silon", "", "✔ ls (10:00:01)", "  one two", ""];
+        Snapshot {
+            rows: rows.iter().map(|row| Line::from(row.to_string())).collect(),
+            continued: vec![false, false, false, true, false, false, false, false],
+            block_rows: vec![0, 5, 8],
+            outputs: vec!["alpha beta\ngamma delta epsilon\n".to_string(), "one two\n".to_string()],
+            last_command: Some(0),
+            top: 0,
+        }
+    }
+
+    #[test]
+    fn test_selection_and_yank() {
+        let mut copy = CopyMode::new(snapshot(), 8);
+        assert_eq!(copy.cursor(), (7, 0));
+        assert_eq!(copy.status_segment(), " 📋 COPY ");
+
+        // Lines broken by wrapping are joined again
+        copy.move_top();
+        copy.move_lines(2);
+        copy.move_word_forward();
+        copy.move_word_forward();
+        assert_eq!(copy.cursor(), (2, 8));
+        copy.toggle_selection();
+        copy.move_lines(1);
+        copy.move_line_end();
+        assert_eq!(copy.selected_text().unwrap(), "delta epsilon");
+        copy.move_word_back();
+        assert_eq!(copy.cursor(), (3, 0));
+
+        // A rectangle takes the same columns from every line
+        copy.toggle_selection();
+        copy.move_top();
+        copy.move_lines(1);
+        copy.move_right();
+        copy.move_right();
+        copy.toggle_rectangle();
+        copy.move_lines(1);
+        for _ in 0..4 {
+            copy.move_right();
+        }
+        assert_eq!(copy.selected_text().unwrap(), "alpha\ngamma");
+
+        // Whole output is yanked as printed
+        assert!(copy.select_last_output());
+        assert_eq!(copy.text_to_yank(), "alpha beta\ngamma delta epsilon\n");
+        copy.move_next_block();
+        assert_eq!(copy.cursor(), (5, 0));
+        assert!(copy.select_block_output());
+        assert_eq!(copy.text_to_yank(), "one two\n");
+        copy.move_previous_block();
+        copy.toggle_selection();
+        assert_eq!(copy.text_to_yank(), "✔ ls (10:00:01)");
+    }
+}
@@ -0,0 +1,79 @@
+//! Diff review widget for the AI Terminal
+//!
+//! Shows a file edit proposed by the model as a unified diff, with added
+//! and removed lines colored, until the user applies or rejects it.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::edits::{DiffLineKind, ProposedEdit};
+use crate::theme::Theme;
+
+/// Popup reviewing a proposed edit
+pub struct DiffReview {
+    edit: ProposedEdit,
+    /// The diff, split into lines once
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+impl DiffReview {
+    /// Create a review of an edit
+    pub fn new(edit: ProposedEdit) -> Self {
+        let lines = edit.unified_diff().lines().map(str::to_string).collect();
+        Self { edit, lines, scroll: 0 }
+    }
+
+    /// Get the edit under review
+    pub fn edit(&self) -> &ProposedEdit {
+        &self.edit
+    }
+
+    /// Scroll by a number of lines, up for negative ones
+    pub fn scroll(&mut self, lines: isize) {
+        self.scroll = self.scroll.saturating_add_signed(lines).min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Render the review
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+        let (added, removed) = self.edit.counts();
+        let action = if self.edit.old.is_some() { "Edit" } else { "New file" };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("{}: {} (+{} -{})", action, self.edit.shown, added, removed));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        let lines: Vec<Line> = self.lines
+            .iter()
+            .skip(self.scroll)
+            .take(chunks[0].height as usize)
+            .map(|line| {
+                let style = match DiffLineKind::of(line) {
+                    DiffLineKind::Header => Style::default().fg(theme.secondary).add_modifier(Modifier::BOLD),
+                    DiffLineKind::Hunk => Style::default().fg(theme.accent),
+                    DiffLineKind::Added => Style::default().fg(theme.success),
+                    DiffLineKind::Removed => Style::default().fg(theme.error),
+                    DiffLineKind::Context => Style::default().fg(theme.text),
+                };
+                Line::from(Span::styled(line.clone(), style))
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), chunks[0]);
+
+        let hints = Paragraph::new("a: Apply | r/Esc: Reject | ↑↓ PgUp/PgDn: Scroll").style(theme.styles.status_info);
+        f.render_widget(hints, chunks[1]);
+    }
+}
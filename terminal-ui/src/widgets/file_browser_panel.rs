@@ -0,0 +1,107 @@
+//! File browser panel widget for the AI Terminal
+//!
+//! Docks the tree of a [`FileBrowser`] over the left of the panes and, while
+//! a file is previewed, shows the file over the rest of them, highlighted in
+//! the theme's colors when built with syntax highlighting.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::file_browser::FileBrowser;
+use crate::theme::Theme;
+use crate::widgets::models_view::format_size;
+
+/// Narrowest the tree gets
+const MIN_WIDTH: u16 = 28;
+
+/// Widest the tree gets
+const MAX_WIDTH: u16 = 48;
+
+/// Panel showing a file browser
+pub struct FileBrowserPanel<'a> {
+    browser: &'a FileBrowser,
+}
+
+impl<'a> FileBrowserPanel<'a> {
+    /// Create a panel for a browser
+    pub fn new(browser: &'a FileBrowser) -> Self {
+        Self { browser }
+    }
+
+    /// Get the area of the tree, docked to the left of the pane area
+    pub fn area(&self, panes: Rect) -> Rect {
+        let width = (panes.width / 3).clamp(MIN_WIDTH, MAX_WIDTH).min(panes.width);
+        Rect::new(panes.x, panes.y, width, panes.height)
+    }
+
+    /// Render the tree, and the preview if a file is open, over the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, theme: &Theme) {
+        let area = self.area(panes);
+        self.render_tree(f, area, theme);
+        if self.browser.preview().is_some() && panes.width > area.width {
+            let preview = Rect::new(area.right(), panes.y, panes.width - area.width, panes.height);
+            self.render_preview(f, preview, theme);
+        }
+    }
+
+    /// Render the tree with the selection scrolled into view
+    fn render_tree(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+        let title = format!("📁 {}", self.browser.root().display());
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(title)
+            .title_bottom("Enter open | i insert | a ask AI | Esc close");
+
+        let items: Vec<ListItem> = self.browser.entries()
+            .iter()
+            .map(|entry| {
+                let marker = match (entry.is_dir, entry.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                let style = if entry.is_dir { Style::default().fg(theme.accent) } else { Style::default().fg(theme.text) };
+                ListItem::new(Line::from(Span::styled(format!("{}{}{}", "  ".repeat(entry.depth), marker, entry.name), style)))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD));
+        let mut state = ListState::default().with_selected(Some(self.browser.selected_index()).filter(|_| !self.browser.entries().is_empty()));
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Render the open file from its scroll position
+    fn render_preview(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let Some(preview) = self.browser.preview() else {
+            return;
+        };
+        f.render_widget(Clear, area);
+        let name = preview.path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let truncated = if preview.is_truncated() { ", start only" } else { "" };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border)
+            .title(format!("{} ({}{})", name, format_size(preview.size), truncated))
+            .title_bottom("PgUp/PgDn scroll | Backspace close preview");
+
+        let lines: Vec<Line> = match &preview.text {
+            Some(text) => {
+                #[cfg(feature = "syntax-highlighting")]
+                let lines = crate::syntax_highlighter::highlight(text, &preview.language, theme);
+                #[cfg(not(feature = "syntax-highlighting"))]
+                let lines: Vec<Line> = text.lines().map(|line| Line::from(Span::styled(line.to_string(), theme.styles.code_block))).collect();
+                lines.into_iter().skip(self.browser.preview_scroll()).take(area.height as usize).collect()
+            }
+            None => vec![Line::from(Span::styled("Binary file; nothing to show", theme.styles.ghost_text))],
+        };
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
@@ -0,0 +1,41 @@
+//! Hint label widget for the AI Terminal
+//!
+//! Draws the label of each URL and path hint mode can choose over the start
+//! of its text, with the letters already typed dimmed, the way kitty shows
+//! its hints.
+
+use ratatui::{
+    style::{Modifier, Style},
+    Frame,
+};
+
+use crate::hints::HintMode;
+use crate::theme::Theme;
+
+/// Labels over the targets of hint mode
+pub struct HintLabels<'a> {
+    hints: &'a HintMode,
+}
+
+impl<'a> HintLabels<'a> {
+    /// Create the labels for hint mode
+    pub fn new(hints: &'a HintMode) -> Self {
+        Self { hints }
+    }
+
+    /// Render the labels of the targets still matching what was typed
+    pub fn render(&self, f: &mut Frame, theme: &Theme) {
+        let label_style = Style::default().fg(theme.background).bg(theme.warning).add_modifier(Modifier::BOLD);
+        let typed_style = label_style.add_modifier(Modifier::DIM);
+        let typed = self.hints.typed().len();
+        let buffer = f.buffer_mut();
+        let area = buffer.area;
+        for (target, label) in self.hints.visible() {
+            for (x, (i, letter)) in (target.column..area.x + area.width).zip(label.char_indices()) {
+                if let Some(cell) = buffer.cell_mut((x, target.row)) {
+                    cell.set_char(letter).set_style(if i < typed { typed_style } else { label_style });
+                }
+            }
+        }
+    }
+}
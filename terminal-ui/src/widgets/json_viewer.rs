@@ -0,0 +1,390 @@
+//! JSON viewer widget for the AI Terminal
+//!
+//! Shows a block's JSON output as a tree of foldable objects and arrays, with
+//! a query bar running jq filters on the document as they are typed. Each
+//! line knows its jq path, which is shown for the selected line and can be
+//! made the query.
+
+use std::collections::HashSet;
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use serde_json::Value;
+
+use crate::json_query;
+use crate::theme::Theme;
+
+/// Lines past which a new viewer starts with everything below the top level folded
+const FOLDED_LINES: usize = 500;
+
+/// What a line of the tree shows
+#[derive(Debug, Clone, PartialEq)]
+enum LineKind {
+    /// Start of an object or array, with its number of entries
+    Open { array: bool, len: usize, collapsed: bool },
+    /// End of an unfolded object or array
+    Close { array: bool },
+    /// A string, number, boolean or null
+    Scalar(Value),
+}
+
+/// Line of the tree
+#[derive(Debug, Clone, PartialEq)]
+struct TreeLine {
+    depth: usize,
+    /// jq path of the value, also identifying it when folded
+    path: String,
+    key: Option<String>,
+    kind: LineKind,
+}
+
+/// Popup showing JSON output as a foldable tree
+pub struct JsonViewer {
+    command: String,
+    document: Value,
+    /// The document, or the result of the query
+    result: Value,
+    query: String,
+    editing: bool,
+    error: Option<String>,
+    /// Paths of the folded objects and arrays
+    collapsed: HashSet<String>,
+    lines: Vec<TreeLine>,
+    selected_index: usize,
+    message: Option<String>,
+}
+
+impl JsonViewer {
+    /// Create a viewer for the JSON output of `command`
+    pub fn new(command: impl Into<String>, document: Value) -> Self {
+        let mut viewer = Self {
+            command: command.into(),
+            result: document.clone(),
+            document,
+            query: String::new(),
+            editing: false,
+            error: None,
+            collapsed: HashSet::new(),
+            lines: Vec::new(),
+            selected_index: 0,
+            message: None,
+        };
+        viewer.update_lines();
+        if viewer.lines.len() > FOLDED_LINES {
+            viewer.collapse_all();
+        }
+        viewer
+    }
+
+    /// Lay the result out as lines, keeping the selection on the same path
+    fn update_lines(&mut self) {
+        let selected = self.lines.get(self.selected_index).map(|line| line.path.clone());
+        let mut lines = Vec::new();
+        flatten(&self.result, None, 0, ".".to_string(), &self.collapsed, &mut lines);
+        self.selected_index = selected
+            .and_then(|path| lines.iter().position(|line| line.path == path))
+            .unwrap_or(0)
+            .min(lines.len().saturating_sub(1));
+        self.lines = lines;
+    }
+
+    /// Get the path of the selected line
+    pub fn selected_path(&self) -> Option<&str> {
+        self.lines.get(self.selected_index).map(|line| line.path.as_str())
+    }
+
+    /// Get the result shown, as indented JSON
+    pub fn result_text(&self) -> String {
+        serde_json::to_string_pretty(&self.result).unwrap_or_default()
+    }
+
+    /// Move the selection up by `lines`
+    pub fn move_selection_up(&mut self, lines: usize) {
+        self.selected_index = self.selected_index.saturating_sub(lines);
+    }
+
+    /// Move the selection down by `lines`
+    pub fn move_selection_down(&mut self, lines: usize) {
+        self.selected_index = (self.selected_index + lines).min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Fold or unfold the object or array the selected line opens or closes
+    pub fn toggle(&mut self) {
+        let Some(line) = self.lines.get(self.selected_index) else {
+            return;
+        };
+        if matches!(line.kind, LineKind::Scalar(_)) {
+            return;
+        }
+        let path = line.path.clone();
+        if !self.collapsed.remove(&path) {
+            self.collapsed.insert(path);
+        }
+        self.update_lines();
+    }
+
+    /// Fold the selected object or array, or go to the one holding the selected line
+    pub fn fold(&mut self) {
+        let Some(line) = self.lines.get(self.selected_index) else {
+            return;
+        };
+        if matches!(line.kind, LineKind::Open { collapsed: false, .. } | LineKind::Close { .. }) {
+            self.collapsed.insert(line.path.clone());
+            self.update_lines();
+        } else if let Some(parent) = self.lines[..self.selected_index]
+            .iter()
+            .rposition(|other| other.depth + 1 == line.depth && matches!(other.kind, LineKind::Open { .. }))
+        {
+            self.selected_index = parent;
+        }
+    }
+
+    /// Unfold the selected object or array
+    pub fn unfold(&mut self) {
+        if let Some(line) = self.lines.get(self.selected_index)
+            && self.collapsed.remove(&line.path)
+        {
+            self.update_lines();
+        }
+    }
+
+    /// Unfold everything
+    pub fn expand_all(&mut self) {
+        self.collapsed.clear();
+        self.update_lines();
+    }
+
+    /// Fold everything below the top level
+    pub fn collapse_all(&mut self) {
+        self.collapsed.clear();
+        self.update_lines();
+        self.collapsed = self.lines
+            .iter()
+            .filter(|line| line.depth > 0 && matches!(line.kind, LineKind::Open { .. }))
+            .map(|line| line.path.clone())
+            .collect();
+        self.update_lines();
+    }
+
+    /// Start typing the query
+    pub fn start_query(&mut self) {
+        self.editing = true;
+    }
+
+    /// Whether keys go to the query
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    /// Stop typing the query, keeping it
+    pub fn end_query(&mut self) {
+        self.editing = false;
+    }
+
+    /// Add a character to the query
+    pub fn push_query_char(&mut self, c: char) {
+        self.query.push(c);
+        self.run_query();
+    }
+
+    /// Remove the last character of the query
+    pub fn query_backspace(&mut self) {
+        crate::text::pop_grapheme(&mut self.query);
+        self.run_query();
+    }
+
+    /// Make the selected line's path the query
+    pub fn query_selected(&mut self) {
+        if let Some(path) = self.selected_path() {
+            // Paths are of the result, which is the document only without a query
+            self.query = if self.query.trim().is_empty() { path.to_string() } else { format!("{} | {}", self.query.trim(), path) };
+            self.run_query();
+        }
+    }
+
+    /// Run the query, keeping the last result while it does not parse or fails
+    fn run_query(&mut self) {
+        let query = self.query.trim();
+        let result = if query.is_empty() { Ok(self.document.clone()) } else { json_query::run(&self.document, query) };
+        match result {
+            Ok(result) => {
+                self.error = None;
+                if result != self.result {
+                    self.result = result;
+                    self.collapsed.clear();
+                    self.update_lines();
+                }
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Show a message below the tree
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Render the viewer
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("{{}} {} ({} lines)", self.command, self.lines.len()));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(inner);
+
+        let query_style = if self.editing { Style::default().fg(theme.text) } else { Style::default().fg(theme.secondary) };
+        let cursor = if self.editing { "▏" } else { "" };
+        let mut query = vec![Span::styled(format!("jq: {}{}", self.query, cursor), query_style)];
+        if let Some(error) = &self.error {
+            query.push(Span::styled(format!("  {}", error), Style::default().fg(theme.error)));
+        }
+        f.render_widget(Paragraph::new(Line::from(query)), chunks[0]);
+
+        let items: Vec<ListItem> = self.lines.iter().map(|line| ListItem::new(self.render_line(line, theme))).collect();
+        let list = List::new(items).highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD));
+        let mut state = ListState::default().with_selected(Some(self.selected_index).filter(|_| !self.lines.is_empty()));
+        f.render_stateful_widget(list, chunks[1], &mut state);
+
+        let status = self.message.clone().unwrap_or_else(|| self.selected_path().unwrap_or_default().to_string());
+        f.render_widget(Paragraph::new(status).style(Style::default().fg(theme.secondary)), chunks[2]);
+
+        let hints = if self.editing {
+            "Type a jq filter, such as .items[] | select(.ready == true) | .name | Enter/Esc: done"
+        } else {
+            "Space: fold | ←→: fold, unfold | e/c: unfold, fold all | /: query | f: query this | y: copy | p: pipe | Esc: close"
+        };
+        f.render_widget(Paragraph::new(hints).style(theme.styles.status_info), chunks[3]);
+    }
+
+    /// Render a line of the tree with its values colored by type
+    fn render_line(&self, line: &TreeLine, theme: &Theme) -> Line<'static> {
+        let mut spans = vec![Span::raw("  ".repeat(line.depth))];
+        spans.push(Span::raw(match line.kind {
+            LineKind::Open { collapsed: true, .. } => "▸ ",
+            LineKind::Open { .. } => "▾ ",
+            _ => "  ",
+        }));
+        if let Some(key) = &line.key {
+            spans.push(Span::styled(key.clone(), Style::default().fg(theme.accent)));
+            spans.push(Span::styled(": ", Style::default().fg(theme.text)));
+        }
+        let bracket = Style::default().fg(theme.text);
+        match &line.kind {
+            LineKind::Open { array, len, collapsed } => {
+                let (open, close, entries) = if *array { ("[", "]", "item") } else { ("{", "}", "key") };
+                if *collapsed {
+                    spans.push(Span::styled(format!("{}…{}", open, close), bracket));
+                    let plural = if *len == 1 { "" } else { "s" };
+                    spans.push(Span::styled(format!(" {} {}{}", len, entries, plural), Style::default().fg(theme.secondary)));
+                } else {
+                    spans.push(Span::styled(open, bracket));
+                }
+            }
+            LineKind::Close { array } => spans.push(Span::styled(if *array { "]" } else { "}" }, bracket)),
+            LineKind::Scalar(value) => {
+                let color = match value {
+                    Value::String(_) => theme.success,
+                    Value::Number(_) => theme.warning,
+                    Value::Bool(_) => theme.primary,
+                    _ => theme.secondary,
+                };
+                spans.push(Span::styled(value.to_string(), Style::default().fg(color)));
+            }
+        }
+        Line::from(spans)
+    }
+}
+
+/// Lay out a value and its unfolded children as lines
+fn flatten(value: &Value, key: Option<String>, depth: usize, path: String, collapsed: &HashSet<String>, lines: &mut Vec<TreeLine>) {
+    let children: Vec<(String, String, &Value)> = match value {
+        Value::Object(object) => object.iter().map(|(name, child)| (name.clone(), child_path(&path, name), child)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, child)| (index.to_string(), bracket_path(&path, &index.to_string()), child))
+            .collect(),
+        scalar => {
+            lines.push(TreeLine { depth, path, key, kind: LineKind::Scalar(scalar.clone()) });
+            return;
+        }
+    };
+    let array = value.is_array();
+    let folded = collapsed.contains(&path);
+    lines.push(TreeLine {
+        depth,
+        path: path.clone(),
+        key,
+        kind: LineKind::Open { array, len: children.len(), collapsed: folded },
+    });
+    if folded {
+        return;
+    }
+    for (name, child_path, child) in children {
+        flatten(child, Some(name), depth + 1, child_path, collapsed, lines);
+    }
+    lines.push(TreeLine { depth, path, key: None, kind: LineKind::Close { array } });
+}
+
+/// Get the jq path of an object's member, quoting keys that are not plain names
+fn child_path(parent: &str, key: &str) -> String {
+    if !key.is_empty() && !key.starts_with(|c: char| c.is_ascii_digit()) && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("{}.{}", parent.trim_end_matches('.'), key)
+    } else {
+        bracket_path(parent, &Value::String(key.to_string()).to_string())
+    }
+}
+
+/// Get the jq path of an index or quoted key, as `.[0]` under the root and `.items[0]` below it
+fn bracket_path(parent: &str, index: &str) -> String {
+    format!("{}[{}]", parent, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fold_and_query_the_tree() {
+        let document = json!({"items": [{"name": "web", "ready": true}, {"name": "db", "ready": false}], "a b": null});
+        let mut viewer = JsonViewer::new("kubectl get pods -o json", document);
+        let paths: Vec<&str> = viewer.lines.iter().map(|line| line.path.as_str()).collect();
+        assert_eq!(paths, [".", ".[\"a b\"]", ".items", ".items[0]", ".items[0].name", ".items[0].ready", ".items[0]", ".items[1]", ".items[1].name", ".items[1].ready", ".items[1]", ".items", "."]);
+
+        // Folding from inside an object first goes to the object, then folds it
+        viewer.move_selection_down(4);
+        viewer.fold();
+        assert_eq!(viewer.selected_path(), Some(".items[0]"));
+        viewer.fold();
+        assert_eq!(viewer.lines.len(), 10);
+        viewer.toggle();
+        assert_eq!(viewer.lines.len(), 13);
+
+        viewer.move_selection_down(1);
+        viewer.query_selected();
+        assert_eq!(viewer.query, ".items[0].name");
+        assert_eq!(viewer.result_text(), "\"web\"");
+
+        // A query that does not parse keeps the last result
+        viewer.query.clear();
+        ".items[] | select(.ready == true".chars().for_each(|c| viewer.push_query_char(c));
+        assert!(viewer.error.is_some());
+        viewer.push_query_char(')');
+        assert!(viewer.error.is_none());
+        assert_eq!(viewer.result_text(), "{\n  \"name\": \"web\",\n  \"ready\": true\n}");
+    }
+}
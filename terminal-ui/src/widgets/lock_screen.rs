@@ -0,0 +1,66 @@
+//! Lock screen widget for the AI Terminal
+//!
+//! While the session is locked, this screen covers the whole terminal with
+//! a passphrase field, so nothing of the session shows through.
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::idle_lock::IdleLock;
+use crate::theme::{Signal, Theme};
+
+/// Privacy screen drawn instead of the session
+pub struct LockScreen<'a> {
+    lock: &'a IdleLock,
+}
+
+impl<'a> LockScreen<'a> {
+    /// Create the screen for a lock
+    pub fn new(lock: &'a IdleLock) -> Self {
+        Self { lock }
+    }
+
+    /// Render the screen over the whole area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(6), Constraint::Fill(1)])
+            .split(area);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Fill(1), Constraint::Length(50), Constraint::Fill(1)])
+            .split(rows[1]);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title("🔒 Session locked");
+
+        let mut lines = if self.lock.has_passphrase() {
+            vec![
+                Line::from("Enter the passphrase to unlock"),
+                Line::from(format!("> {}", "•".repeat(self.lock.entry_len()))).style(Style::default().fg(theme.accent)),
+            ]
+        } else {
+            vec![Line::from("Press Enter to unlock"), Line::from("")]
+        };
+        if self.lock.failed_attempts() > 0 {
+            let message = format!("{} Wrong passphrase ({} attempts)", Signal::Risk.glyph(), self.lock.failed_attempts());
+            lines.push(Line::from(message).style(Signal::Risk.style(theme)));
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .block(block);
+        f.render_widget(paragraph, columns[1]);
+    }
+}
@@ -0,0 +1,371 @@
+//! Log viewer widget for the AI Terminal
+//!
+//! Follows the output of a block as a log, coloring each line by its level
+//! and hiding the lines below a chosen level or not matching a regular
+//! expression. New output is read as the command keeps running, so the
+//! filters apply to lines as they arrive, and the view stays at the bottom
+//! until it is scrolled up.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use regex::{Regex, RegexBuilder};
+use terminal_emulator::{BlockState, OutputBuffer};
+use uuid::Uuid;
+
+use crate::logs::Level;
+use crate::theme::Theme;
+
+/// Most lines kept, the oldest being dropped first
+const MAX_LINES: usize = 20_000;
+
+/// Popup following a block's output as a log
+pub struct LogViewer {
+    block_id: Uuid,
+    command: String,
+    /// Lines read so far, with their levels
+    lines: Vec<(String, Option<Level>)>,
+    /// Lines of the block's output read, counting those its scrollback dropped
+    synced: usize,
+    running: bool,
+    /// Least severe level shown, or `None` for every line
+    min_level: Option<Level>,
+    pattern: String,
+    regex: Option<Regex>,
+    editing: bool,
+    error: Option<String>,
+    /// Indices into `lines` of the lines shown
+    visible: Vec<usize>,
+    selected_index: usize,
+    /// Whether the selection stays on the newest line
+    following: bool,
+    message: Option<String>,
+}
+
+impl LogViewer {
+    /// Create a viewer following the block with `block_id`
+    pub fn new(block_id: Uuid, command: impl Into<String>) -> Self {
+        Self {
+            block_id,
+            command: command.into(),
+            lines: Vec::new(),
+            synced: 0,
+            running: false,
+            min_level: None,
+            pattern: String::new(),
+            regex: None,
+            editing: false,
+            error: None,
+            visible: Vec::new(),
+            selected_index: 0,
+            following: true,
+            message: None,
+        }
+    }
+
+    /// Get the ID of the block followed
+    pub fn block_id(&self) -> Uuid {
+        self.block_id
+    }
+
+    /// Read the output the block printed since the last sync
+    pub fn sync(&mut self, output: &OutputBuffer, state: &BlockState) {
+        self.running = matches!(state, BlockState::Running);
+        let dropped = output.dropped_lines();
+        let total = dropped + output.line_count();
+        if total < self.synced {
+            // The output was cleared, so it is read again from the start
+            self.lines.clear();
+            self.synced = 0;
+        }
+        // The last line read is read again, as it may have been unfinished and grown since
+        let first = self.synced.saturating_sub(1).max(dropped);
+        let rereads_last = self.synced > 0 && first == self.synced - 1;
+        let unchanged = || output.lines().last().map(|line| line.trim_end_matches(['\n', '\r'])) == self.lines.last().map(|(line, _)| line.as_str());
+        if total == self.synced && rereads_last && unchanged() {
+            return;
+        }
+        if rereads_last {
+            self.lines.pop();
+        }
+        for line in output.lines().skip(first - dropped) {
+            let line = line.trim_end_matches(['\n', '\r']).to_string();
+            let level = Level::detect(&line);
+            self.lines.push((line, level));
+        }
+        self.synced = total;
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        if excess > 0 {
+            self.lines.drain(..excess);
+            self.visible.iter_mut().for_each(|i| *i = i.saturating_sub(excess));
+        }
+        self.update_visible();
+    }
+
+    /// Apply the filters, keeping the selection on its line, or at the bottom when following
+    fn update_visible(&mut self) {
+        let selected = self.visible.get(self.selected_index).copied();
+        self.visible = (0..self.lines.len()).filter(|&i| self.shows(i)).collect();
+        self.selected_index = if self.following {
+            self.visible.len().saturating_sub(1)
+        } else {
+            selected
+                .and_then(|selected| self.visible.iter().position(|&i| i >= selected))
+                .unwrap_or(self.visible.len().saturating_sub(1))
+        };
+    }
+
+    /// Check whether a line passes the filters
+    fn shows(&self, index: usize) -> bool {
+        let (line, level) = &self.lines[index];
+        // Lines without a level, such as stack traces, stay with the lines around them
+        let level_passes = match (self.min_level, level) {
+            (Some(min), Some(level)) => *level >= min,
+            _ => true,
+        };
+        level_passes && self.regex.as_ref().is_none_or(|regex| regex.is_match(line))
+    }
+
+    /// Get the lines shown
+    pub fn visible(&self) -> Vec<&str> {
+        self.visible.iter().map(|&i| self.lines[i].0.as_str()).collect()
+    }
+
+    /// Get the least severe level shown
+    pub fn min_level(&self) -> Option<Level> {
+        self.min_level
+    }
+
+    /// Move the selection up by `lines`, which stops following the output
+    pub fn move_selection_up(&mut self, lines: usize) {
+        self.selected_index = self.selected_index.saturating_sub(lines);
+        self.following = false;
+    }
+
+    /// Move the selection down by `lines`, following the output again at the bottom
+    pub fn move_selection_down(&mut self, lines: usize) {
+        self.selected_index = (self.selected_index + lines).min(self.visible.len().saturating_sub(1));
+        self.following = self.selected_index + 1 >= self.visible.len();
+    }
+
+    /// Select the next line at error level, searching down, or up with `backwards`
+    pub fn jump_to_error(&mut self, backwards: bool) {
+        let is_error = |&&i: &&usize| self.lines[i].1 == Some(Level::Error);
+        let found = if backwards {
+            self.visible[..self.selected_index].iter().rposition(|i| is_error(&i))
+        } else {
+            self.visible
+                .iter()
+                .skip(self.selected_index + 1)
+                .position(|i| is_error(&i))
+                .map(|offset| self.selected_index + 1 + offset)
+        };
+        match found {
+            Some(index) => {
+                self.selected_index = index;
+                self.following = false;
+                self.message = None;
+            }
+            None => self.message = Some(format!("No more errors {}", if backwards { "above" } else { "below" })),
+        }
+    }
+
+    /// Show only lines at the next more severe level, going back to every line after errors
+    pub fn cycle_level(&mut self) {
+        self.min_level = match self.min_level {
+            None => Some(Level::Debug),
+            Some(Level::Error) => None,
+            Some(level) => Level::ALL.iter().copied().find(|other| *other > level),
+        };
+        self.update_visible();
+    }
+
+    /// Show only lines at `level` or more severe ones, or every line
+    pub fn set_min_level(&mut self, level: Option<Level>) {
+        self.min_level = level;
+        self.update_visible();
+    }
+
+    /// Start typing the regular expression
+    pub fn start_filter(&mut self) {
+        self.editing = true;
+    }
+
+    /// Whether keys go to the regular expression
+    pub fn is_filtering(&self) -> bool {
+        self.editing
+    }
+
+    /// Stop typing the regular expression, keeping it
+    pub fn end_filter(&mut self) {
+        self.editing = false;
+    }
+
+    /// Add a character to the regular expression
+    pub fn push_filter_char(&mut self, c: char) {
+        self.pattern.push(c);
+        self.compile();
+    }
+
+    /// Remove the last character of the regular expression, ending it once empty
+    pub fn filter_backspace(&mut self) {
+        if !crate::text::pop_grapheme(&mut self.pattern) {
+            self.editing = false;
+        }
+        self.compile();
+    }
+
+    /// Set the regular expression
+    pub fn set_pattern(&mut self, pattern: &str) {
+        self.pattern = pattern.to_string();
+        self.compile();
+    }
+
+    /// Drop the level and the regular expression
+    pub fn clear_filters(&mut self) {
+        self.min_level = None;
+        self.pattern.clear();
+        self.editing = false;
+        self.compile();
+    }
+
+    /// Compile the regular expression, ignoring case unless it has capitals, and keep the last one while it does not
+    fn compile(&mut self) {
+        if self.pattern.is_empty() {
+            self.regex = None;
+            self.error = None;
+        } else {
+            let ignore_case = !self.pattern.chars().any(char::is_uppercase);
+            match RegexBuilder::new(&self.pattern).case_insensitive(ignore_case).build() {
+                Ok(regex) => {
+                    self.regex = Some(regex);
+                    self.error = None;
+                }
+                Err(_) => self.error = Some("incomplete expression".to_string()),
+            }
+        }
+        self.update_visible();
+    }
+
+    /// Get the lines shown as text
+    pub fn text(&self) -> String {
+        self.visible().join("\n")
+    }
+
+    /// Show a message below the log
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Render the viewer
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+
+        let errors = self.lines.iter().filter(|(_, level)| *level == Some(Level::Error)).count();
+        let state = if self.running { ", running" } else { "" };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("📜 {} ({} of {} lines, {} errors{})", self.command, self.visible.len(), self.lines.len(), errors, state));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(inner);
+
+        let level = self.min_level.map_or("all levels".to_string(), |level| format!("{} and above", level));
+        let cursor = if self.editing { "▏" } else { "" };
+        let filter_style = if self.editing { Style::default().fg(theme.text) } else { Style::default().fg(theme.secondary) };
+        let mut filters = vec![
+            Span::styled(format!("Level: {} | ", level), Style::default().fg(theme.secondary)),
+            Span::styled(format!("Regex: {}{}", self.pattern, cursor), filter_style),
+        ];
+        if let Some(error) = &self.error {
+            filters.push(Span::styled(format!("  {}", error), Style::default().fg(theme.error)));
+        }
+        f.render_widget(Paragraph::new(Line::from(filters)), chunks[0]);
+
+        let items: Vec<ListItem> = self.visible.iter().map(|&i| ListItem::new(self.render_line(i, theme))).collect();
+        let list = List::new(items).highlight_style(theme.styles.selection);
+        let mut state = ListState::default().with_selected(Some(self.selected_index).filter(|_| !self.visible.is_empty()));
+        f.render_stateful_widget(list, chunks[1], &mut state);
+
+        let status = self.message.clone().unwrap_or_else(|| {
+            if self.following { "Following the output".to_string() } else { "Scrolled; End follows the output again".to_string() }
+        });
+        f.render_widget(Paragraph::new(status).style(Style::default().fg(theme.secondary)), chunks[2]);
+
+        let hints = if self.editing {
+            "Type a regular expression, matched ignoring case unless it has capitals | Enter/Esc: done"
+        } else {
+            "n/N: next, previous error | l: level | /: regex | c: clear | y: copy shown | End: follow | Esc: close"
+        };
+        f.render_widget(Paragraph::new(hints).style(theme.styles.status_info), chunks[3]);
+    }
+
+    /// Render a line colored by its level, with the matches of the regular expression highlighted
+    fn render_line(&self, index: usize, theme: &Theme) -> Line<'static> {
+        let (line, level) = &self.lines[index];
+        let style = match level {
+            Some(Level::Error) => Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+            Some(Level::Warn) => Style::default().fg(theme.warning),
+            Some(Level::Info) => Style::default().fg(theme.text),
+            Some(Level::Debug | Level::Trace) => Style::default().fg(theme.secondary),
+            None => Style::default().fg(theme.text),
+        };
+        let Some(regex) = &self.regex else {
+            return Line::from(Span::styled(line.clone(), style));
+        };
+        let mut spans = Vec::new();
+        let mut end = 0;
+        for found in regex.find_iter(line).filter(|found| !found.is_empty()) {
+            spans.push(Span::styled(line[end..found.start()].to_string(), style));
+            spans.push(Span::styled(found.as_str().to_string(), style.add_modifier(Modifier::REVERSED)));
+            end = found.end();
+        }
+        spans.push(Span::styled(line[end..].to_string(), style));
+        Line::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follow_filter_and_jump() {
+        let mut output = OutputBuffer::new();
+        output.push_str("INFO starting\nERROR first failure\n  at main.rs:3\nWARN slow");
+        let mut viewer = LogViewer::new(Uuid::new_v4(), "kubectl logs -f web");
+        viewer.sync(&output, &BlockState::Running);
+        assert_eq!(viewer.visible(), ["INFO starting", "ERROR first failure", "  at main.rs:3", "WARN slow"]);
+
+        // The unfinished line is read again once it grows
+        output.push_str(" request\nDEBUG tick\nERROR second failure\n");
+        viewer.sync(&output, &BlockState::Running);
+        assert_eq!(viewer.visible()[3..], ["WARN slow request", "DEBUG tick", "ERROR second failure"]);
+        assert_eq!(viewer.selected_index, 5);
+
+        viewer.set_min_level(Some(Level::Warn));
+        assert_eq!(viewer.visible(), ["ERROR first failure", "  at main.rs:3", "WARN slow request", "ERROR second failure"]);
+        viewer.jump_to_error(true);
+        assert_eq!(viewer.selected_index, 0);
+        viewer.jump_to_error(true);
+        assert!(viewer.message.is_some());
+
+        // New lines pass through the filters while scrolled up, and the selection stays put
+        output.push_str("ERROR third failure\nINFO done\n");
+        viewer.set_pattern("third|first");
+        viewer.sync(&output, &BlockState::Success);
+        assert_eq!(viewer.visible(), ["ERROR first failure", "ERROR third failure"]);
+        assert_eq!(viewer.selected_index, 0);
+        viewer.jump_to_error(false);
+        assert_eq!(viewer.selected_index, 1);
+    }
+}
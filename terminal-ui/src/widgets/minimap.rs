@@ -0,0 +1,135 @@
+//! Minimap gutter widget for the AI Terminal
+//!
+//! A one-column gutter on the right of a pane that shows the whole
+//! scrollback at once: where the view is, where each command block starts,
+//! and which blocks failed. Clicking a row of the gutter scrolls there.
+//! Search hits are not marked, as there is no search over pane output to
+//! take them from.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+};
+
+use crate::theme::{Signal, Theme};
+
+/// What one row of the gutter shows, most important first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapCell {
+    /// A failed block starts in the rows this cell covers
+    Failure,
+    /// Another block starts in the rows this cell covers
+    Boundary {
+        /// Whether the rows are inside the view
+        in_view: bool,
+    },
+    /// The rows are inside the view
+    View,
+    /// The rows are outside the view
+    Track,
+}
+
+/// Positions of the blocks of a pane, scaled to a gutter
+#[derive(Debug, Clone, Default)]
+pub struct Minimap {
+    /// First row of each block and whether it failed
+    blocks: Vec<(usize, bool)>,
+    /// Rows of all blocks together
+    total_rows: usize,
+    /// First row in view
+    view_top: usize,
+    /// Rows in view
+    view_height: usize,
+}
+
+impl Minimap {
+    /// Create a minimap from the first row of each block and whether it failed
+    pub fn new(blocks: Vec<(usize, bool)>, total_rows: usize, view_top: usize, view_height: usize) -> Self {
+        Self { blocks, total_rows, view_top, view_height }
+    }
+
+    /// Content rows covered by gutter row `row` of a gutter `height` rows high
+    fn rows_of(&self, row: usize, height: usize) -> std::ops::Range<usize> {
+        let total = self.total_rows.max(height);
+        (row * total / height)..((row + 1) * total / height).max(row * total / height + 1)
+    }
+
+    /// Compute what each row of a gutter `height` rows high shows
+    pub fn cells(&self, height: usize) -> Vec<MinimapCell> {
+        if height == 0 {
+            return Vec::new();
+        }
+        let view = self.view_top..self.view_top + self.view_height;
+        (0..height)
+            .map(|row| {
+                let rows = self.rows_of(row, height);
+                let in_view = rows.start < view.end && view.start < rows.end;
+                let mut starting = self.blocks.iter().filter(|(start, _)| rows.contains(start)).peekable();
+                if starting.peek().is_none() {
+                    return if in_view { MinimapCell::View } else { MinimapCell::Track };
+                }
+                if starting.any(|&(_, failed)| failed) {
+                    MinimapCell::Failure
+                } else {
+                    MinimapCell::Boundary { in_view }
+                }
+            })
+            .collect()
+    }
+
+    /// Get the first row to show after a click on gutter row `row`, centering the clicked rows
+    pub fn scroll_target(&self, row: usize, height: usize) -> usize {
+        if height == 0 {
+            return 0;
+        }
+        let clicked = self.rows_of(row.min(height - 1), height).start;
+        clicked
+            .saturating_sub(self.view_height / 2)
+            .min(self.total_rows.saturating_sub(self.view_height))
+    }
+
+    /// Draw the gutter into `area`, one column wide
+    pub fn render(&self, buffer: &mut Buffer, area: Rect, theme: &Theme) {
+        let in_view = Style::default().fg(theme.accent);
+        let track = theme.styles.border;
+        for (row, cell) in self.cells(area.height as usize).into_iter().enumerate() {
+            let (symbol, style) = match cell {
+                MinimapCell::Failure => (Signal::Failure.glyph(), Signal::Failure.style(theme)),
+                MinimapCell::Boundary { in_view: true } => ("╋", in_view),
+                MinimapCell::Boundary { in_view: false } => ("┿", track),
+                MinimapCell::View => ("┃", in_view),
+                MinimapCell::Track => ("│", track),
+            };
+            if let Some(cell) = buffer.cell_mut((area.x, area.y + row as u16)) {
+                cell.set_symbol(symbol).set_style(style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_and_clicks() {
+        // Four blocks of 25 rows, the third failed, viewing rows 50..60
+        let minimap = Minimap::new(vec![(0, false), (25, false), (50, true), (75, false)], 100, 50, 10);
+        let cells = minimap.cells(10);
+        assert_eq!(cells[0], MinimapCell::Boundary { in_view: false });
+        assert_eq!(cells[1], MinimapCell::Track);
+        assert_eq!(cells[2], MinimapCell::Boundary { in_view: false });
+        assert_eq!(cells[5], MinimapCell::Failure);
+        assert_eq!(cells[7], MinimapCell::Boundary { in_view: false });
+        assert_eq!(minimap.scroll_target(7, 10), 65);
+        assert_eq!(minimap.scroll_target(0, 10), 0);
+        assert_eq!(minimap.scroll_target(99, 10), 85);
+
+        // Content shorter than the gutter maps one row to one row
+        let short = Minimap::new(vec![(0, false)], 3, 0, 3);
+        let cells = short.cells(5);
+        assert_eq!(cells[0], MinimapCell::Boundary { in_view: true });
+        assert_eq!(cells[1..], [MinimapCell::View, MinimapCell::View, MinimapCell::Track, MinimapCell::Track]);
+    }
+}
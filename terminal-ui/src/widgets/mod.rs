@@ -5,7 +5,57 @@
 pub mod command_palette;
 pub mod confirmation_modal;
 pub mod command_block;
+pub mod theme_picker;
+pub mod scrub_review;
+pub mod path_prompt;
+pub mod models_view;
+pub mod model_picker;
+pub mod persona_picker;
+pub mod minimap;
+pub mod agent_panel;
+pub mod prompt_preview;
+pub mod lock_screen;
+pub mod placeholder_prompt;
+pub mod resource_panel;
+pub mod process_view;
+pub mod file_browser_panel;
+pub mod diff_review;
+pub mod commit_editor;
+pub mod copy_mode;
+pub mod hint_labels;
+pub mod toast;
+pub mod context_panel;
+pub mod chat_panel;
+pub mod pins_panel;
+pub mod table_view;
+pub mod json_viewer;
+pub mod log_viewer;
 
-pub use command_palette::{CommandPalette, Command};
+pub use command_palette::{Action, CommandPalette, Command};
 pub use confirmation_modal::{ConfirmationModal, ModalButton};
-pub use command_block::CommandBlock;
\ No newline at end of file
+pub use command_block::CommandBlock;
+pub use theme_picker::ThemePicker;
+pub use scrub_review::ScrubReview;
+pub use path_prompt::PathPrompt;
+pub use models_view::{ModelsView, PullStatus};
+pub use model_picker::ModelPicker;
+pub use persona_picker::PersonaPicker;
+pub use minimap::Minimap;
+pub use agent_panel::AgentPanel;
+pub use prompt_preview::PromptPreview;
+pub use lock_screen::LockScreen;
+pub use placeholder_prompt::PlaceholderPopup;
+pub use resource_panel::ResourcePanel;
+pub use process_view::ProcessView;
+pub use file_browser_panel::FileBrowserPanel;
+pub use diff_review::DiffReview;
+pub use commit_editor::CommitEditor;
+pub use copy_mode::{CopyMode, Snapshot};
+pub use hint_labels::HintLabels;
+pub use toast::Toast;
+pub use context_panel::ContextPanel;
+pub use chat_panel::ChatPanel;
+pub use pins_panel::PinsPanel;
+pub use table_view::TableView;
+pub use json_viewer::JsonViewer;
+pub use log_viewer::LogViewer;
\ No newline at end of file
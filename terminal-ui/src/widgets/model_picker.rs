@@ -0,0 +1,121 @@
+//! Model picker widget for the AI Terminal
+//!
+//! This widget lists the installed Ollama models in a popup so the model
+//! used by AI commands in the current tab can be switched quickly.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Interactive model selector
+pub struct ModelPicker {
+    models: Vec<String>,
+    selected_index: usize,
+    current_model: String,
+}
+
+impl ModelPicker {
+    /// Create a new model picker with the cursor on the model in use
+    pub fn new(mut models: Vec<String>, current_model: &str) -> Self {
+        models.sort();
+        let selected_index = models
+            .iter()
+            .position(|name| name == current_model)
+            .unwrap_or(0);
+
+        Self {
+            models,
+            selected_index,
+            current_model: current_model.to_string(),
+        }
+    }
+
+    /// Get the model names shown in the picker
+    pub fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    /// Get the name of the highlighted model
+    pub fn selected_model(&self) -> Option<&str> {
+        self.models.get(self.selected_index).map(String::as_str)
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        if !self.models.is_empty() {
+            self.selected_index = if self.selected_index == 0 {
+                self.models.len() - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        if !self.models.is_empty() {
+            self.selected_index = (self.selected_index + 1) % self.models.len();
+        }
+    }
+
+    /// Render the model picker
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let items: Vec<ListItem> = if self.models.is_empty() {
+            vec![ListItem::new(Span::styled("No models installed; pull one from 'Manage Models'", theme.styles.ghost_text))]
+        } else {
+            self.models
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let marker = if *name == self.current_model { "*" } else { " " };
+                    let style = if i == self.selected_index {
+                        theme.styles.selection.add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(theme.text)
+                    };
+
+                    ListItem::new(Line::from(vec![
+                        Span::styled(marker, Style::default().fg(theme.accent)),
+                        Span::raw(" "),
+                        Span::styled(name.clone(), style),
+                    ]))
+                })
+                .collect()
+        };
+
+        let list = List::new(items)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title("Model for this tab (Enter: select, Esc: cancel)")
+            );
+
+        f.render_widget(list, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_picker_starts_on_current_model() {
+        let mut picker = ModelPicker::new(vec!["phi3".to_string(), "llama3".to_string()], "phi3");
+        assert_eq!(picker.models(), ["llama3", "phi3"]);
+        assert_eq!(picker.selected_model(), Some("phi3"));
+
+        picker.move_selection_down();
+        assert_eq!(picker.selected_model(), Some("llama3"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Models view widget for the AI Terminal
+//!
+//! Lists the models installed in Ollama with their size and parameter
+//! details, shows the details of the selected model on request, and displays
+//! the progress of a running pull. The widget only holds display state; the
+//! caller talks to Ollama and feeds the results in.
+
+use ollama_client::{ModelInfo, ModelShow};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Progress of a model pull, for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullStatus {
+    /// Name of the model being pulled
+    pub model: String,
+    /// Latest status reported by Ollama
+    pub status: String,
+    /// Fraction of the current layer downloaded, if known
+    pub fraction: Option<f64>,
+}
+
+/// Popup listing installed models
+pub struct ModelsView {
+    models: Vec<ModelInfo>,
+    selected_index: usize,
+    current_model: String,
+    details: Option<(String, ModelShow)>,
+    pull_input: Option<String>,
+    message: Option<String>,
+}
+
+impl ModelsView {
+    /// Create a view of `models`, marking the model in use
+    pub fn new(models: Vec<ModelInfo>, current_model: &str) -> Self {
+        let mut view = Self {
+            models: Vec::new(),
+            selected_index: 0,
+            current_model: current_model.to_string(),
+            details: None,
+            pull_input: None,
+            message: None,
+        };
+        view.set_models(models);
+        view.selected_index = view.models.iter().position(|model| model.name == current_model).unwrap_or(0);
+        view
+    }
+
+    /// Replace the listed models, keeping the selection on the same model if it still exists
+    pub fn set_models(&mut self, mut models: Vec<ModelInfo>) {
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        let selected = self.selected_model().map(|model| model.name.clone());
+        self.models = models;
+        self.selected_index = selected
+            .and_then(|name| self.models.iter().position(|model| model.name == name))
+            .unwrap_or(0)
+            .min(self.models.len().saturating_sub(1));
+    }
+
+    /// Get the listed models
+    pub fn models(&self) -> &[ModelInfo] {
+        &self.models
+    }
+
+    /// Get the highlighted model
+    pub fn selected_model(&self) -> Option<&ModelInfo> {
+        self.models.get(self.selected_index)
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        if !self.models.is_empty() {
+            self.selected_index = if self.selected_index == 0 {
+                self.models.len() - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        if !self.models.is_empty() {
+            self.selected_index = (self.selected_index + 1) % self.models.len();
+        }
+    }
+
+    /// Show the details of a model
+    pub fn set_details(&mut self, model: &str, details: ModelShow) {
+        self.details = Some((model.to_string(), details));
+    }
+
+    /// Show a status or error message
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Start typing the name of a model to pull
+    pub fn start_pull_input(&mut self) {
+        self.pull_input = Some(String::new());
+    }
+
+    /// Check whether a model name is being typed
+    pub fn is_entering_pull(&self) -> bool {
+        self.pull_input.is_some()
+    }
+
+    /// Type a character of the model name
+    pub fn push_pull_char(&mut self, c: char) {
+        if let Some(input) = &mut self.pull_input {
+            input.push(c);
+        }
+    }
+
+    /// Delete the last character of the model name
+    pub fn pull_backspace(&mut self) {
+        if let Some(input) = &mut self.pull_input {
+            crate::text::pop_grapheme(input);
+        }
+    }
+
+    /// Finish typing, returning the model name unless it is empty
+    pub fn take_pull_input(&mut self) -> Option<String> {
+        self.pull_input.take().map(|input| input.trim().to_string()).filter(|name| !name.is_empty())
+    }
+
+    /// Render the view, with the progress of a running pull if there is one
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme, pull: Option<&PullStatus>) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title("Models");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(7),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        self.render_list(f, chunks[0], theme);
+        self.render_details(f, chunks[1], theme);
+
+        // Pull progress, the pull prompt or the last message
+        if let Some(input) = &self.pull_input {
+            let prompt = Paragraph::new(format!("Pull model: {}▏", input)).style(Style::default().fg(theme.text));
+            f.render_widget(prompt, chunks[2]);
+        } else if let Some(pull) = pull {
+            let label = format!("{}: {}", pull.model, pull.status);
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(theme.accent).bg(theme.background))
+                .ratio(pull.fraction.unwrap_or(0.0))
+                .label(label);
+            f.render_widget(gauge, chunks[2]);
+        } else if let Some(message) = &self.message {
+            let message = Paragraph::new(message.as_str()).style(Style::default().fg(theme.secondary));
+            f.render_widget(message, chunks[2]);
+        }
+
+        let hints = if self.pull_input.is_some() {
+            "Enter: pull | Esc: cancel"
+        } else {
+            "Enter: details | p: pull | d: delete | r: refresh | Esc: close"
+        };
+        f.render_widget(Paragraph::new(hints).style(theme.styles.status_info), chunks[3]);
+    }
+
+    /// Render the list of models
+    fn render_list(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let name_width = self.models.iter().map(|model| model.name.len()).max().unwrap_or(0);
+        let items: Vec<ListItem> = if self.models.is_empty() {
+            vec![ListItem::new(Span::styled("No models installed; press p to pull one", theme.styles.ghost_text))]
+        } else {
+            self.models
+                .iter()
+                .enumerate()
+                .map(|(i, model)| {
+                    let marker = if model.name == self.current_model { "*" } else { " " };
+                    let style = if i == self.selected_index {
+                        theme.styles.selection.add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(theme.text)
+                    };
+                    let details = &model.details;
+                    ListItem::new(Line::from(vec![
+                        Span::styled(marker, Style::default().fg(theme.accent)),
+                        Span::raw(" "),
+                        Span::styled(format!("{:width$}", model.name, width = name_width), style),
+                        Span::styled(
+                            format!(
+                                "  {:>8}  {:>6}  {:<8}  {}",
+                                format_size(model.size),
+                                details.parameter_size,
+                                details.quantization_level,
+                                details.family,
+                            ),
+                            Style::default().fg(theme.secondary),
+                        ),
+                    ]))
+                })
+                .collect()
+        };
+
+        f.render_widget(List::new(items), area);
+    }
+
+    /// Render the details of the selected model, if they were loaded
+    fn render_details(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let block = Block::default().borders(Borders::TOP).border_style(theme.styles.border);
+        let selected = self.selected_model().map(|model| model.name.as_str());
+        let lines: Vec<Line> = match &self.details {
+            Some((name, details)) if Some(name.as_str()) == selected => {
+                let mut lines = vec![Line::from(format!(
+                    "{} · {} · {} · {}",
+                    details.details.family,
+                    details.details.parameter_size,
+                    details.details.quantization_level,
+                    details.details.format,
+                ))];
+                if let Some(parameters) = &details.parameters {
+                    lines.extend(parameters.lines().map(|line| Line::from(line.trim().to_string())));
+                }
+                if let Some(license) = details.license.as_ref().and_then(|license| license.lines().next()) {
+                    lines.push(Line::from(format!("License: {}", license.trim())));
+                }
+                lines
+            }
+            _ => vec![Line::from(Span::styled("Press Enter to show details", theme.styles.ghost_text))],
+        };
+
+        let paragraph = Paragraph::new(lines)
+            .style(Style::default().fg(theme.text))
+            .block(block)
+            .wrap(Wrap { trim: true });
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Format a size in bytes the way Ollama does, with decimal units
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str) -> ModelInfo {
+        ModelInfo {
+            name: name.to_string(),
+            modified_at: String::new(),
+            size: 0,
+            digest: String::new(),
+            details: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_selection_follows_model_across_refresh() {
+        let mut view = ModelsView::new(vec![model("mistral"), model("llama3")], "mistral");
+        assert_eq!(view.selected_model().unwrap().name, "mistral");
+
+        view.set_models(vec![model("phi3"), model("mistral")]);
+        assert_eq!(view.selected_model().unwrap().name, "mistral");
+
+        view.set_models(vec![model("phi3")]);
+        assert_eq!(view.selected_model().unwrap().name, "phi3");
+    }
+
+    #[test]
+    fn test_pull_input() {
+        let mut view = ModelsView::new(Vec::new(), "llama3");
+        view.start_pull_input();
+        for c in " phi3x".chars() {
+            view.push_pull_char(c);
+        }
+        view.pull_backspace();
+        assert_eq!(view.take_pull_input(), Some("phi3".to_string()));
+        assert!(!view.is_entering_pull());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4_661_224_676), "4.7 GB");
+    }
+}
@@ -0,0 +1,101 @@
+//! File path prompt widget for the AI Terminal
+//!
+//! A single-line input in a popup, used when an action needs a destination
+//! file. The prompt starts with a suggested path that the user can edit.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::text;
+use crate::theme::Theme;
+
+/// Popup asking for a file path
+pub struct PathPrompt {
+    title: String,
+    hint: String,
+    input: String,
+}
+
+impl PathPrompt {
+    /// Create a prompt pre-filled with a suggested path
+    pub fn new(title: impl Into<String>, hint: impl Into<String>, suggestion: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            hint: hint.into(),
+            input: suggestion.into(),
+        }
+    }
+
+    /// Get the entered path
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Type a character at the end of the path
+    pub fn push_char(&mut self, c: char) {
+        self.input.push(c);
+    }
+
+    /// Delete the last character of the path
+    pub fn backspace(&mut self) {
+        text::pop_grapheme(&mut self.input);
+    }
+
+    /// Delete the last path component, like Ctrl+W in a shell
+    pub fn delete_component(&mut self) {
+        let trimmed = self.input.trim_end_matches('/');
+        let keep = trimmed.rfind('/').map_or(0, |i| i + 1);
+        self.input.truncate(keep);
+    }
+
+    /// Render the prompt
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(self.title.as_str());
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let hint = Paragraph::new(self.hint.as_str()).style(Style::default().fg(theme.secondary));
+        f.render_widget(hint, chunks[0]);
+
+        // Show the end of long paths, where the file name is
+        let width = chunks[1].width.saturating_sub(1) as usize;
+        let visible = text::tail_to_width(&self.input, width);
+        let input = Paragraph::new(format!("{}▏", visible)).style(Style::default().fg(theme.text));
+        f.render_widget(input, chunks[1]);
+
+        let hints = Paragraph::new("Enter: confirm | Ctrl+W: delete component | Esc: cancel")
+            .style(theme.styles.status_info);
+        f.render_widget(hints, chunks[3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editing() {
+        let mut prompt = PathPrompt::new("Export", "", "/tmp/session.md");
+        prompt.delete_component();
+        assert_eq!(prompt.input(), "/tmp/");
+        prompt.push_char('x');
+        prompt.backspace();
+        prompt.push_char('a');
+        assert_eq!(prompt.input(), "/tmp/a");
+    }
+}
@@ -0,0 +1,113 @@
+//! Persona picker widget for the AI Terminal
+//!
+//! This widget lists the saved personas in a popup so the system prompt used
+//! by AI commands in the current tab can be switched. The first entry turns
+//! the persona off, falling back to the configured system prompt.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Interactive persona selector
+pub struct PersonaPicker {
+    personas: Vec<String>,
+    /// Highlighted row; row 0 is "no persona"
+    selected_index: usize,
+    current_persona: Option<String>,
+}
+
+impl PersonaPicker {
+    /// Create a new persona picker with the cursor on the persona in use
+    pub fn new(personas: Vec<String>, current_persona: Option<&str>) -> Self {
+        let selected_index = current_persona
+            .and_then(|current| personas.iter().position(|name| name == current))
+            .map_or(0, |i| i + 1);
+
+        Self {
+            personas,
+            selected_index,
+            current_persona: current_persona.map(str::to_string),
+        }
+    }
+
+    /// Get the highlighted persona, `None` for the "no persona" row
+    pub fn selected_persona(&self) -> Option<&str> {
+        self.selected_index
+            .checked_sub(1)
+            .and_then(|i| self.personas.get(i))
+            .map(String::as_str)
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        let rows = self.personas.len() + 1;
+        self.selected_index = (self.selected_index + rows - 1) % rows;
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        self.selected_index = (self.selected_index + 1) % (self.personas.len() + 1);
+    }
+
+    /// Render the persona picker
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let rows = std::iter::once((None, "(no persona)"))
+            .chain(self.personas.iter().map(|name| (Some(name.as_str()), name.as_str())));
+        let items: Vec<ListItem> = rows
+            .enumerate()
+            .map(|(i, (persona, label))| {
+                let marker = if persona == self.current_persona.as_deref() { "*" } else { " " };
+                let style = if i == self.selected_index {
+                    theme.styles.selection.add_modifier(Modifier::BOLD)
+                } else if persona.is_none() {
+                    theme.styles.ghost_text
+                } else {
+                    Style::default().fg(theme.text)
+                };
+
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::default().fg(theme.accent)),
+                    Span::raw(" "),
+                    Span::styled(label.to_string(), style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title("Persona for this tab (Enter: select, e: edit, n: new, Esc: cancel)")
+            );
+
+        f.render_widget(list, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_picker_starts_on_current_persona() {
+        let mut picker = PersonaPicker::new(vec!["teacher".to_string(), "terse".to_string()], Some("terse"));
+        assert_eq!(picker.selected_persona(), Some("terse"));
+
+        picker.move_selection_down();
+        assert_eq!(picker.selected_persona(), None);
+        picker.move_selection_up();
+        picker.move_selection_up();
+        assert_eq!(picker.selected_persona(), Some("teacher"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Pins panel widget for the AI Terminal
+//!
+//! Docks the list of [`Pins`] over the right of the panes, with the selected
+//! block's command, directory, tags and the end of its output below it.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::pins::Pins;
+use crate::theme::Theme;
+
+/// Narrowest the panel gets
+const MIN_WIDTH: u16 = 36;
+
+/// Widest the panel gets
+const MAX_WIDTH: u16 = 64;
+
+/// Most rows the list takes before the details
+const MAX_LIST_ROWS: u16 = 12;
+
+/// Panel showing the pinned blocks
+pub struct PinsPanel<'a> {
+    pins: &'a Pins,
+}
+
+impl<'a> PinsPanel<'a> {
+    /// Create a panel for the pins
+    pub fn new(pins: &'a Pins) -> Self {
+        Self { pins }
+    }
+
+    /// Get the areas of the list and of the details, docked to the right of the pane area
+    pub fn areas(&self, panes: Rect) -> (Rect, Rect) {
+        let width = (panes.width / 3).clamp(MIN_WIDTH, MAX_WIDTH).min(panes.width);
+        let list_height = (self.pins.pins().len().max(1) as u16 + 2).min(MAX_LIST_ROWS).min(panes.height / 2);
+        let x = panes.right() - width;
+        (
+            Rect::new(x, panes.y, width, list_height),
+            Rect::new(x, panes.y + list_height, width, panes.height - list_height),
+        )
+    }
+
+    /// Render the list and the selected pin's details over the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, theme: &Theme) {
+        let (list_area, details_area) = self.areas(panes);
+        f.render_widget(Clear, list_area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!(" 📌 Pinned Blocks ({}) ", self.pins.pins().len()));
+        if self.pins.pins().is_empty() {
+            let empty = Paragraph::new(Line::from(Span::styled("Nothing pinned; /pin pins a block", theme.styles.ghost_text))).block(block);
+            f.render_widget(empty, list_area);
+        } else {
+            let items: Vec<ListItem> = self.pins.pins()
+                .iter()
+                .map(|pin| {
+                    ListItem::new(Line::from(vec![
+                        Span::raw(format!("{} ", pin.block.status_icon())),
+                        Span::styled(pin.block.command.clone(), Style::default().fg(theme.command)),
+                    ]))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD));
+            let mut state = ListState::default().with_selected(Some(self.pins.selected_index()));
+            f.render_stateful_widget(list, list_area, &mut state);
+        }
+
+        if details_area.height < 3 {
+            return;
+        }
+        f.render_widget(Clear, details_area);
+        let mut lines = Vec::new();
+        if let Some(pin) = self.pins.selected() {
+            let block = &pin.block;
+            lines.push(Line::from(Span::styled(format!("$ {}", block.command), Style::default().fg(theme.command).add_modifier(Modifier::BOLD))));
+            let mut meta = format!("{} · pinned {}", block.working_dir, pin.pinned_at.format("%Y-%m-%d %H:%M"));
+            if let Some(code) = block.exit_code {
+                meta.push_str(&format!(" · exit {}", code));
+            }
+            lines.push(Line::from(Span::styled(meta, theme.styles.ghost_text)));
+            let tags = block.all_tags();
+            if !tags.is_empty() {
+                let tags: Vec<String> = tags.iter().map(|tag| format!("#{}", tag)).collect();
+                lines.push(Line::from(Span::styled(tags.join(" "), theme.styles.ghost_text)));
+            }
+            lines.push(Line::default());
+            // The end of the output, where results and errors usually are
+            let output = block.output.text();
+            let output: Vec<&str> = output.trim_end().lines().collect();
+            let room = (details_area.height as usize).saturating_sub(lines.len() + 2);
+            lines.extend(output[output.len().saturating_sub(room)..].iter().map(|line| Line::from(line.to_string())));
+        }
+        let details = Paragraph::new(lines)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title_bottom("Enter run | c copy | y copy output | a attach | d unpin | Esc close"),
+            );
+        f.render_widget(details, details_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use terminal_emulator::{CommandBlock, PinnedBlock};
+
+    #[test]
+    fn test_panel_docks_right() {
+        let pin = |id| PinnedBlock { id, block: CommandBlock::new("ls".to_string(), "/".to_string()), pinned_at: Local::now() };
+        let pins = Pins::new(vec![pin(1), pin(2)]);
+        let (list, details) = PinsPanel::new(&pins).areas(Rect::new(0, 1, 120, 30));
+        assert_eq!(list, Rect::new(80, 1, 40, 4));
+        assert_eq!(details, Rect::new(80, 5, 40, 26));
+        let (list, _) = PinsPanel::new(&Pins::default()).areas(Rect::new(0, 1, 60, 30));
+        assert_eq!(list, Rect::new(24, 1, 36, 3));
+    }
+}
@@ -0,0 +1,63 @@
+//! Placeholder prompt widget for the AI Terminal
+//!
+//! Asks for the values of the placeholders of a command one at a time,
+//! masking those marked secret.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::placeholders::PlaceholderPrompt;
+use crate::theme::Theme;
+
+/// Popup asking for a placeholder value
+pub struct PlaceholderPopup<'a> {
+    prompt: &'a PlaceholderPrompt,
+}
+
+impl<'a> PlaceholderPopup<'a> {
+    /// Create the popup for a prompt
+    pub fn new(prompt: &'a PlaceholderPrompt) -> Self {
+        Self { prompt }
+    }
+
+    /// Render the popup
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let Some(placeholder) = self.prompt.current() else {
+            return;
+        };
+        f.render_widget(Clear, area);
+
+        let (position, count) = self.prompt.progress();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("🔑 Fill in placeholders ({}/{})", position, count));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let command = Paragraph::new(self.prompt.command()).style(Style::default().fg(theme.secondary));
+        f.render_widget(command, chunks[0]);
+
+        let label = if placeholder.secret {
+            format!("{} (secret: masked, set only in the command's environment)", placeholder.name)
+        } else {
+            placeholder.name.clone()
+        };
+        f.render_widget(Paragraph::new(label).style(Style::default().fg(theme.accent)), chunks[1]);
+
+        let input = Paragraph::new(format!("{}▏", self.prompt.entry())).style(Style::default().fg(theme.text));
+        f.render_widget(input, chunks[2]);
+
+        let hints = Paragraph::new("Enter: next | Esc: cancel").style(theme.styles.status_info);
+        f.render_widget(hints, chunks[4]);
+    }
+}
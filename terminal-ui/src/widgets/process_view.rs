@@ -0,0 +1,281 @@
+//! Process manager view widget for the AI Terminal
+//!
+//! A sortable table of processes with a fuzzy filter over names and command
+//! lines, the details of the selected process, and the signal `k` sends to
+//! it. The widget only holds display state; the caller reads the process
+//! table, feeds the rows in and sends signals once they are confirmed.
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState, Wrap},
+    Frame,
+};
+
+use crate::processes::{KillSignal, ProcessInfo, SortKey};
+use crate::theme::Theme;
+use crate::widgets::models_view::format_size;
+
+/// Popup listing processes
+pub struct ProcessView {
+    processes: Vec<ProcessInfo>,
+    /// Indices into `processes` of the rows shown, in display order
+    rows: Vec<usize>,
+    filter: String,
+    filtering: bool,
+    sort: SortKey,
+    signal: KillSignal,
+    selected_index: usize,
+    message: Option<String>,
+    matcher: SkimMatcherV2,
+}
+
+impl ProcessView {
+    /// Create a view of `processes`
+    pub fn new(processes: Vec<ProcessInfo>) -> Self {
+        let mut view = Self {
+            processes: Vec::new(),
+            rows: Vec::new(),
+            filter: String::new(),
+            filtering: false,
+            sort: SortKey::default(),
+            signal: KillSignal::default(),
+            selected_index: 0,
+            message: None,
+            matcher: SkimMatcherV2::default(),
+        };
+        view.set_processes(processes);
+        view
+    }
+
+    /// Replace the listed processes, keeping the selection on the same process if it still runs
+    pub fn set_processes(&mut self, processes: Vec<ProcessInfo>) {
+        let selected = self.selected().map(|process| process.pid);
+        self.processes = processes;
+        self.update_rows(selected);
+    }
+
+    /// Filter and sort the rows, selecting `pid` if it is shown
+    fn update_rows(&mut self, pid: Option<u32>) {
+        let mut scored: Vec<(usize, i64)> = self.processes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, process)| {
+                if self.filter.is_empty() {
+                    return Some((i, 0));
+                }
+                let text = format!("{} {} {}", process.pid, process.name, process.command);
+                self.matcher.fuzzy_match(&text, &self.filter).map(|score| (i, score))
+            })
+            .collect();
+        let sort = self.sort;
+        scored.sort_by(|a, b| sort.compare(&self.processes[a.0], &self.processes[b.0]));
+        self.rows = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected_index = pid
+            .and_then(|pid| self.rows.iter().position(|&i| self.processes[i].pid == pid))
+            .unwrap_or(0)
+            .min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Get the highlighted process
+    pub fn selected(&self) -> Option<&ProcessInfo> {
+        self.rows.get(self.selected_index).map(|&i| &self.processes[i])
+    }
+
+    /// Get the processes shown, in display order
+    pub fn visible(&self) -> Vec<&ProcessInfo> {
+        self.rows.iter().map(|&i| &self.processes[i]).collect()
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        if self.selected_index + 1 < self.rows.len() {
+            self.selected_index += 1;
+        }
+    }
+
+    /// Sort by the next column
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.update_rows(self.selected().map(|process| process.pid));
+    }
+
+    /// Get the signal `k` sends
+    pub fn signal(&self) -> KillSignal {
+        self.signal
+    }
+
+    /// Choose the next signal for `k`
+    pub fn cycle_signal(&mut self) {
+        self.signal = self.signal.next();
+    }
+
+    /// Show a message below the table
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Start typing the filter
+    pub fn start_filter(&mut self) {
+        self.filtering = true;
+    }
+
+    /// Whether keys go to the filter
+    pub fn is_filtering(&self) -> bool {
+        self.filtering
+    }
+
+    /// Stop typing the filter, keeping it
+    pub fn end_filter(&mut self) {
+        self.filtering = false;
+    }
+
+    /// Add a character to the filter
+    pub fn push_filter_char(&mut self, c: char) {
+        self.filter.push(c);
+        self.update_rows(None);
+    }
+
+    /// Remove the last character of the filter, ending it once empty
+    pub fn filter_backspace(&mut self) {
+        if !crate::text::pop_grapheme(&mut self.filter) {
+            self.filtering = false;
+        }
+        self.update_rows(None);
+    }
+
+    /// Render the view
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+
+        let title = format!("ps: Process Manager ({} of {}, by {})", self.rows.len(), self.processes.len(), self.sort.name());
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(4), Constraint::Length(1), Constraint::Length(1)])
+            .split(inner);
+
+        let filter_style = if self.filtering { Style::default().fg(theme.text) } else { Style::default().fg(theme.secondary) };
+        let cursor = if self.filtering { "▏" } else { "" };
+        f.render_widget(Paragraph::new(format!("Filter: {}{}", self.filter, cursor)).style(filter_style), chunks[0]);
+
+        self.render_table(f, chunks[1], theme);
+        self.render_details(f, chunks[2], theme);
+
+        let status = self.message.clone().unwrap_or_else(|| format!("k sends {}", self.signal.name()));
+        f.render_widget(Paragraph::new(status).style(Style::default().fg(theme.secondary)), chunks[3]);
+
+        let hints = if self.filtering {
+            "Type to filter | Enter/Esc: done"
+        } else {
+            "/: filter | s: sort | Tab: signal | k: send signal | r: refresh | Esc: close"
+        };
+        f.render_widget(Paragraph::new(hints).style(theme.styles.status_info), chunks[4]);
+    }
+
+    /// Render the process table, scrolled to the selection
+    fn render_table(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let header = Row::new(["PID", "CPU%", "MEM", "NAME", "COMMAND"])
+            .style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+        let rows: Vec<Row> = self.visible()
+            .into_iter()
+            .map(|process| {
+                Row::new([
+                    process.pid.to_string(),
+                    format!("{:.1}", process.cpu),
+                    format_size(process.memory),
+                    process.name.clone(),
+                    process.command.clone(),
+                ])
+                .style(Style::default().fg(theme.text))
+            })
+            .collect();
+        let widths = [Constraint::Length(8), Constraint::Length(6), Constraint::Length(9), Constraint::Length(20), Constraint::Min(10)];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD));
+        let mut state = TableState::default().with_selected(Some(self.selected_index).filter(|_| !self.rows.is_empty()));
+        f.render_stateful_widget(table, area, &mut state);
+    }
+
+    /// Render the details of the selected process
+    fn render_details(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let block = Block::default().borders(Borders::TOP).border_style(theme.styles.border);
+        let lines = match self.selected() {
+            Some(process) => vec![
+                Line::from(format!(
+                    "{} (pid {}, parent {}) · {} · up {}",
+                    process.name,
+                    process.pid,
+                    process.parent.map_or("-".to_string(), |parent| parent.to_string()),
+                    process.status,
+                    format_run_time(process.run_time),
+                )),
+                Line::from(Span::styled(process.command.clone(), Style::default().fg(theme.secondary))),
+            ],
+            None => vec![Line::from(Span::styled("No processes match the filter", theme.styles.ghost_text))],
+        };
+        let paragraph = Paragraph::new(lines)
+            .style(Style::default().fg(theme.text))
+            .block(block)
+            .wrap(Wrap { trim: true });
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Format a run time in seconds, such as `2d 3h` or `5m 12s`
+fn format_run_time(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds % 60),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, command: &str, cpu: f32) -> ProcessInfo {
+        ProcessInfo { pid, parent: Some(1), name: name.to_string(), command: command.to_string(), cpu, memory: 0, run_time: 0, status: String::new() }
+    }
+
+    #[test]
+    fn test_filter_sort_and_selection() {
+        let mut view = ProcessView::new(vec![
+            process(10, "ollama", "ollama serve", 80.0),
+            process(20, "cargo", "cargo build --release", 150.0),
+            process(30, "bash", "-bash", 0.0),
+        ]);
+        assert_eq!(view.selected().unwrap().pid, 20);
+        view.move_selection_down();
+        assert_eq!(view.selected().unwrap().pid, 10);
+
+        // The selection stays on the same process across refreshes
+        view.set_processes(vec![process(10, "ollama", "ollama serve", 1.0), process(20, "cargo", "cargo build", 2.0)]);
+        assert_eq!(view.selected().unwrap().pid, 10);
+
+        view.start_filter();
+        "olm".chars().for_each(|c| view.push_filter_char(c));
+        assert_eq!(view.visible().len(), 1);
+        "xyz".chars().for_each(|c| view.push_filter_char(c));
+        assert!(view.selected().is_none());
+        assert_eq!(format_run_time(93784), "1d 2h");
+        assert_eq!(format_run_time(312), "5m 12s");
+    }
+}
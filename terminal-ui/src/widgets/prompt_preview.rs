@@ -0,0 +1,141 @@
+//! Prompt preview widget for the AI Terminal
+//!
+//! When an AI request is larger than the configured threshold, this popup
+//! lists the pieces it is made of with their sizes, so large ones can be
+//! dropped before the request is sent.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::prompt_guard::PromptDraft;
+use crate::theme::{Signal, Theme};
+use crate::widgets::models_view::format_size;
+
+/// Review list for the pieces of a large AI request
+pub struct PromptPreview {
+    draft: PromptDraft,
+    /// Token count above which the request was previewed
+    threshold: usize,
+    selected_index: usize,
+}
+
+impl PromptPreview {
+    /// Create a preview of a draft that went over `threshold` tokens
+    pub fn new(draft: PromptDraft, threshold: usize) -> Self {
+        Self {
+            draft,
+            threshold,
+            selected_index: 0,
+        }
+    }
+
+    /// Finish the preview and hand back the draft
+    pub fn into_draft(self) -> PromptDraft {
+        self.draft
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        let count = self.draft.pieces.len();
+        self.selected_index = (self.selected_index + count - 1) % count;
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        self.selected_index = (self.selected_index + 1) % self.draft.pieces.len();
+    }
+
+    /// Include or drop the selected piece
+    pub fn toggle_selected(&mut self) {
+        self.draft.toggle(self.selected_index);
+    }
+
+    /// Render the preview popup
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("Large request: {}", self.draft.command.chars().take(40).collect::<String>()));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        let tokens = self.draft.tokens();
+        let size_style = if tokens > self.threshold { Signal::Risk.style(theme) } else { Style::default().fg(theme.text) };
+        let summary = Paragraph::new(vec![
+            Line::from(vec![
+                Span::raw("Sending "),
+                Span::styled(format!("~{} tokens ({})", tokens, format_size(self.draft.bytes() as u64)), size_style),
+                Span::raw(format!("; requests above ~{} tokens are previewed", self.threshold)),
+            ]),
+            Line::from("Drop pieces you did not mean to send."),
+        ])
+        .style(Style::default().fg(theme.text));
+        f.render_widget(summary, chunks[0]);
+
+        let items: Vec<ListItem> = self.draft.pieces
+            .iter()
+            .enumerate()
+            .map(|(i, piece)| {
+                let marker = match (piece.is_prompt, piece.included) {
+                    (true, _) => "[=] ",
+                    (false, true) => "[x] ",
+                    (false, false) => "[ ] ",
+                };
+                let line = Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(format!("{:<24}", piece.label), Style::default().fg(theme.accent)),
+                    Span::raw(format!("{:>10}  ~{} tokens", format_size(piece.text.len() as u64), piece.tokens())),
+                ]);
+                if i == self.selected_index {
+                    ListItem::new(line).style(theme.styles.selection.add_modifier(Modifier::BOLD))
+                } else if piece.included {
+                    ListItem::new(line).style(Style::default().fg(theme.text))
+                } else {
+                    ListItem::new(line).style(theme.styles.ghost_text)
+                }
+            })
+            .collect();
+        f.render_widget(List::new(items), chunks[1]);
+
+        let hints = Paragraph::new("Space: send/drop | Enter: send | Esc: cancel")
+            .style(theme.styles.status_info);
+        f.render_widget(hints, chunks[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_guard::ContextPiece;
+
+    #[test]
+    fn test_toggle_selected_piece() {
+        let draft = PromptDraft::new("/hi".to_string(), vec![
+            ContextPiece::prompt("hi".to_string()),
+            ContextPiece::context("Bookmarks", "- @src = /src".to_string()),
+        ]);
+        let mut preview = PromptPreview::new(draft, 1);
+        // The prompt itself stays
+        preview.toggle_selected();
+        preview.move_selection_up();
+        preview.toggle_selected();
+
+        let draft = preview.into_draft();
+        assert!(draft.pieces[0].included);
+        assert!(!draft.pieces[1].included);
+        assert_eq!(draft.system(), None);
+    }
+}
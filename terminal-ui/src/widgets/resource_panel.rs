@@ -0,0 +1,90 @@
+//! Resource monitor panel widget for the AI Terminal
+//!
+//! Sits over the bottom right of the panes while `/monitor` is on, with a
+//! line per resource giving its current use and a sparkline of its recent
+//! history.
+
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Sparkline},
+    Frame,
+};
+
+use crate::monitor::{Metric, ResourceMonitor};
+use crate::theme::Theme;
+
+/// Width of the panel, border included
+const WIDTH: u16 = 44;
+
+/// Width of the label and value column
+const LABEL_WIDTH: u16 = 26;
+
+/// Panel showing resource usage
+pub struct ResourcePanel<'a> {
+    monitor: &'a ResourceMonitor,
+}
+
+impl<'a> ResourcePanel<'a> {
+    /// Create a panel for a monitor
+    pub fn new(monitor: &'a ResourceMonitor) -> Self {
+        Self { monitor }
+    }
+
+    /// Get the metrics the latest sample has
+    fn metrics(&self) -> Vec<Metric> {
+        let Some(latest) = self.monitor.history().back() else {
+            return Vec::new();
+        };
+        Metric::ALL.into_iter().filter(|metric| metric.percent(latest).is_some()).collect()
+    }
+
+    /// Get the area of the panel, docked to the bottom right of the pane area
+    pub fn area(&self, panes: Rect) -> Rect {
+        let width = WIDTH.min(panes.width);
+        let height = (self.metrics().len().max(1) as u16 + 2).min(panes.height);
+        Rect::new(panes.right() - width, panes.bottom() - height, width, height)
+    }
+
+    /// Render the panel over the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, theme: &Theme) {
+        let area = self.area(panes);
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title("📊 Resources");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let Some(latest) = self.monitor.history().back() else {
+            f.render_widget(Paragraph::new("Sampling…").style(Style::default().fg(theme.secondary)), inner);
+            return;
+        };
+        for (row, metric) in self.metrics().into_iter().enumerate() {
+            let y = inner.y + row as u16;
+            if y >= inner.bottom() {
+                break;
+            }
+            let label_width = LABEL_WIDTH.min(inner.width);
+            let label = format!("{:<7}{}", metric.label(), metric.describe(latest).unwrap_or_default());
+            f.render_widget(
+                Paragraph::new(Line::from(label)).style(Style::default().fg(theme.text)),
+                Rect::new(inner.x, y, label_width, 1),
+            );
+
+            // The newest samples that fit, scaled to 100%
+            let spark = Rect::new(inner.x + label_width, y, inner.width - label_width, 1);
+            let values: Vec<u64> = self.monitor.history().iter()
+                .map(|sample| metric.percent(sample).unwrap_or(0.0).round() as u64)
+                .collect();
+            let skip = values.len().saturating_sub(spark.width as usize);
+            let sparkline = Sparkline::default()
+                .data(&values[skip..])
+                .max(100)
+                .style(Style::default().fg(theme.accent));
+            f.render_widget(sparkline, spark);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Scrub review widget for the AI Terminal
+//!
+//! Before an export or share is written, this popup lists everything the
+//! privacy scrubber flagged so the user can keep or redact each item.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::privacy::PendingArtifact;
+use crate::theme::Theme;
+
+/// Longest preview of a flagged item shown in the list
+const PREVIEW_LEN: usize = 40;
+
+/// Review list for flagged items in an outgoing artifact
+pub struct ScrubReview {
+    pending: PendingArtifact,
+    selected_index: usize,
+}
+
+impl ScrubReview {
+    /// Create a new review for a scanned artifact
+    pub fn new(pending: PendingArtifact) -> Self {
+        Self {
+            pending,
+            selected_index: 0,
+        }
+    }
+
+    /// Get the artifact under review
+    pub fn pending(&self) -> &PendingArtifact {
+        &self.pending
+    }
+
+    /// Finish the review and hand back the artifact
+    pub fn into_pending(self) -> PendingArtifact {
+        self.pending
+    }
+
+    /// Get the selected index
+    pub fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        let count = self.pending.findings().len();
+        if count > 0 {
+            self.selected_index = if self.selected_index == 0 {
+                count - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        let count = self.pending.findings().len();
+        if count > 0 {
+            self.selected_index = (self.selected_index + 1) % count;
+        }
+    }
+
+    /// Toggle redaction of the selected item
+    pub fn toggle_selected(&mut self) {
+        self.pending.toggle(self.selected_index);
+    }
+
+    /// Render the review popup
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let artifact = self.pending.artifact();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(format!("Review {} before writing", artifact.kind.label().to_lowercase()));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        let summary = Paragraph::new(vec![
+            Line::from(format!("Destination: {}", artifact.destination.display())),
+            Line::from(format!(
+                "{} of {} flagged items will be redacted",
+                self.pending.redaction_count(),
+                self.pending.findings().len()
+            )),
+        ])
+        .style(Style::default().fg(theme.text));
+        f.render_widget(summary, chunks[0]);
+
+        let items: Vec<ListItem> = self.pending.findings()
+            .iter()
+            .enumerate()
+            .map(|(i, finding)| {
+                let marker = if finding.redact { "[x] " } else { "[ ] " };
+                let mut preview: String = finding.matched
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .take(PREVIEW_LEN)
+                    .collect();
+                if finding.matched.chars().count() > PREVIEW_LEN {
+                    preview.push('…');
+                }
+
+                let line = Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(format!("{:<16}", finding.rule), Style::default().fg(theme.warning)),
+                    Span::raw(preview),
+                ]);
+                if i == self.selected_index {
+                    ListItem::new(line).style(theme.styles.selection.add_modifier(Modifier::BOLD))
+                } else {
+                    ListItem::new(line).style(Style::default().fg(theme.text))
+                }
+            })
+            .collect();
+        f.render_widget(List::new(items), chunks[1]);
+
+        let hints = Paragraph::new("Space: keep/redact | Enter: write | Esc: cancel")
+            .style(theme.styles.status_info);
+        f.render_widget(hints, chunks[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::{Artifact, ArtifactKind, Scrubber};
+
+    fn review() -> ScrubReview {
+        let artifact = Artifact::new(
+            ArtifactKind::BugReport,
+            "report.md",
+            "user a@example.com, password=letmein1".to_string(),
+        );
+        ScrubReview::new(Scrubber::new().prepare(artifact))
+    }
+
+    #[test]
+    fn test_toggle_selected_item() {
+        let mut review = review();
+        review.move_selection_down();
+        review.toggle_selected();
+
+        let pending = review.into_pending();
+        assert!(pending.findings()[0].redact);
+        assert!(!pending.findings()[1].redact);
+        assert_eq!(pending.scrubbed_content(), "user [REDACTED:email], password=letmein1");
+    }
+
+    #[test]
+    fn test_selection_wraps() {
+        let mut review = review();
+        review.move_selection_up();
+        assert_eq!(review.selected_index(), 1);
+        review.move_selection_down();
+        assert_eq!(review.selected_index(), 0);
+    }
+}
@@ -0,0 +1,336 @@
+//! Table view widget for the AI Terminal
+//!
+//! Shows a block's output read as a [`Table`], scrolling through its rows,
+//! sorted by the selected column and filtered on any number of columns. A
+//! column's filter keeps the rows whose cell contains its text, ignoring
+//! case, or with `>` and `<` the rows whose cell is a larger or smaller
+//! number.
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table as TableWidget, TableState},
+    Frame,
+};
+
+use crate::tables::{compare_cells, number, Table};
+use crate::theme::Theme;
+
+/// Widest a column is drawn
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Popup showing a table read from a block's output
+pub struct TableView {
+    command: String,
+    table: Table,
+    /// Indices into the table's rows of the rows shown, in display order
+    rows: Vec<usize>,
+    /// Filter of each column, empty for none
+    filters: Vec<String>,
+    filtering: bool,
+    column: usize,
+    /// Column sorted by, and whether descending
+    sort: Option<(usize, bool)>,
+    selected_index: usize,
+    message: Option<String>,
+}
+
+impl TableView {
+    /// Create a view of the table read from the output of `command`
+    pub fn new(command: impl Into<String>, table: Table) -> Self {
+        let mut view = Self {
+            command: command.into(),
+            filters: vec![String::new(); table.headers.len()],
+            table,
+            rows: Vec::new(),
+            filtering: false,
+            column: 0,
+            sort: None,
+            selected_index: 0,
+            message: None,
+        };
+        view.update_rows();
+        view
+    }
+
+    /// Filter and sort the rows, keeping the selection on the same row if it is still shown
+    fn update_rows(&mut self) {
+        let selected = self.rows.get(self.selected_index).copied();
+        let filters = &self.filters;
+        let mut rows: Vec<usize> = (0..self.table.rows.len())
+            .filter(|&i| filters.iter().zip(&self.table.rows[i]).all(|(filter, cell)| matches_filter(filter, cell)))
+            .collect();
+        if let Some((column, descending)) = self.sort {
+            let cells = &self.table.rows;
+            rows.sort_by(|&a, &b| {
+                let order = compare_cells(&cells[a][column], &cells[b][column]);
+                if descending { order.reverse() } else { order }
+            });
+        }
+        self.selected_index = selected
+            .and_then(|selected| rows.iter().position(|&i| i == selected))
+            .unwrap_or(0)
+            .min(rows.len().saturating_sub(1));
+        self.rows = rows;
+    }
+
+    /// Get the headers of the table
+    pub fn headers(&self) -> &[String] {
+        &self.table.headers
+    }
+
+    /// Get the rows shown, in display order
+    pub fn visible(&self) -> Vec<&[String]> {
+        self.rows.iter().map(|&i| self.table.rows[i].as_slice()).collect()
+    }
+
+    /// Get the highlighted row
+    pub fn selected(&self) -> Option<&[String]> {
+        self.rows.get(self.selected_index).map(|&i| self.table.rows[i].as_slice())
+    }
+
+    /// Get the index of the selected column
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Move the selection up by `rows`
+    pub fn move_selection_up(&mut self, rows: usize) {
+        self.selected_index = self.selected_index.saturating_sub(rows);
+    }
+
+    /// Move the selection down by `rows`
+    pub fn move_selection_down(&mut self, rows: usize) {
+        self.selected_index = (self.selected_index + rows).min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Select the column to the left
+    pub fn move_column_left(&mut self) {
+        self.column = self.column.saturating_sub(1);
+    }
+
+    /// Select the column to the right
+    pub fn move_column_right(&mut self) {
+        if self.column + 1 < self.table.headers.len() {
+            self.column += 1;
+        }
+    }
+
+    /// Sort by the selected column, then the other way, then not at all
+    pub fn cycle_sort(&mut self) {
+        self.sort = match self.sort {
+            Some((column, false)) if column == self.column => Some((column, true)),
+            Some((column, true)) if column == self.column => None,
+            _ => Some((self.column, false)),
+        };
+        self.update_rows();
+    }
+
+    /// Start typing the selected column's filter
+    pub fn start_filter(&mut self) {
+        self.filtering = true;
+    }
+
+    /// Whether keys go to the filter
+    pub fn is_filtering(&self) -> bool {
+        self.filtering
+    }
+
+    /// Stop typing the filter, keeping it
+    pub fn end_filter(&mut self) {
+        self.filtering = false;
+    }
+
+    /// Add a character to the selected column's filter
+    pub fn push_filter_char(&mut self, c: char) {
+        self.filters[self.column].push(c);
+        self.update_rows();
+    }
+
+    /// Remove the last character of the selected column's filter, ending it once empty
+    pub fn filter_backspace(&mut self) {
+        if !crate::text::pop_grapheme(&mut self.filters[self.column]) {
+            self.filtering = false;
+        }
+        self.update_rows();
+    }
+
+    /// Drop the filters of every column
+    pub fn clear_filters(&mut self) {
+        self.filters.iter_mut().for_each(String::clear);
+        self.filtering = false;
+        self.update_rows();
+    }
+
+    /// Get the rows shown as tab-separated text, headers first
+    pub fn to_tsv(&self) -> String {
+        std::iter::once(self.table.headers.as_slice())
+            .chain(self.visible())
+            .map(|row| row.join("\t"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Show a message below the table
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Render the view
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+
+        let sorted = match self.sort {
+            Some((column, descending)) => format!(", by {} {}", self.table.headers[column], if descending { "↓" } else { "↑" }),
+            None => String::new(),
+        };
+        let title = format!("▦ {} ({} of {} rows{})", self.command, self.rows.len(), self.table.rows.len(), sorted);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.styles.border_focused)
+            .title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(inner);
+
+        let filters = if self.filtering {
+            Line::from(Span::styled(
+                format!("Filter {}: {}▏", self.table.headers[self.column], self.filters[self.column]),
+                Style::default().fg(theme.text),
+            ))
+        } else {
+            let set: Vec<String> = self.table.headers
+                .iter()
+                .zip(&self.filters)
+                .filter(|(_, filter)| !filter.is_empty())
+                .map(|(header, filter)| format!("{} ~ {}", header, filter))
+                .collect();
+            let text = if set.is_empty() { "No filters".to_string() } else { format!("Filters: {}", set.join(", ")) };
+            Line::from(Span::styled(text, Style::default().fg(theme.secondary)))
+        };
+        f.render_widget(Paragraph::new(filters), chunks[0]);
+
+        self.render_table(f, chunks[1], theme);
+
+        let status = self.message.clone().unwrap_or_else(|| {
+            self.selected().map_or_else(String::new, |row| format!("{}: {}", self.table.headers[self.column], row[self.column]))
+        });
+        f.render_widget(Paragraph::new(status).style(Style::default().fg(theme.secondary)), chunks[2]);
+
+        let hints = if self.filtering {
+            "Type to filter this column (>n, <n for numbers) | Enter/Esc: done"
+        } else {
+            "←→: column | s: sort | /: filter column | c: clear filters | Enter: copy cell | y: copy rows | Esc: close"
+        };
+        f.render_widget(Paragraph::new(hints).style(theme.styles.status_info), chunks[3]);
+    }
+
+    /// Render the table, scrolled to the selected row and column
+    fn render_table(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let widths: Vec<usize> = (0..self.table.headers.len())
+            .map(|column| {
+                let header = crate::text::width(&self.table.headers[column]) + 2;
+                let cells = self.table.rows.iter().map(|row| crate::text::width(&row[column])).max().unwrap_or(0);
+                header.max(cells).min(MAX_COLUMN_WIDTH)
+            })
+            .collect();
+        // Columns to the left are scrolled out until the selected one fits
+        let mut first = 0;
+        while first < self.column && widths[first..=self.column].iter().map(|width| width + 1).sum::<usize>() > area.width as usize {
+            first += 1;
+        }
+        let columns = first..widths.len();
+
+        let header = Row::new(columns.clone().map(|column| {
+            let arrow = match self.sort {
+                Some((sorted, descending)) if sorted == column => if descending { " ↓" } else { " ↑" },
+                _ => "",
+            };
+            let mark = if self.filters[column].is_empty() { "" } else { " ~" };
+            let style = if column == self.column {
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+            };
+            Cell::from(format!("{}{}{}", self.table.headers[column], arrow, mark)).style(style)
+        }));
+        let rows: Vec<Row> = self.visible()
+            .into_iter()
+            .map(|row| {
+                Row::new(columns.clone().map(|column| {
+                    let style = if number(&row[column]).is_some() { Style::default().fg(theme.command) } else { Style::default().fg(theme.text) };
+                    Cell::from(row[column].clone()).style(style)
+                }))
+            })
+            .collect();
+        let constraints: Vec<Constraint> = columns.clone().map(|column| Constraint::Length(widths[column] as u16)).collect();
+        let table = TableWidget::new(rows, constraints)
+            .header(header)
+            .highlight_style(theme.styles.selection.add_modifier(Modifier::BOLD));
+        let mut state = TableState::default().with_selected(Some(self.selected_index).filter(|_| !self.rows.is_empty()));
+        f.render_stateful_widget(table, area, &mut state);
+    }
+}
+
+/// Check whether a cell passes a column's filter
+fn matches_filter(filter: &str, cell: &str) -> bool {
+    let filter = filter.trim();
+    // A comparison still being typed keeps every row
+    if filter == ">" || filter == "<" {
+        return true;
+    }
+    let compared = |bound: &str| number(bound).zip(number(cell));
+    if let Some(bound) = filter.strip_prefix('>') {
+        return compared(bound).is_some_and(|(bound, value)| value > bound);
+    }
+    if let Some(bound) = filter.strip_prefix('<') {
+        return compared(bound).is_some_and(|(bound, value)| value < bound);
+    }
+    cell.to_lowercase().contains(&filter.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let row = |cells: [&str; 3]| cells.map(String::from).to_vec();
+        Table {
+            headers: row(["PID", "%CPU", "COMMAND"]),
+            rows: vec![row(["10", "80.0", "ollama serve"]), row(["20", "150.5", "cargo build"]), row(["9", "0.0", "bash"])],
+        }
+    }
+
+    #[test]
+    fn test_sort_and_filter_by_column() {
+        let mut view = TableView::new("ps aux", table());
+        view.move_column_right();
+        view.cycle_sort();
+        assert_eq!(view.visible()[0][2], "bash");
+        view.cycle_sort();
+        assert_eq!(view.visible()[0][2], "cargo build");
+
+        // The selection stays on its row as the rows are sorted and filtered
+        assert_eq!(view.selected().unwrap()[2], "ollama serve");
+        view.start_filter();
+        ">5".chars().for_each(|c| view.push_filter_char(c));
+        assert_eq!(view.visible().len(), 2);
+        assert_eq!(view.selected().unwrap()[2], "ollama serve");
+        view.end_filter();
+        view.move_column_right();
+        view.start_filter();
+        "OLL".chars().for_each(|c| view.push_filter_char(c));
+        assert_eq!(view.to_tsv(), "PID\t%CPU\tCOMMAND\n10\t80.0\tollama serve");
+
+        view.clear_filters();
+        view.move_column_left();
+        view.move_column_left();
+        view.cycle_sort();
+        assert_eq!(view.visible().iter().map(|row| row[0].as_str()).collect::<Vec<_>>(), ["9", "10", "20"]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Theme picker widget for the AI Terminal
+//!
+//! This widget lists the available themes in a popup. Moving the selection
+//! lets the caller preview each theme live before committing to a choice.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Interactive theme selector
+pub struct ThemePicker {
+    themes: Vec<String>,
+    selected_index: usize,
+    original_theme: String,
+}
+
+impl ThemePicker {
+    /// Create a new theme picker with the cursor on the current theme
+    pub fn new(mut themes: Vec<String>, current_theme: &str) -> Self {
+        themes.sort();
+        let selected_index = themes
+            .iter()
+            .position(|name| name == current_theme)
+            .unwrap_or(0);
+
+        Self {
+            themes,
+            selected_index,
+            original_theme: current_theme.to_string(),
+        }
+    }
+
+    /// Get the theme names shown in the picker
+    pub fn themes(&self) -> &[String] {
+        &self.themes
+    }
+
+    /// Get the selected index
+    pub fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    /// Get the name of the highlighted theme
+    pub fn selected_theme(&self) -> Option<&str> {
+        self.themes.get(self.selected_index).map(String::as_str)
+    }
+
+    /// Get the theme that was active when the picker was opened
+    pub fn original_theme(&self) -> &str {
+        &self.original_theme
+    }
+
+    /// Move selection up
+    pub fn move_selection_up(&mut self) {
+        if !self.themes.is_empty() {
+            self.selected_index = if self.selected_index == 0 {
+                self.themes.len() - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+
+    /// Move selection down
+    pub fn move_selection_down(&mut self) {
+        if !self.themes.is_empty() {
+            self.selected_index = (self.selected_index + 1) % self.themes.len();
+        }
+    }
+
+    /// Render the theme picker using the previewed theme's colors
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        // Clear the area behind the popup
+        f.render_widget(Clear, area);
+
+        let items: Vec<ListItem> = self.themes
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let marker = if *name == self.original_theme { "*" } else { " " };
+                let style = if i == self.selected_index {
+                    theme.styles.selection.add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(theme.text)
+                };
+
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::default().fg(theme.accent)),
+                    Span::raw(" "),
+                    Span::styled(name.clone(), style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .style(Style::default().bg(theme.background).fg(theme.text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.styles.border)
+                    .title("Select Theme (Enter: keep, Esc: revert)")
+            );
+
+        f.render_widget(list, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec!["light".to_string(), "dark".to_string(), "default".to_string()]
+    }
+
+    #[test]
+    fn test_picker_starts_on_current_theme() {
+        let picker = ThemePicker::new(names(), "dark");
+        assert_eq!(picker.themes(), ["dark", "default", "light"]);
+        assert_eq!(picker.selected_theme(), Some("dark"));
+        assert_eq!(picker.original_theme(), "dark");
+    }
+
+    #[test]
+    fn test_picker_selection_wraps() {
+        let mut picker = ThemePicker::new(names(), "dark");
+
+        picker.move_selection_up();
+        assert_eq!(picker.selected_theme(), Some("light"));
+
+        picker.move_selection_down();
+        assert_eq!(picker.selected_theme(), Some("dark"));
+    }
+}
@@ -0,0 +1,59 @@
+//! Toast widget for the AI Terminal
+//!
+//! A short message shown over the top right of the panes for a few seconds,
+//! so scripts can report something without adding a block.
+
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::Rect,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// How long a toast is shown
+const SHOWN_FOR: Duration = Duration::from_secs(4);
+
+/// Widest a toast gets, border included
+const MAX_WIDTH: u16 = 48;
+
+/// Most lines of the message shown
+const MAX_LINES: u16 = 6;
+
+/// A message shown briefly
+#[derive(Debug, Clone)]
+pub struct Toast {
+    message: String,
+    shown: Instant,
+}
+
+impl Toast {
+    /// Show a message from now
+    pub fn new(message: &str) -> Self {
+        Self { message: message.trim().to_string(), shown: Instant::now() }
+    }
+
+    /// Whether the toast has been shown long enough
+    pub fn is_expired(&self) -> bool {
+        self.shown.elapsed() >= SHOWN_FOR
+    }
+
+    /// Render the toast over the top right of the pane area
+    pub fn render(&self, f: &mut Frame, panes: Rect, theme: &Theme) {
+        let longest = self.message.lines().map(crate::text::width).max().unwrap_or(0) as u16;
+        let width = (longest + 2).clamp(12, MAX_WIDTH).min(panes.width);
+        let inner_width = width.saturating_sub(2).max(1) as usize;
+        let lines: usize = self.message.lines().map(|line| crate::text::width(line).div_ceil(inner_width).max(1)).sum();
+        let height = (lines as u16).clamp(1, MAX_LINES).saturating_add(2).min(panes.height);
+        let area = Rect::new(panes.right().saturating_sub(width + 1), panes.y + 1, width, height);
+
+        f.render_widget(Clear, area);
+        let message = Paragraph::new(self.message.as_str())
+            .wrap(Wrap { trim: false })
+            .style(theme.styles.status_info)
+            .block(Block::default().borders(Borders::ALL).border_style(theme.styles.border_focused));
+        f.render_widget(message, area);
+    }
+}
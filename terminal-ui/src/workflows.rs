@@ -0,0 +1,334 @@
+//! Workflows for the AI Terminal
+//!
+//! A workflow is a named sequence of commands, such as build, test and
+//! deploy, run as one action from `/workflow run <name>` or the command
+//! palette. Each step runs as its own command block, in its own directory
+//! and with its own environment variables if it sets them, and the status
+//! bar shows which step is running. A failing step stops the workflow unless
+//! it is marked `continue_on_error`.
+//!
+//! Workflows are defined in the `workflows` directory of the config
+//! directory, one TOML or YAML file per workflow, named after the file:
+//!
+//! ```toml
+//! description = "Build, test and deploy"
+//! cwd = "~/src/app"
+//!
+//! [[steps]]
+//! name = "test"
+//! run = "cargo test"
+//!
+//! [[steps]]
+//! run = "./deploy.sh"
+//! env = { TARGET = "staging" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::widgets::command_palette::{Action, Command};
+
+/// Prefix of the command palette ids of workflows
+pub const PALETTE_PREFIX: &str = "workflow:";
+
+/// A step of a workflow
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Step {
+    /// Name shown in the progress; the command if not given
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Command run
+    pub run: String,
+    /// Directory the command runs in, relative to the workflow's
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Environment variables set for the command, over the workflow's
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Whether the workflow goes on when the command fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl Step {
+    /// Get the name shown for the step
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.run)
+    }
+}
+
+/// A named sequence of steps
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Workflow {
+    /// Name of the workflow, from its file name
+    #[serde(skip)]
+    pub name: String,
+    /// What the workflow does, shown in the palette
+    #[serde(default)]
+    pub description: String,
+    /// Directory the steps run in; the working directory when the workflow starts if not given
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Environment variables set for every step
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Commands run in order
+    pub steps: Vec<Step>,
+}
+
+impl Workflow {
+    /// Parse a workflow file, TOML or YAML by its extension
+    pub fn parse(name: &str, content: &str, yaml: bool) -> Result<Self> {
+        let mut workflow: Workflow = if yaml { serde_yaml::from_str(content)? } else { toml::from_str(content)? };
+        if workflow.steps.is_empty() {
+            bail!("the workflow has no steps");
+        }
+        workflow.name = name.to_string();
+        Ok(workflow)
+    }
+}
+
+/// Loads and looks up workflows
+#[derive(Debug, Clone, Default)]
+pub struct Workflows {
+    /// Known workflows by name
+    workflows: BTreeMap<String, Workflow>,
+    /// Directory holding the workflow files, if there is a config directory
+    dir: Option<PathBuf>,
+}
+
+impl Workflows {
+    /// Create a set for the workflows in the user's config directory
+    pub fn new() -> Self {
+        Self::with_dir(dirs::config_dir().map(|path| path.join("ai-terminal").join("workflows")))
+    }
+
+    /// Create a set that reads workflow files from `dir`
+    pub fn with_dir(dir: Option<PathBuf>) -> Self {
+        Self { workflows: BTreeMap::new(), dir }
+    }
+
+    /// Get the directory holding the workflow files
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Load every workflow file, returning how many were loaded
+    ///
+    /// Files that cannot be parsed are skipped with a warning.
+    pub fn load(&mut self) -> Result<usize> {
+        self.workflows.clear();
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        if !dir.exists() {
+            return Ok(0);
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let yaml = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("toml") => false,
+                Some("yaml" | "yml") => true,
+                _ => continue,
+            };
+            let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|content| Workflow::parse(&name, &content, yaml)) {
+                Ok(workflow) => {
+                    self.workflows.insert(name, workflow);
+                }
+                Err(e) => tracing::warn!("Skipping workflow file {}: {:?}", path.display(), e),
+            }
+        }
+        Ok(self.workflows.len())
+    }
+
+    /// Get a workflow by name
+    pub fn get(&self, name: &str) -> Option<&Workflow> {
+        self.workflows.get(name)
+    }
+
+    /// Iterate over the workflows by name
+    pub fn iter(&self) -> impl Iterator<Item = &Workflow> {
+        self.workflows.values()
+    }
+
+    /// Check whether there are no workflows
+    pub fn is_empty(&self) -> bool {
+        self.workflows.is_empty()
+    }
+
+    /// Build the commands offering the workflows in the command palette
+    pub fn palette_commands(&self) -> Vec<Command> {
+        self.iter()
+            .map(|workflow| {
+                let description = if workflow.description.is_empty() {
+                    format!("Run {} steps: {}", workflow.steps.len(), workflow.steps.iter().map(Step::label).collect::<Vec<_>>().join(" → "))
+                } else {
+                    workflow.description.clone()
+                };
+                Command::new(&format!("{}{}", PALETTE_PREFIX, workflow.name), &format!("Workflow: {}", workflow.name), &description, "Workflows", "▶")
+                    .with_action(Action::Slash(format!("/workflow run {}", workflow.name)))
+            })
+            .collect()
+    }
+}
+
+/// Outcome of a step that ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StepResult {
+    label: String,
+    exit_code: i32,
+    duration: Duration,
+}
+
+/// A workflow being run, one step at a time
+#[derive(Debug, Clone)]
+pub struct WorkflowRun {
+    workflow: Workflow,
+    /// Directory the workflow started in, which relative directories are taken from
+    base_dir: PathBuf,
+    results: Vec<StepResult>,
+    /// Why the workflow stopped before its last step
+    stopped: Option<String>,
+    started: Instant,
+}
+
+impl WorkflowRun {
+    /// Start running a workflow from `working_dir`
+    pub fn new(workflow: Workflow, working_dir: &Path) -> Self {
+        let base_dir = match &workflow.cwd {
+            Some(cwd) => working_dir.join(crate::expand_home(cwd)),
+            None => working_dir.to_path_buf(),
+        };
+        Self { workflow, base_dir, results: Vec::new(), stopped: None, started: Instant::now() }
+    }
+
+    /// Get the workflow being run
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
+    }
+
+    /// Get the step to run next, if the workflow is not finished
+    pub fn next_step(&self) -> Option<&Step> {
+        if self.stopped.is_some() {
+            return None;
+        }
+        self.workflow.steps.get(self.results.len())
+    }
+
+    /// Get the directory the next step runs in
+    pub fn step_dir(&self, step: &Step) -> PathBuf {
+        match &step.cwd {
+            Some(cwd) => self.base_dir.join(crate::expand_home(cwd)),
+            None => self.base_dir.clone(),
+        }
+    }
+
+    /// Get the environment variables of a step, its own over the workflow's
+    pub fn step_env(&self, step: &Step) -> Vec<(String, String)> {
+        let mut env = self.workflow.env.clone();
+        env.extend(step.env.clone());
+        env.into_iter().collect()
+    }
+
+    /// Record how the step last returned by [`Self::next_step`] ended
+    pub fn record(&mut self, exit_code: i32, duration: Duration) {
+        let Some(step) = self.next_step() else {
+            return;
+        };
+        let (label, continue_on_error) = (step.label().to_string(), step.continue_on_error);
+        if exit_code != 0 && !continue_on_error {
+            self.stopped = Some(format!("step {} ({}) failed with exit code {}", self.results.len() + 1, label, exit_code));
+        }
+        self.results.push(StepResult { label, exit_code, duration });
+    }
+
+    /// Stop before the next step
+    pub fn stop(&mut self, reason: &str) {
+        self.stopped.get_or_insert_with(|| reason.to_string());
+    }
+
+    /// Whether no steps are left to run
+    pub fn is_finished(&self) -> bool {
+        self.next_step().is_none()
+    }
+
+    /// Whether the workflow ran to its end without stopping
+    pub fn succeeded(&self) -> bool {
+        self.stopped.is_none() && self.results.len() == self.workflow.steps.len()
+    }
+
+    /// Get why the workflow stopped before its last step, if it did
+    pub fn stopped(&self) -> Option<&str> {
+        self.stopped.as_deref()
+    }
+
+    /// Format the status bar segment, such as ` ▶ deploy 2/3: test `
+    pub fn status_segment(&self) -> String {
+        match self.next_step() {
+            Some(step) => format!(" ▶ {} {}/{}: {} ", self.workflow.name, self.results.len() + 1, self.workflow.steps.len(), step.label()),
+            None => format!(" ▶ {} done ", self.workflow.name),
+        }
+    }
+
+    /// Describe how each step went
+    pub fn report(&self) -> String {
+        let mut lines: Vec<String> = self.results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| {
+                let mark = if result.exit_code == 0 { "✓" } else { "✗" };
+                format!("{} {}. {} ({:.1}s, exit {})", mark, i + 1, result.label, result.duration.as_secs_f64(), result.exit_code)
+            })
+            .collect();
+        for (i, step) in self.workflow.steps.iter().enumerate().skip(self.results.len()) {
+            lines.push(format!("· {}. {} (not run)", i + 1, step.label()));
+        }
+        let failed = self.results.iter().filter(|result| result.exit_code != 0).count();
+        lines.push(String::new());
+        lines.push(match &self.stopped {
+            Some(reason) => format!("Stopped: {}", reason),
+            None if failed > 0 => format!("Finished in {:.1}s; {} step(s) failed and were allowed to", self.started.elapsed().as_secs_f64(), failed),
+            None => format!("Finished in {:.1}s", self.started.elapsed().as_secs_f64()),
+        });
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_run() {
+        let toml = "description = \"Ship it\"\nenv = { MODE = \"ci\" }\n\n[[steps]]\nname = \"build\"\nrun = \"make\"\n\n[[steps]]\nrun = \"make lint\"\ncontinue_on_error = true\n\n[[steps]]\nrun = \"./deploy.sh\"\ncwd = \"ops\"\nenv = { MODE = \"prod\" }\n";
+        let workflow = Workflow::parse("ship", toml, false).unwrap();
+        let yaml = "description: Ship it\nenv:\n  MODE: ci\nsteps:\n  - name: build\n    run: make\n  - run: make lint\n    continue_on_error: true\n  - run: ./deploy.sh\n    cwd: ops\n    env:\n      MODE: prod\n";
+        assert_eq!(Workflow::parse("ship", yaml, true).unwrap(), workflow);
+        assert!(Workflow::parse("empty", "steps = []", false).is_err());
+
+        let mut run = WorkflowRun::new(workflow.clone(), Path::new("/src/app"));
+        assert_eq!(run.status_segment(), " ▶ ship 1/3: build ");
+        run.record(0, Duration::from_secs(1));
+        // A step allowed to fail does not stop the workflow
+        run.record(2, Duration::from_secs(1));
+        let step = run.next_step().unwrap().clone();
+        assert_eq!(run.step_dir(&step), Path::new("/src/app/ops"));
+        assert_eq!(run.step_env(&step), vec![("MODE".to_string(), "prod".to_string())]);
+        run.record(0, Duration::from_secs(1));
+        assert!(run.is_finished() && run.succeeded());
+        assert!(run.report().contains("✗ 2. make lint"));
+
+        let mut failing = WorkflowRun::new(workflow, Path::new("/src/app"));
+        failing.record(1, Duration::ZERO);
+        assert!(failing.is_finished() && !failing.succeeded());
+        assert!(failing.report().contains("· 3. ./deploy.sh (not run)\n\nStopped: step 1 (build) failed with exit code 1"));
+    }
+}
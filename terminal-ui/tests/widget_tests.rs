@@ -56,7 +56,7 @@ mod confirmation_modal_tests {
         let button = ModalButton::new("test", "Test Button", true);
         assert_eq!(button.id, "test");
         assert_eq!(button.text, "Test Button");
-        assert_eq!(button.is_default, true);
+        assert!(button.is_default);
     }
 
     #[test]